@@ -0,0 +1,295 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Allocation of a global budget of repetitions between parameter sets (see
+//! `--global-budget`): a pilot of a few repetitions of every set, then rounds
+//! of refinement shared out in proportion to the variance of each set's
+//! estimate, until the budget runs out or every set has its repetitions.
+
+use super::{NN, RR};
+
+use std::cmp::{max, min};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Repetitions of each set in the pilot, unless the budget is too small to
+/// give every set as many
+pub const PILOT_REPETITIONS: NN = 50;
+
+/// Repetitions not yet granted, shared by the sets running. Repetitions are
+/// taken from it as they are granted to a set, so that those of all sets
+/// together never exceed it however the sets are scheduled.
+pub struct RepetitionBudget {
+    remaining: AtomicUsize,
+}
+
+impl RepetitionBudget {
+    pub fn new(total: NN) -> Self {
+        RepetitionBudget { remaining: AtomicUsize::new(total as usize) }
+    }
+
+    /// Take up to `wanted` repetitions, returning the number granted (fewer
+    /// once the budget runs out).
+    pub fn grant(&self, wanted: NN) -> NN {
+        let mut remaining = self.remaining.load(Ordering::SeqCst);
+        loop {
+            let granted = min(wanted as usize, remaining);
+            let was = self.remaining
+                .compare_and_swap(remaining, remaining - granted, Ordering::SeqCst);
+            if was == remaining {
+                return granted as NN;
+            }
+            remaining = was;
+        }
+    }
+
+    /// Give back repetitions granted but not run (e.g. by a set stopped by its
+    /// time limit).
+    pub fn refund(&self, unused: NN) {
+        let _ = self.remaining.fetch_add(unused as usize, Ordering::SeqCst);
+    }
+
+    /// Repetitions not yet granted
+    pub fn remaining(&self) -> NN {
+        self.remaining.load(Ordering::SeqCst) as NN
+    }
+}
+
+/// Plans the rounds of a budgeted run. The first round is the pilot, in which
+/// every set is granted `PILOT_REPETITIONS` (or as many as the budget allows
+/// every set alike, at least one each while it lasts); no set is refined
+/// before all have had their pilot. Each later round grants as many
+/// repetitions as all sets have completed so far (so a few rounds suffice),
+/// shared in proportion to the variance of each set's estimate of
+/// P(compromise), p(1 - p), so that the sets end with similar precision. A
+/// set's estimate counts half a compromise more than observed, so that a set
+/// with none observed yet still gets some. No set is granted more than its
+/// own repetitions, and a set which stopped early (on its time limit or when
+/// interrupted) is granted no more.
+pub struct Allocator {
+    wanted: Vec<NN>,
+    runs: Vec<NN>,
+    p_compromise: Vec<RR>,
+    stopped: Vec<bool>,
+    refined: Vec<bool>,
+    rounds: NN,
+}
+
+impl Allocator {
+    /// Allocator for sets of `wanted` repetitions each (0 for sets not run)
+    pub fn new(wanted: Vec<NN>) -> Self {
+        let n = wanted.len();
+        Allocator {
+            wanted: wanted,
+            runs: vec![0; n],
+            p_compromise: vec![0.0; n],
+            stopped: vec![false; n],
+            refined: vec![false; n],
+            rounds: 0,
+        }
+    }
+
+    /// Repetitions to grant each set in the next round, given those
+    /// `remaining` in the budget. All are 0 once the run is over.
+    pub fn next_round(&mut self, remaining: NN) -> Vec<NN> {
+        let plan = if self.rounds == 0 {
+            self.pilot(remaining)
+        } else {
+            self.refinement(remaining)
+        };
+        if self.rounds > 0 {
+            for (refined, &granted) in self.refined.iter_mut().zip(&plan) {
+                *refined |= granted > 0;
+            }
+        }
+        self.rounds += 1;
+        plan
+    }
+
+    /// Record the outcome of a set's round: the repetitions it has completed
+    /// in all, its estimate of P(compromise) and whether it stopped early.
+    pub fn update(&mut self, i: usize, runs: NN, p_compromise: RR, stopped: bool) {
+        self.runs[i] = runs;
+        self.p_compromise[i] = p_compromise;
+        self.stopped[i] |= stopped;
+    }
+
+    /// True if set `i` still has only the repetitions of the pilot, and fewer
+    /// than it wanted
+    pub fn pilot_only(&self, i: usize) -> bool {
+        !self.refined[i] && self.runs[i] < self.wanted[i]
+    }
+
+    fn need(&self, i: usize) -> NN {
+        if self.stopped[i] {
+            0
+        } else {
+            self.wanted[i].saturating_sub(self.runs[i])
+        }
+    }
+
+    fn pilot(&self, remaining: NN) -> Vec<NN> {
+        let sets = self.wanted.iter().filter(|&&wanted| wanted > 0).count() as NN;
+        let each = if sets == 0 {
+            0
+        } else {
+            min(PILOT_REPETITIONS, remaining / sets)
+        };
+        let mut left = remaining;
+        self.wanted
+            .iter()
+            .map(|&wanted| {
+                let pilot = min(wanted, if each > 0 { each } else { min(left, 1) });
+                left -= pilot;
+                pilot
+            })
+            .collect()
+    }
+
+    fn refinement(&self, remaining: NN) -> Vec<NN> {
+        let n = self.wanted.len();
+        let need: Vec<NN> = (0..n).map(|i| self.need(i)).collect();
+        let weights: Vec<RR> = (0..n)
+            .map(|i| if need[i] == 0 {
+                0.0
+            } else {
+                let runs = self.runs[i] as RR;
+                let p = (self.p_compromise[i] * runs + 0.5) / (runs + 1.0);
+                p * (1.0 - p)
+            })
+            .collect();
+        let total_weight: RR = weights.iter().sum();
+        let mut plan = vec![0; n];
+        if total_weight == 0.0 {
+            return plan;
+        }
+        let round = min(remaining, max(self.runs.iter().sum::<NN>(), 1));
+        for i in 0..n {
+            plan[i] = min(need[i], (round as RR * weights[i] / total_weight) as NN);
+        }
+        // What rounding down (or sets needing less than their share) leaves
+        // goes to the sets of most variance first
+        let mut left = round - plan.iter().sum::<NN>();
+        let mut order: Vec<usize> = (0..n).filter(|&i| weights[i] > 0.0).collect();
+        order.sort_by(|&a, &b| weights[b].partial_cmp(&weights[a]).expect("weight"));
+        for i in order {
+            let extra = min(left, need[i] - plan[i]);
+            plan[i] += extra;
+            left -= extra;
+        }
+        plan
+    }
+}
+
+#[test]
+fn test_repetition_budget() {
+    use std::sync::Arc;
+    use std::thread;
+
+    let budget = RepetitionBudget::new(10);
+    assert_eq!(budget.grant(4), 4);
+    assert_eq!(budget.grant(8), 6);
+    assert_eq!(budget.grant(1), 0);
+    budget.refund(3);
+    assert_eq!(budget.remaining(), 3);
+
+    // Granted from many threads at once, never more than the budget in all
+    let budget = Arc::new(RepetitionBudget::new(1000));
+    let threads: Vec<_> = (0..8)
+        .map(|_| {
+            let budget = budget.clone();
+            thread::spawn(move || (0..100).map(|_| budget.grant(3)).sum::<NN>())
+        })
+        .collect();
+    let granted: NN = threads.into_iter().map(|thread| thread.join().expect("thread")).sum();
+    assert_eq!(granted, 1000);
+    assert_eq!(budget.remaining(), 0);
+}
+
+#[cfg(test)]
+// Run sets of `wanted` repetitions each, with estimates `p` of P(compromise),
+// under a budget of `total`, as the driver does: each round grants the plan
+// from the budget, and each set completes what it was granted, unless it
+// reaches `stop_at` repetitions, when it stops early and gives back the rest.
+// Returns the repetitions each set completed in each round, and the allocator.
+fn run_budget(wanted: &[NN],
+              p: &[RR],
+              total: NN,
+              stop_at: Option<NN>)
+              -> (Vec<Vec<NN>>, Allocator) {
+    let budget = RepetitionBudget::new(total);
+    let mut allocator = Allocator::new(wanted.to_vec());
+    let mut rounds = vec![];
+    loop {
+        let plan = allocator.next_round(budget.remaining());
+        if plan.iter().all(|&planned| planned == 0) {
+            return (rounds, allocator);
+        }
+        let mut completed = vec![0; wanted.len()];
+        for (i, &planned) in plan.iter().enumerate() {
+            let granted = budget.grant(planned);
+            if granted == 0 {
+                continue;
+            }
+            let runs = allocator.runs[i];
+            let stop = stop_at.map_or(false, |at| runs + granted >= at);
+            completed[i] = stop_at.map_or(granted, |at| min(granted, at - runs));
+            budget.refund(granted - completed[i]);
+            allocator.update(i, runs + completed[i], p[i], stop);
+        }
+        rounds.push(completed);
+    }
+}
+
+#[test]
+fn test_allocator() {
+    let total = |rounds: &[Vec<NN>]| -> NN {
+        rounds.iter().map(|round| round.iter().sum::<NN>()).sum()
+    };
+    let given = |rounds: &[Vec<NN>], i: usize| -> NN { rounds.iter().map(|round| round[i]).sum() };
+
+    // A tiny budget: the pilot covers as many sets as it can, one repetition
+    // each, and nothing is left to refine
+    let (rounds, allocator) = run_budget(&[100, 100, 100, 100], &[0.1; 4], 3, None);
+    assert_eq!(rounds, vec![vec![1, 1, 1, 0]]);
+    assert!((0..3).all(|i| allocator.pilot_only(i)));
+
+    // Enough for the pilot of every set, and more: the pilot is complete
+    // before any set is refined, and the whole budget is spent, no more
+    let p = [0.5, 0.01, 0.0, 0.0, 0.5];
+    let (rounds, allocator) = run_budget(&[1000, 1000, 1000, 0, 30], &p, 700, None);
+    assert_eq!(rounds[0], vec![50, 50, 50, 0, 30]);
+    assert_eq!(total(&rounds), 700);
+    // No set gets more than it wants, and the more variable sets get more
+    assert_eq!(given(&rounds, 3), 0);
+    assert_eq!(given(&rounds, 4), 30);
+    assert!(given(&rounds, 0) > given(&rounds, 1));
+    assert!(given(&rounds, 1) > given(&rounds, 2));
+    assert!(given(&rounds, 2) > 50);
+    assert!(!allocator.pilot_only(2));
+    // A set complete after its pilot is not flagged
+    assert!(!allocator.pilot_only(4));
+
+    // A budget larger than wanted: every set gets what it wants, no more
+    let (rounds, _) = run_budget(&[200, 60], &[0.2, 0.0], 1_000_000, None);
+    assert_eq!((given(&rounds, 0), given(&rounds, 1)), (200, 60));
+
+    // Sets stopping early are granted no more, and give back what they did
+    // not run
+    let (rounds, allocator) = run_budget(&[1000, 1000], &[0.5, 0.5], 400, Some(70));
+    assert_eq!(rounds, vec![vec![50, 50], vec![20, 20]]);
+    assert_eq!(allocator.runs, vec![70, 70]);
+}
@@ -17,15 +17,45 @@
 
 //! Argument processing
 
-use docopt::Docopt;
+use docopt::{self, Docopt};
+use log::LogLevelFilter;
 use super::{ToolArgs, NN, RR};
-use super::tools::{Tool, DirectCalcTool, SimStructureTool, FullSimTool, SimResult};
+use super::tools::{Tool, DirectCalcTool, SimStructureTool, FullSimTool, SimResult,
+                   AnyGroupModel, DEFAULT_REJOIN_WINDOW};
 use super::quorum::*;
+use super::churn_model::ChurnModel;
+use super::sim::{Prefix, RelocationTarget, PowScaling};
+use super::uptime::UptimeDist;
+use super::burst::Burst;
+use super::join_cap::{JoinCap, JoinCapMode};
+use super::steady::SteadyState;
+use super::placement::InitialPlacement;
+use super::close_group::CloseGroup;
+use super::corrupt::Corruption;
+use super::checkpoint::{Checkpointing, CheckpointFile, fnv1a};
+use super::cache::ResultCache;
+use super::rng::{Seeding, Stream};
+use super::prob::ProbCache;
+use super::interrupt::Interrupt;
+use super::output::{ProbFormat, Viz, VizScale};
+use super::estimate::Schedule;
+use super::duration::{parse_duration, format_duration};
+use super::batch;
+use super::combo::combo_id;
+use super::logging;
+use super::scan::ScanBounds;
+use super::auto_extend::{AutoExtend, ExtendParam};
 
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::io;
+use std::process;
 use std::str::FromStr;
-use std::fmt::Debug;
+use std::fmt::{self, Debug, Formatter};
 use std::ops::AddAssign;
 use std::cmp::Ordering;
+use std::sync::Arc;
 
 
 #[cfg_attr(rustfmt, rustfmt_skip)]
@@ -34,128 +64,1003 @@ Probability computation tool.
 
 Usage:
     routing-sims [-h | --help]
-    routing-sims calc \
-     [-n RANGE] [-r RANGE] [-k RANGE] [-q RANGE] [-s VAL] [-p VAL]
-    routing-sims structure [-n \
-     RANGE] [-r RANGE] [-k RANGE] [-q RANGE] [-s VAL] [-p VAL]
-    routing-sims full [-n RANGE] \
-     [-r RANGE] [-k RANGE] [-q RANGE] [-s VAL] [-p VAL] [-Q QTYPE] [-T TTYPE]
+    routing-sims calc [options]
+    routing-sims structure [options]
+    routing-sims full [options]
+    routing-sims batch [options]
+    routing-sims selftest
+    routing-sims bench [TIER]
+    routing-sims explain [full] [options]
+    routing-sims id [structure | full] [options]
+    routing-sims scan [structure | full] --samples N [--sampling TYPE] [options]
+    routing-sims targeting-cost [options]
+    routing-sims audit FILE1 FILE2
+    routing-sims cache prune DIR --older-than=AGE
+    routing-sims isoline --input FILE --target P --x COL --y COL
 
 Tools:
     calc        Direct calculation: all groups have min size, no ageing or targetting
     structure   Simulate group structure, but no ageing or targetting
     full        Full simulation (see -Q and -T parameters)
+    batch       Run parameter sets read as CSV from stdin, one per line
+    selftest    Check the tools agree where they must; exits non-zero on failure
+    bench       Time fixed workloads of each tool (TIER: small, medium or large;
+                default all), writing JSON
+    explain     Break down the direct calculation (and with full, the full
+                simulation) of one parameter set, with the options of calc (or
+                full) given single values
+    id          Print the id of each parameter combination of the options of calc
+                (or structure or full), without running anything
+    scan        Run N parameter sets spread within the ranges of -n, -r, -k
+                and -q (steps ignored) of the options of calc (or structure or
+                full), by Latin hypercube sampling (TYPE lhs, the default) or
+                a Sobol sequence (sobol), drawn from --seed
+    targeting-cost
+                Expected joins and steps for a targetting attacker to get a
+                node of a given age into one group, analytically
+    audit       Compare two files written by --rng-audit, giving the first
+                combination, repetition and stream drawing differently; exits
+                non-zero if there is one
+    cache       Remove the results written by --cache to DIR at least AGE ago
+                (e.g. 30d)
+    isoline     The value of column y at which P(compromise) reaches P, at each
+                value of column x, from the results in FILE, as CSV
 
 Options:
     -h --help   Show this message
-    -n RANGE    Number of nodes, total, e.g. 1000-5000:1000.
-    -r RANGE    Either number of compromised nodes (e.g. 50) or percentage (default is 10%).
-    -k RANGE    Minimum group size, e.g. 10-20.
-    -q RANGE    Quorum size as a proportion with step size, e.g. 0.5-0.7:0.1.
-    -s VAL      Maximum number of steps, each the length of one proof-of-work.
-    -p VAL      Number of times to repeat a true/false simulation to calculate
-                an attack success probability.
-    -Q QTYPE    Quorum algorithm: simple, age or all
-    -T TTYPE    Attack targetting strategy: none, simple or all
+
+Use 'routing-sims TOOL --help' to list the options applicable to each tool.
+";
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const USAGE_CALC: &'static str = "
+Direct calculation: all groups have min size, no ageing; targetting only as an
+upper bound.
+
+Usage:
+    routing-sims calc [-n RANGE] [-r RANGE] [-k RANGE] [-q RANGE] [-s VAL] [-p VAL] \
+     [--strict] [--auto-extend PARAM] [--target P] [--extend-bounds RANGE] [--quorum-basis BASIS] \
+     [--quorum-floor RANGE] [--block-prop RANGE] [--participation RANGE] [--any-group-model MODEL] \
+     [--compare-models] \
+     [--model MODEL] [--no-cache] [--cache DIR] [--refresh] [--precision N] [--sci] [--log10] \
+     [--canonical] [--viz] [--viz-range RANGE] [--viz-scale SCALE] \
+     [--baseline FILE] [--allow-model-mismatch] [--marginals] [--log-marginals] \
+     [--report-html FILE] [--dry-run] [--confirm-above DURATION] [--max-mem BYTES] \
+     [--schedule ORDER] [--yes] \
+     [-v...] [--log-level LEVEL]
+    routing-sims calc (-h | --help)
+";
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const USAGE_STRUCTURE: &'static str = "
+Simulate group structure, but no ageing or targetting.
+
+Usage:
+    routing-sims structure [-n RANGE] [-r RANGE] [-k RANGE] [-q RANGE] [-s VAL] \
+     [-p VAL] [--strict] [--auto-extend PARAM] [--target P] [--extend-bounds RANGE] \
+     [--quorum-basis BASIS] [--quorum-floor RANGE] [--block-prop RANGE] [--participation RANGE] \
+     [--initial-placement SPEC] [--quorum-jitter J] [--report LIST] [--correction] \
+     [--close-group SPEC] \
+     [--no-cache] [--seed N] [--fix-stream LIST] [--time-limit DURATION] [--include-truncated] \
+     [--verify] \
+     [--cache DIR] [--refresh] [--precision N] [--sci] [--log10] [--canonical] [--viz] \
+     [--viz-range RANGE] [--viz-scale SCALE] \
+     [--baseline FILE] [--allow-model-mismatch] [--marginals] [--log-marginals] \
+     [--report-html FILE] [--dry-run] [--confirm-above DURATION] [--max-mem BYTES] \
+     [--schedule ORDER] [--yes] \
+     [-v...] [--log-level LEVEL]
+    routing-sims structure (-h | --help)
+";
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const USAGE_FULL: &'static str = "
+Full simulation, with node ageing, relocation and attack strategies.
+
+Usage:
+    routing-sims full [-n RANGE] [-r RANGE] [-k RANGE] [-q RANGE] [-s VAL] [-p VAL] \
+     [--strict] [--auto-extend PARAM] [--target P] [--extend-bounds RANGE] [--quorum-basis BASIS] \
+     [--quorum-floor RANGE] [--block-prop RANGE] [--participation RANGE] [-Q QTYPE] \
+     [--elders RANGE] [--promotion-lag L] [--elder-tiebreak RULE] [--name-grinding P] \
+     [--weight WEIGHT] [--age-cap RANGE] [--cap-ordering] [--paired-ageing] [-T TTYPE] \
+     [--objective OBJ] [--attacker-knowledge K] [--target-prefix BITS] \
+     [--relocation-target TARGET] [--initial-placement SPEC] [--quorum-jitter J] \
+     [--honest-uptime DIST] [--churn-model MODEL] [--burst SPEC] [--corrupt SPEC] \
+     [--eviction-rate R] [--attack-resource X] [--rejoin-age-recovery F] [--rejoin-window STEPS] \
+     [--pow-scaling SCALING] [--attack-start RANGE] [--hold N] [--steady-state SPEC] \
+     [--membership-lag L] [--join-cap CAP] [--join-cap-mode MODE] [--checkpoint-dir DIR] \
+     [--checkpoint-every DURATION] [--interleave] [--split-check K] [--paranoid STEPS] [--verify] \
+     [--record-attack FILE] [--replay-attack FILE] [--rng-audit FILE] [--report LIST] \
+     [--metric LIST] [--curve FILE] [--curve-interval STEPS] [--keep-partial] [--seed N] \
+     [--fix-stream LIST] [--time-limit DURATION] [--include-truncated] [--global-budget N] \
+     [--cache DIR] [--refresh] [--precision N] [--sci] [--log10] [--canonical] [--viz] \
+     [--viz-range RANGE] [--viz-scale SCALE] \
+     [--baseline FILE] [--allow-model-mismatch] [--baseline-column] [--marginals] \
+     [--log-marginals] [--report-html FILE] [--dry-run] [--confirm-above DURATION] \
+     [--max-mem BYTES] [--schedule ORDER] [--yes] \
+     [-v...] [--log-level LEVEL]
+    routing-sims full (-h | --help)
+";
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const USAGE_BATCH: &'static str = "
+Run parameter sets read as CSV from stdin, one per line, without expansion.
+
+The first line names the columns, in any order: Type (dir_calc, structure or
+full_sim), Quorum, Targetting, Prefix, Relocation, Uptime, ChurnModel, Nodes,
+Malicious, MinGroup, QuorumSize, QuorumBasis, QuorumFloor, BlockProp, Objective,
+Knowledge, Burst, Corrupt, EvictionRate, AttackResource, RejoinRecovery,
+RejoinWindow, PowScaling, AttackStart, Hold, MembershipLag, JoinCap, Elders,
+PromotionLag, ElderTiebreak, NameGrinding, Weight, AgeCap, Placement,
+QuorumJitter, Participation, Steps, Repetitions and ComboId. Values are as in
+the output of the option --dry-run, which may be used to generate input. Prefix
+(default any), Relocation (default random), Uptime (default 1), ChurnModel
+(default fixed), QuorumBasis (default actual), QuorumFloor (default none),
+BlockProp (default quorum), Objective (default compromise), Knowledge (default
+full), Burst (default none), Corrupt (default none), EvictionRate (default 0),
+AttackResource (default 1), RejoinRecovery (default 0), RejoinWindow (default
+100), PowScaling (default none), AttackStart (default 0), Hold (default 1),
+MembershipLag (default 0), JoinCap (default none; e.g. 2:10, or 2:10+queue to
+queue nodes kept out), Elders (default 7 for the elder quorum, - for others),
+PromotionLag (default 0 for the elder quorum, - for others), ElderTiebreak
+(default name for the elder quorum, - for others), NameGrinding (default 0 for
+the elder quorum, - for others), Weight (default age for the age quorum, - for
+others), AgeCap (default none; e.g. 16, or 16+ordering to rank elders by capped
+age too), Placement (default uniform; or e.g. concentrated(0.5), for structure
+and full_sim), QuorumJitter (default 0, for structure and full_sim) and
+Participation (default 1) are optional. ComboId, the id of the combination, is
+written for reference and ignored.
+With dir_calc, Targetting simp_bound (or simple) gives the upper bound under
+simple targetting (option --model of calc).
+
+Usage:
+    routing-sims batch [--skip-bad-lines] [--report LIST] [--metric LIST] [--curve FILE] \
+     [--curve-interval STEPS] [--keep-partial] [--checkpoint-dir DIR] \
+     [--checkpoint-every DURATION] [--interleave] \
+     [--split-check K] [--seed N] [--fix-stream LIST] [--time-limit DURATION] \
+     [--include-truncated] [--no-cache] [--cache DIR] [--refresh] [--precision N] [--sci] \
+     [--log10] [--canonical] [--viz] [--viz-range RANGE] [--viz-scale SCALE] \
+     [--baseline FILE] [--allow-model-mismatch] [--marginals] [--log-marginals] \
+     [--report-html FILE] [--dry-run] [--confirm-above DURATION] [--max-mem BYTES] \
+     [--schedule ORDER] [--yes] \
+     [-v...] [--log-level LEVEL]
+    routing-sims batch (-h | --help)
+
+Options:
+    -h --help   Show this message
+    --skip-bad-lines
+                Skip lines which cannot be parsed or are invalid, with a
+                message, instead of aborting.
+    --report LIST  Extra reports, as for the full simulation.
+    --metric LIST  Extra measures, as for the full simulation.
+    --curve FILE   File for time-series reports, as for the full simulation.
+    --curve-interval STEPS
+                Steps between samples of time-series reports (default 10).
+    --keep-partial
+                Resume from complete partial checkpoints, as for the full
+                simulation.
+    --checkpoint-dir DIR
+                Checkpoint full simulations, as for the full simulation.
+    --checkpoint-every DURATION
+                Minimum time between checkpoints (default 10m).
+    --interleave
+                Run full simulations in rounds, as for the full simulation.
+    --split-check K
+                Compare folds of the repetitions of full simulations, as for
+                the full simulation.
+    --seed N    Master seed of the simulations, as for the full simulation.
+    --fix-stream LIST
+                Random streams to fix, as for the full simulation.
+    --time-limit DURATION
+                Wall time per parameter set, as for the full simulation.
+    --include-truncated
+                Include sets stopped by --time-limit in summaries, as for the
+                full simulation.
+    --no-cache  Calculate every probability, as for the direct calculation.
+";
+
+// Parameter options accepted by all tools except batch.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const OPTIONS_COMMON: &'static str = "
+Options:
+    -h --help   Show this message
+    -n RANGE, --nodes RANGE
+                Number of nodes, total, e.g. 1000-5000:1000.
+    -r RANGE, --malicious RANGE
+                Either number of compromised nodes (e.g. 50) or percentage
+                (default is 10%).
+    -k RANGE, --group-size RANGE
+                Minimum group size, e.g. 10-20, or a formula of the number
+                of nodes n: log2+C, sqrt/C or A*n+B (e.g. log2+3, sqrt/4 or
+                0.01*n+5), rounded to the nearest whole number.
+    -q RANGE, --quorum RANGE
+                Quorum size as a proportion with step size, e.g. 0.5-0.7:0.1,
+                or as a number of nodes with suffix 'a', e.g. 5a-7a. A range
+                without a step takes ten steps of proportions (and of
+                percentages; at least 0.001, or 0.1%) and steps of one node.
+    --quorum-basis BASIS
+                Group size a quorum proportion is taken of: actual (the size
+                of the group; the default), minimum (the minimum group size,
+                whatever the actual size) or all.
+    --quorum-floor RANGE
+                Minimum number of nodes in a quorum, whatever the proportion,
+                e.g. 5 or 3-6: a quorum needs the larger of the two. Each must
+                be at most the minimum group size (or, for the elder quorum,
+                the number of elders). By default there is none.
+    --block-prop RANGE
+                Proportion of a group whose absence blocks it (disruption),
+                e.g. 0.3-0.4:0.02. By default a group is disrupted when its
+                honest nodes lack a quorum.
+    --participation RANGE
+                Probability that an honest member takes part in a decision
+                (above 0, at most 1), e.g. 0.7-1:0.1, as when keys are lost:
+                quorums are of the members taking part, malicious members
+                always doing so. By default every member takes part.
+    -s VAL, --steps VAL
+                Maximum number of steps, each the length of one proof-of-work.
+                With 0, the full simulation places the malicious nodes among
+                the initial nodes and evaluates that placement only.
+    -p VAL, --reps VAL
+                Number of times to repeat a true/false simulation to calculate
+                an attack success probability (at least 1, except for calc).
+    --strict    Refuse to run unless -n, -r, -k and -q are all given (for
+                scripts, so that a lost option does not silently run with the
+                default).
+    --auto-extend PARAM
+                Once the parameter sets have run, extend the range of -r, -k
+                or -q (PARAM r, k or q) until it brackets the target
+                probability: where P(compromise) at both ends of the range
+                (for the same values of the other parameters) is on the same
+                side of the target, a set is run one step beyond the end
+                nearer it, the step being the gap between the two values at
+                that end, and so on. The sets added are numbered after the
+                others. The parameter must take at least two values. Not
+                with --interleave or --global-budget.
+    --target P  P(compromise) for --auto-extend to bracket, above 0 and below
+                1 (default 0.5).
+    --extend-bounds RANGE
+                Bounds beyond which --auto-extend does not go, inclusive, as
+                values of the parameter, e.g. 0.4-1 for -q or 5%-50% for -r.
+                Proportions are in any case above 0 and at most 1, counts at
+                least 1 and group sizes at most the number of nodes.
+";
+
+// Output and run options accepted by all tools (including batch).
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const OPTIONS_RUN: &'static str =
+"    --precision N  Number of digits after the decimal point in probabilities
+                (default 6).
+    --sci       Always write probabilities in scientific notation (by
+                default only those below 1e-4).
+    --log10     Write log10 of probabilities instead, to 2 decimal places
+                (zero is -inf in the table and empty in CSV). Differences from
+                a baseline remain probabilities.
+    --canonical
+                Write output which is identical byte for byte between runs
+                (and builds) given the same parameters and --seed, for diffing:
+                probabilities and other numbers in full (the shortest decimal
+                which reads back exactly, ignoring the options above), every
+                optional column of the table whether or not given, and no run
+                time estimates in the log. Not with --time-limit.
+    --viz       Add a column to the table with a bar showing P(compromise).
+    --viz-range RANGE
+                Probabilities shown by the lowest and highest bars, e.g.
+                1e-6:0.1 (default 1e-9:1). Zero is shown as no bar.
+    --viz-scale SCALE
+                Scale of the bar: log (the default) or linear.
+    --baseline FILE
+                Compare P(compromise) with the main table of an earlier run,
+                saved in this file: rows are joined by their parameters, and
+                columns added for the earlier value, the difference and
+                whether it is significant (for simulated results). Rows in
+                only one run are listed after the table.
+    --allow-model-mismatch
+                Use a baseline (or checkpoints) written by another version of
+                the model, with a warning, rather than refusing. Results of
+                different versions may not be comparable.
+    --marginals
+                After the main table, output as CSV a table for each swept
+                parameter (one taking more than one value) of the mean
+                P(compromise) at each of its values, over all other
+                parameters, and the number of rows averaged.
+    --log-marginals
+                As --marginals, but with geometric means. A zero estimated by
+                simulation counts as half a run; a calculated zero makes the
+                mean zero.
+    --report-html FILE
+                Also write the results to FILE as a self-contained HTML page:
+                the parameters, the main table (sorted by clicking a column
+                title), the notes after it and, if one or two parameters
+                other than the quorum type and targetting are swept, charts
+                of P(compromise): a line chart, with a line per quorum type
+                and targetting, or a heatmap per quorum type and targetting.
+                With --log10 probabilities are charted on a log scale.
+    --dry-run   Output the parameter sets as CSV (suitable as batch input)
+                instead of running them.
+    --cache DIR
+                Reuse the results of parameter sets saved in this directory
+                (created if necessary) by earlier runs with the same
+                parameters, repetitions and --seed, and save those run, as
+                small JSON files. Only calculations and simulations given a
+                seed (--seed) are cached, without extra reports, and not
+                those stopped by --time-limit; results of another version of
+                the model are run again. Unrelated to --no-cache. Remove old
+                results with 'routing-sims cache prune DIR --older-than AGE'.
+    --refresh   Run every parameter set, replacing its result in --cache.
+    --confirm-above DURATION
+                Ask for confirmation before starting if the estimated run time
+                exceeds this, e.g. 90s, 30m, 12h or 2d (plain numbers are
+                seconds). If no answer can be read (e.g. input is not
+                interactive), abort instead.
+    --yes       Answer yes to any confirmation.
+    --max-mem BYTES
+                Skip parameter sets whose estimated memory (that of the
+                simulated network) exceeds this, e.g. 512M or 8G (powers of
+                1024; plain numbers are bytes), listing them after the table,
+                and only run sets at once while their estimates together are
+                within it.
+    --schedule ORDER
+                Order in which to start the parameter sets: expansion (the
+                order of the table; the default), cost (by descending
+                estimated run time, so that long sets start first rather than
+                run on alone at the end) or shuffle (a random order, drawn
+                from --seed if given). The output is in the order of the table
+                whatever the schedule.
+    -v          Log in more detail: -v adds debug messages (e.g. each
+                parameter set as it completes) and -vv trace messages of the
+                simulations themselves.
+    --log-level LEVEL
+                Level of the messages logged: off, error, warn, info (the
+                default), debug or trace. Not with -v.
+";
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const OPTIONS_CALC: &'static str = "    --any-group-model MODEL
+                How to combine per-group probabilities into the probability
+                of any group being affected: independent (treat groups as
+                independent; the default) or corrected (accounting for
+                malicious nodes being drawn without replacement: exact while
+                groups x malicious nodes x (group size + 1) is at most 2^24,
+                else approximated to second order).
+    --compare-models
+                Also output the results of both models, as CSV after the main
+                table.
+    --model MODEL  Attack to calculate: untargetted (the default),
+                targetted-bound (an upper bound under simple targetting
+                with random relocation over -s steps, shown as simp_bound:
+                new nodes join the target group at will, but node ageing
+                means that most malicious members must have been relocated
+                there) or all.
+    --no-cache  Calculate every probability, instead of remembering those
+                calculated for earlier parameter sets of the sweep.
+";
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const OPTIONS_STRUCTURE: &'static str =
+"    --report LIST  Extra reports, comma separated, output as CSV after the
+                main table. Supported: malice-hist (histogram of the malicious
+                proportion of each group) and address-coverage (the expected
+                shares of the address space held by compromised and disrupted
+                groups of each structure; see the full simulation).
+    --correction
+                Simulate -p structures instead of one, report the mean, and
+                output as CSV after the main table the ratio of P(compromise)
+                to that of the direct calculation, with a 95% confidence
+                interval (undefined where the calculation gives zero).
+    --close-group SPEC
+                Compare sections with close groups, given as K[,A]: the K
+                nodes nearest (by XOR distance) each of A addresses (default
+                100) drawn at random for each structure simulated, which hold
+                the data at the address. Adds columns to the main table: the
+                proportion of addresses whose section is compromised
+                (P(section)), whose close group is compromised under the same
+                quorum (P(close)) and whose close group has no honest member
+                (P(loss)).
+    --no-cache  Calculate every probability, instead of remembering those
+                calculated for earlier groups and parameter sets.
+";
+
+// Options of the simulation tools.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const OPTIONS_SEED: &'static str = "    --initial-placement SPEC
+                Where malicious nodes are at the start: uniform (placed at
+                random like honest nodes, the default) or concentrated(F),
+                where the proportion F of them start in the smallest group
+                (as many as it has members), trading places with honest
+                members. In the full simulation, with steps, these start in
+                the network and the others join in the attack; the structure
+                simulation counts that group as holding at least those
+                placed, the others drawing from the remaining nodes. When
+                given, the spec is output in a Placement column.
+    --quorum-jitter J
+                Offset the quorum proportion of each group by a uniform draw
+                within plus or minus J (from 0 to 1; default 0), fixed for the
+                group by its prefix and drawn like the structure stream, to
+                test how sensitive the results are to thresholds varying from
+                section to section. The proportion stays within 0 to 1. The
+                structure simulation takes each group's quorum size of its
+                jittered proportion. When not 0, J is output in a Jitter
+                column. Not accepted by calc, whose groups are all alike.
+    --seed N    Master seed of the random streams of the simulations: structure
+                (the network before the attack), attack (malicious nodes),
+                churn (churn and relocation during the attack) and liveness
+                (which nodes are up, for --report liveness). With a seed, a
+                run may be repeated exactly; without, one is drawn for each
+                parameter set.
+    --fix-stream LIST
+                Streams, comma separated, to give the same seed in every
+                repetition, e.g. structure (the same network is attacked
+                each time).
+    --time-limit DURATION
+                Stop repeating the simulations of a parameter set once they
+                have run this long, e.g. 90s, 30m or 2h30m (plain numbers
+                are seconds), and report the repetitions completed. The time is
+                checked between repetitions and every 64 steps; a repetition
+                cut short is discarded. Rows stopped early are marked with *
+                after the number of runs, in the table and in the interim
+                results of --interleave, and listed after it.
+    --include-truncated
+                Include the parameter sets stopped by --time-limit in the
+                summaries over several sets, i.e. the output of --marginals
+                and the charts of --report-html (by default they are left
+                out of these).
+    --verify    Check the invariants of the network structure every step
+                (every node joined: with structure): no node is lost or in
+                two groups, no group is below the minimum size unless a
+                member has left it and no node's age decreases. Stops with
+                a dump of the offending group and the last changes to the
+                network if not. Debug builds check every 64 steps anyway.
+";
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const OPTIONS_FULL: &'static str = "    -Q QTYPE, --quorum-type QTYPE
+                Quorum algorithm: simple, age, elder or all
+    --elders RANGE
+                Number of elders per group of the elder quorum, e.g. 5-13:2
+                (default 7). Each must be at most the minimum group size. Other
+                quorum types are not repeated for each.
+    --promotion-lag L
+                Steps from an elder leaving its group (or no longer being
+                among the oldest) to the promotion of the next-oldest member
+                in its place (default 0: at once). Meanwhile the group has
+                fewer elders, for quorums and liveness alike. Elder quorum
+                only.
+    --elder-tiebreak RULE
+                How members of equal age are ranked for elder seats: name
+                (default; lowest name first), join-order (earliest to join
+                the group first) or random-per-decision (by a hash of the
+                group's membership and ages, so drawn afresh as either
+                changes). Elder quorum only.
+    --name-grinding P
+                Probability that the targetting attacker succeeds in grinding
+                the name of a node it keeps in a group to the lowest name
+                free there, so that it wins every tie of age for elder seats
+                (default 0). Requires the elder tiebreak by name; elder
+                quorum only.
+    --weight WEIGHT
+                What the votes of the age (weighted) quorum are weighted by:
+                age (default), uniform (only the number of voters counts, as
+                in the simple quorum) or capacity(DIST), a capacity drawn for
+                each node as it joins from a distribution of the form of the
+                honest uptime but with any non-negative values, e.g.
+                capacity(1@0.9,10@0.1).
+    --age-cap RANGE
+                Cap on the age counted when weighing votes by age, e.g. 16 or
+                8-32:8: older nodes weigh as if of this age. Actual ages are
+                still kept, and still rank elders unless --cap-ordering is
+                also given. Requires the age quorum, or the elder quorum with
+                that option. By default age is unbounded.
+    --cap-ordering
+                Also rank nodes for elder status by their capped age (ties
+                broken by name, as usual).
+    --paired-ageing
+                Run each parameter set under both the simple and age quorum
+                (instead of -Q), with the same random numbers, and output as
+                CSV after the main table the ratio of P(compromise) without
+                ageing to that with, with a 95% confidence interval. Where the
+                age quorum is never compromised, a lower bound is given
+                instead (e.g. >12.5).
+    -T TTYPE, --targetting TTYPE
+                Attack targetting strategy: none, simple, deadline, threshold
+                or all (the first three), or a composite switching strategy at
+                given steps, e.g. untargetted:500+simple (untargetted before
+                step 500, then simple). threshold holds nodes in reserve until
+                they could compromise some group, then deploys only those
+                needed and targets it.
+    --objective OBJ
+                What targetting strategies aim for: compromise (quorum in one
+                group; the default), disruption (blocking as many groups as
+                possible, moving on from each group once disrupted) or all.
+    --attacker-knowledge K
+                What the attacker knows of the network when choosing where to
+                deploy: full (every node's age and allegiance; the default),
+                membership-only (group sizes and its own nodes, but honest
+                nodes' ages only as the network's typical age),
+                own-nodes-only (only its own nodes, taking each group to hold
+                the typical number of honest nodes of the typical age) or all.
+                Only strategies consulting the network (threshold) are
+                affected.
+    --target-prefix BITS
+                Only count disruption or compromise of the group responsible
+                for this prefix, e.g. 0110, and the groups it splits into.
+                Targetting strategies attack only these groups. It is an error
+                if the prefix is longer than any reached.
+    --relocation-target TARGET
+                Where relocated nodes go: random (a random name), balanced (the
+                smallest group) or all.
+    --honest-uptime DIST
+                Distribution of the uptime of honest nodes: the probability of
+                taking part in each churn event of its group, which scales how
+                fast it ages and how often it is relocated. Either a constant
+                (default 1), uniform(A,B) or a mix of uptimes with weights,
+                e.g. 0.95@0.2,0.3@0.8 (20% of nodes up 95% of the time, 80% up
+                30% of the time).
+    --churn-model MODEL  How many times each honest member takes part in a
+                churn event of its group: fixed (once with probability its
+                uptime, the default) or poisson (a Poisson number with mean
+                its uptime). Under poisson the number of failure bursts
+                (--burst) at each step is Poisson too, with mean P.
+    --burst SPEC
+                Correlated failure bursts, given as P,F: at each step, with
+                probability P, the proportion F of honest nodes fail, taken
+                region by region (each honest node is given one of 16 regions
+                on joining). Failed nodes are down for the rest of the step,
+                then leave the network. Groups are not merged, so may fall
+                below the minimum size. Default none.
+    --corrupt SPEC
+                Corruption of honest nodes in place (key compromise or
+                bribery), given as F@STEP: at step STEP, the proportion F of
+                the honest nodes then in the network turn malicious, keeping
+                their ages and groups. Several events may be given, separated
+                by commas, e.g. 0.1@0,0.05@500. In addition to the nodes of -r.
+                Default none.
+    --eviction-rate R
+                Probability of each honest node failing its storage proof at
+                each step, once joining nodes have joined, and so being
+                evicted from the network (default 0). The last node of a group
+                is not evicted. Under --churn-model poisson, the numbers of
+                each kind evicted are Poisson with the same means.
+    --attack-resource X
+                Resource of malicious nodes relative to honest ones (default
+                1): they are evicted with probability R / X (at most 1), e.g.
+                0.1 for under-provisioned sybils. Evicted malicious nodes are
+                lost to the attacker.
+    --rejoin-age-recovery F
+                Proportion of its former age (rounded) a node recovers on
+                rejoining with its old identity (default 0). Malicious nodes
+                reset after relocation leave with their identity, which the
+                attacker's next new node takes (the oldest first), so that
+                attacks resetting nodes lose less age. Honest nodes which
+                leave never rejoin.
+    --rejoin-window STEPS
+                Steps for which the identities of departed nodes are
+                remembered (default 100).
+    --pow-scaling SCALING
+                How the proof-of-work to join scales with the number of nodes
+                n: none (one step; the default), log-n (log n / log 1000
+                steps), linear-n (n / 1000 steps) or all. Rounded to whole
+                steps, at least one.
+    --attack-start RANGE
+                Step at which malicious nodes start joining (default 0), e.g.
+                0-900:300; each must be below the number of steps. Before it,
+                each step a new honest node joins (a Poisson number, with
+                mean one, under --churn-model poisson).
+    --hold N    Only count a group as disrupted or compromised once it has
+                been so for N consecutive steps (default 1: at once), since
+                a transient quorum may not be exploitable.
+    --steady-state SPEC
+                Measure the long-run steady state instead of stopping runs at
+                compromise, given as BURN[,B]: after BURN steps of burn-in,
+                the remaining steps are split into B batches (default 10, at
+                least 3) and each step counts as compromised (or disrupted)
+                while some group is held so. Adds columns to the main table:
+                the proportion of steps disrupted and compromised, each with
+                the half-width of its 95% confidence interval from the batch
+                means (over the steps of a batch in every run) and, with
+                several runs, from the spread of the runs' proportions; and
+                Trend, flagging batch means which rise or fall throughout (a
+                sign the burn-in is too short). P(disruption)
+                and P(compromise) are unchanged: whether it happened at all.
+    --membership-lag L
+                Check each group's quorums against its membership as of L
+                steps ago (default 0: the current membership), since members
+                may only learn of joins and departures after a delay. Nodes
+                which left within the last L steps still count, with their
+                data when they left; nodes which joined within them do not.
+    --join-cap CAP
+                Rate-limit joins, given as J:W: each group accepts at most J
+                new members in any W consecutive steps, whether joining or
+                relocated, honest or malicious, e.g. 2:10. Honest replacements
+                may be delayed too.
+    --join-cap-mode MODE
+                What happens to a node kept out by --join-cap: reject (it
+                redoes proof-of-work under a new name; the default) or queue
+                (it waits, in order, for a later step).
+    --report LIST  Extra reports, comma separated, output as CSV after the
+                main table. Supported: malice-hist (histogram of the malicious
+                proportion of each group at the end of each run) and elders
+                (malicious elders per group at the end of each run; elder
+                quorum only) and age-share (time series of the largest
+                proportion of any group's age held by malicious nodes, mean
+                over repetitions; written to the --curve file) and liveness
+                (the proportion of runs in which, at some step, the members
+                of a group which are up do not form a quorum, and the mean
+                number of such group-steps per run; see --honest-uptime) and
+                termination (how many runs ended in compromise, stalled with
+                nothing left to change, ran out of malicious nodes, or reached
+                the last step, and the mean steps simulated by those which
+                stalled) and governance (the proportion of runs in which the
+                attacker could block some split (or promotion, with lagging
+                promotions) by disrupting or compromising the voters just
+                before it, and the mean number of such changes and of those
+                blockable per run) and spread (two columns added to the main
+                table instead: the mean number of groups holding at least one
+                malicious node at the end of a run, and the mean of the most
+                malicious nodes in any one group) and address-coverage (four
+                columns added to the main table instead: the mean and largest
+                share of the address space held by compromised groups at the
+                end of a run, then by disrupted ones) and turnover (two
+                columns added to the main table instead: the steps for the
+                median age of honest nodes to double, at the mean rate it
+                grew, and its mean steady-state value, over the second half
+                of each run).
+    --metric LIST  The reports above which measure something other than
+                compromise, comma separated, as an alias for --report with
+                them: liveness and governance.
+    --curve FILE   File to write time-series reports to, as CSV with columns
+                Combination (the row of the main table, from 1), Step and
+                the value. Like every file written, it is written as
+                FILE.partial while the run is in progress, and renamed to
+                FILE when complete. A run which fails or is interrupted leaves
+                FILE.partial, ending with a line '# incomplete: ...'.
+    --curve-interval STEPS
+                Steps between samples of time-series reports (default 10).
+    --keep-partial
+                With --checkpoint-dir, resume from a checkpoint left as
+                FILE.partial by a run stopped while saving it, if complete
+                (by default only checkpoints renamed to FILE are read).
+    --checkpoint-dir DIR
+                Save the progress of each parameter set to a file in this
+                directory (created if necessary), and resume from it when run
+                again with the same parameters. Only completed repetitions are
+                saved. Delete the directory to start afresh.
+    --checkpoint-every DURATION
+                Minimum time between checkpoints, e.g. 90s, 30m or 1h30m
+                (default 10m). A checkpoint is always saved on completion.
+    --interleave
+                Run the repetitions of all parameter sets in rounds of 50,
+                writing interim results of each round to stderr as CSV. The
+                final results are as without this option.
+    --global-budget N
+                Run at most N repetitions in all, shared between the parameter
+                sets: first a pilot of 50 of each (fewer if N is too small to
+                give every set as many), then rounds of more, each set's share
+                in proportion to the variance of its estimate of
+                P(compromise), until N are run or every set has its -p
+                repetitions. The Runs column shows the repetitions each set
+                received, and a Pilot column marks with yes those with only
+                their pilot. Not with --interleave.
+    --split-check K
+                Split the repetitions of each parameter set into K folds of
+                consecutive repetitions (K at least 2) and compare the
+                P(compromise) of each, to detect results depending on the
+                repetition (e.g. state leaking from one to the next). Output
+                as CSV after the main table: the estimate of each fold, the
+                largest difference between two and the p-value of a
+                chi-square test of homogeneity; folds disagreeing (p-value
+                below 0.001) are flagged and listed after the table.
+    --paranoid STEPS
+                Every STEPS steps, check that the quorum checks of the groups
+                changed each step agree with checking every group, stopping
+                with an error if not. For testing the simulation; slow.
+    --record-attack FILE
+                Write the attacker's decisions (whether to reset each malicious
+                node offered to it) in the first repetition of each parameter
+                set to FILE, as CSV: Combination, Step, Prefix and Reset (0 or
+                1), numbered like the main table.
+    --replay-attack FILE
+                Make the attacker of the first repetition of each parameter set
+                in FILE (as written by --record-attack) take the recorded
+                decisions, then follow its strategy once they run out. Stops
+                with an error if the run diverges from the recording, e.g. if
+                a node is offered in another group. Use the same seed.
+    --rng-audit FILE
+                Write the number of values drawn from each random number
+                stream in each repetition of each parameter set to FILE, as
+                CSV: Combination, ComboId, Repetition, Stream and Draws (in
+                32-bit words), numbered like the main table. Two runs which
+                should be identical (e.g. before and after a change meant not
+                to alter results) can then be compared with 'routing-sims
+                audit'.
+    --baseline-column
+                Add an Analytic column to the table after P(compromise): its
+                value by direct calculation at the same parameters (nodes,
+                malicious, group size, quorum size, basis and blocking
+                proportion), i.e. with a simple quorum and without ageing or
+                targetting.
 ";
 
-#[allow(non_snake_case)]
+// Options with a short and a long form are named by the long form
 #[derive(RustcDecodable)]
 struct Args {
-    cmd_calc: bool,
-    cmd_structure: bool,
-    cmd_full: bool,
-    flag_n: Option<String>,
-    flag_r: Option<String>,
-    flag_k: Option<String>,
-    flag_q: Option<String>,
-    flag_s: Option<NN>,
-    flag_p: Option<NN>,
-    flag_Q: Option<String>,
-    flag_T: Option<String>,
+    flag_nodes: Option<String>,
+    flag_malicious: Option<String>,
+    flag_group_size: Option<String>,
+    flag_quorum: Option<String>,
+    flag_block_prop: Option<String>,
+    flag_participation: Option<String>,
+    flag_quorum_basis: Option<String>,
+    flag_quorum_floor: Option<String>,
+    flag_steps: Option<NN>,
+    flag_reps: Option<NN>,
+    flag_quorum_type: Option<String>,
+    flag_elders: Option<String>,
+    flag_weight: Option<String>,
+    flag_age_cap: Option<String>,
+    flag_cap_ordering: Option<bool>,
+    flag_targetting: Option<String>,
+    flag_objective: Option<String>,
+    flag_attacker_knowledge: Option<String>,
+    flag_target_prefix: Option<String>,
+    flag_churn_model: Option<String>,
+    flag_relocation_target: Option<String>,
+    flag_initial_placement: Option<String>,
+    flag_quorum_jitter: Option<RR>,
+    flag_honest_uptime: Option<String>,
+    flag_burst: Option<String>,
+    flag_corrupt: Option<String>,
+    flag_eviction_rate: Option<RR>,
+    flag_attack_resource: Option<RR>,
+    flag_rejoin_age_recovery: Option<RR>,
+    flag_rejoin_window: Option<NN>,
+    flag_pow_scaling: Option<String>,
+    flag_attack_start: Option<String>,
+    flag_hold: Option<NN>,
+    flag_steady_state: Option<String>,
+    flag_close_group: Option<String>,
+    flag_membership_lag: Option<NN>,
+    flag_join_cap: Option<String>,
+    flag_join_cap_mode: Option<String>,
+    flag_paranoid: Option<NN>,
+    flag_split_check: Option<NN>,
+    flag_record_attack: Option<String>,
+    flag_replay_attack: Option<String>,
+    flag_rng_audit: Option<String>,
+    flag_promotion_lag: Option<NN>,
+    flag_elder_tiebreak: Option<String>,
+    flag_name_grinding: Option<RR>,
+    flag_report: Option<String>,
+    flag_metric: Option<String>,
+    flag_curve: Option<String>,
+    flag_curve_interval: Option<NN>,
+    flag_checkpoint_dir: Option<String>,
+    flag_checkpoint_every: Option<String>,
+    flag_time_limit: Option<String>,
+    flag_global_budget: Option<NN>,
+    flag_seed: Option<u64>,
+    flag_fix_stream: Option<String>,
+    flag_confirm_above: Option<String>,
+    flag_yes: bool,
+    flag_v: usize,
+    flag_log_level: Option<String>,
+    flag_max_mem: Option<String>,
+    flag_schedule: Option<String>,
+    flag_auto_extend: Option<String>,
+    flag_target: Option<String>,
+    flag_extend_bounds: Option<String>,
+    flag_dry_run: bool,
+    flag_cache: Option<String>,
+    flag_refresh: bool,
+    flag_precision: Option<usize>,
+    flag_sci: bool,
+    flag_canonical: bool,
+    flag_log10: bool,
+    flag_viz: bool,
+    flag_marginals: bool,
+    flag_log_marginals: bool,
+    flag_report_html: Option<String>,
+    flag_viz_range: Option<String>,
+    flag_viz_scale: Option<String>,
+    flag_baseline: Option<String>,
+    flag_allow_model_mismatch: bool,
+    flag_any_group_model: Option<String>,
+    flag_model: Option<String>,
+    // Switches accepted by only some tools are optional, since the key is
+    // absent from the other tools' usage.
+    flag_compare_models: Option<bool>,
+    flag_correction: Option<bool>,
+    flag_skip_bad_lines: Option<bool>,
+    flag_strict: Option<bool>,
+    flag_no_cache: Option<bool>,
+    flag_interleave: Option<bool>,
+    flag_include_truncated: Option<bool>,
+    flag_paired_ageing: Option<bool>,
+    flag_baseline_column: Option<bool>,
+    flag_keep_partial: Option<bool>,
+    flag_verify: Option<bool>,
+}
+
+/// Number of steps into which a range of real values (including proportions
+/// and percentages) is divided when no step is given
+pub const DEFAULT_STEPS: RR = 10.0;
+
+/// Smallest default step of a range of real values
+pub const MIN_DEFAULT_STEP: RR = 0.001;
+
+// Default step of a range of real values from `start` to `stop`
+fn default_real_step(start: RR, stop: RR) -> RR {
+    snap_real((stop - start) / DEFAULT_STEPS).max(MIN_DEFAULT_STEP)
+}
+
+// Real value with the rounding error of repeated addition removed (to 12 decimal places)
+fn snap_real(x: RR) -> RR {
+    (x * 1e12).round() / 1e12
 }
 
 pub trait DefaultStep<T> {
-    // Return a default step.
+    // Return a default step for a range from `start` to `stop`.
     //
-    // The value `x` is passed so that RelOrAbs can see whether it's being
-    // used in relative or absolute form.
-    fn default_step(x: T) -> T;
+    // Both are passed so that RelOrAbs can see whether it's being used in
+    // relative or absolute form, and so that ranges of reals can be divided
+    // into `DEFAULT_STEPS` steps (of at least `MIN_DEFAULT_STEP`). Counts
+    // step by one.
+    fn default_step(start: T, stop: T) -> T;
+
+    // The value `x` reached by stepping through a range, with the rounding
+    // error of repeated addition of reals removed, so that e.g. 0-1:0.1 ends
+    // at 1.
+    fn snap(x: T) -> T {
+        x
+    }
 }
 
 impl DefaultStep<NN> for NN {
-    fn default_step(_: NN) -> NN {
+    fn default_step(_: NN, _: NN) -> NN {
         1
     }
 }
 
 impl DefaultStep<RR> for RR {
-    fn default_step(_: RR) -> RR {
-        1.0
+    fn default_step(start: RR, stop: RR) -> RR {
+        default_real_step(start, stop)
+    }
+
+    fn snap(x: RR) -> RR {
+        snap_real(x)
+    }
+}
+
+pub trait RangeKind {
+    // Whether `self` and `other` are of the same kind, so may be parts (start, stop and step)
+    // of one range. Types with only one kind of value need not implement this.
+    fn same_kind(&self, _other: &Self) -> bool {
+        true
+    }
+
+    // The kinds of value, for errors, e.g. "absolute counts or all percentages"
+    fn kinds() -> &'static str {
+        "of one kind"
     }
 }
 
+impl RangeKind for NN {}
+
+impl RangeKind for RR {}
+
 pub enum SamplePoints<T> {
     Range(T, T, Option<T>), // start, stop, optional step
-    List(Vec<T>),
+    List(Vec<SamplePoints<T>>), // elements, each a range or number
     Number(T),
 }
 
 impl<T: Copy + Debug + AddAssign + PartialOrd<T> + DefaultStep<T>> SamplePoints<T> {
-    fn iter(&self) -> SamplePointsIterator<T> {
+    /// The values, in order, without repeats
+    pub fn iter(&self) -> SamplePointsIterator<T> {
         SamplePointsIterator {
             iterable: self,
             i: 0,
             prev: None,
+            element: None,
+            seen: vec![],
+        }
+    }
+
+    /// The step used, if a range given without one
+    pub fn default_step(&self) -> Option<T> {
+        match *self {
+            SamplePoints::Range(start, stop, None) => Some(T::default_step(start, stop)),
+            _ => None,
         }
     }
 }
 
-impl<T: FromStr> FromStr for SamplePoints<T>
+impl<T: FromStr + RangeKind> FromStr for SamplePoints<T>
     where <T as FromStr>::Err: Debug
 {
-    type Err = ();  // we just panic!
+    // Errors say which part of the value is bad, so that the caller may name the option
+    type Err = String;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.contains('-') {
-            // We have a range; check for a step:
-            let (first, step) = if s.contains(':') {
-                let mut parts = s.split(':');
-                let first = parts.next().expect("split half");
-                let second = parts.next().expect("split half");
-                if parts.next() != None {
-                    panic!("expected 'start-stop:step', found {}", s);
-                }
-                (first, Some(second.parse().expect("parse")))
-            } else {
-                (s, None)
-            };
-            let mut parts = first.split('-');
-            let start = parts.next().expect("split half").parse().expect("parse");
-            let stop = match parts.next() {
-                    Some(part) => part,
-                    None => panic!("expected 'start-stop:step', found {}", s),
-                }
-                .parse()
-                .expect("parse");
-            if parts.next() != None {
-                panic!("expected 'start-stop:step', found {}", s);
-            }
-            Ok(SamplePoints::Range(start, stop, step))
-        } else if s.contains(',') {
-            // We have a list
-            let parts = s.split(',');
-            Ok(SamplePoints::List(parts.map(|p| p.parse().expect("parse")).collect()))
+        if s.contains(',') {
+            // We have a list, each element a single value or a range
+            let elements: Vec<SamplePoints<T>> = try!(s.split(',').map(parse_element).collect());
+            Ok(SamplePoints::List(elements))
         } else {
-            // Presumably we have a single number
-            Ok(SamplePoints::Number(s.parse().expect("parse")))
+            parse_element(s)
+        }
+    }
+}
+
+// Parse one element of a list (or a whole value): a range `start-stop` or `start-stop:step`, or
+// a single value.
+fn parse_element<T: FromStr + RangeKind>(s: &str) -> Result<SamplePoints<T>, String> {
+    let value = |part: &str| part.parse::<T>().map_err(|_| format!("bad value '{}'", part));
+    let malformed = || format!("expected 'start-stop' or 'start-stop:step', found '{}'", s);
+    if !s.contains('-') {
+        if s.contains(':') {
+            return Err(malformed());
+        }
+        // Presumably we have a single number
+        return Ok(SamplePoints::Number(try!(value(s))));
+    }
+    // We have a range; check for a step:
+    let mut halves = s.split(':');
+    let ends = halves.next().expect("split half");
+    let step = halves.next();
+    let mut parts = ends.split('-');
+    let (start, stop) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(start), Some(stop), None) if !start.is_empty() && !stop.is_empty() => (start, stop),
+        _ => return Err(malformed()),
+    };
+    if halves.next().is_some() || step == Some("") {
+        return Err(malformed());
+    }
+    let (start, stop) = (try!(value(start)), try!(value(stop)));
+    let step = match step {
+        Some(step) => Some(try!(value(step))),
+        None => None,
+    };
+    let mixed = !start.same_kind(&stop) ||
+                step.as_ref().map_or(false, |step| !start.same_kind(step));
+    if mixed {
+        return Err(format!("start, stop and step of '{}' must all be {}", s, T::kinds()));
+    }
+    Ok(SamplePoints::Range(start, stop, step))
+}
+
+// Bounds of an option a scan may vary: none if not given or given a single value, its start
+// and stop if given a range (the step being ignored).
+fn scan_range<T>(value: Option<&String>, option: &str) -> Result<Option<(T, T)>, String>
+    where T: FromStr + RangeKind + PartialOrd + Copy,
+          <T as FromStr>::Err: Debug
+{
+    let value = match value {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+    let points = try!(value.parse::<SamplePoints<T>>()
+        .map_err(|e| format!("{} {}: {}", option, value, e)));
+    match points {
+        SamplePoints::Number(_) => Ok(None),
+        SamplePoints::Range(start, stop, _) if start < stop => Ok(Some((start, stop))),
+        SamplePoints::Range(..) => {
+            Err(format!("{} {}: the bounds of a scan must increase", option, value))
+        }
+        SamplePoints::List(_) => {
+            Err(format!("{} {}: give a range START-STOP to scan, not a list", option, value))
         }
     }
 }
 
-struct SamplePointsIterator<'a, T: Copy + Debug + AddAssign + PartialOrd<T> + DefaultStep<T> + 'a> {
+/// Iterator over the values of `SamplePoints`
+pub struct SamplePointsIterator<'a, T>
+    where T: Copy + Debug + AddAssign + PartialOrd<T> + DefaultStep<T> + 'a
+{
     iterable: &'a SamplePoints<T>,
     i: usize,
     prev: Option<T>,
+    // Of a list: the iterator over the current element, and the values given so far, so that
+    // repeats are skipped
+    element: Option<Box<SamplePointsIterator<'a, T>>>,
+    seen: Vec<T>,
 }
 
 impl<'a, T: Copy + Debug + AddAssign + PartialOrd<T> + DefaultStep<T> + 'a> Iterator
@@ -172,8 +1077,9 @@ impl<'a, T: Copy + Debug + AddAssign + PartialOrd<T> + DefaultStep<T> + 'a> Iter
                         self.prev
                     },
                     Some(mut x) => {
-                        let step = step.unwrap_or(T::default_step(start));
+                        let step = step.unwrap_or(T::default_step(start, stop));
                         x += step;
+                        x = T::snap(x);
                         self.prev = Some(x);
                         if x > stop {
                             None
@@ -184,11 +1090,23 @@ impl<'a, T: Copy + Debug + AddAssign + PartialOrd<T> + DefaultStep<T> + 'a> Iter
                 }
             },
             &SamplePoints::List(ref v) => {
-                if i >= v.len() {
-                    None
-                } else {
-                    self.i = i + 1;
-                    Some(v[i])
+                // The elements in order, skipping values given before
+                loop {
+                    if self.element.is_none() {
+                        if self.i >= v.len() {
+                            return None;
+                        }
+                        self.element = Some(Box::new(v[self.i].iter()));
+                        self.i += 1;
+                    }
+                    match self.element.as_mut().and_then(|element| element.next()) {
+                        Some(x) if self.seen.contains(&x) => {}
+                        Some(x) => {
+                            self.seen.push(x);
+                            return Some(x);
+                        }
+                        None => self.element = None,
+                    }
                 }
             },
             &SamplePoints::Number(n) => {
@@ -203,152 +1121,2440 @@ impl<'a, T: Copy + Debug + AddAssign + PartialOrd<T> + DefaultStep<T> + 'a> Iter
     }
 }
 
-pub struct ArgProc {
-    args: Args,
+/// Options which `--strict` requires
+pub const STRICT_FLAGS: [&'static str; 4] = ["-n", "-r", "-k", "-q"];
+
+/// Options with a long form as well as the short one
+pub const OPTION_ALIASES: [(&'static str, &'static str); 8] = [("-n", "--nodes"),
+                                                                 ("-r", "--malicious"),
+                                                                 ("-k", "--group-size"),
+                                                                 ("-q", "--quorum"),
+                                                                 ("-s", "--steps"),
+                                                                 ("-p", "--reps"),
+                                                                 ("-Q", "--quorum-type"),
+                                                                 ("-T", "--targetting")];
+
+// The first option of `OPTION_ALIASES` given in both forms, if any. Either may
+// carry its value (as in `-n1000` or `--nodes=1000`).
+fn both_forms(argv: &[String]) -> Option<(&'static str, &'static str)> {
+    let given = |form: &str| {
+        argv.iter().any(|arg| {
+            arg == form ||
+            if form.starts_with("--") {
+                arg.starts_with(&format!("{}=", form))
+            } else {
+                !arg.starts_with("--") && arg.starts_with(form)
+            }
+        })
+    };
+    OPTION_ALIASES.iter().cloned().find(|&(short, long)| given(short) && given(long))
+}
+
+// Error for the first range option mixing kinds of value (e.g. counts and
+// percentages), if any
+fn mixed_range(args: &Args) -> Option<String> {
+    let malicious = args.flag_malicious
+        .as_ref()
+        .and_then(|s| s.parse::<SamplePoints<RelOrAbs>>().err())
+        .map(|e| format!("-r: {}", e));
+    malicious.or_else(|| {
+        args.flag_quorum
+            .as_ref()
+            .and_then(|s| s.parse::<SamplePoints<QuorumSize>>().err())
+            .map(|e| format!("-q: {}", e))
+    })
+}
+
+/// Where the value of a parameter came from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Provenance {
+    Default,
+    /// Given by this option
+    Flag(&'static str),
+}
+
+/// A parameter with its value as given (or defaulted), for echoing at
+/// startup, e.g. "nodes = 1000-5000:1000 (from -n)".
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParamSource {
+    pub name: &'static str,
+    /// Option setting the parameter
+    pub flag: &'static str,
+    /// Value as given, or the default
+    pub value: String,
+    pub provenance: Provenance,
+    /// Step used, if the value is a range given without one
+    pub default_step: Option<String>,
+}
+
+impl ParamSource {
+    fn new<D: fmt::Display>(name: &'static str,
+                            flag: &'static str,
+                            given: Option<String>,
+                            default: D)
+                            -> Self {
+        ParamSource {
+            name: name,
+            flag: flag,
+            provenance: given.as_ref().map_or(Provenance::Default, |_| Provenance::Flag(flag)),
+            value: given.unwrap_or_else(|| default.to_string()),
+            default_step: None,
+        }
+    }
+}
+
+impl fmt::Display for ParamSource {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        try!(match self.provenance {
+            Provenance::Default => write!(f, "{} = {} (default)", self.name, self.value),
+            Provenance::Flag(flag) => write!(f, "{} = {} (from {})", self.name, self.value, flag),
+        });
+        match self.default_step {
+            Some(ref step) => write!(f, "; no step given, so stepping by {}", step),
+            None => Ok(()),
+        }
+    }
+}
+
+// The step used for a range given to an option as `s` without one (`None` if `s` is not such a
+// range, or is invalid)
+fn default_step_text<T>(s: &str) -> Option<String>
+    where T: Copy + Debug + AddAssign + PartialOrd<T> + DefaultStep<T> + FromStr + RangeKind +
+             fmt::Display,
+          <T as FromStr>::Err: Debug
+{
+    s.parse::<SamplePoints<T>>().ok().and_then(|range| range.default_step()).map(|step| {
+        step.to_string()
+    })
+}
+
+/// One dimension of the expansion of the options into parameter sets (see
+/// `ArgProc::expand_sim_params`), e.g. the network sizes
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dimension {
+    pub name: &'static str,
+    /// Number of values (1 if the option was not given or does not apply)
+    pub values: usize,
+    /// Number of parameter sets before and after expanding over this
+    /// dimension. Some dimensions (e.g. elders) only expand some sets, so
+    /// `after` may be less than `before × values`.
+    pub before: usize,
+    pub after: usize,
+}
+
+impl Dimension {
+    fn new(name: &'static str, values: usize, before: usize, after: usize) -> Self {
+        Dimension {
+            name: name,
+            values: values,
+            before: before,
+            after: after,
+        }
+    }
+}
+
+impl fmt::Display for Dimension {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        try!(write!(f, "{} × {}", self.values, self.name));
+        if self.after != self.before * self.values {
+            try!(write!(f, " (some sets only)"));
+        }
+        write!(f, " → {}", self.after)
+    }
+}
+
+/// The expansion as text for echoing at startup: the number of values of each
+/// dimension, in order of expansion, with the number of sets so far, and the
+/// product, e.g. "4 × nodes → 4, 6 × malicious → 24, ... = 24 combinations"
+pub fn expansion_text(dims: &[Dimension]) -> String {
+    let product: usize = dims.iter().map(|dim| dim.values).product();
+    let after = dims.last().map_or(1, |dim| dim.after);
+    let mut text = format!("expansion: {} = {} combinations",
+                           dims.iter().map(|dim| dim.to_string()).collect::<Vec<_>>().join(", "),
+                           product);
+    if after != product {
+        text.push_str(&format!(" ({} after dimensions expanding some sets only)", after));
+    }
+    text
+}
+
+/// The parameter sets of the options, made lazily: each set is only made when
+/// reached, so that a sweep of very many combinations need not be held in
+/// memory. The order is that in which the options expand (see `iter`).
+pub struct Expansion {
+    // The sets before expanding: the first of the options, or those read in
+    // batch mode
+    initial: Vec<SimParams>,
+    stages: Vec<Stage>,
+    // Applied to each set once expanded, e.g. clearing the parameters of other
+    // quorums and checking those of its own
+    finish: Box<Fn(&mut SimParams)>,
+    // Where each parameter of the tool came from, recorded as the options are
+    // read (none in batch mode)
+    sources: Vec<ParamSource>,
+}
+
+// One dimension of an expansion: the sets so far, followed by a copy of each
+// it applies to (all, without `applies`) for each further value, set by `set`
+// from the index of that value
+struct Stage {
+    name: &'static str,
+    // Number of values, counting the first (that of the sets so far)
+    values: usize,
+    applies: Option<Box<Fn(&SimParams) -> bool>>,
+    set: Box<Fn(&mut SimParams, usize)>,
+}
+
+impl Expansion {
+    fn new(initial: Vec<SimParams>) -> Self {
+        Expansion {
+            initial: initial,
+            stages: vec![],
+            finish: Box::new(|_| {}),
+            sources: vec![],
+        }
+    }
+
+    /// The parameters of the tool, as `ArgProc::param_sources`
+    pub fn sources(&self) -> &[ParamSource] {
+        &self.sources
+    }
+
+    // Expand all sets so far over the further values `rest` of a dimension
+    fn expand<T, I, F>(&mut self, name: &'static str, rest: I, set: F)
+        where T: 'static,
+              I: IntoIterator<Item = T>,
+              F: Fn(&mut SimParams, &T) + 'static
+    {
+        self.add_stage(name, rest, None, set);
+    }
+
+    // Expand those sets so far that `applies` to over the further values `rest`
+    fn expand_some<T, I, A, F>(&mut self, name: &'static str, rest: I, applies: A, set: F)
+        where T: 'static,
+              I: IntoIterator<Item = T>,
+              A: Fn(&SimParams) -> bool + 'static,
+              F: Fn(&mut SimParams, &T) + 'static
+    {
+        self.add_stage(name, rest, Some(Box::new(applies)), set);
+    }
+
+    fn add_stage<T, I, F>(&mut self,
+                          name: &'static str,
+                          rest: I,
+                          applies: Option<Box<Fn(&SimParams) -> bool>>,
+                          set: F)
+        where T: 'static,
+              I: IntoIterator<Item = T>,
+              F: Fn(&mut SimParams, &T) + 'static
+    {
+        let rest: Vec<T> = rest.into_iter().collect();
+        self.stages.push(Stage {
+            name: name,
+            values: rest.len() + 1,
+            applies: applies,
+            set: Box::new(move |s, i| set(s, &rest[i])),
+        });
+    }
+
+    /// The sets, in order of expansion: those of each dimension's first value
+    /// before those of its second, and so on, the first dimension varying
+    /// fastest. Sets are made as the iterator is advanced.
+    pub fn iter<'a>(&'a self) -> Box<Iterator<Item = SimParams> + 'a> {
+        Box::new(self.expanded(self.stages.len()).map(move |mut s| {
+            (self.finish)(&mut s);
+            s
+        }))
+    }
+
+    // The sets expanded over the first `stages` dimensions, not yet finished
+    fn expanded<'a>(&'a self, stages: usize) -> Box<Iterator<Item = SimParams> + 'a> {
+        if stages == 0 {
+            return Box::new(self.initial.iter().cloned());
+        }
+        let stage = &self.stages[stages - 1];
+        let copies = (1..stage.values).flat_map(move |value| {
+            self.expanded(stages - 1)
+                .filter(move |s| stage.applies.as_ref().map_or(true, |applies| applies(s)))
+                .map(move |mut s| {
+                    (stage.set)(&mut s, value - 1);
+                    s
+                })
+        });
+        Box::new(self.expanded(stages - 1).chain(copies))
+    }
+
+    /// The dimensions expanded over, in order, with the number of sets after
+    /// each. Only dimensions expanding some sets over several values need
+    /// those before made to be counted.
+    pub fn dims(&self) -> Vec<Dimension> {
+        let mut before = self.initial.len();
+        let mut dims = vec![];
+        for (i, stage) in self.stages.iter().enumerate() {
+            let copied = match stage.applies {
+                Some(ref applies) if stage.values > 1 => {
+                    self.expanded(i).filter(|s| applies(s)).count()
+                }
+                _ => before,
+            };
+            let after = before + (stage.values - 1) * copied;
+            dims.push(Dimension::new(stage.name, stage.values, before, after));
+            before = after;
+        }
+        dims
+    }
+
+    /// The number of sets, counted without keeping them
+    pub fn count(&self) -> usize {
+        self.dims().last().map_or(self.initial.len(), |dim| dim.after)
+    }
+}
+
+pub struct ArgProc {
+    // None for batch
+    sim_type: Option<SimType>,
+    args: Args,
+    prob_cache: Arc<ProbCache>,
+    interrupt: Interrupt,
+    // Whether making the sets of a scan (see `set_scan`)
+    scan: bool,
+}
+
+impl ArgProc {
+    /// Parse the arguments of this process. Unlike `from_argv`, the sets made
+    /// are also interrupted by Ctrl-C, once `interrupt::install` is called.
+    pub fn read_args() -> ArgProc {
+        ArgProc::read_argv(env::args())
+    }
+
+    /// As `read_args`, with the arguments given rather than those of this
+    /// process (e.g. those of the tool a command runs).
+    pub fn read_argv<I, S>(argv: I) -> ArgProc
+        where I: IntoIterator<Item = S>,
+              S: AsRef<str>
+    {
+        let mut arg_proc = ArgProc::from_argv(argv).unwrap_or_else(|e| e.exit());
+        arg_proc.interrupt = Interrupt::ctrl_c();
+        arg_proc
+    }
+
+    /// Parse a full argument vector (including the program name).
+    ///
+    /// The tool name selects which usage the remaining arguments are checked
+    /// against; errors name the tool. Without a valid tool name, only the
+    /// overview (`--help`) is available.
+    pub fn from_argv<I, S>(argv: I) -> Result<ArgProc, docopt::Error>
+        where I: IntoIterator<Item = S>,
+              S: AsRef<str>
+    {
+        let argv: Vec<String> = argv.into_iter().map(|s| s.as_ref().to_owned()).collect();
+        let sim_type = match argv.get(1).map(|s| s.as_str()) {
+            Some("calc") => Some(SimType::DirectCalc),
+            Some("structure") => Some(SimType::Structure),
+            Some("full") => Some(SimType::FullSim),
+            Some("batch") => None,
+            _ => {
+                // Either help was requested (giving an error to exit with) or
+                // the arguments are invalid.
+                try!(Docopt::new(USAGE).and_then(|dopt| dopt.argv(argv).parse()));
+                return Err(docopt::Error::WithProgramUsage(Box::new(docopt::Error::NoMatch),
+                                                           USAGE.trim().to_owned()));
+            }
+        };
+
+        let command = argv[1].clone();
+        // Docopt would only say the arguments are invalid
+        if let Some((short, long)) = both_forms(&argv) {
+            let msg = format!("routing-sims {}: {} and {} are the same option; give only one",
+                              command,
+                              short,
+                              long);
+            return Err(docopt::Error::Argv(msg));
+        }
+        let usage = sim_type.map_or(format!("{}{}", USAGE_BATCH, OPTIONS_RUN), |t| t.usage());
+        let args: Args = try!(Docopt::new(usage.as_str())
+            .and_then(|dopt| dopt.argv(argv).decode())
+            .map_err(|e| match e {
+                docopt::Error::WithProgramUsage(e, usage) => {
+                    let e = if e.fatal() {
+                        let msg = format!("routing-sims {}: {}", command, e);
+                        Box::new(docopt::Error::Argv(msg))
+                    } else {
+                        e
+                    };
+                    docopt::Error::WithProgramUsage(e, usage)
+                }
+                e => e,
+            }));
+        // Docopt only checks the form of arguments
+        if let Some(e) = mixed_range(&args) {
+            return Err(docopt::Error::Argv(format!("routing-sims {}: {}", command, e)));
+        }
+
+        let prob_cache = if args.flag_no_cache.unwrap_or(false) {
+            ProbCache::disabled()
+        } else {
+            ProbCache::new()
+        };
+        Ok(ArgProc {
+            sim_type: sim_type,
+            args: args,
+            prob_cache: Arc::new(prob_cache),
+            interrupt: Interrupt::new(),
+            scan: false,
+        })
+    }
+
+    /// Make the sets of a scan (see `scan`): the options it varies (see
+    /// `scan_bounds`) take their lower bounds, for the scan to replace.
+    pub fn set_scan(&mut self) {
+        self.scan = true;
+    }
+
+    /// Bounds of the options a scan varies: `-n`, `-r`, `-k` and `-q` given as
+    /// ranges, their steps ignored. Lists, formulae and bounds which do not
+    /// increase are errors, as is varying none.
+    pub fn scan_bounds(&self) -> Result<ScanBounds, String> {
+        if self.args.flag_group_size.as_ref().map_or(false, |s| GroupSizeFormula::is_formula(s)) {
+            return Err("-k: a formula cannot be scanned; give a range".to_owned());
+        }
+        let bounds = ScanBounds {
+            nodes: try!(scan_range(self.args.flag_nodes.as_ref(), "-n")),
+            malicious: try!(scan_range(self.args.flag_malicious.as_ref(), "-r")),
+            group_size: try!(scan_range(self.args.flag_group_size.as_ref(), "-k")),
+            quorum: try!(scan_range(self.args.flag_quorum.as_ref(), "-q")),
+        };
+        if bounds.dims() == 0 {
+            return Err("give at least one of -n, -r, -k and -q as a range to scan".to_owned());
+        }
+        Ok(bounds)
+    }
+
+    // A range of an option a scan may vary, as a single value at its start when making the
+    // sets of a scan
+    fn pinned<T>(&self, points: SamplePoints<T>) -> SamplePoints<T> {
+        match points {
+            SamplePoints::Range(start, _, _) if self.scan => SamplePoints::Number(start),
+            points => points,
+        }
+    }
+
+    /// Cache of probabilities, shared by all parameter sets
+    pub fn prob_cache(&self) -> &ProbCache {
+        &self.prob_cache
+    }
+
+    /// Request to stop, shared by all parameter sets
+    pub fn interrupt(&self) -> &Interrupt {
+        &self.interrupt
+    }
+
+    /// Estimated run time (seconds) above which to ask for confirmation
+    pub fn confirm_above(&self) -> Option<RR> {
+        self.args.flag_confirm_above.as_ref().map(|s| {
+            parse_duration(s)
+                .unwrap_or_else(|e| panic!("unexpected: --confirm-above {} ({})", s, e))
+        })
+    }
+
+    /// Cap on the estimated memory of parameter sets running at once, in
+    /// bytes (see `--max-mem`)
+    pub fn max_mem(&self) -> Option<RR> {
+        self.args.flag_max_mem.as_ref().map(|s| match parse_bytes(s) {
+            Some(bytes) if bytes > 0.0 => bytes,
+            _ => panic!("unexpected: --max-mem {}", s),
+        })
+    }
+
+    /// Order in which to start the parameter sets (see `--schedule`)
+    pub fn schedule(&self) -> Schedule {
+        match self.args.flag_schedule.as_ref().map(|s| s.as_str()) {
+            None | Some("expansion") => Schedule::Expansion,
+            Some("cost") => Schedule::Cost,
+            Some("shuffle") => {
+                let mut seeding = Seeding::new();
+                seeding.seed = self.args.flag_seed;
+                Schedule::Shuffle(seeding.master())
+            }
+            Some(s) => panic!("unexpected: --schedule {}", s),
+        }
+    }
+
+    /// True if the sets can run as they are made, rather than all made first
+    /// (see `Expansion`): those of the calc tool in the order of expansion,
+    /// unless the options need all sets or results at once (--auto-extend,
+    /// --report-html, --dry-run, --compare-models)
+    pub fn streamed(&self) -> bool {
+        self.sim_type == Some(SimType::DirectCalc) && !self.scan &&
+        self.schedule() == Schedule::Expansion && self.auto_extend().is_none() &&
+        self.report_html().is_none() && !self.dry_run() &&
+        !self.args.flag_compare_models.unwrap_or(false)
+    }
+
+    /// Level of the messages to log (see `-v` and `--log-level`)
+    pub fn log_level(&self) -> LogLevelFilter {
+        match self.args.flag_log_level {
+            None => logging::verbosity(self.args.flag_v),
+            Some(_) if self.args.flag_v > 0 => panic!("unexpected: -v with --log-level"),
+            Some(ref s) => {
+                s.parse().unwrap_or_else(|_| panic!("unexpected: --log-level {}", s))
+            }
+        }
+    }
+
+    /// True if confirmation should be assumed
+    pub fn yes(&self) -> bool {
+        self.args.flag_yes
+    }
+
+    /// True if full simulations should be run in rounds (see `--interleave`)
+    pub fn interleave(&self) -> bool {
+        self.args.flag_interleave.unwrap_or(false)
+    }
+
+    /// Repetitions to share between all parameter sets, if the run is
+    /// budgeted (see `--global-budget`)
+    pub fn global_budget(&self) -> Option<NN> {
+        self.args.flag_global_budget.map(|budget| {
+            if budget == 0 {
+                panic!("unexpected: --global-budget 0 (must be at least 1)");
+            }
+            if self.interleave() {
+                panic!("unexpected: --global-budget with --interleave (both run the sets in \
+                        rounds of their own)");
+            }
+            budget
+        })
+    }
+
+    /// Extension of the range of a parameter once the sets have run, if any
+    /// (see `--auto-extend`)
+    pub fn auto_extend(&self) -> Option<AutoExtend> {
+        let param = match self.args.flag_auto_extend {
+            Some(ref s) => {
+                ExtendParam::from_name(s).unwrap_or_else(|| {
+                    panic!("unexpected: --auto-extend {} (expected r, k or q)", s)
+                })
+            }
+            None => {
+                if self.args.flag_target.is_some() {
+                    panic!("--target requires --auto-extend");
+                }
+                if self.args.flag_extend_bounds.is_some() {
+                    panic!("--extend-bounds requires --auto-extend");
+                }
+                return None;
+            }
+        };
+        if self.interleave() || self.args.flag_global_budget.is_some() {
+            panic!("unexpected: --auto-extend with --interleave or --global-budget (which run \
+                    the sets in rounds of their own)");
+        }
+        let target = match self.args.flag_target {
+            Some(ref s) => {
+                match s.parse::<RR>() {
+                    Ok(p) if p > 0.0 && p < 1.0 => p,
+                    _ => panic!("unexpected: --target {} (must be above 0 and below 1)", s),
+                }
+            }
+            None => 0.5,
+        };
+        let bounds = self.args.flag_extend_bounds.as_ref().map(|s| {
+            param.bounds(s).unwrap_or_else(|e| panic!("unexpected: --extend-bounds {} ({})", s, e))
+        });
+        Some(AutoExtend {
+            param: param,
+            target: target,
+            bounds: bounds,
+        })
+    }
+
+    /// Wall time (seconds) after which to stop repeating the simulations of a
+    /// parameter set, if any (see `--time-limit`)
+    pub fn time_limit(&self) -> Option<RR> {
+        self.args.flag_time_limit.as_ref().map(|s| {
+            if self.args.flag_canonical {
+                panic!("unexpected: --time-limit {} with --canonical (stopping on time is not \
+                        reproducible)",
+                       s);
+            }
+            parse_duration(s)
+                .unwrap_or_else(|e| panic!("unexpected: --time-limit {} ({})", s, e))
+        })
+    }
+
+    /// The durations given (time limit, checkpoint interval and confirmation
+    /// threshold), normalised, for echoing at startup, e.g. "time limit =
+    /// 2h30m (from --time-limit)"
+    pub fn duration_sources(&self) -> Vec<ParamSource> {
+        let every = self.args.flag_checkpoint_every.as_ref().map(|s| {
+            parse_duration(s)
+                .unwrap_or_else(|e| panic!("unexpected: --checkpoint-every {} ({})", s, e))
+        });
+        vec![("time limit", "--time-limit", self.time_limit()),
+             ("checkpoint interval", "--checkpoint-every", every),
+             ("confirmation threshold", "--confirm-above", self.confirm_above())]
+            .into_iter()
+            .filter_map(|(name, flag, secs)| {
+                secs.map(|secs| ParamSource::new(name, flag, Some(format_duration(secs)), ""))
+            })
+            .collect()
+    }
+
+    /// True if output should be the same byte for byte between runs (see
+    /// `--canonical`)
+    pub fn canonical(&self) -> bool {
+        self.args.flag_canonical
+    }
+
+    /// Number of folds of the repetitions of full simulations to compare, if
+    /// any (see `--split-check`)
+    pub fn split_check(&self) -> Option<NN> {
+        match self.args.flag_split_check {
+            Some(k) if k < 2 => panic!("unexpected: --split-check {} (must be at least 2)", k),
+            k => k,
+        }
+    }
+
+    /// True if parameter sets stopped by the time limit should count in the
+    /// reports summarising several sets (see `--include-truncated`)
+    pub fn include_truncated(&self) -> bool {
+        self.args.flag_include_truncated.unwrap_or(false)
+    }
+
+    /// Whether to output the marginal effect of each swept parameter, and if so
+    /// whether with geometric means (see `--marginals`)
+    pub fn marginals(&self) -> Option<bool> {
+        if self.args.flag_log_marginals {
+            Some(true)
+        } else if self.args.flag_marginals {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// File to write the HTML report to, if any (see `--report-html`)
+    pub fn report_html(&self) -> Option<&str> {
+        self.args.flag_report_html.as_ref().map(|s| s.as_str())
+    }
+
+    /// True if parameter sets should be output instead of run
+    pub fn dry_run(&self) -> bool {
+        self.args.flag_dry_run
+    }
+
+    /// File to write the attacker's recorded decisions to, if any (see
+    /// `--record-attack`)
+    pub fn record_attack(&self) -> Option<&str> {
+        self.args.flag_record_attack.as_ref().map(|s| s.as_str())
+    }
+
+    /// File of attacker decisions to replay, if any (see `--replay-attack`)
+    pub fn replay_attack(&self) -> Option<&str> {
+        self.args.flag_replay_attack.as_ref().map(|s| s.as_str())
+    }
+
+    /// File to write the values drawn from each random number stream to, if
+    /// any (see `--rng-audit`)
+    pub fn rng_audit(&self) -> Option<&str> {
+        self.args.flag_rng_audit.as_ref().map(|s| s.as_str())
+    }
+
+    /// File to write time-series reports to, if any
+    pub fn curve_path(&self) -> Option<&str> {
+        self.args.flag_curve.as_ref().map(|s| s.as_str())
+    }
+
+    /// True if the partial curve file of a failed run should be kept
+    pub fn keep_partial(&self) -> bool {
+        self.args.flag_keep_partial.unwrap_or(false)
+    }
+
+    /// Cache of results to reuse and save them to, if any (see `--cache`)
+    pub fn result_cache(&self) -> Option<ResultCache> {
+        match self.args.flag_cache {
+            Some(ref dir) => {
+                if let Err(e) = fs::create_dir_all(dir) {
+                    panic!("unable to create cache directory {}: {}", dir, e);
+                }
+                Some(ResultCache::new(PathBuf::from(dir), self.args.flag_refresh))
+            }
+            None => {
+                if self.args.flag_refresh {
+                    panic!("--refresh requires --cache");
+                }
+                None
+            }
+        }
+    }
+
+    /// Main table of an earlier run to compare with, if any
+    pub fn baseline_path(&self) -> Option<&str> {
+        self.args.flag_baseline.as_ref().map(|s| s.as_str())
+    }
+
+    /// True if a baseline or checkpoints of another version of the model
+    /// should be used anyway (see `model::check`)
+    pub fn allow_model_mismatch(&self) -> bool {
+        self.args.flag_allow_model_mismatch
+    }
+
+    /// True if the analytic P(compromise) of each row should be shown (see
+    /// `--baseline-column`)
+    pub fn baseline_column(&self) -> bool {
+        self.args.flag_baseline_column.unwrap_or(false)
+    }
+
+    /// The parameters of the tool, with their values as given or defaulted and
+    /// where each came from (none in batch mode, where they are read from
+    /// stdin).
+    pub fn param_sources(&self) -> Vec<ParamSource> {
+        self.expansion().sources
+    }
+
+    /// With `--strict`, an error naming the tool unless all of `-n`, `-r`,
+    /// `-k` and `-q` were given.
+    /// `sources` are those of `param_sources`.
+    pub fn check_strict(&self, sources: &[ParamSource]) -> Result<(), String> {
+        if !self.args.flag_strict.unwrap_or(false) {
+            return Ok(());
+        }
+        let missing: Vec<&str> = sources.iter()
+            .filter(|p| STRICT_FLAGS.contains(&p.flag) && p.provenance == Provenance::Default)
+            .map(|p| p.flag)
+            .collect();
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            let command = self.sim_type.map_or("batch", |t| t.command());
+            Err(format!("routing-sims {}: --strict requires {}", command, missing.join(", ")))
+        }
+    }
+
+    /// How to format probabilities for output
+    pub fn prob_format(&self) -> ProbFormat {
+        let mut format = ProbFormat::new();
+        if let Some(precision) = self.args.flag_precision {
+            format.precision = precision;
+        }
+        format.sci = self.args.flag_sci;
+        format.log10 = self.args.flag_log10;
+        format.full = self.args.flag_canonical;
+        format
+    }
+
+    /// How to show P(compromise) as a bar, if at all
+    pub fn viz(&self) -> Option<Viz> {
+        if !self.args.flag_viz {
+            return None;
+        }
+        let mut viz = Viz::new();
+        viz.scale = match self.args.flag_viz_scale.as_ref().map(|s| s.as_str()) {
+            None | Some("log") => VizScale::Log,
+            Some("linear") => VizScale::Linear,
+            Some(x) => panic!("unexpected: --viz-scale {}", x),
+        };
+        if let Some(ref range) = self.args.flag_viz_range {
+            let bounds: Vec<RR> = range.split(':')
+                .map(|x| x.parse().unwrap_or_else(|_| panic!("unexpected: --viz-range {}", range)))
+                .collect();
+            let min_low = if viz.scale == VizScale::Log { 0.0 } else { -1.0 };
+            if bounds.len() != 2 || bounds[0] <= min_low || bounds[0] >= bounds[1] {
+                panic!("unexpected: --viz-range {}", range);
+            }
+            viz.low = bounds[0];
+            viz.high = bounds[1];
+        }
+        Some(viz)
+    }
+
+    pub fn make_sim_params(&self) -> Vec<SimParams> {
+        self.expansion().iter().collect()
+    }
+
+    /// Parameter sets, as `make_sim_params`, with the dimensions they were
+    /// expanded over, in order (none in batch mode).
+    pub fn expand_sim_params(&self) -> (Vec<SimParams>, Vec<Dimension>) {
+        let expansion = self.expansion();
+        (expansion.iter().collect(), expansion.dims())
+    }
+
+    /// The parameter sets of the options, made lazily (see `Expansion`); in
+    /// batch mode, those read from stdin.
+    pub fn expansion(&self) -> Expansion {
+        let mut malice_hist = false;
+        let mut spread = false;
+        let mut address_coverage = false;
+        let mut elders_report = false;
+        let mut age_share = false;
+        let mut liveness = false;
+        let mut termination = false;
+        let mut governance = false;
+        let mut turnover = false;
+        if let Some(reports) = self.args.flag_report.as_ref() {
+            for report in reports.split(',') {
+                match report {
+                    "malice-hist" => malice_hist = true,
+                    "spread" => spread = true,
+                    "address-coverage" => address_coverage = true,
+                    "elders" => elders_report = true,
+                    "age-share" => age_share = true,
+                    "liveness" => liveness = true,
+                    "termination" => termination = true,
+                    "governance" => governance = true,
+                    "turnover" => turnover = true,
+                    x => panic!("unexpected: --report {}", x),
+                }
+            }
+        }
+        // The reports measuring something other than compromise may be given as metrics too
+        if let Some(metrics) = self.args.flag_metric.as_ref() {
+            for metric in metrics.split(',') {
+                match metric {
+                    "liveness" => liveness = true,
+                    "governance" => governance = true,
+                    x => panic!("unexpected: --metric {}", x),
+                }
+            }
+        }
+        if liveness && self.sim_type == Some(SimType::Structure) {
+            panic!("--report liveness requires the full simulation");
+        }
+        if termination && self.sim_type == Some(SimType::Structure) {
+            panic!("--report termination requires the full simulation");
+        }
+        if governance && self.sim_type == Some(SimType::Structure) {
+            panic!("--report governance requires the full simulation");
+        }
+        if turnover && self.sim_type == Some(SimType::Structure) {
+            panic!("--report turnover requires the full simulation");
+        }
+        if age_share != self.args.flag_curve.is_some() {
+            panic!("--report age-share and --curve FILE must be used together");
+        }
+        let age_share_interval = if age_share {
+            match self.args.flag_curve_interval.unwrap_or(10) {
+                0 => panic!("unexpected: --curve-interval 0"),
+                interval => Some(interval),
+            }
+        } else {
+            None
+        };
+
+        let checkpoint = match self.args.flag_checkpoint_dir {
+            Some(ref dir) => {
+                if let Err(e) = fs::create_dir_all(dir) {
+                    panic!("unable to create checkpoint directory {}: {}", dir, e);
+                }
+                let every = self.args.flag_checkpoint_every.as_ref().map_or(600.0, |s| {
+                    parse_duration(s).unwrap_or_else(|e| {
+                        panic!("unexpected: --checkpoint-every {} ({})", s, e)
+                    })
+                });
+                Some(Checkpointing {
+                    dir: PathBuf::from(dir),
+                    every: every,
+                    allow_model_mismatch: self.args.flag_allow_model_mismatch,
+                    keep_partial: self.keep_partial(),
+                })
+            }
+            None => {
+                if self.args.flag_checkpoint_every.is_some() {
+                    panic!("--checkpoint-every requires --checkpoint-dir");
+                }
+                None
+            }
+        };
+
+        let time_limit = self.time_limit();
+
+        let paired_ageing = self.args.flag_paired_ageing.unwrap_or(false);
+        let mut seeding = Seeding::new();
+        seeding.seed = self.args.flag_seed;
+        if paired_ageing && seeding.seed.is_none() {
+            // Both sets of a pair must have the same random numbers
+            let seed = seeding.master();
+            warn!("paired ageing: master seed {}", seed);
+            seeding.seed = Some(seed);
+        }
+        if self.args.flag_canonical && seeding.seed.is_none() &&
+           self.sim_type != Some(SimType::DirectCalc) {
+            warn!("--canonical without --seed: simulated results will differ between runs");
+        }
+        if let Some(streams) = self.args.flag_fix_stream.as_ref() {
+            for name in streams.split(',') {
+                match Stream::from_name(name) {
+                    Some(stream) => seeding.fixed.push(stream),
+                    None => panic!("unexpected: --fix-stream {}", name),
+                }
+            }
+        }
+        let seeding_sources = vec![ParamSource::new("seed",
+                                                    "--seed",
+                                                    self.args.flag_seed.map(|s| s.to_string()),
+                                                    "random"),
+                                   ParamSource::new("fixed streams",
+                                                    "--fix-stream",
+                                                    self.args.flag_fix_stream.clone(),
+                                                    "none")];
+
+        let sim_type = match self.sim_type {
+            Some(sim_type) => sim_type,
+            None => {
+                let stdin = io::stdin();
+                let skip_bad_lines = self.args.flag_skip_bad_lines.unwrap_or(false);
+                let mut v = batch::read_params(stdin.lock(), skip_bad_lines).unwrap_or_else(|e| {
+                    error!("routing-sims batch: {}", e);
+                    process::exit(1);
+                });
+                for params in &mut v {
+                    params.malice_hist = malice_hist;
+                    params.spread = spread;
+                    params.address_coverage = address_coverage;
+                    params.elders_report = elders_report;
+                    params.seeding = seeding.clone();
+                    params.time_limit = time_limit;
+                    params.prob_cache = self.prob_cache.clone();
+                    params.interrupt = self.interrupt.clone();
+                    if params.sim_type == SimType::FullSim {
+                        params.age_share_interval = age_share_interval;
+                        params.liveness = liveness;
+                        params.termination = termination;
+                        params.governance = governance;
+                        params.turnover = turnover;
+                        params.split_check = self.split_check();
+                        params.checkpoint = checkpoint.clone();
+                    }
+                }
+                return Expansion::new(v);
+            }
+        };
+        let a = &self.args;
+        let num = |n: Option<NN>| n.map(|n| n.to_string());
+        let mut sources = vec![];
+
+        let default_nodes: NN = 1000;
+        let nodes_range: SamplePoints<NN> = self.args
+            .flag_nodes
+            .as_ref()
+            .map_or(SamplePoints::Number(default_nodes), |s| {
+                s.parse().unwrap_or_else(|e| panic!("unexpected: -n {} ({})", s, e))
+            });
+        sources.push(ParamSource::new("nodes", "-n", a.flag_nodes.clone(), default_nodes));
+        let nodes_range = self.pinned(nodes_range);
+        let mut nodes_iter = nodes_range.iter();
+
+        let default_malicious = RelOrAbs::Rel(0.1);
+        let mal_nodes_range: SamplePoints<RelOrAbs> =
+            self.args.flag_malicious.as_ref().map_or(SamplePoints::Number(default_malicious), |s| {
+                s.parse().unwrap_or_else(|e| panic!("unexpected: -r {} ({})", s, e))
+            });
+        sources.push(ParamSource::new("malicious",
+                                      "-r",
+                                      a.flag_malicious.clone(),
+                                      default_malicious));
+        let mal_nodes_range = self.pinned(mal_nodes_range);
+        let mut mal_nodes_iter = mal_nodes_range.iter();
+
+        // A formula gives one group size per network size, so is not replicated over; the
+        // size is resolved once the network sizes are known
+        let group_size_formula: Option<GroupSizeFormula> = self.args
+            .flag_group_size
+            .as_ref()
+            .and_then(|s| if GroupSizeFormula::is_formula(s) {
+                Some(s.parse().unwrap_or_else(|e| panic!("unexpected: -k {} ({})", s, e)))
+            } else {
+                None
+            });
+        let default_group_size: NN = 10;
+        let group_size_range: SamplePoints<NN> = match self.args.flag_group_size {
+            Some(ref s) if group_size_formula.is_none() => {
+                s.parse().unwrap_or_else(|e| panic!("unexpected: -k {} ({})", s, e))
+            }
+            _ => SamplePoints::Number(default_group_size),
+        };
+        sources.push(ParamSource::new("min group size",
+                                      "-k",
+                                      a.flag_group_size.clone(),
+                                      default_group_size));
+        let group_size_range = self.pinned(group_size_range);
+        let mut group_size_iter = group_size_range.iter();
+
+        let default_quorum = QuorumSize::Proportion(0.5);
+        let quorum_range = self.args
+            .flag_quorum
+            .as_ref()
+            .map_or(SamplePoints::Number(default_quorum), |s| {
+                s.parse().unwrap_or_else(|e| panic!("unexpected: -q {} ({})", s, e))
+            });
+        sources.push(ParamSource::new("quorum size", "-q", a.flag_quorum.clone(), default_quorum));
+        let quorum_range = self.pinned(quorum_range);
+        let mut quorum_iter = quorum_range.iter();
+
+        let quorum_floor_range: Option<SamplePoints<NN>> =
+            self.args.flag_quorum_floor.as_ref().map(|s| {
+                s.parse().unwrap_or_else(|e| panic!("unexpected: --quorum-floor {} ({})", s, e))
+            });
+        let quorum_floors: Vec<Option<NN>> =
+            quorum_floor_range.map_or(vec![None], |range| range.iter().map(Some).collect());
+        sources.push(ParamSource::new("quorum floor",
+                                      "--quorum-floor",
+                                      a.flag_quorum_floor.clone(),
+                                      "none"));
+        let mut quorum_floor_iter = quorum_floors.iter();
+
+        let block_prop_range: Option<SamplePoints<RR>> =
+            self.args.flag_block_prop.as_ref().map(|s| {
+                s.parse().unwrap_or_else(|e| panic!("unexpected: --block-prop {} ({})", s, e))
+            });
+        let block_props: Vec<Option<RR>> = block_prop_range.map_or(vec![None], |range| {
+            range.iter()
+                .map(|b| {
+                    if b < 0.0 || b > 1.0 {
+                        panic!("unexpected: --block-prop {}", b);
+                    }
+                    Some(b)
+                })
+                .collect()
+        });
+        // Without a blocking proportion, groups are blocked by their quorum
+        sources.push(ParamSource::new("blocking proportion",
+                                      "--block-prop",
+                                      a.flag_block_prop.clone(),
+                                      "quorum"));
+        let mut block_prop_iter = block_props.iter();
+
+        let default_participation: RR = 1.0;
+        let participations: Vec<RR> =
+            self.args.flag_participation.as_ref().map_or(vec![default_participation], |s| {
+                let range: SamplePoints<RR> = s.parse()
+                    .unwrap_or_else(|e| panic!("unexpected: --participation {} ({})", s, e));
+                range.iter()
+                    .map(|p| {
+                        if p <= 0.0 || p > 1.0 {
+                            panic!("unexpected: --participation {} (must be above 0 and at most 1)",
+                                   p);
+                        }
+                        p
+                    })
+                    .collect()
+            });
+        sources.push(ParamSource::new("participation",
+                                      "--participation",
+                                      a.flag_participation.clone(),
+                                      default_participation));
+        let mut participation_iter = participations.iter();
+
+        let q_type = match self.args.flag_quorum_type.as_ref().map(|s| s.as_str()) {
+            None if paired_ageing => vec![QuorumType::Simple, QuorumType::Age],
+            Some(x) if paired_ageing => {
+                panic!("unexpected: -Q {} (--paired-ageing runs the simple and age quorums)", x)
+            }
+            None => vec![QuorumType::Simple],
+            Some("simple") => vec![QuorumType::Simple],
+            Some("age") => vec![QuorumType::Age],
+            Some("elder") => vec![QuorumType::Elder],
+            Some("all") => vec![QuorumType::Simple, QuorumType::Age, QuorumType::Elder],
+            Some(x) => panic!("unexpected: -Q {}", x),
+        };
+        let q_names: Vec<&str> = q_type.iter().map(|q| q.name()).collect();
+        sources.push(ParamSource::new("quorum type",
+                                      "-Q",
+                                      a.flag_quorum_type.clone(),
+                                      q_names.join(",")));
+        let mut q_type_iter = q_type.iter();
+
+        let targetting = a.flag_targetting.as_ref().map_or("none", |s| s.as_str());
+        let at_type = match targetting {
+            "none" => vec![AttackType::Untargetted],
+            "simple" => vec![AttackType::SimpleTargetted],
+            "deadline" => vec![AttackType::DeadlineTargetted],
+            "threshold" => vec![AttackType::Threshold],
+            "all" => {
+                vec![AttackType::Untargetted,
+                     AttackType::SimpleTargetted,
+                     AttackType::DeadlineTargetted]
+            }
+            x if x.contains('+') => {
+                vec![AttackType::parse_composite(x)
+                         .unwrap_or_else(|e| panic!("unexpected: -T {} ({})", x, e))]
+            }
+            x => panic!("unexpected: -T {}", x),
+        };
+        sources.push(ParamSource::new("targetting", "-T", a.flag_targetting.clone(), targetting));
+        // The direct calculation takes --model instead, bounding simple targetting
+        let at_type = match self.args.flag_model.as_ref().map(|s| s.as_str()) {
+            None => at_type,
+            Some("untargetted") => vec![AttackType::Untargetted],
+            Some("targetted-bound") => vec![AttackType::SimpleTargetted],
+            Some("all") => vec![AttackType::Untargetted, AttackType::SimpleTargetted],
+            Some(x) => panic!("unexpected: --model {}", x),
+        };
+        let model_specs: Vec<String> = at_type.iter().map(|at| at.spec()).collect();
+        sources.push(ParamSource::new("model",
+                                      "--model",
+                                      a.flag_model.clone(),
+                                      model_specs.join(",")));
+        let max_steps = self.args.flag_steps.unwrap_or(1000);
+        sources.push(ParamSource::new("steps", "-s", num(a.flag_steps), max_steps));
+        for at in &at_type {
+            at.check_steps(max_steps)
+                .unwrap_or_else(|e| panic!("unexpected: -T {} ({})", at.name(), e));
+        }
+        let mut at_type_iter = at_type.iter();
+
+        let churn_model = match self.args.flag_churn_model.as_ref() {
+            None => ChurnModel::Fixed,
+            Some(x) => match ChurnModel::from_name(x) {
+                Some(model) => model,
+                None => panic!("unexpected: --churn-model {}", x),
+            },
+        };
+        sources.push(ParamSource::new("churn model",
+                                      "--churn-model",
+                                      a.flag_churn_model.clone(),
+                                      churn_model.name()));
+        let objective_name = a.flag_objective.as_ref().map_or("compromise", |s| s.as_str());
+        let objective = match objective_name {
+            "all" => vec![Objective::Compromise, Objective::Disruption],
+            x => {
+                vec![Objective::from_name(x)
+                         .unwrap_or_else(|| panic!("unexpected: --objective {}", x))]
+            }
+        };
+        sources.push(ParamSource::new("objective",
+                                      "--objective",
+                                      a.flag_objective.clone(),
+                                      objective_name));
+        let mut objective_iter = objective.iter();
+
+        let knowledge_name = a.flag_attacker_knowledge.as_ref().map_or("full", |s| s.as_str());
+        let knowledge = match knowledge_name {
+            "all" => {
+                vec![AttackerKnowledge::Full,
+                     AttackerKnowledge::MembershipOnly,
+                     AttackerKnowledge::OwnNodesOnly]
+            }
+            x => {
+                vec![AttackerKnowledge::from_name(x)
+                         .unwrap_or_else(|| panic!("unexpected: --attacker-knowledge {}", x))]
+            }
+        };
+        sources.push(ParamSource::new("attacker knowledge",
+                                      "--attacker-knowledge",
+                                      a.flag_attacker_knowledge.clone(),
+                                      knowledge_name));
+        let mut knowledge_iter = knowledge.iter();
+
+        let relocation_name = a.flag_relocation_target.as_ref().map_or("random", |s| s.as_str());
+        let relocation = match relocation_name {
+            "random" => vec![RelocationTarget::Random],
+            "balanced" => vec![RelocationTarget::Balanced],
+            "all" => vec![RelocationTarget::Random, RelocationTarget::Balanced],
+            x => panic!("unexpected: --relocation-target {}", x),
+        };
+        sources.push(ParamSource::new("relocation target",
+                                      "--relocation-target",
+                                      a.flag_relocation_target.clone(),
+                                      relocation_name));
+        let mut relocation_iter = relocation.iter();
+
+        let pow_scaling_name = a.flag_pow_scaling.as_ref().map_or("none", |s| s.as_str());
+        let pow_scaling = match pow_scaling_name {
+            "all" => vec![PowScaling::Constant, PowScaling::LogN, PowScaling::LinearN],
+            x => {
+                vec![PowScaling::from_name(x)
+                         .unwrap_or_else(|| panic!("unexpected: --pow-scaling {}", x))]
+            }
+        };
+        sources.push(ParamSource::new("pow scaling",
+                                      "--pow-scaling",
+                                      a.flag_pow_scaling.clone(),
+                                      pow_scaling_name));
+        let mut pow_scaling_iter = pow_scaling.iter();
+
+        let default_attack_start: NN = 0;
+        let attack_start_range: SamplePoints<NN> = self.args
+            .flag_attack_start
+            .as_ref()
+            .map_or(SamplePoints::Number(default_attack_start), |s| {
+                s.parse().unwrap_or_else(|e| panic!("unexpected: --attack-start {} ({})", s, e))
+            });
+        for start in attack_start_range.iter() {
+            if start > 0 && start >= max_steps {
+                panic!("unexpected: --attack-start {} (must be below the number of steps)",
+                       start);
+            }
+        }
+        sources.push(ParamSource::new("attack start",
+                                      "--attack-start",
+                                      a.flag_attack_start.clone(),
+                                      default_attack_start));
+        let mut attack_start_iter = attack_start_range.iter();
+
+        let hold = self.args.flag_hold.unwrap_or(1);
+        if hold == 0 {
+            panic!("unexpected: --hold 0 (must be at least 1)");
+        }
+        sources.push(ParamSource::new("hold", "--hold", num(a.flag_hold), hold));
+        let steady_state: Option<SteadyState> = self.args.flag_steady_state.as_ref().map(|s| {
+            let steady: SteadyState =
+                s.parse().unwrap_or_else(|e| panic!("unexpected: --steady-state {} ({})", s, e));
+            if steady.batch_steps(max_steps) == 0 {
+                panic!("unexpected: --steady-state {} (needs a step for each batch after the \
+                        burn-in)",
+                       s);
+            }
+            steady
+        });
+        let close_group: Option<CloseGroup> = self.args.flag_close_group.as_ref().map(|s| {
+            s.parse().unwrap_or_else(|e| panic!("unexpected: --close-group {} ({})", s, e))
+        });
+        let membership_lag = self.args.flag_membership_lag.unwrap_or(0);
+        sources.push(ParamSource::new("membership lag",
+                                      "--membership-lag",
+                                      num(a.flag_membership_lag),
+                                      membership_lag));
+        let join_cap_mode = self.args.flag_join_cap_mode.as_ref().map(|s| {
+            JoinCapMode::from_name(s).unwrap_or_else(|| {
+                panic!("unexpected: --join-cap-mode {} (must be reject or queue)", s)
+            })
+        });
+        if join_cap_mode.is_some() && self.args.flag_join_cap.is_none() {
+            panic!("--join-cap-mode requires --join-cap");
+        }
+        let join_cap: Option<JoinCap> = self.args.flag_join_cap.as_ref().map(|s| {
+            let mut cap: JoinCap =
+                s.parse().unwrap_or_else(|e| panic!("unexpected: --join-cap {} ({})", s, e));
+            if let Some(mode) = join_cap_mode {
+                cap.mode = mode;
+            }
+            cap
+        });
+        sources.push(ParamSource::new("join cap", "--join-cap", a.flag_join_cap.clone(), "none"));
+        // A cap's mode, unless given, is that of its spec: rejecting, without `+queue`
+        sources.push(ParamSource::new("join cap mode",
+                                      "--join-cap-mode",
+                                      a.flag_join_cap_mode.clone(),
+                                      JoinCapMode::Reject.name()));
+        if self.args.flag_paranoid == Some(0) {
+            panic!("unexpected: --paranoid 0 (must be at least 1)");
+        }
+        if self.args.flag_reps == Some(0) && sim_type != SimType::DirectCalc {
+            panic!("unexpected: -p 0 ({} simulates at least one repetition; for the analytic \
+                    result alone, use calc)",
+                   sim_type.command());
+        }
+
+        let quorum_basis_name = a.flag_quorum_basis.as_ref().map_or("actual", |s| s.as_str());
+        let quorum_basis = match quorum_basis_name {
+            "all" => vec![QuorumBasis::Actual, QuorumBasis::Minimum],
+            x => {
+                vec![QuorumBasis::from_name(x)
+                         .unwrap_or_else(|| panic!("unexpected: --quorum-basis {}", x))]
+            }
+        };
+        sources.push(ParamSource::new("quorum basis",
+                                      "--quorum-basis",
+                                      a.flag_quorum_basis.clone(),
+                                      quorum_basis_name));
+        let mut quorum_basis_iter = quorum_basis.iter();
+
+        if elders_report && !q_type.contains(&QuorumType::Elder) {
+            panic!("--report elders requires an elder quorum (-Q elder or -Q all)");
+        }
+
+        let elders_range: SamplePoints<NN> = self.args
+            .flag_elders
+            .as_ref()
+            .map_or(SamplePoints::Number(DEFAULT_ELDERS as NN), |s| {
+                s.parse().unwrap_or_else(|e| panic!("unexpected: --elders {} ({})", s, e))
+            });
+        if self.args.flag_elders.is_some() && !q_type.contains(&QuorumType::Elder) {
+            panic!("--elders requires an elder quorum (-Q elder or -Q all)");
+        }
+        sources.push(ParamSource::new("elders", "--elders", a.flag_elders.clone(), DEFAULT_ELDERS));
+        let mut elders_iter = elders_range.iter();
+        let promotion_lag = self.args.flag_promotion_lag.unwrap_or(0);
+        sources.push(ParamSource::new("promotion lag",
+                                      "--promotion-lag",
+                                      num(a.flag_promotion_lag),
+                                      promotion_lag));
+        if self.args.flag_promotion_lag.is_some() && !q_type.contains(&QuorumType::Elder) {
+            panic!("--promotion-lag requires an elder quorum (-Q elder or -Q all)");
+        }
+        let elder_tiebreak =
+            self.args.flag_elder_tiebreak.as_ref().map_or(ElderTiebreak::Name, |s| {
+                ElderTiebreak::from_name(s).unwrap_or_else(|| {
+                    panic!("unexpected: --elder-tiebreak {} (must be name, join-order or \
+                            random-per-decision)",
+                           s)
+                })
+            });
+        sources.push(ParamSource::new("elder tiebreak",
+                                      "--elder-tiebreak",
+                                      a.flag_elder_tiebreak.clone(),
+                                      elder_tiebreak.name()));
+        if self.args.flag_elder_tiebreak.is_some() && !q_type.contains(&QuorumType::Elder) {
+            panic!("--elder-tiebreak requires an elder quorum (-Q elder or -Q all)");
+        }
+        let name_grinding = self.args.flag_name_grinding.unwrap_or(0.0);
+        if name_grinding < 0.0 || name_grinding > 1.0 {
+            panic!("unexpected: --name-grinding {} (must be from 0 to 1)", name_grinding);
+        }
+        sources.push(ParamSource::new("name grinding",
+                                      "--name-grinding",
+                                      a.flag_name_grinding.map(|p| p.to_string()),
+                                      name_grinding));
+        if self.args.flag_name_grinding.is_some() && !q_type.contains(&QuorumType::Elder) {
+            panic!("--name-grinding requires an elder quorum (-Q elder or -Q all)");
+        }
+        if name_grinding > 0.0 && elder_tiebreak != ElderTiebreak::Name {
+            panic!("--name-grinding requires --elder-tiebreak name (names do not break ties \
+                    under {})",
+                   elder_tiebreak.name());
+        }
+
+        let weighting = self.args.flag_weight.as_ref().map_or(Weighting::Age, |s| {
+            s.parse().unwrap_or_else(|e| panic!("unexpected: --weight {} ({})", s, e))
+        });
+        sources.push(ParamSource::new("weighting", "--weight", a.flag_weight.clone(), &weighting));
+        if self.args.flag_weight.is_some() && !q_type.contains(&QuorumType::Age) {
+            panic!("--weight requires the age (weighted) quorum (-Q age or -Q all)");
+        }
+
+        let cap_ordering = self.args.flag_cap_ordering.unwrap_or(false);
+        let age_cap_range: Option<SamplePoints<NN>> = self.args.flag_age_cap.as_ref().map(|s| {
+            s.parse().unwrap_or_else(|e| panic!("unexpected: --age-cap {} ({})", s, e))
+        });
+        if let Some(ref range) = age_cap_range {
+            if range.iter().any(|cap| cap == 0) {
+                panic!("unexpected: --age-cap {} (must be at least 1)",
+                       self.args.flag_age_cap.as_ref().expect("age cap"));
+            }
+            if weighting != Weighting::Age {
+                panic!("--age-cap requires weighting by age (not --weight {})", weighting);
+            }
+            if !q_type.contains(&QuorumType::Age) &&
+               !(cap_ordering && q_type.contains(&QuorumType::Elder)) {
+                panic!("--age-cap requires the age quorum (-Q age or -Q all), or the elder quorum \
+                        with --cap-ordering");
+            }
+        } else if cap_ordering {
+            panic!("--cap-ordering requires --age-cap");
+        }
+        if cap_ordering && !q_type.contains(&QuorumType::Elder) {
+            panic!("--cap-ordering requires an elder quorum (-Q elder or -Q all)");
+        }
+        let age_caps: Vec<Option<AgeCap>> = age_cap_range.map_or(vec![None], |range| {
+            range.iter()
+                .map(|cap| {
+                    Some(AgeCap {
+                        cap: cap as u32,
+                        ordering: cap_ordering,
+                    })
+                })
+                .collect()
+        });
+        sources.push(ParamSource::new("age cap", "--age-cap", a.flag_age_cap.clone(), "none"));
+        let mut age_cap_iter = age_caps.iter();
+
+        let any_group_name = a.flag_any_group_model.as_ref().map_or("independent", |s| s.as_str());
+        let any_group_model = match any_group_name {
+            "independent" => AnyGroupModel::Independent,
+            "corrected" => AnyGroupModel::Corrected,
+            x => panic!("unexpected: --any-group-model {}", x),
+        };
+        sources.push(ParamSource::new("any group model",
+                                      "--any-group-model",
+                                      a.flag_any_group_model.clone(),
+                                      any_group_name));
+
+        let target_prefix = self.args.flag_target_prefix.as_ref().map(|s| {
+            s.parse().unwrap_or_else(|_| panic!("unexpected: --target-prefix {}", s))
+        });
+        sources.push(ParamSource::new("target prefix",
+                                      "--target-prefix",
+                                      a.flag_target_prefix.clone(),
+                                      "any"));
+
+        let placement = self.args
+            .flag_initial_placement
+            .as_ref()
+            .map_or(InitialPlacement::Uniform, |s| {
+                s.parse()
+                    .unwrap_or_else(|e| panic!("unexpected: --initial-placement {} ({})", s, e))
+            });
+        sources.push(ParamSource::new("initial placement",
+                                      "--initial-placement",
+                                      a.flag_initial_placement.clone(),
+                                      placement));
+        let quorum_jitter = self.args.flag_quorum_jitter.unwrap_or(0.0);
+        if quorum_jitter < 0.0 || quorum_jitter > 1.0 {
+            panic!("unexpected: --quorum-jitter {} (must be from 0 to 1)", quorum_jitter);
+        }
+        sources.push(ParamSource::new("quorum jitter",
+                                      "--quorum-jitter",
+                                      a.flag_quorum_jitter.map(|j| j.to_string()),
+                                      quorum_jitter));
+        let honest_uptime = self.args.flag_honest_uptime.as_ref().map_or(UptimeDist::new(), |s| {
+            s.parse().unwrap_or_else(|e| panic!("unexpected: --honest-uptime {} ({})", s, e))
+        });
+        sources.push(ParamSource::new("honest uptime",
+                                      "--honest-uptime",
+                                      a.flag_honest_uptime.clone(),
+                                      &honest_uptime));
+        let burst = self.args.flag_burst.as_ref().map(|s| {
+            s.parse().unwrap_or_else(|e| panic!("unexpected: --burst {} ({})", s, e))
+        });
+        sources.push(ParamSource::new("burst", "--burst", a.flag_burst.clone(), "none"));
+        let corruption = self.args.flag_corrupt.as_ref().map(|s| {
+            let corruption: Corruption =
+                s.parse().unwrap_or_else(|e| panic!("unexpected: --corrupt {} ({})", s, e));
+            if corruption.last_step() >= max_steps {
+                panic!("unexpected: --corrupt {} (steps must be below the number of steps)", s);
+            }
+            corruption
+        });
+        sources.push(ParamSource::new("corruption", "--corrupt", a.flag_corrupt.clone(), "none"));
+        let eviction_rate = self.args.flag_eviction_rate.unwrap_or(0.0);
+        if eviction_rate < 0.0 || eviction_rate > 1.0 {
+            panic!("unexpected: --eviction-rate {} (must be from 0 to 1)", eviction_rate);
+        }
+        sources.push(ParamSource::new("eviction rate",
+                                      "--eviction-rate",
+                                      a.flag_eviction_rate.map(|r| r.to_string()),
+                                      eviction_rate));
+        let attack_resource = self.args.flag_attack_resource.unwrap_or(1.0);
+        if !(attack_resource > 0.0) {
+            panic!("unexpected: --attack-resource {} (must be positive)", attack_resource);
+        }
+        sources.push(ParamSource::new("attack resource",
+                                      "--attack-resource",
+                                      a.flag_attack_resource.map(|x| x.to_string()),
+                                      attack_resource));
+        let rejoin_recovery = self.args.flag_rejoin_age_recovery.unwrap_or(0.0);
+        if rejoin_recovery < 0.0 || rejoin_recovery > 1.0 {
+            panic!("unexpected: --rejoin-age-recovery {} (must be from 0 to 1)",
+                   rejoin_recovery);
+        }
+        sources.push(ParamSource::new("rejoin age recovery",
+                                      "--rejoin-age-recovery",
+                                      a.flag_rejoin_age_recovery.map(|f| f.to_string()),
+                                      rejoin_recovery));
+        let rejoin_window = self.args.flag_rejoin_window.unwrap_or(DEFAULT_REJOIN_WINDOW);
+        sources.push(ParamSource::new("rejoin window",
+                                      "--rejoin-window",
+                                      num(a.flag_rejoin_window),
+                                      rejoin_window));
+        let repetitions = self.args.flag_reps.unwrap_or(100);
+        sources.push(ParamSource::new("repetitions", "-p", num(a.flag_reps), repetitions));
+        sources.extend(seeding_sources);
+        expansion_sources(sim_type, &mut sources);
+
+        // Create initial parameter set
+        let mut expansion = Expansion::new(vec![SimParams {
+            sim_type: sim_type,
+            quorum_type: *q_type_iter.next().expect("first iter item"),
+            elders: Some(elders_iter.next().expect("first iter item")),
+            promotion_lag: Some(promotion_lag),
+            elder_tiebreak: Some(elder_tiebreak),
+            name_grinding: Some(name_grinding),
+            weighting: Some(weighting),
+            age_cap: *age_cap_iter.next().expect("first iter item"),
+            targetting: at_type_iter.next().expect("first iter item").clone(),
+            objective: *objective_iter.next().expect("first iter item"),
+            attacker_knowledge: *knowledge_iter.next().expect("first iter item"),
+            target_prefix: target_prefix,
+            relocation: *relocation_iter.next().expect("first iter item"),
+            placement: placement,
+            quorum_jitter: quorum_jitter,
+            honest_uptime: honest_uptime,
+            burst: burst,
+            corruption: corruption,
+            eviction_rate: eviction_rate,
+            attack_resource: attack_resource,
+            rejoin_recovery: rejoin_recovery,
+            rejoin_window: rejoin_window,
+            pow_scaling: *pow_scaling_iter.next().expect("first iter item"),
+            attack_start: attack_start_iter.next().expect("first iter item"),
+            hold: hold,
+            steady_state: steady_state,
+            membership_lag: membership_lag,
+            join_cap: join_cap,
+            paranoid: self.args.flag_paranoid,
+            verify: self.args.flag_verify.unwrap_or(false),
+            split_check: self.split_check(),
+            record_attack: self.args.flag_record_attack.is_some(),
+            replay_attack: None,
+            rng_audit: self.args.flag_rng_audit.is_some(),
+            time_limit: time_limit,
+            num_nodes: nodes_iter.next().expect("first iter item"),
+            num_malicious: mal_nodes_iter.next().expect("first iter item"),
+            min_group_size: group_size_iter.next().expect("first iter item"),
+            quorum: quorum_iter.next().expect("first iter item"),
+            block_prop: *block_prop_iter.next().expect("first iter item"),
+            participation: *participation_iter.next().expect("first iter item"),
+            quorum_basis: *quorum_basis_iter.next().expect("first iter item"),
+            quorum_floor: *quorum_floor_iter.next().expect("first iter item"),
+            max_steps: max_steps,
+            repetitions: repetitions,
+            malice_hist: malice_hist,
+            churn_model: churn_model,
+            spread: spread,
+            address_coverage: address_coverage,
+            close_group: close_group,
+            elders_report: elders_report,
+            any_group_model: any_group_model,
+            compare_models: self.args.flag_compare_models.unwrap_or(false),
+            correction: self.args.flag_correction.unwrap_or(false),
+            age_share_interval: age_share_interval,
+            liveness: liveness,
+            termination: termination,
+            governance: governance,
+            turnover: turnover,
+            outcomes: paired_ageing,
+            checkpoint: checkpoint,
+            seeding: seeding,
+            master_seed: None,
+            prob_cache: self.prob_cache.clone(),
+            interrupt: self.interrupt.clone(),
+        }]);
+        expansion.sources = sources;
+
+        // Replicate for all network sizes (num nodes)
+        expansion.expand("nodes", nodes_iter, |s, n| s.num_nodes = *n);
+
+        // Replicate for all numbers of malicious nodes
+        // NOTE: it's important that we replicate over num_nodes first!
+        expansion.expand("malicious", mal_nodes_iter, |s, r| s.num_malicious = *r);
+
+        // Replicate for all group sizes
+        expansion.expand("min group size", group_size_iter, |s, g| s.min_group_size = *g);
+
+        // Replicate for all quorum sizes
+        expansion.expand("quorum size", quorum_iter, |s, q| s.quorum = *q);
+
+        // Replicate for all quorum bases
+        expansion.expand("quorum basis", quorum_basis_iter.cloned(), |s, b| s.quorum_basis = *b);
+
+        // Replicate for all quorum floors
+        expansion.expand("quorum floor", quorum_floor_iter.cloned(), |s, f| s.quorum_floor = *f);
+
+        // Replicate for all blocking proportions
+        expansion.expand("blocking proportion", block_prop_iter.cloned(), |s, b| s.block_prop = *b);
+
+        // Replicate for all participation probabilities
+        expansion.expand("participation", participation_iter.cloned(), |s, p| s.participation = *p);
+
+        // Replicate for all quorum types
+        expansion.expand("quorum type", q_type_iter.cloned(), |s, q| s.quorum_type = *q);
+
+        // Replicate elder quorums for all elder counts; other quorums have none
+        expansion.expand_some("elders",
+                              elders_iter,
+                              |s| s.quorum_type == QuorumType::Elder,
+                              |s, e| s.elders = Some(*e));
+
+        // Replicate for all age caps the quorums they apply to: the age quorum,
+        // and the elder quorum when ranking elders by capped age
+        expansion.expand_some("age cap",
+                              age_cap_iter.cloned(),
+                              move |s| age_cap_applies(s, cap_ordering),
+                              |s, c| s.age_cap = *c);
+
+        // Each set is finished once expanded: a formula's group size is resolved from
+        // its network size, and the parameters of other quorums cleared
+        expansion.finish = Box::new(move |s: &mut SimParams| {
+            if let Some(ref formula) = group_size_formula {
+                s.min_group_size = formula.of(s.num_nodes);
+            }
+            if !age_cap_applies(s, cap_ordering) {
+                s.age_cap = None;
+            } else if s.quorum_type == QuorumType::Age {
+                // The age quorum has no elders to rank
+                s.age_cap.as_mut().map(|c| c.ordering = false);
+            }
+            if s.quorum_type != QuorumType::Age {
+                s.weighting = None;
+            }
+            if s.quorum_type != QuorumType::Elder {
+                s.elders = None;
+                s.promotion_lag = None;
+                s.elder_tiebreak = None;
+                s.name_grinding = None;
+            } else if let Some(e) = s.elders {
+                if e == 0 || e > s.min_group_size {
+                    panic!("unexpected: --elders {} (must be from 1 to the minimum group size, {})",
+                           e,
+                           s.min_group_size);
+                }
+            }
+            // Of elders, the floor can be no more than the number voting
+            let voters = s.elders.unwrap_or(s.min_group_size);
+            if let Some(floor) = s.quorum_floor {
+                if floor == 0 || floor > voters {
+                    panic!("unexpected: --quorum-floor {} (must be from 1 to the number voting, \
+                            {}, with the {} quorum and minimum group size {})",
+                           floor,
+                           voters,
+                           s.quorum_type.name(),
+                           s.min_group_size);
+                }
+            }
+        });
+
+        // Replicate for all attack strategies
+        expansion.expand("targetting", at_type_iter.cloned(), |s, at| s.targetting = at.clone());
+
+        // Replicate for all objectives
+        expansion.expand("objective", objective_iter.cloned(), |s, o| s.objective = *o);
+
+        // Replicate for all attacker knowledge levels
+        expansion.expand("attacker knowledge",
+                         knowledge_iter.cloned(),
+                         |s, k| s.attacker_knowledge = *k);
+
+        // Replicate for all relocation targets
+        expansion.expand("relocation target", relocation_iter.cloned(), |s, r| s.relocation = *r);
+
+        // Replicate for all proof-of-work scalings
+        expansion.expand("pow scaling", pow_scaling_iter.cloned(), |s, p| s.pow_scaling = *p);
+
+        // Replicate for all attack start steps
+        expansion.expand("attack start", attack_start_iter, |s, a| s.attack_start = *a);
+
+        expansion
+    }
+}
+
+// Keep the sources of the parameters `sim_type` takes, noting with each range
+// given without a step the step used
+fn expansion_sources(sim_type: SimType, sources: &mut Vec<ParamSource>) {
+    let usage = sim_type.usage();
+    sources.retain(|param| usage.contains(&format!("[{} ", param.flag)));
+    for param in sources.iter_mut() {
+        if param.provenance == Provenance::Default {
+            continue;
+        }
+        param.default_step = match param.flag {
+            "-n" | "--quorum-floor" | "--attack-start" | "--elders" | "--age-cap" => {
+                default_step_text::<NN>(&param.value)
+            }
+            "-k" if !GroupSizeFormula::is_formula(&param.value) => {
+                default_step_text::<NN>(&param.value)
+            }
+            "-r" => default_step_text::<RelOrAbs>(&param.value),
+            "-q" => default_step_text::<QuorumSize>(&param.value),
+            "--block-prop" | "--participation" => default_step_text::<RR>(&param.value),
+            _ => None,
+        };
+    }
+}
+
+// Whether age caps apply to a set: the age quorum's, or the elder quorum's
+// when ranking elders by capped age
+fn age_cap_applies(s: &SimParams, cap_ordering: bool) -> bool {
+    s.quorum_type == QuorumType::Age || (cap_ordering && s.quorum_type == QuorumType::Elder)
+}
+
+// Parse a number of bytes: a number, optionally followed by K, M, G or T
+// (powers of 1024).
+fn parse_bytes(s: &str) -> Option<RR> {
+    let (num, unit) = match s.chars().last() {
+        Some('K') => (&s[..s.len() - 1], 1024.0),
+        Some('M') => (&s[..s.len() - 1], 1024.0 * 1024.0),
+        Some('G') => (&s[..s.len() - 1], 1024.0 * 1024.0 * 1024.0),
+        Some('T') => (&s[..s.len() - 1], 1024.0 * 1024.0 * 1024.0 * 1024.0),
+        _ => (s, 1.0),
+    };
+    match num.parse::<RR>() {
+        Ok(x) if x >= 0.0 => Some(x * unit),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_parse_bytes() {
+    assert_eq!(parse_bytes("4096"), Some(4096.0));
+    assert_eq!(parse_bytes("1.5K"), Some(1536.0));
+    assert_eq!(parse_bytes("512M"), Some(512.0 * 1024.0 * 1024.0));
+    assert_eq!(parse_bytes("8G"), Some(8.0 * 1024.0 * 1024.0 * 1024.0));
+    assert_eq!(parse_bytes("G"), None);
+    assert_eq!(parse_bytes("-1M"), None);
+    assert_eq!(parse_bytes("2GB"), None);
+}
+
+#[cfg(test)]
+fn parse_args(args: &[&str]) -> Result<ArgProc, docopt::Error> {
+    ArgProc::from_argv(Some("routing-sims").into_iter().chain(args.iter().cloned()))
+}
+
+/// The parameter sets of the command line `args` (without the program name),
+/// for a test to use; panics if they do not parse
+#[cfg(test)]
+pub fn sim_params(args: &[&str]) -> Vec<SimParams> {
+    parse_args(args).unwrap_or_else(|e| panic!("{}", e)).make_sim_params()
+}
+
+#[test]
+fn test_range_kinds() {
+    let error = |s: &str| s.parse::<SamplePoints<RelOrAbs>>().err();
+    // Every mix of absolute and relative parts is rejected, quoting the whole value
+    for &(start, stop) in &[("10", "100"), ("10%", "50%"), ("10", "50%"), ("10%", "100")] {
+        for step in &[None, Some("5"), Some("5%")] {
+            let s = match *step {
+                Some(step) => format!("{}-{}:{}", start, stop, step),
+                None => format!("{}-{}", start, stop),
+            };
+            let rel = |part: &str| part.ends_with('%');
+            let mixed = rel(start) != rel(stop) ||
+                        step.map_or(false, |step| rel(step) != rel(start));
+            match error(&s) {
+                Some(e) => {
+                    assert!(mixed, "{}", s);
+                    assert_eq!(e,
+                               format!("start, stop and step of '{}' must all be absolute counts \
+                                        (e.g. 10-100:5) or all percentages (e.g. 5%-50%:5%)",
+                                       s));
+                }
+                None => assert!(!mixed, "{}", s),
+            }
+        }
+    }
+    // Lists may mix
+    assert_eq!(error("10,5%"), None);
+
+    assert!("0.5-4a".parse::<SamplePoints<QuorumSize>>().is_err());
+    assert!("4a-6a:0.1".parse::<SamplePoints<QuorumSize>>().is_err());
+    assert!("4a-6a:1a".parse::<SamplePoints<QuorumSize>>().is_ok());
+    let counts: SamplePoints<RelOrAbs> = "10-20:5".parse().expect("parse");
+    let counts: Vec<RelOrAbs> = counts.iter().collect();
+    assert_eq!(counts, vec![RelOrAbs::Abs(10), RelOrAbs::Abs(15), RelOrAbs::Abs(20)]);
+
+    // The tools report the error like other invalid arguments, naming the option
+    match parse_args(&["calc", "-r", "10-50%"]) {
+        Ok(_) => panic!("accepted -r 10-50%"),
+        Err(e) => {
+            assert!(e.fatal());
+            assert_eq!(e.to_string(),
+                       "routing-sims calc: -r: start, stop and step of '10-50%' must all be \
+                        absolute counts (e.g. 10-100:5) or all percentages (e.g. 5%-50%:5%)");
+        }
+    }
+}
+
+#[test]
+fn test_mixed_lists() {
+    // Lists may mix single values and ranges, in order, skipping repeats
+    let values = |s: &str| -> Vec<RR> {
+        s.parse::<SamplePoints<RR>>().expect("parse").iter().collect()
+    };
+    assert_eq!(values("0.5,0.55-0.6:0.01,0.7,0.6,0.8"),
+               vec![0.5, 0.55, 0.56, 0.57, 0.58, 0.59, 0.6, 0.7, 0.8]);
+    assert_eq!(values("0.5,0.5"), vec![0.5]);
+    let counts: SamplePoints<NN> = "1000,2000-4000:1000,3000,10000-10002".parse().expect("parse");
+    let counts: Vec<NN> = counts.iter().collect();
+    assert_eq!(counts, vec![1000, 2000, 3000, 4000, 10000, 10001, 10002]);
+    let malicious: SamplePoints<RelOrAbs> = "5%,10-20:5,10%-20%:10%,15".parse().expect("parse");
+    let malicious: Vec<RelOrAbs> = malicious.iter().collect();
+    assert_eq!(malicious,
+               vec![RelOrAbs::Rel(0.05),
+                    RelOrAbs::Abs(10),
+                    RelOrAbs::Abs(15),
+                    RelOrAbs::Abs(20),
+                    RelOrAbs::Rel(0.1),
+                    RelOrAbs::Rel(0.2)]);
+
+    // Errors name the bad element
+    let error = |s: &str| s.parse::<SamplePoints<RR>>().err().expect("an error");
+    assert_eq!(error("0.5-,0.6"),
+               "expected 'start-stop' or 'start-stop:step', found '0.5-'");
+    assert_eq!(error("0.5,0.6-0.7:0.01:0.02"),
+               "expected 'start-stop' or 'start-stop:step', found '0.6-0.7:0.01:0.02'");
+    assert_eq!(error("0.5:0.1,0.6"),
+               "expected 'start-stop' or 'start-stop:step', found '0.5:0.1'");
+    assert_eq!(error("0.5,,0.6"), "bad value ''");
+    assert_eq!(error("0.5-0.6:x"), "bad value 'x'");
+    assert_eq!("10,5%-20".parse::<SamplePoints<RelOrAbs>>().err(),
+               Some("start, stop and step of '5%-20' must all be absolute counts (e.g. 10-100:5) \
+                     or all percentages (e.g. 5%-50%:5%)"
+                   .to_owned()));
+
+    // Each value is one parameter set
+    let arg_proc = parse_args(&["calc", "-q", "0.5,0.55-0.65:0.05,0.7"]).expect("parse");
+    assert_eq!(arg_proc.make_sim_params().len(), 5);
+}
+
+#[test]
+fn test_default_step() {
+    // Reals are divided into ten steps, each ending at the stop
+    let quorum: SamplePoints<QuorumSize> = "0.5-0.9".parse().expect("parse");
+    assert_eq!(quorum.default_step(), Some(QuorumSize::Proportion(0.04)));
+    let values: Vec<QuorumSize> = quorum.iter().collect();
+    assert_eq!(values.len(), 11);
+    assert_eq!(values[5], QuorumSize::Proportion(0.7));
+    assert_eq!(values[10], QuorumSize::Proportion(0.9));
+
+    let values: Vec<RR> = "0-1".parse::<SamplePoints<RR>>().expect("parse").iter().collect();
+    assert_eq!(values.len(), 11);
+    assert_eq!((values[3], values[10]), (0.3, 1.0));
+    let values: Vec<RR> = "0.50-0.52".parse::<SamplePoints<RR>>().expect("parse").iter().collect();
+    assert_eq!(values.len(), 11);
+    assert_eq!((values[1], values[10]), (0.502, 0.52));
+    // The step is at least MIN_DEFAULT_STEP
+    let values: Vec<RR> =
+        "0.500-0.503".parse::<SamplePoints<RR>>().expect("parse").iter().collect();
+    assert_eq!(values, vec![0.5, 0.501, 0.502, 0.503]);
+    // An explicit step also ends at the stop
+    let values: Vec<RR> =
+        "0.1-0.3:0.1".parse::<SamplePoints<RR>>().expect("parse").iter().collect();
+    assert_eq!(values, vec![0.1, 0.2, 0.3]);
+
+    let percentages: SamplePoints<RelOrAbs> = "5%-50%".parse().expect("parse");
+    assert_eq!(percentages.default_step().map(|step| step.to_string()),
+               Some("4.5%".to_owned()));
+    assert_eq!(percentages.iter().count(), 11);
+    let counts: SamplePoints<RelOrAbs> = "10-20".parse().expect("parse");
+    assert_eq!(counts.default_step(), Some(RelOrAbs::Abs(1)));
+    assert_eq!("10-20:5".parse::<SamplePoints<NN>>().expect("parse").default_step(), None);
+
+    // The step used is noted with the parameter
+    let arg_proc = parse_args(&["calc", "-q", "0.5-0.9", "-r", "5%-50%:5%", "-n", "1000-1002"])
+        .expect("parse");
+    let sources: Vec<String> = arg_proc.param_sources().iter().map(|p| p.to_string()).collect();
+    assert!(sources.contains(&"quorum size = 0.5-0.9 (from -q); no step given, so stepping by 0.04"
+        .to_owned()));
+    assert!(sources.contains(&"malicious = 5%-50%:5% (from -r)".to_owned()));
+    assert!(sources.contains(&"nodes = 1000-1002 (from -n); no step given, so stepping by 1"
+        .to_owned()));
+}
+
+#[test]
+fn test_option_aliases() {
+    let values = [("-n", "2000"), ("-r", "5%"), ("-k", "8"), ("-q", "0.6"), ("-s", "20"),
+                  ("-p", "3"), ("-Q", "age"), ("-T", "simple")];
+    for &(short, long) in &OPTION_ALIASES {
+        let value = values.iter().find(|v| v.0 == short).expect("value").1;
+        let sources = |flag: &str| {
+            parse_args(&["full", flag, value]).unwrap_or_else(|e| panic!("{}", e)).param_sources()
+        };
+        let given = sources(long);
+        assert!(given.iter().any(|p| p.flag == short && p.value == value), "{}", long);
+        assert_eq!(given, sources(short));
+    }
+}
+
+#[test]
+fn test_tool_args() {
+    assert!(parse_args(&["calc", "-n", "1000", "-q", "5a", "-s", "10", "-p", "5"]).is_ok());
+    assert!(parse_args(&["calc", "--any-group-model", "corrected", "--compare-models"]).is_ok());
+    assert!(parse_args(&["structure", "-k", "10-20", "--report", "malice-hist"]).is_ok());
+    assert!(parse_args(&["full", "-Q", "all", "-T", "deadline", "--target-prefix", "01",
+                         "--relocation-target", "balanced", "--honest-uptime",
+                         "0.95@0.2,0.3@0.8", "--churn-model", "poisson", "--burst", "0.01,0.3",
+                         "--pow-scaling", "log-n", "--attack-start", "0-600:300",
+                         "--report", "elders"])
+        .is_ok());
+
+    let check_rejected = |args: &[&str]| {
+        let tool = args[0];
+        match parse_args(args) {
+            Ok(_) => panic!("accepted: {:?}", args),
+            Err(e) => {
+                assert!(e.fatal());
+                assert!(e.to_string().starts_with(&format!("routing-sims {}:", tool)));
+            }
+        }
+    };
+    check_rejected(&["calc", "-Q", "age"]);
+    check_rejected(&["calc", "--report", "malice-hist"]);
+    check_rejected(&["structure", "-T", "simple"]);
+    check_rejected(&["full", "--compare-models"]);
+    check_rejected(&["calc", "--correction"]);
+    check_rejected(&["full", "--close-group", "8"]);
+    assert!(parse_args(&["structure", "--close-group", "8,50"]).is_ok());
+    check_rejected(&["structure", "--paired-ageing"]);
+    check_rejected(&["calc", "--baseline-column"]);
+    check_rejected(&["full", "--model", "targetted-bound"]);
+    assert!(parse_args(&["structure", "--correction", "-p", "10"]).is_ok());
+    check_rejected(&["structure", "--relocation-target", "random"]);
+    assert!(parse_args(&["structure", "--initial-placement", "concentrated(0.5)"]).is_ok());
+    check_rejected(&["calc", "--initial-placement", "uniform"]);
+    assert!(parse_args(&["structure", "--quorum-jitter", "0.05"]).is_ok());
+    check_rejected(&["calc", "--quorum-jitter", "0.05"]);
+    assert!(parse_args(&["calc", "--participation", "0.9", "--any-group-model", "corrected"])
+        .is_ok());
+    check_rejected(&["structure", "--target-prefix", "01"]);
+    check_rejected(&["calc", "--churn-model", "poisson"]);
+    check_rejected(&["calc", "--attacker-knowledge", "full"]);
+    check_rejected(&["structure", "--attacker-knowledge", "membership-only"]);
+    check_rejected(&["calc", "--honest-uptime", "0.5"]);
+    check_rejected(&["structure", "--burst", "1,0.5"]);
+    check_rejected(&["calc", "--corrupt", "0.1@0"]);
+    check_rejected(&["calc", "--pow-scaling", "linear-n"]);
+    check_rejected(&["structure", "--attack-start", "10"]);
+    check_rejected(&["calc", "--hold", "2"]);
+    check_rejected(&["structure", "--steady-state", "100"]);
+    check_rejected(&["calc", "--membership-lag", "2"]);
+    check_rejected(&["structure", "--join-cap", "2:10"]);
+    check_rejected(&["structure", "--paranoid", "10"]);
+    assert!(parse_args(&["structure", "--verify"]).is_ok());
+    check_rejected(&["calc", "--verify"]);
+    check_rejected(&["calc", "--split-check", "2"]);
+    check_rejected(&["structure", "--record-attack", "attack.csv"]);
+    check_rejected(&["calc", "--replay-attack", "attack.csv"]);
+    check_rejected(&["structure", "--rng-audit", "draws.csv"]);
+    check_rejected(&["calc", "--time-limit", "10"]);
+    check_rejected(&["structure", "--global-budget", "1000"]);
+    check_rejected(&["calc", "--include-truncated"]);
+    check_rejected(&["structure", "--eviction-rate", "0.01"]);
+    check_rejected(&["calc", "--rejoin-age-recovery", "0.5"]);
+    check_rejected(&["calc", "--promotion-lag", "2"]);
+    check_rejected(&["calc", "--elder-tiebreak", "join-order"]);
+    check_rejected(&["structure", "--name-grinding", "0.5"]);
+    check_rejected(&["full", "--bogus"]);
+    check_rejected(&["full", "-r", "10-100:5%"]);
+    check_rejected(&["calc", "-q", "0.5-4a"]);
+    check_rejected(&["full", "extra"]);
+    check_rejected(&["structure", "--curve", "curve.csv"]);
+    check_rejected(&["batch", "-n", "1000"]);
+    check_rejected(&["batch", "--auto-extend", "q"]);
+    assert!(parse_args(&["full", "--report", "age-share", "--curve", "curve.csv",
+                         "--curve-interval", "5"])
+        .is_ok());
+    assert!(parse_args(&["batch", "--skip-bad-lines", "--dry-run"]).is_ok());
+    assert!(parse_args(&["full", "--dry-run"]).is_ok());
+
+    check_rejected(&["calc", "-n", "1000", "--nodes", "2000"]);
+    check_rejected(&["full", "-T", "simple", "--targetting=deadline"]);
+
+    let viz = parse_args(&["calc", "--viz", "--viz-range", "1e-6:0.1", "--viz-scale", "linear"])
+        .unwrap_or_else(|e| panic!("{}", e))
+        .viz()
+        .expect("viz");
+    assert_eq!((viz.low, viz.high, viz.scale), (1e-6, 0.1, VizScale::Linear));
+    assert!(parse_args(&["calc"]).unwrap_or_else(|e| panic!("{}", e)).viz().is_none());
+
+    assert!(parse_args(&["bogus"]).is_err());
+    assert!(parse_args(&[]).is_err());
+}
+
+#[test]
+fn test_metric() {
+    // --metric liveness is --report liveness, and likewise governance, whichever gives it
+    let metrics = |args: &[&str]| {
+        let params = parse_args(args).unwrap_or_else(|e| panic!("{}", e)).make_sim_params();
+        (params[0].liveness, params[0].governance)
+    };
+    assert_eq!(metrics(&["full", "--metric", "liveness"]), (true, false));
+    assert_eq!(metrics(&["full", "--report", "spread", "--metric", "liveness"]), (true, false));
+    assert_eq!(metrics(&["full", "--report", "spread"]), (false, false));
+    assert_eq!(metrics(&["full", "--metric", "governance"]), (false, true));
+    assert_eq!(metrics(&["full", "--report", "liveness", "--metric", "governance"]),
+               (true, true));
+    assert!(parse_args(&["batch", "--metric", "liveness"]).is_ok());
+    assert!(parse_args(&["structure", "--metric", "liveness"]).is_err());
 }
 
-impl ArgProc {
-    pub fn read_args() -> ArgProc {
-        let args: Args = Docopt::new(USAGE)
-            .and_then(|dopt| dopt.decode())
-            .unwrap_or_else(|e| e.exit());
+#[test]
+fn test_param_sources() {
+    let arg_proc = parse_args(&["calc", "-n", "1000-5000:1000", "-q", "5a"])
+        .unwrap_or_else(|e| panic!("{}", e));
+    let sources: Vec<String> = arg_proc.param_sources().iter().map(|p| p.to_string()).collect();
+    assert_eq!(&sources[..4],
+               &["nodes = 1000-5000:1000 (from -n)",
+                 "malicious = 10% (default)",
+                 "min group size = 10 (default)",
+                 "quorum size = 5a (from -q)"]);
+    assert!(sources.contains(&"any group model = independent (default)".to_owned()));
+    assert!(!sources.iter().any(|s| s.starts_with("targetting")));
+    // Not strict unless asked
+    assert_eq!(arg_proc.check_strict(&arg_proc.param_sources()), Ok(()));
 
-        ArgProc { args: args }
-    }
+    let strict = |args: &[&str]| {
+        let arg_proc = parse_args(args).unwrap_or_else(|e| panic!("{}", e));
+        arg_proc.check_strict(&arg_proc.param_sources())
+    };
+    assert_eq!(strict(&["full", "--strict", "-n", "1000", "-k", "10"]),
+               Err("routing-sims full: --strict requires -r, -q".to_owned()));
+    assert_eq!(strict(&["structure", "--strict", "-n", "1000", "-r", "5%", "-k", "10", "-q",
+                        "0.5"]),
+               Ok(()));
+    assert!(parse_args(&["full", "-p", "5"])
+        .unwrap_or_else(|e| panic!("{}", e))
+        .param_sources()
+        .contains(&ParamSource::new("repetitions", "-p", Some("5".to_owned()), "100")));
+    let sources: Vec<String> = parse_args(&["full", "--churn-model", "poisson"])
+        .unwrap_or_else(|e| panic!("{}", e))
+        .param_sources()
+        .iter()
+        .map(|p| p.to_string())
+        .collect();
+    assert!(sources.contains(&"churn model = poisson (from --churn-model)".to_owned()));
+    // A default is shown as applied
+    let sources: Vec<String> = parse_args(&["full", "--paired-ageing", "--seed", "1"])
+        .unwrap_or_else(|e| panic!("{}", e))
+        .param_sources()
+        .iter()
+        .map(|p| p.to_string())
+        .collect();
+    assert!(sources.contains(&"quorum type = simple,age (default)".to_owned()));
+}
 
-    // TODO: is Vec suitable for this use?
-    pub fn make_sim_params(&self) -> Vec<SimParams> {
-        let mut v = Vec::new();
+#[test]
+fn test_seeding() {
+    let params = parse_args(&["full", "--seed", "42", "--fix-stream", "structure,churn"])
+        .unwrap_or_else(|e| panic!("{}", e))
+        .make_sim_params();
+    assert_eq!(params[0].seeding,
+               Seeding {
+                   seed: Some(42),
+                   fixed: vec![Stream::Structure, Stream::Churn],
+               });
+    let params = parse_args(&["structure"]).unwrap_or_else(|e| panic!("{}", e)).make_sim_params();
+    assert_eq!(params[0].seeding, Seeding::new());
+    assert!(parse_args(&["calc", "--seed", "42"]).is_err());
+}
 
-        let nodes_range: SamplePoints<NN> = self.args
-            .flag_n
-            .as_ref()
-            .map_or(SamplePoints::Number(1000), |s| s.parse().expect("parse"));
-        let mut nodes_iter = nodes_range.iter();
+#[test]
+fn test_group_size_formula_expansion() {
+    let sizes = |args: &[&str]| -> Vec<(NN, NN)> {
+        parse_args(args)
+            .unwrap_or_else(|e| panic!("{}", e))
+            .make_sim_params()
+            .iter()
+            .map(|params| (params.num_nodes, params.min_group_size))
+            .collect()
+    };
+    // One group size per network size, not every size with every other
+    assert_eq!(sizes(&["calc", "-n", "1024,4096,16384", "-k", "log2+2", "-q", "0.5,0.6"]),
+               vec![(1024, 12), (4096, 14), (16384, 16), (1024, 12), (4096, 14), (16384, 16)]);
+    assert_eq!(sizes(&["structure", "-n", "1600-6400:4800", "-k", "sqrt/4"]),
+               vec![(1600, 10), (6400, 20)]);
+    assert_eq!(sizes(&["calc", "-n", "1000,2000", "-k", "8,10"]),
+               vec![(1000, 8), (2000, 8), (1000, 10), (2000, 10)]);
+}
 
-        let mal_nodes_range: SamplePoints<RelOrAbs> =
-            self.args.flag_r.as_ref().map_or(SamplePoints::Number(RelOrAbs::Rel(0.1)),
-                                             |s| s.parse().expect("parse"));
-        let mut mal_nodes_iter = mal_nodes_range.iter();
+#[test]
+fn test_elders_expansion() {
+    let sets = |args: &[&str]| -> Vec<(&'static str, Option<NN>)> {
+        parse_args(args)
+            .unwrap_or_else(|e| panic!("{}", e))
+            .make_sim_params()
+            .iter()
+            .map(|params| (params.quorum_type.name(), params.elders))
+            .collect()
+    };
+    // Only the elder quorum is repeated for each elder count
+    assert_eq!(sets(&["full", "-Q", "all", "--elders", "5-9:2", "-k", "10"]),
+               vec![("simple", None),
+                    ("age", None),
+                    ("elder", Some(5)),
+                    ("elder", Some(7)),
+                    ("elder", Some(9))]);
+    let mixed = sets(&["full", "-Q", "all", "--elders", "5,7", "-k", "8,10", "-q", "0.5,0.6"]);
+    assert_eq!(mixed.len(), 2 * 2 * (2 + 2));
+    assert_eq!(sets(&["full", "-Q", "elder"]), vec![("elder", Some(7))]);
 
-        let group_size_range: SamplePoints<NN> = self.args
-            .flag_k
-            .as_ref()
-            .map_or(SamplePoints::Number(10), |s| s.parse().expect("parse"));
-        let mut group_size_iter = group_size_range.iter();
+    // The promotion lag is of elder quorums only
+    let lags: Vec<Option<NN>> = parse_args(&["full", "-Q", "all", "--promotion-lag", "3"])
+        .unwrap_or_else(|e| panic!("{}", e))
+        .make_sim_params()
+        .iter()
+        .map(|params| params.promotion_lag)
+        .collect();
+    assert_eq!(lags, vec![None, None, Some(3)]);
 
-        let quorum_range = self.args
-            .flag_q
-            .as_ref()
-            .map_or(SamplePoints::Number(0.5), |s| s.parse().expect("parse"));
-        let mut quorum_iter = quorum_range.iter();
+    // So are the elder tiebreak and name grinding
+    let ties: Vec<(Option<ElderTiebreak>, Option<RR>)> =
+        parse_args(&["full", "-Q", "all", "--name-grinding", "0.25"])
+            .unwrap_or_else(|e| panic!("{}", e))
+            .make_sim_params()
+            .iter()
+            .map(|params| (params.elder_tiebreak, params.name_grinding))
+            .collect();
+    assert_eq!(ties,
+               vec![(None, None), (None, None), (Some(ElderTiebreak::Name), Some(0.25))]);
+}
 
-        let q_use_age = match self.args.flag_Q.as_ref().map(|s| s.as_str()) {
-            None => vec![false],
-            Some("simple") => vec![false],
-            Some("age") => vec![true],
-            Some("all") => vec![false, true],
-            Some(x) => panic!("unexpected: -Q {}", x),
-        };
-        let mut q_use_age_iter = q_use_age.iter();
+#[test]
+#[should_panic(expected = "--name-grinding requires --elder-tiebreak name")]
+fn test_name_grinding_without_name_tiebreak() {
+    let _ = parse_args(&["full",
+                         "-Q",
+                         "elder",
+                         "--elder-tiebreak",
+                         "join-order",
+                         "--name-grinding",
+                         "0.5"])
+        .unwrap_or_else(|e| panic!("{}", e))
+        .make_sim_params();
+}
 
-        let at_type = match self.args.flag_T.as_ref().map(|s| s.as_str()) {
-            None => vec![AttackType::Untargetted],
-            Some("none") => vec![AttackType::Untargetted],
-            Some("simple") => vec![AttackType::SimpleTargetted],
-            Some("all") => vec![AttackType::Untargetted, AttackType::SimpleTargetted],
-            Some(x) => panic!("unexpected: -T {}", x),
-        };
-        let mut at_type_iter = at_type.iter();
+#[test]
+fn test_quorum_floor_expansion() {
+    let floors = |args: &[&str]| -> Vec<(NN, Option<NN>)> {
+        parse_args(args)
+            .unwrap_or_else(|e| panic!("{}", e))
+            .make_sim_params()
+            .iter()
+            .map(|params| (params.min_group_size, params.quorum_floor))
+            .collect()
+    };
+    assert_eq!(floors(&["calc", "-k", "8"]), vec![(8, None)]);
+    assert_eq!(floors(&["calc", "-k", "8,10", "--quorum-floor", "4-5"]),
+               vec![(8, Some(4)), (10, Some(4)), (8, Some(5)), (10, Some(5))]);
+    assert_eq!(floors(&["full", "-Q", "all", "--elders", "5", "--quorum-floor", "5"]).len(), 3);
+}
 
-        // Create initial parameter set
-        let tool = if self.args.cmd_calc {
-            SimType::DirectCalc
-        } else if self.args.cmd_structure {
-            SimType::Structure
-        } else if self.args.cmd_full {
-            SimType::FullSim
-        } else {
-            unreachable!()
-        };
-        v.push(SimParams {
-            sim_type: tool,
-            age_quorum: *q_use_age_iter.next().expect("first iter item"),
-            targetting: *at_type_iter.next().expect("first iter item"),
-            num_nodes: nodes_iter.next().expect("first iter item"),
-            num_malicious: mal_nodes_iter.next().expect("first iter item"),
-            min_group_size: group_size_iter.next().expect("first iter item"),
-            quorum_prop: quorum_iter.next().expect("first iter item"),
-            max_steps: self.args.flag_s.unwrap_or(1000),
-            repetitions: self.args.flag_p.unwrap_or(100),
-        });
+#[test]
+#[should_panic(expected = "--quorum-floor 6 (must be from 1 to the number voting, 5,")]
+fn test_quorum_floor_above_elders() {
+    // Allowed by the minimum group size, but not by the number of elders
+    let _ = parse_args(&["full", "-Q", "all", "--elders", "5", "--quorum-floor", "6"])
+        .unwrap_or_else(|e| panic!("{}", e))
+        .make_sim_params();
+}
 
-        // Replicate for all network sizes (num nodes)
-        let range = 0..v.len();
-        for n in nodes_iter {
-            for i in range.clone() {
-                let mut s = v[i].clone();
-                s.num_nodes = n;
-                v.push(s);
-            }
-        }
+#[test]
+fn test_age_cap_expansion() {
+    let caps = |args: &[&str]| -> Vec<(&'static str, Option<String>)> {
+        parse_args(args)
+            .unwrap_or_else(|e| panic!("{}", e))
+            .make_sim_params()
+            .iter()
+            .map(|params| (params.quorum_type.name(), params.age_cap.map(|c| c.to_string())))
+            .collect()
+    };
+    let some = |s: &str| Some(s.to_owned());
+    assert_eq!(caps(&["full", "-Q", "age"]), vec![("age", None)]);
+    // Only the age quorum weighs by age, so only it is swept
+    assert_eq!(caps(&["full", "-Q", "all", "--age-cap", "8,16"]),
+               vec![("simple", None),
+                    ("age", some("8")),
+                    ("elder", None),
+                    ("age", some("16"))]);
+    // Unless elders are also ranked by capped age
+    assert_eq!(caps(&["full", "-Q", "elder", "--age-cap", "8,16", "--cap-ordering"]),
+               vec![("elder", some("8+ordering")), ("elder", some("16+ordering"))]);
+}
 
-        // Replicate for all numbers of malicious nodes
-        let range = 0..v.len();
-        for r in mal_nodes_iter {
-            for i in range.clone() {
-                let mut s = v[i].clone();
-                // NOTE: it's important that we replicate over num_nodes first!
-                s.num_malicious = r;
-                v.push(s);
-            }
-        }
+#[test]
+#[should_panic(expected = "--cap-ordering requires --age-cap")]
+fn test_cap_ordering_without_cap() {
+    let _ = parse_args(&["full", "-Q", "elder", "--cap-ordering"])
+        .unwrap_or_else(|e| panic!("{}", e))
+        .make_sim_params();
+}
 
-        // Replicate for all group sizes
-        let range = 0..v.len();
-        for g in group_size_iter {
-            for i in range.clone() {
-                let mut s = v[i].clone();
-                s.min_group_size = g;
-                v.push(s);
-            }
-        }
+#[test]
+#[should_panic(expected = "--age-cap requires the age quorum")]
+fn test_age_cap_elder_only() {
+    // The elder quorum does not weigh votes, so the cap needs --cap-ordering
+    let _ = parse_args(&["full", "-Q", "elder", "--age-cap", "8"])
+        .unwrap_or_else(|e| panic!("{}", e))
+        .make_sim_params();
+}
 
-        // Replicate for all quorum sizes
-        let range = 0..v.len();
-        for q in quorum_iter {
-            for i in range.clone() {
-                let mut s = v[i].clone();
-                s.quorum_prop = q;
-                v.push(s);
-            }
-        }
+#[test]
+#[should_panic(expected = "--corrupt 0.2@0,0.1@20 (steps must be below the number of steps)")]
+fn test_corrupt_after_last_step() {
+    let _ = parse_args(&["full", "-s", "20", "--corrupt", "0.2@0,0.1@20"])
+        .unwrap_or_else(|e| panic!("{}", e))
+        .make_sim_params();
+}
 
-        // Replicate for all quorum types
-        let range = 0..v.len();
-        for q in q_use_age_iter {
-            for i in range.clone() {
-                let mut s = v[i].clone();
-                s.age_quorum = *q;
-                v.push(s);
-            }
-        }
+#[test]
+#[should_panic(expected = "--participation 0 (must be above 0 and at most 1)")]
+fn test_participation_range() {
+    let params = parse_args(&["calc", "--participation", "0.5-1:0.25"])
+        .unwrap_or_else(|e| panic!("{}", e))
+        .make_sim_params();
+    let participation: Vec<RR> = params.iter().map(|params| params.participation).collect();
+    assert_eq!(participation, vec![0.5, 0.75, 1.0]);
+    let _ = parse_args(&["full", "--participation", "0-0.5:0.5"])
+        .unwrap_or_else(|e| panic!("{}", e))
+        .make_sim_params();
+}
 
-        // Replicate for all attack strategies
-        let range = 0..v.len();
-        for at in at_type_iter {
-            for i in range.clone() {
-                let mut s = v[i].clone();
-                s.targetting = *at;
-                v.push(s);
-            }
+#[test]
+fn test_canonical() {
+    // The same seeded sweep, run twice, is written the same byte for byte
+    let sweep = || {
+        let arg_proc = parse_args(&["full", "-n", "60", "-r", "10%,20%", "-s", "20", "-p", "10",
+                                    "--seed", "7", "--canonical"])
+            .unwrap_or_else(|e| panic!("{}", e));
+        let format = arg_proc.prob_format();
+        let mut out = String::new();
+        for params in arg_proc.make_sim_params() {
+            let result = params.result();
+            out.push_str(&format!("{},{},{},{}\n",
+                                  batch::params_row(&params),
+                                  result.groups,
+                                  format.format(result.p_disrupt),
+                                  format.format(result.p_compromise)));
         }
+        out
+    };
+    let first = sweep();
+    assert_eq!(first.lines().count(), 2);
+    assert_eq!(sweep().as_bytes(), first.as_bytes());
+}
+
+#[test]
+#[should_panic(expected = "--time-limit 10 with --canonical")]
+fn test_canonical_time_limit() {
+    let _ = parse_args(&["full", "--canonical", "--time-limit", "10"])
+        .unwrap_or_else(|e| panic!("{}", e))
+        .make_sim_params();
+}
+
+#[test]
+fn test_duration_sources() {
+    let sources: Vec<String> =
+        parse_args(&["full", "--time-limit", "150m", "--confirm-above", "3600"])
+            .unwrap_or_else(|e| panic!("{}", e))
+            .duration_sources()
+            .iter()
+            .map(|source| source.to_string())
+            .collect();
+    assert_eq!(sources,
+               vec!["time limit = 2h30m (from --time-limit)",
+                    "confirmation threshold = 1h (from --confirm-above)"]);
+}
+
+#[test]
+#[should_panic(expected = "--time-limit 1.5h30m (only the last component may have a fraction)")]
+fn test_ambiguous_duration() {
+    let _ = parse_args(&["full", "--time-limit", "1.5h30m"])
+        .unwrap_or_else(|e| panic!("{}", e))
+        .time_limit();
+}
+
+#[test]
+#[should_panic(expected = "--global-budget with --interleave")]
+fn test_global_budget_interleaved() {
+    let arg_proc = parse_args(&["full", "--global-budget", "1000", "--interleave"])
+        .unwrap_or_else(|e| panic!("{}", e));
+    assert_eq!(parse_args(&["full", "--global-budget", "1000"])
+                   .unwrap_or_else(|e| panic!("{}", e))
+                   .global_budget(),
+               Some(1000));
+    let _ = arg_proc.global_budget();
+}
+
+#[test]
+#[should_panic(expected = "--target requires --auto-extend")]
+fn test_target_without_auto_extend() {
+    let auto_extend = |args: &[&str]| {
+        parse_args(args).unwrap_or_else(|e| panic!("{}", e)).auto_extend()
+    };
+    assert_eq!(auto_extend(&["calc", "--auto-extend", "q", "--target", "0.1", "--extend-bounds",
+                             "0.3-1"]),
+               Some(AutoExtend {
+                   param: ExtendParam::Quorum,
+                   target: 0.1,
+                   bounds: ExtendParam::Quorum.bounds("0.3-1").ok(),
+               }));
+    assert_eq!(auto_extend(&["full", "--auto-extend", "malicious"]).map(|e| e.target),
+               Some(0.5));
+    assert_eq!(auto_extend(&["full"]), None);
+    let _ = auto_extend(&["full", "--target", "0.1"]);
+}
+
+#[test]
+#[should_panic(expected = "--refresh requires --cache")]
+fn test_refresh_without_cache() {
+    assert!(parse_args(&["calc"]).unwrap_or_else(|e| panic!("{}", e)).result_cache().is_none());
+    let _ = parse_args(&["batch", "--refresh"]).unwrap_or_else(|e| panic!("{}", e)).result_cache();
+}
+
+#[test]
+#[should_panic(expected = "--join-cap-mode requires --join-cap")]
+fn test_join_cap_mode() {
+    let caps = |args: &[&str]| -> Vec<Option<JoinCap>> {
+        parse_args(args)
+            .unwrap_or_else(|e| panic!("{}", e))
+            .make_sim_params()
+            .iter()
+            .map(|params| params.join_cap)
+            .collect()
+    };
+    assert_eq!(caps(&["full"]), vec![None]);
+    assert_eq!(caps(&["full", "--join-cap", "2:10"]),
+               vec![Some("2:10".parse().expect("parse"))]);
+    assert_eq!(caps(&["full", "--join-cap", "2:10", "--join-cap-mode", "queue"]),
+               vec![Some("2:10+queue".parse().expect("parse"))]);
+    let _ = caps(&["full", "--join-cap-mode", "queue"]);
+}
+
+#[test]
+#[should_panic(expected = "use calc")]
+fn test_no_repetitions() {
+    // Accepted (and ignored) by calc, but not by the simulations
+    assert_eq!(parse_args(&["calc", "-p", "0"])
+                   .unwrap_or_else(|e| panic!("{}", e))
+                   .make_sim_params()
+                   .len(),
+               1);
+    let _ = parse_args(&["structure", "-p", "0"])
+        .unwrap_or_else(|e| panic!("{}", e))
+        .make_sim_params();
+}
+
+#[test]
+fn test_expansion_dimensions() {
+    let expand = |args: &[&str]| {
+        parse_args(args).unwrap_or_else(|e| panic!("{}", e)).expand_sim_params()
+    };
+    let (sets, dims) = expand(&["full", "-n", "1000-4000:1000", "-r", "5%-30%:5%", "-k", "8-12:1",
+                                "-q", "0.5,0.6,0.7,0.8", "-Q", "all", "-T", "all"]);
+    let values: Vec<(&str, usize)> =
+        dims.iter().filter(|dim| dim.values > 1).map(|dim| (dim.name, dim.values)).collect();
+    assert_eq!(values,
+               vec![("nodes", 4), ("malicious", 6), ("min group size", 5), ("quorum size", 4),
+                    ("quorum type", 3), ("targetting", 3)]);
+    // Dimensions not given are there, with one value
+    assert_eq!(dims.iter().find(|dim| dim.name == "objective").map(|dim| dim.values), Some(1));
+    assert_eq!(dims.iter().map(|dim| dim.values).product::<usize>(), sets.len());
+    assert!(dims.windows(2).all(|w| w[0].after == w[1].before));
+    assert!(expansion_text(&dims).ends_with(&format!("= {} combinations", sets.len())));
+
+    // Elder counts only expand elder quorums
+    let (sets, dims) = expand(&["full", "-Q", "all", "--elders", "5,7", "-k", "8"]);
+    assert_eq!(sets.len(), 4);
+    let elders = dims.iter().find(|dim| dim.name == "elders").expect("elders");
+    assert_eq!((elders.values, elders.before, elders.after), (2, 3, 4));
+    assert_eq!(expansion_text(&dims).split(" = ").nth(1),
+               Some("6 combinations (4 after dimensions expanding some sets only)"));
+
+    // Each attacker knowledge level is a set of its own
+    let (sets, _) = expand(&["full", "-T", "threshold", "--attacker-knowledge", "all"]);
+    let knowledge: Vec<&str> = sets.iter().map(|params| params.attacker_knowledge.name()).collect();
+    assert_eq!(knowledge, vec!["full", "membership-only", "own-nodes-only"]);
+}
+
+#[test]
+fn test_lazy_expansion() {
+    let expansion =
+        |args: &[&str]| parse_args(args).unwrap_or_else(|e| panic!("{}", e)).expansion();
+    // Sets are made as reached, so the first of a grid far too large to hold come at once, in
+    // the order of expansion
+    let grid = expansion(&["calc", "-n", "1000-1000000:1", "-k", "8-1007", "-q", "0.5-0.99:0.01"]);
+    assert_eq!(grid.count(), 999001 * 1000 * 50);
+    let first: Vec<(NN, NN)> =
+        grid.iter().take(3).map(|params| (params.num_nodes, params.min_group_size)).collect();
+    assert_eq!(first, vec![(1000, 8), (1001, 8), (1002, 8)]);
+
+    // A sweep of 200k calc sets runs through without holding them
+    let grid = expansion(&["calc", "-n", "1000-2000000:1000", "-k", "8-107"]);
+    assert_eq!(grid.count(), 200000);
+    let mut sets = 0;
+    let mut last = None;
+    for params in grid.iter() {
+        sets += 1;
+        last = Some((params.num_nodes, params.min_group_size));
+    }
+    assert_eq!((sets, last), (200000, Some((2000000, 107))));
+}
+
+#[test]
+fn test_weight_expansion() {
+    let weights = |args: &[&str]| -> Vec<(&'static str, Option<String>)> {
+        parse_args(args)
+            .unwrap_or_else(|e| panic!("{}", e))
+            .make_sim_params()
+            .iter()
+            .map(|params| {
+                (params.quorum_type.name(), params.weighting.as_ref().map(|w| w.to_string()))
+            })
+            .collect()
+    };
+    // Only the age quorum is weighted
+    assert_eq!(weights(&["full", "-Q", "all", "--weight", "capacity(uniform(1,3))"]),
+               vec![("simple", None),
+                    ("age", Some("capacity(uniform(1;3))".to_owned())),
+                    ("elder", None)]);
+    assert_eq!(weights(&["full", "-Q", "age"]), vec![("age", Some("age".to_owned()))]);
+}
+
+#[test]
+fn test_paired_ageing_expansion() {
+    let params = parse_args(&["full", "--paired-ageing", "-n", "100,200"])
+        .unwrap_or_else(|e| panic!("{}", e))
+        .make_sim_params();
+    let mut quorums: Vec<&str> = params.iter().map(|p| p.quorum_type.name()).collect();
+    quorums.sort();
+    assert_eq!(quorums, vec!["age", "age", "simple", "simple"]);
+    // All share one master seed, drawn if not given
+    let seed = params[0].seeding.seed;
+    assert!(seed.is_some());
+    assert!(params.iter().all(|p| p.outcomes && p.seeding.seed == seed));
+}
 
-        v
+#[test]
+fn test_analytic() {
+    let params = |args: &[&str]| {
+        parse_args(args)
+            .unwrap_or_else(|e| panic!("{}", e))
+            .make_sim_params()
+    };
+    let common = ["-n", "300", "-r", "30,60", "-k", "8", "-q", "0.5,0.6", "--quorum-basis",
+                  "minimum", "--block-prop", "0.4"];
+    let calc = params(&[&["calc"][..], &common[..]].concat());
+    let full = params(&[&["full", "-Q", "all", "-T", "simple", "--baseline-column"][..],
+                        &common[..]]
+        .concat());
+    assert_eq!(full.len(), 3 * calc.len());
+    // Each simulated set has the P(compromise) of the calculation at its
+    // numbers and quorum rule, whatever its quorum type and targetting
+    for sim in &full {
+        let same = calc.iter()
+            .find(|c| {
+                c.num_malicious.from_base(c.num_nodes) ==
+                sim.num_malicious.from_base(sim.num_nodes) && c.quorum == sim.quorum
+            })
+            .expect("calc set");
+        assert_eq!(sim.analytic(), Some(same.result().p_compromise));
     }
+    assert_eq!(calc[0].analytic(), None);
+}
+
+#[test]
+fn test_log_level() {
+    let level = |args: &[&str]| {
+        parse_args(args)
+            .unwrap_or_else(|e| panic!("{}", e))
+            .log_level()
+    };
+    assert_eq!(level(&["calc"]), LogLevelFilter::Info);
+    assert_eq!(level(&["full", "-v"]), LogLevelFilter::Debug);
+    assert_eq!(level(&["structure", "-vv"]), LogLevelFilter::Trace);
+    assert_eq!(level(&["batch", "-v", "-v"]), LogLevelFilter::Trace);
+    assert_eq!(level(&["calc", "--log-level", "warn"]), LogLevelFilter::Warn);
+    assert_eq!(level(&["full", "--log-level", "OFF"]), LogLevelFilter::Off);
+}
+
+#[test]
+fn test_tool_help() {
+    let help = |args: &[&str]| match parse_args(args) {
+        Ok(_) => panic!("no help: {:?}", args),
+        Err(e) => {
+            assert!(!e.fatal());
+            e.to_string()
+        }
+    };
+    assert!(help(&["--help"]).contains("routing-sims full [options]"));
+    assert!(!help(&["calc", "--help"]).contains("-Q QTYPE"));
+    assert!(!help(&["structure", "-h"]).contains("-Q QTYPE"));
+    assert!(help(&["structure", "-h"]).contains("--report LIST"));
+    assert!(help(&["full", "--help"]).contains("-Q QTYPE"));
+    assert!(help(&["batch", "--help"]).contains("--skip-bad-lines"));
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq)]
 pub enum SimType {
     DirectCalc,
     Structure,
@@ -363,22 +3569,220 @@ impl SimType {
             SimType::FullSim => "full_sim",
         }
     }
+
+    /// Parse from `name()` or `command()`
+    pub fn from_name(name: &str) -> Option<SimType> {
+        [SimType::DirectCalc, SimType::Structure, SimType::FullSim]
+            .iter()
+            .cloned()
+            .find(|t| t.name() == name || t.command() == name)
+    }
+
+    /// Name of the tool on the command line
+    pub fn command(self) -> &'static str {
+        match self {
+            SimType::DirectCalc => "calc",
+            SimType::Structure => "structure",
+            SimType::FullSim => "full",
+        }
+    }
+
+    // Usage of this tool, listing only the options it accepts.
+    fn usage(self) -> String {
+        match self {
+            SimType::DirectCalc => {
+                format!("{}{}{}{}", USAGE_CALC, OPTIONS_COMMON, OPTIONS_CALC, OPTIONS_RUN)
+            }
+            SimType::Structure => {
+                format!("{}{}{}{}{}",
+                        USAGE_STRUCTURE,
+                        OPTIONS_COMMON,
+                        OPTIONS_STRUCTURE,
+                        OPTIONS_SEED,
+                        OPTIONS_RUN)
+            }
+            SimType::FullSim => {
+                format!("{}{}{}{}{}",
+                        USAGE_FULL,
+                        OPTIONS_COMMON,
+                        OPTIONS_FULL,
+                        OPTIONS_SEED,
+                        OPTIONS_RUN)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum QuorumType {
+    Simple,
+    Age,
+    Elder,
 }
 
-#[derive(Clone, Copy)]
+impl QuorumType {
+    pub fn name(self) -> &'static str {
+        match self {
+            QuorumType::Simple => "simple",
+            QuorumType::Age => "age",
+            QuorumType::Elder => "elder",
+        }
+    }
+
+    /// Parse from `name()`
+    pub fn from_name(name: &str) -> Option<QuorumType> {
+        [QuorumType::Simple, QuorumType::Age, QuorumType::Elder]
+            .iter()
+            .cloned()
+            .find(|t| t.name() == name)
+    }
+}
 
+#[derive(Clone, Debug, PartialEq)]
 pub enum AttackType {
     Untargetted,
     SimpleTargetted,
+    DeadlineTargetted,
+    /// Deploys nodes only where they could compromise a group (see
+    /// `ThresholdAttack`)
+    Threshold,
+    /// The first strategy before the given step, then the second (see
+    /// `CompositeAttack`); written as in `-T`, e.g. `untargetted:500+simple`.
+    Composite(Box<AttackType>, NN, Box<AttackType>),
 }
 
 impl AttackType {
-    pub fn name(&self) -> &'static str {
+    pub fn name(&self) -> String {
+        match self {
+            &AttackType::Untargetted => "untarg.".to_owned(),
+            &AttackType::SimpleTargetted => "simp_targ".to_owned(),
+            &AttackType::DeadlineTargetted => "deadline".to_owned(),
+            &AttackType::Threshold => "threshold".to_owned(),
+            &AttackType::Composite(..) => self.spec(),
+        }
+    }
+
+    // Name as written in a composite spec
+    fn spec(&self) -> String {
+        match self {
+            &AttackType::Untargetted => "untargetted".to_owned(),
+            &AttackType::SimpleTargetted => "simple".to_owned(),
+            &AttackType::DeadlineTargetted => "deadline".to_owned(),
+            &AttackType::Threshold => "threshold".to_owned(),
+            &AttackType::Composite(ref first, step, ref second) => {
+                format!("{}:{}+{}", first.spec(), step, second.spec())
+            }
+        }
+    }
+
+    /// Whether the strategy may hold nodes in reserve, so that fewer are
+    /// deployed than are available (i.e. it is or includes `Threshold`)
+    pub fn holds_reserve(&self) -> bool {
         match self {
-            &AttackType::Untargetted => "untarg.",
-            &AttackType::SimpleTargetted => "simp_targ",
+            &AttackType::Threshold => true,
+            &AttackType::Composite(ref first, _, ref second) => {
+                first.holds_reserve() || second.holds_reserve()
+            }
+            _ => false,
         }
     }
+
+    /// Parse from `name()`
+    pub fn from_name(name: &str) -> Option<AttackType> {
+        if name.contains('+') {
+            return AttackType::parse_composite(name).ok();
+        }
+        [AttackType::Untargetted,
+         AttackType::SimpleTargetted,
+         AttackType::DeadlineTargetted,
+         AttackType::Threshold]
+            .iter()
+            .cloned()
+            .find(|t| t.name() == name)
+    }
+
+    /// Parse a composite strategy: strategies (untargetted, simple, deadline or
+    /// threshold) joined by `:STEP+`, switching to the next at step STEP, e.g.
+    /// `untargetted:500+simple`.
+    pub fn parse_composite(spec: &str) -> Result<AttackType, String> {
+        let component = |name: &str| {
+            [AttackType::Untargetted,
+             AttackType::SimpleTargetted,
+             AttackType::DeadlineTargetted,
+             AttackType::Threshold]
+                .iter()
+                .cloned()
+                .find(|t| t.spec() == name)
+                .ok_or_else(|| format!("unknown strategy '{}'", name))
+        };
+        let (first, rest) = match spec.find('+') {
+            Some(i) => (&spec[..i], &spec[i + 1..]),
+            None => return Err(format!("expected STRATEGY:STEP+STRATEGY, found '{}'", spec)),
+        };
+        let mut parts = first.splitn(2, ':');
+        let name = parts.next().expect("first split item");
+        let step = match parts.next().map(|s| s.parse::<NN>()) {
+            Some(Ok(step)) => step,
+            _ => return Err(format!("expected STRATEGY:STEP before '+', found '{}'", first)),
+        };
+        let second = if rest.contains('+') {
+            try!(AttackType::parse_composite(rest))
+        } else {
+            try!(component(rest))
+        };
+        Ok(AttackType::Composite(Box::new(try!(component(name))), step, Box::new(second)))
+    }
+
+    /// Check the switch steps of a composite strategy are increasing and within
+    /// `max_steps` (i.e. each strategy is used for some step).
+    pub fn check_steps(&self, max_steps: NN) -> Result<(), String> {
+        let mut prev = 0;
+        let mut targetting = self;
+        while let &AttackType::Composite(_, step, ref second) = targetting {
+            if step <= prev || step >= max_steps {
+                return Err(format!("switch step {} not within {}-{}",
+                                   step,
+                                   prev + 1,
+                                   max_steps - 1));
+            }
+            prev = step;
+            targetting = second;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_composite_spec() {
+    let spec = "untargetted:500+simple:800+deadline";
+    let composite = AttackType::parse_composite(spec).expect("parse");
+    assert_eq!(composite,
+               AttackType::Composite(Box::new(AttackType::Untargetted),
+                                     500,
+                                     Box::new(AttackType::Composite(
+                                         Box::new(AttackType::SimpleTargetted),
+                                         800,
+                                         Box::new(AttackType::DeadlineTargetted)))));
+    assert_eq!(composite.name(), spec);
+    assert_eq!(AttackType::from_name(spec), Some(composite.clone()));
+    assert_eq!(composite.check_steps(1000), Ok(()));
+    assert_eq!(composite.check_steps(800),
+               Err("switch step 800 not within 501-799".to_owned()));
+    assert!(AttackType::parse_composite("simple:800+untargetted:500+deadline")
+        .expect("parse")
+        .check_steps(1000)
+        .is_err());
+
+    let err = |s: &str| AttackType::parse_composite(s).err().expect("error");
+    assert_eq!(err("untargetted+simple"),
+               "expected STRATEGY:STEP before '+', found 'untargetted'");
+    assert_eq!(err("none:500+simple"), "unknown strategy 'none'");
+    assert_eq!(err("simple"), "expected STRATEGY:STEP+STRATEGY, found 'simple'");
+
+    assert!(!composite.holds_reserve());
+    let threshold = AttackType::parse_composite("untargetted:500+threshold").expect("parse");
+    assert!(threshold.holds_reserve());
+    assert_eq!(AttackType::from_name("threshold"), Some(AttackType::Threshold));
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -397,25 +3801,27 @@ impl RelOrAbs {
 }
 
 impl FromStr for RelOrAbs {
-    type Err = ();  // we just panic!
+    type Err = ();
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if s.ends_with('%') {
             let mut s = s.to_string();
             let _ = s.pop();
-            let perc = s.parse::<RR>().expect("parse");
+            let perc = try!(s.parse::<RR>().map_err(|_| ()));
             Ok(RelOrAbs::Rel(perc * 0.01))
         } else {
-            Ok(RelOrAbs::Abs(s.parse().expect("parse")))
+            s.parse().map(RelOrAbs::Abs).map_err(|_| ())
         }
     }
 }
 
+// Ranges mixing relative and absolute values are rejected when parsed (see `RangeKind`), so
+// mixed operands are not expected.
 impl AddAssign for RelOrAbs {
     fn add_assign(&mut self, rhs: RelOrAbs) {
         match (self, rhs) {
             (&mut RelOrAbs::Rel(ref mut x), RelOrAbs::Rel(y)) => *x += y,
             (&mut RelOrAbs::Abs(ref mut x), RelOrAbs::Abs(y)) => *x += y,
-            _ => panic!("wrong rel/abs type!"),
+            _ => debug_assert!(false, "wrong rel/abs type!"),
         }
     }
 }
@@ -425,82 +3831,577 @@ impl PartialOrd<RelOrAbs> for RelOrAbs {
         match (self, rhs) {
             (&RelOrAbs::Rel(x), &RelOrAbs::Rel(ref y)) => x.partial_cmp(y),
             (&RelOrAbs::Abs(x), &RelOrAbs::Abs(ref y)) => x.partial_cmp(y),
-            _ => panic!("wrong rel/abs type!"),
+            _ => {
+                debug_assert!(false, "wrong rel/abs type!");
+                None
+            }
+        }
+    }
+}
+
+impl RangeKind for RelOrAbs {
+    fn same_kind(&self, other: &RelOrAbs) -> bool {
+        match (*self, *other) {
+            (RelOrAbs::Rel(_), RelOrAbs::Rel(_)) |
+            (RelOrAbs::Abs(_), RelOrAbs::Abs(_)) => true,
+            _ => false,
         }
     }
+
+    fn kinds() -> &'static str {
+        "absolute counts (e.g. 10-100:5) or all percentages (e.g. 5%-50%:5%)"
+    }
 }
 
 impl DefaultStep<RelOrAbs> for RelOrAbs {
-    fn default_step(x: RelOrAbs) -> RelOrAbs {
+    fn default_step(start: RelOrAbs, stop: RelOrAbs) -> RelOrAbs {
+        match (start, stop) {
+            (RelOrAbs::Rel(start), RelOrAbs::Rel(stop)) => {
+                RelOrAbs::Rel(default_real_step(start, stop))
+            }
+            _ => RelOrAbs::Abs(1),
+        }
+    }
+
+    fn snap(x: RelOrAbs) -> RelOrAbs {
+        match x {
+            RelOrAbs::Rel(r) => RelOrAbs::Rel(snap_real(r)),
+            RelOrAbs::Abs(_) => x,
+        }
+    }
+}
+
+impl fmt::Display for RelOrAbs {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            RelOrAbs::Rel(r) => write!(f, "{}%", snap_real(r * 100.0)),
+            RelOrAbs::Abs(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+/// Minimum group size as a function of the number of nodes `n`, given to `-k`
+/// instead of a range. The result is rounded to the nearest whole number.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GroupSizeFormula {
+    /// `log2+C` (or `log2-C`): log2(n) + C
+    Log2(RR),
+    /// `sqrt/C`: sqrt(n) / C
+    Sqrt(RR),
+    /// `A*n+B` (or `A*n-B`, or `A*n`): A × n + B
+    Linear(RR, RR),
+}
+
+impl GroupSizeFormula {
+    /// Whether `s` is meant as a formula rather than a range: it has characters
+    /// other than those of numbers, ranges and lists.
+    pub fn is_formula(s: &str) -> bool {
+        s.chars().any(|c| !(c.is_digit(10) || c == '-' || c == ':' || c == ','))
+    }
+
+    /// Group size for `n` nodes. Panics if less than 1.
+    pub fn of(self, n: NN) -> NN {
+        let n = n as RR;
+        let k = match self {
+            GroupSizeFormula::Log2(c) => n.log2() + c,
+            GroupSizeFormula::Sqrt(c) => n.sqrt() / c,
+            GroupSizeFormula::Linear(a, b) => a * n + b,
+        };
+        if k.round() < 1.0 {
+            panic!("group size formula gives {} for {} nodes", k, n);
+        }
+        k.round() as NN
+    }
+}
+
+// Parse a signed term such as "+3" or "-0.5" (or "" for 0).
+fn parse_term(s: &str) -> Result<RR, ()> {
+    if s.is_empty() {
+        Ok(0.0)
+    } else if s.starts_with('+') {
+        s[1..].parse().map_err(|_| ())
+    } else if s.starts_with('-') {
+        s[1..].parse::<RR>().map(|x| -x).map_err(|_| ())
+    } else {
+        Err(())
+    }
+}
+
+impl FromStr for GroupSizeFormula {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad = || format!("expected log2+C, sqrt/C or A*n+B, found '{}'", s);
+        if s.starts_with("log2") {
+            parse_term(&s["log2".len()..]).map(GroupSizeFormula::Log2).map_err(|_| bad())
+        } else if s.starts_with("sqrt/") {
+            match s["sqrt/".len()..].parse::<RR>() {
+                Ok(c) if c > 0.0 => Ok(GroupSizeFormula::Sqrt(c)),
+                _ => Err(bad()),
+            }
+        } else if let Some(i) = s.find("*n") {
+            let a = try!(s[..i].parse::<RR>().map_err(|_| bad()));
+            let b = try!(parse_term(&s[i + "*n".len()..]).map_err(|_| bad()));
+            Ok(GroupSizeFormula::Linear(a, b))
+        } else {
+            Err(bad())
+        }
+    }
+}
+
+#[test]
+fn test_group_size_formula() {
+    let of = |formula: &str, n: NN| formula.parse::<GroupSizeFormula>().expect("parse").of(n);
+    assert_eq!(of("log2+2", 1024), 12);
+    assert_eq!(of("log2-2", 4096), 10);
+    assert_eq!(of("log2", 1000), 10);  // 9.97
+    assert_eq!(of("sqrt/4", 1600), 10);
+    assert_eq!(of("sqrt/2.5", 10000), 40);
+    assert_eq!(of("0.01*n+5", 500), 10);
+    assert_eq!(of("0.002*n", 5000), 10);
+    assert_eq!(of("0.01*n-2", 1500), 13);
+
+    assert!(GroupSizeFormula::is_formula("log2+2"));
+    assert!(!GroupSizeFormula::is_formula("8-12:2"));
+    assert!(!GroupSizeFormula::is_formula("8,10"));
+    for s in &["log2*2", "sqrt/0", "sqrt+2", "n+2", "2*n+x", "bogus"] {
+        assert_eq!(s.parse::<GroupSizeFormula>(),
+                   Err(format!("expected log2+C, sqrt/C or A*n+B, found '{}'", s)));
+    }
+}
+
+impl FromStr for QuorumSize {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.ends_with('a') {
+            let mut s = s.to_string();
+            let _ = s.pop();
+            s.parse().map(QuorumSize::Count).map_err(|_| ())
+        } else {
+            s.parse().map(QuorumSize::Proportion).map_err(|_| ())
+        }
+    }
+}
+
+// As for `RelOrAbs`, mixed operands are rejected when parsed.
+impl AddAssign for QuorumSize {
+    fn add_assign(&mut self, rhs: QuorumSize) {
+        match (self, rhs) {
+            (&mut QuorumSize::Proportion(ref mut x), QuorumSize::Proportion(y)) => *x += y,
+            (&mut QuorumSize::Count(ref mut x), QuorumSize::Count(y)) => *x += y,
+            _ => debug_assert!(false, "wrong proportion/count quorum type!"),
+        }
+    }
+}
+
+impl PartialOrd<QuorumSize> for QuorumSize {
+    fn partial_cmp(&self, rhs: &QuorumSize) -> Option<Ordering> {
+        match (self, rhs) {
+            (&QuorumSize::Proportion(x), &QuorumSize::Proportion(ref y)) => x.partial_cmp(y),
+            (&QuorumSize::Count(x), &QuorumSize::Count(ref y)) => x.partial_cmp(y),
+            _ => {
+                debug_assert!(false, "wrong proportion/count quorum type!");
+                None
+            }
+        }
+    }
+}
+
+impl RangeKind for QuorumSize {
+    fn same_kind(&self, other: &QuorumSize) -> bool {
+        match (*self, *other) {
+            (QuorumSize::Proportion(_), QuorumSize::Proportion(_)) |
+            (QuorumSize::Count(_), QuorumSize::Count(_)) => true,
+            _ => false,
+        }
+    }
+
+    fn kinds() -> &'static str {
+        "proportions (e.g. 0.5-0.7:0.1) or all counts (e.g. 4a-6a:1a)"
+    }
+}
+
+impl DefaultStep<QuorumSize> for QuorumSize {
+    fn default_step(start: QuorumSize, stop: QuorumSize) -> QuorumSize {
+        match (start, stop) {
+            (QuorumSize::Proportion(start), QuorumSize::Proportion(stop)) => {
+                QuorumSize::Proportion(default_real_step(start, stop))
+            }
+            _ => QuorumSize::Count(1),
+        }
+    }
+
+    fn snap(x: QuorumSize) -> QuorumSize {
         match x {
-            RelOrAbs::Rel(_) => RelOrAbs::Rel(0.1),
-            RelOrAbs::Abs(_) => RelOrAbs::Abs(1),
+            QuorumSize::Proportion(p) => QuorumSize::Proportion(snap_real(p)),
+            QuorumSize::Count(_) => x,
         }
     }
 }
 
-pub const PARAM_TITLES: [&'static str; 9] = ["Type",
-                                             "AgeQuorum",
-                                             "Targetting",
-                                             "Nodes",
-                                             "Malicious",
-                                             "MinGroup",
-                                             "QuorumProp",
-                                             "P(disruption)",
-                                             "P(compromise)"];
+pub const PARAM_TITLES: [&'static str; 15] = ["Type",
+                                              "Quorum",
+                                              "Targetting",
+                                              "Prefix",
+                                              "Relocation",
+                                              "Uptime",
+                                              "Nodes",
+                                              "Malicious",
+                                              "MinGroup",
+                                              "QuorumSize",
+                                              "QuorumBasis",
+                                              "Groups",
+                                              "Runs",
+                                              "P(disruption)",
+                                              "P(compromise)"];
 #[derive(Clone)]
 pub struct SimParams {
     pub sim_type: SimType,
-    pub age_quorum: bool,
+    pub quorum_type: QuorumType,
+    /// Number of elders per group (elder quorum only)
+    pub elders: Option<NN>,
+    /// Steps by which promotions to elder lag (elder quorum only)
+    pub promotion_lag: Option<NN>,
+    /// How elders of equal age are ranked (elder quorum only)
+    pub elder_tiebreak: Option<ElderTiebreak>,
+    /// Probability that the attacker grinds a winning name (elder quorum only)
+    pub name_grinding: Option<RR>,
+    /// What votes are weighted by (age quorum only)
+    pub weighting: Option<Weighting>,
+    /// Cap on the age counted by the quorum, if any (age quorum, or elder
+    /// quorum when ranking elders by capped age)
+    pub age_cap: Option<AgeCap>,
     pub targetting: AttackType,
+    /// What targetting strategies aim for
+    pub objective: Objective,
+    /// What the attacker knows of the network when deploying its nodes
+    pub attacker_knowledge: AttackerKnowledge,
+    pub target_prefix: Option<Prefix>,
+    pub relocation: RelocationTarget,
+    /// Where malicious nodes are when the simulation starts
+    pub placement: InitialPlacement,
+    /// Bound of the offset of each group's quorum proportion (simulations
+    /// only; see `quorum::QuorumJitter`)
+    pub quorum_jitter: RR,
+    /// Distribution of honest node uptime
+    pub honest_uptime: UptimeDist,
+    /// Correlated failure bursts, if any
+    pub burst: Option<Burst>,
+    /// Corruption of honest nodes in place, if any
+    pub corruption: Option<Corruption>,
+    /// Probability of each honest node being evicted at each step
+    pub eviction_rate: RR,
+    /// Resource of malicious nodes relative to honest ones
+    pub attack_resource: RR,
+    /// Proportion of its former age a rejoining node recovers
+    pub rejoin_recovery: RR,
+    /// Steps for which the identities of departed nodes are remembered
+    pub rejoin_window: NN,
+    /// How the proof-of-work to join scales with network size
+    pub pow_scaling: PowScaling,
+    /// Step at which malicious nodes start joining
+    pub attack_start: NN,
+    /// Consecutive steps a group must stay disrupted or compromised for it to
+    /// count
+    pub hold: NN,
+    /// Measurement of the long-run steady state, if requested
+    pub steady_state: Option<SteadyState>,
+    /// Steps by which the membership the quorums are checked against lags
+    pub membership_lag: NN,
+    /// Cap on the nodes each group accepts per window of steps, if any
+    pub join_cap: Option<JoinCap>,
+    /// Steps between cross-checks of the incremental quorum checks, if any
+    pub paranoid: Option<NN>,
+    /// Whether to check the invariants of the network structure every step
+    pub verify: bool,
+    /// Number of folds of the repetitions to compare, if any
+    pub split_check: Option<NN>,
+    /// Whether to record the attacker's decisions in the first repetition
+    pub record_attack: bool,
+    /// Decisions for the attacker to replay in the first repetition, if any
+    pub replay_attack: Option<Vec<AttackDecision>>,
+    /// Whether to count the values drawn from each random number stream
+    pub rng_audit: bool,
+    /// Seconds of wall time after which to stop repeating simulations, if any
+    pub time_limit: Option<RR>,
     pub num_nodes: NN,
     pub num_malicious: RelOrAbs,
     pub min_group_size: NN,
-    pub quorum_prop: RR,
+    pub quorum: QuorumSize,
+    pub quorum_basis: QuorumBasis,
+    /// Minimum number of voters for a quorum, if any
+    pub quorum_floor: Option<NN>,
+    /// Proportion of a group whose absence causes disruption, if not the
+    /// complement of the quorum
+    pub block_prop: Option<RR>,
+    /// Probability that an honest member takes part in a decision
+    pub participation: RR,
     pub max_steps: NN,
     pub repetitions: NN,
+    pub malice_hist: bool,
+    pub churn_model: ChurnModel,
+    /// Whether to report the spread of the malicious nodes over the groups
+    pub spread: bool,
+    /// Whether to report the share of the address space held by compromised
+    /// and disrupted groups
+    pub address_coverage: bool,
+    /// Close groups to compare with sections, if requested
+    pub close_group: Option<CloseGroup>,
+    pub elders_report: bool,
+    pub any_group_model: AnyGroupModel,
+    pub compare_models: bool,
+    /// Whether to calculate the correction factor of the structure simulation
+    pub correction: bool,
+    /// Steps between samples of the age share, if reported
+    pub age_share_interval: Option<NN>,
+    /// Whether to report liveness (full simulation only)
+    pub liveness: bool,
+    /// Whether to report why runs ended (full simulation only)
+    pub termination: bool,
+    /// Whether to report if structural changes could be blocked (full
+    /// simulation only)
+    pub governance: bool,
+    /// Whether to report the turnover of honest ages (full simulation only)
+    pub turnover: bool,
+    /// Whether to record the outcome of each repetition (full simulation
+    /// only; see `--paired-ageing`)
+    pub outcomes: bool,
+    /// Where to checkpoint the full simulation, if at all
+    pub checkpoint: Option<Checkpointing>,
+    /// Seeds of the random streams of the simulations
+    pub seeding: Seeding,
+    /// Master seed of this set, overriding that of `seeding`, so that the set
+    /// may be run in parts (see `--interleave`)
+    pub master_seed: Option<u64>,
+    /// Cache of probabilities, shared between parameter sets
+    pub prob_cache: Arc<ProbCache>,
+    /// Request to stop, shared between parameter sets
+    pub interrupt: Interrupt,
 }
 
 impl SimParams {
+    /// Whether this is the direct calculation's upper bound under simple
+    /// targetting (see `--model`)
+    pub fn targetted_bound(&self) -> bool {
+        self.sim_type == SimType::DirectCalc && self.targetting == AttackType::SimpleTargetted
+    }
+
+    /// Name of the targetting for output: `simp_bound` for the upper bound
+    pub fn targetting_name(&self) -> String {
+        if self.targetted_bound() {
+            "simp_bound".to_owned()
+        } else {
+            self.targetting.name()
+        }
+    }
+
     pub fn result(&self) -> SimResult {
+        self.tool().calc_p_compromise()
+    }
+
+    /// P(compromise) by direct calculation with the same numbers, group size
+    /// and quorum rule, i.e. without ageing or targetting, for comparison with
+    /// a simulation (`None` if this is a direct calculation).
+    pub fn analytic(&self) -> Option<RR> {
+        if self.sim_type == SimType::DirectCalc {
+            return None;
+        }
+        Some(self.as_calc().result().p_compromise)
+    }
+
+    /// This set as a direct calculation: the same numbers, group size and
+    /// quorum rule, without ageing, targetting or quorum jitter (unchanged if
+    /// already one).
+    pub fn as_calc(&self) -> SimParams {
+        let mut params = self.clone();
+        if self.sim_type != SimType::DirectCalc {
+            params.sim_type = SimType::DirectCalc;
+            params.targetting = AttackType::Untargetted;
+            params.quorum_jitter = 0.0;
+            params.checkpoint = None;
+        }
+        params
+    }
+
+    /// Full description of the runs of this set, on one line: its parameters,
+    /// repetitions and seeds, and anything else changing the runs (which the
+    /// combination id leaves out), for checkpoints and cached results.
+    pub fn run_description(&self) -> String {
+        let mut description = format!("{}{}", batch::params_row(self), self.seeding.describe());
+        // A replayed attack changes the first repetition
+        if let Some(ref replay) = self.replay_attack {
+            let decisions: Vec<String> = replay.iter().map(|d| d.to_string()).collect();
+            description.push_str(&format!(" replay {:016x}", fnv1a(&decisions.join(" "))));
+        }
+        if let Some(steady) = self.steady_state {
+            description.push_str(&format!(" steady {},{}", steady.burn_in, steady.batches));
+        }
+        description
+    }
+
+    /// The arguments of the tool calculating the result of this set
+    pub fn tool_args(&self) -> ToolArgs {
+        let mut seeding = self.seeding.clone();
+        seeding.seed = self.master_seed.or(seeding.seed);
         let args = ToolArgs {
             num_nodes: self.num_nodes,
             num_malicious: self.num_malicious.from_base(self.num_nodes),
             min_group_size: self.min_group_size,
-            quorum_prop: self.quorum_prop,
+            quorum: self.quorum,
             any_group: true, // only support this mode now
             max_steps: self.max_steps,
             repetitions: self.repetitions,
+            relocation: self.relocation,
+            placement: self.placement,
+            malice_hist: self.malice_hist,
+            churn_model: self.churn_model,
+            spread: self.spread,
+            address_coverage: self.address_coverage,
+            close_group: self.close_group,
+            elders_report: self.elders_report,
+            any_group_model: self.any_group_model,
+            compare_models: self.compare_models,
+            correction: self.correction,
+            target_prefix: self.target_prefix,
+            age_share_interval: self.age_share_interval,
+            liveness: self.liveness,
+            termination: self.termination,
+            governance: self.governance,
+            turnover: self.turnover,
+            outcomes: self.outcomes,
+            honest_uptime: self.honest_uptime.clone(),
+            weighting: self.weighting.clone().unwrap_or(Weighting::Age),
+            age_cap: self.age_cap,
+            burst: self.burst,
+            corruption: self.corruption.clone(),
+            eviction_rate: self.eviction_rate,
+            attack_resource: self.attack_resource,
+            rejoin_recovery: self.rejoin_recovery,
+            rejoin_window: self.rejoin_window,
+            pow_scaling: self.pow_scaling,
+            attack_start: self.attack_start,
+            hold: self.hold,
+            steady_state: self.steady_state,
+            membership_lag: self.membership_lag,
+            join_cap: self.join_cap,
+            paranoid: self.paranoid,
+            verify: self.verify,
+            split_check: self.split_check,
+            record_attack: self.record_attack,
+            replay_attack: self.replay_attack.clone(),
+            rng_audit: self.rng_audit,
+            time_limit: self.time_limit,
+            interrupt: self.interrupt.clone(),
+            promotion_lag: self.promotion_lag.unwrap_or(0),
+            elder_tiebreak: self.elder_tiebreak.unwrap_or(ElderTiebreak::Name),
+            name_grinding: self.name_grinding.unwrap_or(0.0),
+            checkpoint: self.checkpoint.as_ref().map(|checkpointing| {
+                CheckpointFile::new(checkpointing, &combo_id(self), &self.run_description())
+            }),
+            block_prop: self.block_prop,
+            participation: self.participation,
+            quorum_basis: self.quorum_basis,
+            quorum_floor: self.quorum_floor,
+            quorum_jitter: self.quorum_jitter,
+            seeding: seeding,
+            targetted_bound: self.targetted_bound(),
+            objective: self.objective,
+            attacker_knowledge: self.attacker_knowledge,
+            prob_cache: self.prob_cache.clone(),
         };
         args.check_invariant();
+        args
+    }
+
+    /// The tool calculating the result of this set
+    pub fn tool(&self) -> Box<Tool> {
+        self.sweep_tool(&[])
+    }
 
-        let tool: Box<Tool> = match self.sim_type {
+    /// The tool calculating the result of this set and, from the same runs,
+    /// of this set with each quorum size of `sizes` (see `Tool::calc_sweep`).
+    /// Other sizes can only be given if `sweepable`.
+    pub fn sweep_tool(&self, sizes: &[QuorumSize]) -> Box<Tool> {
+        let args = self.tool_args();
+        match self.sim_type {
             SimType::DirectCalc => Box::new(DirectCalcTool::new(args)),
             SimType::Structure => Box::new(SimStructureTool::new(args)),
             SimType::FullSim => {
                 // note: FullSimTool is templated on quorum and attack strategy parameters, so
                 // we need to create the whole thing at once (not create parameters first)
-                match (self.age_quorum, self.targetting) {
-                    (false, AttackType::Untargetted) => {
-                        Box::new(FullSimTool::new(args, SimpleQuorum::new(), UntargettedAttack {}))
+                let targetting = &self.targetting;
+                match self.quorum_type {
+                    QuorumType::Simple => {
+                        full_sim_tool(args, &SimpleQuorum::new, sizes, targetting)
                     }
-                    (true, AttackType::Untargetted) => {
-                        Box::new(FullSimTool::new(args, AgeQuorum::new(), UntargettedAttack {}))
+                    QuorumType::Age => {
+                        full_sim_tool(args, &WeightedQuorum::new, sizes, targetting)
                     }
-                    (false, AttackType::SimpleTargetted) => {
-                        Box::new(FullSimTool::new(args,
-                                                  SimpleQuorum::new(),
-                                                  SimpleTargettedAttack::new()))
-                    }
-                    (true, AttackType::SimpleTargetted) => {
-                        Box::new(FullSimTool::new(args,
-                                                  AgeQuorum::new(),
-                                                  SimpleTargettedAttack::new()))
+                    QuorumType::Elder => {
+                        let elders = self.elders.unwrap_or(DEFAULT_ELDERS as NN) as usize;
+                        let make = || ElderQuorum::with_elders(elders);
+                        full_sim_tool(args, &make, sizes, targetting)
                     }
                 }
             }
-        };
+        }
+    }
+
+    /// Whether the runs of this set may be shared with sets differing only in
+    /// the quorum size: those of the full simulation with an attack strategy
+    /// which does not depend on the quorum, requesting no reports looking at
+    /// the network (but termination and address coverage) and not run in parts (no checkpoints or
+    /// time limit), recording or replaying the attack or measuring the steady
+    /// state.
+    pub fn sweepable(&self) -> bool {
+        let mut attack = boxed_attack(&self.targetting);
+        attack.set_objective(self.objective);
+        self.sim_type == SimType::FullSim && !attack.depends_on_quorum() && !self.malice_hist &&
+        !self.spread && !self.elders_report && self.age_share_interval.is_none() &&
+        !self.liveness && !self.governance && !self.turnover && !self.outcomes &&
+        self.split_check.is_none() && !self.record_attack && self.replay_attack.is_none() &&
+        !self.rng_audit &&
+        self.checkpoint.is_none() && self.time_limit.is_none() && self.steady_state.is_none()
+    }
+}
+
+// Create a FullSimTool with quorums made by `make`, of its own size and then of each of
+// `sweep` (see `FullSimTool::sweep_quorums`), and the given attack strategy.
+fn full_sim_tool<Q: Quorum + 'static>(args: ToolArgs,
+                                      make: &Fn() -> Q,
+                                      sweep: &[QuorumSize],
+                                      targetting: &AttackType)
+                                      -> Box<Tool> {
+    let swept = || sweep.iter().map(|&size| (size, make())).collect();
+    match *targetting {
+        AttackType::Untargetted => {
+            Box::new(FullSimTool::new(args, make(), UntargettedAttack {}).sweep_quorums(swept()))
+        }
+        AttackType::SimpleTargetted => {
+            Box::new(FullSimTool::new(args, make(), SimpleTargettedAttack::new())
+                .sweep_quorums(swept()))
+        }
+        AttackType::DeadlineTargetted => {
+            Box::new(FullSimTool::new(args, make(), DeadlineAttack::new()).sweep_quorums(swept()))
+        }
+        AttackType::Threshold => {
+            Box::new(FullSimTool::new(args, make(), ThresholdAttack::new()).sweep_quorums(swept()))
+        }
+        AttackType::Composite(ref first, step, ref second) => {
+            let attack = CompositeAttack::new(boxed_attack(first), step, boxed_attack(second));
+            Box::new(FullSimTool::new(args, make(), attack).sweep_quorums(swept()))
+        }
+    }
+}
 
-        tool.calc_p_compromise()
+// Create a boxed attack strategy (for composites).
+fn boxed_attack(targetting: &AttackType) -> Box<BoxedAttack> {
+    match *targetting {
+        AttackType::Untargetted => Box::new(UntargettedAttack {}),
+        AttackType::SimpleTargetted => Box::new(SimpleTargettedAttack::new()),
+        AttackType::DeadlineTargetted => Box::new(DeadlineAttack::new()),
+        AttackType::Threshold => Box::new(ThresholdAttack::new()),
+        AttackType::Composite(ref first, step, ref second) => {
+            Box::new(CompositeAttack::new(boxed_attack(first), step, boxed_attack(second)))
+        }
     }
 }
@@ -0,0 +1,203 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Audits of the random numbers drawn (see `--rng-audit`)
+//!
+//! Runs with the same parameters and seed draw the same values from each
+//! stream in each repetition. If a change meant to leave the results alone
+//! draws more or fewer, its audit shows where the runs part.
+
+use super::NN;
+use super::rng::{Draws, Stream, STREAMS};
+use super::model;
+
+use std::fmt::{self, Formatter};
+use std::fs::File;
+use std::io::Read;
+
+
+/// Titles of the audit file
+pub const AUDIT_TITLES: &'static str = "Combination,ComboId,Repetition,Stream,Draws";
+
+/// Audit lines of the parameter set with index `i` and combination id `id`,
+/// one per stream of each repetition of `record` (numbered like the main
+/// table, repetitions counting from 0).
+pub fn audit_rows(i: usize, id: &str, record: &[Draws]) -> String {
+    record.iter()
+        .enumerate()
+        .flat_map(|(repetition, draws)| {
+            STREAMS.iter().zip(draws.iter()).map(move |(stream, draws)| {
+                format!("{},{},{},{},{}", i + 1, id, repetition, stream.name(), draws)
+            })
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// One line of an audit
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    pub combination: usize,
+    pub id: String,
+    pub repetition: NN,
+    pub stream: Stream,
+    /// Values drawn, in 32-bit words
+    pub draws: NN,
+}
+
+impl Record {
+    // Parse line `line` (counting from 1) of an audit
+    fn parse(line: &str, number: usize) -> Result<Record, String> {
+        let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+        let bad = |what: &str| format!("line {}: bad {} in '{}'", number, what, line);
+        if fields.len() != 5 {
+            return Err(bad("number of fields"));
+        }
+        Ok(Record {
+            combination: try!(fields[0].parse().map_err(|_| bad("combination"))),
+            id: fields[1].to_owned(),
+            repetition: try!(fields[2].parse().map_err(|_| bad("repetition"))),
+            stream: try!(Stream::from_name(fields[3]).ok_or_else(|| bad("stream"))),
+            draws: try!(fields[4].parse().map_err(|_| bad("draws"))),
+        })
+    }
+
+    // Whether `other` is of the same combination, repetition and stream
+    fn same_place(&self, other: &Record) -> bool {
+        (self.combination, &self.id, self.repetition, self.stream) ==
+        (other.combination, &other.id, other.repetition, other.stream)
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f,
+               "combination {} ({}), repetition {}, {} stream",
+               self.combination,
+               self.id,
+               self.repetition,
+               self.stream.name())
+    }
+}
+
+/// The first line at which two audits differ
+#[derive(Debug, PartialEq)]
+pub struct Divergence {
+    /// Line number (counting the titles as line 1)
+    pub line: usize,
+    /// The line of each audit, if it has one there
+    pub first: Option<Record>,
+    pub second: Option<Record>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match (self.first.as_ref(), self.second.as_ref()) {
+            (Some(a), Some(b)) if a.same_place(b) => {
+                write!(f,
+                       "line {}: {} drew {} values, against {}",
+                       self.line,
+                       a,
+                       a.draws,
+                       b.draws)
+            }
+            (Some(a), Some(b)) => write!(f, "line {}: {} against {}", self.line, a, b),
+            (Some(a), None) => write!(f, "line {}: {} only in the first", self.line, a),
+            (None, Some(b)) => write!(f, "line {}: {} only in the second", self.line, b),
+            (None, None) => write!(f, "line {}", self.line),
+        }
+    }
+}
+
+// Records of audit `contents`, skipping the stamp of the model version (audits of different
+// versions may still be compared, to see where they part)
+fn parse_audit(contents: &str) -> Result<Vec<Record>, String> {
+    let mut lines = contents.lines()
+        .filter(|line| !line.trim().is_empty() && model::read_stamp(line).is_none());
+    if lines.next().map(|titles| titles.trim()) != Some(AUDIT_TITLES) {
+        return Err(format!("expected titles '{}'", AUDIT_TITLES));
+    }
+    lines.enumerate().map(|(i, line)| Record::parse(line, i + 2)).collect()
+}
+
+/// Compare audits `first` and `second` (the contents of files written by
+/// `--rng-audit`), giving the first line at which they differ, if any.
+pub fn compare(first: &str, second: &str) -> Result<Option<Divergence>, String> {
+    let first = try!(parse_audit(first).map_err(|e| format!("first audit: {}", e)));
+    let second = try!(parse_audit(second).map_err(|e| format!("second audit: {}", e)));
+    let (mut first, mut second) = (first.into_iter(), second.into_iter());
+    let mut line = 1;
+    loop {
+        line += 1;
+        match (first.next(), second.next()) {
+            (None, None) => return Ok(None),
+            (Some(ref a), Some(ref b)) if a == b => {}
+            (a, b) => {
+                return Ok(Some(Divergence {
+                    line: line,
+                    first: a,
+                    second: b,
+                }))
+            }
+        }
+    }
+}
+
+/// `compare` the audit files at paths `first` and `second`
+pub fn compare_files(first: &str, second: &str) -> Result<Option<Divergence>, String> {
+    let read = |path: &str| -> Result<String, String> {
+        let mut contents = String::new();
+        try!(File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut contents))
+            .map_err(|e| format!("unable to read {}: {}", path, e)));
+        Ok(contents)
+    };
+    compare(&try!(read(first)), &try!(read(second)))
+}
+
+#[test]
+fn test_compare() {
+    let record = vec![[310, 28, 4096, 0], [306, 30, 4102, 0]];
+    let audit =
+        |record: &[Draws]| format!("{}\n{}\n", AUDIT_TITLES, audit_rows(2, "v1-ab", record));
+    assert_eq!(audit_rows(2, "v1-ab", &record[..1]),
+               "3,v1-ab,0,structure,310\n3,v1-ab,0,attack,28\n3,v1-ab,0,churn,4096\n\
+                3,v1-ab,0,liveness,0");
+    assert_eq!(compare(&audit(&record), &audit(&record)), Ok(None));
+    let stamped = format!("{}\n{}", model::stamp(), audit(&record));
+    assert_eq!(compare(&stamped, &audit(&record)), Ok(None));
+
+    // One more draw from the churn stream of the second repetition
+    let mut changed = record.clone();
+    changed[1][2] += 1;
+    let divergence = compare(&audit(&record), &audit(&changed)).expect("audits").expect("differ");
+    assert_eq!(divergence.line, 8);
+    let first = divergence.first.clone().expect("first");
+    assert_eq!((first.repetition, first.stream, first.draws), (1, Stream::Churn, 4102));
+    assert_eq!(divergence.second.as_ref().map(|b| b.draws), Some(4103));
+    assert_eq!(divergence.to_string(),
+               "line 8: combination 3 (v1-ab), repetition 1, churn stream drew 4102 values, \
+                against 4103");
+
+    // A repetition missing from the second
+    let divergence =
+        compare(&audit(&record), &audit(&record[..1])).expect("audits").expect("differ");
+    assert_eq!((divergence.line, divergence.second), (6, None));
+
+    assert!(compare("bogus\n", &audit(&record)).is_err());
+    assert!(compare(&audit(&record), &format!("{}\n3,v1-ab,0,bogus,1\n", AUDIT_TITLES)).is_err());
+}
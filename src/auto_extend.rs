@@ -0,0 +1,441 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Extending the range of a parameter until it brackets the transition (see
+//! `--auto-extend`)
+//!
+//! P(compromise) typically goes from near zero to near one over a short
+//! stretch of the quorum size (or malicious proportion, or group size), and
+//! a sweep guessed wrongly lies wholly to one side of it. After the sets of
+//! the options have run, each series of sets differing only in the parameter
+//! whose P(compromise) at both ends of its range lies on the same side of the
+//! target gets a set one step beyond the end nearer the target, and so on
+//! until the target lies between the ends or the next step is out of bounds.
+
+use super::{NN, RR};
+use super::args::{SimParams, RelOrAbs};
+use super::batch::validate;
+use super::combo::serialise;
+use super::quorum::QuorumSize;
+use super::tools::SimResult;
+
+use std::collections::HashMap;
+
+
+/// A parameter whose range may be extended: one whose effect on
+/// P(compromise) is known in direction
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExtendParam {
+    /// The number of malicious nodes (`-r`)
+    Malicious,
+    /// The minimum group size (`-k`)
+    GroupSize,
+    /// The quorum size (`-q`)
+    Quorum,
+}
+
+impl ExtendParam {
+    /// Parse from the name of the option, long or short: e.g. `quorum` or `q`
+    pub fn from_name(name: &str) -> Option<ExtendParam> {
+        match name {
+            "r" | "malicious" => Some(ExtendParam::Malicious),
+            "k" | "group-size" => Some(ExtendParam::GroupSize),
+            "q" | "quorum" => Some(ExtendParam::Quorum),
+            _ => None,
+        }
+    }
+
+    /// The option giving the parameter
+    pub fn option(self) -> &'static str {
+        match self {
+            ExtendParam::Malicious => "-r",
+            ExtendParam::GroupSize => "-k",
+            ExtendParam::Quorum => "-q",
+        }
+    }
+
+    // Whether P(compromise) rises with the value: it does with more malicious nodes, and falls
+    // with larger groups and quorums
+    fn rising(self) -> bool {
+        self == ExtendParam::Malicious
+    }
+
+    // The line of the parameter in `combo::serialise`
+    fn field(self) -> &'static str {
+        match self {
+            ExtendParam::Malicious => "num_malicious=",
+            ExtendParam::GroupSize => "min_group_size=",
+            ExtendParam::Quorum => "quorum_size=",
+        }
+    }
+
+    /// The value of the parameter in `params`, and whether it is relative (a
+    /// percentage, as a proportion, or a quorum proportion) rather than a count
+    pub fn value(self, params: &SimParams) -> (RR, bool) {
+        match self {
+            ExtendParam::Malicious => {
+                match params.num_malicious {
+                    RelOrAbs::Rel(r) => (r, true),
+                    RelOrAbs::Abs(n) => (n as RR, false),
+                }
+            }
+            ExtendParam::GroupSize => (params.min_group_size as RR, false),
+            ExtendParam::Quorum => {
+                match params.quorum {
+                    QuorumSize::Proportion(prop) => (prop, true),
+                    QuorumSize::Count(n) => (n as RR, false),
+                }
+            }
+        }
+    }
+
+    /// `params` with the parameter at `value`, of the same kind: relative
+    /// values are rounded to six decimal places and counts to whole numbers.
+    /// `None` if the value is out of its natural range: a proportion above 0
+    /// and at most 1, a count of at least 1 (and a group size at most the
+    /// number of nodes).
+    pub fn with_value(self, params: &SimParams, value: RR) -> Option<SimParams> {
+        let (_, relative) = self.value(params);
+        let value = if relative {
+            (value * 1e6).round() / 1e6
+        } else {
+            value.round()
+        };
+        let usable = if relative {
+            value > 0.0 && value <= 1.0
+        } else {
+            value >= 1.0
+        };
+        if !usable {
+            return None;
+        }
+        let mut params = params.clone();
+        match self {
+            ExtendParam::Malicious if relative => params.num_malicious = RelOrAbs::Rel(value),
+            ExtendParam::Malicious => params.num_malicious = RelOrAbs::Abs(value as NN),
+            ExtendParam::GroupSize if value as NN > params.num_nodes => return None,
+            ExtendParam::GroupSize => params.min_group_size = value as NN,
+            ExtendParam::Quorum if relative => params.quorum = QuorumSize::Proportion(value),
+            ExtendParam::Quorum => params.quorum = QuorumSize::Count(value as NN),
+        }
+        Some(params)
+    }
+
+    /// Parse bounds `LOW-HIGH` (inclusive), in the form of the values of the
+    /// option, e.g. `0.3-0.9` or `5a-9a` for the quorum size
+    pub fn bounds(self, s: &str) -> Result<Bounds, String> {
+        let parts: Vec<&str> = s.split('-').collect();
+        if parts.len() != 2 {
+            return Err(format!("expected LOW-HIGH, found '{}'", s));
+        }
+        let mut values = vec![];
+        for part in parts {
+            let bad = || format!("bad value '{}'", part);
+            values.push(match self {
+                ExtendParam::Malicious => {
+                    match try!(part.parse::<RelOrAbs>().map_err(|_| bad())) {
+                        RelOrAbs::Rel(r) => (r, true),
+                        RelOrAbs::Abs(n) => (n as RR, false),
+                    }
+                }
+                ExtendParam::GroupSize => {
+                    (try!(part.parse::<NN>().map_err(|_| bad())) as RR, false)
+                }
+                ExtendParam::Quorum => {
+                    match try!(part.parse::<QuorumSize>().map_err(|_| bad())) {
+                        QuorumSize::Proportion(prop) => (prop, true),
+                        QuorumSize::Count(n) => (n as RR, false),
+                    }
+                }
+            });
+        }
+        if values[0].1 != values[1].1 {
+            return Err(format!("low and high of '{}' must be of one kind", s));
+        }
+        if values[0].0 >= values[1].0 {
+            return Err(format!("low must be below high, found '{}'", s));
+        }
+        Ok(Bounds {
+            low: values[0].0,
+            high: values[1].0,
+            relative: values[0].1,
+        })
+    }
+}
+
+/// Bounds of the values to extend to (inclusive), as given by `value()`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    pub low: RR,
+    pub high: RR,
+    pub relative: bool,
+}
+
+/// The extension of the range of a parameter (see `--auto-extend`)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AutoExtend {
+    pub param: ExtendParam,
+    /// The P(compromise) to bracket
+    pub target: RR,
+    /// Bounds given beyond those of the parameter itself, if any
+    pub bounds: Option<Bounds>,
+}
+
+impl AutoExtend {
+    /// Series of `param_sets`: the indices of the sets identical but for the
+    /// parameter, each ordered by value, ordered by their first set
+    pub fn series(&self, param_sets: &[SimParams]) -> Vec<Vec<usize>> {
+        let mut series: Vec<Vec<usize>> = vec![];
+        let mut by_key: HashMap<String, usize> = HashMap::new();
+        for (i, params) in param_sets.iter().enumerate() {
+            let key: String = serialise(params)
+                .lines()
+                .filter(|line| !line.starts_with(self.param.field()))
+                .map(|line| format!("{}\n", line))
+                .collect();
+            if let Some(&s) = by_key.get(&key) {
+                series[s].push(i);
+            } else {
+                by_key.insert(key, series.len());
+                series.push(vec![i]);
+            }
+        }
+        for indices in &mut series {
+            indices.sort_by(|&i, &j| {
+                let value = |i: usize| self.param.value(&param_sets[i]).0;
+                value(i).partial_cmp(&value(j)).expect("comparable values")
+            });
+        }
+        series
+    }
+
+    /// Check that the sets may be extended: the parameter takes at least two
+    /// values in each series, their gap giving the step, and any bounds are
+    /// of the same kind as its values
+    pub fn check(&self, param_sets: &[SimParams]) -> Result<(), String> {
+        for indices in self.series(param_sets) {
+            if indices.len() < 2 {
+                return Err(format!("{} must take at least two values in every combination of \
+                                    the other parameters, to give the step to extend by",
+                                   self.param.option()));
+            }
+            let relative = self.param.value(&param_sets[indices[0]]).1;
+            if self.bounds.map_or(false, |bounds| bounds.relative != relative) {
+                return Err(format!("--extend-bounds must be of the same kind as the values of \
+                                    {}",
+                                   self.param.option()));
+            }
+        }
+        Ok(())
+    }
+
+    // Whether P(compromise) at the ends of a series lies either side of the target (or on it)
+    fn brackets(&self, p_first: RR, p_last: RR) -> bool {
+        p_first.min(p_last) <= self.target && self.target <= p_first.max(p_last)
+    }
+
+    /// The sets to run next, given P(compromise) of each of `param_sets`
+    /// (`None` if not run): for each series not bracketing the target, the
+    /// set one step beyond the end nearer it, the step being the gap between
+    /// the two values at that end. Series with an end not run, or whose next
+    /// value is out of bounds or gives an unusable set, are left as they are.
+    pub fn extension(&self, param_sets: &[SimParams], p: &[Option<RR>]) -> Vec<SimParams> {
+        let mut sets = vec![];
+        for indices in self.series(param_sets) {
+            let (first, last) = (indices[0], indices[indices.len() - 1]);
+            let (p_first, p_last) = match (p[first], p[last]) {
+                (Some(p_first), Some(p_last)) => (p_first, p_last),
+                _ => continue,
+            };
+            if indices.len() < 2 || self.brackets(p_first, p_last) {
+                continue;
+            }
+            // Both ends lie on the same side of the target
+            let upward = (p_last < self.target) == self.param.rising();
+            let (end, next) = if upward {
+                (last, indices[indices.len() - 2])
+            } else {
+                (first, indices[1])
+            };
+            let value = self.param.value(&param_sets[end]).0;
+            let beyond = 2.0 * value - self.param.value(&param_sets[next]).0;
+            if let Some(params) = self.param.with_value(&param_sets[end], beyond) {
+                let value = self.param.value(&params).0;
+                let within = self.bounds.map_or(true, |bounds| {
+                    value >= bounds.low - 1e-9 && value <= bounds.high + 1e-9
+                });
+                if within && validate(&params).is_ok() {
+                    sets.push(params);
+                }
+            }
+        }
+        sets
+    }
+
+    /// Run `param_sets` with `run`, then the sets of each `extension` in turn
+    /// until there are none, adding them to `param_sets`. `run` is given the
+    /// index of the first set of those to run. Returns the results of all
+    /// the sets.
+    pub fn run_extended<F>(&self,
+                           param_sets: &mut Vec<SimParams>,
+                           mut run: F)
+                           -> Vec<Option<SimResult>>
+        where F: FnMut(usize, &[SimParams]) -> Vec<Option<SimResult>>
+    {
+        let mut results = run(0, param_sets);
+        loop {
+            let p: Vec<Option<RR>> =
+                results.iter().map(|result| result.as_ref().map(|r| r.p_compromise)).collect();
+            let sets = self.extension(param_sets, &p);
+            if sets.is_empty() {
+                break;
+            }
+            info!("--auto-extend: adding {} parameter sets beyond the range of {}",
+                  sets.len(),
+                  self.param.option());
+            let first = param_sets.len();
+            param_sets.extend(sets);
+            let more = run(first, &param_sets[first..]);
+            results.extend(more);
+        }
+        let series = self.series(param_sets);
+        let unbracketed = series.iter()
+            .filter(|indices| {
+                let ends = (results[indices[0]].as_ref(),
+                            results[indices[indices.len() - 1]].as_ref());
+                match ends {
+                    (Some(first), Some(last)) => {
+                        !self.brackets(first.p_compromise, last.p_compromise)
+                    }
+                    _ => true,
+                }
+            })
+            .count();
+        if unbracketed > 0 {
+            warn!("--auto-extend: {} of {} series of {} do not bracket P(compromise) {}, reaching \
+                   the bounds (or an unusable or unrun set) first",
+                  unbracketed,
+                  series.len(),
+                  self.param.option(),
+                  self.target);
+        }
+        results
+    }
+}
+
+#[test]
+fn test_extend_param() {
+    use super::args::sim_params;
+
+    assert_eq!(ExtendParam::from_name("q"), Some(ExtendParam::Quorum));
+    assert_eq!(ExtendParam::from_name("group-size"), Some(ExtendParam::GroupSize));
+    assert_eq!(ExtendParam::from_name("n"), None);
+    let quorum = ExtendParam::Quorum;
+    assert_eq!(quorum.bounds("0.3-0.9"),
+               Ok(Bounds {
+                   low: 0.3,
+                   high: 0.9,
+                   relative: true,
+               }));
+    assert_eq!(ExtendParam::Malicious.bounds("5-40").map(|b| b.relative), Ok(false));
+    assert!(quorum.bounds("5a-0.9").is_err());
+    assert!(quorum.bounds("0.9-0.3").is_err());
+    assert!(quorum.bounds("0.5").is_err());
+    assert!(ExtendParam::GroupSize.bounds("8-10.5").is_err());
+
+    // Values are rounded to their kind, and kept within their natural range
+    let sets = sim_params(&["calc", "-n", "100", "-k", "10", "-q", "6a", "-r", "10%"]);
+    let value = |param: ExtendParam, x: RR| param.with_value(&sets[0], x).map(|p| param.value(&p));
+    assert_eq!(value(quorum, 4.4), Some((4.0, false)));
+    assert_eq!(value(quorum, 0.0), None);
+    assert_eq!(value(ExtendParam::Malicious, 0.3000001), Some((0.3, true)));
+    assert_eq!(value(ExtendParam::Malicious, 1.05), None);
+    assert_eq!(value(ExtendParam::GroupSize, 101.0), None);
+
+    // A single value gives no step
+    let extend = AutoExtend {
+        param: quorum,
+        target: 0.5,
+        bounds: None,
+    };
+    assert!(extend.check(&sim_params(&["calc", "-n", "100,200", "-q", "0.5"])).is_err());
+    assert!(extend.check(&sim_params(&["calc", "-n", "100,200", "-q", "0.5,0.6"])).is_ok());
+    let bounded = AutoExtend { bounds: Some(quorum.bounds("5a-9a").expect("bounds")), ..extend };
+    assert!(bounded.check(&sim_params(&["calc", "-q", "0.5,0.6"])).is_err());
+}
+
+#[test]
+fn test_auto_extend() {
+    use super::args::sim_params;
+
+    // Runs the sets with the direct calculation, noting how many each round
+    let run_extended = |extend: AutoExtend, args: &[&str]| {
+        let mut sets = sim_params(args);
+        let mut rounds = vec![];
+        let results = extend.run_extended(&mut sets, |first, new: &[SimParams]| {
+            rounds.push((first, new.len()));
+            new.iter().map(|params| Some(params.result())).collect()
+        });
+        let p: Vec<RR> = results.iter().map(|r| r.as_ref().expect("result").p_compromise).collect();
+        (sets, p, rounds)
+    };
+    let extend = AutoExtend {
+        param: ExtendParam::Quorum,
+        target: 0.5,
+        bounds: None,
+    };
+    let quorum = |params: &SimParams| ExtendParam::Quorum.value(params).0;
+
+    // Quorums too large to be reached: extended downward a set at a time, by the step, and no
+    // further once the lowest is compromised with probability at least the target
+    let args = ["calc", "-n", "1000", "-r", "30%", "-k", "10", "-q", "0.8,0.85,0.9"];
+    let (sets, p, rounds) = run_extended(extend, &args);
+    assert!(rounds.len() > 1);
+    assert_eq!(rounds[0], (0, 3));
+    for (round, &(first, n)) in rounds.iter().enumerate().skip(1) {
+        assert_eq!((first, n), (2 + round, 1));
+        assert!((quorum(&sets[first]) - (0.8 - 0.05 * round as RR)).abs() < 1e-9);
+    }
+    let last = sets.len() - 1;
+    assert!(p[last] >= 0.5);
+    assert!(p[..last].iter().all(|&p| p < 0.5), "{:?}", p);
+
+    // Already bracketed, so nothing is added
+    let (sets, _, rounds) = run_extended(extend, &["calc", "-n", "1000", "-r", "30%", "-k", "10",
+                                                   "-q", "0.5-0.9:0.1"]);
+    assert_eq!((sets.len(), rounds), (5, vec![(0, 5)]));
+
+    // Nor beyond the bounds given
+    let bounded = AutoExtend {
+        bounds: Some(ExtendParam::Quorum.bounds("0.8-1").expect("bounds")),
+        ..extend
+    };
+    let (sets, _, rounds) = run_extended(bounded, &args);
+    assert_eq!((sets.len(), rounds), (3, vec![(0, 3)]));
+
+    // Too few malicious nodes: extended upward, in each series (network size) separately
+    let malicious = AutoExtend { param: ExtendParam::Malicious, ..extend };
+    let (sets, p, _) = run_extended(malicious, &["calc", "-n", "500,1000", "-r", "1%-2%:1%",
+                                                 "-k", "10", "-q", "0.5"]);
+    assert!(sets.len() > 4);
+    for indices in malicious.series(&sets) {
+        let last = indices[indices.len() - 1];
+        assert!(ExtendParam::Malicious.value(&sets[last]).0 > 0.02);
+        assert!(p[last] >= 0.5);
+        assert!(indices[..indices.len() - 1].iter().all(|&i| p[i] < 0.5), "{:?}", p);
+    }
+}
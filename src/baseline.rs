@@ -0,0 +1,407 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Comparison of results with those of an earlier run (the baseline)
+
+use super::{NN, RR};
+use super::combo::COMBO_ID_TITLE;
+use super::model;
+
+use std::io::BufRead;
+
+
+/// Columns of the main table holding results rather than parameters, with
+/// probabilities written as probabilities or as log10 (see `--log10`). All
+/// other columns identify the parameter set.
+pub const RESULT_TITLES: [&'static str; 32] = ["Groups",
+                                               "Runs",
+                                               "P(disruption)",
+                                               "P(compromise)",
+                                               "Deployed",
+                                               "Spread",
+                                               "MaxCluster",
+                                               "Coverage",
+                                               "MaxCoverage",
+                                               "Coverage(disruption)",
+                                               "MaxCoverage(disruption)",
+                                               "P(section)",
+                                               "P(close)",
+                                               "P(loss)",
+                                               "Turnover",
+                                               "MedianAge",
+                                               "Steady(disruption)",
+                                               "SteadyCI(disruption)",
+                                               "RunsCI(disruption)",
+                                               "Steady(compromise)",
+                                               "SteadyCI(compromise)",
+                                               "RunsCI(compromise)",
+                                               "Trend",
+                                               "log10P(disruption)",
+                                               "log10P(compromise)",
+                                               "Analytic",
+                                               "log10Analytic",
+                                               "Baseline",
+                                               "log10Baseline",
+                                               "Delta",
+                                               "Signif.",
+                                               "Viz"];
+
+/// Critical value of the two-proportion z-test (two-sided, 5% level)
+pub const Z_CRITICAL: RR = 1.96;
+
+/// Parameters identifying a row of the main table: `(column title, value)`
+/// pairs, in table order.
+pub type RowKey = Vec<(String, String)>;
+
+/// Columns added after the first tables were written, with their default
+/// values (which older tables implicitly have)
+const DEFAULTED_COLUMNS: [(&'static str, &'static str); 31] = [("ChurnModel", "fixed"),
+                                                               ("QuorumBasis", "actual"),
+                                                               ("QuorumFloor", "none"),
+                                                               ("BlockProp", "quorum"),
+                                                               ("Objective", "compromise"),
+                                                               ("Knowledge", "full"),
+                                                               ("Burst", "none"),
+                                                               ("Corrupt", "none"),
+                                                               ("EvictionRate", "0"),
+                                                               ("AttackResource", "1"),
+                                                               ("RejoinRecovery", "0"),
+                                                               ("RejoinWindow", "100"),
+                                                               ("PowScaling", "none"),
+                                                               ("AttackStart", "0"),
+                                                               ("Hold", "1"),
+                                                               ("MembershipLag", "0"),
+                                                               ("JoinCap", "none"),
+                                                               ("Elders", "-"),
+                                                               ("Elders", "7"),
+                                                               ("PromotionLag", "-"),
+                                                               ("PromotionLag", "0"),
+                                                               ("ElderTiebreak", "-"),
+                                                               ("ElderTiebreak", "name"),
+                                                               ("NameGrinding", "-"),
+                                                               ("NameGrinding", "0"),
+                                                               ("Weight", "-"),
+                                                               ("Weight", "age"),
+                                                               ("AgeCap", "none"),
+                                                               ("Placement", "uniform"),
+                                                               ("Jitter", "0"),
+                                                               ("Participation", "1")];
+
+/// Key of a row from the titles and values of its parameter columns. Default
+/// values of optional columns are dropped, so that tables with and without
+/// such a column can be joined.
+pub fn row_key<'a, I: IntoIterator<Item = (&'a str, &'a str)>>(cells: I) -> RowKey {
+    cells.into_iter()
+        .filter(|cell| !DEFAULTED_COLUMNS.contains(cell))
+        .map(|(title, value)| (title.to_owned(), value.to_owned()))
+        .collect()
+}
+
+/// Row key as text, e.g. for listing unmatched rows
+pub fn key_text(key: &RowKey) -> String {
+    key.iter()
+        .map(|&(ref title, ref value)| format!("{}={}", title, value))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// One row of the baseline
+#[derive(Debug)]
+pub struct BaselineRow {
+    pub key: RowKey,
+    pub p_compromise: RR,
+    /// Number of simulation runs, if estimated by simulation
+    pub runs: Option<NN>,
+}
+
+/// Comparison of one row of this run with the baseline
+#[derive(Debug, PartialEq)]
+pub struct Comparison {
+    /// P(compromise) of the baseline
+    pub baseline: RR,
+    /// P(compromise) of this run minus that of the baseline
+    pub delta: RR,
+    /// Whether the difference is significant, if both are estimated by
+    /// simulation (`None` otherwise)
+    pub significant: Option<bool>,
+}
+
+/// Results of an earlier run, read from its main table.
+pub struct Baseline {
+    pub rows: Vec<BaselineRow>,
+    /// Version of the model stamped on the table, if any (see `model`)
+    pub model: Option<NN>,
+}
+
+impl Baseline {
+    /// Read the main table as written to standard output by an earlier run.
+    /// Columns are found by title; reading stops at the first blank line, so
+    /// any reports following the table are ignored. P(compromise) may be
+    /// written as log10. The table may start with the stamp of the model
+    /// version.
+    pub fn read<R: BufRead>(input: R) -> Result<Self, String> {
+        let mut lines = input.lines().enumerate().peekable();
+        let stamp = lines.peek().and_then(|&(_, ref line)| {
+            line.as_ref().ok().and_then(|line| model::read_stamp(line))
+        });
+        let model = match stamp {
+            Some(version) => {
+                let _ = lines.next();
+                Some(try!(version.map_err(|e| format!("line 1: {}", e))))
+            }
+            None => None,
+        };
+        let titles: Vec<String> = match lines.next() {
+            Some((_, Ok(header))) => header.split_whitespace().map(|s| s.to_owned()).collect(),
+            Some((i, Err(e))) => return Err(format!("line {}: {}", i + 1, e)),
+            None => return Err("no header line".to_owned()),
+        };
+        // The Viz column is last and may be empty, so is not read
+        let num_cols = titles.iter().take_while(|title| *title != "Viz").count();
+        let column = |name: &str| titles[..num_cols].iter().position(|title| title == name);
+        let (p_col, log10) = try!(column("P(compromise)")
+            .map(|col| (col, false))
+            .or_else(|| column("log10P(compromise)").map(|col| (col, true)))
+            .ok_or_else(|| "line 1: no P(compromise) column".to_owned()));
+        let runs_col = column("Runs");
+
+        let mut rows = Vec::new();
+        for (i, line) in lines {
+            let line_num = i + 1;
+            let line = try!(line.map_err(|e| format!("line {}: {}", line_num, e)));
+            if line.trim().is_empty() {
+                break;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < num_cols {
+                return Err(format!("line {}: expected {} fields, found {}",
+                                   line_num,
+                                   num_cols,
+                                   fields.len()));
+            }
+            let bad = |col: usize| {
+                format!("line {}: bad value for {}: '{}'", line_num, titles[col], fields[col])
+            };
+            // Marks (`*`) after P(compromise), of too few compromises, and after the runs, of
+            // a set stopped early, are dropped
+            let unmarked = |col: usize| fields[col].trim_right_matches('*');
+            let p_compromise: RR = try!(unmarked(p_col).parse().map_err(|_| bad(p_col)));
+            let p_compromise = if log10 {
+                (10.0 as RR).powf(p_compromise)     // -inf gives zero
+            } else {
+                p_compromise
+            };
+            let runs = match runs_col {
+                None => None,
+                Some(col) if fields[col] == "-" => None,
+                Some(col) => Some(try!(unmarked(col).parse().map_err(|_| bad(col)))),
+            };
+            let key = row_key(titles[..num_cols]
+                .iter()
+                .zip(&fields)
+                .filter(|&(title, _)| {
+                    !RESULT_TITLES.contains(&title.as_str()) && title != COMBO_ID_TITLE
+                })
+                .map(|(title, value)| (title.as_str(), *value)));
+            rows.push(BaselineRow {
+                key: key,
+                p_compromise: p_compromise,
+                runs: runs,
+            });
+        }
+        Ok(Baseline {
+            rows: rows,
+            model: model,
+        })
+    }
+
+    /// Check the baseline was written by this version of the model (see
+    /// `model::check`), the baseline being `path`.
+    pub fn check_model(&self, path: &str, allow: bool) -> Result<(), String> {
+        model::check(&format!("baseline {}", path), self.model, allow)
+    }
+
+    /// Compare one row of this run, with key `key`, P(compromise) `p` and
+    /// number of runs `runs`, with the baseline. Returns the index of the
+    /// baseline row matched (the first with the same key) and the comparison,
+    /// or `None` if the row is not in the baseline.
+    pub fn compare_row(&self,
+                       key: &RowKey,
+                       p: RR,
+                       runs: Option<NN>)
+                       -> Option<(usize, Comparison)> {
+        self.rows.iter().position(|row| row.key == *key).map(|i| {
+            let row = &self.rows[i];
+            let comparison = Comparison {
+                baseline: row.p_compromise,
+                delta: p - row.p_compromise,
+                significant: match (runs, row.runs) {
+                    (Some(n1), Some(n2)) if n1 > 0 && n2 > 0 => {
+                        Some(significant_difference(p, n1, row.p_compromise, n2))
+                    }
+                    _ => None,
+                },
+            };
+            (i, comparison)
+        })
+    }
+
+    /// Join the rows of this run, given as `(key, P(compromise), runs)`, with
+    /// the baseline by key. Returns the comparison for each row of this run
+    /// (`None` if not in the baseline), and the baseline rows not matched.
+    pub fn compare(&self,
+                   current: &[(RowKey, RR, Option<NN>)])
+                   -> (Vec<Option<Comparison>>, Vec<&BaselineRow>) {
+        let mut matched = vec![false; self.rows.len()];
+        let comparisons = current.iter()
+            .map(|&(ref key, p, runs)| {
+                self.compare_row(key, p, runs).map(|(i, comparison)| {
+                    matched[i] = true;
+                    comparison
+                })
+            })
+            .collect();
+        (comparisons, self.unmatched(&matched))
+    }
+
+    /// Baseline rows not matched, given whether each row was
+    pub fn unmatched(&self, matched: &[bool]) -> Vec<&BaselineRow> {
+        self.rows.iter().zip(matched).filter(|&(_, m)| !m).map(|(row, _)| row).collect()
+    }
+}
+
+/// Two-proportion z-test: whether proportions `p1` of `n1` runs and `p2` of
+/// `n2` runs differ significantly (at the level of `Z_CRITICAL`).
+pub fn significant_difference(p1: RR, n1: NN, p2: RR, n2: NN) -> bool {
+    let (n1, n2) = (n1 as RR, n2 as RR);
+    // Proportions are of whole numbers of runs
+    let (x1, x2) = ((p1 * n1).round(), (p2 * n2).round());
+    let pooled = (x1 + x2) / (n1 + n2);
+    let std_err = (pooled * (1.0 - pooled) * (1.0 / n1 + 1.0 / n2)).sqrt();
+    if std_err == 0.0 {
+        return false;   // both all or none
+    }
+    ((x1 / n1 - x2 / n2) / std_err).abs() > Z_CRITICAL
+}
+
+#[test]
+fn test_significant_difference() {
+    // 60/100 against 40/100: z = 2.83
+    assert!(significant_difference(0.6, 100, 0.4, 100));
+    // 55/100 against 45/100: z = 1.41
+    assert!(!significant_difference(0.55, 100, 0.45, 100));
+    assert!(!significant_difference(0.0, 100, 0.0, 50));
+    assert!(!significant_difference(1.0, 10, 1.0, 10));
+}
+
+#[test]
+fn test_baseline_compare() {
+    let table = "\
+Type     Quorum   Nodes    QuorumSize Groups   Runs     P(disruption) P(compromise) Viz
+full_sim simple   1000     0.50000000 100.0    100      0.500000      0.400000      ▆
+full_sim simple   2000     0.50000000 200.0    100*     0.500000      0.000000*
+dir_calc simple   1000     0.50000000 100.0    -        0.500000      0.100000      ▅
+dir_calc simple   3000     0.50000000 300.0    -        0.500000      0.100000      ▅
+
+Type,Quorum,Nodes,QuorumSize,0-5%
+full_sim,simple,1000,0.5,7
+";
+    let baseline = Baseline::read(table.as_bytes()).expect("read");
+    assert_eq!(baseline.rows.len(), 4);
+    // Marked values read as unmarked
+    assert_eq!((baseline.rows[1].p_compromise, baseline.rows[1].runs), (0.0, Some(100)));
+
+    let key = |typ: &str, nodes: &str, block: Option<&str>| {
+        let mut cells = vec![("Type", typ), ("Quorum", "simple"), ("Nodes", nodes),
+                             ("QuorumSize", "0.50000000")];
+        if let Some(block) = block {
+            cells.push(("QuorumBasis", "actual"));
+            cells.push(("BlockProp", block));
+        }
+        row_key(cells)
+    };
+    let current = vec![(key("full_sim", "1000", None), 0.6, Some(100)),
+                       (key("full_sim", "2000", Some("quorum")), 0.02, Some(100)),
+                       (key("dir_calc", "1000", None), 0.15, None),
+                       (key("dir_calc", "1000", Some("0.3")), 0.2, None)];
+    let (comparisons, unmatched) = baseline.compare(&current);
+
+    let first = comparisons[0].as_ref().expect("matched");
+    assert_eq!(first.baseline, 0.4);
+    assert!((first.delta - 0.2).abs() < 1e-12);
+    assert_eq!(first.significant, Some(true));
+    // Defaults of newer columns match a table without them
+    let second = comparisons[1].as_ref().expect("matched");
+    assert_eq!((second.delta, second.significant), (0.02, Some(false)));
+    let third = comparisons[2].as_ref().expect("matched");
+    assert!((third.delta - 0.05).abs() < 1e-12);
+    assert_eq!(third.significant, None);
+    assert_eq!(comparisons[3], None);
+    // Likewise the fixed churn model
+    assert_eq!(row_key(vec![("Nodes", "1000"), ("ChurnModel", "fixed")]),
+               row_key(vec![("Nodes", "1000")]));
+
+    assert_eq!(unmatched.len(), 1);
+    assert_eq!(key_text(&unmatched[0].key),
+               "Type=dir_calc Quorum=simple Nodes=3000 QuorumSize=0.50000000");
+
+    // Written with --log10
+    let table = "Nodes    Runs     log10P(compromise)\n1000     100      -0.40\n\
+                 2000     100      -inf\n";
+    let baseline = Baseline::read(table.as_bytes()).expect("read");
+    assert_eq!(key_text(&baseline.rows[0].key), "Nodes=1000");
+    assert!((baseline.rows[0].p_compromise - 0.398107).abs() < 1e-6);
+    assert_eq!(baseline.rows[1].p_compromise, 0.0);
+
+    // Written with --report spread: its columns are results, not parameters
+    let table = "Nodes    P(compromise) Spread   MaxCluster\n1000     0.250000      11.8     3.9\n";
+    let baseline = Baseline::read(table.as_bytes()).expect("read");
+    assert_eq!(key_text(&baseline.rows[0].key), "Nodes=1000");
+
+    // The combination id is derived from the parameters, and older tables lack it
+    let table = "Nodes    P(compromise) ComboId\n1000     0.250000      v1-0123456789ab\n";
+    let baseline = Baseline::read(table.as_bytes()).expect("read");
+    assert_eq!(key_text(&baseline.rows[0].key), "Nodes=1000");
+
+    assert!(Baseline::read("Type Nodes\n".as_bytes()).is_err());
+    assert_eq!(Baseline::read("Nodes P(compromise)\n1000 x\n".as_bytes()).err(),
+               Some("line 2: bad value for P(compromise): 'x'".to_owned()));
+}
+
+#[test]
+fn test_baseline_model() {
+    use super::model::{stamp, MODEL_VERSION};
+
+    let table = "Nodes    P(compromise)\n1000     0.250000\n";
+    let stamped = Baseline::read(format!("{}\n{}", stamp(), table).as_bytes()).expect("read");
+    assert_eq!((stamped.model, stamped.rows.len()), (Some(MODEL_VERSION), 1));
+    assert_eq!(stamped.check_model("base.txt", false), Ok(()));
+
+    // Of another version, or of none: refused unless allowed
+    let other = format!("# routing-sims model {}\n{}", MODEL_VERSION + 1, table);
+    let other = Baseline::read(other.as_bytes()).expect("read");
+    assert_eq!(other.rows.len(), 1);
+    let e = other.check_model("base.txt", false).unwrap_err();
+    assert!(e.contains("baseline base.txt is of model version") &&
+            e.contains("--allow-model-mismatch"));
+    assert_eq!(other.check_model("base.txt", true), Ok(()));
+    let unstamped = Baseline::read(table.as_bytes()).expect("read");
+    assert_eq!(unstamped.model, None);
+    assert!(unstamped.check_model("base.txt", false).is_err());
+
+    assert!(Baseline::read("# routing-sims model x\nNodes P(compromise)\n".as_bytes()).is_err());
+}
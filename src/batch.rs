@@ -0,0 +1,771 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Batch input: parameter sets read as CSV, one per line, without expansion
+
+use super::{NN, RR};
+use super::args::{SimParams, SimType, QuorumType, AttackType, RelOrAbs};
+use super::quorum::{QuorumSize, QuorumBasis, Objective, Weighting, AgeCap, ElderTiebreak,
+                    AttackerKnowledge, DEFAULT_ELDERS};
+use super::sim::{RelocationTarget, PowScaling};
+use super::churn_model::ChurnModel;
+use super::tools::{AnyGroupModel, DEFAULT_REJOIN_WINDOW};
+use super::uptime::UptimeDist;
+use super::burst::burst_name;
+use super::join_cap::join_cap_name;
+use super::corrupt::corruption_name;
+use super::placement::InitialPlacement;
+use super::combo::combo_id;
+use super::rng::Seeding;
+use super::prob::ProbCache;
+use super::interrupt::Interrupt;
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::sync::Arc;
+
+
+/// Columns of batch input, as written by `--dry-run`. Prefix, Relocation,
+/// Uptime, ChurnModel, QuorumBasis, QuorumFloor, BlockProp, Objective,
+/// Knowledge, Burst, Corrupt, EvictionRate, AttackResource, RejoinRecovery,
+/// RejoinWindow, PowScaling, AttackStart, Hold, MembershipLag, JoinCap, Elders,
+/// PromotionLag, ElderTiebreak, NameGrinding, Weight, AgeCap, Placement,
+/// QuorumJitter and Participation are optional (defaulting to "any", "random",
+/// 1, "fixed", "actual", "none", "quorum", "compromise", "full", "none", "none",
+/// 0, 1, 0, 100, "none", 0, 1, 0, "none", for the elder quorum 7, 0, "name" and
+/// 0, for the age quorum "age", "none", "uniform", 0 and 1); the others are
+/// required, except ComboId, which is written for reference (see
+/// `combo::combo_id`) and ignored on input.
+pub const BATCH_COLUMNS: [&'static str; 39] = ["Type",
+                                               "Quorum",
+                                               "Targetting",
+                                               "Prefix",
+                                               "Relocation",
+                                               "Uptime",
+                                               "ChurnModel",
+                                               "Nodes",
+                                               "Malicious",
+                                               "MinGroup",
+                                               "QuorumSize",
+                                               "QuorumBasis",
+                                               "QuorumFloor",
+                                               "BlockProp",
+                                               "Objective",
+                                               "Knowledge",
+                                               "Burst",
+                                               "Corrupt",
+                                               "EvictionRate",
+                                               "AttackResource",
+                                               "RejoinRecovery",
+                                               "RejoinWindow",
+                                               "PowScaling",
+                                               "AttackStart",
+                                               "Hold",
+                                               "MembershipLag",
+                                               "JoinCap",
+                                               "Elders",
+                                               "PromotionLag",
+                                               "ElderTiebreak",
+                                               "NameGrinding",
+                                               "Weight",
+                                               "AgeCap",
+                                               "Placement",
+                                               "QuorumJitter",
+                                               "Participation",
+                                               "Steps",
+                                               "Repetitions",
+                                               "ComboId"];
+
+const OPTIONAL_COLUMNS: [&'static str; 30] = ["Prefix",
+                                             "Relocation",
+                                             "Uptime",
+                                             "ChurnModel",
+                                             "QuorumBasis",
+                                             "QuorumFloor",
+                                             "BlockProp",
+                                             "Objective",
+                                             "Knowledge",
+                                             "Burst",
+                                             "Corrupt",
+                                             "EvictionRate",
+                                             "AttackResource",
+                                             "RejoinRecovery",
+                                             "RejoinWindow",
+                                             "PowScaling",
+                                             "AttackStart",
+                                             "Hold",
+                                             "MembershipLag",
+                                             "JoinCap",
+                                             "Elders",
+                                             "PromotionLag",
+                                             "ElderTiebreak",
+                                             "NameGrinding",
+                                             "Weight",
+                                             "AgeCap",
+                                             "Placement",
+                                             "QuorumJitter",
+                                             "Participation",
+                                             "ComboId"];
+
+/// Write parameter sets as CSV with a header line, in the format read by
+/// `read_params`.
+pub fn write_params<W: Write>(param_sets: &[SimParams], out: &mut W) -> io::Result<()> {
+    try!(writeln!(out, "{}", BATCH_COLUMNS.join(",")));
+    for params in param_sets {
+        try!(writeln!(out, "{}", params_row(params)));
+    }
+    Ok(())
+}
+
+/// One parameter set as a line of CSV (without line ending), with columns as
+/// in `BATCH_COLUMNS`.
+pub fn params_row(params: &SimParams) -> String {
+    format!("{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},\
+             {},{},{},{},{},{},{},{},{},{},{}",
+            params.sim_type.name(),
+            params.quorum_type.name(),
+            params.targetting_name(),
+            params.target_prefix.map_or("any".to_owned(), |p| p.to_string()),
+            params.relocation.name(),
+            params.honest_uptime,
+            params.churn_model.name(),
+            params.num_nodes,
+            params.num_malicious.from_base(params.num_nodes),
+            params.min_group_size,
+            params.quorum,
+            params.quorum_basis.name(),
+            quorum_floor_name(params.quorum_floor),
+            block_prop_name(params.block_prop),
+            params.objective.name(),
+            params.attacker_knowledge.name(),
+            burst_name(params.burst),
+            corruption_name(params.corruption.as_ref()),
+            params.eviction_rate,
+            params.attack_resource,
+            params.rejoin_recovery,
+            params.rejoin_window,
+            params.pow_scaling.name(),
+            params.attack_start,
+            params.hold,
+            params.membership_lag,
+            join_cap_name(params.join_cap),
+            elders_name(params.elders),
+            elders_name(params.promotion_lag),
+            elder_tiebreak_name(params.elder_tiebreak),
+            name_grinding_name(params.name_grinding),
+            weighting_name(params.weighting.as_ref()),
+            age_cap_name(params.age_cap),
+            params.placement,
+            params.quorum_jitter,
+            params.participation,
+            params.max_steps,
+            params.repetitions,
+            combo_id(params))
+}
+
+/// Number of elders (or promotion lag) as written in the Elders (or
+/// PromotionLag) column: "-" for quorums without elders.
+pub fn elders_name(elders: Option<NN>) -> String {
+    elders.map_or("-".to_owned(), |e| e.to_string())
+}
+
+/// Elder tiebreak as written in the ElderTiebreak column: "-" for quorums
+/// without elders.
+pub fn elder_tiebreak_name(tiebreak: Option<ElderTiebreak>) -> String {
+    tiebreak.map_or("-".to_owned(), |t| t.name().to_owned())
+}
+
+/// Name grinding probability as written in the NameGrinding column: "-" for
+/// quorums without elders.
+pub fn name_grinding_name(grinding: Option<RR>) -> String {
+    grinding.map_or("-".to_owned(), |p| p.to_string())
+}
+
+/// Weighting as written in the Weight column: "-" for quorums without
+/// weights.
+pub fn weighting_name(weighting: Option<&Weighting>) -> String {
+    weighting.map_or("-".to_owned(), |w| w.to_string())
+}
+
+/// Age cap as written in the AgeCap column: "none" if not given.
+pub fn age_cap_name(age_cap: Option<AgeCap>) -> String {
+    age_cap.map_or("none".to_owned(), |c| c.to_string())
+}
+
+/// Quorum floor as written in the QuorumFloor column: "none" if not given.
+pub fn quorum_floor_name(quorum_floor: Option<NN>) -> String {
+    quorum_floor.map_or("none".to_owned(), |f| f.to_string())
+}
+
+/// Blocking proportion as written in the BlockProp column: "quorum" if not
+/// given (disruption when honest nodes lack a quorum).
+pub fn block_prop_name(block_prop: Option<RR>) -> String {
+    block_prop.map_or("quorum".to_owned(), |b| b.to_string())
+}
+
+/// Read parameter sets as CSV: a header line naming the columns (see
+/// `BATCH_COLUMNS`, in any order), then one parameter set per line. Blank lines
+/// are ignored.
+///
+/// Errors give the line number. Bad lines are skipped (with a message to
+/// stderr) if `skip_bad_lines` is true; otherwise the first is returned as an
+/// error. Errors in the header are always returned.
+pub fn read_params<R: BufRead>(input: R, skip_bad_lines: bool) -> Result<Vec<SimParams>, String> {
+    let mut lines = input.lines().enumerate();
+    let columns = match lines.next() {
+        Some((_, Ok(header))) => try!(parse_header(&header).map_err(|e| format!("line 1: {}", e))),
+        Some((_, Err(e))) => return Err(format!("line 1: {}", e)),
+        None => return Err("no header line".to_owned()),
+    };
+
+    let mut param_sets = Vec::new();
+    for (i, line) in lines {
+        let line_num = i + 1;
+        let result = line.map_err(|e| e.to_string()).and_then(|line| {
+            if line.trim().is_empty() {
+                Ok(None)
+            } else {
+                parse_line(&columns, &line).map(Some)
+            }
+        });
+        match result {
+            Ok(Some(params)) => param_sets.push(params),
+            Ok(None) => {}
+            Err(e) => {
+                if skip_bad_lines {
+                    warn!("skipping line {}: {}", line_num, e);
+                } else {
+                    return Err(format!("line {}: {}", line_num, e));
+                }
+            }
+        }
+    }
+    Ok(param_sets)
+}
+
+// Map column name to index, checking all names are known and required columns
+// are present.
+fn parse_header(header: &str) -> Result<HashMap<&'static str, usize>, String> {
+    let mut columns = HashMap::new();
+    for (i, name) in header.split(',').map(|s| s.trim()).enumerate() {
+        let column = match BATCH_COLUMNS.iter().find(|c| **c == name) {
+            Some(column) => *column,
+            None => return Err(format!("unknown column '{}'", name)),
+        };
+        if columns.insert(column, i).is_some() {
+            return Err(format!("repeated column '{}'", name));
+        }
+    }
+    for column in BATCH_COLUMNS.iter() {
+        if !columns.contains_key(column) && !OPTIONAL_COLUMNS.contains(column) {
+            return Err(format!("missing column '{}'", column));
+        }
+    }
+    Ok(columns)
+}
+
+fn parse_line(columns: &HashMap<&'static str, usize>, line: &str) -> Result<SimParams, String> {
+    let fields: Vec<&str> = line.split(',').map(|s| s.trim()).collect();
+    if fields.len() != columns.len() {
+        return Err(format!("expected {} fields, found {}", columns.len(), fields.len()));
+    }
+    let field = |column: &str| columns.get(column).map(|i| fields[*i]);
+    let required = |column: &str| field(column).expect("required column");
+    let bad = |column: &str| format!("bad value for {}: '{}'", column, required(column));
+
+    let sim_type = try!(SimType::from_name(required("Type")).ok_or_else(|| bad("Type")));
+    let quorum_type = try!(QuorumType::from_name(required("Quorum")).ok_or_else(|| bad("Quorum")));
+    let targetting = match required("Targetting") {
+        // The direct calculation's bound under simple targetting
+        "simp_bound" if sim_type == SimType::DirectCalc => AttackType::SimpleTargetted,
+        name => try!(AttackType::from_name(name).ok_or_else(|| bad("Targetting"))),
+    };
+    let target_prefix = match field("Prefix") {
+        None | Some("any") => None,
+        Some(s) => Some(try!(s.parse().map_err(|_| bad("Prefix")))),
+    };
+    let relocation = match field("Relocation") {
+        None => RelocationTarget::Random,
+        Some(s) => try!(RelocationTarget::from_name(s).ok_or_else(|| bad("Relocation"))),
+    };
+    let honest_uptime = match field("Uptime") {
+        None => UptimeDist::new(),
+        Some(s) => try!(s.parse().map_err(|e| format!("bad value for Uptime: {}", e))),
+    };
+    let churn_model = match field("ChurnModel") {
+        None => ChurnModel::Fixed,
+        Some(s) => try!(ChurnModel::from_name(s).ok_or_else(|| bad("ChurnModel"))),
+    };
+    let num_nodes: NN = try!(required("Nodes").parse().map_err(|_| bad("Nodes")));
+    let num_malicious: RelOrAbs = try!(required("Malicious")
+        .parse()
+        .map_err(|_| bad("Malicious")));
+    let min_group_size: NN = try!(required("MinGroup").parse().map_err(|_| bad("MinGroup")));
+    let quorum: QuorumSize = try!(required("QuorumSize").parse().map_err(|_| bad("QuorumSize")));
+    let quorum_basis = match field("QuorumBasis") {
+        None => QuorumBasis::Actual,
+        Some(s) => try!(QuorumBasis::from_name(s).ok_or_else(|| bad("QuorumBasis"))),
+    };
+    let quorum_floor = match field("QuorumFloor") {
+        None | Some("none") => None,
+        Some(s) => Some(try!(s.parse::<NN>().map_err(|_| bad("QuorumFloor")))),
+    };
+    let block_prop = match field("BlockProp") {
+        None | Some("quorum") => None,
+        Some(s) => Some(try!(s.parse::<RR>().map_err(|_| bad("BlockProp")))),
+    };
+    let objective = match field("Objective") {
+        None => Objective::Compromise,
+        Some(s) => try!(Objective::from_name(s).ok_or_else(|| bad("Objective"))),
+    };
+    let attacker_knowledge = match field("Knowledge") {
+        None => AttackerKnowledge::Full,
+        Some(s) => try!(AttackerKnowledge::from_name(s).ok_or_else(|| bad("Knowledge"))),
+    };
+    let burst = match field("Burst") {
+        None | Some("none") => None,
+        Some(s) => Some(try!(s.parse().map_err(|e| format!("bad value for Burst: {}", e)))),
+    };
+    let corruption = match field("Corrupt") {
+        None | Some("none") => None,
+        Some(s) => Some(try!(s.parse().map_err(|e| format!("bad value for Corrupt: {}", e)))),
+    };
+    let eviction_rate: RR = match field("EvictionRate") {
+        None => 0.0,
+        Some(s) => try!(s.parse().map_err(|_| bad("EvictionRate"))),
+    };
+    let attack_resource: RR = match field("AttackResource") {
+        None => 1.0,
+        Some(s) => try!(s.parse().map_err(|_| bad("AttackResource"))),
+    };
+    let rejoin_recovery: RR = match field("RejoinRecovery") {
+        None => 0.0,
+        Some(s) => try!(s.parse().map_err(|_| bad("RejoinRecovery"))),
+    };
+    let rejoin_window: NN = match field("RejoinWindow") {
+        None => DEFAULT_REJOIN_WINDOW,
+        Some(s) => try!(s.parse().map_err(|_| bad("RejoinWindow"))),
+    };
+    let pow_scaling = match field("PowScaling") {
+        None => PowScaling::Constant,
+        Some(s) => try!(PowScaling::from_name(s).ok_or_else(|| bad("PowScaling"))),
+    };
+    let attack_start: NN = match field("AttackStart") {
+        None => 0,
+        Some(s) => try!(s.parse().map_err(|_| bad("AttackStart"))),
+    };
+    let hold: NN = match field("Hold") {
+        None => 1,
+        Some(s) => try!(s.parse().map_err(|_| bad("Hold"))),
+    };
+    let membership_lag: NN = match field("MembershipLag") {
+        None => 0,
+        Some(s) => try!(s.parse().map_err(|_| bad("MembershipLag"))),
+    };
+    let join_cap = match field("JoinCap") {
+        None | Some("none") => None,
+        Some(s) => Some(try!(s.parse().map_err(|e| format!("bad value for JoinCap: {}", e)))),
+    };
+    let elders = match (quorum_type, field("Elders")) {
+        (QuorumType::Elder, None) |
+        (QuorumType::Elder, Some("-")) => Some(DEFAULT_ELDERS as NN),
+        (QuorumType::Elder, Some(s)) => Some(try!(s.parse().map_err(|_| bad("Elders")))),
+        (_, None) | (_, Some("-")) => None,
+        (_, Some(_)) => return Err("Elders applies to the elder quorum only".to_owned()),
+    };
+    let promotion_lag = match (quorum_type, field("PromotionLag")) {
+        (QuorumType::Elder, None) |
+        (QuorumType::Elder, Some("-")) => Some(0),
+        (QuorumType::Elder, Some(s)) => Some(try!(s.parse().map_err(|_| bad("PromotionLag")))),
+        (_, None) | (_, Some("-")) => None,
+        (_, Some(_)) => return Err("PromotionLag applies to the elder quorum only".to_owned()),
+    };
+    let elder_tiebreak = match (quorum_type, field("ElderTiebreak")) {
+        (QuorumType::Elder, None) |
+        (QuorumType::Elder, Some("-")) => Some(ElderTiebreak::Name),
+        (QuorumType::Elder, Some(s)) => {
+            Some(try!(ElderTiebreak::from_name(s).ok_or_else(|| bad("ElderTiebreak"))))
+        }
+        (_, None) | (_, Some("-")) => None,
+        (_, Some(_)) => return Err("ElderTiebreak applies to the elder quorum only".to_owned()),
+    };
+    let name_grinding = match (quorum_type, field("NameGrinding")) {
+        (QuorumType::Elder, None) |
+        (QuorumType::Elder, Some("-")) => Some(0.0),
+        (QuorumType::Elder, Some(s)) => Some(try!(s.parse().map_err(|_| bad("NameGrinding")))),
+        (_, None) | (_, Some("-")) => None,
+        (_, Some(_)) => return Err("NameGrinding applies to the elder quorum only".to_owned()),
+    };
+    let weighting = match (quorum_type, field("Weight")) {
+        (QuorumType::Age, None) |
+        (QuorumType::Age, Some("-")) => Some(Weighting::Age),
+        (QuorumType::Age, Some(s)) => {
+            Some(try!(s.parse().map_err(|e| format!("bad value for Weight: {}", e))))
+        }
+        (_, None) | (_, Some("-")) => None,
+        (_, Some(_)) => return Err("Weight applies to the age quorum only".to_owned()),
+    };
+    let age_cap = match field("AgeCap") {
+        None | Some("none") => None,
+        Some(s) => Some(try!(s.parse().map_err(|e| format!("bad value for AgeCap: {}", e)))),
+    };
+    let placement = match field("Placement") {
+        None => InitialPlacement::Uniform,
+        Some(s) => try!(s.parse().map_err(|e| format!("bad value for Placement: {}", e))),
+    };
+    let quorum_jitter: RR = match field("QuorumJitter") {
+        None => 0.0,
+        Some(s) => try!(s.parse().map_err(|_| bad("QuorumJitter"))),
+    };
+    let participation: RR = match field("Participation") {
+        None => 1.0,
+        Some(s) => try!(s.parse().map_err(|_| bad("Participation"))),
+    };
+    let max_steps: NN = try!(required("Steps").parse().map_err(|_| bad("Steps")));
+    let repetitions: NN = try!(required("Repetitions").parse().map_err(|_| bad("Repetitions")));
+
+    let params = SimParams {
+        sim_type: sim_type,
+        quorum_type: quorum_type,
+        elders: elders,
+        promotion_lag: promotion_lag,
+        elder_tiebreak: elder_tiebreak,
+        name_grinding: name_grinding,
+        weighting: weighting,
+        age_cap: age_cap,
+        targetting: targetting,
+        objective: objective,
+        attacker_knowledge: attacker_knowledge,
+        target_prefix: target_prefix,
+        relocation: relocation,
+        placement: placement,
+        quorum_jitter: quorum_jitter,
+        honest_uptime: honest_uptime,
+        churn_model: churn_model,
+        burst: burst,
+        corruption: corruption,
+        eviction_rate: eviction_rate,
+        attack_resource: attack_resource,
+        rejoin_recovery: rejoin_recovery,
+        rejoin_window: rejoin_window,
+        pow_scaling: pow_scaling,
+        attack_start: attack_start,
+        hold: hold,
+        steady_state: None,
+        membership_lag: membership_lag,
+        join_cap: join_cap,
+        paranoid: None,
+        verify: false,
+        split_check: None,
+        record_attack: false,
+        replay_attack: None,
+        rng_audit: false,
+        time_limit: None,
+        num_nodes: num_nodes,
+        num_malicious: num_malicious,
+        min_group_size: min_group_size,
+        quorum: quorum,
+        quorum_basis: quorum_basis,
+        quorum_floor: quorum_floor,
+        block_prop: block_prop,
+        participation: participation,
+        max_steps: max_steps,
+        repetitions: repetitions,
+        malice_hist: false,
+        spread: false,
+        address_coverage: false,
+        close_group: None,
+        elders_report: false,
+        any_group_model: AnyGroupModel::Independent,
+        compare_models: false,
+        correction: false,
+        age_share_interval: None,
+        liveness: false,
+        outcomes: false,
+        termination: false,
+        governance: false,
+        turnover: false,
+        checkpoint: None,
+        seeding: Seeding::new(),
+        master_seed: None,
+        prob_cache: Arc::new(ProbCache::new()),
+        interrupt: Interrupt::new(),
+    };
+    try!(validate(&params));
+    Ok(params)
+}
+
+/// Check a parameter set is usable (the checks made by `ToolArgs`, and that
+/// only the full simulation is given full simulation parameters).
+pub fn validate(params: &SimParams) -> Result<(), String> {
+    if params.min_group_size == 0 {
+        return Err("MinGroup must be at least 1".to_owned());
+    }
+    if params.num_malicious.from_base(params.num_nodes) > params.num_nodes {
+        return Err("more malicious nodes than nodes".to_owned());
+    }
+    match params.quorum {
+        QuorumSize::Proportion(prop) if prop < 0.0 || prop > 1.0 => {
+            return Err(format!("quorum proportion {} outside range 0-1", prop));
+        }
+        QuorumSize::Count(n) if n > params.min_group_size => {
+            return Err(format!("quorum count {} exceeds min group size {}",
+                               n,
+                               params.min_group_size));
+        }
+        _ => {}
+    }
+    if let Some(block) = params.block_prop {
+        if block < 0.0 || block > 1.0 {
+            return Err(format!("blocking proportion {} outside range 0-1", block));
+        }
+    }
+    try!(params.targetting.check_steps(params.max_steps));
+    if let Some(elders) = params.elders {
+        if elders == 0 || elders > params.min_group_size {
+            return Err(format!("Elders {} must be from 1 to MinGroup {}",
+                               elders,
+                               params.min_group_size));
+        }
+    }
+    if let Some(grinding) = params.name_grinding {
+        if grinding < 0.0 || grinding > 1.0 {
+            return Err(format!("NameGrinding {} outside range 0-1", grinding));
+        }
+        if grinding > 0.0 && params.elder_tiebreak != Some(ElderTiebreak::Name) {
+            return Err("NameGrinding requires ElderTiebreak name".to_owned());
+        }
+    }
+    if let Some(floor) = params.quorum_floor {
+        // Of elders, the floor can be no more than the number voting
+        let voters = params.elders.unwrap_or(params.min_group_size);
+        if floor == 0 || floor > voters {
+            return Err(format!("QuorumFloor {} must be from 1 to {} ({})",
+                               floor,
+                               voters,
+                               if params.elders.is_some() {
+                                   "Elders"
+                               } else {
+                                   "MinGroup"
+                               }));
+        }
+    }
+    if let Some(cap) = params.age_cap {
+        match params.quorum_type {
+            QuorumType::Age if params.weighting == Some(Weighting::Age) => {}
+            QuorumType::Age => return Err("AgeCap requires Weight age".to_owned()),
+            QuorumType::Elder if cap.ordering => {}
+            _ => {
+                return Err("AgeCap applies to the age quorum, or the elder quorum with \
+                            +ordering, only"
+                    .to_owned())
+            }
+        }
+    }
+    if params.hold == 0 {
+        return Err("Hold must be at least 1".to_owned());
+    }
+    if params.eviction_rate < 0.0 || params.eviction_rate > 1.0 {
+        return Err(format!("EvictionRate {} outside range 0-1", params.eviction_rate));
+    }
+    if !(params.attack_resource > 0.0) {
+        return Err(format!("AttackResource {} must be positive", params.attack_resource));
+    }
+    if params.rejoin_recovery < 0.0 || params.rejoin_recovery > 1.0 {
+        return Err(format!("RejoinRecovery {} outside range 0-1", params.rejoin_recovery));
+    }
+    if params.attack_start > 0 && params.attack_start >= params.max_steps {
+        return Err(format!("AttackStart {} must be below Steps {}",
+                           params.attack_start,
+                           params.max_steps));
+    }
+    if let Some(ref corruption) = params.corruption {
+        if corruption.last_step() >= params.max_steps {
+            return Err(format!("Corrupt {} has a step not below Steps {}",
+                               corruption,
+                               params.max_steps));
+        }
+    }
+    if params.repetitions == 0 && params.sim_type != SimType::DirectCalc {
+        return Err(format!("Repetitions 0: {} simulates at least one (for the analytic result \
+                            alone, use dir_calc)",
+                           params.sim_type.name()));
+    }
+    if params.sim_type == SimType::DirectCalc && params.placement != InitialPlacement::Uniform {
+        return Err("Placement applies to structure and full_sim only".to_owned());
+    }
+    if params.quorum_jitter < 0.0 || params.quorum_jitter > 1.0 {
+        return Err(format!("QuorumJitter {} outside range 0-1", params.quorum_jitter));
+    }
+    if !(params.participation > 0.0 && params.participation <= 1.0) {
+        return Err(format!("Participation {} must be above 0 and at most 1",
+                           params.participation));
+    }
+    if params.sim_type == SimType::DirectCalc && params.quorum_jitter != 0.0 {
+        return Err("QuorumJitter applies to structure and full_sim only".to_owned());
+    }
+    match params.sim_type {
+        SimType::FullSim => {}
+        _ => {
+            if params.quorum_type != QuorumType::Simple {
+                return Err("Quorum applies to full_sim only".to_owned());
+            }
+            if params.targetting != AttackType::Untargetted && !params.targetted_bound() {
+                return Err("Targetting applies to full_sim only (or simple to dir_calc, as an \
+                            upper bound)"
+                    .to_owned());
+            }
+            if params.target_prefix.is_some() {
+                return Err("Prefix applies to full_sim only".to_owned());
+            }
+            if params.relocation != RelocationTarget::Random {
+                return Err("Relocation applies to full_sim only".to_owned());
+            }
+            if params.honest_uptime != UptimeDist::new() {
+                return Err("Uptime applies to full_sim only".to_owned());
+            }
+            if params.churn_model != ChurnModel::Fixed {
+                return Err("ChurnModel applies to full_sim only".to_owned());
+            }
+            if params.objective != Objective::Compromise {
+                return Err("Objective applies to full_sim only".to_owned());
+            }
+            if params.attacker_knowledge != AttackerKnowledge::Full {
+                return Err("Knowledge applies to full_sim only".to_owned());
+            }
+            if params.burst.is_some() {
+                return Err("Burst applies to full_sim only".to_owned());
+            }
+            if params.corruption.is_some() {
+                return Err("Corrupt applies to full_sim only".to_owned());
+            }
+            if params.eviction_rate != 0.0 {
+                return Err("EvictionRate applies to full_sim only".to_owned());
+            }
+            if params.attack_resource != 1.0 {
+                return Err("AttackResource applies to full_sim only".to_owned());
+            }
+            if params.rejoin_recovery != 0.0 {
+                return Err("RejoinRecovery applies to full_sim only".to_owned());
+            }
+            if params.rejoin_window != DEFAULT_REJOIN_WINDOW {
+                return Err("RejoinWindow applies to full_sim only".to_owned());
+            }
+            if params.pow_scaling != PowScaling::Constant {
+                return Err("PowScaling applies to full_sim only".to_owned());
+            }
+            if params.attack_start != 0 {
+                return Err("AttackStart applies to full_sim only".to_owned());
+            }
+            if params.hold != 1 {
+                return Err("Hold applies to full_sim only".to_owned());
+            }
+            if params.membership_lag != 0 {
+                return Err("MembershipLag applies to full_sim only".to_owned());
+            }
+            if params.join_cap.is_some() {
+                return Err("JoinCap applies to full_sim only".to_owned());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+fn params_csv(param_sets: &[SimParams]) -> String {
+    let mut csv = Vec::new();
+    write_params(param_sets, &mut csv).expect("write to Vec");
+    String::from_utf8(csv).expect("UTF-8")
+}
+
+#[test]
+fn test_batch_round_trip() {
+    use super::args::sim_params;
+
+    for args in &[&["calc", "-n", "100-300:100", "-r", "5%-15%:5%", "-q", "0.5-0.7:0.1",
+                    "--model", "all", "--participation", "0.8,1"][..],
+                  &["full", "-k", "8,10", "-q", "4a-5a", "-Q", "all", "-T", "all",
+                    "--target-prefix", "01", "--relocation-target", "all",
+                    "--honest-uptime", "uniform(0.2,0.9)", "--churn-model", "poisson",
+                    "--block-prop", "0.3-0.4:0.1", "--quorum-basis", "all",
+                    "--quorum-floor", "3,4", "--objective", "all",
+                    "--attacker-knowledge", "all", "--burst", "0.1,0.25",
+                    "--corrupt", "0.1@0,0.05@10",
+                    "--eviction-rate", "0.01", "--attack-resource", "0.25",
+                    "--rejoin-age-recovery", "0.5", "--rejoin-window", "20",
+                    "--pow-scaling", "linear-n", "--attack-start", "0,10", "--hold", "2",
+                    "--membership-lag", "3", "--elders", "5,7", "--promotion-lag", "2",
+                    "--name-grinding", "0.5", "--join-cap", "2:10", "--join-cap-mode", "queue",
+                    "--weight", "capacity(1@0.9,10@0.1)", "-s", "20",
+                    "-p", "3"][..],
+                  &["full", "-T", "untargetted:5+simple", "-s", "20", "-p", "3"][..],
+                  &["full", "-Q", "elder", "--elder-tiebreak", "random-per-decision", "-s", "20",
+                    "-p", "3"][..],
+                  &["full", "-Q", "all", "--age-cap", "4,8", "--cap-ordering", "-s", "20",
+                    "-p", "3"][..],
+                  &["structure", "--initial-placement", "concentrated(0.25)",
+                    "--quorum-jitter", "0.05", "--participation", "0.9", "-p", "3"][..]] {
+        let csv = params_csv(&sim_params(args));
+        let param_sets = read_params(csv.as_bytes(), false).unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(params_csv(&param_sets), csv);
+    }
+}
+
+#[test]
+fn test_batch_bad_lines() {
+    use super::logging;
+    use log::LogLevelFilter;
+
+    let input = "Nodes,Malicious,MinGroup,QuorumSize,Type,Quorum,Targetting,Steps,Repetitions
+1000,100,10,0.5,dir_calc,simple,untarg.,0,0
+
+1000,100,10,11a,dir_calc,simple,untarg.,0,0
+1000,100,10,5a,full_sim,age,simp_targ,10,2
+1000,100,10,5a,dir_calc,age,untarg.,10,2
+";
+    assert_eq!(read_params(input.as_bytes(), false).err(),
+               Some("line 4: quorum count 11 exceeds min group size 10".to_owned()));
+    let param_sets = read_params(input.as_bytes(), true).expect("skipped bad lines");
+    assert_eq!(param_sets.len(), 2);
+    // Skipped lines are logged as warnings
+    let skip = || {
+        let _ = read_params(input.as_bytes(), true);
+    };
+    let lines = logging::capture(LogLevelFilter::Warn, &skip);
+    assert!(lines.contains("WARN  routing_sims::batch: skipping line 4: quorum count 11 exceeds \
+                            min group size 10\n"),
+            "{}",
+            lines);
+    assert_eq!(logging::capture(LogLevelFilter::Error, &skip), "");
+    assert_eq!(param_sets[1].quorum, QuorumSize::Count(5));
+    assert_eq!(param_sets[1].relocation, RelocationTarget::Random);
+    assert_eq!(param_sets[1].churn_model, ChurnModel::Fixed);
+
+    let no_reps = "Nodes,Malicious,MinGroup,QuorumSize,Type,Quorum,Targetting,Steps,Repetitions
+1000,100,10,0.5,structure,simple,untarg.,0,0
+";
+    assert!(read_params(no_reps.as_bytes(), false).is_err());
+    let floor = "Nodes,Malicious,MinGroup,QuorumSize,QuorumFloor,Type,Quorum,Targetting,Elders,\
+                 Steps,Repetitions
+1000,100,10,0.5,6,full_sim,simple,untarg.,-,10,2
+1000,100,10,0.5,6,full_sim,elder,untarg.,5,10,2
+";
+    assert_eq!(read_params(floor.as_bytes(), false).err(),
+               Some("line 3: QuorumFloor 6 must be from 1 to 5 (Elders)".to_owned()));
+    assert!(read_params("Nodes,Malicious\n".as_bytes(), true).is_err());
+    assert!(read_params("Nodes,Bogus\n".as_bytes(), true).is_err());
+}
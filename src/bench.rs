@@ -0,0 +1,222 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Benchmark: fixed workloads of each tool, timed for performance tracking
+//!
+//! The workloads and their seed are compiled in, so that timings of different
+//! versions are comparable. Changing them breaks that comparison: add new
+//! workloads rather than changing these.
+
+use super::{ToolArgs, NN, RR};
+use super::args::SimType;
+use super::estimate::node_steps;
+use super::quorum::{QuorumSize, SimpleQuorum, UntargettedAttack};
+use super::tools::{Tool, DirectCalcTool, SimStructureTool, FullSimTool};
+use super::model::MODEL_VERSION;
+
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+
+/// Master seed of every workload
+pub const SEED: u64 = 1;
+
+/// Size of a workload
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tier {
+    Small,
+    Medium,
+    Large,
+}
+
+impl Tier {
+    pub fn name(self) -> &'static str {
+        match self {
+            Tier::Small => "small",
+            Tier::Medium => "medium",
+            Tier::Large => "large",
+        }
+    }
+}
+
+impl Display for Tier {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Tier {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [Tier::Small, Tier::Medium, Tier::Large]
+            .iter()
+            .cloned()
+            .find(|tier| tier.name() == s)
+            .ok_or_else(|| format!("unknown tier '{}' (expected small, medium or large)", s))
+    }
+}
+
+/// One workload: a tool and its parameters. The quorum is simple, with size
+/// half the group; the full simulation attack is untargetted.
+pub struct Workload {
+    pub tier: Tier,
+    pub sim_type: SimType,
+    pub num_nodes: NN,
+    pub num_malicious: NN,
+    pub min_group_size: NN,
+    pub max_steps: NN,
+    pub repetitions: NN,
+}
+
+/// All workloads, by tool and then tier
+#[cfg_attr(rustfmt, rustfmt_skip)]
+pub const WORKLOADS: [Workload; 9] = [
+    Workload { tier: Tier::Small, sim_type: SimType::DirectCalc, num_nodes: 1000,
+               num_malicious: 100, min_group_size: 8, max_steps: 0, repetitions: 1 },
+    Workload { tier: Tier::Medium, sim_type: SimType::DirectCalc, num_nodes: 10000,
+               num_malicious: 1000, min_group_size: 8, max_steps: 0, repetitions: 1 },
+    Workload { tier: Tier::Large, sim_type: SimType::DirectCalc, num_nodes: 100000,
+               num_malicious: 10000, min_group_size: 8, max_steps: 0, repetitions: 1 },
+    Workload { tier: Tier::Small, sim_type: SimType::Structure, num_nodes: 1000,
+               num_malicious: 100, min_group_size: 8, max_steps: 0, repetitions: 1 },
+    Workload { tier: Tier::Medium, sim_type: SimType::Structure, num_nodes: 10000,
+               num_malicious: 1000, min_group_size: 8, max_steps: 0, repetitions: 1 },
+    Workload { tier: Tier::Large, sim_type: SimType::Structure, num_nodes: 100000,
+               num_malicious: 10000, min_group_size: 8, max_steps: 0, repetitions: 1 },
+    Workload { tier: Tier::Small, sim_type: SimType::FullSim, num_nodes: 200,
+               num_malicious: 20, min_group_size: 8, max_steps: 50, repetitions: 2 },
+    Workload { tier: Tier::Medium, sim_type: SimType::FullSim, num_nodes: 1000,
+               num_malicious: 100, min_group_size: 8, max_steps: 100, repetitions: 5 },
+    Workload { tier: Tier::Large, sim_type: SimType::FullSim, num_nodes: 5000,
+               num_malicious: 500, min_group_size: 8, max_steps: 200, repetitions: 10 },
+];
+
+impl Workload {
+    /// Name for reports, e.g. `full/small`
+    pub fn name(&self) -> String {
+        format!("{}/{}", self.sim_type.command(), self.tier)
+    }
+
+    /// Work done, in node-steps (see `estimate::work_units`)
+    pub fn node_steps(&self) -> RR {
+        node_steps(self.sim_type,
+                   self.num_nodes,
+                   self.max_steps,
+                   self.repetitions)
+    }
+
+    /// Run the workload once, timing it.
+    pub fn run(&self) -> Measurement {
+        let mut args = ToolArgs::new(self.num_nodes,
+                                     self.num_malicious,
+                                     self.min_group_size,
+                                     QuorumSize::Proportion(0.5),
+                                     self.max_steps,
+                                     self.repetitions);
+        args.seeding.seed = Some(SEED);
+        let tool: Box<Tool> = match self.sim_type {
+            SimType::DirectCalc => Box::new(DirectCalcTool::new(args)),
+            SimType::Structure => Box::new(SimStructureTool::new(args)),
+            SimType::FullSim => {
+                Box::new(FullSimTool::new(args, SimpleQuorum::new(), UntargettedAttack {}))
+            }
+        };
+        let (result, secs) = tool.timed_calc();
+        Measurement {
+            name: self.name(),
+            secs: secs,
+            node_steps: self.node_steps(),
+            p_compromise: result.p_compromise,
+        }
+    }
+}
+
+/// Timing of one workload
+#[derive(Debug)]
+pub struct Measurement {
+    pub name: String,
+    /// Wall time, in seconds
+    pub secs: RR,
+    pub node_steps: RR,
+    /// Result of the workload, which should only change with the tool's model
+    pub p_compromise: RR,
+}
+
+impl Measurement {
+    /// Node-steps simulated per second (`None` for direct calculation, which
+    /// simulates none)
+    pub fn node_steps_per_sec(&self) -> Option<RR> {
+        if self.node_steps > 0.0 && self.secs > 0.0 {
+            Some(self.node_steps / self.secs)
+        } else {
+            None
+        }
+    }
+}
+
+/// Workloads of the given tier (all if `None`)
+pub fn workloads(tier: Option<Tier>) -> Vec<&'static Workload> {
+    WORKLOADS.iter().filter(|workload| tier.map_or(true, |tier| workload.tier == tier)).collect()
+}
+
+/// Run the workloads of the given tier (all if `None`), one at a time.
+pub fn run(tier: Option<Tier>) -> Vec<Measurement> {
+    workloads(tier).into_iter().map(|workload| workload.run()).collect()
+}
+
+/// Measurements as JSON: the version, the model version, the seed and an
+/// object per workload.
+pub fn to_json(measurements: &[Measurement]) -> String {
+    let workloads: Vec<String> = measurements.iter()
+        .map(|m| {
+            let rate = m.node_steps_per_sec().map_or("null".to_owned(), |r| format!("{:.1}", r));
+            format!("    {{\"name\": \"{}\", \"secs\": {:.6}, \"node_steps\": {}, \
+                     \"node_steps_per_sec\": {}, \"p_compromise\": {}}}",
+                    m.name,
+                    m.secs,
+                    m.node_steps,
+                    rate,
+                    m.p_compromise)
+        })
+        .collect();
+    format!("{{\n  \"version\": \"{}\",\n  \"model_version\": {},\n  \"seed\": {},\n  \
+             \"workloads\": [\n{}\n  ]\n}}",
+            env!("CARGO_PKG_VERSION"),
+            MODEL_VERSION,
+            SEED,
+            workloads.join(",\n"))
+}
+
+#[test]
+fn test_bench_small() {
+    // Generous: the small tier takes well under a second in release builds
+    const LIMIT_SECS: RR = 120.0;
+    let measurements = run(Some(Tier::Small));
+    let names: Vec<&str> = measurements.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(names, vec!["calc/small", "structure/small", "full/small"]);
+    let total: RR = measurements.iter().map(|m| m.secs).sum();
+    assert!(total < LIMIT_SECS, "small tier took {} s", total);
+    assert_eq!(measurements[0].node_steps_per_sec(), None);
+    assert_eq!(measurements[2].node_steps, 20000.0);
+
+    let json = to_json(&measurements);
+    assert!(json.contains("\"name\": \"full/small\""));
+    assert!(json.contains("\"node_steps_per_sec\": null"));
+    assert!(json.contains(&format!("\"model_version\": {},", MODEL_VERSION)));
+    assert_eq!("medium".parse::<Tier>(), Ok(Tier::Medium));
+    assert!("huge".parse::<Tier>().is_err());
+}
@@ -0,0 +1,138 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Correlated failure bursts (region outages)
+
+use super::RR;
+use super::sim::{Group, NodeName};
+
+use std::fmt::{self, Formatter};
+use std::str::FromStr;
+
+use rand::Rng;
+
+
+/// Number of regions honest nodes are assigned to on joining
+pub const REGIONS: u32 = 16;
+
+/// Draw the region of a joining node from `rng`.
+pub fn sample_region<R: Rng>(rng: &mut R) -> u32 {
+    rng.gen_range(0, REGIONS)
+}
+
+/// Bursts of correlated failures: at each step of the attack, with
+/// probability `prob` (or a Poisson number of times with mean `prob`, under
+/// `ChurnModel::Poisson`), the proportion `fraction` of honest nodes fail, taken
+/// region by region (see `Burst::victims`), as when a datacentre or ISP drops
+/// out. Failed nodes are down for the rest of the step, then leave the
+/// network.
+///
+/// Written (and parsed) as `P,F`; since output is comma separated, `Display`
+/// uses `;` instead of `,`, and either is accepted when parsing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Burst {
+    pub prob: RR,
+    pub fraction: RR,
+}
+
+impl Burst {
+    /// Honest nodes lost in one burst: starting from a region drawn from `rng`,
+    /// whole regions in turn (nodes of a region in name order) until `fraction`
+    /// of all honest nodes (rounded to the nearest node) are taken.
+    pub fn victims<'a, I, R>(&self, groups: I, rng: &mut R) -> Vec<NodeName>
+        where I: IntoIterator<Item = &'a Group>,
+              R: Rng
+    {
+        let start = sample_region(rng);
+        let mut honest: Vec<(u32, NodeName)> = groups.into_iter()
+            .flat_map(|group| group.iter())
+            .filter(|&(_, data)| !data.is_malicious())
+            .map(|(name, data)| ((data.region() + REGIONS - start) % REGIONS, *name))
+            .collect();
+        honest.sort();
+        let count = (self.fraction * honest.len() as RR).round() as usize;
+        honest.into_iter().take(count).map(|(_, name)| name).collect()
+    }
+}
+
+/// Burst as written in the Burst column: "none" if not given.
+pub fn burst_name(burst: Option<Burst>) -> String {
+    burst.map_or("none".to_owned(), |b| b.to_string())
+}
+
+impl fmt::Display for Burst {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.pad(&format!("{};{}", self.prob, self.fraction))
+    }
+}
+
+impl FromStr for Burst {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let separators: &[char] = &[',', ';'];
+        let parts: Vec<&str> = s.trim().split(separators).collect();
+        if parts.len() != 2 {
+            return Err(format!("expected P,F, found '{}'", s.trim()));
+        }
+        let mut values = [0.0; 2];
+        for (value, part) in values.iter_mut().zip(&parts) {
+            *value = match part.trim().parse::<RR>() {
+                Ok(x) if x >= 0.0 && x <= 1.0 => x,
+                Ok(_) => return Err(format!("{} outside range 0-1", part.trim())),
+                Err(_) => return Err(format!("bad number '{}'", part.trim())),
+            };
+        }
+        Ok(Burst {
+            prob: values[0],
+            fraction: values[1],
+        })
+    }
+}
+
+#[test]
+fn test_burst() {
+    use super::sim::NodeData;
+    use rand::{SeedableRng, XorShiftRng};
+
+    let burst: Burst = "1,0.5".parse().expect("parse");
+    assert_eq!(burst, Burst { prob: 1.0, fraction: 0.5 });
+    assert_eq!(burst.to_string().parse(), Ok(burst));
+    assert_eq!(burst_name(None), "none");
+    assert_eq!("0.1,2".parse::<Burst>(), Err("2 outside range 0-1".to_owned()));
+    assert_eq!("0.1".parse::<Burst>(), Err("expected P,F, found '0.1'".to_owned()));
+
+    // Nodes 0-9 in regions 0-9 (one malicious) and 10-19 in the same regions
+    let mut group = Group::new();
+    for name in 0..20 {
+        let mut data = if name == 3 {
+            NodeData::new_malicious()
+        } else {
+            NodeData::new()
+        };
+        data.set_region(name as u32 % 10);
+        group.insert(name, data);
+    }
+    let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+    let victims = burst.victims(Some(&group), &mut rng);
+    assert_eq!(victims.len(), 10);   // half of the 19 honest nodes
+    assert!(!victims.contains(&3));
+    // Regions are lost whole, except perhaps the last
+    let split = (0..10)
+        .filter(|&name| name != 3 && victims.contains(&name) != victims.contains(&(name + 10)))
+        .count();
+    assert!(split <= 1, "{:?}", victims);
+}
@@ -0,0 +1,434 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Cache of the results of parameter sets (see `--cache`), so that the output
+//! of a run can be reworked without running its simulations again
+//!
+//! Each result is a small JSON file named by the combination id of its set and
+//! a hash of the full description of its runs (see `SimParams::run_description`),
+//! holding the model version and the description, which are checked on
+//! loading. Only the probabilities, group counts and runs are cached, so sets
+//! requesting reports are always run.
+
+use super::{NN, RR};
+use super::args::{SimParams, SimType};
+use super::tools::SimResult;
+use super::checkpoint::fnv1a;
+use super::combo::combo_id;
+use super::duration::parse_duration;
+use super::model::MODEL_VERSION;
+use super::output::PartialFile;
+
+use docopt::{self, Docopt};
+use rustc_serialize::json::Json;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const USAGE: &'static str = "
+Manage a cache of results written by --cache.
+
+Usage:
+    routing-sims cache prune DIR --older-than AGE
+    routing-sims cache (-h | --help)
+
+Options:
+    -h --help   Show this message
+    --older-than AGE
+                Remove the results in DIR written at least AGE ago, e.g. 12h
+                or 30d.
+";
+
+#[derive(RustcDecodable)]
+#[allow(non_snake_case)]
+struct Args {
+    arg_DIR: String,
+    flag_older_than: String,
+}
+
+/// Extension of the files of cached results
+pub const EXTENSION: &'static str = "json";
+
+/// Directory of cached results, and whether to ignore those already in it.
+#[derive(Clone, Debug)]
+pub struct ResultCache {
+    dir: PathBuf,
+    refresh: bool,
+}
+
+impl ResultCache {
+    /// Cache in `dir`. With `refresh`, nothing is loaded from it, but results
+    /// are still saved (replacing those there).
+    pub fn new(dir: PathBuf, refresh: bool) -> Self {
+        ResultCache {
+            dir: dir,
+            refresh: refresh,
+        }
+    }
+
+    /// Path of the file holding the result of `params`
+    pub fn path(&self, params: &SimParams) -> PathBuf {
+        let name = format!("{}-{:016x}.{}",
+                           combo_id(params),
+                           fnv1a(&params.run_description()),
+                           EXTENSION);
+        self.dir.join(name)
+    }
+
+    /// The cached result of `params`, if there is one of this version of the
+    /// model (and the cache is not being refreshed). A file which cannot be
+    /// read is ignored with a warning, and replaced once the set is run.
+    pub fn load(&self, params: &SimParams) -> Option<SimResult> {
+        if self.refresh || !cacheable(params) {
+            return None;
+        }
+        let path = self.path(params);
+        let mut contents = String::new();
+        match File::open(&path).and_then(|mut file| file.read_to_string(&mut contents)) {
+            Ok(_) => {}
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                warn!("ignoring cached result {}: {}", path.display(), e);
+                return None;
+            }
+        }
+        match from_json(&contents, &params.run_description()) {
+            Ok(result) => result,
+            Err(e) => {
+                warn!("ignoring cached result {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Save the result of `params`, unless it may not be cached: it requests
+    /// reports, is not reproducible (a simulation without `--seed`), or was
+    /// stopped before all its repetitions were run. Failure to save is logged.
+    pub fn save(&self, params: &SimParams, result: &SimResult) {
+        if !cacheable(params) || result.truncated ||
+           result.runs.map_or(false, |runs| runs != params.repetitions) {
+            return;
+        }
+        let path = self.path(params);
+        let json = to_json(result, &params.run_description());
+        let saved = PartialFile::create(&path)
+            .and_then(|mut file| file.write_all(json.as_bytes()).map(|_| file))
+            .and_then(PartialFile::finish);
+        if let Err(e) = saved {
+            warn!("unable to save result to cache {}: {}", path.display(), e);
+        }
+    }
+}
+
+// Whether the result of `params` may be cached: one calculated, or simulated
+// from a given seed, requesting none of the reports the cache leaves out.
+fn cacheable(params: &SimParams) -> bool {
+    (params.sim_type == SimType::DirectCalc || params.seeding.seed.is_some()) &&
+    !params.malice_hist && !params.spread && !params.address_coverage &&
+    params.close_group.is_none() && !params.elders_report && !params.compare_models &&
+    !params.correction && params.age_share_interval.is_none() && !params.liveness &&
+    !params.termination && !params.governance && !params.turnover && !params.outcomes &&
+    params.steady_state.is_none() && !params.record_attack && !params.rng_audit &&
+    params.split_check.is_none()
+}
+
+// An optional value as JSON
+fn json_option<T: ToString>(value: Option<T>) -> String {
+    value.map_or("null".to_owned(), |value| value.to_string())
+}
+
+// A real value as JSON: a string of its Display, which `str::parse` reads back
+// exactly (unlike the JSON parser, whose numbers may differ in the last digit)
+fn json_real(x: RR) -> String {
+    Json::String(x.to_string()).to_string()
+}
+
+// The cached part of `result` as JSON, for the runs of `description`
+fn to_json(result: &SimResult, description: &str) -> String {
+    format!("{{\n  \"model_version\": {},\n  \"description\": {},\n  \"p_disrupt\": {},\n  \
+             \"p_compromise\": {},\n  \"groups\": {},\n  \"groups_min\": {},\n  \
+             \"groups_max\": {},\n  \"deployed\": {},\n  \"runs\": {}\n}}\n",
+            MODEL_VERSION,
+            Json::String(description.to_owned()),
+            json_real(result.p_disrupt),
+            json_real(result.p_compromise),
+            json_real(result.groups),
+            json_option(result.groups_range.map(|range| range.0)),
+            json_option(result.groups_range.map(|range| range.1)),
+            result.deployed.map_or("null".to_owned(), json_real),
+            json_option(result.runs))
+}
+
+// The result saved as `json` by `to_json`, if of this version of the model and
+// for the runs of `description` (`None` otherwise), or why it cannot be read.
+fn from_json(json: &str, description: &str) -> Result<Option<SimResult>, String> {
+    let (version, saved_for, result) = try!(read_saved(json));
+    if version != MODEL_VERSION {
+        debug!("cached result of model version {}, not {}: run again",
+               version,
+               MODEL_VERSION);
+        return Ok(None);
+    }
+    if saved_for != description {
+        return Err("saved for different parameters".to_owned());
+    }
+    Ok(Some(result))
+}
+
+/// The model version, description of the runs and result saved as `json`
+/// (the contents of a cached result), whatever the version, or why it cannot
+/// be read. The description starts with the parameters of the set as a row of
+/// batch input (see `batch::params_row`).
+pub fn read_saved(json: &str) -> Result<(NN, String, SimResult), String> {
+    let json = try!(Json::from_str(json).map_err(|e| e.to_string()));
+    let field = |name: &str| -> Result<&Json, String> {
+        json.find(name).ok_or_else(|| format!("no {}", name))
+    };
+    // Written as strings, read exactly; plain numbers are also accepted
+    let number = |name: &str| -> Result<RR, String> {
+        let bad = || format!("bad {}", name);
+        match *try!(field(name)) {
+            Json::String(ref s) => s.parse().map_err(|_| bad()),
+            ref x => x.as_f64().ok_or_else(bad),
+        }
+    };
+    let version = try!(try!(field("model_version"))
+        .as_u64()
+        .ok_or_else(|| "bad model_version".to_owned()));
+    let description = try!(try!(field("description"))
+        .as_string()
+        .ok_or_else(|| "bad description".to_owned()));
+    let deployed = match *try!(field("deployed")) {
+        Json::Null => None,
+        _ => Some(try!(number("deployed"))),
+    };
+    // Absent from results cached before the range was kept
+    let groups_bound = |name: &str| -> Result<Option<NN>, String> {
+        match json.find(name) {
+            None | Some(&Json::Null) => Ok(None),
+            Some(bound) => {
+                bound.as_u64().map(|bound| Some(bound as NN)).ok_or_else(|| format!("bad {}", name))
+            }
+        }
+    };
+    let groups_range = match (try!(groups_bound("groups_min")), try!(groups_bound("groups_max"))) {
+        (Some(fewest), Some(most)) => Some((fewest, most)),
+        _ => None,
+    };
+    let runs = match *try!(field("runs")) {
+        Json::Null => None,
+        ref runs => Some(try!(runs.as_u64().ok_or_else(|| "bad runs".to_owned())) as NN),
+    };
+    let result = SimResult {
+        p_disrupt: try!(number("p_disrupt")),
+        p_compromise: try!(number("p_compromise")),
+        groups: try!(number("groups")),
+        groups_range: groups_range,
+        deployed: deployed,
+        malice_hist: None,
+        elders: None,
+        models: None,
+        correction: None,
+        age_share: None,
+        liveness: None,
+        outcomes: None,
+        termination: None,
+        governance: None,
+        spread: None,
+        coverage: None,
+        close_group: None,
+        turnover: None,
+        steady_state: None,
+        attack_record: None,
+        rng_draws: None,
+        runs: runs,
+        truncated: false,
+        split_check: None,
+    };
+    Ok((version as NN, description.to_owned(), result))
+}
+
+/// Remove the cached results in `dir` written at least `older_than` seconds
+/// ago, returning the number removed and the number kept.
+pub fn prune(dir: &Path, older_than: RR) -> io::Result<(NN, NN)> {
+    let now = SystemTime::now();
+    let (mut removed, mut kept) = (0, 0);
+    for entry in try!(fs::read_dir(dir)) {
+        let path = try!(entry).path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(EXTENSION) {
+            continue;
+        }
+        let modified = try!(try!(fs::metadata(&path)).modified());
+        // A file written "in the future" (the clock moved back) is new
+        let age = now.duration_since(modified)
+            .map(|age| age.as_secs() as RR + age.subsec_nanos() as RR * 1e-9)
+            .unwrap_or(0.0);
+        if age >= older_than {
+            try!(fs::remove_file(&path));
+            removed += 1;
+        } else {
+            kept += 1;
+        }
+    }
+    Ok((removed, kept))
+}
+
+/// The directory and age (in seconds) of the arguments of `routing-sims cache
+/// prune` (including the program name and command).
+pub fn parse_argv(argv: &[String]) -> Result<(PathBuf, RR), docopt::Error> {
+    let args: Args = try!(Docopt::new(USAGE).and_then(|dopt| dopt.argv(argv).decode()));
+    let older_than = parse_duration(&args.flag_older_than).unwrap_or_else(|e| {
+        panic!("unexpected: --older-than {} ({})", args.flag_older_than, e)
+    });
+    Ok((PathBuf::from(args.arg_DIR), older_than))
+}
+
+#[test]
+fn test_result_cache() {
+    use super::args::sim_params;
+    use super::explain::single;
+    use super::output::test_dir;
+
+    let dir = test_dir();
+    let cache = ResultCache::new(dir.clone(), false);
+    let params = single(sim_params(&["calc", "-n", "200", "-k", "8"])).expect("one set");
+
+    // Miss, then a hit once saved
+    assert!(cache.load(&params).is_none());
+    let mut result = params.result();
+    result.p_compromise = 1.0 / 3.0;
+    cache.save(&params, &result);
+    let cached = cache.load(&params).expect("cached");
+    assert_eq!((cached.p_disrupt, cached.p_compromise, cached.groups, cached.runs),
+               (result.p_disrupt, 1.0 / 3.0, result.groups, None));
+    assert!(cache.path(&params).starts_with(&dir));
+    // Refreshing loads nothing
+    assert!(ResultCache::new(dir.clone(), true).load(&params).is_none());
+    // Other parameters miss
+    let other = single(sim_params(&["calc", "-n", "200", "-k", "9"])).expect("one set");
+    assert!(cache.load(&other).is_none());
+
+    // A result of another version of the model misses, and is replaced
+    let mut contents = String::new();
+    let _ = File::open(cache.path(&params))
+        .and_then(|mut file| file.read_to_string(&mut contents))
+        .expect("read");
+    let version = format!("\"model_version\": {},", MODEL_VERSION);
+    assert!(contents.contains(&version));
+    let other_model = contents.replace(&version,
+                                       &format!("\"model_version\": {},", MODEL_VERSION + 1));
+    File::create(cache.path(&params))
+        .and_then(|mut file| file.write_all(other_model.as_bytes()))
+        .expect("write");
+    assert!(cache.load(&params).is_none());
+    cache.save(&params, &result);
+    assert!(cache.load(&params).is_some());
+
+    // The fewest and most groups are kept, and read as unknown from results cached without them
+    result.groups_range = Some((24, 27));
+    cache.save(&params, &result);
+    assert_eq!(cache.load(&params).expect("cached").groups_range, Some((24, 27)));
+    let unranged: String = contents.lines()
+        .filter(|line| !line.contains("\"groups_m"))
+        .map(|line| format!("{}\n", line))
+        .collect();
+    File::create(cache.path(&params))
+        .and_then(|mut file| file.write_all(unranged.as_bytes()))
+        .expect("write");
+    assert_eq!(cache.load(&params).expect("cached").groups_range, None);
+
+    // A corrupted result misses
+    for corrupted in &[&contents[..contents.len() / 2],
+                       "",
+                       "[]",
+                       &contents.replace("\"p_compromise\"", "\"p\"")[..],
+                       &contents.replace("\"runs\": null", "\"runs\": -1")[..],
+                       &contents.replace("\"groups_min\": null", "\"groups_min\": 1.5")[..]] {
+        File::create(cache.path(&params))
+            .and_then(|mut file| file.write_all(corrupted.as_bytes()))
+            .expect("write");
+        assert!(cache.load(&params).is_none(), "{:?}", corrupted);
+    }
+
+    fs::remove_dir_all(&dir).expect("remove directory");
+}
+
+#[test]
+fn test_cacheable() {
+    use super::args::sim_params;
+    use super::explain::single;
+    use super::output::test_dir;
+
+    let dir = test_dir();
+    let cache = ResultCache::new(dir.clone(), false);
+    let run = |args: &[&str]| -> bool {
+        let params = single(sim_params(args)).expect("one set");
+        let result = params.result();
+        cache.save(&params, &result);
+        match cache.load(&params) {
+            Some(cached) => {
+                assert_eq!((cached.p_compromise, cached.runs), (result.p_compromise, result.runs));
+                true
+            }
+            None => false,
+        }
+    };
+    let full = ["full", "-n", "50", "-k", "8", "-s", "5", "-p", "3"];
+    let with = |extra: &[&'static str]| -> Vec<&'static str> {
+        full.iter().chain(extra).cloned().collect()
+    };
+    // Simulations given a seed are cached
+    assert!(run(&with(&["--seed", "1"])));
+    // Not without one, nor requesting reports
+    assert!(!run(&full[..]));
+    assert!(!run(&with(&["--seed", "1", "--report", "malice-hist"])));
+    // Nor results of fewer repetitions than requested
+    let params = single(sim_params(&with(&["--seed", "2"]))).expect("one set");
+    let mut result = params.result();
+    result.runs = Some(2);
+    cache.save(&params, &result);
+    assert!(cache.load(&params).is_none());
+
+    fs::remove_dir_all(&dir).expect("remove directory");
+}
+
+#[test]
+fn test_prune() {
+    use super::output::test_dir;
+
+    let dir = test_dir();
+    for name in &["a.json", "b.json", "notes.txt"] {
+        File::create(dir.join(name)).expect("create");
+    }
+    assert_eq!(prune(&dir, 86400.0).expect("prune"), (0, 2));
+    assert_eq!(prune(&dir, 0.0).expect("prune"), (2, 0));
+    // Only cached results are removed
+    assert!(dir.join("notes.txt").exists());
+    assert!(!dir.join("a.json").exists());
+
+    let argv: Vec<String> = ["routing-sims", "cache", "prune", "some/dir", "--older-than", "30d"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let (path, older_than) = parse_argv(&argv).unwrap_or_else(|e| panic!("{}", e));
+    assert_eq!((path, older_than), (PathBuf::from("some/dir"), 30.0 * 86400.0));
+
+    fs::remove_dir_all(&dir).expect("remove directory");
+}
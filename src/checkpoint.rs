@@ -0,0 +1,668 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Checkpoints of the full simulation, so that long runs survive restarts
+//!
+//! Only completed repetitions are saved; a repetition in progress when the
+//! program stops is run again from scratch on restart.
+
+use super::{NN, RR};
+use super::tools::{MaliceHist, MALICE_HIST_BUCKETS};
+use super::quorum::AttackDecision;
+use super::rng::Draws;
+use super::model::{self, MODEL_VERSION};
+use super::output::{partial_path, PartialFile};
+
+use std::fmt::{self, Formatter};
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+
+/// Where and how often to checkpoint (common to all parameter sets).
+#[derive(Clone, Debug)]
+pub struct Checkpointing {
+    /// Directory holding one file per parameter set
+    pub dir: PathBuf,
+    /// Minimum time between saves, in seconds
+    pub every: RR,
+    /// Whether to load checkpoints saved by another version of the model (see
+    /// `--allow-model-mismatch`)
+    pub allow_model_mismatch: bool,
+    /// Whether to resume from a complete checkpoint left under its partial
+    /// name (see `--keep-partial`)
+    pub keep_partial: bool,
+}
+
+/// Sums over the repetitions completed so far, from which `FullSimTool`
+/// calculates its results.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Progress {
+    /// Number of completed repetitions
+    pub repetitions: NN,
+    pub disruptions: NN,
+    pub compromises: NN,
+    /// Sum of the number of groups at the end of each repetition
+    pub groups: NN,
+    /// Fewest and most groups at the end of a repetition (`None` before the
+    /// first)
+    pub groups_range: Option<(NN, NN)>,
+    /// Sum of the mean malicious nodes deployed in each repetition (see
+    /// `AttackStrategy::deploy`)
+    pub deployed: RR,
+    /// Whether some repetition reached the target prefix
+    pub target_reached: bool,
+    /// Wall time spent on the repetitions so far, in seconds (counted against
+    /// the time limit)
+    pub elapsed: RR,
+    /// Sums of the mean age of the honest nodes at the end of each repetition
+    /// and of the malicious nodes (over the repetitions ending with any), the
+    /// number of those repetitions, and the oldest age at the end of any
+    pub ages: (RR, RR, NN, NN),
+    pub malice_hist: Option<MaliceHist>,
+    /// Sum of malicious elders, maximum malicious elders in any group and
+    /// number of repetitions ending with a quorum of malicious elders
+    pub elders: Option<(NN, NN, NN)>,
+    /// Sum of the age share at each sample
+    pub age_share: Option<Vec<RR>>,
+    /// Number of repetitions in which some group lacked a quorum of live
+    /// members, and the number of such group-steps
+    pub liveness: Option<(NN, NN)>,
+    /// Whether each repetition, in order, ended in compromise
+    pub outcomes: Option<Vec<bool>>,
+    /// Number of repetitions which stalled, the sum of the steps they
+    /// simulated, and the number in which the attacker ran out of nodes
+    pub termination: Option<(NN, NN, NN)>,
+    /// Number of repetitions in which some structural change was blockable,
+    /// the number of changes and the number blockable
+    pub governance: Option<(NN, NN, NN)>,
+    /// Sum of the number of groups holding malicious nodes at the end of each
+    /// repetition, and of the most malicious nodes in one group
+    pub spread: Option<(NN, NN)>,
+    /// Sums of the steps over which the median honest age was sampled, of its
+    /// initial values, of its growth and of its steady-state values
+    pub turnover: Option<(NN, RR, RR, RR)>,
+    /// Sum and maximum of the share of the address space held by compromised
+    /// groups at the end of each repetition, then the same for disruptable
+    /// groups
+    pub coverage: Option<(RR, RR, RR, RR)>,
+    /// Steps in which a compromise and a disruption were held `(compromised,
+    /// disrupted)`, summed over the repetitions, in each batch of the steady
+    /// state
+    pub steady_state: Option<Vec<(NN, NN)>>,
+    /// Sums over the repetitions of the measured steps in which a compromise
+    /// was held and of their squares, then the same for disruptions, for the
+    /// spread of the steady state between repetitions
+    pub steady_state_runs: Option<(NN, NN, NN, NN)>,
+    /// The attacker's decisions in the first repetition, once completed
+    pub attack_record: Option<Vec<AttackDecision>>,
+    /// Values drawn from each random number stream in each repetition
+    pub rng_draws: Option<Vec<Draws>>,
+}
+
+/// The optional reports kept in a `Progress` (by default, none).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Reports {
+    pub malice_hist: bool,
+    pub elders: bool,
+    /// Number of age share samples
+    pub age_share_samples: Option<usize>,
+    pub liveness: bool,
+    pub outcomes: bool,
+    pub termination: bool,
+    pub governance: bool,
+    pub spread: bool,
+    pub turnover: bool,
+    pub coverage: bool,
+    /// Number of batches of the steady state
+    pub steady_state_batches: Option<usize>,
+    pub attack_record: bool,
+    pub rng_draws: bool,
+}
+
+impl Progress {
+    /// No repetitions completed, with the reports requested.
+    pub fn new(reports: Reports) -> Self {
+        Progress {
+            repetitions: 0,
+            disruptions: 0,
+            compromises: 0,
+            groups: 0,
+            groups_range: None,
+            deployed: 0.0,
+            target_reached: false,
+            elapsed: 0.0,
+            ages: (0.0, 0.0, 0, 0),
+            malice_hist: if reports.malice_hist {
+                Some(MaliceHist::new())
+            } else {
+                None
+            },
+            elders: if reports.elders { Some((0, 0, 0)) } else { None },
+            age_share: reports.age_share_samples.map(|n| vec![0.0; n]),
+            liveness: if reports.liveness { Some((0, 0)) } else { None },
+            outcomes: if reports.outcomes { Some(vec![]) } else { None },
+            termination: if reports.termination { Some((0, 0, 0)) } else { None },
+            governance: if reports.governance { Some((0, 0, 0)) } else { None },
+            spread: if reports.spread { Some((0, 0)) } else { None },
+            turnover: if reports.turnover { Some((0, 0.0, 0.0, 0.0)) } else { None },
+            coverage: if reports.coverage { Some((0.0, 0.0, 0.0, 0.0)) } else { None },
+            steady_state: reports.steady_state_batches.map(|n| vec![(0, 0); n]),
+            steady_state_runs: reports.steady_state_batches.map(|_| (0, 0, 0, 0)),
+            attack_record: if reports.attack_record { Some(vec![]) } else { None },
+            rng_draws: if reports.rng_draws { Some(vec![]) } else { None },
+        }
+    }
+
+    // True if both have the same reports (so one may be continued as the other).
+    fn same_reports(&self, other: &Progress) -> bool {
+        self.malice_hist.is_some() == other.malice_hist.is_some() &&
+        self.elders.is_some() == other.elders.is_some() &&
+        self.age_share.as_ref().map(|v| v.len()) == other.age_share.as_ref().map(|v| v.len()) &&
+        self.liveness.is_some() == other.liveness.is_some() &&
+        self.outcomes.is_some() == other.outcomes.is_some() &&
+        self.termination.is_some() == other.termination.is_some() &&
+        self.governance.is_some() == other.governance.is_some() &&
+        self.spread.is_some() == other.spread.is_some() &&
+        self.turnover.is_some() == other.turnover.is_some() &&
+        self.coverage.is_some() == other.coverage.is_some() &&
+        self.steady_state.as_ref().map(|v| v.len()) ==
+        other.steady_state.as_ref().map(|v| v.len()) &&
+        self.steady_state_runs.is_some() == other.steady_state_runs.is_some() &&
+        self.attack_record.is_some() == other.attack_record.is_some() &&
+        self.rng_draws.is_some() == other.rng_draws.is_some()
+    }
+}
+
+// Join values with spaces
+fn join<T: fmt::Display>(values: &[T]) -> String {
+    values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        try!(writeln!(f, "repetitions {}", self.repetitions));
+        try!(writeln!(f, "disruptions {}", self.disruptions));
+        try!(writeln!(f, "compromises {}", self.compromises));
+        try!(writeln!(f, "groups {}", self.groups));
+        if let Some((fewest, most)) = self.groups_range {
+            try!(writeln!(f, "groups_range {} {}", fewest, most));
+        }
+        try!(writeln!(f, "deployed {}", self.deployed));
+        try!(writeln!(f, "target_reached {}", self.target_reached));
+        try!(writeln!(f, "elapsed {}", self.elapsed));
+        let (honest, malicious, runs, oldest) = self.ages;
+        try!(writeln!(f, "ages {} {} {} {}", honest, malicious, runs, oldest));
+        if let Some(ref hist) = self.malice_hist {
+            try!(writeln!(f, "malice_hist {}", join(&hist.counts)));
+        }
+        if let Some((sum, max, quorums)) = self.elders {
+            try!(writeln!(f, "elders {} {} {}", sum, max, quorums));
+        }
+        if let Some(ref sums) = self.age_share {
+            // Display of floats is exact (it round-trips through parse)
+            try!(writeln!(f, "age_share {}", join(sums)));
+        }
+        if let Some((runs, group_steps)) = self.liveness {
+            try!(writeln!(f, "liveness {} {}", runs, group_steps));
+        }
+        if let Some(ref outcomes) = self.outcomes {
+            let flags: String = outcomes.iter().map(|&c| if c { '1' } else { '0' }).collect();
+            try!(writeln!(f, "outcomes {}", flags));
+        }
+        if let Some((stalled, stall_steps, budget_exhausted)) = self.termination {
+            try!(writeln!(f, "termination {} {} {}", stalled, stall_steps, budget_exhausted));
+        }
+        if let Some((runs, changes, blockable)) = self.governance {
+            try!(writeln!(f, "governance {} {} {}", runs, changes, blockable));
+        }
+        if let Some((groups, max_cluster)) = self.spread {
+            try!(writeln!(f, "spread {} {}", groups, max_cluster));
+        }
+        if let Some((steps, initial, growth, steady)) = self.turnover {
+            try!(writeln!(f, "turnover {} {} {} {}", steps, initial, growth, steady));
+        }
+        if let Some((compromised, max_compromised, disrupted, max_disrupted)) = self.coverage {
+            try!(writeln!(f,
+                          "coverage {} {} {} {}",
+                          compromised,
+                          max_compromised,
+                          disrupted,
+                          max_disrupted));
+        }
+        if let Some(ref batches) = self.steady_state {
+            let sums: Vec<NN> = batches.iter().flat_map(|&(c, d)| vec![c, d]).collect();
+            try!(writeln!(f, "steady_state {}", join(&sums)));
+        }
+        if let Some((compromised, compromised_sq, disrupted, disrupted_sq)) =
+            self.steady_state_runs {
+            try!(writeln!(f,
+                          "steady_state_runs {} {} {} {}",
+                          compromised,
+                          compromised_sq,
+                          disrupted,
+                          disrupted_sq));
+        }
+        if let Some(ref decisions) = self.attack_record {
+            try!(writeln!(f, "attack_record {}", join(decisions)));
+        }
+        if let Some(ref record) = self.rng_draws {
+            let draws: Vec<String> = record.iter()
+                .map(|draws| draws.iter().map(|d| d.to_string()).collect::<Vec<_>>().join(":"))
+                .collect();
+            try!(writeln!(f, "rng_draws {}", draws.join(" ")));
+        }
+        Ok(())
+    }
+}
+
+// Parse space-separated values
+fn parse_all<T: FromStr>(key: &str, values: &str) -> Result<Vec<T>, String> {
+    values.split_whitespace()
+        .map(|v| v.parse().map_err(|_| format!("bad value for {}: '{}'", key, v)))
+        .collect()
+}
+
+impl FromStr for Progress {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut progress = Progress::new(Reports::default());
+        let mut found = 0;
+        for line in s.lines().filter(|line| !line.trim().is_empty()) {
+            let (key, values) = match line.find(' ') {
+                Some(i) => (&line[..i], &line[i + 1..]),
+                None => (line, ""),
+            };
+            let one = |values: &str| -> Result<NN, String> {
+                values.trim().parse().map_err(|_| format!("bad value for {}: '{}'", key, values))
+            };
+            match key {
+                "repetitions" => progress.repetitions = try!(one(values)),
+                "disruptions" => progress.disruptions = try!(one(values)),
+                "compromises" => progress.compromises = try!(one(values)),
+                "groups" => progress.groups = try!(one(values)),
+                "target_reached" => {
+                    progress.target_reached = try!(values.trim()
+                        .parse()
+                        .map_err(|_| format!("bad value for {}: '{}'", key, values)))
+                }
+                "groups_range" => {
+                    let v: Vec<NN> = try!(parse_all(key, values));
+                    if v.len() != 2 {
+                        return Err("expected 2 groups_range values".to_owned());
+                    }
+                    progress.groups_range = Some((v[0], v[1]));
+                    continue;
+                }
+                // Absent from checkpoints saved before partial deployment
+                "deployed" => {
+                    progress.deployed = try!(values.trim()
+                        .parse()
+                        .map_err(|_| format!("bad value for {}: '{}'", key, values)));
+                    continue;
+                }
+                // Absent from checkpoints saved before the time limit
+                "elapsed" => {
+                    progress.elapsed = try!(values.trim()
+                        .parse()
+                        .map_err(|_| format!("bad value for {}: '{}'", key, values)));
+                    continue;
+                }
+                // Absent from checkpoints saved before the age check
+                "ages" => {
+                    let v: Vec<&str> = values.split_whitespace().collect();
+                    if v.len() != 4 {
+                        return Err("expected 4 ages values".to_owned());
+                    }
+                    let sums: Vec<RR> = try!(parse_all(key, &v[..2].join(" ")));
+                    progress.ages = (sums[0], sums[1], try!(one(v[2])), try!(one(v[3])));
+                    continue;
+                }
+                "malice_hist" => {
+                    let counts: Vec<NN> = try!(parse_all(key, values));
+                    if counts.len() != MALICE_HIST_BUCKETS {
+                        return Err(format!("expected {} malice_hist counts", MALICE_HIST_BUCKETS));
+                    }
+                    let mut hist = MaliceHist::new();
+                    hist.counts.copy_from_slice(&counts);
+                    progress.malice_hist = Some(hist);
+                    continue;
+                }
+                "elders" => {
+                    let v: Vec<NN> = try!(parse_all(key, values));
+                    if v.len() != 3 {
+                        return Err("expected 3 elders values".to_owned());
+                    }
+                    progress.elders = Some((v[0], v[1], v[2]));
+                    continue;
+                }
+                "age_share" => {
+                    progress.age_share = Some(try!(parse_all(key, values)));
+                    continue;
+                }
+                "liveness" => {
+                    let v: Vec<NN> = try!(parse_all(key, values));
+                    if v.len() != 2 {
+                        return Err("expected 2 liveness values".to_owned());
+                    }
+                    progress.liveness = Some((v[0], v[1]));
+                    continue;
+                }
+                "outcomes" => {
+                    let flags = values.trim();
+                    if flags.chars().any(|c| c != '0' && c != '1') {
+                        return Err(format!("bad value for {}: '{}'", key, flags));
+                    }
+                    progress.outcomes = Some(flags.chars().map(|c| c == '1').collect());
+                    continue;
+                }
+                "termination" => {
+                    let v: Vec<NN> = try!(parse_all(key, values));
+                    if v.len() != 3 {
+                        return Err("expected 3 termination values".to_owned());
+                    }
+                    progress.termination = Some((v[0], v[1], v[2]));
+                    continue;
+                }
+                "governance" => {
+                    let v: Vec<NN> = try!(parse_all(key, values));
+                    if v.len() != 3 {
+                        return Err("expected 3 governance values".to_owned());
+                    }
+                    progress.governance = Some((v[0], v[1], v[2]));
+                    continue;
+                }
+                "spread" => {
+                    let v: Vec<NN> = try!(parse_all(key, values));
+                    if v.len() != 2 {
+                        return Err("expected 2 spread values".to_owned());
+                    }
+                    progress.spread = Some((v[0], v[1]));
+                    continue;
+                }
+                "turnover" => {
+                    let v: Vec<&str> = values.split_whitespace().collect();
+                    if v.len() != 4 {
+                        return Err("expected 4 turnover values".to_owned());
+                    }
+                    let sums: Vec<RR> = try!(parse_all(key, &v[1..].join(" ")));
+                    progress.turnover = Some((try!(one(v[0])), sums[0], sums[1], sums[2]));
+                    continue;
+                }
+                "coverage" => {
+                    let v: Vec<RR> = try!(parse_all(key, values));
+                    if v.len() != 4 {
+                        return Err("expected 4 coverage values".to_owned());
+                    }
+                    progress.coverage = Some((v[0], v[1], v[2], v[3]));
+                    continue;
+                }
+                "steady_state" => {
+                    let sums: Vec<NN> = try!(parse_all(key, values));
+                    if sums.len() % 2 != 0 {
+                        return Err("expected pairs of steady_state values".to_owned());
+                    }
+                    progress.steady_state = Some(sums.chunks(2).map(|s| (s[0], s[1])).collect());
+                    continue;
+                }
+                "steady_state_runs" => {
+                    let v: Vec<NN> = try!(parse_all(key, values));
+                    if v.len() != 4 {
+                        return Err("expected 4 steady_state_runs values".to_owned());
+                    }
+                    progress.steady_state_runs = Some((v[0], v[1], v[2], v[3]));
+                    continue;
+                }
+                "attack_record" => {
+                    let decisions: Result<Vec<AttackDecision>, String> = values
+                        .split_whitespace()
+                        .map(|v| v.parse().map_err(|e| format!("bad value for {}: {}", key, e)))
+                        .collect();
+                    progress.attack_record = Some(try!(decisions));
+                    continue;
+                }
+                "rng_draws" => {
+                    let mut record = vec![];
+                    for repetition in values.split_whitespace() {
+                        let counts: Vec<NN> = try!(parse_all(key, &repetition.replace(':', " ")));
+                        if counts.len() != 4 {
+                            return Err(format!("expected 4 rng_draws counts: '{}'", repetition));
+                        }
+                        record.push([counts[0], counts[1], counts[2], counts[3]]);
+                    }
+                    progress.rng_draws = Some(record);
+                    continue;
+                }
+                _ => return Err(format!("unknown key '{}'", key)),
+            }
+            found += 1;
+        }
+        if found < 5 {
+            return Err("missing values".to_owned());
+        }
+        Ok(progress)
+    }
+}
+
+#[test]
+fn test_progress_round_trip() {
+    let mut progress = Progress::new(Reports {
+        malice_hist: true,
+        elders: true,
+        age_share_samples: Some(3),
+        liveness: true,
+        outcomes: true,
+        termination: true,
+        governance: true,
+        spread: true,
+        turnover: true,
+        coverage: true,
+        steady_state_batches: Some(2),
+        attack_record: true,
+        rng_draws: true,
+    });
+    progress.repetitions = 7;
+    progress.disruptions = 5;
+    progress.compromises = 2;
+    progress.groups = 123;
+    progress.groups_range = Some((17, 18));
+    progress.deployed = 1234.0 / 7.0;
+    progress.target_reached = true;
+    progress.elapsed = 12.25;
+    progress.ages = (31.5, 2.0 / 3.0, 6, 11);
+    progress.malice_hist.as_mut().expect("hist").counts[3] = 42;
+    progress.elders = Some((10, 4, 1));
+    progress.age_share = Some(vec![0.1, 1.0 / 3.0, 2.5e-7]);
+    progress.liveness = Some((3, 250));
+    progress.outcomes = Some(vec![false, true, false]);
+    progress.termination = Some((2, 31, 1));
+    progress.governance = Some((1, 12, 3));
+    progress.spread = Some((40, 23));
+    progress.turnover = Some((710, 21.5, 1.0 / 3.0, 40.125));
+    progress.coverage = Some((1.375, 0.5, 2.0 / 3.0, 0.75));
+    progress.steady_state = Some(vec![(12, 40), (0, 38)]);
+    progress.steady_state_runs = Some((12, 80, 78, 1010));
+    progress.attack_record = Some(vec!["0::1".parse().expect("decision"),
+                                       "3:01:0".parse().expect("decision")]);
+    progress.rng_draws = Some(vec![[310, 28, 4096, 0], [306, 30, 4102, 0]]);
+    assert_eq!(progress.to_string().parse(), Ok(progress));
+
+    let progress = Progress::new(Reports::default());
+    assert_eq!(progress.to_string().parse(), Ok(progress));
+
+    assert!("repetitions 3\n".parse::<Progress>().is_err());
+    assert!("bogus 1\n".parse::<Progress>().is_err());
+}
+
+
+// 64-bit FNV-1a hash. Unlike the standard library's hasher, this is stable
+// across versions, so may be used to name files.
+pub fn fnv1a(s: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in s.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// Checkpoint file of one parameter set.
+///
+/// The file is named by the combination id of the parameter set (see
+/// `combo::combo_id`) and a hash of a full description of it (including the
+/// repetitions and seeds, which the id leaves out). The description is also
+/// stored in the file and checked on loading, as is the version of the model
+/// which saved it. Files are saved as partial files (see `PartialFile`) and
+/// end with the line `end`, so that one left under its partial name by a
+/// program stopped before renaming it can be told to be complete.
+pub struct CheckpointFile {
+    path: PathBuf,
+    params: String,
+    every: RR,
+    allow_model_mismatch: bool,
+    keep_partial: bool,
+}
+
+impl CheckpointFile {
+    /// File in `checkpointing.dir` for the parameter set of combination id
+    /// `id` described by `params` (which must be a single line).
+    pub fn new(checkpointing: &Checkpointing, id: &str, params: &str) -> Self {
+        let name = format!("{}-{:016x}.ckpt", id, fnv1a(params));
+        CheckpointFile {
+            path: checkpointing.dir.join(name),
+            params: params.to_owned(),
+            every: checkpointing.every,
+            allow_model_mismatch: checkpointing.allow_model_mismatch,
+            keep_partial: checkpointing.keep_partial,
+        }
+    }
+
+    /// Path of the file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Minimum time between saves, in seconds
+    pub fn every(&self) -> RR {
+        self.every
+    }
+
+    /// Load progress, if saved and compatible with `fresh` (progress with no
+    /// repetitions, for the reports now requested). If the file exists but
+    /// cannot be used, a warning is logged and `None` returned. If partial
+    /// files are kept, a complete partial file (the later save) is loaded in
+    /// preference.
+    pub fn load(&self, fresh: &Progress) -> Option<Progress> {
+        let partial = partial_path(&self.path);
+        if self.keep_partial {
+            match read(&partial) {
+                Ok(Some(ref contents)) if ended(contents) => {
+                    return self.parse(&partial, contents, fresh);
+                }
+                Ok(Some(_)) => self.warn(&partial, "incomplete"),
+                Ok(None) => {}
+                Err(e) => self.warn(&partial, &e.to_string()),
+            }
+        }
+        match read(&self.path) {
+            Ok(Some(contents)) => self.parse(&self.path, &contents, fresh),
+            Ok(None) => None,
+            Err(e) => {
+                self.warn(&self.path, &e.to_string());
+                None
+            }
+        }
+    }
+
+    // Progress from the contents of the file at `path`
+    fn parse(&self, path: &Path, contents: &str, fresh: &Progress) -> Option<Progress> {
+        // Checkpoints saved before model versions were recorded start with the parameters
+        let (version, rest) = if contents.starts_with("model ") {
+            let mut parts = contents.splitn(2, '\n');
+            let version = parts.next().and_then(|line| line["model ".len()..].parse::<NN>().ok());
+            (version, parts.next().unwrap_or(""))
+        } else {
+            (None, &contents[..])
+        };
+        let what = format!("checkpoint {}", path.display());
+        if let Err(e) = model::check(&what, version, self.allow_model_mismatch) {
+            warn!("ignoring {}", e);
+            return None;
+        }
+        let params = format!("params {}", self.params);
+        let mut lines = rest.splitn(2, '\n');
+        if lines.next() != Some(&params) {
+            self.warn(path, "saved for different parameters");
+            return None;
+        }
+        let saved = lines.next().unwrap_or("");
+        // Checkpoints saved before they were ended have no end line
+        let saved = if ended(saved) {
+            &saved[..saved.trim_right().len() - END.len()]
+        } else {
+            saved
+        };
+        match saved.parse::<Progress>() {
+            Ok(ref progress) if !progress.same_reports(fresh) => {
+                self.warn(path, "saved with different reports");
+                None
+            }
+            Ok(progress) => Some(progress),
+            Err(e) => {
+                self.warn(path, &e);
+                None
+            }
+        }
+    }
+
+    fn warn(&self, path: &Path, msg: &str) {
+        warn!("ignoring checkpoint {}: {}", path.display(), msg);
+    }
+
+    /// Save progress. The file is replaced atomically, so is not lost if the
+    /// program stops while saving.
+    pub fn save(&self, progress: &Progress) -> io::Result<()> {
+        let mut file = try!(PartialFile::create(&self.path));
+        try!(write!(file,
+                    "model {}\nparams {}\n{}{}\n",
+                    MODEL_VERSION,
+                    self.params,
+                    progress,
+                    END));
+        file.finish()
+    }
+}
+
+// Last line of a checkpoint file
+const END: &'static str = "end";
+
+// Whether `contents` end with the end line
+fn ended(contents: &str) -> bool {
+    let contents = contents.trim_right();
+    contents == END || contents.ends_with(&format!("\n{}", END))
+}
+
+// The contents of the file at `path`, if it exists
+fn read(path: &Path) -> io::Result<Option<String>> {
+    let mut contents = String::new();
+    match File::open(path).and_then(|mut file| file.read_to_string(&mut contents)) {
+        Ok(_) => Ok(Some(contents)),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
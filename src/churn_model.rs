@@ -0,0 +1,121 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! How many churn events happen at each step (see `--churn-model`)
+
+use super::{NN, RR};
+
+use rand::Rng;
+
+
+/// Largest mean drawn from in one go by `sample_poisson`: e^-30 is still far
+/// from the smallest float
+const POISSON_CHUNK: RR = 30.0;
+
+/// How the full simulation draws the number of events at each step from
+/// their expected number (the rate). Currently this is the number of times
+/// each honest member takes part in a churn event of its group, at the rate
+/// of its uptime.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChurnModel {
+    /// As the rates give: an event happens with the rate as probability, so
+    /// a node takes part in each churn event exactly once
+    Fixed,
+    /// A Poisson number of each, with the same mean, so that some steps see
+    /// several
+    Poisson,
+}
+
+impl ChurnModel {
+    pub fn name(self) -> &'static str {
+        match self {
+            ChurnModel::Fixed => "fixed",
+            ChurnModel::Poisson => "poisson",
+        }
+    }
+
+    /// Parse from `name()`
+    pub fn from_name(name: &str) -> Option<ChurnModel> {
+        [ChurnModel::Fixed, ChurnModel::Poisson].iter().cloned().find(|m| m.name() == name)
+    }
+
+    /// Number of events at a step of an event happening with probability
+    /// `rate` under the fixed model (at most one), drawn from `rng`
+    pub fn events<R: Rng>(self, rate: RR, rng: &mut R) -> NN {
+        match self {
+            ChurnModel::Fixed => if rng.gen::<RR>() < rate { 1 } else { 0 },
+            ChurnModel::Poisson => sample_poisson(rate, rng),
+        }
+    }
+}
+
+/// Draw from the Poisson distribution with mean `mean`, by Knuth's algorithm:
+/// the number of uniform draws whose product stays above e^-mean. Means above
+/// `POISSON_CHUNK` are drawn as the sum of draws of smaller means.
+pub fn sample_poisson<R: Rng>(mean: RR, rng: &mut R) -> NN {
+    let (mut mean, mut count) = (mean, 0);
+    while mean > POISSON_CHUNK {
+        count += sample_poisson(POISSON_CHUNK, rng);
+        mean -= POISSON_CHUNK;
+    }
+    let limit = (-mean).exp();
+    let mut product = rng.gen::<RR>();
+    while product > limit {
+        count += 1;
+        product *= rng.gen::<RR>();
+    }
+    count
+}
+
+#[test]
+fn test_churn_model() {
+    use rand::{SeedableRng, XorShiftRng};
+
+    assert_eq!(ChurnModel::from_name("poisson"), Some(ChurnModel::Poisson));
+    assert_eq!(ChurnModel::from_name(ChurnModel::Fixed.name()), Some(ChurnModel::Fixed));
+    assert_eq!(ChurnModel::from_name("bogus"), None);
+
+    // The mean and variance of many draws: the variance is the mean for the Poisson model, and
+    // the mean times one minus it for the fixed one
+    let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+    let draws = 40000;
+    let mut moments = |draw: &mut FnMut(&mut XorShiftRng) -> NN| {
+        let counts: Vec<RR> = (0..draws).map(|_| draw(&mut rng) as RR).collect();
+        let mean = counts.iter().sum::<RR>() / draws as RR;
+        let var = counts.iter().map(|x| (x - mean) * (x - mean)).sum::<RR>() / (draws - 1) as RR;
+        (mean, var)
+    };
+    for &rate in &[0.0, 0.05, 0.3, 1.0] {
+        let (mean, var) = moments(&mut |rng| ChurnModel::Poisson.events(rate, rng));
+        // Within four standard errors
+        assert!((mean - rate).abs() <= 4.0 * (rate / draws as RR).sqrt(),
+                "rate {}: mean {}",
+                rate,
+                mean);
+        assert!((var - rate).abs() <= 0.1 * rate, "rate {}: variance {}", rate, var);
+        let (mean, var) = moments(&mut |rng| ChurnModel::Fixed.events(rate, rng));
+        assert!((mean - rate).abs() <= 4.0 * (rate * (1.0 - rate) / draws as RR).sqrt() + 1e-12,
+                "rate {}: mean {}",
+                rate,
+                mean);
+        assert!(var <= rate * (1.0 - rate) * 1.1 + 1e-12);
+    }
+    // Large means are drawn in parts, summing to the whole
+    let (mean, var) = moments(&mut |rng| sample_poisson(75.0, rng));
+    assert!((mean - 75.0).abs() <= 4.0 * (75.0 / draws as RR).sqrt(), "mean {}", mean);
+    assert!((var - 75.0).abs() <= 7.5, "variance {}", var);
+}
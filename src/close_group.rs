@@ -0,0 +1,268 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Close groups of addresses, as opposed to sections
+//!
+//! Sections (the groups of the simulations) are responsible for the names
+//! matching their prefix, but data at an address is held by its close group:
+//! the K nodes with names nearest the address (by XOR distance), which may
+//! come from more than one section. With `--close-group K[,A]` the structure
+//! simulation samples A addresses of each network and checks, for each,
+//! whether its section is compromised, whether its close group is, and
+//! whether its close group has no honest member (so data there may be lost).
+
+use super::{NN, RR};
+use super::sim::{Group, NodeName, Prefix};
+
+use rand::Rng;
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use std::str::FromStr;
+
+
+/// Addresses sampled per network if not given
+pub const DEFAULT_ADDRESSES: NN = 100;
+
+/// Analysis of close groups: `size` nodes nearest each of `addresses`
+/// addresses sampled per network. Parsed from `K[,A]`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CloseGroup {
+    pub size: NN,
+    pub addresses: NN,
+}
+
+/// Of the addresses sampled, the number whose section is compromised, whose
+/// close group is compromised and whose close group is all malicious
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CloseGroupCounts {
+    pub section: NN,
+    pub close: NN,
+    pub lost: NN,
+}
+
+impl CloseGroup {
+    /// Sample addresses from `rng` and count how they fare in the network of
+    /// sections `groups`, a group of `k` members being compromised when at
+    /// least `quorum_size(k)` are malicious.
+    pub fn sample<R: Rng>(&self,
+                          groups: &HashMap<Prefix, Group>,
+                          quorum_size: &Fn(NN) -> NN,
+                          rng: &mut R)
+                          -> CloseGroupCounts {
+        let mut names: Vec<NodeName> =
+            groups.values().flat_map(|group| group.keys()).cloned().collect();
+        names.sort();
+        let malicious: HashSet<NodeName> = groups.values()
+            .flat_map(|group| group.iter())
+            .filter(|&(_, data)| data.is_malicious())
+            .map(|(name, _)| *name)
+            .collect();
+        let mut counts = CloseGroupCounts::default();
+        for _ in 0..self.addresses {
+            let address = rng.gen::<NodeName>();
+            let section = groups.iter()
+                .find(|&(prefix, _)| prefix.matches(address))
+                .map(|(_, group)| group)
+                .expect("every address has a section");
+            let bad = section.values().filter(|data| data.is_malicious()).count() as NN;
+            if bad >= quorum_size(section.len() as NN) {
+                counts.section += 1;
+            }
+            let close = nearest(&names, address, self.size as usize);
+            let bad = close.iter().filter(|&name| malicious.contains(name)).count() as NN;
+            if bad >= quorum_size(close.len() as NN) {
+                counts.close += 1;
+            }
+            if bad == close.len() as NN {
+                counts.lost += 1;
+            }
+        }
+        counts
+    }
+}
+
+impl FromStr for CloseGroup {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.trim().split(',').map(|part| part.trim()).collect();
+        if parts.len() > 2 {
+            return Err(format!("expected K[,A], found '{}'", s.trim()));
+        }
+        let mut values = [0, DEFAULT_ADDRESSES];
+        for (value, part) in values.iter_mut().zip(&parts) {
+            *value = try!(part.parse::<NN>().map_err(|_| format!("bad count '{}'", part)));
+        }
+        if values[0] == 0 || values[1] == 0 {
+            return Err("the close group and the addresses must number at least 1".to_owned());
+        }
+        Ok(CloseGroup {
+            size: values[0],
+            addresses: values[1],
+        })
+    }
+}
+
+/// The close groups of a network compared with its sections, as proportions
+/// of the addresses sampled (see the module documentation)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CloseGroupReport {
+    /// Proportion of addresses whose section is compromised
+    pub p_section: RR,
+    /// Proportion whose close group is compromised
+    pub p_close: RR,
+    /// Proportion whose close group has no honest member
+    pub p_loss: RR,
+}
+
+impl CloseGroupReport {
+    /// Report of `counts` over `addresses` addresses
+    pub fn new(counts: CloseGroupCounts, addresses: NN) -> Self {
+        let addresses = addresses as RR;
+        CloseGroupReport {
+            p_section: counts.section as RR / addresses,
+            p_close: counts.close as RR / addresses,
+            p_loss: counts.lost as RR / addresses,
+        }
+    }
+}
+
+/// The `k` names of `names` (sorted, without repeats) nearest `address` by XOR
+/// distance, or all of them if there are no more than `k`.
+///
+/// The names sharing a prefix are consecutive, so the names nearer than any
+/// differing at some bit (those agreeing with the address there) are found by
+/// a binary search within the names agreeing on all earlier bits.
+pub fn nearest(names: &[NodeName], address: NodeName, k: usize) -> Vec<NodeName> {
+    let mut found = Vec::with_capacity(k);
+    add_nearest(names, address, 0, k, &mut found);
+    found
+}
+
+// Add to `found` the `k` of `names` nearest `address`, given that all of them agree with it on
+// the first `bit` bits
+fn add_nearest(names: &[NodeName],
+               address: NodeName,
+               bit: usize,
+               k: usize,
+               found: &mut Vec<NodeName>) {
+    if names.len() <= k {
+        found.extend_from_slice(names);
+        return;
+    }
+    if k == 0 {
+        return;
+    }
+    // Distinct names differ at some bit, so more than one name means bits remain
+    let mask: NodeName = 1 << (mem::size_of::<NodeName>() * 8 - 1 - bit);
+    // No name compares equal, so the search ends at the first name with the bit set
+    let split = names.binary_search_by(|name| if name & mask == 0 {
+            Ordering::Less
+        } else {
+            Ordering::Greater
+        })
+        .unwrap_err();
+    let (zeros, ones) = names.split_at(split);
+    let (near, far) = if address & mask == 0 {
+        (zeros, ones)
+    } else {
+        (ones, zeros)
+    };
+    add_nearest(near, address, bit + 1, k, found);
+    if near.len() < k {
+        add_nearest(far, address, bit + 1, k - near.len(), found);
+    }
+}
+
+#[test]
+fn test_nearest() {
+    use rand::{SeedableRng, XorShiftRng};
+
+    // Names and addresses in a space of 6 bits (at the top of the name), against sorting every
+    // name by distance
+    let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+    for _ in 0..20 {
+        let mut names: Vec<NodeName> =
+            (0..64).filter(|_| rng.gen_weighted_bool(3)).map(|n: NodeName| n << 58).collect();
+        names.sort();
+        for address in (0..64).map(|a: NodeName| a << 58) {
+            let mut by_distance = names.clone();
+            by_distance.sort_by_key(|name| name ^ address);
+            for k in 0..names.len() + 2 {
+                let mut found = nearest(&names, address, k);
+                found.sort_by_key(|name| name ^ address);
+                let expected = &by_distance[..::std::cmp::min(k, names.len())];
+                assert_eq!((address, k, &found[..]), (address, k, expected));
+            }
+        }
+    }
+    assert!(nearest(&[], 5, 3).is_empty());
+}
+
+#[test]
+fn test_close_group() {
+    use super::sim::NodeData;
+    use rand::{SeedableRng, XorShiftRng};
+
+    assert_eq!("3".parse(),
+               Ok(CloseGroup {
+                   size: 3,
+                   addresses: DEFAULT_ADDRESSES,
+               }));
+    assert_eq!(" 4, 50 ".parse(),
+               Ok(CloseGroup {
+                   size: 4,
+                   addresses: 50,
+               }));
+    assert!("0".parse::<CloseGroup>().is_err());
+    assert!("3,0".parse::<CloseGroup>().is_err());
+    assert!("3,5,7".parse::<CloseGroup>().is_err());
+
+    // Sections 0 and 1 of 4 nodes each; the two nodes of section 0 nearest section 1 are
+    // malicious, which the close groups of 2 at the start of section 1 (01...) hold, but no
+    // section has a quorum of 3
+    let top = |bits: NodeName| bits << 60;
+    let mut groups = HashMap::new();
+    for (prefix, names) in vec![("0", vec![0b0000, 0b0001, 0b0110, 0b0111]),
+                                ("1", vec![0b1000, 0b1001, 0b1110, 0b1111])] {
+        let group: Group = names.into_iter()
+            .map(|name| {
+                (top(name),
+                 if name == 0b0110 || name == 0b0111 {
+                     NodeData::new_malicious()
+                 } else {
+                     NodeData::new()
+                 })
+            })
+            .collect();
+        groups.insert(prefix.parse().expect("prefix"), group);
+    }
+    let quorum = |k: NN| k / 2 + 1;
+    let close_group = CloseGroup {
+        size: 2,
+        addresses: 400,
+    };
+    let mut rng = XorShiftRng::from_seed([5, 6, 7, 8]);
+    let counts = close_group.sample(&groups, &quorum, &mut rng);
+    assert_eq!(counts.section, 0);
+    // Addresses starting 01 (a quarter) have both malicious nodes as their close group
+    assert_eq!(counts.close, counts.lost);
+    assert!(counts.close > 60 && counts.close < 140);
+    let report = CloseGroupReport::new(counts, 400);
+    assert_eq!(report.p_loss, counts.lost as RR / 400.0);
+}
@@ -0,0 +1,274 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Stable identifiers of parameter combinations, for referring to the same
+//! combination across runs (in checkpoints, curve files and tables)
+
+use super::{NN, RR};
+use super::args::SimParams;
+use super::checkpoint::fnv1a;
+use super::quorum::{QuorumSize, Weighting, ElderTiebreak, AttackerKnowledge};
+use super::uptime::UptimeDist;
+use super::placement::InitialPlacement;
+
+
+/// Version of the serialisation and hash giving combination ids. It prefixes
+/// every id, so must be increased whenever either changes (e.g. a parameter
+/// is added, unless only written when not its default, which leaves existing
+/// ids as they were); ids of different schemes then never match.
+pub const COMBO_ID_SCHEME: u32 = 1;
+
+/// Title of the column of combination ids
+pub const COMBO_ID_TITLE: &'static str = "ComboId";
+
+/// Identifier of the parameter combination of `params`: the scheme version
+/// and a 48-bit hash of `serialise(params)`, e.g. `v1-3fa4c2d1907e`.
+pub fn combo_id(params: &SimParams) -> String {
+    let hash = fnv1a(&serialise(params)) & 0xffff_ffff_ffff;
+    format!("v{}-{:012x}", COMBO_ID_SCHEME, hash)
+}
+
+/// Canonical serialisation of every parameter of `params` which determines
+/// what is calculated, as `name=value` pairs in a fixed order, one per line.
+/// Floats are written exactly (see `exact`), so that distinct values never
+/// share a serialisation.
+///
+/// Repetitions and seeds are left out: runs of the same combination with more
+/// repetitions or another seed estimate the same probabilities. So are the
+/// reports requested and how the run is carried out (e.g. time limits and
+/// checkpointing).
+pub fn serialise(params: &SimParams) -> String {
+    let fields = vec![("scheme", COMBO_ID_SCHEME.to_string()),
+                      ("type", params.sim_type.name().to_owned()),
+                      ("quorum", params.quorum_type.name().to_owned()),
+                      ("elders", optional(params.elders)),
+                      ("promotion_lag", optional(params.promotion_lag)),
+                      ("weighting", weighting(params.weighting.as_ref())),
+                      ("age_cap", params.age_cap.map_or("none".to_owned(), |c| c.to_string())),
+                      ("targetting", params.targetting_name()),
+                      ("objective", params.objective.name().to_owned()),
+                      ("target_prefix",
+                       params.target_prefix.map_or("any".to_owned(), |p| p.to_string())),
+                      ("relocation", params.relocation.name().to_owned()),
+                      ("honest_uptime", dist(&params.honest_uptime)),
+                      ("churn_model", params.churn_model.name().to_owned()),
+                      ("burst",
+                       params.burst
+                           .map_or("none".to_owned(),
+                                   |b| format!("{},{}", exact(b.prob), exact(b.fraction)))),
+                      ("corruption",
+                       params.corruption.as_ref().map_or("none".to_owned(), |c| {
+                           let events: Vec<String> = c.events
+                               .iter()
+                               .map(|&(fraction, step)| format!("{}@{}", exact(fraction), step))
+                               .collect();
+                           events.join(",")
+                       })),
+                      ("eviction_rate", exact(params.eviction_rate)),
+                      ("attack_resource", exact(params.attack_resource)),
+                      ("rejoin_recovery", exact(params.rejoin_recovery)),
+                      ("rejoin_window", params.rejoin_window.to_string()),
+                      ("pow_scaling", params.pow_scaling.name().to_owned()),
+                      ("attack_start", params.attack_start.to_string()),
+                      ("hold", params.hold.to_string()),
+                      ("membership_lag", params.membership_lag.to_string()),
+                      ("num_nodes", params.num_nodes.to_string()),
+                      // As simulated: a proportion and the count it gives are the same
+                      ("num_malicious",
+                       params.num_malicious.from_base(params.num_nodes).to_string()),
+                      ("min_group_size", params.min_group_size.to_string()),
+                      ("quorum_size",
+                       match params.quorum {
+                           QuorumSize::Proportion(prop) => format!("prop:{}", exact(prop)),
+                           QuorumSize::Count(n) => format!("count:{}", n),
+                       }),
+                      ("quorum_basis", params.quorum_basis.name().to_owned()),
+                      ("quorum_floor", params.quorum_floor.map_or("none".to_owned(), |f| {
+                          f.to_string()
+                      })),
+                      ("block_prop", params.block_prop.map_or("quorum".to_owned(), exact)),
+                      ("max_steps", params.max_steps.to_string()),
+                      ("any_group_model", params.any_group_model.name().to_owned()),
+                      ("correction", params.correction.to_string())];
+    let mut serialised: String =
+        fields.iter().map(|&(name, ref value)| format!("{}={}\n", name, value)).collect();
+    // Added within scheme 1, so written only when not the default: combinations without it
+    // keep their ids
+    if let InitialPlacement::Concentrated(fraction) = params.placement {
+        serialised.push_str(&format!("placement=concentrated({})\n", exact(fraction)));
+    }
+    if params.quorum_jitter != 0.0 {
+        serialised.push_str(&format!("quorum_jitter={}\n", exact(params.quorum_jitter)));
+    }
+    if params.participation != 1.0 {
+        serialised.push_str(&format!("participation={}\n", exact(params.participation)));
+    }
+    if let Some(tiebreak) = params.elder_tiebreak {
+        if tiebreak != ElderTiebreak::Name {
+            serialised.push_str(&format!("elder_tiebreak={}\n", tiebreak.name()));
+        }
+    }
+    if let Some(grinding) = params.name_grinding {
+        if grinding != 0.0 {
+            serialised.push_str(&format!("name_grinding={}\n", exact(grinding)));
+        }
+    }
+    if let Some(cap) = params.join_cap {
+        serialised.push_str(&format!("join_cap={}\n", cap));
+    }
+    if params.attacker_knowledge != AttackerKnowledge::Full {
+        serialised.push_str(&format!("attacker_knowledge={}\n", params.attacker_knowledge.name()));
+    }
+    if let Some(steady) = params.steady_state {
+        serialised.push_str(&format!("steady_state={},{}\n", steady.burn_in, steady.batches));
+    }
+    serialised
+}
+
+/// Exact encoding of a float: the shortest decimal which reads back as the
+/// same value, in scientific notation (so independent of magnitude), e.g.
+/// `1e-1` or `3.0000000000000004e-1`.
+pub fn exact(x: RR) -> String {
+    format!("{:e}", x)
+}
+
+// A count which only some quorums have, or "-"
+fn optional(n: Option<NN>) -> String {
+    n.map_or("-".to_owned(), |n| n.to_string())
+}
+
+fn dist(dist: &UptimeDist) -> String {
+    match *dist {
+        UptimeDist::Constant(x) => exact(x),
+        UptimeDist::Uniform(a, b) => format!("uniform({},{})", exact(a), exact(b)),
+        UptimeDist::Mix(ref mix) => {
+            let parts: Vec<String> =
+                mix.iter().map(|&(x, w)| format!("{}@{}", exact(x), exact(w))).collect();
+            parts.join(",")
+        }
+    }
+}
+
+fn weighting(weighting: Option<&Weighting>) -> String {
+    match weighting {
+        None => "-".to_owned(),
+        Some(&Weighting::Capacity(ref capacity)) => format!("capacity({})", dist(capacity)),
+        Some(w) => w.to_string(),
+    }
+}
+
+/// Arguments of the tool whose combinations to identify, from those of `id`:
+/// `id full ...` and `id structure ...` identify those of the full simulation
+/// and of the structure simulation, and `id ...` those of the direct
+/// calculation.
+pub fn tool_argv(argv: &[String]) -> Vec<String> {
+    let tool = argv.get(2).and_then(|arg| match arg.as_str() {
+        "full" | "structure" => Some(arg.clone()),
+        _ => None,
+    });
+    let rest = argv.iter().skip(if tool.is_some() { 3 } else { 2 }).cloned();
+    argv.iter()
+        .take(1)
+        .cloned()
+        .chain(Some(tool.unwrap_or("calc".to_owned())))
+        .chain(rest)
+        .collect()
+}
+
+#[test]
+fn test_combo_id_golden() {
+    use super::args::sim_params;
+
+    let ids = |args: &[&str]| -> Vec<String> { sim_params(args).iter().map(combo_id).collect() };
+    // These must never change within a scheme: ids are compared across runs
+    // and builds. If a change to the parameters makes them change, increase
+    // COMBO_ID_SCHEME and update them.
+    assert_eq!(ids(&["calc"]), vec!["v1-3e6032897fc0"]);
+    assert_eq!(ids(&["structure", "-n", "500", "-r", "5%,20"]),
+               vec!["v1-febe67bc2960", "v1-b52faa13a199"]);
+    assert_eq!(ids(&["full", "-Q", "elder", "-T", "simple", "--burst", "0.1,0.25", "-s", "200"]),
+               vec!["v1-e2577cabffd5"]);
+}
+
+#[test]
+fn test_combo_id() {
+    use super::args::sim_params;
+
+    let ids = |args: &[&str]| -> Vec<String> { sim_params(args).iter().map(combo_id).collect() };
+    let id = |args: &[&str]| {
+        let mut ids = ids(args);
+        assert_eq!(ids.len(), 1);
+        ids.remove(0)
+    };
+    let base = id(&["full", "-n", "200", "-s", "50"]);
+    assert!(base.starts_with(&format!("v{}-", COMBO_ID_SCHEME)));
+    assert_eq!(base.len(), 15);
+    // Neither repetitions nor seeds change the combination
+    assert_eq!(id(&["full", "-n", "200", "-s", "50", "-p", "7", "--seed", "3"]), base);
+    // Nor whether malicious nodes are given as a proportion (10% is the default)
+    assert_eq!(id(&["full", "-n", "200", "-s", "50", "-r", "20"]), base);
+    // Nor full participation, given or not
+    assert_eq!(id(&["full", "-n", "200", "-s", "50", "--participation", "1"]), base);
+    // Nor the default attacker knowledge, given or not
+    assert_eq!(id(&["full", "-n", "200", "-s", "50", "--attacker-knowledge", "full"]), base);
+    // Nor the default elder tiebreak, without grinding
+    let elder = id(&["full", "-n", "200", "-s", "50", "-Q", "elder"]);
+    assert_eq!(id(&["full", "-n", "200", "-s", "50", "-Q", "elder", "--elder-tiebreak", "name",
+                    "--name-grinding", "0"]),
+               elder);
+    for option in &[["--elder-tiebreak", "join-order"], ["--name-grinding", "0.5"]] {
+        let mut args = vec!["full", "-n", "200", "-s", "50", "-Q", "elder"];
+        args.extend_from_slice(option);
+        assert!(id(&args) != elder, "{:?}", args);
+    }
+    // Every parameter does
+    for args in &[&["full", "-n", "201", "-s", "50"][..],
+                  &["full", "-n", "200", "-s", "51"][..],
+                  &["full", "-n", "200", "-s", "50", "-Q", "age"][..],
+                  &["full", "-n", "200", "-s", "50", "-r", "21"][..],
+                  &["full", "-n", "200", "-s", "50", "--eviction-rate", "0.01"][..],
+                  &["full", "-n", "200", "-s", "50", "--churn-model", "poisson"][..],
+                  &["full", "-n", "200", "-s", "50", "--initial-placement", "concentrated(1)"][..],
+                  &["full", "-n", "200", "-s", "50", "--participation", "0.9"][..],
+                  &["full", "-n", "200", "-s", "50", "--attacker-knowledge", "own-nodes-only"][..],
+                  &["full", "-n", "200", "-s", "50", "--join-cap", "2:10"][..],
+                  &["full", "-n", "200", "-s", "50", "--steady-state", "20,4"][..],
+                  &["structure", "-n", "200", "-s", "50"][..]] {
+        assert!(id(args) != base, "{:?}", args);
+    }
+    // Floats are exact: values differing in the last bit differ
+    assert_eq!(exact(0.1), "1e-1");
+    assert!(exact(0.1 + 0.2) != exact(0.3));
+    assert_eq!(exact(0.1 + 0.2).parse::<RR>(), Ok(0.1 + 0.2));
+    // One id for each combination, in the order of the table
+    let sweep = ids(&["calc", "-n", "100-300:100"]);
+    assert_eq!(sweep[1], id(&["calc", "-n", "200"]));
+}
+
+#[test]
+fn test_tool_argv() {
+    let argv = |args: &[&str]| -> Vec<String> {
+        let argv: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        tool_argv(&argv)
+    };
+    assert_eq!(argv(&["routing-sims", "id", "-n", "10"]),
+               vec!["routing-sims", "calc", "-n", "10"]);
+    assert_eq!(argv(&["routing-sims", "id", "structure"]),
+               vec!["routing-sims", "structure"]);
+    assert_eq!(argv(&["routing-sims", "id", "full", "-Q", "age"]),
+               vec!["routing-sims", "full", "-Q", "age"]);
+}
@@ -0,0 +1,156 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Corruption of honest nodes in place (key compromise or bribery)
+
+use super::{NN, RR};
+use super::sim::{Group, NodeName};
+
+use std::fmt::{self, Formatter};
+use std::str::FromStr;
+
+use rand::Rng;
+
+
+/// Corruption of honest nodes in place, as by key compromise or bribery: at
+/// the step of each event, the proportion given of the honest nodes then in
+/// the network turn malicious, keeping their names, ages and groups. Unlike
+/// joining nodes, corrupted nodes bring the age honest nodes have earned, so
+/// this is the worst case for ageing defences.
+///
+/// Written (and parsed) as `F@STEP`, several events separated by commas, e.g.
+/// `0.1@0,0.05@200`; since output is comma separated, `Display` uses `;`
+/// instead, and either is accepted when parsing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Corruption {
+    /// The proportion of honest nodes corrupted and the step, of each event,
+    /// in order of step
+    pub events: Vec<(RR, NN)>,
+}
+
+impl Corruption {
+    /// Proportions of honest nodes corrupted at `step`, in turn
+    pub fn fractions_at(&self, step: NN) -> Vec<RR> {
+        self.events
+            .iter()
+            .filter(|&&(_, at)| at == step)
+            .map(|&(fraction, _)| fraction)
+            .collect()
+    }
+
+    /// Step of the last event
+    pub fn last_step(&self) -> NN {
+        self.events.last().map_or(0, |&(_, step)| step)
+    }
+
+    /// Honest nodes corrupted in one event of `fraction`: that proportion of
+    /// all honest nodes (rounded to the nearest node), drawn from `rng`.
+    pub fn victims<'a, I, R>(fraction: RR, groups: I, rng: &mut R) -> Vec<NodeName>
+        where I: IntoIterator<Item = &'a Group>,
+              R: Rng
+    {
+        // In name order, so the draw does not depend on the order of the groups
+        let mut honest: Vec<NodeName> = groups.into_iter()
+            .flat_map(|group| group.iter())
+            .filter(|&(_, data)| !data.is_malicious())
+            .map(|(name, _)| *name)
+            .collect();
+        honest.sort();
+        let count = (fraction * honest.len() as RR).round() as usize;
+        for i in 0..count {
+            let j = rng.gen_range(i, honest.len());
+            honest.swap(i, j);
+        }
+        honest.truncate(count);
+        honest
+    }
+}
+
+/// Corruption as written in the Corrupt column: "none" if not given.
+pub fn corruption_name(corruption: Option<&Corruption>) -> String {
+    corruption.map_or("none".to_owned(), |c| c.to_string())
+}
+
+impl fmt::Display for Corruption {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let events: Vec<String> =
+            self.events.iter().map(|&(fraction, step)| format!("{}@{}", fraction, step)).collect();
+        f.pad(&events.join(";"))
+    }
+}
+
+impl FromStr for Corruption {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let separators: &[char] = &[',', ';'];
+        let mut events = Vec::new();
+        for part in s.trim().split(separators) {
+            let mut iter = part.split('@');
+            let (fraction, step) = match (iter.next(), iter.next(), iter.next()) {
+                (Some(fraction), Some(step), None) => (fraction.trim(), step.trim()),
+                _ => return Err(format!("expected F@STEP, found '{}'", part.trim())),
+            };
+            let fraction = match fraction.parse::<RR>() {
+                Ok(x) if x >= 0.0 && x <= 1.0 => x,
+                Ok(_) => return Err(format!("{} outside range 0-1", fraction)),
+                Err(_) => return Err(format!("bad number '{}'", fraction)),
+            };
+            let step = try!(step.parse::<NN>().map_err(|_| format!("bad step '{}'", step)));
+            events.push((fraction, step));
+        }
+        // Stable, so events of the same step keep their order
+        events.sort_by_key(|&(_, step)| step);
+        Ok(Corruption { events: events })
+    }
+}
+
+#[test]
+fn test_corruption() {
+    use super::sim::NodeData;
+    use rand::{SeedableRng, XorShiftRng};
+
+    let corruption: Corruption = "0.2@50,0.1@0;0.3@50".parse().expect("parse");
+    assert_eq!(corruption.events, vec![(0.1, 0), (0.2, 50), (0.3, 50)]);
+    assert_eq!(corruption.to_string(), "0.1@0;0.2@50;0.3@50");
+    assert_eq!(corruption.to_string().parse(), Ok(corruption.clone()));
+    assert_eq!(corruption_name(None), "none");
+    assert_eq!((corruption.fractions_at(50), corruption.fractions_at(1)), (vec![0.2, 0.3], vec![]));
+    assert_eq!(corruption.last_step(), 50);
+    assert_eq!("1.5@2".parse::<Corruption>(), Err("1.5 outside range 0-1".to_owned()));
+    assert_eq!("0.1".parse::<Corruption>(), Err("expected F@STEP, found '0.1'".to_owned()));
+    assert_eq!("0.1@x".parse::<Corruption>(), Err("bad step 'x'".to_owned()));
+
+    // Nodes 0-19, of which 3 is malicious
+    let mut group = Group::new();
+    for name in 0..20 {
+        group.insert(name,
+                     if name == 3 {
+                         NodeData::new_malicious()
+                     } else {
+                         NodeData::new()
+                     });
+    }
+    let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+    let victims = Corruption::victims(0.5, Some(&group), &mut rng);
+    assert_eq!(victims.len(), 10);   // half of the 19 honest nodes
+    assert!(!victims.contains(&3));
+    let mut distinct = victims.clone();
+    distinct.sort();
+    distinct.dedup();
+    assert_eq!(distinct.len(), 10);
+    assert!(Corruption::victims(0.0, Some(&group), &mut rng).is_empty());
+}
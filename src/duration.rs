@@ -0,0 +1,140 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Durations as given to options and shown to people: a plain number of
+//! seconds, or components of days, hours, minutes and seconds, e.g. `90s`,
+//! `15m` or `2h30m`
+
+use super::RR;
+
+/// Units of the components, largest first, with their lengths in seconds
+const UNITS: [(char, RR); 4] = [('d', 86400.0), ('h', 3600.0), ('m', 60.0), ('s', 1.0)];
+
+/// Parse a duration in seconds: a plain number (of seconds), or one or more
+/// components, each a number followed by a unit (`d`, `h`, `m` or `s`), with
+/// the units in decreasing order and each at most once, e.g. `2h30m`. Only the
+/// last component may have a fraction (`1.5h`, `2h7.5m`), so that `1.5h30m`
+/// is rejected as ambiguous. Empty and negative durations are rejected.
+pub fn parse_duration(s: &str) -> Result<RR, String> {
+    if s.is_empty() {
+        return Err("empty duration".to_owned());
+    }
+    if s.starts_with('-') {
+        return Err("negative duration".to_owned());
+    }
+    if let Ok(secs) = s.parse::<RR>() {
+        return if secs.is_finite() {
+            Ok(secs)
+        } else {
+            Err("not a number of seconds".to_owned())
+        };
+    }
+    let mut secs = 0.0;
+    let mut number = String::new();
+    // Index in `UNITS` of the last unit, and whether its number had a fraction
+    let mut last: Option<(usize, bool)> = None;
+    for c in s.chars() {
+        if c.is_digit(10) || c == '.' {
+            number.push(c);
+            continue;
+        }
+        let unit = match UNITS.iter().position(|&(unit, _)| unit == c) {
+            Some(unit) => unit,
+            None => return Err(format!("unknown unit '{}' (use d, h, m or s)", c)),
+        };
+        if number.is_empty() {
+            return Err(format!("no number before '{}'", c));
+        }
+        match last {
+            Some((previous, _)) if previous >= unit => {
+                return Err("units must decrease, each used once".to_owned());
+            }
+            Some((_, true)) => {
+                return Err("only the last component may have a fraction".to_owned());
+            }
+            _ => {}
+        }
+        let x: RR = try!(number.parse().map_err(|_| format!("bad number '{}'", number)));
+        secs += x * UNITS[unit].1;
+        last = Some((unit, number.contains('.')));
+        number.clear();
+    }
+    if !number.is_empty() {
+        return Err(format!("no unit after '{}'", number));
+    }
+    Ok(secs)
+}
+
+/// Format a duration in seconds for people to read, in the form parsed by
+/// `parse_duration`: the non-zero components of whole seconds, e.g. `2h30m`
+/// or `1d4h`, or tenths of seconds below a minute, e.g. `3.5s`.
+pub fn format_duration(secs: RR) -> String {
+    if secs < 60.0 {
+        let text = format!("{:.1}", secs);
+        return format!("{}s", text.trim_right_matches(".0"));
+    }
+    let mut left = secs.round();
+    let mut text = String::new();
+    for &(unit, length) in &UNITS {
+        let n = (left / length).floor();
+        if n > 0.0 {
+            text.push_str(&format!("{}{}", n, unit));
+            left -= n * length;
+        }
+    }
+    text
+}
+
+#[test]
+fn test_parse_duration() {
+    assert_eq!(parse_duration("90"), Ok(90.0));
+    assert_eq!(parse_duration("90s"), Ok(90.0));
+    assert_eq!(parse_duration("1.5m"), Ok(90.0));
+    assert_eq!(parse_duration("12h"), Ok(43200.0));
+    assert_eq!(parse_duration("2d"), Ok(172800.0));
+    // Compound forms
+    assert_eq!(parse_duration("2h30m"), Ok(9000.0));
+    assert_eq!(parse_duration("1d2h3m4s"), Ok(93784.0));
+    assert_eq!(parse_duration("1h30s"), Ok(3630.0));
+    assert_eq!(parse_duration("2h7.5m"), Ok(7650.0));
+    // Zero
+    assert_eq!(parse_duration("0"), Ok(0.0));
+    assert_eq!(parse_duration("0s"), Ok(0.0));
+    assert_eq!(parse_duration("0h0m"), Ok(0.0));
+    // Rejected
+    for bad in &["", "-1", "-1m", "h", "2w", "1.5h30m", "30m2h", "1h1h", "2h30", "1..5m", "inf",
+                 "1 h"] {
+        assert!(parse_duration(bad).is_err(), "{:?}", bad);
+    }
+    assert_eq!(parse_duration("1.5h30m"),
+               Err("only the last component may have a fraction".to_owned()));
+}
+
+#[test]
+fn test_format_duration() {
+    assert_eq!(format_duration(0.0), "0s");
+    assert_eq!(format_duration(3.5), "3.5s");
+    assert_eq!(format_duration(42.0), "42s");
+    assert_eq!(format_duration(90.0), "1m30s");
+    assert_eq!(format_duration(9000.0), "2h30m");
+    assert_eq!(format_duration(93784.4), "1d2h3m4s");
+    assert_eq!(format_duration(86400.0 + 5.0), "1d5s");
+    // Read back as the same duration
+    for &secs in &[42.0, 90.0, 3630.0, 93784.0] {
+        assert_eq!(parse_duration(&format_duration(secs)), Ok(secs));
+    }
+}
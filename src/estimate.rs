@@ -0,0 +1,331 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Rough estimates of run time and memory, made before starting
+
+use super::{ToolArgs, NN, RR};
+use super::args::{SimParams, SimType};
+use super::quorum::{QuorumSize, SimpleQuorum, UntargettedAttack};
+use super::sim::{Group, NodeData, NodeName, Prefix};
+use super::tools::{Tool, FullSimTool};
+use super::rng::schedule_rng;
+
+use std::cmp::Ordering;
+use std::mem;
+use std::sync::{Condvar, Mutex};
+
+use rand::Rng;
+
+
+/// Capacity of a hash table over the number of entries in it, on average:
+/// tables grow by doubling, so are between about half and fully used
+const TABLE_SLACK: RR = 1.6;
+/// Mean size of a group as a multiple of the minimum (groups split at about
+/// twice the minimum)
+const GROUP_SIZE_FACTOR: RR = 1.5;
+
+/// Amount of work needed for one parameter set, in node-steps.
+///
+/// For the full simulation this is `repetitions × max_steps × num_nodes`. The
+/// structure simulation builds a single network, counted as `num_nodes`, and
+/// direct calculation is counted as no work.
+pub fn work_units(params: &SimParams) -> RR {
+    node_steps(params.sim_type,
+               params.num_nodes,
+               params.max_steps,
+               params.repetitions)
+}
+
+/// Work of a tool for the given parameters, in node-steps (see `work_units`).
+pub fn node_steps(sim_type: SimType, num_nodes: NN, max_steps: NN, repetitions: NN) -> RR {
+    match sim_type {
+        SimType::DirectCalc => 0.0,
+        SimType::Structure => num_nodes as RR,
+        SimType::FullSim => (repetitions as RR) * (max_steps as RR) * (num_nodes as RR),
+    }
+}
+
+/// Total work for all parameter sets, in node-steps.
+pub fn total_work_units(param_sets: &[SimParams]) -> RR {
+    param_sets.iter().map(work_units).sum()
+}
+
+/// Measure the time (in seconds) per node-step by running a tiny throwaway
+/// full simulation.
+pub fn calibrate() -> RR {
+    const NODES: NN = 200;
+    const STEPS: NN = 50;
+    const REPS: NN = 2;
+    let args = ToolArgs::new(NODES, NODES / 10, 8, QuorumSize::Proportion(0.5), STEPS, REPS);
+    let tool = FullSimTool::new(args, SimpleQuorum::new(), UntargettedAttack);
+    let (_, secs) = tool.timed_calc();
+    secs / ((REPS * STEPS * NODES) as RR)
+}
+
+/// Estimated run time (seconds) for all parameter sets, given the time per
+/// node-step (see `calibrate`). This assumes a single thread.
+pub fn estimate_secs(param_sets: &[SimParams], secs_per_unit: RR) -> RR {
+    total_work_units(param_sets) * secs_per_unit
+}
+
+/// Order in which to start the parameter sets (see `--schedule`). Whatever the
+/// order, results are output in the order of expansion.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Schedule {
+    /// The order of expansion
+    Expansion,
+    /// By descending estimated work (see `work_units`), so that the longest
+    /// sets do not start last and keep one thread busy after the others finish
+    Cost,
+    /// A random order, drawn with the given seed
+    Shuffle(u64),
+}
+
+/// The indices of `param_sets` in the order to start them.
+pub fn schedule(param_sets: &[SimParams], schedule: Schedule) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..param_sets.len()).collect();
+    match schedule {
+        Schedule::Expansion => {}
+        Schedule::Cost => {
+            let work: Vec<RR> = param_sets.iter().map(work_units).collect();
+            // Stable, so sets of equal work keep the order of expansion
+            order.sort_by(|&i, &j| work[j].partial_cmp(&work[i]).unwrap_or(Ordering::Equal));
+        }
+        Schedule::Shuffle(seed) => schedule_rng(seed).shuffle(&mut order),
+    }
+    order
+}
+
+/// Bytes of a hash table entry, with the hash kept with it
+fn entry_bytes(size: usize) -> RR {
+    (size + mem::size_of::<u64>()) as RR
+}
+
+/// Estimated memory of a network of `nodes` nodes, in bytes: that of the
+/// table of each group's nodes, and of the table of groups.
+pub fn network_bytes(nodes: NN, min_group_size: NN) -> RR {
+    let groups = (nodes as RR / (GROUP_SIZE_FACTOR * min_group_size as RR)).max(1.0);
+    TABLE_SLACK *
+    (nodes as RR * entry_bytes(mem::size_of::<(NodeName, NodeData)>()) +
+     groups * entry_bytes(mem::size_of::<(Prefix, Group)>()))
+}
+
+/// Estimated peak memory of one parameter set, in bytes: that of the network
+/// simulated, of all the nodes (in a full simulation, the honest nodes and the
+/// malicious nodes added). Direct calculation is counted as none. Repetitions
+/// run one at a time, so do not add to it.
+pub fn memory_bytes(params: &SimParams) -> RR {
+    match params.sim_type {
+        SimType::DirectCalc => 0.0,
+        SimType::Structure | SimType::FullSim => {
+            network_bytes(params.num_nodes, params.min_group_size)
+        }
+    }
+}
+
+/// Memory shared by parameter sets running at once (see `--max-mem`): each
+/// reserves its estimate before starting, and waits while that would take
+/// the total over the cap, unless nothing else is running. Sets estimated
+/// above the cap should not be run at all. Whole bytes are counted, so that
+/// reserving and releasing leaves no rounding error behind.
+pub struct MemoryBudget {
+    cap: NN,
+    /// Bytes reserved by the sets running
+    in_use: Mutex<NN>,
+    released: Condvar,
+}
+
+impl MemoryBudget {
+    /// Budget of `cap` bytes (rounded down to whole bytes)
+    pub fn new(cap: RR) -> Self {
+        MemoryBudget {
+            cap: cap.floor() as NN,
+            in_use: Mutex::new(0),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Reserve `bytes` (rounded up to whole bytes) until the reservation is
+    /// dropped, waiting for others to be released if needed.
+    pub fn reserve(&self, bytes: RR) -> Reservation {
+        let bytes = bytes.ceil() as NN;
+        let mut in_use = self.in_use.lock().expect("lock");
+        while *in_use > 0 && *in_use + bytes > self.cap {
+            in_use = self.released.wait(in_use).expect("lock");
+        }
+        *in_use += bytes;
+        Reservation {
+            budget: self,
+            bytes: bytes,
+        }
+    }
+}
+
+/// Memory reserved from a `MemoryBudget`, released when dropped
+pub struct Reservation<'a> {
+    budget: &'a MemoryBudget,
+    bytes: NN,
+}
+
+impl<'a> Drop for Reservation<'a> {
+    fn drop(&mut self) {
+        let mut in_use = self.budget.in_use.lock().expect("lock");
+        *in_use -= self.bytes;
+        self.budget.released.notify_all();
+    }
+}
+
+/// Format a number of bytes for people to read, e.g. "1.5 GiB".
+pub fn format_bytes(bytes: RR) -> String {
+    let units = ["bytes", "KiB", "MiB", "GiB"];
+    let mut value = bytes;
+    for unit in &units[..units.len() - 1] {
+        if value < 1024.0 {
+            return format!("{:.1} {}", value, unit);
+        }
+        value /= 1024.0;
+    }
+    format!("{:.1} {}", value, units[units.len() - 1])
+}
+
+#[test]
+fn test_estimate() {
+    use super::args::sim_params;
+
+    let full = sim_params(&["full", "-n", "100-200:100", "-s", "10", "-p", "3", "-Q", "all"]);
+    // 3 quorum types × 3 reps × 10 steps × (100 + 200) nodes
+    assert_eq!(total_work_units(&full), 27000.0);
+    assert_eq!(estimate_secs(&full, 0.25), 6750.0);
+
+    let structure = sim_params(&["structure", "-n", "100-200:100", "-s", "10", "-p", "3"]);
+    assert_eq!(estimate_secs(&structure, 0.25), 75.0);
+
+    let calc = sim_params(&["calc", "-n", "100-200:100"]);
+    assert_eq!(estimate_secs(&calc, 0.25), 0.0);
+}
+
+#[test]
+fn test_schedule() {
+    use super::args::sim_params;
+
+    let sets = sim_params(&["full", "-n", "100-300:100", "-k", "8", "-s", "20", "-p", "4",
+                            "-Q", "all", "--seed", "5"]);
+    let expansion: Vec<usize> = (0..9).collect();
+    assert_eq!(schedule(&sets, Schedule::Expansion), expansion);
+    // The quorum types of each network size take the same work, and keep their order
+    assert_eq!(schedule(&sets, Schedule::Cost), vec![2, 5, 8, 1, 4, 7, 0, 3, 6]);
+    let shuffled = schedule(&sets, Schedule::Shuffle(7));
+    assert_eq!(shuffled, schedule(&sets, Schedule::Shuffle(7)));
+    let mut sorted = shuffled.clone();
+    sorted.sort();
+    assert!(shuffled != expansion);
+    assert_eq!(sorted, expansion);
+
+    // Run in any order and put back in order, a seeded sweep gives the same results
+    let results = |order: Vec<usize>| {
+        let mut results: Vec<(usize, (RR, RR, RR))> = order.into_iter()
+            .map(|i| {
+                let result = sets[i].result();
+                (i, (result.p_disrupt, result.p_compromise, result.groups))
+            })
+            .collect();
+        results.sort_by_key(|&(i, _)| i);
+        results
+    };
+    let expected = results(schedule(&sets, Schedule::Expansion));
+    assert_eq!(results(schedule(&sets, Schedule::Cost)), expected);
+    assert_eq!(results(shuffled), expected);
+}
+
+#[test]
+fn test_memory_bytes() {
+    use super::args::sim_params;
+    use super::sim::{Network, NoAddRestriction, new_node_name};
+    use rand::{SeedableRng, XorShiftRng};
+
+    // The estimate is within a quarter of the memory held by the tables of a
+    // network built as by the structure simulation
+    let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+    for &(nodes, min_group_size) in &[(1000, 8), (20000, 10)] {
+        let mut net = Network::<NoAddRestriction>::new(min_group_size as usize);
+        let mut added = 0;
+        while added < nodes {
+            let name = new_node_name(&mut rng);
+            if let Ok(prefix) = net.add_node(name, NodeData::new()) {
+                added += 1;
+                let _ = net.maybe_split(prefix, name, &mut UntargettedAttack {});
+            }
+        }
+        let (estimate, actual) = (network_bytes(nodes, min_group_size), net.heap_bytes() as RR);
+        assert!((estimate / actual - 1.0).abs() < 0.25,
+                "{} nodes: estimated {}, actual {}",
+                nodes,
+                estimate,
+                actual);
+    }
+
+    let full = sim_params(&["full", "-n", "1000", "-r", "100", "-k", "8"]);
+    assert_eq!(memory_bytes(&full[0]), network_bytes(1000, 8));
+    let calc = sim_params(&["calc", "-n", "1000"]);
+    assert_eq!(memory_bytes(&calc[0]), 0.0);
+    assert_eq!(format_bytes(512.0), "512.0 bytes");
+    assert_eq!(format_bytes(1.5 * 1024.0 * 1024.0 * 1024.0), "1.5 GiB");
+}
+
+#[test]
+fn test_memory_budget() {
+    use std::sync::Arc;
+    use std::thread;
+
+    // Sets estimated at 600 and 300 bytes under a cap of 1000: the two big
+    // ones never run together, though a big and a small one may
+    let budget = Arc::new(MemoryBudget::new(1000.0));
+    let peak = Arc::new(Mutex::new((0.0, 0.0)));
+    let threads: Vec<_> = [600.0, 600.0, 300.0, 600.0, 300.0]
+        .iter()
+        .map(|&bytes| {
+            let (budget, peak) = (budget.clone(), peak.clone());
+            thread::spawn(move || {
+                let _reserved = budget.reserve(bytes);
+                {
+                    let mut peak = peak.lock().expect("lock");
+                    peak.0 += bytes;
+                    peak.1 = peak.0.max(peak.1);
+                }
+                thread::sleep(::std::time::Duration::from_millis(20));
+                peak.lock().expect("lock").0 -= bytes;
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().expect("thread");
+    }
+    let peak = peak.lock().expect("lock").1;
+    assert!(peak <= 1000.0 && peak >= 600.0, "peak {}", peak);
+    assert_eq!(*budget.in_use.lock().expect("lock"), 0);
+
+    // A set above the cap still runs, alone
+    let reserved = budget.reserve(5000.0);
+    assert_eq!(*budget.in_use.lock().expect("lock"), 5000);
+    drop(reserved);
+
+    // Estimates are not whole bytes, but what is released is exactly what was reserved
+    for _ in 0..1000 {
+        let _reserved = (budget.reserve(0.1), budget.reserve(1.0 / 3.0));
+    }
+    assert_eq!(*budget.in_use.lock().expect("lock"), 0);
+}
@@ -0,0 +1,235 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Breakdown of a single parameter set (see `routing-sims explain`)
+//!
+//! The direct calculation is shown step by step. For a full simulation it is
+//! followed by the simulated P(compromise), with a confidence interval, and
+//! the mean number of steps to compromise.
+
+use super::{NN, RR};
+use super::args::{SimParams, SimType, RelOrAbs};
+use super::baseline::Z_CRITICAL;
+use super::output::ProbFormat;
+use super::tools::DirectCalcTool;
+
+
+/// Width of the labels of the report
+const LABEL_WIDTH: usize = 32;
+
+/// Arguments of the tool to explain, from those of `explain`: `explain full
+/// ...` explains a full simulation and `explain ...` a direct calculation.
+pub fn tool_argv(argv: &[String]) -> Vec<String> {
+    let full = argv.get(2).map_or(false, |arg| arg == "full");
+    let tool = if full { "full" } else { "calc" };
+    let rest = argv.iter().skip(if full { 3 } else { 2 }).cloned();
+    argv.iter().take(1).cloned().chain(Some(tool.to_owned())).chain(rest).collect()
+}
+
+/// The one parameter set to explain, or an error if the options give more
+/// (ranges, lists or `all`) or none.
+pub fn single(mut param_sets: Vec<SimParams>) -> Result<SimParams, String> {
+    if param_sets.len() != 1 {
+        return Err(format!("routing-sims explain: the options give {} parameter sets; give \
+                            single values only",
+                           param_sets.len()));
+    }
+    Ok(param_sets.remove(0))
+}
+
+/// Wilson score interval `(low, high)` of a proportion, from `successes` of
+/// `n` trials, at the level of `baseline::Z_CRITICAL`. Unlike the normal
+/// approximation it does not collapse when none (or all) succeed.
+pub fn wilson_interval(successes: NN, n: NN) -> (RR, RR) {
+    if n == 0 {
+        return (0.0, 1.0);
+    }
+    let (n, p) = (n as RR, successes as RR / n as RR);
+    let z2 = Z_CRITICAL * Z_CRITICAL;
+    let centre = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+    let margin = Z_CRITICAL * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt() / (1.0 + z2 / n);
+    // At the extremes the bound is exact, rather than off by rounding
+    let low = if p == 0.0 { 0.0 } else { centre - margin };
+    let high = if p == 1.0 { 1.0 } else { centre + margin };
+    (low, high)
+}
+
+/// The report on one parameter set, as labelled lines. Probabilities are
+/// written in `prob_format`.
+pub fn report(params: &SimParams, prob_format: ProbFormat) -> String {
+    let mut lines = vec!["Direct calculation (all groups of the assumed size, no ageing or \
+                          targetting):"
+                             .to_owned()];
+    {
+        let mut line = |label: &str, value: String| {
+            lines.push(format!("  {1:<0$} {2}", LABEL_WIDTH, label, value));
+        };
+
+        let calc = DirectCalcTool::new(params.as_calc().tool_args()).breakdown();
+        line("Nodes", calc.num_nodes.to_string());
+        line("Malicious nodes",
+             match params.num_malicious {
+                 RelOrAbs::Rel(_) => {
+                     format!("{} ({} of nodes)", calc.num_malicious, params.num_malicious)
+                 }
+                 RelOrAbs::Abs(_) => calc.num_malicious.to_string(),
+             });
+        line("Minimum group size", calc.min_group_size.to_string());
+        line("Quorum at the minimum size",
+             match params.quorum_floor {
+                 Some(floor) => {
+                     format!("{} nodes (quorum size {}, at least {})",
+                             calc.min_quorum,
+                             params.quorum,
+                             floor)
+                 }
+                 None => format!("{} nodes (quorum size {})", calc.min_quorum, params.quorum),
+             });
+        line("Group size assumed",
+             if calc.group_size == calc.min_group_size {
+                 format!("{} (all groups of the minimum size)", calc.group_size)
+             } else {
+                 format!("{} (too few nodes to split: one group)", calc.group_size)
+             });
+        line("Groups assumed", format!("{:.1}", calc.result.groups));
+        if calc.group_size != calc.min_group_size {
+            line("Quorum", format!("{} nodes", calc.quorum));
+        }
+        line("Honest nodes needed",
+             format!("{} (fewer disrupt a group)", calc.honest_needed));
+        line("P(compromise) of one group",
+             prob_format.format(calc.p_compromise_group));
+        line("P(compromise) of any group",
+             format!("{} ({} groups)",
+                     prob_format.format(calc.result.p_compromise),
+                     params.any_group_model.name()));
+        line("P(disruption) of one group",
+             prob_format.format(calc.p_disrupt_group));
+        line("P(disruption) of any group",
+             format!("{} ({} groups)",
+                     prob_format.format(calc.result.p_disrupt),
+                     params.any_group_model.name()));
+    }
+    if params.sim_type == SimType::FullSim {
+        lines.push(format!("Full simulation ({} quorum, targetting {}, {} steps):",
+                           params.quorum_type.name(),
+                           params.targetting_name(),
+                           params.max_steps));
+        let steps = params.tool().compromise_steps().expect("full simulation steps");
+        let runs = steps.len() as NN;
+        let compromised: Vec<NN> = steps.iter().filter_map(|&step| step).collect();
+        let (low, high) = wilson_interval(compromised.len() as NN, runs);
+        let p = if runs > 0 {
+            compromised.len() as RR / runs as RR
+        } else {
+            0.0
+        };
+        let mut line = |label: &str, value: String| {
+            lines.push(format!("  {1:<0$} {2}", LABEL_WIDTH, label, value));
+        };
+        line("Runs compromised", format!("{} of {}", compromised.len(), runs));
+        line("P(compromise)",
+             format!("{} (95% interval {} to {})",
+                     prob_format.format(p),
+                     prob_format.format(low),
+                     prob_format.format(high)));
+        // Steps are counted from 0, so compromise at step s took s + 1 steps
+        line("Mean steps to compromise",
+             if compromised.is_empty() {
+                 "- (no run compromised)".to_owned()
+             } else {
+                 let total: NN = compromised.iter().map(|step| step + 1).sum();
+                 format!("{:.1} (of the runs compromised)",
+                         total as RR / compromised.len() as RR)
+             });
+    }
+    let mut report = lines.join("\n");
+    report.push('\n');
+    report
+}
+
+#[cfg(test)]
+fn explain_params(args: &[&str]) -> Result<SimParams, String> {
+    use super::args::sim_params;
+
+    let argv: Vec<String> =
+        Some("routing-sims").into_iter().chain(args.iter().cloned()).map(String::from).collect();
+    let argv = tool_argv(&argv);
+    let tool_args: Vec<&str> = argv[1..].iter().map(|arg| arg.as_str()).collect();
+    single(sim_params(&tool_args))
+}
+
+#[test]
+fn test_explain_calc() {
+    let params = explain_params(&["explain", "-n", "100", "-r", "10%", "-k", "10", "-q", "0.5"])
+        .expect("one set");
+    assert!(params.sim_type == SimType::DirectCalc);
+    assert_eq!(report(&params, ProbFormat::new()),
+               "Direct calculation (all groups of the assumed size, no ageing or targetting):
+  Nodes                            100
+  Malicious nodes                  10 (10% of nodes)
+  Minimum group size               10
+  Quorum at the minimum size       5 nodes (quorum size 0.5)
+  Group size assumed               10 (all groups of the minimum size)
+  Groups assumed                   10.0
+  Honest nodes needed              5 (fewer disrupt a group)
+  P(compromise) of one group       0.000672
+  P(compromise) of any group       0.006696 (independent groups)
+  P(disruption) of one group       3.182315e-5
+  P(disruption) of any group       0.000318 (independent groups)
+");
+
+    // Too few nodes to split
+    let params = explain_params(&["explain", "-n", "15", "-r", "3", "-k", "10", "-q", "0.5"])
+        .expect("one set");
+    let text = report(&params, ProbFormat::new());
+    assert!(text.contains("Group size assumed               15 (too few nodes to split: one \
+                           group)\n"));
+    assert!(text.contains("Quorum                           8 nodes\n"));
+
+    // Ranges are rejected
+    assert!(explain_params(&["explain", "-n", "100,200"]).is_err());
+    assert!(explain_params(&["explain", "full", "-Q", "all"]).is_err());
+}
+
+#[test]
+fn test_explain_full() {
+    let args = ["explain", "full", "-n", "200", "-r", "60", "-k", "8", "-q", "0.5", "-s", "20",
+                "-p", "20", "--seed", "3"];
+    let params = explain_params(&args).expect("one set");
+    let text = report(&params, ProbFormat::new());
+    assert!(text.contains("Full simulation (simple quorum, targetting untarg., 20 steps):\n"));
+    // The estimate is that of the full simulation
+    let result = params.result();
+    let compromised = (result.p_compromise * 20.0).round() as NN;
+    assert!(text.contains(&format!("  Runs compromised                 {} of 20\n", compromised)));
+    assert!(text.contains(&format!("  P(compromise)                    {} (95% interval",
+                                   ProbFormat::new().format(result.p_compromise))));
+    assert!(compromised > 0 && text.contains("(of the runs compromised)"));
+}
+
+#[test]
+fn test_wilson_interval() {
+    // 5 of 10: symmetric about 0.5
+    let (low, high) = wilson_interval(5, 10);
+    assert!((low - 0.2366).abs() < 1e-4 && (high - 0.7634).abs() < 1e-4);
+    // None of 20: from zero, but not to zero
+    let (low, high) = wilson_interval(0, 20);
+    assert_eq!(low, 0.0);
+    assert!((high - 0.1611).abs() < 1e-4);
+    assert_eq!(wilson_interval(0, 0), (0.0, 1.0));
+}
@@ -0,0 +1,551 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Self-contained HTML report of a run (see `--report-html`)
+//!
+//! The page holds the parameters of the run, the main table (sorted by
+//! clicking a column title), the notes following it and, if one or two
+//! parameters are swept, charts of P(compromise) drawn as inline SVG: a line
+//! chart over the swept parameter, with a line for each quorum type and
+//! targetting, or over two a heatmap for each quorum type and targetting.
+
+use super::RR;
+use super::marginals::Row;
+
+use std::cmp::max;
+
+
+/// Size of a chart, in pixels
+const WIDTH: RR = 560.0;
+const HEIGHT: RR = 320.0;
+/// Margins around the plotting area, for the axes and the legend
+const LEFT: RR = 70.0;
+const RIGHT: RR = 160.0;
+const TOP: RR = 30.0;
+const BOTTOM: RR = 50.0;
+/// Most tick labels on the x axis; values between them are left unlabelled
+const MAX_X_LABELS: usize = 10;
+/// Lowest probability on a log scale
+const MIN_DECADE: i32 = -12;
+/// Colours of the lines of a line chart, in turn
+const COLOURS: [&'static str; 6] = ["#1f77b4", "#d62728", "#2ca02c", "#9467bd", "#ff7f0e",
+                                    "#8c564b"];
+
+/// Sorts the main table by the column clicked, numerically where both values
+/// are numbers, and the other way round when clicked again
+const SORT_SCRIPT: &'static str = r#"function sortTable(col) {
+  var body = document.getElementById("results").tBodies[0];
+  var rows = Array.prototype.slice.call(body.rows);
+  var up = body.getAttribute("data-col") != col || body.getAttribute("data-order") != "up";
+  var key = function(row) {
+    var text = row.cells[col].textContent;
+    var x = text == "-inf" ? -Infinity : Number(text);
+    return text == "" || isNaN(x) ? text : x;
+  };
+  rows.sort(function(a, b) {
+    var x = key(a), y = key(b);
+    if (typeof x != typeof y) { x = String(x); y = String(y); }
+    return (x < y ? -1 : x > y ? 1 : 0) * (up ? 1 : -1);
+  });
+  rows.forEach(function(row) { body.appendChild(row); });
+  body.setAttribute("data-col", col);
+  body.setAttribute("data-order", up ? "up" : "down");
+}"#;
+
+const STYLE: &'static str = "body { font-family: sans-serif; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 2px 6px; text-align: right; }
+th { background: #eee; cursor: pointer; }
+svg { margin: 8px; }";
+
+/// P(compromise) at each value of the swept parameter, for one quorum type
+/// and targetting
+pub struct Series {
+    pub name: String,
+    /// Index of the value and P(compromise)
+    pub points: Vec<(usize, RR)>,
+}
+
+/// Scale of P(compromise), as the fraction of the axis from the bottom
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Scale {
+    Linear,
+    /// From 10 to the power of this to 1
+    Log(i32),
+}
+
+impl Scale {
+    /// Log scale from the decade of the lowest non-zero probability (at most
+    /// 0.1 and at least `MIN_DECADE`), if `log`
+    fn new<I: Iterator<Item = RR>>(ps: I, log: bool) -> Self {
+        if !log {
+            return Scale::Linear;
+        }
+        let low = ps.filter(|&p| p > 0.0).fold(0.1, |low: RR, p| low.min(p));
+        Scale::Log(max(low.log10().floor() as i32, MIN_DECADE))
+    }
+
+    /// Zero, and probabilities below the scale, are at the bottom.
+    fn fraction(self, p: RR) -> RR {
+        match self {
+            Scale::Linear => p.max(0.0).min(1.0),
+            Scale::Log(low) => {
+                if p <= 0.0 {
+                    0.0
+                } else {
+                    ((p.log10() - low as RR) / -low as RR).max(0.0).min(1.0)
+                }
+            }
+        }
+    }
+
+    /// Probabilities labelled on the axis, with their labels
+    fn ticks(self) -> Vec<(RR, String)> {
+        match self {
+            Scale::Linear => (0..6).map(|i| i as RR / 5.0).map(|p| (p, p.to_string())).collect(),
+            Scale::Log(low) => {
+                // At most seven labels, down from 1
+                let every = (-low + 5) / 6;
+                (low..1)
+                    .filter(|e| e % every == 0)
+                    .map(|e| {
+                        let label = if e == 0 { "1".to_owned() } else { format!("1e{}", e) };
+                        (10.0f64.powi(e), label)
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Scale::Linear => "P(compromise)",
+            Scale::Log(_) => "P(compromise), log scale",
+        }
+    }
+}
+
+/// Escape text for HTML (and SVG).
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// Height of a probability in the plotting area
+fn y_pos(scale: Scale, p: RR) -> RR {
+    HEIGHT - BOTTOM - scale.fraction(p) * (HEIGHT - BOTTOM - TOP)
+}
+
+// Start of the SVG of a chart, with its title
+fn svg_start(svg: &mut String, title: &str) {
+    svg.push_str(&format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+                           font-family=\"sans-serif\" font-size=\"11\">\n",
+                          WIDTH,
+                          HEIGHT));
+    if !title.is_empty() {
+        svg.push_str(&format!("<text x=\"{:.1}\" y=\"16\" text-anchor=\"middle\" \
+                               font-weight=\"bold\">{}</text>\n",
+                              (LEFT + WIDTH - RIGHT) / 2.0,
+                              escape(title)));
+    }
+}
+
+// Titles of the axes
+fn axis_titles(svg: &mut String, x_title: &str, y_title: &str) {
+    svg.push_str(&format!("<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>\n",
+                          (LEFT + WIDTH - RIGHT) / 2.0,
+                          HEIGHT - 10.0,
+                          escape(x_title)));
+    svg.push_str(&format!("<text transform=\"translate(14,{:.1}) rotate(-90)\" \
+                           text-anchor=\"middle\">{}</text>\n",
+                          (TOP + HEIGHT - BOTTOM) / 2.0,
+                          escape(y_title)));
+}
+
+// Label of the x axis at `x`, if the `i`th of `n` values is labelled
+fn x_label(svg: &mut String, x: RR, i: usize, n: usize, value: &str) {
+    let every = (n + MAX_X_LABELS - 1) / MAX_X_LABELS;
+    if i % every == 0 {
+        svg.push_str(&format!("<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>\n",
+                              x,
+                              HEIGHT - BOTTOM + 16.0,
+                              escape(value)));
+    }
+}
+
+/// Line chart of P(compromise) over the values `xs` of the parameter
+/// `x_title`, evenly spaced in order, with a line and legend entry for each
+/// series. If `log`, P(compromise) is on a log scale, with zero at the bottom.
+pub fn line_chart(x_title: &str, xs: &[String], series: &[Series], log: bool) -> String {
+    let scale = Scale::new(series.iter().flat_map(|s| s.points.iter().map(|&(_, p)| p)), log);
+    let (x0, x1, y0, y1) = (LEFT, WIDTH - RIGHT, TOP, HEIGHT - BOTTOM);
+    let x_pos = |i: usize| if xs.len() < 2 {
+        (x0 + x1) / 2.0
+    } else {
+        x0 + (x1 - x0) * i as RR / (xs.len() - 1) as RR
+    };
+
+    let mut svg = String::new();
+    svg_start(&mut svg, "");
+    svg.push_str(&format!("<line x1=\"{0:.1}\" y1=\"{1:.1}\" x2=\"{2:.1}\" y2=\"{1:.1}\" \
+                           stroke=\"black\"/>\n",
+                          x0,
+                          y1,
+                          x1));
+    svg.push_str(&format!("<line x1=\"{0:.1}\" y1=\"{1:.1}\" x2=\"{0:.1}\" y2=\"{2:.1}\" \
+                           stroke=\"black\"/>\n",
+                          x0,
+                          y0,
+                          y1));
+    for (p, label) in scale.ticks() {
+        let y = y_pos(scale, p);
+        svg.push_str(&format!("<line x1=\"{0:.1}\" y1=\"{1:.1}\" x2=\"{2:.1}\" y2=\"{1:.1}\" \
+                               stroke=\"#ddd\"/>\n",
+                              x0,
+                              y,
+                              x1));
+        svg.push_str(&format!("<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>\n",
+                              x0 - 4.0,
+                              y + 4.0,
+                              label));
+    }
+    for (i, x) in xs.iter().enumerate() {
+        svg.push_str(&format!("<line x1=\"{0:.1}\" y1=\"{1:.1}\" x2=\"{0:.1}\" y2=\"{2:.1}\" \
+                               stroke=\"black\"/>\n",
+                              x_pos(i),
+                              y1,
+                              y1 + 4.0));
+        x_label(&mut svg, x_pos(i), i, xs.len(), x);
+    }
+    axis_titles(&mut svg, x_title, scale.title());
+
+    for (k, s) in series.iter().enumerate() {
+        let colour = COLOURS[k % COLOURS.len()];
+        let points: Vec<String> = s.points
+            .iter()
+            .map(|&(i, p)| format!("{:.1},{:.1}", x_pos(i), y_pos(scale, p)))
+            .collect();
+        svg.push_str(&format!("<polyline fill=\"none\" stroke=\"{}\" points=\"{}\"/>\n",
+                              colour,
+                              points.join(" ")));
+        for &(i, p) in &s.points {
+            svg.push_str(&format!("<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\" fill=\"{}\">\
+                                   <title>{}: {} {}, P(compromise) {}</title></circle>\n",
+                                  x_pos(i),
+                                  y_pos(scale, p),
+                                  colour,
+                                  escape(&s.name),
+                                  escape(x_title),
+                                  escape(&xs[i]),
+                                  p));
+        }
+        let y = y0 + 6.0 + 16.0 * k as RR;
+        svg.push_str(&format!("<line x1=\"{0:.1}\" y1=\"{1:.1}\" x2=\"{2:.1}\" y2=\"{1:.1}\" \
+                               stroke=\"{3}\" stroke-width=\"2\"/>\n",
+                              x1 + 12.0,
+                              y,
+                              x1 + 32.0,
+                              colour));
+        svg.push_str(&format!("<text x=\"{:.1}\" y=\"{:.1}\">{}</text>\n",
+                              x1 + 38.0,
+                              y + 4.0,
+                              escape(&s.name)));
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Heatmap of P(compromise) over the values `xs` and `ys` of two parameters,
+/// evenly spaced in order, from white (zero, or the bottom of the log scale
+/// if `log`) to red (one). Cells are given by the index of each value; those
+/// not given are left empty.
+pub fn heatmap(title: &str,
+               x_title: &str,
+               xs: &[String],
+               y_title: &str,
+               ys: &[String],
+               cells: &[(usize, usize, RR)],
+               log: bool)
+               -> String {
+    let scale = Scale::new(cells.iter().map(|&(_, _, p)| p), log);
+    let (x0, x1, y0, y1) = (LEFT, WIDTH - RIGHT, TOP, HEIGHT - BOTTOM);
+    let width = (x1 - x0) / max(xs.len(), 1) as RR;
+    let height = (y1 - y0) / max(ys.len(), 1) as RR;
+
+    let mut svg = String::new();
+    svg_start(&mut svg, title);
+    for &(i, j, p) in cells {
+        let shade = (255.0 * (1.0 - scale.fraction(p))).round();
+        svg.push_str(&format!("<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" \
+                               fill=\"rgb(255,{},{4})\"><title>{} {}, {} {}, P(compromise) \
+                               {}</title></rect>\n",
+                              x0 + width * i as RR,
+                              y1 - height * (j + 1) as RR,
+                              width,
+                              height,
+                              shade,
+                              escape(x_title),
+                              escape(&xs[i]),
+                              escape(y_title),
+                              escape(&ys[j]),
+                              p));
+    }
+    svg.push_str(&format!("<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" \
+                           fill=\"none\" stroke=\"black\"/>\n",
+                          x0,
+                          y0,
+                          x1 - x0,
+                          y1 - y0));
+    for (i, x) in xs.iter().enumerate() {
+        x_label(&mut svg, x0 + width * (i as RR + 0.5), i, xs.len(), x);
+    }
+    let every = (ys.len() + MAX_X_LABELS - 1) / MAX_X_LABELS;
+    for (j, y) in ys.iter().enumerate().filter(|&(j, _)| j % every == 0) {
+        svg.push_str(&format!("<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>\n",
+                              x0 - 4.0,
+                              y1 - height * (j as RR + 0.5) + 4.0,
+                              escape(y)));
+    }
+    axis_titles(&mut svg, x_title, y_title);
+    let low = match scale {
+        Scale::Linear => "0".to_owned(),
+        Scale::Log(low) => format!("1e{} or less", low),
+    };
+    svg.push_str(&format!("<text x=\"{:.1}\" y=\"{:.1}\">{}</text>\n\
+                           <text x=\"{0:.1}\" y=\"{:.1}\">white: {}</text>\n\
+                           <text x=\"{0:.1}\" y=\"{:.1}\">red: 1</text>\n",
+                          x1 + 12.0,
+                          y0 + 10.0,
+                          scale.title(),
+                          y0 + 26.0,
+                          low,
+                          y0 + 42.0));
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Charts of the rows of the main table (see `marginals::Row`), whose
+/// parameter columns have the given titles: if one parameter other than the
+/// quorum type and targetting is swept, a line chart over it, or if two, a
+/// heatmap over them for each quorum type and targetting. There are none
+/// otherwise. As for `--marginals`, a parameter is swept if its column takes
+/// more than one value.
+pub fn charts(titles: &[&str], rows: &[Row], log: bool) -> Vec<String> {
+    // Values of each column, in order of first appearance
+    let mut values: Vec<Vec<&str>> = vec![vec![]; titles.len()];
+    for &(ref row, _, _) in rows {
+        for (col, value) in row.iter().enumerate() {
+            if !values[col].contains(&value.as_str()) {
+                values[col].push(value);
+            }
+        }
+    }
+    let series_cols: Vec<usize> = titles.iter()
+        .enumerate()
+        .filter(|&(_, &title)| title == "Quorum" || title == "Targetting")
+        .map(|(col, _)| col)
+        .collect();
+    // A column whose values pair one-to-one with those of an earlier swept
+    // column (as the number of malicious nodes given as a proportion does with
+    // the number of nodes) is the same parameter
+    let mut swept: Vec<usize> = vec![];
+    for col in 0..titles.len() {
+        if values[col].len() < 2 || series_cols.contains(&col) {
+            continue;
+        }
+        let paired = swept.iter().any(|&earlier| {
+            let mut pairs: Vec<(&str, &str)> = vec![];
+            for &(ref row, _, _) in rows {
+                let pair = (row[earlier].as_str(), row[col].as_str());
+                if !pairs.contains(&pair) {
+                    pairs.push(pair);
+                }
+            }
+            pairs.len() == values[earlier].len() && pairs.len() == values[col].len()
+        });
+        if !paired {
+            swept.push(col);
+        }
+    }
+    let owned = |col: usize| -> Vec<String> {
+        values[col].iter().map(|&value| value.to_owned()).collect()
+    };
+    let index = |col: usize, value: &str| {
+        values[col].iter().position(|&v| v == value).expect("value")
+    };
+
+    // Rows of each series, in order of first appearance
+    let mut series: Vec<(String, Vec<&Row>)> = vec![];
+    for row in rows {
+        let name: Vec<&str> = series_cols.iter().map(|&col| row.0[col].as_str()).collect();
+        let name = name.join(", ");
+        match series.iter().position(|&(ref n, _)| *n == name) {
+            Some(k) => series[k].1.push(row),
+            None => series.push((name, vec![row])),
+        }
+    }
+
+    match swept.len() {
+        1 => {
+            let col = swept[0];
+            let series: Vec<Series> = series.into_iter()
+                .map(|(name, rows)| {
+                    Series {
+                        name: name,
+                        points: rows.iter().map(|row| (index(col, &row.0[col]), row.1)).collect(),
+                    }
+                })
+                .collect();
+            vec![line_chart(titles[col], &owned(col), &series, log)]
+        }
+        2 => {
+            let (x, y) = (swept[0], swept[1]);
+            series.into_iter()
+                .map(|(name, rows)| {
+                    let cells: Vec<(usize, usize, RR)> = rows.iter()
+                        .map(|row| (index(x, &row.0[x]), index(y, &row.0[y]), row.1))
+                        .collect();
+                    heatmap(&name, titles[x], &owned(x), titles[y], &owned(y), &cells, log)
+                })
+                .collect()
+        }
+        _ => vec![],
+    }
+}
+
+/// The HTML page: the parameters of the run (`config`, as lines), the main
+/// table with a column numbering the rows, the notes after it and the charts.
+pub fn page(config: &[String],
+            titles: &[String],
+            rows: &[Vec<String>],
+            notes: &[String],
+            charts: &[String])
+            -> String {
+    let mut html = format!("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+                            <title>routing-sims {}</title>\n<style>\n{}\n</style>\n</head>\n\
+                            <body>\n<h1>routing-sims {0}</h1>\n",
+                           env!("CARGO_PKG_VERSION"),
+                           STYLE);
+    if !config.is_empty() {
+        html.push_str("<h2>Parameters</h2>\n<pre>\n");
+        for line in config {
+            html.push_str(&format!("{}\n", escape(line)));
+        }
+        html.push_str("</pre>\n");
+    }
+    html.push_str("<h2>Results</h2>\n<table id=\"results\">\n<thead><tr>");
+    let all_titles = Some("Row".to_owned()).into_iter().chain(titles.iter().cloned());
+    for (col, title) in all_titles.enumerate() {
+        html.push_str(&format!("<th onclick=\"sortTable({})\">{}</th>", col, escape(&title)));
+    }
+    html.push_str("</tr></thead>\n<tbody>\n");
+    for (i, row) in rows.iter().enumerate() {
+        html.push_str(&format!("<tr><td>{}</td>", i + 1));
+        for value in row {
+            html.push_str(&format!("<td>{}</td>", escape(value)));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</tbody>\n</table>\n");
+    for note in notes {
+        html.push_str(&format!("<pre>\n{}\n</pre>\n", escape(note)));
+    }
+    if !charts.is_empty() {
+        html.push_str("<h2>Charts</h2>\n");
+        for chart in charts {
+            html.push_str(chart);
+        }
+    }
+    html.push_str(&format!("<script>\n{}\n</script>\n</body>\n</html>\n", SORT_SCRIPT));
+    html
+}
+
+// A sweep of the number of nodes (with malicious nodes a tenth of them), under
+// two quorum types, and of the minimum group size under one
+#[cfg(test)]
+fn synthetic_rows() -> (Vec<&'static str>, Vec<Row>) {
+    let titles = vec!["Type", "Quorum", "Targetting", "Nodes", "MinGroup", "Malicious"];
+    let row = |quorum: &str, nodes: &str, group: &str, p: RR| {
+        let malicious = (nodes.parse::<usize>().expect("nodes") / 10).to_string();
+        (vec!["full", quorum, "untarg.", nodes, group, &malicious]
+             .into_iter()
+             .map(String::from)
+             .collect(),
+         p,
+         Some(100))
+    };
+    let rows = vec![row("simple", "100", "8", 0.5),
+                    row("simple", "200", "8", 0.02),
+                    row("simple", "400", "8", 0.0),
+                    row("age", "100", "8", 0.25),
+                    row("age", "200", "8", 0.001),
+                    row("age", "400", "8", 0.0),
+                    row("age", "100", "10", 0.125),
+                    row("age", "200", "10", 1e-4)];
+    (titles, rows)
+}
+
+// The golden files were checked by eye in a browser. If a change to the
+// charts is intended, check the new output likewise before replacing them.
+#[test]
+fn test_line_chart() {
+    let (titles, rows) = synthetic_rows();
+    // Nodes is the one parameter swept
+    let rows: Vec<Row> = rows.into_iter().filter(|row| row.0[4] == "8").collect();
+    let linear = charts(&titles, &rows, false);
+    assert_eq!(linear.len(), 1);
+    assert_eq!(linear[0], include_str!("../testdata/line_chart.svg"));
+    let log = charts(&titles, &rows, true);
+    assert_eq!(log[0], include_str!("../testdata/line_chart_log.svg"));
+}
+
+#[test]
+fn test_heatmap() {
+    let (titles, rows) = synthetic_rows();
+    // Nodes and MinGroup are swept: a heatmap for each quorum type
+    let maps = charts(&titles, &rows, true);
+    assert_eq!(maps.len(), 2);
+    assert!(maps[0].contains(">simple, untarg.</text>"));
+    assert!(maps[1].contains(">age, untarg.</text>"));
+    assert_eq!(maps[1], include_str!("../testdata/heatmap.svg"));
+
+    // Nothing swept but the quorum type, or three parameters: no charts
+    let two: Vec<Row> = rows.iter()
+        .filter(|row| row.0[3] == "100" && row.0[4] == "8")
+        .cloned()
+        .collect();
+    assert_eq!(two.len(), 2);
+    assert!(charts(&titles, &two, false).is_empty());
+    let mut three = rows.clone();
+    three[0].0[0] = "structure".to_owned();
+    assert!(charts(&titles, &three, false).is_empty());
+}
+#[test]
+fn test_page() {
+    let html = page(&["nodes (-n): 100 <default>".to_owned()],
+                    &["Nodes".to_owned(), "P(compromise)".to_owned()],
+                    &[vec!["100".to_owned(), "0.5".to_owned()]],
+                    &["simp_bound: upper bounds".to_owned()],
+                    &["<svg/>\n".to_owned()]);
+    assert!(html.starts_with("<!DOCTYPE html>\n"));
+    assert!(html.contains("<pre>\nnodes (-n): 100 &lt;default&gt;\n</pre>"));
+    assert!(html.contains("<tr><th onclick=\"sortTable(0)\">Row</th><th \
+                           onclick=\"sortTable(1)\">Nodes</th><th \
+                           onclick=\"sortTable(2)\">P(compromise)</th></tr>"));
+    assert!(html.contains("<tr><td>1</td><td>100</td><td>0.5</td></tr>\n"));
+    assert!(html.contains("<pre>\nsimp_bound: upper bounds\n</pre>\n<h2>Charts</h2>\n<svg/>\n"));
+    assert!(html.contains("function sortTable(col)"));
+}
@@ -0,0 +1,121 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Stopping a sweep early, on Ctrl-C
+//!
+//! The first Ctrl-C (SIGINT) only sets a flag: sets not yet started are not
+//! run, and those running stop at their next repetition, so the results so far
+//! can still be written. A second Ctrl-C exits at once.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+/// Exit status on a second Ctrl-C (that of a process killed by SIGINT)
+pub const FORCED_EXIT: i32 = 130;
+
+// Number of Ctrl-Cs received since the handler was installed
+static SIGNALS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// Request to stop, shared by clones. Checked between parameter sets and
+/// between repetitions.
+#[derive(Clone, Default)]
+pub struct Interrupt {
+    flag: Arc<AtomicBool>,
+    // Whether Ctrl-C also sets this (once the handler is installed)
+    ctrl_c: bool,
+}
+
+impl Interrupt {
+    /// Create, set only by `interrupt`.
+    pub fn new() -> Self {
+        Interrupt::default()
+    }
+
+    /// Create, set also by Ctrl-C once `install` is called.
+    pub fn ctrl_c() -> Self {
+        Interrupt {
+            flag: Arc::new(AtomicBool::new(false)),
+            ctrl_c: true,
+        }
+    }
+
+    /// Ask everything sharing this to stop.
+    pub fn interrupt(&self) {
+        self.flag.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether stopping has been asked for
+    pub fn is_set(&self) -> bool {
+        self.flag.load(Ordering::SeqCst) || (self.ctrl_c && SIGNALS.load(Ordering::SeqCst) > 0)
+    }
+}
+
+/// Install the Ctrl-C handler (only on Unix; elsewhere Ctrl-C still kills the
+/// process).
+pub fn install() {
+    imp::install();
+}
+
+/// The line printed after the table of an interrupted run
+pub fn banner(completed: usize, total: usize) -> String {
+    format!("INTERRUPTED after {}/{} combinations", completed, total)
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::sync::atomic::Ordering;
+    use libc;
+    use super::{FORCED_EXIT, SIGNALS};
+
+    const MESSAGE: &'static [u8] = b"Interrupted: finishing the parameter sets running (Ctrl-C \
+                                     again to exit at once)\n";
+
+    // Only async-signal-safe calls here: no allocation or locking
+    extern "C" fn on_sigint(_: libc::c_int) {
+        if SIGNALS.fetch_add(1, Ordering::SeqCst) > 0 {
+            unsafe { libc::_exit(FORCED_EXIT) }
+        }
+        unsafe {
+            let _ = libc::write(2, MESSAGE.as_ptr() as *const libc::c_void, MESSAGE.len());
+        }
+    }
+
+    pub fn install() {
+        unsafe {
+            let _ = libc::signal(libc::SIGINT, on_sigint as libc::sighandler_t);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    pub fn install() {}
+}
+
+#[test]
+fn test_interrupt() {
+    // Clones share the request; separately created interrupts are independent
+    let interrupt = Interrupt::new();
+    let clone = interrupt.clone();
+    let other = Interrupt::new();
+    assert!(!interrupt.is_set() && !clone.is_set());
+    clone.interrupt();
+    assert!(interrupt.is_set() && clone.is_set());
+    assert!(!other.is_set());
+
+    assert_eq!(banner(3, 8), "INTERRUPTED after 3/8 combinations");
+}
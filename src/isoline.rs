@@ -0,0 +1,575 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Isolines of P(compromise) from the results of a sweep (see `routing-sims
+//! isoline`): for each value of one parameter, the value of another at which
+//! P(compromise) reaches a target, e.g. the malicious nodes needed to reach
+//! 1e-5 at each quorum size.
+//!
+//! Results are read from the main table written by a tool (as written, or
+//! converted to CSV), or from a directory of results saved by `--cache`, whose
+//! descriptions hold the parameters. Between the two rows bracketing the
+//! target, the value is interpolated linearly in log10 P(compromise), which is
+//! exact where the probability changes exponentially. A probability simulated
+//! as zero counts as half a compromise (0.5 / runs), as long as that is below
+//! the target; between a calculated zero (or a simulated one too coarse for the
+//! target) and the other row, the interpolation is linear in P(compromise).
+
+use super::{NN, RR};
+use super::baseline::RESULT_TITLES;
+use super::batch::BATCH_COLUMNS;
+use super::cache::{self, read_saved};
+use super::combo::COMBO_ID_TITLE;
+use super::model::{self, MODEL_VERSION};
+
+use docopt::{self, Docopt};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const USAGE: &'static str = "
+The value of one parameter at which P(compromise) reaches a target, at each
+value of another, from the results of a sweep. Written as CSV.
+
+Usage:
+    routing-sims isoline --input FILE --target P --x COL --y COL
+    routing-sims isoline (-h | --help)
+
+Options:
+    -h --help   Show this message
+    --input FILE
+                Main table written by calc, structure, full or batch (as
+                written, or as CSV), or a directory of results written with
+                the option --cache.
+    --target P  P(compromise) of the isoline, e.g. 1e-5.
+    --x COL     Title of the column along the isoline, e.g. QuorumSize.
+    --y COL     Title of the column to find at each value of x, e.g.
+                Malicious. Its values must be numbers (or percentages).
+";
+
+#[derive(RustcDecodable)]
+struct Args {
+    flag_input: String,
+    flag_target: RR,
+    flag_x: String,
+    flag_y: String,
+}
+
+/// An isoline to extract
+#[derive(Clone, Debug, PartialEq)]
+pub struct Query {
+    pub input: PathBuf,
+    pub target: RR,
+    pub x: String,
+    pub y: String,
+}
+
+/// The query of the arguments of `routing-sims isoline` (including the
+/// program name and command).
+pub fn parse_argv(argv: &[String]) -> Result<Query, docopt::Error> {
+    let args: Args = try!(Docopt::new(USAGE).and_then(|dopt| dopt.argv(argv).decode()));
+    if !(args.flag_target > 0.0 && args.flag_target < 1.0) {
+        panic!("unexpected: --target {} (must be between 0 and 1)", args.flag_target);
+    }
+    if args.flag_x == args.flag_y {
+        panic!("unexpected: --x and --y are both {}", args.flag_x);
+    }
+    Ok(Query {
+        input: PathBuf::from(args.flag_input),
+        target: args.flag_target,
+        x: args.flag_x,
+        y: args.flag_y,
+    })
+}
+
+/// One row of results
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResultRow {
+    /// Values of the parameter columns
+    pub cells: Vec<String>,
+    pub p_compromise: RR,
+    /// Number of simulation runs, if estimated by simulation
+    pub runs: Option<NN>,
+}
+
+/// Results of a sweep: the titles of the parameter columns, and the rows.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Results {
+    pub titles: Vec<String>,
+    pub rows: Vec<ResultRow>,
+}
+
+impl Results {
+    /// Read the main table as written to standard output by a tool, or as
+    /// CSV (if the header has commas). As for a baseline, columns are found
+    /// by title, P(compromise) may be written as log10 (where `-inf`, or in
+    /// CSV nothing, is zero), marks (`*`) are dropped, and reading stops at
+    /// the first blank line. Result columns other than P(compromise) and Runs
+    /// are ignored.
+    pub fn read<R: BufRead>(input: R) -> Result<Self, String> {
+        let mut lines = input.lines().enumerate().peekable();
+        // Any version of the model will do
+        let stamped = lines.peek().map_or(false, |&(_, ref line)| {
+            line.as_ref().ok().map_or(false, |line| model::read_stamp(line).is_some())
+        });
+        if stamped {
+            let _ = lines.next();
+        }
+        let header = match lines.next() {
+            Some((_, Ok(header))) => header,
+            Some((i, Err(e))) => return Err(format!("line {}: {}", i + 1, e)),
+            None => return Err("no header line".to_owned()),
+        };
+        let csv = header.contains(',');
+        let split = |line: &str| -> Vec<String> {
+            if csv {
+                line.split(',').map(|cell| cell.trim().to_owned()).collect()
+            } else {
+                line.split_whitespace().map(|cell| cell.to_owned()).collect()
+            }
+        };
+        let titles = split(&header);
+        // The Viz column is last and may be empty, so is not read
+        let num_cols = titles.iter().take_while(|title| *title != "Viz").count();
+        let column = |name: &str| titles[..num_cols].iter().position(|title| title == name);
+        let (p_col, log10) = try!(column("P(compromise)")
+            .map(|col| (col, false))
+            .or_else(|| column("log10P(compromise)").map(|col| (col, true)))
+            .ok_or_else(|| "no P(compromise) column".to_owned()));
+        let runs_col = column("Runs");
+        let param_cols: Vec<usize> = (0..num_cols)
+            .filter(|&col| {
+                !RESULT_TITLES.contains(&titles[col].as_str()) && titles[col] != COMBO_ID_TITLE
+            })
+            .collect();
+
+        let mut rows = Vec::new();
+        for (i, line) in lines {
+            let line_num = i + 1;
+            let line = try!(line.map_err(|e| format!("line {}: {}", line_num, e)));
+            if line.trim().is_empty() {
+                break;
+            }
+            let fields = split(&line);
+            if fields.len() < num_cols {
+                return Err(format!("line {}: expected {} fields, found {}",
+                                   line_num,
+                                   num_cols,
+                                   fields.len()));
+            }
+            let bad = |col: usize| {
+                format!("line {}: bad value for {}: '{}'", line_num, titles[col], fields[col])
+            };
+            let unmarked = |col: usize| fields[col].trim_right_matches('*');
+            let p_compromise = if log10 && unmarked(p_col).is_empty() {
+                0.0
+            } else {
+                let p: RR = try!(unmarked(p_col).parse().map_err(|_| bad(p_col)));
+                if log10 { (10.0 as RR).powf(p) } else { p }
+            };
+            let runs = match runs_col {
+                None => None,
+                Some(col) if fields[col] == "-" => None,
+                Some(col) => Some(try!(unmarked(col).parse().map_err(|_| bad(col)))),
+            };
+            rows.push(ResultRow {
+                cells: param_cols.iter().map(|&col| fields[col].clone()).collect(),
+                p_compromise: p_compromise,
+                runs: runs,
+            });
+        }
+        Ok(Results {
+            titles: param_cols.iter().map(|&col| titles[col].clone()).collect(),
+            rows: rows,
+        })
+    }
+
+    /// Read the results saved by `--cache` in `dir`, with the parameters of
+    /// their descriptions (the columns of batch input). Results of other
+    /// versions of the model are skipped with a warning.
+    pub fn read_cache(dir: &Path) -> Result<Self, String> {
+        let mut paths = vec![];
+        for entry in try!(fs::read_dir(dir).map_err(|e| e.to_string())) {
+            let path = try!(entry.map_err(|e| e.to_string())).path();
+            if path.extension().and_then(|ext| ext.to_str()) == Some(cache::EXTENSION) {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        let mut files = vec![];
+        for path in paths {
+            let mut contents = String::new();
+            try!(File::open(&path)
+                .and_then(|mut file| file.read_to_string(&mut contents))
+                .map_err(|e| format!("{}: {}", path.display(), e)));
+            files.push((path.display().to_string(), contents));
+        }
+        Results::from_saved(files)
+    }
+
+    // Results from the contents of cached results, with their names
+    fn from_saved<I: IntoIterator<Item = (String, String)>>(files: I) -> Result<Self, String> {
+        let num_cols = BATCH_COLUMNS.len() - 1;
+        assert_eq!(BATCH_COLUMNS[num_cols], COMBO_ID_TITLE);
+        let mut rows = vec![];
+        for (name, json) in files {
+            let (version, description, result) = try!(read_saved(&json)
+                .map_err(|e| format!("{}: {}", name, e)));
+            if version != MODEL_VERSION {
+                warn!("skipping {}: saved by model version {}, not {}",
+                      name,
+                      version,
+                      MODEL_VERSION);
+                continue;
+            }
+            // The parameters are followed by a description of the seeding, if any
+            let params = description.split(' ').next().unwrap_or("");
+            let cells: Vec<String> = params.split(',').map(|cell| cell.to_owned()).collect();
+            if cells.len() != num_cols + 1 {
+                return Err(format!("{}: expected {} parameters, found {}",
+                                   name,
+                                   num_cols + 1,
+                                   cells.len()));
+            }
+            rows.push(ResultRow {
+                cells: cells[..num_cols].to_vec(),
+                p_compromise: result.p_compromise,
+                runs: result.runs,
+            });
+        }
+        Ok(Results {
+            titles: BATCH_COLUMNS[..num_cols].iter().map(|title| (*title).to_owned()).collect(),
+            rows: rows,
+        })
+    }
+}
+
+/// Where P(compromise) reaches the target along y, at one value of x
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Crossing {
+    /// At this value of y
+    At(RR),
+    /// P(compromise) is above the target at every value of y given
+    Above,
+    /// P(compromise) is below the target at every value of y given
+    Below,
+}
+
+impl Crossing {
+    /// Status as written in the Status column
+    pub fn status(self) -> &'static str {
+        match self {
+            Crossing::At(_) => "ok",
+            Crossing::Above => "above",
+            Crossing::Below => "below",
+        }
+    }
+}
+
+// log10 of P(compromise) for interpolation towards `target`, if it may be
+// taken: a simulated zero counts as half a compromise, if below the target.
+fn log_p(p: RR, runs: Option<NN>, target: RR) -> Option<RR> {
+    if p > 0.0 {
+        return Some(p.log10());
+    }
+    match runs {
+        Some(runs) if runs > 0 && 0.5 / (runs as RR) < target => Some((0.5 / runs as RR).log10()),
+        _ => None,
+    }
+}
+
+/// Where P(compromise) first reaches `target` as y increases, given
+/// `(y, P(compromise), runs)` for values of y in increasing order (at least
+/// one).
+pub fn crossing(points: &[(RR, RR, Option<NN>)], target: RR) -> Crossing {
+    for (i, &(y, p, runs)) in points.iter().enumerate() {
+        if p == target {
+            return Crossing::At(y);
+        }
+        if i == 0 {
+            continue;
+        }
+        let (y0, p0, runs0) = points[i - 1];
+        if (p0 < target) == (p < target) {
+            continue;
+        }
+        let fraction = match (log_p(p0, runs0, target), log_p(p, runs, target)) {
+            (Some(l0), Some(l)) => (target.log10() - l0) / (l - l0),
+            _ => (target - p0) / (p - p0),
+        };
+        return Crossing::At(y0 + (y - y0) * fraction);
+    }
+    if points[0].1 > target {
+        Crossing::Above
+    } else {
+        Crossing::Below
+    }
+}
+
+// A value of y as a number, and whether it is a percentage
+fn parse_y(value: &str) -> Option<(RR, bool)> {
+    if value.ends_with('%') {
+        value[..value.len() - 1].parse().ok().map(|y| (y, true))
+    } else {
+        value.parse().ok().map(|y| (y, false))
+    }
+}
+
+/// The isoline of `results` where P(compromise) is `target`, as CSV: for each
+/// value of the column `x` (in each combination of the values of the other
+/// parameters which vary, given first), the value of `y` where it is reached,
+/// and the status of its crossing (see `Crossing`). Values of x are in order
+/// if all numbers, else in the order found.
+pub fn isoline(results: &Results, x: &str, y: &str, target: RR) -> Result<String, String> {
+    let column = |name: &str| {
+        results.titles.iter().position(|title| title == name).ok_or_else(|| {
+            format!("no column {} (parameters are {})", name, results.titles.join(", "))
+        })
+    };
+    let (x_col, y_col) = (try!(column(x)), try!(column(y)));
+    // Parameters which vary, other than x and y, separate the isolines
+    let series_cols: Vec<usize> = (0..results.titles.len())
+        .filter(|&col| {
+            col != x_col && col != y_col &&
+            results.rows.iter().any(|row| row.cells[col] != results.rows[0].cells[col])
+        })
+        .collect();
+
+    // Rows of each line and value of x, in the order found
+    let mut lines: Vec<Vec<&String>> = vec![];
+    let mut points: Vec<(usize, &String, Vec<(RR, RR, Option<NN>)>)> = vec![];
+    let mut percent = None;
+    for row in &results.rows {
+        let series: Vec<&String> = series_cols.iter().map(|&col| &row.cells[col]).collect();
+        let found = lines.iter().position(|line| *line == series);
+        let line = match found {
+            Some(line) => line,
+            None => {
+                lines.push(series);
+                lines.len() - 1
+            }
+        };
+        let (value, is_percent) = try!(parse_y(&row.cells[y_col]).ok_or_else(|| {
+            format!("bad value for {}: '{}' (must be a number)", y, row.cells[y_col])
+        }));
+        if percent.map_or(false, |percent| percent != is_percent) {
+            return Err(format!("values of {} are not all numbers, or all percentages", y));
+        }
+        percent = Some(is_percent);
+        let point = (value, row.p_compromise, row.runs);
+        let x_value = &row.cells[x_col];
+        let found = points.iter().position(|&(l, x, _)| l == line && x == x_value);
+        match found {
+            Some(i) => points[i].2.push(point),
+            None => points.push((line, x_value, vec![point])),
+        }
+    }
+    let numeric: Option<Vec<RR>> = points.iter().map(|&(_, x, _)| x.parse().ok()).collect();
+    if let Some(x_values) = numeric {
+        let mut order: Vec<usize> = (0..points.len()).collect();
+        order.sort_by(|&i, &j| {
+            (points[i].0, x_values[i]).partial_cmp(&(points[j].0, x_values[j])).expect("number")
+        });
+        let mut sorted: Vec<_> = points.into_iter().map(Some).collect();
+        points = order.iter().map(|&i| sorted[i].take().expect("point")).collect();
+    }
+
+    let mut titles: Vec<&str> = series_cols.iter()
+        .map(|&col| results.titles[col].as_str())
+        .collect();
+    titles.extend(&[x, y, "Status"]);
+    let mut csv = titles.join(",") + "\n";
+    for (line, x_value, mut ys) in points {
+        ys.sort_by(|a, b| a.0.partial_cmp(&b.0).expect("number"));
+        let found = crossing(&ys, target);
+        let y_value = match found {
+            Crossing::At(y) => {
+                let y = format!("{}", (y * 1e6).round() / 1e6);
+                if percent == Some(true) { y + "%" } else { y }
+            }
+            _ => String::new(),
+        };
+        let mut cells: Vec<&str> = lines[line].iter().map(|cell| cell.as_str()).collect();
+        cells.extend(&[x_value.as_str(), y_value.as_str(), found.status()]);
+        csv.push_str(&(cells.join(",") + "\n"));
+    }
+    Ok(csv)
+}
+
+/// The isoline of `query`, as CSV (see `isoline`), or why it cannot be found.
+pub fn run(query: &Query) -> Result<String, String> {
+    let results = if query.input.is_dir() {
+        try!(Results::read_cache(&query.input))
+    } else {
+        let file = try!(File::open(&query.input).map_err(|e| e.to_string()));
+        try!(Results::read(BufReader::new(file)))
+    };
+    if results.rows.is_empty() {
+        return Err("no results".to_owned());
+    }
+    isoline(&results, &query.x, &query.y, query.target)
+}
+
+#[cfg(test)]
+// Synthetic P(compromise) of `malicious` nodes among `nodes` with quorum size
+// `quorum`, whose isoline at 1e-5 is known: log10 P rises by 0.01 per malicious
+// node, from -5 at `malicious_at(nodes, quorum)`.
+fn synthetic_p(nodes: RR, malicious: RR, quorum: RR) -> RR {
+    let log10_p = -5.0 + 0.01 * (malicious - malicious_at(nodes, quorum));
+    (10.0 as RR).powf(log10_p).min(1.0)
+}
+
+#[cfg(test)]
+fn malicious_at(nodes: RR, quorum: RR) -> RR {
+    800.0 * quorum - 230.0 + (nodes - 1000.0) / 10.0
+}
+
+#[cfg(test)]
+// Check an isoline of the synthetic results at 1e-5 (from `synthetic_p`), with
+// the given Nodes column
+fn check_synthetic(csv: &str, nodes_col: bool) {
+    let mut lines = csv.lines();
+    let header = lines.next().expect("header");
+    if nodes_col {
+        assert_eq!(header, "Nodes,QuorumSize,Malicious,Status");
+    } else {
+        assert_eq!(header, "QuorumSize,Malicious,Status");
+    }
+    let mut found = 0;
+    for line in lines {
+        let cells: Vec<&str> = line.split(',').collect();
+        let nodes: RR = if nodes_col { cells[0].parse().expect("nodes") } else { 1000.0 };
+        let cells = &cells[cells.len() - 3..];
+        let quorum: RR = cells[0].parse().expect("quorum");
+        let expected = malicious_at(nodes, quorum);
+        if expected < 50.0 {
+            assert_eq!(cells[1..].to_vec(), vec!["", "above"], "{}", line);
+        } else if expected > 450.0 {
+            assert_eq!(cells[1..].to_vec(), vec!["", "below"], "{}", line);
+        } else {
+            let malicious: RR = cells[1].parse().expect("malicious");
+            assert!((malicious - expected).abs() < 1e-3, "{}: expected {}", line, expected);
+            assert_eq!(cells[2], "ok");
+            found += 1;
+        }
+    }
+    assert!(found >= 4);
+}
+
+#[test]
+fn test_crossing() {
+    let at = |points: &[(RR, RR, Option<NN>)], target: RR| match crossing(points, target) {
+        Crossing::At(y) => y,
+        other => panic!("{:?}", other),
+    };
+    // Exact where log10 P is linear
+    let points = [(10.0, 1e-7, None), (20.0, 1e-6, None), (30.0, 1e-4, None)];
+    assert!((at(&points, 1e-5) - 25.0).abs() < 1e-9);
+    assert_eq!(crossing(&points, 1e-6), Crossing::At(20.0));
+    assert_eq!(crossing(&points, 1e-8), Crossing::Above);
+    assert_eq!(crossing(&points, 1e-3), Crossing::Below);
+    // The first crossing, as y increases
+    let points = [(1.0, 0.1, None), (2.0, 0.3, None), (3.0, 0.1, None)];
+    let y = at(&points, 0.2);
+    assert!(y > 1.0 && y < 2.0);
+    assert_eq!(Crossing::At(y).status(), "ok");
+
+    // A simulated zero counts as half a compromise: 0.5 / 500 = 1e-3
+    let points = [(0.0, 0.0, Some(500)), (10.0, 1e-1, Some(500))];
+    assert!((at(&points, 1e-2) - 5.0).abs() < 1e-9);
+    // Unless that is not below the target, when P is linear, as for a calculated zero
+    let points = [(0.0, 0.0, Some(50)), (10.0, 1e-1, Some(50))];
+    assert!((at(&points, 1e-2) - 1.0).abs() < 1e-9);
+    let points = [(0.0, 0.0, None), (10.0, 1e-1, None)];
+    assert!((at(&points, 1e-2) - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_isoline() {
+    // The main table of a sweep of -n 1000,2000 -r 50-450:50 -q 0.1,0.5-0.9:0.1
+    let mut table = "Type     Nodes    Malicious QuorumSize Runs     P(compromise) Viz\n"
+        .to_owned();
+    for &nodes in &[1000.0, 2000.0] {
+        for &quorum in &[0.1, 0.5, 0.6, 0.7, 0.8, 0.9] {
+            // Rows in any order
+            for &malicious in &[450.0, 50.0, 100.0, 150.0, 200.0, 250.0, 300.0, 350.0, 400.0] {
+                table.push_str(&format!("dir_calc {:<8} {:<9} {:<10} -        {:e} ▆\n",
+                                        nodes,
+                                        malicious,
+                                        quorum,
+                                        synthetic_p(nodes, malicious, quorum)));
+            }
+        }
+    }
+    let results = Results::read(table.as_bytes()).expect("read");
+    assert_eq!(results.titles, vec!["Type", "Nodes", "Malicious", "QuorumSize"]);
+    let csv = isoline(&results, "QuorumSize", "Malicious", 1e-5).expect("isoline");
+    check_synthetic(&csv, true);
+    assert_eq!(csv.lines().nth(1), Some("1000,0.1,,above"));
+    assert_eq!(csv.lines().nth(6), Some("1000,0.9,,below"));
+
+    // The same as CSV, in log10, after the model stamp
+    let mut log10_csv = format!("{}\nType,Nodes,Malicious,QuorumSize,Runs,log10P(compromise)\n",
+                                model::stamp());
+    for row in &results.rows {
+        log10_csv.push_str(&format!("{},-,{}\n", row.cells.join(","), row.p_compromise.log10()));
+    }
+    let from_csv = Results::read(log10_csv.as_bytes()).expect("read");
+    assert_eq!(isoline(&from_csv, "QuorumSize", "Malicious", 1e-5), Ok(csv));
+
+    assert_eq!(isoline(&results, "QuorumProp", "Malicious", 1e-5).err(),
+               Some("no column QuorumProp (parameters are Type, Nodes, Malicious, QuorumSize)"
+                   .to_owned()));
+    assert!(isoline(&results, "QuorumSize", "Type", 1e-5).is_err());
+}
+
+#[test]
+fn test_isoline_cache() {
+    use super::args::sim_params;
+
+    // Results saved by --cache, of a sweep of -r 50-450:50 -q 0.5-0.9:0.1
+    let param_sets = sim_params(&["calc", "-n", "1000", "-r", "50-450:50", "-q", "0.5-0.9:0.1"]);
+    let files: Vec<(String, String)> = param_sets.iter()
+        .enumerate()
+        .map(|(i, params)| {
+            let description = params.run_description();
+            let cells: Vec<RR> = description.split(',')
+                .map(|cell| cell.parse().unwrap_or(0.0))
+                .collect();
+            let p = synthetic_p(1000.0, cells[8], cells[10]);
+            let json = format!("{{\"model_version\": {}, \"description\": \"{}\", \
+                                \"p_disrupt\": 1, \"p_compromise\": {}, \"groups\": 1, \
+                                \"deployed\": null, \"runs\": null}}",
+                               MODEL_VERSION,
+                               description,
+                               p);
+            (format!("{}.json", i), json)
+        })
+        .collect();
+    assert_eq!(files.len(), 45);
+    let results = Results::from_saved(files.clone()).expect("read");
+    check_synthetic(&isoline(&results, "QuorumSize", "Malicious", 1e-5).expect("isoline"),
+                    false);
+
+    // Results of other versions are skipped
+    let mut files = files;
+    files[0].1 = files[0].1.replace(&format!("\"model_version\": {}", MODEL_VERSION),
+                                    "\"model_version\": 0");
+    assert_eq!(Results::from_saved(files).expect("read").rows.len(), 44);
+}
@@ -0,0 +1,225 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Rate limiting of joins: a cap on the nodes each group accepts per window
+//! of steps (see `--join-cap`)
+
+use super::NN;
+use super::sim::{NodeName, NodeData, Prefix};
+
+use std::collections::VecDeque;
+use std::fmt::{self, Formatter};
+use std::str::FromStr;
+
+
+/// What happens to a node the cap keeps out of its group
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JoinCapMode {
+    /// Turned away: it redoes proof-of-work under a new name, like a node
+    /// refused for its age
+    Reject,
+    /// Kept waiting, in order, and tried again at the next step
+    Queue,
+}
+
+impl JoinCapMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            JoinCapMode::Reject => "reject",
+            JoinCapMode::Queue => "queue",
+        }
+    }
+
+    /// Parse from `name()`
+    pub fn from_name(name: &str) -> Option<JoinCapMode> {
+        [JoinCapMode::Reject, JoinCapMode::Queue].iter().cloned().find(|m| m.name() == name)
+    }
+}
+
+/// A cap on joins: each group accepts at most `joins` new members in any
+/// `window` consecutive steps, whether they join or are relocated there,
+/// honest or malicious.
+///
+/// Written (and parsed) as `J:W`, followed by `+queue` in queue mode, e.g.
+/// `2:10+queue`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct JoinCap {
+    pub joins: NN,
+    pub window: NN,
+    pub mode: JoinCapMode,
+}
+
+/// Join cap as written in the JoinCap column: "none" if not given.
+pub fn join_cap_name(cap: Option<JoinCap>) -> String {
+    cap.map_or("none".to_owned(), |c| c.to_string())
+}
+
+impl fmt::Display for JoinCap {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.mode {
+            JoinCapMode::Reject => f.pad(&format!("{}:{}", self.joins, self.window)),
+            JoinCapMode::Queue => f.pad(&format!("{}:{}+queue", self.joins, self.window)),
+        }
+    }
+}
+
+impl FromStr for JoinCap {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (cap, mode) = if s.ends_with("+queue") {
+            (&s[..s.len() - "+queue".len()], JoinCapMode::Queue)
+        } else {
+            (s, JoinCapMode::Reject)
+        };
+        let parts: Vec<&str> = cap.split(':').collect();
+        if parts.len() != 2 {
+            return Err(format!("expected J:W, found '{}'", s));
+        }
+        let mut values = [0; 2];
+        for (value, part) in values.iter_mut().zip(&parts) {
+            *value = match part.parse::<NN>() {
+                Ok(n) if n >= 1 => n,
+                Ok(_) => return Err("joins and window must be at least 1".to_owned()),
+                Err(_) => return Err(format!("bad number '{}'", part)),
+            };
+        }
+        Ok(JoinCap {
+            joins: values[0],
+            window: values[1],
+            mode: mode,
+        })
+    }
+}
+
+/// A node waiting to join: the step from which it may, with its name and data
+pub type Waiting = (NN, (NodeName, NodeData));
+
+/// Enforces a `JoinCap` over one simulation, remembering the joins accepted
+/// within the window and, in queue mode, the nodes held back at the current
+/// step. Joins count by name, so that those accepted by a group before it
+/// split count against whichever half each node is now in.
+pub struct JoinLimiter {
+    cap: JoinCap,
+    // Step and name of each join accepted within the window, oldest first
+    accepted: VecDeque<(NN, NodeName)>,
+    held: Vec<Waiting>,
+}
+
+impl JoinLimiter {
+    pub fn new(cap: JoinCap) -> Self {
+        JoinLimiter {
+            cap: cap,
+            accepted: VecDeque::new(),
+            held: vec![],
+        }
+    }
+
+    pub fn mode(&self) -> JoinCapMode {
+        self.cap.mode
+    }
+
+    /// Whether the group of `prefix` may accept another node at `step`.
+    /// Steps must not decrease from one call to the next.
+    pub fn admits(&mut self, prefix: Prefix, step: NN) -> bool {
+        while self.accepted.front().map_or(false, |&(at, _)| at + self.cap.window <= step) {
+            let _ = self.accepted.pop_front();
+        }
+        let joined = self.accepted.iter().filter(|&&(_, name)| prefix.matches(name)).count();
+        (joined as NN) < self.cap.joins
+    }
+
+    /// Note that the node `name` joined at `step`
+    pub fn record(&mut self, name: NodeName, step: NN) {
+        self.accepted.push_back((step, name));
+    }
+
+    /// Hold back a node the cap keeps out this step (queue mode)
+    pub fn hold(&mut self, node: Waiting) {
+        self.held.push(node);
+    }
+
+    /// The nodes held back this step, in the order they were held, to wait
+    /// ahead of any node ready later
+    pub fn release(&mut self) -> Vec<Waiting> {
+        self.held.drain(..).collect()
+    }
+}
+
+#[test]
+fn test_join_cap() {
+    let cap: JoinCap = "2:10".parse().expect("parse");
+    assert_eq!(cap,
+               JoinCap {
+                   joins: 2,
+                   window: 10,
+                   mode: JoinCapMode::Reject,
+               });
+    assert_eq!(cap.to_string().parse(), Ok(cap));
+    let queued: JoinCap = "3:5+queue".parse().expect("parse");
+    assert_eq!(queued.mode, JoinCapMode::Queue);
+    assert_eq!(queued.to_string(), "3:5+queue");
+    assert_eq!(join_cap_name(None), "none");
+    assert_eq!("0:5".parse::<JoinCap>(),
+               Err("joins and window must be at least 1".to_owned()));
+    assert_eq!("2".parse::<JoinCap>(), Err("expected J:W, found '2'".to_owned()));
+    assert!("2:x".parse::<JoinCap>().is_err());
+    assert_eq!(JoinCapMode::from_name("queue"), Some(JoinCapMode::Queue));
+}
+
+#[test]
+fn test_join_limiter() {
+    use rand::{Rng, SeedableRng, XorShiftRng};
+
+    let prefix = |bits: &str| bits.parse::<Prefix>().expect("prefix");
+    // Up to 3 joins in any 4 steps. Nodes try to join groups 0 and 1 at random, until group 0
+    // splits at step 20; joins to it before then count against each half by name.
+    let cap: JoinCap = "3:4".parse().expect("parse");
+    let mut limiter = JoinLimiter::new(cap);
+    let mut rng = XorShiftRng::from_seed([5, 6, 7, 8]);
+    let mut joins: Vec<(NN, NodeName)> = vec![];
+    for step in 0..60 {
+        let prefixes = if step < 20 {
+            vec![prefix("0"), prefix("1")]
+        } else {
+            vec![prefix("00"), prefix("01"), prefix("1")]
+        };
+        for _ in 0..rng.gen_range(0, 5) {
+            let name: NodeName = rng.gen();
+            let group = *prefixes.iter().find(|p| p.matches(name)).expect("group");
+            if limiter.admits(group, step) {
+                limiter.record(name, step);
+                joins.push((step, name));
+            }
+        }
+    }
+    // Never more than the cap in any window of any group of the time, while nodes still join
+    // throughout
+    for end in 0..60 {
+        let groups: &[&str] = if end < 20 { &["0", "1"] } else { &["00", "01", "1"] };
+        for &bits in groups {
+            let in_window = joins.iter()
+                .filter(|&&(step, name)| {
+                    step <= end && step + cap.window > end && prefix(bits).matches(name)
+                })
+                .count();
+            assert!(in_window as NN <= cap.joins, "{} joins to {} by {}", in_window, bits, end);
+        }
+    }
+    assert!(joins.iter().any(|&(step, _)| step >= 40));
+    assert!(joins.len() > 40);
+}
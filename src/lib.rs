@@ -0,0 +1,375 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Calculations to do with security of routing system
+//!
+//! The binary drives these from the command line; the library may also be used
+//! directly, e.g. to plug in custom attack strategies (see
+//! `quorum::AttackStrategy`).
+
+#![feature(inclusive_range_syntax)]
+
+extern crate rand;
+extern crate rustc_serialize;
+extern crate docopt;
+extern crate libc;
+#[macro_use]
+extern crate log;
+
+pub mod prob;
+pub mod sim;
+pub mod args;
+pub mod quorum;
+pub mod tools;
+pub mod churn_model;
+pub mod estimate;
+pub mod output;
+pub mod batch;
+pub mod uptime;
+pub mod burst;
+pub mod checkpoint;
+pub mod baseline;
+pub mod rng;
+pub mod selftest;
+pub mod marginals;
+pub mod bench;
+pub mod explain;
+pub mod html;
+pub mod targeting_cost;
+pub mod logging;
+pub mod split_check;
+pub mod interrupt;
+pub mod corrupt;
+pub mod combo;
+pub mod sweep;
+pub mod steady;
+pub mod placement;
+pub mod audit;
+pub mod close_group;
+pub mod model;
+pub mod scan;
+pub mod verify;
+pub mod allocation;
+pub mod duration;
+pub mod cache;
+pub mod join_cap;
+pub mod isoline;
+pub mod auto_extend;
+
+use std::result;
+use std::sync::Arc;
+use std::fmt::{self, Formatter};
+
+use quorum::{QuorumSize, QuorumBasis, Objective, Weighting, AgeCap, AttackDecision,
+             ElderTiebreak, AttackerKnowledge};
+use churn_model::ChurnModel;
+use sim::{Prefix, RelocationTarget, PowScaling};
+use tools::{AnyGroupModel, DEFAULT_REJOIN_WINDOW};
+use uptime::UptimeDist;
+use burst::Burst;
+use join_cap::JoinCap;
+use corrupt::Corruption;
+use steady::SteadyState;
+use placement::InitialPlacement;
+use close_group::CloseGroup;
+use checkpoint::CheckpointFile;
+use rng::Seeding;
+use prob::ProbCache;
+use interrupt::Interrupt;
+
+
+// We could use templating but there's no reason not to do the easy thing and
+// fix types.
+
+pub type NN = u64;
+pub type RR = f64;
+
+/// Error type
+pub enum Error {
+    AddRestriction,
+    AlreadyExists,
+    NotFound,
+}
+
+/// Result type
+pub type Result<T> = result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            &Error::AddRestriction => write!(f, "addition prevented by AddRestriction"),
+            &Error::AlreadyExists => write!(f, "already exists"),
+            &Error::NotFound => write!(f, "not found"),
+        }
+    }
+}
+
+/// Parameters common to all tools
+pub struct ToolArgs {
+    num_nodes: NN,
+    num_malicious: NN,
+    min_group_size: NN,
+    quorum: QuorumSize,
+    any_group: bool,
+    max_steps: NN,
+    repetitions: NN,
+    relocation: RelocationTarget,
+    /// Where malicious nodes are when the simulation starts
+    placement: InitialPlacement,
+    malice_hist: bool,
+    churn_model: ChurnModel,
+    elders_report: bool,
+    any_group_model: AnyGroupModel,
+    compare_models: bool,
+    correction: bool,
+    target_prefix: Option<Prefix>,
+    age_share_interval: Option<NN>,
+    /// Whether to count groups lacking a quorum of live members
+    liveness: bool,
+    /// Whether to record the outcome of each repetition
+    outcomes: bool,
+    /// Whether to record why each repetition ended
+    termination: bool,
+    /// Whether to check if structural changes could be blocked
+    governance: bool,
+    /// Whether to count the groups holding malicious nodes at the end
+    spread: bool,
+    /// Whether to sum the shares of the address space of the compromised and
+    /// disrupted groups at the end
+    address_coverage: bool,
+    /// Close groups to compare with sections, if requested (structure
+    /// simulation only)
+    close_group: Option<CloseGroup>,
+    /// Whether to sample the median age of honest nodes, to report how quickly
+    /// it grows
+    turnover: bool,
+    /// Measurement of the long-run steady state, if requested (full simulation
+    /// only; runs then continue past compromise)
+    steady_state: Option<SteadyState>,
+    /// Steps between cross-checks of the incremental quorum checks of the full
+    /// simulation against checking every group, if any
+    paranoid: Option<NN>,
+    /// Whether to check the invariants of the network structure every step of
+    /// the simulations (see `verify::Verifier`; debug builds check every
+    /// `verify::DEBUG_STEPS` steps regardless)
+    verify: bool,
+    /// Whether to record the attacker's decisions in the first repetition of
+    /// the full simulation
+    record_attack: bool,
+    /// Decisions for the attacker to replay in the first repetition of the
+    /// full simulation, if any
+    replay_attack: Option<Vec<AttackDecision>>,
+    /// Whether to count the values drawn from each random number stream in
+    /// each repetition of the full simulation
+    rng_audit: bool,
+    honest_uptime: UptimeDist,
+    /// What the votes of a weighted quorum are weighted by
+    weighting: Weighting,
+    /// Cap on the age counted by quorums, if any
+    age_cap: Option<AgeCap>,
+    /// Consecutive steps a group must stay disrupted or compromised for it to
+    /// count
+    hold: NN,
+    /// Steps by which the membership groups are checked against lags behind
+    /// the network
+    membership_lag: NN,
+    /// Steps from an elder's seat falling vacant to the promotion filling it
+    promotion_lag: NN,
+    /// How elders of equal age are ranked
+    elder_tiebreak: ElderTiebreak,
+    /// Probability that a targetting attacker grinding the name of a new node
+    /// wins ties of age with it (see `quorum::AttackStrategy::grinds_name`)
+    name_grinding: RR,
+    /// Proportion of its former age a malicious node rejoining with its old
+    /// identity recovers
+    rejoin_recovery: RR,
+    /// Steps for which the identities of departed nodes are remembered
+    rejoin_window: NN,
+    /// Correlated failure bursts, if any
+    burst: Option<Burst>,
+    /// Corruption of honest nodes in place, if any
+    corruption: Option<Corruption>,
+    /// Probability of each honest node being evicted at each step
+    eviction_rate: RR,
+    /// Resource of malicious nodes relative to honest ones; their eviction
+    /// rate is divided by this
+    attack_resource: RR,
+    /// How the proof-of-work to join scales with network size
+    pow_scaling: PowScaling,
+    /// Step at which malicious nodes start joining
+    attack_start: NN,
+    /// Cap on the nodes each group accepts per window of steps, if any
+    join_cap: Option<JoinCap>,
+    checkpoint: Option<CheckpointFile>,
+    /// Seconds of wall time after which repeated simulation stops, returning
+    /// the repetitions completed so far, if any
+    time_limit: Option<RR>,
+    /// Stops repeated simulation at the next repetition once set, returning
+    /// the repetitions completed so far
+    interrupt: Interrupt,
+    /// Number of folds of consecutive repetitions whose estimates are compared
+    /// (see `split_check`), if any
+    split_check: Option<NN>,
+    block_prop: Option<RR>,
+    quorum_basis: QuorumBasis,
+    /// Minimum number of voters for a quorum, whatever the proportion, if any
+    quorum_floor: Option<NN>,
+    /// Bound of the offset of each group's quorum proportion (see
+    /// `quorum::QuorumJitter`; simulations only)
+    quorum_jitter: RR,
+    /// Probability that an honest member takes part in a decision (see
+    /// `quorum::Participation`)
+    participation: RR,
+    seeding: Seeding,
+    targetted_bound: bool,
+    objective: Objective,
+    /// What the attacker knows of the network when deploying its nodes
+    attacker_knowledge: AttackerKnowledge,
+    /// Cache of probabilities, possibly shared with other tools
+    prob_cache: Arc<ProbCache>,
+}
+
+impl ToolArgs {
+    /// Create, with the "any group" mode (groups treated as independent), no
+    /// target prefix, random relocation, malicious nodes placed uniformly,
+    /// honest nodes always up, the fixed churn model, no failure bursts, no
+    /// corruption of honest nodes, no evictions, one step of proof-of-work to
+    /// join, no cap on joins, the attack starting at once, disruption when
+    /// honest nodes lack a quorum, quorum proportions of the actual group size,
+    /// no quorum floor or jitter, every honest node taking part in decisions,
+    /// weighted quorums weighted by age with no age cap, attackers aiming for
+    /// compromise and knowing the whole network, disruption and compromise
+    /// counting at once and of the current membership, elders promoted at once
+    /// with ties of age broken by name and no name grinding, no age recovered
+    /// on rejoining, no extra reports, no checkpoints, no time limit, no
+    /// interruption, no split check, no cross-checks of the incremental quorum
+    /// checks, no recording or replay of the attack, a random seed and a cache
+    /// of probabilities of its own.
+    pub fn new(num_nodes: NN,
+               num_malicious: NN,
+               min_group_size: NN,
+               quorum: QuorumSize,
+               max_steps: NN,
+               repetitions: NN)
+               -> Self {
+        let args = ToolArgs {
+            num_nodes: num_nodes,
+            num_malicious: num_malicious,
+            min_group_size: min_group_size,
+            quorum: quorum,
+            any_group: true,
+            max_steps: max_steps,
+            repetitions: repetitions,
+            relocation: RelocationTarget::Random,
+            placement: InitialPlacement::Uniform,
+            malice_hist: false,
+            churn_model: ChurnModel::Fixed,
+            elders_report: false,
+            any_group_model: AnyGroupModel::Independent,
+            compare_models: false,
+            correction: false,
+            target_prefix: None,
+            age_share_interval: None,
+            liveness: false,
+            outcomes: false,
+            termination: false,
+            governance: false,
+            spread: false,
+            address_coverage: false,
+            close_group: None,
+            turnover: false,
+            steady_state: None,
+            paranoid: None,
+            verify: false,
+            record_attack: false,
+            replay_attack: None,
+            rng_audit: false,
+            honest_uptime: UptimeDist::new(),
+            weighting: Weighting::Age,
+            age_cap: None,
+            hold: 1,
+            membership_lag: 0,
+            promotion_lag: 0,
+            elder_tiebreak: ElderTiebreak::Name,
+            name_grinding: 0.0,
+            rejoin_recovery: 0.0,
+            rejoin_window: DEFAULT_REJOIN_WINDOW,
+            burst: None,
+            corruption: None,
+            eviction_rate: 0.0,
+            attack_resource: 1.0,
+            pow_scaling: PowScaling::Constant,
+            attack_start: 0,
+            join_cap: None,
+            checkpoint: None,
+            time_limit: None,
+            interrupt: Interrupt::new(),
+            split_check: None,
+            block_prop: None,
+            quorum_basis: QuorumBasis::Actual,
+            quorum_floor: None,
+            quorum_jitter: 0.0,
+            participation: 1.0,
+            seeding: Seeding::new(),
+            targetted_bound: false,
+            objective: Objective::Compromise,
+            attacker_knowledge: AttackerKnowledge::Full,
+            prob_cache: Arc::new(ProbCache::new()),
+        };
+        args.check_invariant();
+        args
+    }
+
+    fn check_invariant(&self) {
+        assert!(self.num_nodes >= self.num_malicious);
+        match self.quorum {
+            QuorumSize::Proportion(prop) => assert!(prop >= 0.0 && prop <= 1.0),
+            QuorumSize::Count(n) => {
+                assert!(n <= self.min_group_size,
+                        "quorum count {} exceeds min group size {}",
+                        n,
+                        self.min_group_size)
+            }
+        }
+        if let Some(floor) = self.quorum_floor {
+            assert!(floor >= 1 && floor <= self.min_group_size,
+                    "quorum floor {} outside range 1 to min group size {}",
+                    floor,
+                    self.min_group_size);
+        }
+        assert!(self.quorum_jitter >= 0.0 && self.quorum_jitter <= 1.0);
+        assert!(self.participation > 0.0 && self.participation <= 1.0);
+        assert!(self.name_grinding >= 0.0 && self.name_grinding <= 1.0);
+        assert!(self.age_share_interval != Some(0));
+        assert!(self.paranoid != Some(0));
+        assert!(self.hold >= 1);
+        assert!(self.split_check.map_or(true, |k| k >= 2));
+        assert!(self.eviction_rate >= 0.0 && self.eviction_rate <= 1.0);
+        assert!(self.attack_resource > 0.0);
+        assert!(self.rejoin_recovery >= 0.0 && self.rejoin_recovery <= 1.0);
+        assert!(self.attack_start == 0 || self.attack_start < self.max_steps);
+        if let Some(ref corruption) = self.corruption {
+            assert!(corruption.last_step() < self.max_steps,
+                    "corruption at step {} is not within {} steps",
+                    corruption.last_step(),
+                    self.max_steps);
+        }
+        if let Some(block) = self.block_prop {
+            assert!(block >= 0.0 && block <= 1.0);
+        }
+    }
+}
@@ -0,0 +1,166 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Leveled logging to stderr
+//!
+//! Messages go through the `log` macros (`error!` to `trace!`) and are written
+//! to stderr one per line, as the level, the module and the message, e.g.
+//! `WARN  routing_sims::batch: skipping line 4: ...`, so that they may be
+//! filtered with grep. By default messages from `info` up are written; `-v`
+//! adds debug messages, `-vv` trace messages, and `--log-level` sets the level
+//! outright.
+//!
+//! The macros compare the level with the maximum set here before evaluating
+//! their arguments, so a message not written costs one comparison; messages in
+//! the inner loops of the simulations whose arguments take work to compute are
+//! also guarded with `log_enabled!`.
+
+use std::io::{self, Write};
+
+use log::{self, Log, LogLevel, LogLevelFilter, LogMetadata, LogRecord, SetLoggerError};
+
+
+/// Level written without `-v` or `--log-level`
+pub const DEFAULT_LEVEL: LogLevelFilter = LogLevelFilter::Info;
+
+/// Level given by `count` occurrences of `-v`: each adds a level of detail
+/// from the default
+pub fn verbosity(count: usize) -> LogLevelFilter {
+    match count {
+        0 => DEFAULT_LEVEL,
+        1 => LogLevelFilter::Debug,
+        _ => LogLevelFilter::Trace,
+    }
+}
+
+/// A message as written: the level (padded to a fixed width), the module and
+/// the message
+pub fn format_line(level: LogLevel, module: &str, message: &str) -> String {
+    format!("{:<5} {}: {}", level, module, message)
+}
+
+/// Logger writing messages of a level or above to stderr
+pub struct Logger {
+    level: LogLevelFilter,
+}
+
+impl Logger {
+    /// Create, writing messages at `level` and above
+    pub fn new(level: LogLevelFilter) -> Self {
+        Logger { level: level }
+    }
+
+    // The line for `record`, if at the level or above.
+    fn line(&self, record: &LogRecord) -> Option<String> {
+        if self.enabled(record.metadata()) {
+            Some(format_line(record.level(), record.target(), &record.args().to_string()))
+        } else {
+            None
+        }
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &LogMetadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &LogRecord) {
+        if let Some(line) = self.line(record) {
+            let _ = writeln!(io::stderr(), "{}", line);
+        }
+    }
+}
+
+/// Install the logger, writing messages at `level` and above. Fails if a
+/// logger is already installed.
+pub fn init(level: LogLevelFilter) -> Result<(), SetLoggerError> {
+    log::set_logger(|max_level| {
+        max_level.set(level);
+        Box::new(Logger::new(level))
+    })
+}
+
+// Tests capture the messages of their own thread rather than writing them, each at a level of
+// its own; messages of other threads are dropped.
+#[cfg(test)]
+thread_local!(static CAPTURED: ::std::cell::RefCell<Option<(Logger, String)>> =
+    ::std::cell::RefCell::new(None));
+
+#[cfg(test)]
+struct CaptureLogger;
+
+#[cfg(test)]
+impl Log for CaptureLogger {
+    fn enabled(&self, _: &LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &LogRecord) {
+        CAPTURED.with(|captured| if let Some((ref logger, ref mut out)) = *captured.borrow_mut() {
+            if let Some(line) = logger.line(record) {
+                out.push_str(&line);
+                out.push('\n');
+            }
+        });
+    }
+}
+
+/// Run `f`, returning the lines it logged on this thread at `level` and above,
+/// as they would be written to stderr
+#[cfg(test)]
+pub fn capture<F: FnOnce()>(level: LogLevelFilter, f: F) -> String {
+    use std::sync::{Once, ONCE_INIT};
+
+    static INIT: Once = ONCE_INIT;
+    INIT.call_once(|| {
+        let _ = log::set_logger(|max_level| {
+            max_level.set(LogLevelFilter::Trace);
+            Box::new(CaptureLogger)
+        });
+    });
+    CAPTURED.with(|captured| *captured.borrow_mut() = Some((Logger::new(level), String::new())));
+    f();
+    CAPTURED.with(|captured| captured.borrow_mut().take().map_or(String::new(), |c| c.1))
+}
+
+#[test]
+fn test_verbosity() {
+    assert_eq!(verbosity(0), LogLevelFilter::Info);
+    assert_eq!(verbosity(1), LogLevelFilter::Debug);
+    assert_eq!(verbosity(2), LogLevelFilter::Trace);
+    assert_eq!(verbosity(3), LogLevelFilter::Trace);
+    assert_eq!(format_line(LogLevel::Warn, "routing_sims::batch", "skipping line 4"),
+               "WARN  routing_sims::batch: skipping line 4");
+}
+
+#[test]
+fn test_capture() {
+    let log_all = || {
+        error!("an error");
+        warn!("a warning");
+        info!("some information");
+        debug!("a detail");
+        trace!("a finer detail");
+    };
+    assert_eq!(capture(LogLevelFilter::Warn, &log_all),
+               "ERROR routing_sims::logging: an error\nWARN  routing_sims::logging: a warning\n");
+    let lines = capture(LogLevelFilter::Trace, &log_all);
+    assert_eq!(lines.lines().count(), 5);
+    assert!(lines.contains("TRACE routing_sims::logging: a finer detail"));
+    assert_eq!(capture(LogLevelFilter::Off, &log_all), "");
+}
@@ -17,115 +17,2235 @@
 
 // Calculations to do with security of routing system
 
-#![feature(inclusive_range_syntax)]
-
-extern crate rand;
-extern crate rustc_serialize;
-extern crate docopt;
+extern crate routing_sims;
 #[macro_use]
 extern crate log;
-extern crate env_logger;
 extern crate rayon;
 
-mod prob;
-mod sim;
-mod args;
-mod quorum;
-mod tools;
-
-use std::result;
-use std::fmt::{self, Formatter};
 use std::cmp::max;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::process;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use rayon::prelude::*;
 use rayon::par_iter::collect::collect_into;
 
-use args::{ArgProc, PARAM_TITLES};
+use routing_sims::{NN, RR};
+use routing_sims::args::{ArgProc, Expansion, QuorumType, SimParams, PARAM_TITLES,
+                         expansion_text};
+use routing_sims::checkpoint::Progress;
+use routing_sims::tools::{AgeingBenefit, SimResult, MALICE_HIST_BUCKETS, DEFAULT_REJOIN_WINDOW};
+use routing_sims::quorum::{Objective, Weighting, ElderTiebreak, AttackerKnowledge,
+                           DEFAULT_ELDERS, read_attack_decisions};
+use routing_sims::sim::PowScaling;
+use routing_sims::estimate::{self, MemoryBudget, Reservation};
+use routing_sims::batch::{self, age_cap_name, block_prop_name, elders_name, elder_tiebreak_name,
+                          name_grinding_name, quorum_floor_name, weighting_name};
+use routing_sims::burst::burst_name;
+use routing_sims::join_cap::join_cap_name;
+use routing_sims::corrupt::corruption_name;
+use routing_sims::placement::InitialPlacement;
+use routing_sims::output::{self, OrderedRows, PartialFile, ProbFormat, Viz};
+use routing_sims::baseline::{Baseline, row_key, key_text};
+use routing_sims::churn_model::ChurnModel;
+use routing_sims::selftest;
+use routing_sims::bench::{self, Tier};
+use routing_sims::explain;
+use routing_sims::combo::{self, combo_id, COMBO_ID_TITLE};
+use routing_sims::html;
+use routing_sims::targeting_cost;
+use routing_sims::marginals::{self, Marginal, Row, Summary};
+use routing_sims::logging;
+use routing_sims::split_check;
+use routing_sims::sweep;
+use routing_sims::interrupt::{self, Interrupt};
+use routing_sims::audit::{self, AUDIT_TITLES, audit_rows};
+use routing_sims::model::{self, MODEL_VERSION};
+use routing_sims::scan::{self, Scan};
+use routing_sims::rng::{Seeding, scan_rng};
+use routing_sims::allocation::{Allocator, RepetitionBudget};
+use routing_sims::duration::format_duration;
+use routing_sims::cache::{self, ResultCache};
+use routing_sims::isoline;
+
+
+/// Repetitions of each parameter set per round, with `--interleave`
+const INTERLEAVE_BATCH: NN = 50;
+
+fn main() {
+    // The self-test takes no options
+    if env::args().nth(1).map_or(false, |command| command == "selftest") {
+        logging::init(logging::DEFAULT_LEVEL).unwrap();
+        process::exit(if run_selftest() { 0 } else { 1 });
+    }
+    // Neither does the benchmark, other than its tier
+    if env::args().nth(1).map_or(false, |command| command == "bench") {
+        logging::init(logging::DEFAULT_LEVEL).unwrap();
+        let tier = env::args().nth(2).map(|tier| {
+            tier.parse::<Tier>().unwrap_or_else(|e| panic!("unexpected: bench {} ({})", tier, e))
+        });
+        run_bench(tier);
+        return;
+    }
+    // The explanation takes the options of calc or full, for one parameter set
+    if env::args().nth(1).map_or(false, |command| command == "explain") {
+        let argv: Vec<String> = env::args().collect();
+        let arg_proc = ArgProc::from_argv(explain::tool_argv(&argv)).unwrap_or_else(|e| e.exit());
+        logging::init(arg_proc.log_level()).unwrap();
+        let params = explain::single(arg_proc.make_sim_params()).unwrap_or_else(|e| {
+            error!("{}", e);
+            process::exit(1);
+        });
+        print!("{}", explain::report(&params, arg_proc.prob_format()));
+        return;
+    }
+
+    // The ids take the options of calc, structure or full
+    if env::args().nth(1).map_or(false, |command| command == "id") {
+        let argv: Vec<String> = env::args().collect();
+        let arg_proc = ArgProc::from_argv(combo::tool_argv(&argv)).unwrap_or_else(|e| e.exit());
+        logging::init(arg_proc.log_level()).unwrap();
+        for params in arg_proc.make_sim_params() {
+            println!("{}", combo_id(&params));
+        }
+        return;
+    }
+
+    // The targetting cost is analytic, with options of its own
+    if env::args().nth(1).map_or(false, |command| command == "targeting-cost") {
+        let argv: Vec<String> = env::args().collect();
+        let queries = targeting_cost::parse_argv(&argv).unwrap_or_else(|e| e.exit());
+        logging::init(logging::DEFAULT_LEVEL).unwrap();
+        print!("{}", targeting_cost::report(&queries));
+        return;
+    }
+
+    // The audit compares two files written by --rng-audit
+    if env::args().nth(1).map_or(false, |command| command == "audit") {
+        logging::init(logging::DEFAULT_LEVEL).unwrap();
+        let argv: Vec<String> = env::args().collect();
+        if argv.len() != 4 {
+            panic!("unexpected: audit takes two files");
+        }
+        match audit::compare_files(&argv[2], &argv[3]) {
+            Ok(None) => println!("{} and {} draw alike", argv[2], argv[3]),
+            Ok(Some(divergence)) => {
+                println!("First divergence, {}", divergence);
+                process::exit(1);
+            }
+            Err(e) => {
+                error!("{}", e);
+                process::exit(2);
+            }
+        }
+        return;
+    }
+
+    // Pruning the cache takes its directory and the age of results to remove
+    if env::args().nth(1).map_or(false, |command| command == "cache") {
+        logging::init(logging::DEFAULT_LEVEL).unwrap();
+        let argv: Vec<String> = env::args().collect();
+        let (dir, older_than) = cache::parse_argv(&argv).unwrap_or_else(|e| e.exit());
+        match cache::prune(&dir, older_than) {
+            Ok((removed, kept)) => {
+                println!("Removed {} cached results written at least {} ago from {} ({} kept)",
+                         removed,
+                         format_duration(older_than),
+                         dir.display(),
+                         kept)
+            }
+            Err(e) => {
+                error!("unable to prune cache {}: {}", dir.display(), e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // An isoline is read from the results of an earlier sweep
+    if env::args().nth(1).map_or(false, |command| command == "isoline") {
+        logging::init(logging::DEFAULT_LEVEL).unwrap();
+        let argv: Vec<String> = env::args().collect();
+        let query = isoline::parse_argv(&argv).unwrap_or_else(|e| e.exit());
+        match isoline::run(&query) {
+            Ok(csv) => print!("{}", csv),
+            Err(e) => {
+                error!("unable to read an isoline from {}: {}", query.input.display(), e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // A scan takes the options of calc, structure or full, sampling within their ranges
+    let scan = if env::args().nth(1).map_or(false, |command| command == "scan") {
+        let argv: Vec<String> = env::args().collect();
+        Some(scan::parse_argv(&argv).unwrap_or_else(|e| panic!("unexpected: scan: {}", e)))
+    } else {
+        None
+    };
+    let mut arg_proc = match scan {
+        Some((_, ref argv)) => ArgProc::read_argv(argv),
+        None => ArgProc::read_args(),
+    };
+    logging::init(arg_proc.log_level()).unwrap();
+    let expansion = match scan {
+        Some(_) => None,
+        None => Some(arg_proc.expansion()),
+    };
+    let sources = match expansion {
+        Some(ref expansion) => expansion.sources().to_vec(),
+        None => arg_proc.param_sources(),
+    };
+    if let Err(e) = arg_proc.check_strict(&sources) {
+        error!("{}", e);
+        process::exit(1);
+    }
+    // The parameters as given, also for the HTML report
+    let mut config: Vec<String> = sources.iter().map(|param| param.to_string()).collect();
+    config.extend(arg_proc.duration_sources().iter().map(|param| param.to_string()));
+    config.push(format!("model version {}", MODEL_VERSION));
+    for line in &config {
+        info!("{}", line);
+    }
+    let dims = expansion.as_ref().map_or_else(Vec::new, |expansion| expansion.dims());
+    if !dims.is_empty() {
+        info!("{}", expansion_text(&dims));
+        config.push(expansion_text(&dims));
+    }
+    let prob_format = arg_proc.prob_format();
+    let viz = arg_proc.viz();
+    let baseline = arg_proc.baseline_path().map(|path| {
+        File::open(path)
+            .map_err(|e| e.to_string())
+            .and_then(|file| Baseline::read(BufReader::new(file)))
+            .unwrap_or_else(|e| panic!("unable to read baseline {}: {}", path, e))
+    });
+    // Results of another version of the model aren't comparable
+    if let (Some(path), Some(baseline)) = (arg_proc.baseline_path(), baseline.as_ref()) {
+        if let Err(e) = baseline.check_model(path, arg_proc.allow_model_mismatch()) {
+            error!("{}", e);
+            process::exit(1);
+        }
+    }
+    if let Some(ref expansion) = expansion {
+        if arg_proc.streamed() {
+            run_streamed(&arg_proc, expansion, baseline.as_ref(), viz);
+            return;
+        }
+    }
+    let mut param_sets = match scan {
+        Some((scan, _)) => scan_sets(&mut arg_proc, scan),
+        None => expansion.expect("expansion").iter().collect(),
+    };
+    let auto_extend = arg_proc.auto_extend();
+    if let Some(extend) = auto_extend {
+        extend.check(&param_sets).unwrap_or_else(|e| panic!("unexpected: --auto-extend: {}", e));
+    }
+    if let Some(path) = arg_proc.replay_attack() {
+        let decisions = File::open(path)
+            .map_err(|e| e.to_string())
+            .and_then(|file| read_attack_decisions(BufReader::new(file)))
+            .unwrap_or_else(|e| panic!("unable to read attack decisions {}: {}", path, e));
+        for (combination, decisions) in decisions {
+            if combination == 0 || combination > param_sets.len() {
+                panic!("unexpected: --replay-attack {} (combination {} is not in this run, of \
+                        {} combinations)",
+                       path,
+                       combination,
+                       param_sets.len());
+            }
+            param_sets[combination - 1].replay_attack = Some(decisions);
+        }
+    }
+    if arg_proc.dry_run() {
+        batch::write_params(&param_sets, &mut io::stdout()).expect("write to stdout");
+        return;
+    }
+
+    let global_budget = arg_proc.global_budget();
+    let canonical = arg_proc.canonical();
+    let work = estimate::total_work_units(&param_sets);
+    // Sets whose estimated memory is above --max-mem are skipped, and the
+    // others only run at once while within it
+    let max_mem = arg_proc.max_mem();
+    let mut memory = MemoryPlan::new(&param_sets, max_mem);
+    let skipped = memory.skipped.iter().filter(|&&skip| skip).count();
+    announce(&arg_proc, param_sets.len(), work, skipped);
+    // From here, Ctrl-C stops the sweep early but still writes the results so far
+    let interrupt = arg_proc.interrupt();
+    interrupt::install();
+
+    let table = Table::new(&Given::of(&param_sets),
+                           arg_proc.baseline_column(),
+                           baseline.is_some(),
+                           global_budget.is_some(),
+                           canonical,
+                           prob_format,
+                           viz);
+    // Rows are printed as each parameter set completes, held back until all
+    // rows above them are printed so that the table is in order (and flushed a
+    // batch at a time, see `OrderedRows`), after the stamp of the model version
+    let printed = Mutex::new(Printed {
+        rows: OrderedRows::new(BufWriter::new(io::stdout()),
+                               &format!("{}\n{}", model::stamp(), table.header()))
+            .expect("write to stdout"),
+        not_in_baseline: Vec::new(),
+        matched: vec![false; baseline.as_ref().map_or(0, |b| b.rows.len())],
+    });
+
+    info!("Starting to simulate {} different parameter sets",
+          param_sets.len());
+    // Sets start in the order of the schedule, but are output by number
+    let mut order = estimate::schedule(&param_sets, arg_proc.schedule());
+    // With --interleave or --global-budget the results are calculated first,
+    // and the table is printed from them as usual
+    let in_rounds = arg_proc.interleave() || global_budget.is_some();
+    if in_rounds {
+        for params in &mut param_sets {
+            params.master_seed = Some(params.seeding.master());
+        }
+    }
+    // Sets whose results are in the cache are not run, in rounds, sweeps or alone
+    let result_cache = arg_proc.result_cache();
+    let cached: Vec<Option<SimResult>> = param_sets.iter()
+        .zip(&memory.skipped)
+        .map(|(params, &skipped)| if skipped {
+            None
+        } else {
+            result_cache.as_ref().and_then(|cache| cache.load(params))
+        })
+        .collect();
+    let hits = cached.iter().filter(|result| result.is_some()).count();
+    if hits > 0 {
+        info!("Reusing the cached results of {} parameter sets", hits);
+    }
+    let mut not_run: Vec<bool> = cached.iter()
+        .zip(&memory.skipped)
+        .map(|(result, &skipped)| skipped || result.is_some())
+        .collect();
+    let mut cached: Vec<Mutex<Option<SimResult>>> = cached.into_iter().map(Mutex::new).collect();
+    let (interleaved, pilot_only) = if let Some(total) = global_budget {
+        run_budgeted(&param_sets, &order, &not_run, &memory, interrupt, total)
+    } else if arg_proc.interleave() {
+        (run_interleaved(&param_sets, &order, &not_run, &memory, interrupt, prob_format),
+         vec![])
+    } else if let Some(extend) = auto_extend {
+        // With --auto-extend the results are calculated first too, with those
+        // of the sets it adds, which are numbered after the others
+        let initial = param_sets.len();
+        // Sets added whose results are in the cache
+        let reused = Mutex::new(Vec::new());
+        let results = extend.run_extended(&mut param_sets, |first, sets| {
+            let order: Vec<usize> = (0..sets.len()).collect();
+            run_scheduled(&order, |j| {
+                let (i, params) = (first + j, &sets[j]);
+                let bytes = if i < initial {
+                    if memory.skipped(i) {
+                        return None;
+                    }
+                    if let Some(result) = cached[i].lock().expect("lock").take() {
+                        return Some(result);
+                    }
+                    memory.estimate(i)
+                } else {
+                    let bytes = estimate::memory_bytes(params);
+                    if max_mem.map_or(false, |cap| bytes > cap) {
+                        return None;
+                    }
+                    if let Some(result) = result_cache.as_ref().and_then(|c| c.load(params)) {
+                        reused.lock().expect("lock").push(i);
+                        return Some(result);
+                    }
+                    bytes
+                };
+                if interrupt.is_set() {
+                    return None;
+                }
+                let _reserved = memory.budget.as_ref().map(|budget| budget.reserve(bytes));
+                Some(params.result())
+            })
+        });
+        let reused = reused.into_inner().expect("lock");
+        memory.add(&param_sets[initial..], max_mem);
+        for i in initial..param_sets.len() {
+            not_run.push(memory.skipped(i) || reused.contains(&i));
+            cached.push(Mutex::new(None));
+            order.push(i);
+        }
+        (results.into_iter().map(Mutex::new).collect(), vec![])
+    } else {
+        (Vec::new(), vec![])
+    };
+    // Sets differing only in the quorum size share their runs, where the attack does not depend
+    // on the quorum. Their results are also calculated first, a sweep at a time.
+    let sweeps = if in_rounds || auto_extend.is_some() {
+        vec![]
+    } else {
+        sweep::quorum_sweeps(&param_sets, &not_run)
+    };
+    if !sweeps.is_empty() {
+        info!("Sharing runs between {} parameter sets differing only in the quorum size",
+              sweeps.iter().map(|group| group.len()).sum::<usize>());
+    }
+    let sweep_order: Vec<usize> = (0..sweeps.len()).collect();
+    let swept: Vec<Vec<SimResult>> = run_scheduled(&sweep_order, |g| {
+        if interrupt.is_set() {
+            return vec![];
+        }
+        let reserve = sweeps[g].iter().map(|&i| memory.estimate(i)).fold(0.0, RR::max);
+        let _reserved = memory.budget.as_ref().map(|budget| budget.reserve(reserve));
+        sweep::sweep_results(&param_sets, &sweeps[g])
+    });
+    let swept: Vec<Mutex<Option<SimResult>>> = {
+        let mut by_set: Vec<Option<SimResult>> = param_sets.iter().map(|_| None).collect();
+        for (group, results) in sweeps.iter().zip(swept) {
+            for (&i, result) in group.iter().zip(results) {
+                by_set[i] = Some(result);
+            }
+        }
+        by_set.into_iter().map(Mutex::new).collect()
+    };
+    // The curve file is written as each parameter set completes, in order, to
+    // a partial file renamed once all are complete (and left, labelled, if the
+    // run fails or is interrupted), after the stamp of the model version
+    let curve = arg_proc.curve_path().map(|path| {
+        let file = PartialFile::create(path)
+            .unwrap_or_else(|e| panic!("unable to create curve file {}: {}", path, e));
+        let header = format!("{}\nCombination,ComboId,Step,AgeShare", model::stamp());
+        (path,
+         Mutex::new(OrderedRows::new(file, &header)
+            .unwrap_or_else(|e| panic!("unable to write curve file {}: {}", path, e))))
+    });
+    // Likewise the attacker's decisions
+    let attack_record = arg_proc.record_attack().map(|path| {
+        let file = PartialFile::create(path)
+            .unwrap_or_else(|e| panic!("unable to create attack record {}: {}", path, e));
+        let header = format!("{}\nCombination,Step,Prefix,Reset", model::stamp());
+        (path,
+         Mutex::new(OrderedRows::new(file, &header)
+            .unwrap_or_else(|e| panic!("unable to write attack record {}: {}", path, e))))
+    });
+    // And the values drawn from each stream
+    let rng_audit = arg_proc.rng_audit().map(|path| {
+        let file = PartialFile::create(path)
+            .unwrap_or_else(|e| panic!("unable to create RNG audit {}: {}", path, e));
+        let header = format!("{}\n{}", model::stamp(), AUDIT_TITLES);
+        (path,
+         Mutex::new(OrderedRows::new(file, &header)
+            .unwrap_or_else(|e| panic!("unable to write RNG audit {}: {}", path, e))))
+    });
+    let files = RowFiles {
+        curve: curve.as_ref(),
+        attack_record: attack_record.as_ref(),
+        rng_audit: rng_audit.as_ref(),
+    };
+    let results = run_scheduled(&order, |i| {
+        let results = simulate_set(i,
+                                   &param_sets[i],
+                                   &cached[i],
+                                   interleaved.get(i),
+                                   pilot_only.get(i).cloned().unwrap_or(false),
+                                   &swept[i],
+                                   result_cache.as_ref(),
+                                   &memory,
+                                   interrupt,
+                                   &table,
+                                   baseline.as_ref(),
+                                   &printed,
+                                   &files);
+        log_done(i, param_sets.len(), results.as_ref(), &memory);
+        results
+    });
+    let Printed { rows, mut not_in_baseline, matched } = printed.into_inner().expect("lock");
+    rows.into_inner().expect("write to stdout");
+    not_in_baseline.sort();
+    log_prob_cache(&arg_proc);
+
+    // Notes on the main table, also for the HTML report
+    let interrupted = interrupt.is_set();
+    let mut tally = Tally::default();
+    for (i, (params, results)) in param_sets.iter().zip(&results).enumerate() {
+        tally.add(i, params, results.as_ref(), &memory);
+    }
+    let notes = table_notes(&arg_proc, &tally, baseline.as_ref(), &not_in_baseline, &matched);
+    for note in &notes {
+        println!();
+        println!("{}", note);
+    }
+
+    // The rows of the main table, with their baseline comparisons, for the
+    // HTML report
+    let html_rows: Option<Vec<Vec<String>>> = arg_proc.report_html().map(|_| {
+        param_sets.iter()
+            .zip(&results)
+            .enumerate()
+            .map(|(i, (params, results))| {
+                let pilot = pilot_only.get(i).cloned().unwrap_or(false);
+                let mut row = table.row(params, results.as_ref(), memory.skipped(i), pilot);
+                if let Some(ref baseline) = baseline {
+                    table.compare(&mut row, results.as_ref(), baseline);
+                }
+                row.push(combo_id(params));
+                row
+            })
+            .collect()
+    });
+
+    // The reports after the table are of the parameter sets run
+    let (param_sets, results): (Vec<SimParams>, Vec<SimResult>) = param_sets.into_iter()
+        .zip(results)
+        .filter_map(|(params, results)| results.map(|results| (params, results)))
+        .unzip();
+
+    if results.iter().any(|r| r.malice_hist.is_some()) {
+        println!();
+        print_malice_hist(&param_sets, &results);
+    }
+    if results.iter().any(|r| r.elders.is_some()) {
+        println!();
+        print_elders(&param_sets, &results, prob_format);
+    }
+    if results.iter().any(|r| r.liveness.is_some()) {
+        println!();
+        print_liveness(&param_sets, &results, prob_format);
+    }
+    if results.iter().any(|r| r.termination.is_some()) {
+        println!();
+        print_termination(&param_sets, &results);
+    }
+    if results.iter().any(|r| r.governance.is_some()) {
+        println!();
+        print_governance(&param_sets, &results, prob_format);
+    }
+    if results.iter().any(|r| r.models.is_some()) {
+        println!();
+        print_models(&param_sets, &results, prob_format);
+    }
+    if results.iter().any(|r| r.correction.is_some()) {
+        println!();
+        print_correction(&param_sets, &results, prob_format);
+    }
+    if results.iter().any(|r| r.outcomes.is_some()) {
+        println!();
+        print_ageing_benefit(&param_sets, &results, prob_format);
+    }
+    if results.iter().any(|r| r.split_check.is_some()) {
+        println!();
+        print_split_check(&param_sets, &results, prob_format);
+    }
+    // The marginals and charts summarise the rows run, leaving out those stopped
+    // by the time limit unless included
+    let summarised: Vec<Row> = param_sets.iter()
+        .zip(&results)
+        .filter(|&(_, results)| !results.truncated || arg_proc.include_truncated())
+        .map(|(params, results)| table.marginal_row(params, results))
+        .collect();
+    if let Some(log) = arg_proc.marginals() {
+        let titles = &table.titles[..table.num_param_cols];
+        print_marginals(&marginals::marginals(titles, &summarised, log), log, prob_format);
+    }
+    // Incomplete once interrupted, so left as partial files
+    let complete = |file: PartialFile| if interrupted {
+        file.leave_partial()
+    } else {
+        file.finish()
+    };
+    if let (Some(path), Some(rows)) = (arg_proc.report_html(), html_rows) {
+        // Charted from the probabilities, rather than as shown
+        let num_param_cols = table.num_param_cols;
+        let charts = html::charts(&table.titles[..num_param_cols], &summarised, prob_format.log10);
+        let page = html::page(&config, &table.shown_titles(), &rows, &notes, &charts);
+        PartialFile::create(path)
+            .and_then(|mut file| file.write_all(page.as_bytes()).map(|_| file))
+            .and_then(&complete)
+            .unwrap_or_else(|e| panic!("unable to write HTML report {}: {}", path, e));
+    }
+    if let Some((path, curve)) = curve {
+        curve.into_inner()
+            .expect("lock")
+            .into_inner()
+            .and_then(&complete)
+            .unwrap_or_else(|e| panic!("unable to write curve file {}: {}", path, e));
+    }
+    if let Some((path, record)) = attack_record {
+        record.into_inner()
+            .expect("lock")
+            .into_inner()
+            .and_then(&complete)
+            .unwrap_or_else(|e| panic!("unable to write attack record {}: {}", path, e));
+    }
+    if let Some((path, audit)) = rng_audit {
+        audit.into_inner()
+            .expect("lock")
+            .into_inner()
+            .and_then(&complete)
+            .unwrap_or_else(|e| panic!("unable to write RNG audit {}: {}", path, e));
+    }
+}
+
+// Whether any parameter set gives a value to each of the optional columns of
+// the main table (see `Table::new`), gathered a set at a time so that the sets
+// need not all be held
+#[derive(Default)]
+struct Given {
+    floor: bool,
+    block: bool,
+    objective: bool,
+    knowledge: bool,
+    burst: bool,
+    corrupt: bool,
+    eviction: bool,
+    resource: bool,
+    recovery: bool,
+    window: bool,
+    churn: bool,
+    pow: bool,
+    start: bool,
+    hold: bool,
+    membership: bool,
+    join_cap: bool,
+    elders: bool,
+    lag: bool,
+    tiebreak: bool,
+    grinding: bool,
+    weight: bool,
+    age_cap: bool,
+    placement: bool,
+    jitter: bool,
+    participation: bool,
+    deployed: bool,
+    spread: bool,
+    coverage: bool,
+    close: bool,
+    turnover: bool,
+    steady: bool,
+}
+
+impl Given {
+    fn of(param_sets: &[SimParams]) -> Self {
+        let mut given = Given::default();
+        for params in param_sets {
+            given.add(params);
+        }
+        given
+    }
+
+    fn add(&mut self, params: &SimParams) {
+        self.floor |= params.quorum_floor.is_some();
+        self.block |= params.block_prop.is_some();
+        self.objective |= params.objective != Objective::Compromise;
+        self.knowledge |= params.attacker_knowledge != AttackerKnowledge::Full;
+        self.burst |= params.burst.is_some();
+        self.corrupt |= params.corruption.is_some();
+        self.eviction |= params.eviction_rate != 0.0;
+        self.resource |= params.attack_resource != 1.0;
+        self.recovery |= params.rejoin_recovery != 0.0;
+        self.window |= params.rejoin_window != DEFAULT_REJOIN_WINDOW;
+        self.churn |= params.churn_model != ChurnModel::Fixed;
+        self.pow |= params.pow_scaling != PowScaling::Constant;
+        self.start |= params.attack_start != 0;
+        self.hold |= params.hold != 1;
+        self.membership |= params.membership_lag != 0;
+        self.join_cap |= params.join_cap.is_some();
+        self.elders |= params.elders.map_or(false, |e| e != DEFAULT_ELDERS as NN);
+        self.lag |= params.promotion_lag.map_or(false, |lag| lag != 0);
+        self.tiebreak |= params.elder_tiebreak.map_or(false, |t| t != ElderTiebreak::Name);
+        self.grinding |= params.name_grinding.map_or(false, |p| p != 0.0);
+        self.weight |= params.weighting.as_ref().map_or(false, |w| *w != Weighting::Age);
+        self.age_cap |= params.age_cap.is_some();
+        self.placement |= params.placement != InitialPlacement::Uniform;
+        self.jitter |= params.quorum_jitter != 0.0;
+        self.participation |= params.participation != 1.0;
+        self.deployed |= params.targetting.holds_reserve();
+        self.spread |= params.spread;
+        self.coverage |= params.address_coverage;
+        self.close |= params.close_group.is_some();
+        self.turnover |= params.turnover;
+        self.steady |= params.steady_state.is_some();
+    }
+}
+
+// Layout of the main table: the columns shown and their widths
+struct Table {
+    titles: Vec<&'static str>,
+    col_widths: Vec<usize>,
+    // Number of leading columns holding parameters
+    num_param_cols: usize,
+    // Whether the optional parameter columns are shown
+    floor_col: bool,
+    block_col: bool,
+    objective_col: bool,
+    knowledge_col: bool,
+    burst_col: bool,
+    corrupt_col: bool,
+    eviction_col: bool,
+    resource_col: bool,
+    recovery_col: bool,
+    window_col: bool,
+    churn_col: bool,
+    pow_col: bool,
+    start_col: bool,
+    hold_col: bool,
+    membership_col: bool,
+    join_cap_col: bool,
+    elders_col: bool,
+    lag_col: bool,
+    tiebreak_col: bool,
+    grinding_col: bool,
+    weight_col: bool,
+    age_cap_col: bool,
+    placement_col: bool,
+    jitter_col: bool,
+    participation_col: bool,
+    // Whether each set's having only its pilot repetitions is shown (with a
+    // global budget)
+    pilot_col: bool,
+    // Whether the mean number of malicious nodes deployed is shown
+    deployed_col: bool,
+    // Whether the spread, address coverage, close group and turnover reports'
+    // and steady state's columns are shown
+    spread_cols: bool,
+    coverage_cols: bool,
+    close_cols: bool,
+    turnover_cols: bool,
+    steady_cols: bool,
+    // Whether the analytic P(compromise) is shown
+    analytic_col: bool,
+    // Whether the output is canonical (see --canonical)
+    canonical: bool,
+    prob_format: ProbFormat,
+    viz: Option<Viz>,
+}
+
+impl Table {
+    fn new(given: &Given,
+           analytic_col: bool,
+           with_baseline: bool,
+           pilot_col: bool,
+           canonical: bool,
+           prob_format: ProbFormat,
+           viz: Option<Viz>)
+           -> Self {
+        // The quorum floor, blocking proportion, objective, attacker knowledge,
+        // bursts, corruption, evictions, age recovery on rejoining, proof-of-work
+        // scaling, attack start, hold, membership lag, join cap, elder count,
+        // promotion lag, elder tiebreak, name grinding, weighting, age cap, initial
+        // placement, quorum jitter and participation are only shown when given
+        // (always, with --canonical, so the columns are fixed)
+        let shown = |any: bool| canonical || any;
+        let floor_col = shown(given.floor);
+        let block_col = shown(given.block);
+        let objective_col = shown(given.objective);
+        let knowledge_col = shown(given.knowledge);
+        let burst_col = shown(given.burst);
+        let corrupt_col = shown(given.corrupt);
+        let eviction_col = shown(given.eviction);
+        let resource_col = shown(given.resource);
+        let recovery_col = shown(given.recovery);
+        let window_col = shown(given.window);
+        // Likewise the churn model, when not fixed
+        let churn_col = shown(given.churn);
+        let pow_col = shown(given.pow);
+        let start_col = shown(given.start);
+        let hold_col = shown(given.hold);
+        let membership_col = shown(given.membership);
+        let join_cap_col = shown(given.join_cap);
+        let elders_col = shown(given.elders);
+        let lag_col = shown(given.lag);
+        let tiebreak_col = shown(given.tiebreak);
+        let grinding_col = shown(given.grinding);
+        let weight_col = shown(given.weight);
+        let age_cap_col = shown(given.age_cap);
+        let placement_col = shown(given.placement);
+        let jitter_col = shown(given.jitter);
+        let participation_col = shown(given.participation);
+        let mut titles = PARAM_TITLES.to_vec();
+        // After QuorumBasis
+        let mut col = 11;
+        if floor_col {
+            titles.insert(col, "QuorumFloor");
+            col += 1;
+        }
+        if block_col {
+            titles.insert(col, "BlockProp");
+            col += 1;
+        }
+        if objective_col {
+            titles.insert(col, "Objective");
+            col += 1;
+        }
+        if knowledge_col {
+            titles.insert(col, "Knowledge");
+        }
+        if churn_col {
+            titles.insert(6, "ChurnModel");
+        }
+        if burst_col {
+            let col = titles.len() - 4;
+            titles.insert(col, "Burst");
+        }
+        if corrupt_col {
+            let col = titles.len() - 4;
+            titles.insert(col, "Corrupt");
+        }
+        if eviction_col {
+            let col = titles.len() - 4;
+            titles.insert(col, "EvictionRate");
+        }
+        if resource_col {
+            let col = titles.len() - 4;
+            titles.insert(col, "AttackResource");
+        }
+        if recovery_col {
+            let col = titles.len() - 4;
+            titles.insert(col, "RejoinRecovery");
+        }
+        if window_col {
+            let col = titles.len() - 4;
+            titles.insert(col, "RejoinWindow");
+        }
+        if pow_col {
+            let col = titles.len() - 4;
+            titles.insert(col, "PowScaling");
+        }
+        if start_col {
+            let col = titles.len() - 4;
+            titles.insert(col, "AttackStart");
+        }
+        if hold_col {
+            let col = titles.len() - 4;
+            titles.insert(col, "Hold");
+        }
+        if membership_col {
+            let col = titles.len() - 4;
+            titles.insert(col, "MembershipLag");
+        }
+        if join_cap_col {
+            let col = titles.len() - 4;
+            titles.insert(col, "JoinCap");
+        }
+        if elders_col {
+            let col = titles.len() - 4;
+            titles.insert(col, "Elders");
+        }
+        if lag_col {
+            let col = titles.len() - 4;
+            titles.insert(col, "PromotionLag");
+        }
+        if tiebreak_col {
+            let col = titles.len() - 4;
+            titles.insert(col, "ElderTiebreak");
+        }
+        if grinding_col {
+            let col = titles.len() - 4;
+            titles.insert(col, "NameGrinding");
+        }
+        if weight_col {
+            let col = titles.len() - 4;
+            titles.insert(col, "Weight");
+        }
+        if age_cap_col {
+            let col = titles.len() - 4;
+            titles.insert(col, "AgeCap");
+        }
+        if placement_col {
+            let col = titles.len() - 4;
+            titles.insert(col, "Placement");
+        }
+        if jitter_col {
+            let col = titles.len() - 4;
+            titles.insert(col, "Jitter");
+        }
+        if participation_col {
+            let col = titles.len() - 4;
+            titles.insert(col, "Participation");
+        }
+        let num_param_cols = titles.len() - 4;
+        // With a global budget, whether each set has only its pilot repetitions
+        if pilot_col {
+            titles.push("Pilot");
+        }
+        // Where a strategy may hold nodes in reserve, the mean number deployed
+        // (as against the Malicious column, the number available)
+        let deployed_col = given.deployed;
+        if deployed_col {
+            titles.push("Deployed");
+        }
+        // The spread report is a pair of columns rather than a table of its own
+        let spread_cols = given.spread;
+        if spread_cols {
+            titles.extend_from_slice(&["Spread", "MaxCluster"]);
+        }
+        // As is the address coverage
+        let coverage_cols = given.coverage;
+        if coverage_cols {
+            titles.extend_from_slice(&["Coverage",
+                                       "MaxCoverage",
+                                       "Coverage(disruption)",
+                                       "MaxCoverage(disruption)"]);
+        }
+        // And the close groups
+        let close_cols = given.close;
+        if close_cols {
+            titles.extend_from_slice(&["P(section)", "P(close)", "P(loss)"]);
+        }
+        // So is the turnover report
+        let turnover_cols = given.turnover;
+        if turnover_cols {
+            titles.extend_from_slice(&["Turnover", "MedianAge"]);
+        }
+        // And the steady state
+        let steady_cols = given.steady;
+        if steady_cols {
+            titles.extend_from_slice(&["Steady(disruption)",
+                                       "SteadyCI(disruption)",
+                                       "RunsCI(disruption)",
+                                       "Steady(compromise)",
+                                       "SteadyCI(compromise)",
+                                       "RunsCI(compromise)",
+                                       "Trend"]);
+        }
+        if analytic_col {
+            titles.push("Analytic");
+        }
+        if with_baseline {
+            titles.extend_from_slice(&["Baseline", "Delta", "Signif."]);
+        }
+        titles.push(COMBO_ID_TITLE);
+        // Probability columns are marked if written as log10
+        let col_widths =
+            titles.iter().map(|title| max(shown_title(title, prob_format).len(), 8)).collect();
+        Table {
+            titles: titles,
+            col_widths: col_widths,
+            num_param_cols: num_param_cols,
+            floor_col: floor_col,
+            block_col: block_col,
+            objective_col: objective_col,
+            knowledge_col: knowledge_col,
+            burst_col: burst_col,
+            corrupt_col: corrupt_col,
+            eviction_col: eviction_col,
+            resource_col: resource_col,
+            recovery_col: recovery_col,
+            window_col: window_col,
+            churn_col: churn_col,
+            pow_col: pow_col,
+            start_col: start_col,
+            hold_col: hold_col,
+            membership_col: membership_col,
+            join_cap_col: join_cap_col,
+            elders_col: elders_col,
+            lag_col: lag_col,
+            tiebreak_col: tiebreak_col,
+            grinding_col: grinding_col,
+            weight_col: weight_col,
+            age_cap_col: age_cap_col,
+            placement_col: placement_col,
+            jitter_col: jitter_col,
+            participation_col: participation_col,
+            pilot_col: pilot_col,
+            deployed_col: deployed_col,
+            spread_cols: spread_cols,
+            coverage_cols: coverage_cols,
+            close_cols: close_cols,
+            turnover_cols: turnover_cols,
+            steady_cols: steady_cols,
+            analytic_col: analytic_col,
+            canonical: canonical,
+            prob_format: prob_format,
+            viz: viz,
+        }
+    }
+
+    fn shown_titles(&self) -> Vec<String> {
+        self.titles.iter().map(|title| shown_title(title, self.prob_format)).collect()
+    }
+
+    fn header(&self) -> String {
+        let mut header = String::new();
+        for (width, title) in self.col_widths.iter().zip(self.shown_titles()) {
+            header.push_str(&format!("{1:<0$} ", width, title));
+        }
+        if self.viz.is_some() {
+            header.push_str("Viz");
+        }
+        header
+    }
+
+    // The parameter and result columns of the row for a parameter set (or of a
+    // parameter set skipped for its memory or not run, without results), which
+    // may have only its `pilot` repetitions of a global budget
+    fn row(&self,
+           params: &SimParams,
+           results: Option<&SimResult>,
+           skipped: bool,
+           pilot: bool)
+           -> Vec<String> {
+        let mut row = vec![params.sim_type.name().to_owned(),
+                           params.quorum_type.name().to_owned(),
+                           params.targetting_name(),
+                           target_name(params),
+                           params.relocation.name().to_owned(),
+                           params.honest_uptime.to_string()];
+        if self.churn_col {
+            row.push(params.churn_model.name().to_owned());
+        }
+        row.extend(vec![params.num_nodes.to_string(),
+                        params.num_malicious.from_base(params.num_nodes).to_string(),
+                        params.min_group_size.to_string(),
+                        format!("{:.1$}", params.quorum, "QuorumSize".len() - 2),
+                        params.quorum_basis.name().to_owned()]);
+        if self.floor_col {
+            row.push(quorum_floor_name(params.quorum_floor));
+        }
+        if self.block_col {
+            row.push(block_prop_name(params.block_prop));
+        }
+        if self.objective_col {
+            row.push(params.objective.name().to_owned());
+        }
+        if self.knowledge_col {
+            row.push(params.attacker_knowledge.name().to_owned());
+        }
+        if self.burst_col {
+            row.push(burst_name(params.burst));
+        }
+        if self.corrupt_col {
+            row.push(corruption_name(params.corruption.as_ref()));
+        }
+        if self.eviction_col {
+            row.push(params.eviction_rate.to_string());
+        }
+        if self.resource_col {
+            row.push(params.attack_resource.to_string());
+        }
+        if self.recovery_col {
+            row.push(params.rejoin_recovery.to_string());
+        }
+        if self.window_col {
+            row.push(params.rejoin_window.to_string());
+        }
+        if self.pow_col {
+            row.push(params.pow_scaling.name().to_owned());
+        }
+        if self.start_col {
+            row.push(params.attack_start.to_string());
+        }
+        if self.hold_col {
+            row.push(params.hold.to_string());
+        }
+        if self.membership_col {
+            row.push(params.membership_lag.to_string());
+        }
+        if self.join_cap_col {
+            row.push(join_cap_name(params.join_cap));
+        }
+        if self.elders_col {
+            row.push(elders_name(params.elders));
+        }
+        if self.lag_col {
+            row.push(elders_name(params.promotion_lag));
+        }
+        if self.tiebreak_col {
+            row.push(elder_tiebreak_name(params.elder_tiebreak));
+        }
+        if self.grinding_col {
+            row.push(name_grinding_name(params.name_grinding));
+        }
+        if self.weight_col {
+            row.push(weighting_name(params.weighting.as_ref()));
+        }
+        if self.age_cap_col {
+            row.push(age_cap_name(params.age_cap));
+        }
+        if self.placement_col {
+            row.push(params.placement.to_string());
+        }
+        if self.jitter_col {
+            row.push(params.quorum_jitter.to_string());
+        }
+        if self.participation_col {
+            row.push(params.participation.to_string());
+        }
+        match results {
+            Some(results) => {
+                row.push(if self.canonical {
+                    results.groups.to_string()
+                } else {
+                    format!("{:.1}", results.groups)
+                });
+                row.push(runs_name(results));
+                row.push(self.prob_format.format(results.p_disrupt));
+                row.push(p_compromise_name(results, self.prob_format));
+            }
+            None => {
+                row.push(if skipped { "skipped" } else { "not run" }.to_owned());
+                row.extend(vec!["-".to_owned(); 3]);
+            }
+        }
+        if self.pilot_col {
+            let pilot = results.is_some() && pilot;
+            row.push(if pilot { "yes" } else { "-" }.to_owned());
+        }
+        if self.deployed_col {
+            row.push(match results.and_then(|results| results.deployed) {
+                Some(deployed) if self.canonical => deployed.to_string(),
+                Some(deployed) => format!("{:.1}", deployed),
+                None => "-".to_owned(),
+            });
+        }
+        if self.spread_cols {
+            match results.and_then(|results| results.spread.as_ref()) {
+                Some(spread) if self.canonical => {
+                    row.push(spread.mean_groups.to_string());
+                    row.push(spread.mean_max_cluster.to_string());
+                }
+                Some(spread) => {
+                    row.push(format!("{:.1}", spread.mean_groups));
+                    row.push(format!("{:.1}", spread.mean_max_cluster));
+                }
+                None => row.extend(vec!["-".to_owned(); 2]),
+            }
+        }
+        if self.coverage_cols {
+            match results.and_then(|results| results.coverage.as_ref()) {
+                Some(coverage) => {
+                    for &x in &[coverage.mean_compromised,
+                                coverage.max_compromised,
+                                coverage.mean_disrupted,
+                                coverage.max_disrupted] {
+                        row.push(if self.canonical {
+                            x.to_string()
+                        } else {
+                            format!("{:.4}", x)
+                        });
+                    }
+                }
+                None => row.extend(vec!["-".to_owned(); 4]),
+            }
+        }
+        if self.close_cols {
+            match results.and_then(|results| results.close_group.as_ref()) {
+                Some(close) => {
+                    for &x in &[close.p_section, close.p_close, close.p_loss] {
+                        row.push(if self.canonical {
+                            x.to_string()
+                        } else {
+                            format!("{:.4}", x)
+                        });
+                    }
+                }
+                None => row.extend(vec!["-".to_owned(); 3]),
+            }
+        }
+        if self.turnover_cols {
+            match results.and_then(|results| results.turnover.as_ref()) {
+                Some(turnover) => {
+                    let (steps, median) = if self.canonical {
+                        (turnover.mean_doubling_steps.map(|steps| steps.to_string()),
+                         turnover.mean_median_age.to_string())
+                    } else {
+                        (turnover.mean_doubling_steps.map(|steps| format!("{:.1}", steps)),
+                         format!("{:.2}", turnover.mean_median_age))
+                    };
+                    row.push(steps.unwrap_or("-".to_owned()));
+                    row.push(median);
+                }
+                None => row.extend(vec!["-".to_owned(); 2]),
+            }
+        }
+        if self.steady_cols {
+            match results.and_then(|results| results.steady_state.as_ref()) {
+                Some(steady) => {
+                    for &x in &[Some(steady.disrupted),
+                                Some(steady.disrupted_ci),
+                                steady.disrupted_runs_ci,
+                                Some(steady.compromised),
+                                Some(steady.compromised_ci),
+                                steady.compromised_runs_ci] {
+                        row.push(match x {
+                            Some(x) if self.canonical => x.to_string(),
+                            Some(x) => format!("{:.4}", x),
+                            None => "-".to_owned(),
+                        });
+                    }
+                    row.push(if steady.trending { "yes" } else { "-" }.to_owned());
+                }
+                None => row.extend(vec!["-".to_owned(); 7]),
+            }
+        }
+        if self.analytic_col {
+            row.push(params.analytic().map_or("-".to_owned(), |p| self.prob_format.format(p)));
+        }
+        row
+    }
+
+    // The parameter columns and P(compromise) of a parameter set run, for the
+    // marginals and charts
+    fn marginal_row(&self, params: &SimParams, results: &SimResult) -> Row {
+        let row = self.row(params, Some(results), false, false);
+        (row[..self.num_param_cols].to_vec(), results.p_compromise, results.runs)
+    }
 
+    // Compare a row, of the given results (none if skipped), with the
+    // baseline, adding the comparison columns. Returns the baseline row matched
+    // (if any).
+    fn compare(&self,
+               row: &mut Vec<String>,
+               results: Option<&SimResult>,
+               baseline: &Baseline)
+               -> Option<usize> {
+        let comparison = results.and_then(|results| {
+            // Compare probabilities at the precision shown (as probabilities,
+            // even if shown as log10), so that identical results have no
+            // difference
+            let key = row_key(self.titles
+                .iter()
+                .cloned()
+                .zip(row.iter().map(|s| s.as_str()))
+                .take(self.num_param_cols));
+            let p = self.prob_format
+                .linear()
+                .format(results.p_compromise)
+                .parse()
+                .expect("formatted probability");
+            baseline.compare_row(&key, p, results.runs)
+        });
+        match comparison {
+            Some((j, c)) => {
+                row.push(self.prob_format.format(c.baseline));
+                row.push(self.prob_format.linear().format(c.delta));
+                row.push(match c.significant {
+                        Some(true) => "yes",
+                        Some(false) => "no",
+                        None => "-",
+                    }
+                    .to_owned());
+                Some(j)
+            }
+            None => {
+                row.extend(vec!["-".to_owned(); 3]);
+                None
+            }
+        }
+    }
 
-// We could use templating but there's no reason not to do the easy thing and
-// fix types.
+    fn line(&self, row: &[String], p_compromise: Option<RR>) -> String {
+        let mut line = String::new();
+        for col in 0..self.col_widths.len() {
+            line.push_str(&format!("{1:<0$} ", self.col_widths[col], row[col]));
+        }
+        if let (Some(viz), Some(p_compromise)) = (self.viz, p_compromise) {
+            line.push_str(viz.bar(p_compromise));
+        }
+        line
+    }
+}
 
-pub type NN = u64;
-pub type RR = f64;
+// A column title as shown: probability columns are marked if written as log10.
+fn shown_title(title: &str, prob_format: ProbFormat) -> String {
+    match title {
+        "P(disruption)" | "P(compromise)" | "Analytic" | "Baseline" => prob_format.title(title),
+        _ => title.to_owned(),
+    }
+}
 
-/// Error type
-pub enum Error {
-    AddRestriction,
-    AlreadyExists,
-    NotFound,
+// The main table as printed so far. With a baseline: rows (numbered from 1) not
+// in it, and which of its rows were matched.
+struct Printed {
+    rows: OrderedRows<BufWriter<io::Stdout>>,
+    not_in_baseline: Vec<usize>,
+    matched: Vec<bool>,
 }
 
-/// Result type
-pub type Result<T> = result::Result<T, Error>;
+// Files written a part per parameter set as each completes, in order, each
+// with its path: the age share curve, the attacker's decisions and the values
+// drawn from each random number stream
+struct RowFiles<'a> {
+    curve: Option<&'a (&'a str, Mutex<OrderedRows<PartialFile>>)>,
+    attack_record: Option<&'a (&'a str, Mutex<OrderedRows<PartialFile>>)>,
+    rng_audit: Option<&'a (&'a str, Mutex<OrderedRows<PartialFile>>)>,
+}
 
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        match self {
-            &Error::AddRestriction => write!(f, "addition prevented by AddRestriction"),
-            &Error::AlreadyExists => write!(f, "already exists"),
-            &Error::NotFound => write!(f, "not found"),
+// Simulate parameter set `i` (or take its result from `cached`, `interleaved`
+// or `swept`), unless skipped for its memory or not started when interrupted,
+// save a result not cached to `result_cache` (if any), and print its row of the
+// table (marked if it has only its `pilot` repetitions) and its parts of the
+// row files.
+fn simulate_set(i: usize,
+                params: &SimParams,
+                cached: &Mutex<Option<SimResult>>,
+                interleaved: Option<&Mutex<Option<SimResult>>>,
+                pilot: bool,
+                swept: &Mutex<Option<SimResult>>,
+                result_cache: Option<&ResultCache>,
+                memory: &MemoryPlan,
+                interrupt: &Interrupt,
+                table: &Table,
+                baseline: Option<&Baseline>,
+                printed: &Mutex<Printed>,
+                files: &RowFiles)
+                -> Option<SimResult> {
+    let cached = cached.lock().expect("lock").take();
+    let was_cached = cached.is_some();
+    let results = if memory.skipped(i) {
+        None
+    } else if cached.is_some() {
+        cached
+    } else if let Some(result) = interleaved {
+        result.lock().expect("lock").take()
+    } else if let Some(result) = swept.lock().expect("lock").take() {
+        Some(result)
+    } else if interrupt.is_set() {
+        None
+    } else {
+        let _reserved = memory.reserve(i);
+        Some(params.result())
+    };
+    if let (Some(cache), Some(result)) = (result_cache, results.as_ref()) {
+        if !was_cached {
+            cache.save(params, result);
         }
     }
+    let p_compromise = results.as_ref().map(|results| results.p_compromise);
+    let mut row = table.row(params, results.as_ref(), memory.skipped(i), pilot);
+    let matched = baseline.map(|baseline| table.compare(&mut row, results.as_ref(), baseline));
+    row.push(combo_id(params));
+    let line = table.line(&row, p_compromise);
+
+    let mut printed = printed.lock().expect("lock");
+    match matched {
+        Some(Some(j)) => printed.matched[j] = true,
+        Some(None) if results.is_some() => printed.not_in_baseline.push(i + 1),
+        _ => {}
+    }
+    printed.rows.add(i, line).expect("write to stdout");
+    if let Some(&(path, ref curve)) = files.curve {
+        let rows = results.as_ref()
+            .map_or(String::new(), |results| {
+                age_share_rows(i, &combo_id(params), results, table.prob_format)
+            });
+        curve.lock()
+            .expect("lock")
+            .add(i, rows)
+            .unwrap_or_else(|e| panic!("unable to write curve file {}: {}", path, e));
+    }
+    if let Some(&(path, ref record)) = files.attack_record {
+        let rows = results.as_ref().map_or(String::new(), |results| attack_rows(i, results));
+        record.lock()
+            .expect("lock")
+            .add(i, rows)
+            .unwrap_or_else(|e| panic!("unable to write attack record {}: {}", path, e));
+    }
+    if let Some(&(path, ref audit)) = files.rng_audit {
+        let rows = results.as_ref()
+            .and_then(|results| results.rng_draws.as_ref())
+            .map_or(String::new(), |record| audit_rows(i, &combo_id(params), record));
+        audit.lock()
+            .expect("lock")
+            .add(i, rows)
+            .unwrap_or_else(|e| panic!("unable to write RNG audit {}: {}", path, e));
+    }
+    // Only the reports after the table are needed from here, so with very many
+    // sets the rows already written aren't kept
+    results.map(|mut results| {
+        results.age_share = None;
+        results.attack_record = None;
+        results.rng_draws = None;
+        results
+    })
 }
 
-pub struct ToolArgs {
-    num_nodes: NN,
-    num_malicious: NN,
-    min_group_size: NN,
-    quorum_prop: RR,
-    any_group: bool,
-    max_steps: NN,
-    repetitions: NN,
+// Log that parameter set `i` (from 0) of `sets` is done, skipped or not run
+fn log_done(i: usize, sets: usize, results: Option<&SimResult>, memory: &MemoryPlan) {
+    match results {
+        Some(_) => debug!("parameter set {} of {} done", i + 1, sets),
+        None if memory.skipped(i) => {
+            info!("skipping parameter set {}: estimated memory {} above --max-mem",
+                  i + 1,
+                  estimate::format_bytes(memory.estimate(i)))
+        }
+        None => debug!("parameter set {} not run: interrupted", i + 1),
+    }
+}
+
+// What the notes on the main table need of the parameter sets, gathered a set
+// at a time so that the sets and their results need not all be held (see
+// `table_notes`). Rows are numbered from 1.
+#[derive(Default)]
+struct Tally {
+    sets: usize,
+    // Those run to completion, rather than stopped by the time limit
+    completed: usize,
+    targetted_bound: bool,
+    // With their estimated memory
+    skipped: Vec<(usize, RR)>,
+    // With P(compromise) and the runs, of those estimated from runs
+    estimated: Vec<(usize, RR, NN)>,
+    truncated: Vec<usize>,
+    // Neither run nor skipped
+    not_run: Vec<usize>,
+    disagreeing: Vec<usize>,
 }
 
-impl ToolArgs {
-    fn check_invariant(&self) {
-        assert!(self.num_nodes >= self.num_malicious);
-        assert!(self.quorum_prop >= 0.0 && self.quorum_prop <= 1.0);
+impl Tally {
+    fn add(&mut self,
+           i: usize,
+           params: &SimParams,
+           results: Option<&SimResult>,
+           memory: &MemoryPlan) {
+        self.sets += 1;
+        self.targetted_bound |= params.targetted_bound();
+        let results = match results {
+            Some(results) => results,
+            None if memory.skipped(i) => {
+                self.skipped.push((i + 1, memory.estimate(i)));
+                return;
+            }
+            None => {
+                self.not_run.push(i + 1);
+                return;
+            }
+        };
+        if results.truncated {
+            self.truncated.push(i + 1);
+        } else {
+            self.completed += 1;
+        }
+        if let Some(runs) = results.runs {
+            self.estimated.push((i + 1, results.p_compromise, runs));
+        }
+        if results.split_check.as_ref().map_or(false, |check| check.flagged()) {
+            self.disagreeing.push(i + 1);
+        }
     }
 }
 
+// Notes on the main table, from the tally of its rows and those matched in the
+// baseline (if any)
+fn table_notes(arg_proc: &ArgProc,
+               tally: &Tally,
+               baseline: Option<&Baseline>,
+               not_in_baseline: &[usize],
+               matched: &[bool])
+               -> Vec<String> {
+    let numbers = |rows: &[usize]| rows.iter().map(|i| i.to_string()).collect::<Vec<_>>();
+    let mut notes = Vec::new();
+    let interrupted = arg_proc.interrupt().is_set();
+    if interrupted {
+        notes.push(interrupt::banner(tally.completed, tally.sets));
+    }
+    if tally.targetted_bound {
+        notes.push("simp_bound: P(disruption) and P(compromise) are upper bounds under simple \
+                    targetting"
+            .to_owned());
+    }
+    if let (Some(path), Some(baseline)) = (arg_proc.baseline_path(), baseline) {
+        if !not_in_baseline.is_empty() {
+            notes.push(format!("Rows not in baseline {}: {}",
+                               path,
+                               numbers(not_in_baseline).join(", ")));
+        }
+        let unmatched = baseline.unmatched(matched);
+        if !unmatched.is_empty() {
+            let mut note = format!("Only in baseline {}:", path);
+            for row in unmatched {
+                note.push_str(&format!("\n{}", key_text(&row.key)));
+            }
+            notes.push(note);
+        }
+    }
+    if let Some(cap) = arg_proc.max_mem() {
+        let rows: Vec<String> = tally.skipped
+            .iter()
+            .map(|&(i, bytes)| format!("{} ({})", i, estimate::format_bytes(bytes)))
+            .collect();
+        if !rows.is_empty() {
+            notes.push(format!("Rows skipped, estimated memory above --max-mem {}: {}",
+                               estimate::format_bytes(cap),
+                               rows.join(", ")));
+        }
+    }
+    if let Some(note) = output::few_observed_note(&tally.estimated, arg_proc.prob_format()) {
+        notes.push(note);
+    }
+    if !tally.truncated.is_empty() {
+        let limit = arg_proc.time_limit().map_or("--time-limit".to_owned(), |limit| {
+            format!("--time-limit {}", format_duration(limit))
+        });
+        let cause = if interrupted {
+            format!("{} or the interruption", limit)
+        } else {
+            limit
+        };
+        let summaries = if arg_proc.include_truncated() {
+            "included in"
+        } else {
+            "left out of"
+        };
+        notes.push(format!("Rows stopped by {} before all repetitions (runs marked *, {} \
+                            summaries): {}",
+                           cause,
+                           summaries,
+                           numbers(&tally.truncated).join(", ")));
+    }
+    if interrupted && !tally.not_run.is_empty() {
+        notes.push(format!("Rows not run, interrupted first: {}",
+                           numbers(&tally.not_run).join(", ")));
+    }
+    if !tally.disagreeing.is_empty() {
+        notes.push(format!("Rows whose folds of repetitions disagree (--split-check, p-value \
+                            below {}): {}",
+                           split_check::FLAG_P_VALUE,
+                           numbers(&tally.disagreeing).join(", ")));
+    }
+    notes
+}
 
-fn main() {
-    env_logger::init().unwrap();
+// Log the number of parameter sets and their estimated run time, warn of those
+// skipped for --max-mem, and ask to confirm a long run (exiting if declined)
+fn announce(arg_proc: &ArgProc, sets: usize, work: RR, skipped: usize) {
+    // Run time estimates differ between runs, so are left out of canonical output
+    let canonical = arg_proc.canonical();
+    let secs = if work > 0.0 && (!canonical || arg_proc.confirm_above().is_some()) {
+        work * estimate::calibrate()
+    } else {
+        0.0
+    };
+    if canonical {
+        info!("{} parameter combinations", sets);
+    } else {
+        info!("{} parameter combinations; estimated run time {} (single thread)",
+              sets,
+              format_duration(secs));
+    }
+    if let Some(cap) = arg_proc.max_mem() {
+        if skipped > 0 {
+            warn!("{} parameter combinations will be skipped: estimated memory above {}",
+                  skipped,
+                  estimate::format_bytes(cap));
+        }
+    }
+    if let Some(limit) = arg_proc.confirm_above() {
+        let question = format!("Estimated run time {}. Continue?", format_duration(secs));
+        if secs > limit && !arg_proc.yes() && !confirm(&question) {
+            error!("Aborted");
+            process::exit(1);
+        }
+    }
+}
 
-    let param_sets = ArgProc::read_args().make_sim_params();
+fn log_prob_cache(arg_proc: &ArgProc) {
+    let prob_cache = arg_proc.prob_cache();
+    if prob_cache.lookups() > 0 && !arg_proc.canonical() {
+        info!("probability cache: {} hits of {} lookups",
+              prob_cache.hits(),
+              prob_cache.lookups());
+    }
+}
 
-    info!("Starting to simulate {} different parameter sets",
-          param_sets.len());
+// Sets made and run at a time when streamed
+const STREAM_CHUNK: usize = 4096;
+
+// Run the sets of an expansion as they are made (see `ArgProc::streamed`), a
+// chunk at a time, printing the table as usual. Once written, only what the
+// notes and marginals need of each row is kept, so memory stays flat however
+// many combinations there are.
+fn run_streamed(arg_proc: &ArgProc,
+                expansion: &Expansion,
+                baseline: Option<&Baseline>,
+                viz: Option<Viz>) {
+    // A first pass, not keeping the sets, for their number, work and columns
+    let max_mem = arg_proc.max_mem();
+    let (mut sets, mut work, mut skipped) = (0, 0.0, 0);
+    let mut given = Given::default();
+    for params in expansion.iter() {
+        sets += 1;
+        work += estimate::work_units(&params);
+        skipped += max_mem.map_or(0, |cap| (estimate::memory_bytes(&params) > cap) as usize);
+        given.add(&params);
+    }
+    announce(arg_proc, sets, work, skipped);
+    let interrupt = arg_proc.interrupt();
+    interrupt::install();
+
+    let prob_format = arg_proc.prob_format();
+    let table = Table::new(&given,
+                           arg_proc.baseline_column(),
+                           baseline.is_some(),
+                           false,
+                           arg_proc.canonical(),
+                           prob_format,
+                           viz);
+    let printed = Mutex::new(Printed {
+        rows: OrderedRows::new(BufWriter::new(io::stdout()),
+                               &format!("{}\n{}", model::stamp(), table.header()))
+            .expect("write to stdout"),
+        not_in_baseline: Vec::new(),
+        matched: vec![false; baseline.map_or(0, |b| b.rows.len())],
+    });
+    info!("Starting to simulate {} different parameter sets", sets);
+    let result_cache = arg_proc.result_cache();
+    let no_files = RowFiles {
+        curve: None,
+        attack_record: None,
+        rng_audit: None,
+    };
+    let mut tally = Tally::default();
+    let mut summary = arg_proc.marginals()
+        .map(|log| Summary::new(&table.titles[..table.num_param_cols], log));
+    let mut expanded = expansion.iter();
+    let mut first = 0;
+    loop {
+        let chunk: Vec<SimParams> = expanded.by_ref().take(STREAM_CHUNK).collect();
+        if chunk.is_empty() {
+            break;
+        }
+        let memory = MemoryPlan::starting(first, &chunk, max_mem);
+        let order: Vec<usize> = (first..first + chunk.len()).collect();
+        let results = run_scheduled(&order, |i| {
+            let params = &chunk[i - first];
+            let cached = if memory.skipped(i) {
+                None
+            } else {
+                result_cache.as_ref().and_then(|cache| cache.load(params))
+            };
+            let results = simulate_set(i,
+                                       params,
+                                       &Mutex::new(cached),
+                                       None,
+                                       false,
+                                       &Mutex::new(None),
+                                       result_cache.as_ref(),
+                                       &memory,
+                                       interrupt,
+                                       &table,
+                                       baseline,
+                                       &printed,
+                                       &no_files);
+            log_done(i, sets, results.as_ref(), &memory);
+            results
+        });
+        for (i, (params, results)) in order.into_iter().zip(chunk.iter().zip(&results)) {
+            tally.add(i, params, results.as_ref(), &memory);
+            if let (Some(summary), Some(results)) = (summary.as_mut(), results.as_ref()) {
+                if !results.truncated || arg_proc.include_truncated() {
+                    summary.add(&table.marginal_row(params, results));
+                }
+            }
+        }
+        first += chunk.len();
+    }
+    let Printed { rows, mut not_in_baseline, matched } = printed.into_inner().expect("lock");
+    rows.into_inner().expect("write to stdout");
+    not_in_baseline.sort();
+    log_prob_cache(arg_proc);
+
+    for note in table_notes(arg_proc, &tally, baseline, &not_in_baseline, &matched) {
+        println!();
+        println!("{}", note);
+    }
+    if let (Some(log), Some(summary)) = (arg_proc.marginals(), summary) {
+        print_marginals(&summary.marginals(), log, prob_format);
+    }
+}
+
+// The estimated memory of each parameter set, whether it is skipped for being
+// above --max-mem, and the budget shared by those running at once (if capped)
+struct MemoryPlan {
+    // Number of the first set planned (from 0): the first of its chunk, when
+    // streamed (see `run_streamed`)
+    first: usize,
+    estimates: Vec<RR>,
+    skipped: Vec<bool>,
+    budget: Option<MemoryBudget>,
+}
+
+impl MemoryPlan {
+    fn new(param_sets: &[SimParams], max_mem: Option<RR>) -> Self {
+        MemoryPlan::starting(0, param_sets, max_mem)
+    }
+
+    // Plan for sets numbered from `first`
+    fn starting(first: usize, param_sets: &[SimParams], max_mem: Option<RR>) -> Self {
+        let mut plan = MemoryPlan {
+            first: first,
+            estimates: vec![],
+            skipped: vec![],
+            budget: max_mem.map(MemoryBudget::new),
+        };
+        plan.add(param_sets, max_mem);
+        plan
+    }
+
+    // Plan for the sets `added` too, numbered after those already planned (as
+    // --auto-extend adds them)
+    fn add(&mut self, added: &[SimParams], max_mem: Option<RR>) {
+        for params in added {
+            let bytes = estimate::memory_bytes(params);
+            self.estimates.push(bytes);
+            self.skipped.push(max_mem.map_or(false, |cap| bytes > cap));
+        }
+    }
+
+    fn estimate(&self, i: usize) -> RR {
+        self.estimates[i - self.first]
+    }
+
+    fn skipped(&self, i: usize) -> bool {
+        self.skipped[i - self.first]
+    }
+
+    // Reserve the estimated memory of set `i` from the budget (if any), waiting
+    // until it fits, for as long as the reservation is held
+    fn reserve(&self, i: usize) -> Option<Reservation> {
+        self.budget.as_ref().map(|budget| budget.reserve(self.estimate(i)))
+    }
+}
+
+// P(compromise) of a result as shown, marked * if it rests on too few compromises (see
+// `output::few_observed`)
+fn p_compromise_name(results: &SimResult, prob_format: ProbFormat) -> String {
+    let p = prob_format.format(results.p_compromise);
+    if output::few_observed(results.p_compromise, results.runs) {
+        format!("{}*", p)
+    } else {
+        p
+    }
+}
+
+// The number of runs of a result as shown, marked * if stopped by the time limit ("-" if
+// calculated)
+fn runs_name(results: &SimResult) -> String {
+    match (results.runs, results.truncated) {
+        (Some(runs), true) => format!("{}*", runs),
+        (Some(runs), false) => runs.to_string(),
+        (None, true) => "*".to_owned(),
+        (None, false) => "-".to_owned(),
+    }
+}
+
+// The parameter sets of a scan: its points, drawn from the master seed (at random if not given),
+// applied to each set of the options made with the ranges scanned at their lower bounds.
+fn scan_sets(arg_proc: &mut ArgProc, scan: Scan) -> Vec<SimParams> {
+    let bounds = arg_proc.scan_bounds().unwrap_or_else(|e| panic!("unexpected: scan: {}", e));
+    arg_proc.set_scan();
+    let base = arg_proc.make_sim_params();
+    let seed = base.first().map_or_else(|| Seeding::new().master(), |p| p.seeding.master());
+    let points = scan.points(bounds.dims(), &mut scan_rng(seed))
+        .unwrap_or_else(|e| panic!("unexpected: scan: {}", e));
+    let (sets, repeats, unusable) = scan.param_sets(&base, &bounds, &points);
+    info!("scan: {} points by {} (seed {}) give {} parameter sets, leaving out {} repeated and \
+           {} unusable",
+          points.len(),
+          scan.sampling,
+          seed,
+          sets.len(),
+          repeats,
+          unusable);
+    sets
+}
+
+// Run the parameter sets in rounds of `INTERLEAVE_BATCH` repetitions, each set
+// continuing from its progress in the last round, and write the results so far
+// to stderr as CSV after each round. Sets not repeating simulations complete in
+// the first round. Returns the final results, which are those of running each
+// set at once (given each its own master seed). Each round starts the sets in
+// `order`. Sets `not_run` (skipped for their memory or cached) are not run, and
+// while running each set reserves its estimated memory. Once interrupted, sets
+// started stop at their next repetition, and those not started have no result.
+fn run_interleaved(param_sets: &[SimParams],
+                   order: &[usize],
+                   not_run: &[bool],
+                   memory: &MemoryPlan,
+                   interrupt: &Interrupt,
+                   prob_format: ProbFormat)
+                   -> Vec<Mutex<Option<SimResult>>> {
+    // Progress of each set, and its results once complete
+    let states: Vec<Mutex<(Option<Progress>, Option<SimResult>)>> =
+        param_sets.iter().map(|_| Mutex::new((None, None))).collect();
+    let _ = writeln!(io::stderr(),
+                     "Round,Combination,Runs,{},{},FewObserved",
+                     prob_format.title("P(disruption)"),
+                     prob_format.title("P(compromise)"));
+    let mut round = 1;
+    let pending = |(i, state): (usize, &Mutex<(Option<Progress>, Option<SimResult>)>)| {
+        let state = state.lock().expect("lock");
+        !not_run[i] && state.1.is_none() && (state.0.is_some() || !interrupt.is_set())
+    };
+    while states.iter().enumerate().any(&pending) {
+        let lines = run_scheduled(order, |i| {
+            run_round(round,
+                      i,
+                      &param_sets[i],
+                      &states[i],
+                      not_run[i],
+                      memory,
+                      interrupt,
+                      prob_format)
+        });
+        for line in lines.into_iter().filter_map(|line| line) {
+            let _ = writeln!(io::stderr(), "{}", line);
+        }
+        round += 1;
+    }
+    states.into_iter().map(|state| Mutex::new(state.into_inner().expect("lock").1)).collect()
+}
+
+// Run the parameter sets within a global budget of `total` repetitions (see `--global-budget`),
+// in rounds planned by an `Allocator`: a pilot of every set, then refinement. Within a round
+// the sets start in `order`, each taking its grant from the shared budget as it starts and
+// giving back what it did not run. Each set continues from its progress in the last round, so
+// its results are those of running its repetitions at once. Sets `not_run` (skipped for their
+// memory or cached) are not run, and while running each set reserves its estimated memory. Once
+// interrupted, sets started stop at their next repetition and no further round starts. Returns
+// the results of each set (none if it was granted no repetition) and whether each has only its
+// pilot.
+fn run_budgeted(param_sets: &[SimParams],
+                order: &[usize],
+                not_run: &[bool],
+                memory: &MemoryPlan,
+                interrupt: &Interrupt,
+                total: NN)
+                -> (Vec<Mutex<Option<SimResult>>>, Vec<bool>) {
+    let repetitions = RepetitionBudget::new(total);
+    let mut allocator = Allocator::new(param_sets.iter()
+        .zip(not_run)
+        .map(|(params, &not_run)| if not_run { 0 } else { params.repetitions })
+        .collect());
+    // Progress of each set, and its results so far
+    let states: Vec<Mutex<(Option<Progress>, Option<SimResult>)>> =
+        param_sets.iter().map(|_| Mutex::new((None, None))).collect();
+    let mut round = 1;
+    while !interrupt.is_set() {
+        let plan = allocator.next_round(repetitions.remaining());
+        if plan.iter().all(|&planned| planned == 0) {
+            break;
+        }
+        info!("Round {} of the budget: {} repetitions of {} left",
+              round,
+              plan.iter().sum::<NN>(),
+              repetitions.remaining());
+        let run = |i: usize| {
+            let granted = repetitions.grant(plan[i]);
+            if granted == 0 {
+                return None;
+            }
+            let mut state = states[i].lock().expect("lock");
+            let (ref mut progress, ref mut latest) = *state;
+            let _reserved = memory.reserve(i);
+            let tool = param_sets[i].tool();
+            let mut current = progress.take().unwrap_or_else(|| tool.start());
+            let before = current.repetitions;
+            let result = tool.calc_until(&mut current, before + granted);
+            repetitions.refund(granted - (current.repetitions - before));
+            let outcome = (current.repetitions, result.p_compromise, result.truncated);
+            *progress = Some(current);
+            *latest = Some(result);
+            Some(outcome)
+        };
+        let outcomes = run_scheduled(order, run);
+        for (i, outcome) in outcomes.into_iter().enumerate() {
+            if let Some((runs, p_compromise, stopped)) = outcome {
+                allocator.update(i, runs, p_compromise, stopped);
+            }
+        }
+        round += 1;
+    }
+    let pilot_only = (0..param_sets.len()).map(|i| allocator.pilot_only(i)).collect();
+    (states.into_iter().map(|state| Mutex::new(state.into_inner().expect("lock").1)).collect(),
+     pilot_only)
+}
+
+// Call `f` with each index in `order`, starting them in that order however the work is split
+// between threads, and return the results by index.
+fn run_scheduled<T, F>(order: &[usize], f: F) -> Vec<T>
+    where T: Send,
+          F: Fn(usize) -> T + Sync
+{
+    // Each task runs the next index in order, rather than one of its own
+    let next = AtomicUsize::new(0);
+    let run = |_: &usize| {
+        let i = order[next.fetch_add(1, Ordering::SeqCst)];
+        (i, f(i))
+    };
     let mut results = Vec::new();
-    collect_into(param_sets.par_iter().map(|item| item.result()),
+    collect_into(order.par_iter().map(run),
                  &mut results);
+    results.sort_by_key(|&(i, _)| i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+// Continue parameter set `i` to the end of the given round, unless complete,
+// `not_run` (skipped or cached), or not started when interrupted. Returns its
+// CSV line for the round, if run.
+fn run_round(round: NN,
+             i: usize,
+             params: &SimParams,
+             state: &Mutex<(Option<Progress>, Option<SimResult>)>,
+             not_run: bool,
+             memory: &MemoryPlan,
+             interrupt: &Interrupt,
+             prob_format: ProbFormat)
+             -> Option<String> {
+    let mut state = state.lock().expect("lock");
+    let (ref mut progress, ref mut final_result) = *state;
+    if not_run || final_result.is_some() || (progress.is_none() && interrupt.is_set()) {
+        return None;
+    }
+    let _reserved = memory.reserve(i);
+    let tool = params.tool();
+    let mut current = progress.take().unwrap_or_else(|| tool.start());
+    let result = tool.calc_until(&mut current, round * INTERLEAVE_BATCH);
+    let line = format!("{},{},{},{},{},{}",
+                       round,
+                       i + 1,
+                       runs_name(&result),
+                       prob_format.format_csv(result.p_disrupt),
+                       prob_format.format_csv(result.p_compromise),
+                       output::few_observed(result.p_compromise, result.runs) as u8);
+    // Stopped by the time limit or interrupted, the set is not continued
+    if result.truncated || result.runs.map_or(true, |runs| runs >= params.repetitions) {
+        *final_result = Some(result);
+    } else {
+        *progress = Some(current);
+    }
+    Some(line)
+}
 
-    //     tool.print_message();
-    let col_widths: Vec<usize> = PARAM_TITLES.iter().map(|name| max(name.len(), 8)).collect();
-    for col in 0..col_widths.len() {
-        print!("{1:<0$}", col_widths[col], PARAM_TITLES[col]);
-        print!(" ");
+// Run the self-test, printing each check as it completes. Returns true if all
+// passed.
+fn run_selftest() -> bool {
+    let prob_format = ProbFormat::new();
+    let checks = selftest::run();
+    let width = checks.iter().map(|check| check.name.len()).max().unwrap_or(0);
+    println!("{1:<0$} {2:<13} {3:<13} {4:<13} Result",
+             width,
+             "Check",
+             "Observed",
+             "Expected",
+             "Tolerance");
+    for check in &checks {
+        println!("{1:<0$} {2:<13} {3:<13} {4:<13} {5}",
+                 width,
+                 check.name,
+                 prob_format.format(check.observed),
+                 prob_format.format(check.expected),
+                 prob_format.format(check.tolerance),
+                 if check.passed() { "pass" } else { "FAIL" });
+    }
+    let failed = checks.iter().filter(|check| !check.passed()).count();
+    println!("{} of {} checks passed", checks.len() - failed, checks.len());
+    failed == 0
+}
+
+// Run the benchmark, printing each workload to stderr as it completes and the
+// results to stdout as JSON.
+fn run_bench(tier: Option<Tier>) {
+    let measurements: Vec<_> = bench::workloads(tier)
+        .into_iter()
+        .map(|workload| {
+            let measurement = workload.run();
+            info!("{}: {:.3} s", measurement.name, measurement.secs);
+            measurement
+        })
+        .collect();
+    println!("{}", bench::to_json(&measurements));
+}
+
+// Ask a yes/no question on stderr and read the answer from stdin. If no answer
+// can be read (e.g. end of input), the answer is no.
+fn confirm(question: &str) -> bool {
+    let _ = write!(io::stderr(), "{} [y/N] ", question);
+    let mut answer = String::new();
+    match io::stdin().read_line(&mut answer) {
+        Ok(n) if n > 0 => {
+            let answer = answer.trim().to_lowercase();
+            answer == "y" || answer == "yes"
+        }
+        _ => false,
+    }
+}
+
+// Age share curve of the parameter set with index `i` and combination id `id`
+// as CSV, one line per sample (none if not reported). Combinations are numbered
+// from 1 in the order of the main table. Shares are not probabilities, so never
+// written as log10.
+fn age_share_rows(i: usize, id: &str, results: &SimResult, prob_format: ProbFormat) -> String {
+    let format = prob_format.linear();
+    results.age_share.as_ref().map_or(vec![], |curve| {
+        curve.iter()
+            .map(|&(step, share)| format!("{},{},{},{}", i + 1, id, step, format.format(share)))
+            .collect()
+    }).join("\n")
+}
+
+// Attacker decisions of the parameter set with index `i` as CSV, one line per
+// decision (none if not recorded), numbered like `age_share_rows`.
+fn attack_rows(i: usize, results: &SimResult) -> String {
+    results.attack_record.as_ref().map_or(vec![], |decisions| {
+        decisions.iter()
+            .map(|d| format!("{},{},{},{}", i + 1, d.step, d.prefix, if d.reset { 1 } else { 0 }))
+            .collect()
+    }).join("\n")
+}
+
+// Name of the targetted prefix, if any
+fn target_name(params: &SimParams) -> String {
+    params.target_prefix.map_or("any".to_owned(), |prefix| prefix.to_string())
+}
+
+// Print parameters as CSV (without a line ending).
+fn print_params_csv(params: &SimParams) {
+    print!("{},{},{},{},{},{},{},{},{},{},{}",
+           params.sim_type.name(),
+           params.quorum_type.name(),
+           params.targetting_name(),
+           target_name(params),
+           params.relocation.name(),
+           params.honest_uptime,
+           params.num_nodes,
+           params.num_malicious.from_base(params.num_nodes),
+           params.min_group_size,
+           params.quorum,
+           params.quorum_basis.name());
+}
+
+// Print malice histograms as CSV, one line per parameter set.
+fn print_malice_hist(param_sets: &[SimParams], results: &[SimResult]) {
+    print!("{}", PARAM_TITLES[..11].join(","));
+    for i in 0..MALICE_HIST_BUCKETS {
+        let width = 100 / MALICE_HIST_BUCKETS;
+        print!(",{}-{}%", i * width, (i + 1) * width);
     }
     println!();
 
     for (params, results) in param_sets.iter().zip(results) {
-        print!("{1:<0$}", col_widths[0], params.sim_type.name());
-        print!(" ");
-        print!("{1:<0$}", col_widths[1], params.age_quorum);
-        print!(" ");
-        print!("{1:<0$}", col_widths[2], params.targetting.name());
-        print!(" ");
-        print!("{1:<0$}", col_widths[3], params.num_nodes);
-        print!(" ");
-        print!("{1:<0$}",
-               col_widths[4],
-               params.num_malicious.from_base(params.num_nodes));
-        print!(" ");
-        print!("{1:<0$}", col_widths[5], params.min_group_size);
-        print!(" ");
-        print!("{1:<.*}", col_widths[6] - 2, params.quorum_prop);
-        print!(" ");
-        print!("{1:<.*}", col_widths[7] - 2, results.p_disrupt);
-        print!(" ");
-        print!("{1:<.*}", col_widths[8] - 2, results.p_compromise);
+        let hist = match results.malice_hist {
+            Some(ref hist) => hist,
+            None => continue,
+        };
+        print_params_csv(params);
+        for count in hist.counts.iter() {
+            print!(",{}", count);
+        }
+        println!();
+    }
+}
+
+// Print elder reports as CSV, one line per parameter set.
+fn print_elders(param_sets: &[SimParams], results: &[SimResult], prob_format: ProbFormat) {
+    println!("{},MeanMalElders,MaxMalElders,{}",
+             PARAM_TITLES[..11].join(","),
+             prob_format.title("P(elder quorum)"));
+
+    for (params, results) in param_sets.iter().zip(results) {
+        let elders = match results.elders {
+            Some(ref elders) => elders,
+            None => continue,
+        };
+        print_params_csv(params);
+        println!(",{},{},{}",
+                 elders.mean_malicious,
+                 elders.max_malicious,
+                 prob_format.format_csv(elders.p_quorum));
+    }
+}
+
+// Print liveness reports as CSV, one line per parameter set.
+fn print_liveness(param_sets: &[SimParams], results: &[SimResult], prob_format: ProbFormat) {
+    println!("{},{},MeanUnliveGroupSteps",
+             PARAM_TITLES[..11].join(","),
+             prob_format.title("P(liveness failure)"));
+
+    for (params, results) in param_sets.iter().zip(results) {
+        let liveness = match results.liveness {
+            Some(ref liveness) => liveness,
+            None => continue,
+        };
+        print_params_csv(params);
+        println!(",{},{}",
+                 prob_format.format_csv(liveness.p_failure),
+                 liveness.mean_group_steps);
+    }
+}
+
+// Print termination reports as CSV, one line per parameter set: the number of runs ending each
+// way, and the mean steps simulated by those which stalled ("-" if none did).
+fn print_termination(param_sets: &[SimParams], results: &[SimResult]) {
+    println!("{},Compromised,Stalled,BudgetExhausted,StepsExhausted,MeanStallSteps",
+             PARAM_TITLES[..11].join(","));
+
+    for (params, results) in param_sets.iter().zip(results) {
+        let termination = match results.termination {
+            Some(ref termination) => termination,
+            None => continue,
+        };
+        print_params_csv(params);
+        println!(",{},{},{},{},{}",
+                 termination.compromised,
+                 termination.stalled,
+                 termination.budget_exhausted,
+                 termination.steps_exhausted,
+                 termination.mean_stall_steps.map_or("-".to_owned(), |s| format!("{:.1}", s)));
+    }
+}
+
+// Print governance reports as CSV, one line per parameter set.
+fn print_governance(param_sets: &[SimParams], results: &[SimResult], prob_format: ProbFormat) {
+    println!("{},{},MeanChanges,MeanBlockableChanges",
+             PARAM_TITLES[..11].join(","),
+             prob_format.title("P(blockable change)"));
+
+    for (params, results) in param_sets.iter().zip(results) {
+        let governance = match results.governance {
+            Some(ref governance) => governance,
+            None => continue,
+        };
+        print_params_csv(params);
+        println!(",{},{},{}",
+                 prob_format.format_csv(governance.p_blockable),
+                 governance.mean_changes,
+                 governance.mean_blockable);
+    }
+}
+
+// Print the split check as CSV, one line per parameter set: the runs and P(compromise) of each
+// fold (separated by spaces), the largest difference between folds, the p-value of the test of
+// their homogeneity and whether they are flagged as disagreeing.
+fn print_split_check(param_sets: &[SimParams], results: &[SimResult], prob_format: ProbFormat) {
+    println!("{},FoldRuns,{},MaxDifference,PValue,Flag",
+             PARAM_TITLES[..11].join(","),
+             prob_format.title("P(compromise) by fold"));
+
+    let linear = prob_format.linear();
+    for (params, results) in param_sets.iter().zip(results) {
+        let check = match results.split_check {
+            Some(ref check) => check,
+            None => continue,
+        };
+        let runs: Vec<String> = check.folds.iter().map(|&(runs, _)| runs.to_string()).collect();
+        let probs: Vec<String> = check.folds
+            .iter()
+            .map(|&(runs, compromises)| prob_format.format_csv(compromises as RR / runs as RR))
+            .collect();
+        print_params_csv(params);
+        println!(",{},{},{},{},{}",
+                 runs.join(" "),
+                 probs.join(" "),
+                 linear.format_csv(check.max_difference),
+                 linear.format_csv(check.p_value),
+                 if check.flagged() { "yes" } else { "no" });
+    }
+}
+
+// Print results under both "any group" models as CSV, one line per parameter set.
+fn print_models(param_sets: &[SimParams], results: &[SimResult], prob_format: ProbFormat) {
+    let titles: Vec<String> = ["P(disruption) indep.",
+                               "P(compromise) indep.",
+                               "P(disruption) corr.",
+                               "P(compromise) corr."]
+        .iter()
+        .map(|name| prob_format.title(name))
+        .collect();
+    println!("{},{}", PARAM_TITLES[..11].join(","), titles.join(","));
+
+    for (params, results) in param_sets.iter().zip(results) {
+        let models = match results.models {
+            Some(ref models) => models,
+            None => continue,
+        };
+        print_params_csv(params);
+        println!(",{},{},{},{}",
+                 prob_format.format_csv(models.p_disrupt_independent),
+                 prob_format.format_csv(models.p_compromise_independent),
+                 prob_format.format_csv(models.p_disrupt_corrected),
+                 prob_format.format_csv(models.p_compromise_corrected));
+    }
+}
+
+// Print structure correction factors as CSV, one line per parameter set.
+fn print_correction(param_sets: &[SimParams], results: &[SimResult], prob_format: ProbFormat) {
+    println!("{},{},{},Correction,Correction low,Correction high",
+             PARAM_TITLES[..11].join(","),
+             prob_format.title("P(compromise) calc."),
+             prob_format.title("P(compromise) struct."));
+
+    for (params, results) in param_sets.iter().zip(results) {
+        let correction = match results.correction {
+            Some(ref correction) => correction,
+            None => continue,
+        };
+        print_params_csv(params);
+        print!(",{},{}",
+               prob_format.format_csv(correction.p_calc),
+               prob_format.format_csv(correction.p_structure));
+        match correction.ratio {
+            Some((ratio, low, high)) => println!(",{:.4},{:.4},{:.4}", ratio, low, high),
+            None => println!(",undefined,undefined,undefined"),
+        }
+    }
+}
+
+// Print the marginal effect of each swept parameter as CSV, one table per
+// parameter with a line per value.
+fn print_marginals(marginals: &[Marginal], log: bool, prob_format: ProbFormat) {
+    let mean = if log { "GeoMean" } else { "Mean" };
+    for marginal in marginals {
         println!();
+        println!("{},{} {},Rows",
+                 marginal.title,
+                 mean,
+                 prob_format.title("P(compromise)"));
+        for level in &marginal.levels {
+            println!("{},{},{}", level.value, prob_format.format_csv(level.mean), level.rows);
+        }
+    }
+}
+
+// Print the benefit of ageing as CSV, one line per pair of parameter sets
+// differing only in the simple and age quorum. Pairs are identified by the
+// rows of the main table, numbered from 1.
+fn print_ageing_benefit(param_sets: &[SimParams], results: &[SimResult], prob_format: ProbFormat) {
+    println!("Combination simple,Combination age,Runs,{},{},Ratio,Ratio low,Ratio high",
+             prob_format.title("P(compromise) simple"),
+             prob_format.title("P(compromise) age"));
+
+    for (i, params) in param_sets.iter().enumerate() {
+        let simple = match results[i].outcomes {
+            Some(ref outcomes) if params.quorum_type == QuorumType::Simple => outcomes,
+            _ => continue,
+        };
+        // The age quorum is paired whatever its weighting (see `--weight`)
+        let unweighted = |params: &SimParams| {
+            let mut params = params.clone();
+            params.weighting = None;
+            batch::params_row(&params)
+        };
+        let mut paired = params.clone();
+        paired.quorum_type = QuorumType::Age;
+        let row = unweighted(&paired);
+        let j = match param_sets.iter().position(|params| unweighted(params) == row) {
+            Some(j) => j,
+            None => continue,
+        };
+        let age = results[j].outcomes.as_ref().expect("outcomes of age quorum");
+        let benefit = AgeingBenefit::new(simple, age);
+        print!("{},{},{},{},{}",
+               i + 1,
+               j + 1,
+               benefit.runs,
+               prob_format.format_csv(benefit.p_simple),
+               prob_format.format_csv(benefit.p_age));
+        match (benefit.ratio, benefit.lower_bound) {
+            (Some((ratio, low, high)), _) => println!(",{:.4},{:.4},{:.4}", ratio, low, high),
+            (None, Some(bound)) => println!(",>{:.4},{:.4},unbounded", bound, bound),
+            (None, None) => println!(",undefined,undefined,undefined"),
+        }
     }
 }
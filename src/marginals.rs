@@ -0,0 +1,171 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Marginal effects of the swept parameters (see `--marginals`)
+//!
+//! A parameter is swept if its column of the main table takes more than one
+//! value. For each such parameter, P(compromise) is averaged over the rows at
+//! each of its values, i.e. over all other parameters.
+
+use super::{NN, RR};
+
+
+/// Mean P(compromise) at one value of a swept parameter
+#[derive(Debug, PartialEq)]
+pub struct Level {
+    /// The value, as in the main table
+    pub value: String,
+    pub mean: RR,
+    /// Number of rows with this value
+    pub rows: NN,
+}
+
+/// Marginal effect of one swept parameter
+#[derive(Debug, PartialEq)]
+pub struct Marginal {
+    /// Title of the parameter's column
+    pub title: String,
+    /// Each value, in order of first appearance
+    pub levels: Vec<Level>,
+}
+
+/// One row of the main table: its parameter values (in the order of the
+/// titles), P(compromise) and the number of runs it was estimated from (`None`
+/// if calculated)
+pub type Row = (Vec<String>, RR, Option<NN>);
+
+/// Marginal effect of each swept parameter, in column order.
+///
+/// If `log`, the means are geometric, so that probabilities differing by
+/// orders of magnitude are averaged on that scale. A zero estimated from `N`
+/// runs then counts as half a run (`0.5 / N`), the probability being below
+/// `1 / N` rather than zero; a calculated zero makes the mean zero.
+pub fn marginals(titles: &[&str], rows: &[Row], log: bool) -> Vec<Marginal> {
+    let mut summary = Summary::new(titles, log);
+    for row in rows {
+        summary.add(row);
+    }
+    summary.marginals()
+}
+
+/// The marginals of rows added one at a time, keeping only the sums at each
+/// value rather than the rows (as `marginals`)
+pub struct Summary {
+    titles: Vec<String>,
+    log: bool,
+    // Per column, the sums of P(compromise) (or its log) and the rows at each value
+    levels: Vec<Vec<(String, RR, NN)>>,
+}
+
+impl Summary {
+    pub fn new(titles: &[&str], log: bool) -> Self {
+        Summary {
+            titles: titles.iter().map(|&title| title.to_owned()).collect(),
+            log: log,
+            levels: titles.iter().map(|_| vec![]).collect(),
+        }
+    }
+
+    pub fn add(&mut self, row: &Row) {
+        let (ref values, p, runs) = *row;
+        let term = if !self.log {
+            p
+        } else {
+            match runs {
+                Some(runs) if p == 0.0 && runs > 0 => (0.5 / runs as RR).ln(),
+                _ => p.ln(),    // a calculated zero gives minus infinity
+            }
+        };
+        for (levels, value) in self.levels.iter_mut().zip(values) {
+            match levels.iter().position(|&(ref v, _, _)| v == value) {
+                Some(i) => {
+                    levels[i].1 += term;
+                    levels[i].2 += 1;
+                }
+                None => levels.push((value.clone(), term, 1)),
+            }
+        }
+    }
+
+    /// Marginal effect of each swept parameter, in column order
+    pub fn marginals(&self) -> Vec<Marginal> {
+        let log = self.log;
+        self.titles
+            .iter()
+            .zip(&self.levels)
+            .filter(|&(_, levels)| levels.len() >= 2)    // others not swept
+            .map(|(title, levels)| {
+                Marginal {
+                    title: title.clone(),
+                    levels: levels.iter()
+                        .map(|&(ref value, sum, n)| {
+                            let mean = sum / n as RR;
+                            Level {
+                                value: value.clone(),
+                                mean: if log { mean.exp() } else { mean },
+                                rows: n,
+                            }
+                        })
+                        .collect(),
+                }
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn test_marginals() {
+    // A 2 x 3 sweep of Nodes and MinGroup, with P(compromise) 10^-(i + j) for
+    // the i-th number of nodes and j-th group size
+    let titles = ["Type", "Nodes", "MinGroup"];
+    let mut rows = vec![];
+    for (i, nodes) in ["1000", "2000"].iter().enumerate() {
+        for (j, k) in ["8", "10", "12"].iter().enumerate() {
+            let values = vec!["dir_calc".to_owned(), nodes.to_string(), k.to_string()];
+            rows.push((values, (10.0 as RR).powi(-((i + j) as i32)), None));
+        }
+    }
+    let close = |a: RR, b: RR| (a - b).abs() <= 1e-12 * b.abs();
+
+    let m = marginals(&titles, &rows, false);
+    // Type is not swept
+    assert_eq!(m.len(), 2);
+    assert_eq!(m[0].title, "Nodes");
+    let values: Vec<&str> = m[1].levels.iter().map(|l| l.value.as_str()).collect();
+    assert_eq!(values, vec!["8", "10", "12"]);
+    assert!(m[0].levels.iter().all(|l| l.rows == 3) && m[1].levels.iter().all(|l| l.rows == 2));
+    assert!(close(m[0].levels[0].mean, 1.11 / 3.0));
+    assert!(close(m[0].levels[1].mean, 0.111 / 3.0));
+    assert!(close(m[1].levels[0].mean, 0.55));
+    assert!(close(m[1].levels[2].mean, 0.0055));
+
+    // Geometric: 10^-(mean exponent)
+    let m = marginals(&titles, &rows, true);
+    assert!(close(m[0].levels[0].mean, 0.1));
+    assert!(close(m[0].levels[1].mean, 0.01));
+    assert!(close(m[1].levels[1].mean, (10.0 as RR).powf(-1.5)));
+
+    // Zeros: half a run if estimated, else the mean is zero
+    rows[0].1 = 0.0;
+    rows[0].2 = Some(50);
+    let m = marginals(&titles, &rows, true);
+    assert!(close(m[1].levels[0].mean, (0.01 as RR * 0.1).sqrt()));
+    rows[0].2 = None;
+    let m = marginals(&titles, &rows, true);
+    assert_eq!(m[1].levels[0].mean, 0.0);
+    assert!(close(m[1].levels[1].mean, (10.0 as RR).powf(-1.5)));
+}
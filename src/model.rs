@@ -0,0 +1,93 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Version of the simulation model
+//!
+//! Results of different versions of the model (the rules simulated, e.g. of
+//! quorums or ageing) are not comparable, even with the same parameters and
+//! seed. `MODEL_VERSION` is stamped on what a run writes, and checked where
+//! the results of an earlier run are read back: baselines and checkpoints.
+
+use super::NN;
+
+
+/// Version of the model. Bump this with any change which alters the results of
+/// some parameter set (but not for changes to output alone).
+///
+/// - 1: the first stamped. It differs from the unstamped model before it, even
+///   with the default options: a node refused by a group (for its age, or a
+///   name taken) tries again under its new name the next step, not the same
+///   one, which never ended once no group could take it.
+/// - 2: ties broken by prefix and name rather than by hash map order.
+/// - 3: an evicted node's departure is a churn event, and an evicted malicious
+///   node returns to the attacker to rejoin.
+/// - 4: a node rejoining with part of its former age (`--rejoin-age-recovery`)
+///   keeps its identity's full age for the next time it leaves, rather than
+///   losing the rest.
+pub const MODEL_VERSION: NN = 4;
+
+const STAMP: &'static str = "# routing-sims model ";
+
+/// Comment line stamping the model version on tables and CSV files
+pub fn stamp() -> String {
+    format!("{}{}", STAMP, MODEL_VERSION)
+}
+
+/// The version stamped on `line`, if it is a stamp (see `stamp`)
+pub fn read_stamp(line: &str) -> Option<Result<NN, String>> {
+    let line = line.trim();
+    if !line.starts_with(STAMP) {
+        return None;
+    }
+    let version = &line[STAMP.len()..];
+    Some(version.parse().map_err(|_| format!("bad model version '{}'", version)))
+}
+
+/// Check that `what` was written by this version of the model, given the
+/// version found (`None` if it has none, i.e. it was written before versions
+/// were recorded). If not, it is an error, unless `allow` (see
+/// `--allow-model-mismatch`), in which case a warning is logged.
+pub fn check(what: &str, found: Option<NN>, allow: bool) -> Result<(), String> {
+    let msg = match found {
+        Some(version) if version == MODEL_VERSION => return Ok(()),
+        Some(version) => {
+            format!("{} is of model version {}, not {}", what, version, MODEL_VERSION)
+        }
+        None => format!("{} has no model version (this is version {})", what, MODEL_VERSION),
+    };
+    if allow {
+        warn!("{}; results may not be comparable", msg);
+        Ok(())
+    } else {
+        Err(format!("{} (use --allow-model-mismatch to use it anyway)", msg))
+    }
+}
+
+#[test]
+fn test_model_version() {
+    assert_eq!(read_stamp(&stamp()), Some(Ok(MODEL_VERSION)));
+    assert_eq!(read_stamp("# routing-sims model 7\n"), Some(Ok(7)));
+    assert!(read_stamp("# routing-sims model x").expect("stamp").is_err());
+    assert_eq!(read_stamp("Type     Quorum"), None);
+
+    assert_eq!(check("baseline", Some(MODEL_VERSION), false), Ok(()));
+    let mismatch = check("baseline", Some(MODEL_VERSION + 1), false).unwrap_err();
+    assert!(mismatch.starts_with(&format!("baseline is of model version {}", MODEL_VERSION + 1)));
+    assert!(check("baseline", None, false).is_err());
+    assert_eq!(check("baseline", Some(MODEL_VERSION + 1), true), Ok(()));
+    assert_eq!(check("baseline", None, true), Ok(()));
+}
@@ -0,0 +1,638 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Formatting of results for output
+
+use super::{NN, RR};
+
+use std::cmp::min;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+
+/// Probabilities below this are written in scientific notation.
+pub const SCI_THRESHOLD: RR = 1e-4;
+
+/// Number of digits after the decimal point of log10 probabilities
+pub const LOG10_PRECISION: usize = 2;
+
+/// How to format probabilities. All output formats use this, so that tables
+/// and CSV agree.
+#[derive(Clone, Copy, Debug)]
+pub struct ProbFormat {
+    /// Number of digits after the decimal point (of the mantissa, in
+    /// scientific notation)
+    pub precision: usize,
+    /// Always use scientific notation, not only for small probabilities
+    pub sci: bool,
+    /// Write log10 of probabilities instead (to `LOG10_PRECISION` digits),
+    /// ignoring the above
+    pub log10: bool,
+    /// Write probabilities in full, as the shortest decimal which reads back
+    /// exactly, ignoring all the above (for `--canonical`)
+    pub full: bool,
+}
+
+impl ProbFormat {
+    /// Six digits; scientific notation below `SCI_THRESHOLD`.
+    pub fn new() -> Self {
+        ProbFormat {
+            precision: 6,
+            sci: false,
+            log10: false,
+            full: false,
+        }
+    }
+
+    /// Format a probability for a table. As log10, zero (or below, from
+    /// rounding) is `-inf`.
+    pub fn format(&self, p: RR) -> String {
+        if self.full {
+            p.to_string()
+        } else if self.log10 {
+            if p <= 0.0 {
+                "-inf".to_owned()
+            } else {
+                format!("{:.*}", LOG10_PRECISION, p.log10())
+            }
+        } else if self.sci || (p != 0.0 && p.abs() < SCI_THRESHOLD) {
+            format!("{:.*e}", self.precision, p)
+        } else {
+            format!("{:.*}", self.precision, p)
+        }
+    }
+
+    /// Format a probability for CSV. As log10, zero is empty.
+    pub fn format_csv(&self, p: RR) -> String {
+        if self.log10 && !self.full && p <= 0.0 {
+            String::new()
+        } else {
+            self.format(p)
+        }
+    }
+
+    /// Title of a column of probabilities named `name`, e.g. `P(compromise)`
+    pub fn title(&self, name: &str) -> String {
+        if self.log10 && !self.full {
+            format!("log10{}", name)
+        } else {
+            name.to_owned()
+        }
+    }
+
+    /// The same format without log10, for values which are not probabilities
+    /// (e.g. differences) or must be read back as such
+    pub fn linear(&self) -> Self {
+        ProbFormat { log10: false, ..*self }
+    }
+}
+
+#[test]
+fn test_prob_format() {
+    let fmt = ProbFormat::new();
+    assert_eq!(fmt.format(0.0), "0.000000");
+    assert_eq!(fmt.format(1.0), "1.000000");
+    assert_eq!(fmt.format(3.2e-9), "3.200000e-9");
+    assert_eq!(fmt.format(SCI_THRESHOLD), "0.000100");
+    assert_eq!(fmt.format(0.99 * SCI_THRESHOLD), "9.900000e-5");
+
+    let fmt = ProbFormat {
+        precision: 2,
+        sci: true,
+        log10: false,
+        full: false,
+    };
+    assert_eq!(fmt.format(0.0), "0.00e0");
+    assert_eq!(fmt.format(1.0), "1.00e0");
+    assert_eq!(fmt.format(3.2e-9), "3.20e-9");
+    assert_eq!(fmt.format(0.25), "2.50e-1");
+}
+
+#[test]
+fn test_prob_format_log10() {
+    let mut fmt = ProbFormat::new();
+    fmt.log10 = true;
+    fmt.sci = true;
+    // Zero has no logarithm: -inf in tables, empty in CSV
+    assert_eq!(fmt.format(0.0), "-inf");
+    assert_eq!(fmt.format_csv(0.0), "");
+    assert_eq!(fmt.format(-2.2e-15), "-inf");
+    assert_eq!(fmt.format(1.0), "0.00");
+    assert_eq!(fmt.format_csv(1.0), "0.00");
+    assert_eq!(fmt.format(3.2e-7), "-6.49");
+    assert_eq!(fmt.format(8.1e-9), "-8.09");
+    assert_eq!(fmt.title("P(compromise)"), "log10P(compromise)");
+    assert_eq!(fmt.linear().format(0.25), "2.500000e-1");
+    assert_eq!(fmt.linear().title("P(compromise)"), "P(compromise)");
+}
+
+#[test]
+fn test_prob_format_full() {
+    let mut fmt = ProbFormat::new();
+    fmt.log10 = true;
+    fmt.full = true;
+    // Not rounded, and read back exactly
+    for &p in &[0.0, 1.0, 0.1 + 0.2, 3.2e-9, 1.0 / 3.0] {
+        assert_eq!(fmt.format(p).parse::<RR>(), Ok(p));
+        assert_eq!(fmt.format_csv(p), fmt.format(p));
+    }
+    assert_eq!(fmt.format(0.1 + 0.2), "0.30000000000000004");
+    assert_eq!(fmt.format(0.0), "0");
+    assert_eq!(fmt.title("P(compromise)"), "P(compromise)");
+}
+
+
+/// Fewest compromises for a P(compromise) estimated by simulation to be read
+/// at face value. The relative error of an estimate from `c` of them is about
+/// `1 / sqrt(c)`, so is large below this (and unbounded with none).
+pub const MIN_OBSERVED: NN = 5;
+
+/// Number of compromises observed for estimate `p` from `runs` runs.
+pub fn observed(p: RR, runs: NN) -> NN {
+    (p * runs as RR).round() as NN
+}
+
+/// Whether estimate `p` from `runs` runs (`None` if calculated, not
+/// estimated) rests on fewer than `MIN_OBSERVED` compromises.
+pub fn few_observed(p: RR, runs: Option<NN>) -> bool {
+    runs.map_or(false, |runs| observed(p, runs) < MIN_OBSERVED)
+}
+
+/// Smallest probability `runs` runs can detect: with none observed, the 95%
+/// upper bound of the probability is about `3 / runs` (the rule of three).
+pub fn min_detectable(runs: NN) -> RR {
+    3.0 / runs as RR
+}
+
+/// Note on the rows of the main table whose P(compromise) rests on too few
+/// compromises (see `few_observed`), given the number (from 1), estimate and
+/// runs of each row estimated from runs, in order; `None` if there are none.
+pub fn few_observed_note(rows: &[(usize, RR, NN)], prob_format: ProbFormat) -> Option<String> {
+    let mut all = vec![];
+    // The same rows, by number of runs
+    let mut by_runs: BTreeMap<NN, Vec<String>> = BTreeMap::new();
+    for &(row, p, runs) in rows {
+        if few_observed(p, Some(runs)) {
+            all.push(row.to_string());
+            by_runs.entry(runs).or_insert_with(Vec::new).push(row.to_string());
+        }
+    }
+    if all.is_empty() {
+        return None;
+    }
+    let detectable: Vec<String> = by_runs.iter()
+        .map(|(&runs, rows)| {
+            format!("{} at {} runs (rows {})",
+                    prob_format.linear().format(min_detectable(runs)),
+                    runs,
+                    rows.join(", "))
+        })
+        .collect();
+    Some(format!("Rows whose P(compromise) rests on fewer than {} compromises (marked *), so has \
+                  a large relative error: {}\nWith none observed, P(compromise) may still be up \
+                  to about 3 / runs: {}",
+                 MIN_OBSERVED,
+                 all.join(", "),
+                 detectable.join("; ")))
+}
+
+#[test]
+fn test_few_observed() {
+    // (P(compromise), runs, compromises, whether too few)
+    let cases = [(0.0, 100, 0, true),
+                 (0.04, 100, 4, true),
+                 (0.05, 100, 5, false),
+                 (0.4, 10, 4, true),
+                 (0.5, 10, 5, false),
+                 (1.0, 3, 3, true),
+                 (0.001, 10000, 10, false),
+                 (0.0004, 10000, 4, true)];
+    for &(p, runs, count, few) in &cases {
+        assert_eq!(observed(p, runs), count, "p {}, runs {}", p, runs);
+        assert_eq!(few_observed(p, Some(runs)), few, "p {}, runs {}", p, runs);
+    }
+    // Calculated probabilities are exact
+    assert!(!few_observed(0.0, None));
+    assert_eq!(min_detectable(100), 0.03);
+    assert_eq!(min_detectable(3000), 0.001);
+
+    let fmt = ProbFormat::new();
+    assert_eq!(few_observed_note(&[(1, 0.5, 100)], fmt), None);
+    let rows = [(1, 0.0, 100), (2, 0.5, 100), (4, 0.0004, 10000), (5, 0.02, 100)];
+    assert_eq!(few_observed_note(&rows, fmt).expect("note"),
+               "Rows whose P(compromise) rests on fewer than 5 compromises (marked *), so has \
+                a large relative error: 1, 4, 5\nWith none observed, P(compromise) may still be \
+                up to about 3 / runs: 0.030000 at 100 runs (rows 1, 5); 0.000300 at 10000 runs \
+                (rows 4)");
+}
+
+
+/// Glyphs of a `Viz` bar, from lowest to highest
+pub const VIZ_GLYPHS: [&'static str; 8] = ["▁", "▂", "▃", "▄", "▅", "▆", "▇", "█"];
+
+/// How probabilities are mapped onto the height of a `Viz` bar
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VizScale {
+    Log,
+    Linear,
+}
+
+/// A one-character bar showing a probability, for scanning tables by eye.
+#[derive(Clone, Copy, Debug)]
+pub struct Viz {
+    /// Probability shown by the lowest bar (lower probabilities also use this)
+    pub low: RR,
+    /// Probability shown by the highest bar (higher probabilities also use this)
+    pub high: RR,
+    pub scale: VizScale,
+}
+
+impl Viz {
+    /// Log scale from 1e-9 to 1.
+    pub fn new() -> Self {
+        Viz {
+            low: 1e-9,
+            high: 1.0,
+            scale: VizScale::Log,
+        }
+    }
+
+    /// Bar for probability `p`. Zero is shown as nothing (an empty string) and
+    /// one as the highest bar.
+    pub fn bar(&self, p: RR) -> &'static str {
+        if p <= 0.0 {
+            return "";
+        }
+        let last = VIZ_GLYPHS.len() - 1;
+        if p >= 1.0 {
+            return VIZ_GLYPHS[last];
+        }
+        let t = match self.scale {
+            VizScale::Log => (p.ln() - self.low.ln()) / (self.high.ln() - self.low.ln()),
+            VizScale::Linear => (p - self.low) / (self.high - self.low),
+        };
+        let i = (t * VIZ_GLYPHS.len() as RR).floor();
+        VIZ_GLYPHS[if i < 0.0 { 0 } else { min(i as usize, last) }]
+    }
+}
+
+#[test]
+fn test_viz_bar() {
+    let viz = Viz::new();
+    assert_eq!(viz.bar(0.0), "");
+    assert_eq!(viz.bar(1.0), "█");
+    assert_eq!(viz.bar(1e-12), "▁");
+    assert_eq!(viz.bar(1e-9), "▁");
+    assert_eq!(viz.bar(1e-6), "▃");
+    assert_eq!(viz.bar(1e-3), "▆");
+    assert_eq!(viz.bar(0.5), "█");
+
+    let viz = Viz {
+        low: 0.0,
+        high: 0.5,
+        scale: VizScale::Linear,
+    };
+    assert_eq!(viz.bar(0.01), "▁");
+    assert_eq!(viz.bar(0.25), "▅");
+    assert_eq!(viz.bar(0.49), "█");
+    assert_eq!(viz.bar(0.75), "█");
+}
+
+
+/// Rows `OrderedRows` writes between flushes, at most
+pub const FLUSH_ROWS: usize = 256;
+
+/// Milliseconds `OrderedRows` leaves rows unflushed, at most (checked as rows
+/// are added)
+pub const FLUSH_INTERVAL_MS: u64 = 200;
+
+/// Writer of a table whose rows are completed out of order (e.g. calculated in
+/// parallel). The header is written at once, and each row as soon as it and
+/// all rows before it are complete, so rows appear in order.
+///
+/// Rows are flushed every `FLUSH_ROWS` rows, or when a row is added
+/// `FLUSH_INTERVAL_MS` or more after the last flush, rather than one at a
+/// time, which dominates the run time of fast sweeps with very many
+/// combinations over a buffered writer. `flush` (or `into_inner`) writes out
+/// the rest.
+pub struct OrderedRows<W: Write> {
+    out: W,
+    // Completed rows waiting for an earlier row, by index
+    pending: BTreeMap<usize, String>,
+    next: usize,
+    // Rows written since the last flush, and when that was
+    unflushed: usize,
+    flushed_at: Instant,
+}
+
+impl<W: Write> OrderedRows<W> {
+    /// Write the header line.
+    pub fn new(mut out: W, header: &str) -> io::Result<Self> {
+        try!(writeln!(out, "{}", header));
+        try!(out.flush());
+        Ok(OrderedRows {
+            out: out,
+            pending: BTreeMap::new(),
+            next: 0,
+            unflushed: 0,
+            flushed_at: Instant::now(),
+        })
+    }
+
+    /// Add the row with the given index (counting from 0), writing it and any
+    /// rows after it which are waiting. An empty row writes nothing (so a row
+    /// may also be several lines, or none).
+    pub fn add(&mut self, index: usize, row: String) -> io::Result<()> {
+        let _ = self.pending.insert(index, row);
+        while let Some(row) = self.pending.remove(&self.next) {
+            if !row.is_empty() {
+                try!(writeln!(self.out, "{}", row));
+                self.unflushed += 1;
+            }
+            self.next += 1;
+        }
+        if self.unflushed >= FLUSH_ROWS ||
+           (self.unflushed > 0 &&
+            self.flushed_at.elapsed() >= Duration::from_millis(FLUSH_INTERVAL_MS)) {
+            try!(self.flush());
+        }
+        Ok(())
+    }
+
+    /// Flush the rows written so far.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.unflushed = 0;
+        self.flushed_at = Instant::now();
+        self.out.flush()
+    }
+
+    /// Number of rows written
+    pub fn written(&self) -> usize {
+        self.next
+    }
+
+    /// Flush and give back the writer.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        try!(self.flush());
+        Ok(self.out)
+    }
+}
+
+#[test]
+fn test_ordered_rows() {
+    use super::args::sim_params;
+
+    let mut rows = OrderedRows::new(Vec::new(), "Header").expect("write");
+    assert_eq!(rows.out, b"Header\n");
+
+    // Rows completing out of order are held back until those before them
+    for &(i, written) in &[(2, 0), (0, 1), (4, 1), (1, 3), (3, 5)] {
+        rows.add(i, format!("row {}", i)).expect("write");
+        assert_eq!(rows.written(), written);
+    }
+    let out = String::from_utf8(rows.into_inner().expect("write")).expect("utf8");
+    assert_eq!(out, "Header\nrow 0\nrow 1\nrow 2\nrow 3\nrow 4\n");
+
+    // Every expanded combination appears exactly once, completing in any order
+    let param_sets = sim_params(&["calc", "-n", "1000-3000:1000", "-k", "8-10"]);
+    assert_eq!(param_sets.len(), 9);
+    let mut rows = OrderedRows::new(Vec::new(), "Header").expect("write");
+    for (i, params) in param_sets.iter().enumerate().rev() {
+        let row = format!("{} {}", params.num_nodes, params.min_group_size);
+        rows.add(i, row).expect("write");
+    }
+    let out = String::from_utf8(rows.into_inner().expect("write")).expect("utf8");
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 1 + param_sets.len());
+    for params in &param_sets {
+        let row = format!("{} {}", params.num_nodes, params.min_group_size);
+        assert_eq!(lines.iter().filter(|line| **line == row).count(), 1);
+    }
+}
+
+#[test]
+fn test_ordered_rows_large_sweep() {
+    use super::args::sim_params;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    // Counts the lines written and the flushes
+    struct Counting {
+        lines: usize,
+        flushes: Rc<Cell<usize>>,
+    }
+    impl Write for Counting {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.lines += buf.iter().filter(|&&b| b == b'\n').count();
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            self.flushes.set(self.flushes.get() + 1);
+            Ok(())
+        }
+    }
+
+    // A synthetic calc grid of 10000 combinations, calculated and then written in blocks
+    // completing in reverse, as from parallel workers
+    let param_sets = sim_params(&["calc", "-n", "1000-100000:1000", "-k", "8-107"]);
+    assert_eq!(param_sets.len(), 10000);
+    let rows: Vec<String> = param_sets.iter()
+        .map(|params| {
+            format!("{} {} {:e}",
+                    params.num_nodes,
+                    params.min_group_size,
+                    params.result().p_compromise)
+        })
+        .collect();
+
+    let flushes = Rc::new(Cell::new(0));
+    let out = Counting {
+        lines: 0,
+        flushes: flushes.clone(),
+    };
+    let mut table = OrderedRows::new(out, "Header").expect("write");
+    let start = Instant::now();
+    for block in (0..rows.len()).collect::<Vec<_>>().chunks(100) {
+        for &i in block.iter().rev() {
+            table.add(i, rows[i].clone()).expect("write");
+        }
+    }
+    let out = table.into_inner().expect("write");
+    let written = start.elapsed();
+    assert_eq!(out.lines, 1 + rows.len());
+
+    // The header, a flush per FLUSH_ROWS rows, the rest at the end, and at most one per
+    // FLUSH_INTERVAL_MS taken
+    let millis = written.as_secs() * 1000 + written.subsec_nanos() as u64 / 1_000_000;
+    assert!(flushes.get() <= 2 + rows.len() / FLUSH_ROWS + (millis / FLUSH_INTERVAL_MS) as usize,
+            "{} flushes",
+            flushes.get());
+}
+
+
+/// Output file written during a run under a temporary name, `FILE.partial`,
+/// and renamed to `FILE` by `finish` once complete, so that a file with the
+/// final name is always complete. If left unfinished (dropped, e.g. when a
+/// panic aborts the run, or left when the run is interrupted), the partial
+/// file is kept, ending with the line `INCOMPLETE`. A run killed outright
+/// leaves the partial file unlabelled.
+pub struct PartialFile {
+    path: PathBuf,
+    partial: PathBuf,
+    // None once finished or left
+    file: Option<File>,
+}
+
+/// Last line of a partial file left unfinished (see `PartialFile`)
+pub const INCOMPLETE: &'static str = "# incomplete: the run stopped before this file was finished";
+
+impl PartialFile {
+    /// Create `path` with `.partial` appended.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let partial = partial_path(&path);
+        let file = try!(File::create(&partial));
+        Ok(PartialFile {
+            path: path,
+            partial: partial,
+            file: Some(file),
+        })
+    }
+
+    /// Name of the partial file
+    pub fn partial(&self) -> &Path {
+        &self.partial
+    }
+
+    /// Complete the file, renaming it to its final name (replacing any file
+    /// of that name atomically).
+    pub fn finish(mut self) -> io::Result<()> {
+        {
+            let file = self.file.as_mut().expect("unfinished");
+            try!(file.flush());
+            try!(file.sync_all());
+        }
+        self.file = None;
+        fs::rename(&self.partial, &self.path)
+    }
+
+    /// Leave the file incomplete, under its partial name and labelled so
+    /// (e.g. when the run is interrupted).
+    pub fn leave_partial(mut self) -> io::Result<()> {
+        let mut file = self.file.take().expect("unfinished");
+        try!(writeln!(file, "{}", INCOMPLETE));
+        file.sync_all()
+    }
+}
+
+impl Write for PartialFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.as_mut().expect("unfinished").write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.as_mut().expect("unfinished").flush()
+    }
+}
+
+impl Drop for PartialFile {
+    fn drop(&mut self) {
+        if let Some(mut file) = self.file.take() {
+            let _ = writeln!(file, "{}", INCOMPLETE);
+        }
+    }
+}
+
+/// The name under which `path` is written until complete: `.partial` appended
+pub fn partial_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(".partial");
+    PathBuf::from(name)
+}
+
+/// A new directory under the system's temporary one, for a test to write files
+/// to (and remove when done)
+#[cfg(test)]
+pub fn test_dir() -> PathBuf {
+    use rand::{thread_rng, Rng};
+    use std::env;
+
+    let dir = env::temp_dir().join(format!("routing-sims-test-{:x}", thread_rng().gen::<u64>()));
+    fs::create_dir_all(&dir).expect("create directory");
+    dir
+}
+
+#[test]
+fn test_partial_file() {
+    use std::io::Read;
+    use std::panic;
+
+    let dir = test_dir();
+    let path = dir.join("curve.csv");
+    let partial = partial_path(&path);
+    assert_eq!(partial, dir.join("curve.csv.partial"));
+
+    // A sweep writing a row per combination, of which `failing` (if any) fails
+    let sweep = |failing: Option<usize>| {
+        let path = path.clone();
+        panic::catch_unwind(move || {
+            let file = PartialFile::create(&path).expect("create");
+            let mut rows = OrderedRows::new(file, "Combination").expect("write");
+            for i in 0..5 {
+                if failing == Some(i) {
+                    panic!("combination {} failed", i + 1);
+                }
+                rows.add(i, if i == 2 { String::new() } else { (i + 1).to_string() })
+                    .expect("write");
+            }
+            rows.into_inner().expect("write").finish().expect("finish");
+        })
+    };
+    let read = |path: &Path| {
+        let mut contents = String::new();
+        let _ = File::open(path).expect("open").read_to_string(&mut contents).expect("read");
+        contents
+    };
+
+    // Completed: only the final file, with every row
+    assert!(sweep(None).is_ok());
+    assert!(!partial.exists());
+    assert_eq!(read(&path), "Combination\n1\n2\n4\n5\n");
+    fs::remove_file(&path).expect("remove");
+
+    // Aborted: no file with the final name, and the partial file labelled incomplete
+    assert!(sweep(Some(3)).is_err());
+    assert!(!path.exists());
+    assert_eq!(read(&partial), format!("Combination\n1\n2\n{}\n", INCOMPLETE));
+    fs::remove_file(&partial).expect("remove");
+
+    // Interrupted: likewise, with the rows written so far
+    let mut rows = OrderedRows::new(PartialFile::create(&path).expect("create"), "Combination")
+        .expect("write");
+    for i in &[0, 2] {
+        rows.add(*i, (i + 1).to_string()).expect("write");
+    }
+    rows.into_inner().expect("write").leave_partial().expect("leave partial");
+    assert!(!path.exists());
+    assert_eq!(read(&partial), format!("Combination\n1\n{}\n", INCOMPLETE));
+
+    let _ = fs::remove_dir_all(&dir);
+}
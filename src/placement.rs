@@ -0,0 +1,167 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Initial placement of malicious nodes
+
+use super::{NN, RR};
+use super::sim::{Group, NodeName, Prefix};
+
+use std::cmp::min;
+use std::collections::HashMap;
+use std::fmt::{self, Formatter};
+use std::str::FromStr;
+
+
+/// Where malicious nodes are when the simulation starts.
+///
+/// Written (and parsed) as `uniform` or `concentrated(F)`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InitialPlacement {
+    /// Placed at random, like honest nodes (the analytic assumption)
+    Uniform,
+    /// The proportion given of the malicious nodes starts in a single group:
+    /// the smallest (the lowest prefix of those as small), whose quorum is
+    /// the easiest to reach, which can hold as many as it has members. The
+    /// structure of the network is that of uniform placement: malicious nodes
+    /// trade places with honest members of the group, each keeping its own
+    /// data.
+    Concentrated(RR),
+}
+
+impl InitialPlacement {
+    /// Malicious nodes of `malicious` which start concentrated (rounded to the
+    /// nearest node)
+    pub fn concentrated(self, malicious: NN) -> NN {
+        match self {
+            InitialPlacement::Uniform => 0,
+            InitialPlacement::Concentrated(fraction) => (fraction * malicious as RR).round() as NN,
+        }
+    }
+
+    /// Pairs of nodes of `groups` to trade places (see `Network::swap_data`)
+    /// to concentrate the share of `malicious` nodes as placed at random: a
+    /// malicious node outside the group and an honest member. None for uniform
+    /// placement.
+    pub fn swaps(self,
+                 malicious: NN,
+                 groups: &HashMap<Prefix, Group>)
+                 -> Vec<(NodeName, NodeName)> {
+        let wanted = self.concentrated(malicious) as usize;
+        let target = match self.target(malicious, groups) {
+            Some(prefix) => prefix,
+            None => return vec![],
+        };
+        let group = &groups[&target];
+        // In name order, so the choice does not depend on the order of the hash maps
+        let mut honest: Vec<NodeName> =
+            group.iter().filter(|&(_, data)| !data.is_malicious()).map(|(name, _)| *name).collect();
+        honest.sort();
+        let mut outside: Vec<NodeName> = groups.iter()
+            .filter(|&(prefix, _)| *prefix != target)
+            .flat_map(|(_, group)| group.iter())
+            .filter(|&(_, data)| data.is_malicious())
+            .map(|(name, _)| *name)
+            .collect();
+        outside.sort();
+        let needed = min(wanted, group.len()).saturating_sub(group.len() - honest.len());
+        outside.into_iter().zip(honest).take(needed).collect()
+    }
+
+    /// The group of `groups` in which some of `malicious` nodes start
+    /// concentrated: the smallest, the lowest prefix of those as small. None
+    /// if none do.
+    pub fn target(self, malicious: NN, groups: &HashMap<Prefix, Group>) -> Option<Prefix> {
+        if self.concentrated(malicious) == 0 {
+            return None;
+        }
+        let mut prefixes: Vec<Prefix> = groups.keys().cloned().collect();
+        prefixes.sort();
+        let smallest = groups.values().map(|group| group.len()).min().unwrap_or(0);
+        prefixes.into_iter().find(|prefix| groups[prefix].len() == smallest)
+    }
+}
+
+impl fmt::Display for InitialPlacement {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            InitialPlacement::Uniform => f.pad("uniform"),
+            InitialPlacement::Concentrated(fraction) => {
+                f.pad(&format!("concentrated({})", fraction))
+            }
+        }
+    }
+}
+
+impl FromStr for InitialPlacement {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s == "uniform" {
+            return Ok(InitialPlacement::Uniform);
+        }
+        if !(s.starts_with("concentrated(") && s.ends_with(')')) {
+            return Err(format!("expected uniform or concentrated(F), found '{}'", s));
+        }
+        let fraction = s["concentrated(".len()..s.len() - 1].trim();
+        match fraction.parse::<RR>() {
+            Ok(x) if x >= 0.0 && x <= 1.0 => Ok(InitialPlacement::Concentrated(x)),
+            Ok(_) => Err(format!("{} outside range 0-1", fraction)),
+            Err(_) => Err(format!("bad number '{}'", fraction)),
+        }
+    }
+}
+
+#[test]
+fn test_initial_placement() {
+    use super::sim::NodeData;
+
+    let placement: InitialPlacement = "concentrated(0.5)".parse().expect("parse");
+    assert_eq!(placement, InitialPlacement::Concentrated(0.5));
+    assert_eq!(placement.to_string(), "concentrated(0.5)");
+    assert_eq!("uniform".parse(), Ok(InitialPlacement::Uniform));
+    assert_eq!("concentrated(1.5)".parse::<InitialPlacement>(),
+               Err("1.5 outside range 0-1".to_owned()));
+    assert!("concentrated".parse::<InitialPlacement>().is_err());
+    assert_eq!((placement.concentrated(7), InitialPlacement::Uniform.concentrated(7)), (4, 0));
+
+    // Groups 0 (names 0-5, 3 malicious) and 1 (names 10-14, 12 malicious): 1 is the smaller
+    let group = |names: ::std::ops::Range<NodeName>, malicious: &[NodeName]| -> Group {
+        names.map(|name| {
+                (name,
+                 if malicious.contains(&name) {
+                     NodeData::new_malicious()
+                 } else {
+                     NodeData::new()
+                 })
+            })
+            .collect()
+    };
+    let mut groups = HashMap::new();
+    groups.insert("0".parse().expect("prefix"), group(0..6, &[3]));
+    groups.insert("1".parse().expect("prefix"), group(10..15, &[12]));
+    assert!(InitialPlacement::Uniform.swaps(2, &groups).is_empty());
+    // Both malicious nodes in group 1: node 3 trades with its first honest member
+    assert_eq!(InitialPlacement::Concentrated(1.0).swaps(2, &groups), vec![(3, 10)]);
+    // One is already there
+    assert!(InitialPlacement::Concentrated(0.5).swaps(2, &groups).is_empty());
+
+    // No more than the group holds: 8 malicious nodes, but group 1 has 5 members
+    groups.insert("0".parse().expect("prefix"), group(0..6, &[0, 1, 2, 3, 4, 5]));
+    groups.insert("1".parse().expect("prefix"), group(10..15, &[12, 14]));
+    assert_eq!(InitialPlacement::Concentrated(1.0).swaps(8, &groups),
+               vec![(0, 10), (1, 11), (2, 13)]);
+}
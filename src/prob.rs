@@ -20,6 +20,10 @@
 use super::{NN, RR};
 
 use std::cmp::min;
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 
 /// Calculate `n choose k`, i.e. `n! / (k! (n-k)!)`.
@@ -44,6 +48,29 @@ fn test_choose() {
     assert_eq!(choose(56, 7) as NN, (8 * 11 * 9 * 53 * 13 * 17 * 25));
 }
 
+/// Calculate the probability of at least `x` successes in `trials` independent
+/// trials, each succeeding with probability `p`.
+pub fn prob_binomial_at_least(trials: NN, p: RR, x: NN) -> RR {
+    if x == 0 {
+        return 1.0;
+    } else if x > trials {
+        return 0.0;
+    }
+    (x...trials)
+        .map(|i| choose(trials, i) * p.powi(i as i32) * (1.0 - p).powi((trials - i) as i32))
+        .sum::<RR>()
+        .min(1.0)
+}
+
+#[test]
+fn test_prob_binomial_at_least() {
+    assert_eq!(prob_binomial_at_least(5, 0.3, 0), 1.0);
+    assert_eq!(prob_binomial_at_least(5, 0.3, 6), 0.0);
+    assert!((prob_binomial_at_least(3, 0.5, 2) - 0.5).abs() < 1e-12);
+    // One minus both misses
+    assert!((prob_binomial_at_least(2, 0.1, 1) - 0.19).abs() < 1e-12);
+}
+
 /// Calculate the probability of less than `q` "black" nodes, where there
 /// are `n` total nodes ("red" + "black"), `r` red, and we choose `k`.
 pub fn prob_disruption(n: NN, r: NN, k: NN, q: NN) -> RR {
@@ -51,14 +78,14 @@ pub fn prob_disruption(n: NN, r: NN, k: NN, q: NN) -> RR {
 }
 
 /// Calculate the probability of choosing at least `q` "red" nodes, where there
-/// are `n` total nodes, `r` red, and we choose `k`.
+/// are `n` total nodes, `r` red, and we choose `k`. This is zero if `q > k`
+/// (e.g. a fixed quorum size in a group below the minimum size).
 pub fn prob_compromise(n: NN, r: NN, k: NN, q: NN) -> RR {
     assert!(n >= r, "expected n >= r; found n={}, r={}", n, r);
-    assert!(k >= q, "expected k >= q; found k={}, q={}", k, q);
-    assert!(n - r >= k - q,
-            "expected n-r >= k-q; found n-r = {}, k-q = {}",
-            n - r,
-            k - q);
+    assert!(n >= k, "expected n >= k; found n={}, k={}", n, k);
+    if q > k {
+        return 0.0;
+    }
 
     // In this we consider each node from n/r distinct, and consider recruiting
     // k nodes into a set (so order doesn't matter).
@@ -66,7 +93,7 @@ pub fn prob_compromise(n: NN, r: NN, k: NN, q: NN) -> RR {
     // First, calculate the number of ways of choosing less than q red nodes
     let mut combs_compr: RR = 0 as RR;
     for x in q...k {
-        if x > r {
+        if x > r || k - x > n - r {
             continue;   // impossible: 0 combinations to add
         }
 
@@ -79,3 +106,400 @@ pub fn prob_compromise(n: NN, r: NN, k: NN, q: NN) -> RR {
     let total_combs = choose(n, k);
     combs_compr / total_combs
 }
+
+#[test]
+fn test_prob_compromise_certain() {
+    // A group of all nodes holds every red node
+    assert_eq!(prob_compromise(12, 9, 12, 6), 1.0);
+    assert_eq!(prob_compromise(12, 3, 12, 6), 0.0);
+    assert_eq!(prob_disruption(12, 8, 12, 7), 1.0);
+    // A quorum larger than the group can never be reached
+    assert_eq!(prob_compromise(5, 5, 5, 6), 0.0);
+    assert_eq!(prob_disruption(5, 0, 5, 6), 1.0);
+    // Any 5 of 6 nodes include at least 3 of the 4 red
+    assert!((prob_compromise(6, 4, 5, 3) - 1.0).abs() < 1e-12);
+}
+
+/// Calculate the probability that a group of `k` nodes, chosen from `n` nodes
+/// of which `r` are red, is `affected(x, h)`, where `x` is the number of red
+/// nodes in the group and `h` the number of its `k - x` other nodes taking
+/// part, each independently with probability `p`. With `p` one, `h` is always
+/// `k - x`.
+pub fn prob_thinned<F>(n: NN, r: NN, k: NN, p: RR, affected: F) -> RR
+    where F: Fn(NN, NN) -> bool
+{
+    assert!(n >= r, "expected n >= r; found n={}, r={}", n, r);
+    assert!(n >= k, "expected n >= k; found n={}, k={}", n, k);
+    let total_combs = choose(n, k);
+    let mut prob: RR = 0 as RR;
+    for x in 0...min(r, k) {
+        if k - x > n - r {
+            continue;   // impossible
+        }
+        let p_x = choose(r, x) * choose(n - r, k - x) / total_combs;
+        prob += p_x * prob_taking_part(k - x, p, |h| affected(x, h));
+    }
+    prob
+}
+
+/// Calculate the probability that `affected(h)`, where `h` of `m` nodes take
+/// part, each independently with probability `p`.
+pub fn prob_taking_part<F>(m: NN, p: RR, affected: F) -> RR
+    where F: Fn(NN) -> bool
+{
+    let mut prob: RR = 0 as RR;
+    for h in 0...m {
+        if affected(h) {
+            // probability exactly h of the m take part
+            prob += choose(m, h) * p.powi(h as i32) * (1.0 - p).powi((m - h) as i32);
+        }
+    }
+    prob
+}
+
+#[test]
+fn test_prob_thinned() {
+    // Taking part with certainty is prob_compromise
+    for &(n, r, k, q) in &[(100, 30, 10, 5), (50, 10, 8, 3), (12, 9, 12, 6)] {
+        let thinned = prob_thinned(n, r, k, 1.0, |x, _| x >= q);
+        assert!((thinned - prob_compromise(n, r, k, q)).abs() < 1e-12);
+    }
+    // One red node among four, each other taking part with probability 0.5: it
+    // holds half the votes (with quorum 0.5) unless two or three others do
+    let half = |x: NN, h: NN| 2 * x >= x + h;
+    assert!((prob_thinned(4, 1, 4, 0.5, half) - 0.5).abs() < 1e-12);
+    // One group of the nodes is unaffected with the complement
+    let unaffected: Vec<RR> =
+        (0...8).map(|x| 1.0 - prob_taking_part(8 - x, 0.7, |h| half(x, h))).collect();
+    let p_affected = prob_thinned(40, 12, 8, 0.7, half);
+    assert!((prob_all_groups_unaffected(40, 12, 8, 1, &unaffected) - (1.0 - p_affected)).abs() <
+            1e-12);
+    // Nobody else ever takes part
+    assert_eq!(prob_thinned(4, 1, 4, 0.0, |_, h| h == 0), 1.0);
+}
+
+/// Most terms `g r (k + 1)` for which `prob_all_groups_at_most` and
+/// `prob_all_groups_unaffected` are calculated exactly; above this they are
+/// approximated (see `prob_all_groups_approx`).
+pub const MAX_EXACT_TERMS: NN = 1 << 24;
+
+// Terms of the exact calculation of `prob_all_groups_at_most`
+fn exact_terms(r: NN, k: NN, g: NN) -> NN {
+    g.saturating_mul(r).saturating_mul(k + 1)
+}
+
+/// Calculate the probability that every one of `g` disjoint groups of `k`
+/// nodes contains at most `x_max` "red" nodes, where `n` nodes in total, `r`
+/// of them red, are assigned to the groups uniformly at random. Any `n - g k`
+/// nodes left over are in no group.
+///
+/// Unlike treating groups as independent, this accounts for red nodes being
+/// drawn without replacement across groups. Groups are filled one at a time,
+/// tracking the distribution of the number of red nodes remaining, which is
+/// exact but takes time in proportion to `g r k^2`: with more than
+/// `MAX_EXACT_TERMS` terms the result is approximated instead.
+pub fn prob_all_groups_at_most(n: NN, r: NN, k: NN, g: NN, x_max: NN) -> RR {
+    assert!(n >= r, "expected n >= r; found n={}, r={}", n, r);
+    assert!(n >= g * k, "expected n >= g*k; found n={}, g={}, k={}", n, g, k);
+    if exact_terms(r, k, g) > MAX_EXACT_TERMS {
+        let at_most: Vec<RR> = (0...k).map(|x| if x <= x_max { 1.0 } else { 0.0 }).collect();
+        return prob_all_groups_approx(n, r, k, g, &at_most);
+    }
+
+    // p_remaining[s] is the probability that s red nodes remain unassigned
+    // (and no group so far has more than x_max red nodes).
+    let mut p_remaining = vec![0 as RR; r as usize + 1];
+    p_remaining[r as usize] = 1.0;
+    for i in 0..g {
+        let m = n - i * k;  // nodes not yet assigned
+        let total_combs = choose(m, k);
+        let mut next = vec![0 as RR; r as usize + 1];
+        for s in 0...r {
+            let p = p_remaining[s as usize];
+            if p == 0.0 {
+                continue;
+            }
+            for x in 0...min(x_max, min(s, k)) {
+                if m - s < k - x {
+                    continue;   // not enough other nodes to fill the group
+                }
+                next[(s - x) as usize] += p * choose(s, x) * choose(m - s, k - x) / total_combs;
+            }
+        }
+        p_remaining = next;
+    }
+    p_remaining.iter().sum()
+}
+
+/// As `prob_all_groups_at_most`, except that a group of `x` red nodes is
+/// unaffected with probability `unaffected[x]` rather than exactly when `x`
+/// is at most some maximum (`unaffected` has `k + 1` entries).
+pub fn prob_all_groups_unaffected(n: NN, r: NN, k: NN, g: NN, unaffected: &[RR]) -> RR {
+    assert!(n >= r, "expected n >= r; found n={}, r={}", n, r);
+    assert!(n >= g * k, "expected n >= g*k; found n={}, g={}, k={}", n, g, k);
+    assert_eq!(unaffected.len() as NN, k + 1);
+    if exact_terms(r, k, g) > MAX_EXACT_TERMS {
+        return prob_all_groups_approx(n, r, k, g, unaffected);
+    }
+
+    let mut p_remaining = vec![0 as RR; r as usize + 1];
+    p_remaining[r as usize] = 1.0;
+    for i in 0..g {
+        let m = n - i * k;
+        let total_combs = choose(m, k);
+        let mut next = vec![0 as RR; r as usize + 1];
+        for s in 0...r {
+            let p = p_remaining[s as usize];
+            if p == 0.0 {
+                continue;
+            }
+            for x in 0...min(s, k) {
+                if m - s < k - x || unaffected[x as usize] == 0.0 {
+                    continue;
+                }
+                next[(s - x) as usize] += p * choose(s, x) * choose(m - s, k - x) / total_combs *
+                                          unaffected[x as usize];
+            }
+        }
+        p_remaining = next;
+    }
+    p_remaining.iter().sum()
+}
+
+/// Approximate `prob_all_groups_unaffected` for many groups, to second order.
+///
+/// Drawing without replacement is the same as giving each node colour red
+/// independently with probability `r / n`, conditioned on `r` being red in
+/// total. Groups are then independent binomials, all unaffected with
+/// probability `a^g` (`a` that of one group), and the condition is corrected
+/// for by the ratio of the densities of the total, as a normal distribution,
+/// given all unaffected and not. The error shrinks as `g` grows.
+fn prob_all_groups_approx(n: NN, r: NN, k: NN, g: NN, unaffected: &[RR]) -> RR {
+    let rho = r as RR / n as RR;
+    let binomial = |x: NN| choose(k, x) * rho.powi(x as i32) * (1.0 - rho).powi((k - x) as i32);
+    // Probability, mean and variance of the red nodes of one group which is unaffected
+    let (mut a, mut sum, mut sum_sq) = (0.0, 0.0, 0.0);
+    for x in 0...k {
+        let p = unaffected[x as usize] * binomial(x);
+        a += p;
+        sum += p * x as RR;
+        sum_sq += p * (x * x) as RR;
+    }
+    if a == 0.0 {
+        return 0.0;
+    }
+    let all = a.powf(g as RR);
+    // Variance of the total, then its mean and variance given all groups unaffected (the nodes
+    // left over unconditioned)
+    let var_total = n as RR * rho * (1.0 - rho);
+    if var_total == 0.0 {
+        return all;     // every node is red, or none
+    }
+    let left_over = (n - g * k) as RR;
+    let mean = g as RR * sum / a + left_over * rho;
+    let var = g as RR * (sum_sq / a - (sum / a) * (sum / a)) + left_over * rho * (1.0 - rho);
+    let ratio = if var <= 0.0 {
+        // The total is fixed: it is `r`, or cannot be
+        if (mean - r as RR).abs() < 0.5 {
+            (2.0 * PI * var_total).sqrt()
+        } else {
+            0.0
+        }
+    } else {
+        (var_total / var).sqrt() * (-(r as RR - mean).powi(2) / (2.0 * var)).exp()
+    };
+    (all * ratio).min(1.0)
+}
+
+#[test]
+fn test_prob_all_groups_approx() {
+    // Close to the exact value for many groups, and closer than treating them as independent
+    for &(n, r, k, g, x_max) in &[(1000, 300, 20, 50, 9), (2000, 600, 20, 100, 9),
+                                  (500, 150, 10, 50, 4)] {
+        let at_most: Vec<RR> = (0...k).map(|x| if x <= x_max { 1.0 } else { 0.0 }).collect();
+        let exact = prob_all_groups_at_most(n, r, k, g, x_max);
+        let approx = prob_all_groups_approx(n, r, k, g, &at_most);
+        let independent = (1.0 - prob_compromise(n, r, k, x_max + 1)).powi(g as i32);
+        assert!((approx - exact).abs() < 1e-3 &&
+                (approx - exact).abs() < (independent - exact).abs(),
+                "n={}, r={}, k={}, g={}: {} (independent {}), exact {}",
+                n,
+                r,
+                k,
+                g,
+                approx,
+                independent,
+                exact);
+    }
+    // Past the limit the approximation is used, and stays a probability
+    let (n, r, k) = (100_000, 30_000, 20);
+    assert!(exact_terms(r, k, n / k) > MAX_EXACT_TERMS);
+    let p = prob_all_groups_at_most(n, r, k, n / k, 9);
+    assert!(p >= 0.0 && p < 1e-6, "{}", p);
+}
+
+#[test]
+fn test_prob_all_groups_at_most() {
+    // Brute force: try every placement of r red nodes among n, where group j
+    // is nodes j*k .. (j+1)*k.
+    fn brute_force(n: NN, r: NN, k: NN, g: NN, x_max: NN) -> RR {
+        let mut n_ok = 0;
+        let mut n_all = 0;
+        for bits in 0..(1u32 << n) {
+            if bits.count_ones() as NN != r {
+                continue;
+            }
+            n_all += 1;
+            if (0..g).all(|j| {
+                let mask = ((1u32 << k) - 1) << (j * k);
+                (bits & mask).count_ones() as NN <= x_max
+            }) {
+                n_ok += 1;
+            }
+        }
+        (n_ok as RR) / (n_all as RR)
+    }
+
+    for &(n, k, g) in &[(12, 4, 3), (10, 3, 3), (16, 5, 3), (8, 2, 4)] {
+        for r in 0...n {
+            for x_max in 0...k {
+                let exact = prob_all_groups_at_most(n, r, k, g, x_max);
+                let expected = brute_force(n, r, k, g, x_max);
+                assert!((exact - expected).abs() < 1e-9,
+                        "n={}, r={}, k={}, g={}, x_max={}: {} != {}",
+                        n,
+                        r,
+                        k,
+                        g,
+                        x_max,
+                        exact,
+                        expected);
+                let at_most: Vec<RR> =
+                    (0...k).map(|x| if x <= x_max { 1.0 } else { 0.0 }).collect();
+                assert_eq!(prob_all_groups_unaffected(n, r, k, g, &at_most), exact);
+            }
+        }
+    }
+}
+
+
+/// Most values held by a `ProbCache` before it is emptied
+pub const MAX_CACHED: usize = 1 << 16;
+
+// Inputs of a cached probability function
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum CacheKey {
+    Compromise(NN, NN, NN, NN),
+    AllGroupsAtMost(NN, NN, NN, NN, NN),
+}
+
+/// Memo of the probability functions above, keyed on their exact inputs.
+///
+/// A sweep shares one cache between its parameter sets (and threads), since
+/// many differ only in parameters the probabilities do not depend on. Cached
+/// values are those the functions return, so results are identical with or
+/// without the cache. It is emptied once holding `MAX_CACHED` values, so that a
+/// long sweep of ever new inputs does not grow it without bound.
+pub struct ProbCache {
+    enabled: bool,
+    values: Mutex<HashMap<CacheKey, RR>>,
+    lookups: AtomicUsize,
+    hits: AtomicUsize,
+}
+
+impl ProbCache {
+    /// An empty cache
+    pub fn new() -> Self {
+        ProbCache {
+            enabled: true,
+            values: Mutex::new(HashMap::new()),
+            lookups: AtomicUsize::new(0),
+            hits: AtomicUsize::new(0),
+        }
+    }
+
+    /// A cache which stores nothing, calculating every value
+    pub fn disabled() -> Self {
+        ProbCache { enabled: false, ..ProbCache::new() }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Number of values looked up (none if disabled)
+    pub fn lookups(&self) -> usize {
+        self.lookups.load(Ordering::Relaxed)
+    }
+
+    /// Number of values found in the cache
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    // Value for `key`, calculating it with `calc` if not cached. The lock is
+    // not held while calculating, so another thread may calculate the same
+    // value meanwhile (with the same result).
+    fn get<F: FnOnce() -> RR>(&self, key: CacheKey, calc: F) -> RR {
+        if !self.enabled {
+            return calc();
+        }
+        let _ = self.lookups.fetch_add(1, Ordering::Relaxed);
+        if let Some(&value) = self.values.lock().expect("lock").get(&key) {
+            let _ = self.hits.fetch_add(1, Ordering::Relaxed);
+            return value;
+        }
+        let value = calc();
+        let mut values = self.values.lock().expect("lock");
+        if values.len() >= MAX_CACHED {
+            values.clear();
+        }
+        let _ = values.insert(key, value);
+        value
+    }
+
+    /// `prob_disruption`, cached
+    pub fn prob_disruption(&self, n: NN, r: NN, k: NN, q: NN) -> RR {
+        1.0 - self.prob_compromise(n, n - r, k, q)
+    }
+
+    /// `prob_compromise`, cached
+    pub fn prob_compromise(&self, n: NN, r: NN, k: NN, q: NN) -> RR {
+        self.get(CacheKey::Compromise(n, r, k, q), || prob_compromise(n, r, k, q))
+    }
+
+    /// `prob_all_groups_at_most`, cached
+    pub fn prob_all_groups_at_most(&self, n: NN, r: NN, k: NN, g: NN, x_max: NN) -> RR {
+        self.get(CacheKey::AllGroupsAtMost(n, r, k, g, x_max),
+                 || prob_all_groups_at_most(n, r, k, g, x_max))
+    }
+}
+
+#[test]
+fn test_prob_cache() {
+    // Values must be exactly equal (not merely close)
+    let cache = ProbCache::new();
+    for _ in 0..2 {
+        for &(n, r, k, q) in &[(1000, 100, 10, 5), (105, 40, 10, 5), (50, 5, 8, 3)] {
+            assert_eq!(cache.prob_compromise(n, r, k, q), prob_compromise(n, r, k, q));
+            assert_eq!(cache.prob_disruption(n, r, k, q), prob_disruption(n, r, k, q));
+        }
+        assert_eq!(cache.prob_all_groups_at_most(100, 20, 8, 12, 3),
+                   prob_all_groups_at_most(100, 20, 8, 12, 3));
+    }
+    // The second pass found everything calculated by the first
+    assert_eq!((cache.lookups(), cache.hits()), (14, 7));
+
+    // Once full, the cache starts again, with the same values
+    let cache = ProbCache::new();
+    for n in 0..MAX_CACHED as NN + 1 {
+        let _ = cache.prob_compromise(100 + n, 10, 8, 4);
+    }
+    assert_eq!(cache.values.lock().expect("lock").len(), 1);
+    assert_eq!(cache.prob_compromise(100, 10, 8, 4), prob_compromise(100, 10, 8, 4));
+
+    let cache = ProbCache::disabled();
+    assert_eq!(cache.prob_compromise(1000, 100, 10, 5),
+               prob_compromise(1000, 100, 10, 5));
+    assert_eq!((cache.lookups(), cache.hits()), (0, 0));
+}
@@ -18,11 +18,414 @@
 //! Quorum
 
 use super::{NN, RR};
-use super::sim::{Prefix, Node, NodeName, NodeData};
-use std::collections::HashMap;
+use super::sim::{Prefix, Node, NodeName, NodeData, Group};
+use super::uptime::UptimeDist;
+use super::rng::Seeding;
+use std::borrow::Cow;
+use std::cmp::{max, min};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::BufRead;
+use std::fmt::{self, Formatter};
+use std::str::FromStr;
 
+use rand::Rng;
+
+
+/// Size of a quorum: either a proportion of the group or a fixed number of
+/// nodes, regardless of group size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QuorumSize {
+    Proportion(RR),
+    Count(NN),
+}
+
+impl QuorumSize {
+    /// Get the number of nodes needed for a quorum in a group of size `k`.
+    pub fn of(self, k: NN) -> NN {
+        match self {
+            QuorumSize::Proportion(prop) => (k as RR * prop).ceil() as NN,
+            QuorumSize::Count(n) => n,
+        }
+    }
+
+    /// Get the number of nodes needed for a quorum in a group of size `k`, but
+    /// at least `floor` if given (see `Quorum::set_quorum_floor`).
+    pub fn of_floored(self, k: NN, floor: Option<NN>) -> NN {
+        max(self.of(k), floor.unwrap_or(0))
+    }
+
+    /// Get the proportion of a group of size `k` needed for a quorum.
+    ///
+    /// For `Count(n)`, comparing `x / k` against this is equivalent to
+    /// comparing `x` against `n`.
+    pub fn proportion(self, k: NN) -> RR {
+        match self {
+            QuorumSize::Proportion(prop) => prop,
+            QuorumSize::Count(n) => (n as RR) / (k as RR),
+        }
+    }
+
+    /// The size of a quorum of a group of size `k` with its proportion offset
+    /// by `offset` (see `QuorumJitter`), kept within 0 to 1. Unchanged without
+    /// an offset.
+    pub fn offset(self, k: NN, offset: RR) -> QuorumSize {
+        if offset == 0.0 {
+            return self;
+        }
+        QuorumSize::Proportion((self.proportion(k) + offset).max(0.0).min(1.0))
+    }
+}
+
+/// Group size a quorum proportion is taken of: the actual size of the group
+/// (the default) or the minimum group size, whatever the actual size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QuorumBasis {
+    Actual,
+    Minimum,
+}
+
+impl QuorumBasis {
+    pub fn name(self) -> &'static str {
+        match self {
+            QuorumBasis::Actual => "actual",
+            QuorumBasis::Minimum => "minimum",
+        }
+    }
+
+    /// Parse from `name()`
+    pub fn from_name(name: &str) -> Option<QuorumBasis> {
+        [QuorumBasis::Actual, QuorumBasis::Minimum].iter().cloned().find(|b| b.name() == name)
+    }
+
+    /// Group size to take proportions of, if not the actual size (see
+    /// `Quorum::set_basis_size`).
+    pub fn basis_size(self, min_group_size: NN) -> Option<NN> {
+        match self {
+            QuorumBasis::Actual => None,
+            QuorumBasis::Minimum => Some(min_group_size),
+        }
+    }
+}
+
+impl fmt::Display for QuorumSize {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            &QuorumSize::Proportion(prop) => fmt::Display::fmt(&prop, f),
+            &QuorumSize::Count(n) => f.pad(&format!("{}a", n)),
+        }
+    }
+}
+
+
+/// Largest number of malicious members a group of size `k` may have without
+/// being blocked, given a blocking proportion `block`: a group is blocked
+/// (disrupted) when more than this proportion of it is malicious.
+pub fn max_unblocked(block: RR, k: NN) -> NN {
+    // allow for rounding error, e.g. in 0.29 × 100
+    (block * (k as RR) + 1e-9).floor() as NN
+}
+
+
+/// Jitter of the quorum proportion from group to group (see
+/// `--quorum-jitter`), as where rounding against actual section sizes or
+/// differences between implementations make the effective threshold vary: the
+/// proportion of each group is offset by a uniform draw within ±`jitter`,
+/// fixed for the group by its prefix, as if drawn when the group was created.
+/// The draws of each repetition differ, unless the structure stream is fixed.
+#[derive(Clone, Debug)]
+pub struct QuorumJitter {
+    jitter: RR,
+    seeding: Seeding,
+    master: u64,
+    repetition: NN,
+}
+
+impl QuorumJitter {
+    /// Jitter within ±`jitter` (none if zero) of the given repetition, drawn
+    /// from the master seed `master`.
+    pub fn new(jitter: RR, seeding: &Seeding, master: u64, repetition: NN) -> Self {
+        assert!(jitter >= 0.0 && jitter <= 1.0);
+        QuorumJitter {
+            jitter: jitter,
+            seeding: seeding.clone(),
+            master: master,
+            repetition: repetition,
+        }
+    }
+
+    /// No jitter: every offset is zero
+    pub fn none() -> Self {
+        QuorumJitter::new(0.0, &Seeding::new(), 0, 0)
+    }
+
+    /// Offset of the quorum proportion of the group of `prefix`
+    pub fn offset(&self, prefix: Prefix) -> RR {
+        if self.jitter == 0.0 {
+            return 0.0;
+        }
+        let mut rng = self.seeding.jitter_rng(self.master, self.repetition, prefix);
+        rng.gen_range(-self.jitter, self.jitter)
+    }
+}
+
+/// Honest members taking part in decisions (see `--participation`), as where
+/// keys are lost or nodes briefly unreachable: each honest member of a group
+/// takes part with probability `participation`, each malicious member always.
+/// The draws are fixed for the group by its prefix and membership, as if it
+/// decided once per membership, so that checking a group again gives the same
+/// answer. The draws of each repetition differ, unless the liveness stream is
+/// fixed.
+#[derive(Clone, Debug)]
+pub struct Participation {
+    participation: RR,
+    seeding: Seeding,
+    master: u64,
+    repetition: NN,
+}
+
+impl Participation {
+    /// Participation with probability `participation` (every member if one) of
+    /// the given repetition, drawn from the master seed `master`.
+    pub fn new(participation: RR, seeding: &Seeding, master: u64, repetition: NN) -> Self {
+        assert!(participation > 0.0 && participation <= 1.0);
+        Participation {
+            participation: participation,
+            seeding: seeding.clone(),
+            master: master,
+            repetition: repetition,
+        }
+    }
+
+    /// Every member takes part
+    pub fn full() -> Self {
+        Participation::new(1.0, &Seeding::new(), 0, 0)
+    }
+
+    /// The members of `group`, the group of `prefix`, taking part in a decision
+    pub fn participating<'a>(&self, prefix: Prefix, group: &'a Group) -> Cow<'a, Group> {
+        if self.participation == 1.0 {
+            return Cow::Borrowed(group);
+        }
+        // Honest members draw in order of name, so that the draws do not depend on the order of
+        // the hash map
+        let mut names: Vec<NodeName> = group.keys().cloned().collect();
+        names.sort();
+        let membership = names.iter().fold(0, |key, name| key ^ name);
+        let mut rng =
+            self.seeding.participation_rng(self.master, self.repetition, prefix, membership);
+        let participating = names.into_iter()
+            .filter(|name| group[name].is_malicious() || rng.gen::<RR>() < self.participation)
+            .map(|name| (name, group[&name]))
+            .collect();
+        Cow::Owned(participating)
+    }
+}
+
+
+/// Information about a group member visible to a `Quorum` algorithm.
+///
+/// Note that this deliberately does not say whether the member is malicious.
+#[derive(Clone, Copy, Debug)]
+pub struct Member {
+    /// Node name
+    pub name: NodeName,
+    /// Node age
+    pub age: u32,
+    /// Node capacity (see `Weighting`)
+    pub capacity: RR,
+    /// When the node joined its group, as counted by the network (see
+    /// `NodeData::joined`)
+    pub joined: NN,
+}
+
+impl Member {
+    /// Create from a node's name and data
+    pub fn new(name: NodeName, data: &NodeData) -> Self {
+        Member {
+            name: name,
+            age: data.age(),
+            capacity: data.capacity(),
+            joined: data.joined(),
+        }
+    }
+}
+
+/// Default number of elders per group, used by `ElderQuorum`
+pub const DEFAULT_ELDERS: usize = 7;
+
+/// Select the elders of a group: the `k` oldest members, ties broken by name.
+/// If the group has at most `k` members, all are elders.
+pub fn elders(group: &[Member], k: usize) -> Vec<Member> {
+    elders_capped(group, k, None)
+}
+
+/// As `elders`, but ranking members older than `cap`, if given, as of that age
+/// (so that among them the names decide; see `AgeCap`).
+pub fn elders_capped(group: &[Member], k: usize, cap: Option<u32>) -> Vec<Member> {
+    elders_ranked(group, k, cap, ElderTiebreak::Name)
+}
+
+/// As `elders_capped`, with ties of age broken by `tiebreak` (then by name).
+pub fn elders_ranked(group: &[Member],
+                     k: usize,
+                     cap: Option<u32>,
+                     tiebreak: ElderTiebreak)
+                     -> Vec<Member> {
+    let age = |member: &Member| cap.map_or(member.age, |cap| min(member.age, cap));
+    // The decision is known by the members and their ages; XOR makes it independent of order
+    let decision = match tiebreak {
+        ElderTiebreak::RandomPerDecision => {
+            group.iter().fold(0, |key, member| key ^ mix(member.name ^ mix(member.age as NN)))
+        }
+        _ => 0,
+    };
+    let tie = |member: &Member| match tiebreak {
+        ElderTiebreak::Name => 0,
+        ElderTiebreak::JoinOrder => member.joined,
+        ElderTiebreak::RandomPerDecision => mix(member.name ^ decision),
+    };
+    let mut members = group.to_vec();
+    members.sort_by(|a, b| (age(b), tie(a), a.name).cmp(&(age(a), tie(b), b.name)));
+    members.truncate(k);
+    members
+}
+
+// Scramble the bits of `x` (the finaliser of SplitMix64)
+fn mix(x: NN) -> NN {
+    let x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    let x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+/// How elders of equal age are ranked (see `elders_ranked`). Ties are common
+/// while ages are low, and which of the members tied takes the last seats
+/// matters to an attacker.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ElderTiebreak {
+    /// Lowest name first. Joining nodes choose their names, so an attacker may
+    /// grind names to win ties (see `AttackStrategy::grinds_name`).
+    Name,
+    /// Earliest to join the group first (a relocated node joins anew)
+    JoinOrder,
+    /// At random, drawn afresh for each decision: for each membership of the
+    /// group and ages of its members, by a hash of them, so the same in every
+    /// process
+    RandomPerDecision,
+}
+
+impl ElderTiebreak {
+    pub fn name(self) -> &'static str {
+        match self {
+            ElderTiebreak::Name => "name",
+            ElderTiebreak::JoinOrder => "join-order",
+            ElderTiebreak::RandomPerDecision => "random-per-decision",
+        }
+    }
+
+    /// Parse from `name()`
+    pub fn from_name(name: &str) -> Option<ElderTiebreak> {
+        [ElderTiebreak::Name, ElderTiebreak::JoinOrder, ElderTiebreak::RandomPerDecision]
+            .iter()
+            .cloned()
+            .find(|t| t.name() == name)
+    }
+}
+
+/// Cap on the age counted by quorums: ages above `cap` weigh as `cap` in a
+/// quorum weighted by age, so that the oldest nodes cannot dominate it
+/// forever. The actual age is still used to rank elders unless `ordering`.
+///
+/// Written (and parsed) as the cap, followed by `+ordering` if `ordering`,
+/// e.g. `16+ordering`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AgeCap {
+    pub cap: u32,
+    pub ordering: bool,
+}
+
+impl AgeCap {
+    /// The cap on the age used to rank elders, if any: `cap` of `age_cap`
+    /// with `ordering`
+    pub fn ordering_cap(age_cap: Option<AgeCap>) -> Option<u32> {
+        age_cap.and_then(|age_cap| if age_cap.ordering { Some(age_cap.cap) } else { None })
+    }
+}
+
+impl fmt::Display for AgeCap {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if self.ordering {
+            f.pad(&format!("{}+ordering", self.cap))
+        } else {
+            f.pad(&self.cap.to_string())
+        }
+    }
+}
+
+impl FromStr for AgeCap {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (cap, ordering) = if s.ends_with("+ordering") {
+            (&s[..s.len() - "+ordering".len()], true)
+        } else {
+            (s, false)
+        };
+        match cap.parse::<u32>() {
+            Ok(cap) if cap >= 1 => {
+                Ok(AgeCap {
+                    cap: cap,
+                    ordering: ordering,
+                })
+            }
+            Ok(_) => Err("age cap must be at least 1".to_owned()),
+            Err(_) => Err(format!("bad age cap '{}'", s)),
+        }
+    }
+}
 
 /// Describes the "quorum" algorithm
+///
+/// This is an extension point: custom rules may be implemented outside this
+/// crate and passed to `tools::FullSimTool::new`. An implementation never sees
+/// which nodes are malicious; it is only asked whether some set of members
+/// forms a quorum of a group, or can block one. The simulator asks both of the
+/// malicious members (to check for compromise and for disruption).
+///
+/// For example, a quorum requiring more than half of the group, used in a tiny
+/// simulation:
+///
+/// ```
+/// use routing_sims::ToolArgs;
+/// use routing_sims::quorum::{Quorum, QuorumSize, Member, UntargettedAttack};
+/// use routing_sims::sim::NodeData;
+/// use routing_sims::tools::{Tool, FullSimTool};
+/// use routing_sims::NN;
+///
+/// struct MajorityQuorum;
+///
+/// impl Quorum for MajorityQuorum {
+///     fn quorum_size(&self, k: NN) -> Option<NN> {
+///         Some(k / 2 + 1)
+///     }
+///
+///     fn set_quorum_size(&mut self, _size: QuorumSize) {}
+///
+///     fn is_quorum(&self, group: &[Member], voters: &[Member]) -> bool {
+///         voters.len() * 2 > group.len()
+///     }
+/// }
+///
+/// // Blocking follows from `is_quorum`: 2 of 4 members block, as the other 2
+/// // are not a majority
+/// let group: Vec<Member> = (0..4).map(|name| Member::new(name, &NodeData::new())).collect();
+/// assert!(MajorityQuorum.is_blocking(&group, &group[..2]));
+/// assert!(!MajorityQuorum.is_blocking(&group, &group[..1]));
+///
+/// let args = ToolArgs::new(50, 5, 5, QuorumSize::Proportion(0.5), 10, 2);
+/// let tool = FullSimTool::new(args, MajorityQuorum, UntargettedAttack);
+/// let result = tool.calc_p_compromise();
+/// assert!(result.p_compromise <= result.p_disrupt);
+/// ```
 pub trait Quorum {
     /// Get number of nodes needed for a quorum, given group size k.
     ///
@@ -31,118 +434,1042 @@ pub trait Quorum {
     /// return `None`.
     fn quorum_size(&self, k: NN) -> Option<NN>;
 
-    /// Specify proportion of group agreement required, range 0-1. Use slightly
-    /// greater than half if number must be greater than 50%.
-    fn set_quorum_proportion(&mut self, prop: RR);
+    /// Specify group agreement required: either a proportion, range 0-1, or a
+    /// number of nodes. Use a proportion slightly greater than half if number
+    /// must be greater than 50%.
+    fn set_quorum_size(&mut self, size: QuorumSize);
+
+    /// Returns true if `voters` (a subset of the members of `group`) form a
+    /// quorum.
+    fn is_quorum(&self, group: &[Member], voters: &[Member]) -> bool;
+
+    /// As `quorum_size`, with the quorum proportion of the group offset by
+    /// `offset` (see `QuorumJitter`). By default, the offset is ignored.
+    fn quorum_size_offset(&self, k: NN, _offset: RR) -> Option<NN> {
+        self.quorum_size(k)
+    }
+
+    /// As `is_quorum`, with the quorum proportion of the group offset by
+    /// `offset`. By default, the offset is ignored.
+    fn is_quorum_offset(&self, group: &[Member], voters: &[Member], _offset: RR) -> bool {
+        self.is_quorum(group, voters)
+    }
+
+    /// If only the elders of a group (see `elders`) vote, returns the number
+    /// of elders per group.
+    fn elder_count(&self) -> Option<usize> {
+        None
+    }
+
+    /// Specify the group size a quorum proportion is taken of, or `None` for
+    /// the actual size of the group (see `QuorumBasis`). Implementations may
+    /// ignore this.
+    fn set_basis_size(&mut self, _size: Option<NN>) {}
+
+    /// Specify a minimum number of voters for a quorum, whatever the
+    /// proportion, or `None` for no minimum: a proportional quorum then needs
+    /// the larger of the two. Implementations may ignore this.
+    fn set_quorum_floor(&mut self, _floor: Option<NN>) {}
+
+    /// Specify a blocking proportion (see `max_unblocked`) to use for
+    /// blocking instead of the rest of the group failing to form a quorum, or
+    /// `None` for the latter (see `is_blocking`). Implementations may ignore
+    /// this.
+    fn set_block_prop(&mut self, _prop: Option<RR>) {}
+
+    /// Specify what the votes of members are weighted by (see `Weighting`).
+    /// Implementations may ignore this.
+    fn set_weighting(&mut self, _weighting: &Weighting) {}
+
+    /// Specify a cap on the age counted (see `AgeCap`), or `None` for no cap.
+    /// Implementations may ignore this.
+    fn set_age_cap(&mut self, _cap: Option<AgeCap>) {}
+
+    /// Specify how elders of equal age are ranked (see `ElderTiebreak`).
+    /// Implementations without elders may ignore this.
+    fn set_elder_tiebreak(&mut self, _tiebreak: ElderTiebreak) {}
+
+    /// Returns true if `members` (a subset of the members of `group`) can
+    /// block it, so that the group cannot reach a quorum without them. By
+    /// default, this is when the rest of the group does not form a quorum.
+    fn is_blocking(&self, group: &[Member], members: &[Member]) -> bool {
+        !self.is_quorum(group, &others(group, members))
+    }
+
+    /// As `is_blocking`, with the quorum proportion of the group offset by
+    /// `offset`. By default, the offset is ignored.
+    fn is_blocking_offset(&self, group: &[Member], members: &[Member], _offset: RR) -> bool {
+        self.is_blocking(group, members)
+    }
+}
+
+// Split a group into all and malicious members.
+fn members(group: &HashMap<NodeName, NodeData>) -> (Vec<Member>, Vec<Member>) {
+    // In order of name, so that sums of weights do not depend on the order of the hash map
+    let mut names: Vec<&NodeName> = group.keys().collect();
+    names.sort();
+    let mut all = Vec::with_capacity(group.len());
+    let mut bad = Vec::new();
+    for name in names {
+        let data = &group[name];
+        let member = Member::new(*name, data);
+        all.push(member);
+        if data.is_malicious() {
+            bad.push(member);
+        }
+    }
+    (all, bad)
+}
+
+// The members of `group` not among `members`, in the order of `group`.
+fn others(group: &[Member], members: &[Member]) -> Vec<Member> {
+    let names: HashSet<NodeName> = members.iter().map(|member| member.name).collect();
+    group.iter().filter(|member| !names.contains(&member.name)).cloned().collect()
+}
+
+// True if `voters` voters reach the quorum floor, if any.
+fn meets_floor(floor: Option<NN>, voters: usize) -> bool {
+    voters as NN >= floor.unwrap_or(0)
+}
+
+/// Returns true if the bad nodes in the passed group block it.
+pub fn quorum_disrupted<Q: Quorum + ?Sized>(quorum: &Q,
+                                            group: &HashMap<NodeName, NodeData>)
+                                            -> bool {
+    let (all, bad) = members(group);
+    quorum.is_blocking(&all, &bad)
+}
+
+/// Returns true if there is a quorum of bad nodes in the passed group.
+pub fn quorum_compromised<Q: Quorum + ?Sized>(quorum: &Q,
+                                              group: &HashMap<NodeName, NodeData>)
+                                              -> bool {
+    let (all, bad) = members(group);
+    quorum.is_quorum(&all, &bad)
+}
+
+/// As `quorum_disrupted`, with the quorum proportion of the group offset by
+/// `offset` (see `QuorumJitter`).
+pub fn quorum_disrupted_offset<Q: Quorum + ?Sized>(quorum: &Q,
+                                                   group: &HashMap<NodeName, NodeData>,
+                                                   offset: RR)
+                                                   -> bool {
+    let (all, bad) = members(group);
+    quorum.is_blocking_offset(&all, &bad, offset)
+}
+
+/// As `quorum_compromised`, with the quorum proportion of the group offset by
+/// `offset`.
+pub fn quorum_compromised_offset<Q: Quorum + ?Sized>(quorum: &Q,
+                                                     group: &HashMap<NodeName, NodeData>,
+                                                     offset: RR)
+                                                     -> bool {
+    let (all, bad) = members(group);
+    quorum.is_quorum_offset(&all, &bad, offset)
+}
+
+/// Quorum based on simply meeting some minimum proportion of the group (or
+/// a minimum number of nodes).
+pub struct SimpleQuorum {
+    size: QuorumSize,
+    basis: Option<NN>,
+    floor: Option<NN>,
+    block: Option<RR>,
+}
+
+impl SimpleQuorum {
+    /// New structure. Default to requiring a quorum of the entire group.
+    pub fn new() -> Self {
+        SimpleQuorum::from(QuorumSize::Proportion(1.0))
+    }
+
+    /// New structure, with specified quorum size requried.
+    pub fn from(size: QuorumSize) -> Self {
+        SimpleQuorum {
+            size: size,
+            basis: None,
+            floor: None,
+            block: None,
+        }
+    }
+}
+
+impl Quorum for SimpleQuorum {
+    fn quorum_size(&self, k: NN) -> Option<NN> {
+        self.quorum_size_offset(k, 0.0)
+    }
+
+    fn set_quorum_size(&mut self, size: QuorumSize) {
+        self.size = size;
+    }
+
+    fn is_quorum(&self, group: &[Member], voters: &[Member]) -> bool {
+        self.is_quorum_offset(group, voters, 0.0)
+    }
+
+    fn quorum_size_offset(&self, k: NN, offset: RR) -> Option<NN> {
+        let k = self.basis.unwrap_or(k);
+        Some(self.size.offset(k, offset).of_floored(k, self.floor))
+    }
+
+    fn is_quorum_offset(&self, group: &[Member], voters: &[Member], offset: RR) -> bool {
+        if let Some(basis) = self.basis {
+            let size = self.size.offset(basis, offset);
+            return voters.len() as NN >= size.of_floored(basis, self.floor);
+        }
+        let k = group.len() as NN;
+        let all = group.len() as RR;
+        (voters.len() as RR) / all >= self.size.offset(k, offset).proportion(k) &&
+        meets_floor(self.floor, voters.len())
+    }
+
+    fn set_basis_size(&mut self, size: Option<NN>) {
+        self.basis = size;
+    }
+
+    fn set_quorum_floor(&mut self, floor: Option<NN>) {
+        self.floor = floor;
+    }
+
+    fn set_block_prop(&mut self, prop: Option<RR>) {
+        self.block = prop;
+    }
+
+    fn is_blocking(&self, group: &[Member], members: &[Member]) -> bool {
+        self.is_blocking_offset(group, members, 0.0)
+    }
+
+    fn is_blocking_offset(&self, group: &[Member], members: &[Member], offset: RR) -> bool {
+        match self.block {
+            Some(block) => members.len() as NN > max_unblocked(block, group.len() as NN),
+            None => !self.is_quorum_offset(group, &others(group, members), offset),
+        }
+    }
+}
+
+#[test]
+fn test_block_prop() {
+    // Groups of 9 with quorum 0.67 (7 nodes) and blocking proportion 0.34 (more
+    // than 3 nodes): 4 malicious nodes disrupt and 7 compromise.
+    let mut quorum = SimpleQuorum::from(QuorumSize::Proportion(0.67));
+    quorum.set_block_prop(Some(0.34));
+    let mut group = HashMap::new();
+    for name in 0..9 {
+        group.insert(name, NodeData::new());
+    }
+    for bad in 0..10 {
+        assert_eq!(quorum_disrupted(&quorum, &group), bad >= 4, "{} malicious", bad);
+        assert_eq!(quorum_compromised(&quorum, &group), bad >= 7, "{} malicious", bad);
+        if bad < 9 {
+            group.insert(bad, NodeData::new_malicious());
+        }
+    }
+    // Without it, disruption is when fewer than 7 are honest
+    quorum.set_block_prop(None);
+    for name in 0..9 {
+        group.insert(name, if name < 3 {
+            NodeData::new_malicious()
+        } else {
+            NodeData::new()
+        });
+    }
+    assert!(quorum_disrupted(&quorum, &group));
+
+    assert_eq!(max_unblocked(0.34, 9), 3);
+    assert_eq!(max_unblocked(0.29, 100), 29);
+}
+
+#[test]
+fn test_quorum_basis() {
+    // Quorum 0.5 with minimum group size 8: a group of 12 needs 6 by its actual
+    // size, but 4 by the minimum.
+    let mut simple = SimpleQuorum::from(QuorumSize::Proportion(0.5));
+    let mut age = AgeQuorum::new();
+    age.set_quorum_size(QuorumSize::Proportion(0.5));
+    let group: Vec<Member> = (0..12).map(|name| Member::new(name, &NodeData::new())).collect();
+    for &(basis, needed) in &[(QuorumBasis::Actual, 6), (QuorumBasis::Minimum, 4)] {
+        simple.set_basis_size(basis.basis_size(8));
+        age.set_basis_size(basis.basis_size(8));
+        assert_eq!(simple.quorum_size(12), Some(needed));
+        for quorum in &[&simple as &Quorum, &age as &Quorum] {
+            assert!(quorum.is_quorum(&group, &group[..needed as usize]));
+            assert!(!quorum.is_quorum(&group, &group[..needed as usize - 1]));
+        }
+    }
+}
+
+#[test]
+fn test_quorum_jitter() {
+    use super::rng::Stream;
+
+    // Offsets are within the jitter, fixed per group and differ between groups and repetitions,
+    // unless the structure stream is fixed
+    let prefixes: Vec<Prefix> =
+        ["", "0", "1", "01", "10"].iter().map(|s| s.parse().expect("parse")).collect();
+    let seeding = Seeding {
+        seed: Some(3),
+        fixed: vec![],
+    };
+    let jitter = QuorumJitter::new(0.1, &seeding, 3, 0);
+    let offsets: Vec<RR> = prefixes.iter().map(|prefix| jitter.offset(*prefix)).collect();
+    assert!(offsets.iter().all(|offset| offset.abs() <= 0.1), "{:?}", offsets);
+    assert_eq!(prefixes.iter().map(|prefix| jitter.offset(*prefix)).collect::<Vec<_>>(),
+               offsets);
+    assert!(offsets[1] != offsets[2]);
+    assert!(QuorumJitter::new(0.1, &seeding, 3, 1).offset(prefixes[1]) != offsets[1]);
+    let fixed = Seeding {
+        seed: Some(3),
+        fixed: vec![Stream::Structure],
+    };
+    assert_eq!(QuorumJitter::new(0.1, &fixed, 3, 1).offset(prefixes[1]),
+               QuorumJitter::new(0.1, &fixed, 3, 0).offset(prefixes[1]));
+    assert_eq!(QuorumJitter::none().offset(prefixes[1]), 0.0);
+
+    // No offset leaves the size as it is; others give proportions within 0 to 1
+    for &size in &[QuorumSize::Proportion(0.6), QuorumSize::Count(5)] {
+        assert_eq!(size.offset(8, 0.0), size);
+    }
+    assert_eq!(QuorumSize::Proportion(0.5).offset(8, 0.25),
+               QuorumSize::Proportion(0.75));
+    assert_eq!(QuorumSize::Count(4).offset(8, -0.25), QuorumSize::Proportion(0.25));
+    assert_eq!(QuorumSize::Proportion(0.9).offset(8, 0.25), QuorumSize::Proportion(1.0));
+
+    // Quorum 0.5 of 10 offset by 0.2 needs 7 voters, of every quorum
+    let group: Vec<Member> = (0..10).map(|name| Member::new(name, &NodeData::new())).collect();
+    let simple = SimpleQuorum::from(QuorumSize::Proportion(0.5));
+    let mut age = AgeQuorum::new();
+    age.set_quorum_size(QuorumSize::Proportion(0.5));
+    let mut elder = ElderQuorum::with_elders(10);
+    elder.set_quorum_size(QuorumSize::Proportion(0.5));
+    assert_eq!(simple.quorum_size_offset(10, 0.2), Some(7));
+    for quorum in &[&simple as &Quorum, &age as &Quorum, &elder as &Quorum] {
+        assert!(quorum.is_quorum_offset(&group, &group[..7], 0.2));
+        assert!(!quorum.is_quorum_offset(&group, &group[..6], 0.2));
+        assert!(quorum.is_quorum_offset(&group, &group[..5], 0.0));
+        assert!(quorum.is_blocking_offset(&group, &group[..4], 0.2));
+        assert!(!quorum.is_blocking_offset(&group, &group[..4], 0.0));
+    }
+}
+
+#[test]
+fn test_participation() {
+    use super::rng::Stream;
+
+    // 1000 members, the first 100 malicious
+    let prefix: Prefix = "01".parse().expect("parse");
+    let mut group = Group::new();
+    for name in 0..1000 {
+        group.insert(name,
+                     if name < 100 {
+                         NodeData::new_malicious()
+                     } else {
+                         NodeData::new()
+                     });
+    }
+    let seeding = Seeding {
+        seed: Some(5),
+        fixed: vec![],
+    };
+
+    // Every member takes part, as is
+    match Participation::full().participating(prefix, &group) {
+        Cow::Borrowed(participating) => assert_eq!(participating.len(), 1000),
+        Cow::Owned(_) => panic!("full participation copies the group"),
+    }
+    // Otherwise every malicious member and about the proportion of the honest ones, the same
+    // each time while the membership is unchanged
+    let names = |participation: &Participation, group: &Group| {
+        let mut names: Vec<NodeName> =
+            participation.participating(prefix, group).keys().cloned().collect();
+        names.sort();
+        names
+    };
+    let participation = Participation::new(0.3, &seeding, 5, 0);
+    let taking_part = names(&participation, &group);
+    assert_eq!(taking_part[..100], (0..100).collect::<Vec<NodeName>>()[..]);
+    assert!(taking_part.len() > 100 + 220 && taking_part.len() < 100 + 320,
+            "{} taking part",
+            taking_part.len());
+    assert_eq!(names(&participation, &group), taking_part);
+    // Draws differ with membership and repetition, unless the liveness stream is fixed
+    let mut changed = group.clone();
+    let _ = changed.remove(&999);
+    let unchanged = |names: Vec<NodeName>| {
+        names.into_iter().filter(|name| *name != 999).collect::<Vec<NodeName>>()
+    };
+    assert!(unchanged(names(&participation, &changed)) != unchanged(taking_part.clone()));
+    assert!(names(&Participation::new(0.3, &seeding, 5, 1), &group) != taking_part);
+    let fixed = Seeding {
+        seed: Some(5),
+        fixed: vec![Stream::Liveness],
+    };
+    assert_eq!(names(&Participation::new(0.3, &fixed, 5, 1), &group),
+               names(&Participation::new(0.3, &fixed, 5, 0), &group));
+}
+
+#[test]
+fn test_quorum_count_matches_proportion() {
+    let by_prop = SimpleQuorum::from(QuorumSize::Proportion(0.5));
+    let by_count = SimpleQuorum::from(QuorumSize::Count(5));
+    assert_eq!(by_prop.quorum_size(10), Some(5));
+    assert_eq!(by_count.quorum_size(10), Some(5));
+
+    let mut group = HashMap::new();
+    for name in 0..10 {
+        group.insert(name, NodeData::new());
+    }
+    for name in 0..10 {
+        assert_eq!(quorum_disrupted(&by_prop, &group),
+                   quorum_disrupted(&by_count, &group));
+        assert_eq!(quorum_compromised(&by_prop, &group),
+                   quorum_compromised(&by_count, &group));
+        group.insert(name, NodeData::new_malicious());
+    }
+}
+
+/// What the votes of members are weighted by in a `WeightedQuorum`.
+///
+/// Written (and parsed) as `age`, `uniform` or `capacity(DIST)`, where `DIST`
+/// is a distribution of the form of `uptime::UptimeDist`, but with any
+/// non-negative values, e.g. `capacity(1@0.9,10@0.1)`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Weighting {
+    /// Node age
+    Age,
+    /// All members weigh the same, so only the number of voters counts
+    Uniform,
+    /// A capacity (e.g. stake or storage), drawn from the distribution for
+    /// each node when it joins (see `sim::NodeData::capacity`)
+    Capacity(UptimeDist),
+}
+
+impl Weighting {
+    /// Weight of a member's vote
+    pub fn weight(&self, member: &Member) -> RR {
+        match *self {
+            Weighting::Age => member.age as RR,
+            Weighting::Uniform => 1.0,
+            Weighting::Capacity(_) => member.capacity,
+        }
+    }
+
+    /// Draw the capacity of a joining node from `rng` under capacity
+    /// weighting. Nothing is drawn otherwise, so that other results do not
+    /// change.
+    pub fn sample_capacity<R: Rng>(&self, rng: &mut R) -> Option<RR> {
+        match *self {
+            Weighting::Capacity(ref dist) => Some(dist.sample(rng)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Weighting {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let s = match *self {
+            Weighting::Age => "age".to_owned(),
+            Weighting::Uniform => "uniform".to_owned(),
+            Weighting::Capacity(ref dist) => format!("capacity({})", dist),
+        };
+        f.pad(&s)
+    }
+}
+
+impl FromStr for Weighting {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "age" => Ok(Weighting::Age),
+            "uniform" => Ok(Weighting::Uniform),
+            s if s.starts_with("capacity(") && s.ends_with(')') => {
+                UptimeDist::parse_capacity(&s["capacity(".len()..s.len() - 1])
+                    .map(Weighting::Capacity)
+            }
+            s => Err(format!("expected age, uniform or capacity(DIST), found '{}'", s)),
+        }
+    }
+}
+
+/// Quorum which requires some proportion of the group's total weight (see
+/// `Weighting`) as well as number
+///
+/// We require the same proportion of weight as of the number of nodes
+/// (although these could be separated). Where a count of nodes is specified,
+/// the proportion of weight required is that count over the group size. If the
+/// group's total weight is zero, or all members weigh the same, only the number
+/// of nodes is considered.
+///
+/// With a basis size (see `QuorumBasis`), the number of nodes needed is taken
+/// of that size, and the proportion of weight is that number over that size.
+/// A quorum floor raises the number of nodes needed only.
+pub struct WeightedQuorum {
+    size: QuorumSize,
+    basis: Option<NN>,
+    floor: Option<NN>,
+    block: Option<RR>,
+    weighting: Weighting,
+    age_cap: Option<u32>,
+}
+
+/// Quorum which requires some proportion of group age as well as number: the
+/// age weighting of `WeightedQuorum`
+pub type AgeQuorum = WeightedQuorum;
+
+impl WeightedQuorum {
+    /// New structure, weighted by age. Default to requiring a quorum of the
+    /// entire group.
+    pub fn new() -> Self {
+        WeightedQuorum::with_weighting(Weighting::Age)
+    }
+
+    /// New structure, with the given weighting
+    pub fn with_weighting(weighting: Weighting) -> Self {
+        WeightedQuorum {
+            size: QuorumSize::Proportion(1.0),
+            basis: None,
+            floor: None,
+            block: None,
+            weighting: weighting,
+            age_cap: None,
+        }
+    }
+
+    // Weight of a member's vote: its age capped, if weighted by age
+    fn weight(&self, member: &Member) -> RR {
+        match (&self.weighting, self.age_cap) {
+            (&Weighting::Age, Some(cap)) => min(member.age, cap) as RR,
+            (weighting, _) => weighting.weight(member),
+        }
+    }
+
+    // Proportion of the total weight of `group` held by `part`, or `None` if
+    // weight does not count.
+    fn weight_share(&self, group: &[Member], part: &[Member]) -> Option<RR> {
+        if self.weighting == Weighting::Uniform {
+            return None;    // the proportion of the number
+        }
+        let total: RR = group.iter().map(|member| self.weight(member)).sum();
+        let weight: RR = part.iter().map(|member| self.weight(member)).sum();
+        if total == 0.0 { None } else { Some(weight / total) }
+    }
+}
+
+impl Quorum for WeightedQuorum {
+    fn quorum_size(&self, _: NN) -> Option<NN> {
+        None
+    }
+
+    fn set_quorum_size(&mut self, size: QuorumSize) {
+        self.size = size;
+    }
+
+    fn is_quorum(&self, group: &[Member], voters: &[Member]) -> bool {
+        self.is_quorum_offset(group, voters, 0.0)
+    }
+
+    fn is_quorum_offset(&self, group: &[Member], voters: &[Member], offset: RR) -> bool {
+        let (enough_voters, proportion) = match self.basis {
+            Some(basis) => {
+                let size = self.size.offset(basis, offset);
+                (voters.len() as NN >= size.of(basis), size.proportion(basis))
+            }
+            None => {
+                let k = group.len() as NN;
+                let proportion = self.size.offset(k, offset).proportion(k);
+                ((voters.len() as RR) / (group.len() as RR) >= proportion, proportion)
+            }
+        };
+        enough_voters && meets_floor(self.floor, voters.len()) &&
+        self.weight_share(group, voters).map_or(true, |share| share >= proportion)
+    }
+
+    fn set_basis_size(&mut self, size: Option<NN>) {
+        self.basis = size;
+    }
+
+    fn set_quorum_floor(&mut self, floor: Option<NN>) {
+        self.floor = floor;
+    }
+
+    fn set_block_prop(&mut self, prop: Option<RR>) {
+        self.block = prop;
+    }
+
+    fn set_weighting(&mut self, weighting: &Weighting) {
+        self.weighting = weighting.clone();
+    }
+
+    fn set_age_cap(&mut self, cap: Option<AgeCap>) {
+        self.age_cap = cap.map(|cap| cap.cap);
+    }
+
+    fn is_blocking(&self, group: &[Member], members: &[Member]) -> bool {
+        self.is_blocking_offset(group, members, 0.0)
+    }
+
+    // With a blocking proportion, members block by exceeding it in either
+    // number or weight.
+    fn is_blocking_offset(&self, group: &[Member], members: &[Member], offset: RR) -> bool {
+        let block = match self.block {
+            Some(block) => block,
+            None => return !self.is_quorum_offset(group, &others(group, members), offset),
+        };
+        members.len() as NN > max_unblocked(block, group.len() as NN) ||
+        self.weight_share(group, members).map_or(false, |share| share > block)
+    }
+}
+
+#[test]
+fn test_uniform_weighting_matches_simple() {
+    // Groups of 12 with varied ages and capacities, of which the first `bad`
+    // are malicious
+    let group: Vec<Member> = (0..12)
+        .map(|name| {
+            Member {
+                name: name,
+                age: (name * 7 % 5) as u32,
+                capacity: (name % 3) as RR,
+                joined: 0,
+            }
+        })
+        .collect();
+    let sizes = [QuorumSize::Proportion(0.5), QuorumSize::Proportion(0.67), QuorumSize::Count(5)];
+    for &size in &sizes {
+        for &basis in &[None, Some(8)] {
+            for &block in &[None, Some(0.3)] {
+                let mut simple = SimpleQuorum::from(size);
+                let mut uniform = WeightedQuorum::with_weighting(Weighting::Uniform);
+                uniform.set_quorum_size(size);
+                for quorum in &mut [&mut simple as &mut Quorum, &mut uniform as &mut Quorum] {
+                    quorum.set_basis_size(basis);
+                    quorum.set_block_prop(block);
+                }
+                for bad in 0..13 {
+                    let malicious = &group[..bad];
+                    assert_eq!(uniform.is_quorum(&group, malicious),
+                               simple.is_quorum(&group, malicious));
+                    assert_eq!(uniform.is_blocking(&group, malicious),
+                               simple.is_blocking(&group, malicious));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_capacity_weighting() {
+    // Two members of capacity 10 outweigh six of capacity 1, so block (but do
+    // not form a quorum, lacking the number)
+    let group: Vec<Member> = (0..8)
+        .map(|name| {
+            Member {
+                name: name,
+                age: 0,
+                capacity: if name < 2 { 10.0 } else { 1.0 },
+                joined: 0,
+            }
+        })
+        .collect();
+    let mut quorum = WeightedQuorum::new();
+    quorum.set_quorum_size(QuorumSize::Proportion(0.5));
+    quorum.set_weighting(&"capacity(1@0.75,10@0.25)".parse().expect("parse"));
+    let (heavy, light) = group.split_at(2);
+    assert!(!quorum.is_quorum(&group, heavy));
+    assert!(!quorum.is_quorum(&group, light));
+    assert!(quorum.is_blocking(&group, heavy));
+    assert!(quorum.is_quorum(&group, &group[..4]));
+    // By age, all weigh nothing, so only the number counts
+    quorum.set_weighting(&Weighting::Age);
+    assert!(quorum.is_quorum(&group, light));
+
+    for s in &["age", "uniform", "capacity(uniform(1,5))", "capacity(1@0.9,10@0.1)"] {
+        let weighting: Weighting = s.parse().expect("parse");
+        assert_eq!(weighting.to_string().parse(), Ok(weighting));
+    }
+    assert!("capacity(-1)".parse::<Weighting>().is_err());
+    assert!("stake".parse::<Weighting>().is_err());
+}
+
+
+/// Quorum of elders: only the oldest members of the group vote (see `elders`),
+/// and the proportion (or number) required is of the elders, as is any quorum
+/// floor. An age cap only counts if it also applies to ordering.
+pub struct ElderQuorum {
+    size: QuorumSize,
+    elders: usize,
+    floor: Option<NN>,
+    block: Option<RR>,
+    ordering_cap: Option<u32>,
+    tiebreak: ElderTiebreak,
+}
+
+impl ElderQuorum {
+    /// New structure, with `DEFAULT_ELDERS` elders. Default to requiring a
+    /// quorum of all elders.
+    pub fn new() -> Self {
+        ElderQuorum::with_elders(DEFAULT_ELDERS)
+    }
+
+    /// New structure, with the given number of elders per group.
+    pub fn with_elders(elders: usize) -> Self {
+        ElderQuorum {
+            size: QuorumSize::Proportion(1.0),
+            elders: elders,
+            floor: None,
+            block: None,
+            ordering_cap: None,
+            tiebreak: ElderTiebreak::Name,
+        }
+    }
+}
+
+impl Quorum for ElderQuorum {
+    fn quorum_size(&self, _: NN) -> Option<NN> {
+        None
+    }
+
+    fn set_quorum_size(&mut self, size: QuorumSize) {
+        self.size = size;
+    }
+
+    fn is_quorum(&self, group: &[Member], voters: &[Member]) -> bool {
+        self.is_quorum_offset(group, voters, 0.0)
+    }
+
+    fn is_quorum_offset(&self, group: &[Member], voters: &[Member], offset: RR) -> bool {
+        let elders = elders_ranked(group, self.elders, self.ordering_cap, self.tiebreak);
+        let n_voting = voters.iter()
+            .filter(|voter| elders.iter().any(|elder| elder.name == voter.name))
+            .count();
+        let k = elders.len() as NN;
+        (n_voting as RR) / (elders.len() as RR) >= self.size.offset(k, offset).proportion(k) &&
+        meets_floor(self.floor, n_voting)
+    }
+
+    fn elder_count(&self) -> Option<usize> {
+        Some(self.elders)
+    }
+
+    fn set_quorum_floor(&mut self, floor: Option<NN>) {
+        self.floor = floor;
+    }
 
-    /// Returns true if there is not a quorum of good nodes in the passed group.
-    fn quorum_disrupted(&self, group: &HashMap<NodeName, NodeData>) -> bool;
+    fn set_block_prop(&mut self, prop: Option<RR>) {
+        self.block = prop;
+    }
 
-    /// Returns true if there is a quorum of bad nodes in the passed group.
-    fn quorum_compromised(&self, group: &HashMap<NodeName, NodeData>) -> bool;
-}
+    fn set_age_cap(&mut self, cap: Option<AgeCap>) {
+        self.ordering_cap = AgeCap::ordering_cap(cap);
+    }
 
-/// Quorum based on simply meeting some minimum proportion of the group.
-pub struct SimpleQuorum {
-    proportion: RR,
-}
+    fn set_elder_tiebreak(&mut self, tiebreak: ElderTiebreak) {
+        self.tiebreak = tiebreak;
+    }
 
-impl SimpleQuorum {
-    /// New structure. Default to requiring a quorum of the entire group.
-    pub fn new() -> Self {
-        SimpleQuorum { proportion: 1.0 }
+    fn is_blocking(&self, group: &[Member], members: &[Member]) -> bool {
+        self.is_blocking_offset(group, members, 0.0)
     }
 
-    /// New structure, with specified proportion requried.
-    pub fn from(prop: RR) -> Self {
-        SimpleQuorum { proportion: prop }
+    fn is_blocking_offset(&self, group: &[Member], members: &[Member], offset: RR) -> bool {
+        let block = match self.block {
+            Some(block) => block,
+            None => return !self.is_quorum_offset(group, &others(group, members), offset),
+        };
+        let elders = elders_ranked(group, self.elders, self.ordering_cap, self.tiebreak);
+        let n_blocking = members.iter()
+            .filter(|member| elders.iter().any(|elder| elder.name == member.name))
+            .count();
+        n_blocking as NN > max_unblocked(block, elders.len() as NN)
     }
 }
 
-impl Quorum for SimpleQuorum {
-    fn quorum_size(&self, k: NN) -> Option<NN> {
-        Some((k as RR * self.proportion).ceil() as NN)
-    }
 
-    fn set_quorum_proportion(&mut self, prop: RR) {
-        self.proportion = prop;
+#[test]
+fn test_quorum_floor() {
+    // Quorum 0.66 with floor 5: the floor binds in a group of 6 (0.66 of which
+    // is 4 nodes) but not in one of 12 (8 nodes)
+    let members = |k: NN| -> Vec<Member> {
+        (0..k).map(|name| Member::new(name, &NodeData::new())).collect()
+    };
+    for &(k, needed) in &[(6, 5), (12, 8)] {
+        let group = members(k);
+        let mut simple = SimpleQuorum::from(QuorumSize::Proportion(0.66));
+        let mut age = AgeQuorum::new();
+        age.set_quorum_size(QuorumSize::Proportion(0.66));
+        for quorum in &mut [&mut simple as &mut Quorum, &mut age as &mut Quorum] {
+            quorum.set_quorum_floor(Some(5));
+        }
+        assert_eq!(simple.quorum_size(k), Some(needed));
+        let needed = needed as usize;
+        for quorum in &[&simple as &Quorum, &age as &Quorum] {
+            assert!(quorum.is_quorum(&group, &group[..needed]), "k = {}", k);
+            assert!(!quorum.is_quorum(&group, &group[..needed - 1]), "k = {}", k);
+            assert!(!quorum.is_blocking(&group, &group[..k as usize - needed]), "k = {}", k);
+            assert!(quorum.is_blocking(&group, &group[..k as usize - needed + 1]), "k = {}", k);
+        }
     }
 
-    fn quorum_disrupted(&self, group: &HashMap<NodeName, NodeData>) -> bool {
-        let good = group.iter().filter(|node| !node.1.is_malicious()).count() as RR;
-        let all = group.len() as RR;
-        good / all < self.proportion
-    }
+    // A count or a proportion of the minimum group size is raised the same way
+    let mut count = SimpleQuorum::from(QuorumSize::Count(4));
+    count.set_quorum_floor(Some(5));
+    assert_eq!(count.quorum_size(12), Some(5));
+    let mut basis = SimpleQuorum::from(QuorumSize::Proportion(0.5));
+    basis.set_basis_size(Some(8));
+    basis.set_quorum_floor(Some(5));
+    let group = members(12);
+    assert!(basis.is_quorum(&group, &group[..5]));
+    assert!(!basis.is_quorum(&group, &group[..4]));
 
-    fn quorum_compromised(&self, group: &HashMap<NodeName, NodeData>) -> bool {
-        let bad = group.iter().filter(|node| node.1.is_malicious()).count() as RR;
-        let all = group.len() as RR;
-        bad / all >= self.proportion
+    // Of 7 elders, 0.66 is 5: a floor of 5 does not bind, one of 6 does
+    let group = members(10);
+    let elders = elders(&group, DEFAULT_ELDERS);
+    let mut elder = ElderQuorum::new();
+    elder.set_quorum_size(QuorumSize::Proportion(0.66));
+    for &(floor, needed) in &[(None, 5), (Some(5), 5), (Some(6), 6)] {
+        elder.set_quorum_floor(floor);
+        assert!(elder.is_quorum(&group, &elders[..needed]), "floor {:?}", floor);
+        assert!(!elder.is_quorum(&group, &elders[..needed - 1]), "floor {:?}", floor);
     }
 }
 
-/// Quorum which requires some proportion of group age as well as number
-///
-/// We require the same proportion of age as of the number of nodes (although
-/// these could be separated).
-pub struct AgeQuorum {
-    proportion: RR,
+
+#[test]
+fn test_age_cap() {
+    // Members 6 and 7 of age 100, the others of age 4
+    let group: Vec<Member> = (0..8)
+        .map(|name| {
+            Member {
+                name: name,
+                age: if name >= 6 { 100 } else { 4 },
+                capacity: 1.0,
+                joined: 0,
+            }
+        })
+        .collect();
+    let (young, old) = group.split_at(6);
+    let cap = |ordering| {
+        Some(AgeCap {
+            cap: 4,
+            ordering: ordering,
+        })
+    };
+
+    // Capped, the old members weigh no more than the young: four young members
+    // are half the weight, and the old no longer block by weight
+    let mut age = AgeQuorum::new();
+    age.set_quorum_size(QuorumSize::Proportion(0.5));
+    age.set_block_prop(Some(0.3));
+    assert!(!age.is_quorum(&group, &young[..4]));
+    assert!(age.is_blocking(&group, old));
+    age.set_age_cap(cap(false));
+    assert!(age.is_quorum(&group, &young[..4]));
+    assert!(!age.is_blocking(&group, old));
+
+    // The old members remain elders unless the cap also applies to ordering,
+    // when all rank alike and the names decide
+    let mut elder = ElderQuorum::with_elders(3);
+    elder.set_quorum_size(QuorumSize::Proportion(0.5));
+    elder.set_age_cap(cap(false));
+    assert!(elder.is_quorum(&group, old));
+    elder.set_age_cap(cap(true));
+    assert!(!elder.is_quorum(&group, old));
+    assert_eq!(elders_capped(&group, 3, Some(4)).iter().map(|m| m.name).collect::<Vec<_>>(),
+               vec![0, 1, 2]);
+
+    assert_eq!(cap(true).expect("cap").to_string(), "4+ordering");
+    assert_eq!("4+ordering".parse(), Ok(cap(true).expect("cap")));
+    assert_eq!("16".parse::<AgeCap>().map(|c| (c.cap, c.ordering)), Ok((16, false)));
+    assert_eq!(AgeCap::ordering_cap(cap(true)), Some(4));
+    assert_eq!(AgeCap::ordering_cap(cap(false)), None);
+    assert!("0".parse::<AgeCap>().is_err());
+    assert!("x+ordering".parse::<AgeCap>().is_err());
 }
 
-impl AgeQuorum {
-    /// New structure. Default to requiring a quorum of the entire group.
-    pub fn new() -> Self {
-        AgeQuorum { proportion: 1.0 }
+#[test]
+fn test_elder_tiebreak() {
+    // Six members of age 4 joined in reverse order of name, and an older member 9 which joined
+    // last
+    let mut group: Vec<Member> = (0..6)
+        .map(|name| {
+            Member {
+                name: name,
+                age: 4,
+                capacity: 1.0,
+                joined: 10 - name,
+            }
+        })
+        .collect();
+    group.push(Member {
+        name: 9,
+        age: 5,
+        capacity: 1.0,
+        joined: 20,
+    });
+    let seated = |group: &[Member], tiebreak| -> Vec<NodeName> {
+        elders_ranked(group, 3, None, tiebreak).iter().map(|m| m.name).collect()
+    };
+    assert_eq!(seated(&group, ElderTiebreak::Name), vec![9, 0, 1]);
+    assert_eq!(seated(&group, ElderTiebreak::JoinOrder), vec![9, 5, 4]);
+
+    // At random: the same for the same group, whatever the order of its members, but drawn
+    // afresh as the membership changes; the older member always first
+    let random = seated(&group, ElderTiebreak::RandomPerDecision);
+    assert_eq!(random[0], 9);
+    let mut reversed = group.clone();
+    reversed.reverse();
+    assert_eq!(seated(&reversed, ElderTiebreak::RandomPerDecision), random);
+    let draws: Vec<Vec<NodeName>> = (10..30)
+        .map(|name| {
+            let mut changed = group.clone();
+            changed[0].name = name;
+            seated(&changed, ElderTiebreak::RandomPerDecision)
+        })
+        .collect();
+    assert!(draws.iter().any(|draw| *draw != draws[0]));
+    assert!(draws.iter().all(|draw| draw[0] == 9));
+
+    // The quorum seats its elders by the rule
+    let mut quorum = ElderQuorum::with_elders(3);
+    quorum.set_quorum_size(QuorumSize::Proportion(0.5));
+    assert!(quorum.is_quorum(&group, &group[..2]));
+    quorum.set_elder_tiebreak(ElderTiebreak::JoinOrder);
+    assert!(!quorum.is_quorum(&group, &group[..2]));
+
+    for tiebreak in &[ElderTiebreak::Name,
+                      ElderTiebreak::JoinOrder,
+                      ElderTiebreak::RandomPerDecision] {
+        assert_eq!(ElderTiebreak::from_name(tiebreak.name()), Some(*tiebreak));
     }
+    assert_eq!(ElderTiebreak::from_name("age"), None);
 }
 
-impl Quorum for AgeQuorum {
-    fn quorum_size(&self, _: NN) -> Option<NN> {
-        None
+
+/// Progress of the attack, passed to `AttackStrategy::reset_node`.
+#[derive(Clone, Copy, Debug)]
+pub struct AttackProgress {
+    /// Current step, starting from 0
+    pub step: NN,
+    /// Number of steps the simulation runs for (unless compromise occurs first)
+    pub max_steps: NN,
+    /// Number of malicious nodes not currently in the network or doing
+    /// proof-of-work to join (i.e. the attacker's remaining join budget)
+    pub available: NN,
+    /// Whether the group the node would join is already disrupted. Only found
+    /// under the disruption objective (`false` otherwise).
+    pub disrupted: bool,
+    /// Whether every group counted (of the target prefix's lineage, if any)
+    /// is already disrupted. Only found under the disruption objective.
+    pub all_disrupted: bool,
+}
+
+/// What the attacker aims for: compromise of a group (the default) or
+/// disruption, which needs fewer malicious nodes per group.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Objective {
+    Compromise,
+    Disruption,
+}
+
+impl Objective {
+    pub fn name(self) -> &'static str {
+        match self {
+            Objective::Compromise => "compromise",
+            Objective::Disruption => "disruption",
+        }
     }
 
-    fn set_quorum_proportion(&mut self, prop: RR) {
-        self.proportion = prop;
+    /// Parse from `name()`
+    pub fn from_name(name: &str) -> Option<Objective> {
+        [Objective::Compromise, Objective::Disruption].iter().cloned().find(|o| o.name() == name)
     }
+}
 
-    fn quorum_disrupted(&self, group: &HashMap<NodeName, NodeData>) -> bool {
-        let n_nodes = group.len() as RR;
-        let mut sum_age = 0;
-        let mut n_good = 0;
-        let mut good_age = 0;
-        for data in group.values() {
-            sum_age += data.age();
-            if !data.is_malicious() {
-                n_good += 1;
-                good_age += data.age();
-            }
+/// What the attacker knows of the network when deciding where to deploy its
+/// nodes (see `NetworkView`). Under every level it knows the prefixes of the
+/// groups, which routing makes public, and which groups are disrupted (see
+/// `AttackProgress`), since those visibly stop deciding.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AttackerKnowledge {
+    /// Every member of every group, with its age (the default)
+    Full,
+    /// The members of every group, but not the ages of honest members, which
+    /// it takes to be the mean age of honest nodes in the network
+    MembershipOnly,
+    /// Only its own nodes and their groups: it takes every group to hold the
+    /// mean number of honest members per group, of the mean honest age, so
+    /// that groups differ to it only in its own nodes
+    OwnNodesOnly,
+}
+
+impl AttackerKnowledge {
+    pub fn name(self) -> &'static str {
+        match self {
+            AttackerKnowledge::Full => "full",
+            AttackerKnowledge::MembershipOnly => "membership-only",
+            AttackerKnowledge::OwnNodesOnly => "own-nodes-only",
         }
-        (n_good as RR) / n_nodes < self.proportion ||
-        (good_age as RR) / (sum_age as RR) < self.proportion
     }
 
-    fn quorum_compromised(&self, group: &HashMap<NodeName, NodeData>) -> bool {
-        let n_nodes = group.len() as RR;
-        let mut sum_age = 0;
-        let mut n_bad = 0;
-        let mut bad_age = 0;
-        for data in group.values() {
-            sum_age += data.age();
-            if data.is_malicious() {
-                n_bad += 1;
-                bad_age += data.age();
-            }
-        }
-        (n_bad as RR) / n_nodes >= self.proportion &&
-        (bad_age as RR) / (sum_age as RR) >= self.proportion
+    /// Parse from `name()`
+    pub fn from_name(name: &str) -> Option<AttackerKnowledge> {
+        [AttackerKnowledge::Full,
+         AttackerKnowledge::MembershipOnly,
+         AttackerKnowledge::OwnNodesOnly]
+            .iter()
+            .cloned()
+            .find(|k| k.name() == name)
     }
 }
 
+/// What the attacker may find out about the network when deciding how many of
+/// its nodes to deploy (see `AttackStrategy::deploy`), as limited by its
+/// `AttackerKnowledge`.
+pub trait NetworkView {
+    /// Prefixes of the groups counted (of the target prefix's lineage, if
+    /// any), in order
+    fn prefixes(&self) -> Vec<Prefix>;
+
+    /// Whether the group with `prefix` would be compromised were `extra` new
+    /// malicious nodes (of age 0) to join it, its other members unchanged, as
+    /// far as the attacker can tell. Only as many join as the group admits (see
+    /// `RestrictOnePerAge`); the others would land elsewhere.
+    fn compromised_with(&self, prefix: Prefix, extra: NN) -> bool;
+}
 
 /// Determines a few things about how attacks work.
 ///
 /// A clone is made for each simulation, which may hold mutable state.
 /// This state is lost at the end of the simulation.
+///
+/// This is an extension point: custom strategies may be implemented outside
+/// this crate and passed to `tools::FullSimTool::new`. For example, a strategy
+/// holding every malicious node in reserve during the first half of the
+/// simulation, then deploying them all:
+///
+/// ```
+/// use routing_sims::NN;
+/// use routing_sims::quorum::{AttackStrategy, AttackProgress, NetworkView};
+/// use routing_sims::sim::{Prefix, Node, NodeName, NodeData};
+///
+/// #[derive(Clone)]
+/// struct LateAttack;
+///
+/// impl AttackStrategy for LateAttack {
+///     fn split(&mut self, _: Prefix, _: Prefix, _: NodeName, _: &NodeData) {}
+///
+///     fn reset_node(&mut self, _: &Node, _: Prefix, _: &AttackProgress) -> bool {
+///         false
+///     }
+///
+///     fn deploy(&mut self, _view: &NetworkView, progress: &AttackProgress) -> NN {
+///         if progress.step < progress.max_steps / 2 {
+///             0
+///         } else {
+///             progress.available
+///         }
+///     }
+/// }
+/// ```
 pub trait AttackStrategy {
     /// Called when splitting occurs
     fn split(&mut self,
@@ -151,7 +1478,49 @@ pub trait AttackStrategy {
              node_name: NodeName,
              node_data: &NodeData);
     /// This should return true if the attacker decides to reset this malicious node.
-    fn reset_node(&mut self, node: &Node, prefix: Prefix) -> bool;
+    ///
+    /// This is called for new malicious nodes (which will start proof-of-work
+    /// unless reset) and for relocated malicious nodes. The node's data holds
+    /// its weight, e.g. its capacity (see `Weighting`). A reset new node is
+    /// offered again at once under a new name, so to hold nodes back use
+    /// `deploy` instead; a step's deployment stops after many resets in a row.
+    fn reset_node(&mut self, node: &Node, prefix: Prefix, progress: &AttackProgress) -> bool;
+
+    /// How many of the `progress.available` malicious nodes to deploy this
+    /// step, i.e. to offer to `reset_node` and have join; the rest are held in
+    /// reserve for later steps. Called once a step while the attack is on and
+    /// nodes are available (with `progress.disrupted` false). By default, all
+    /// are deployed.
+    fn deploy(&mut self, _view: &NetworkView, progress: &AttackProgress) -> NN {
+        progress.available
+    }
+
+    /// Pin the attack to the lineage of the group responsible for `target`
+    /// (i.e. to groups compatible with it). Strategies which do not target a
+    /// group may ignore this.
+    fn set_target(&mut self, _target: Prefix) {}
+
+    /// Set what the attacker aims for (compromise by default). Strategies
+    /// which do not target a group may ignore this.
+    fn set_objective(&mut self, _objective: Objective) {}
+
+    /// Whether the attacker grinds the name of a new node it has just decided
+    /// to keep in the group of `prefix`: tries names until one wins ties of
+    /// age for elder seats under `ElderTiebreak::Name`. The simulation decides
+    /// whether the grinding succeeds (see `--name-grinding`). By default, it
+    /// does not grind.
+    fn grinds_name(&self, _prefix: Prefix, _progress: &AttackProgress) -> bool {
+        false
+    }
+
+    /// Whether the decisions may depend on the quorum, i.e. on which groups
+    /// are disrupted (see `AttackProgress`). If not, runs which differ only in
+    /// the quorum size follow the same trajectories, so may share them (see
+    /// `FullSimTool::sweep_quorums`). Strategies are assumed to unless they say
+    /// otherwise.
+    fn depends_on_quorum(&self) -> bool {
+        true
+    }
 }
 
 /// Strategy which does not involve any targetting.
@@ -165,21 +1534,40 @@ impl AttackStrategy for UntargettedAttack {
              _node_name: NodeName,
              _node_data: &NodeData) {
     }
-    fn reset_node(&mut self, _node: &Node, _prefix: Prefix) -> bool {
+    fn reset_node(&mut self, _node: &Node, _prefix: Prefix, _progress: &AttackProgress) -> bool {
+        false
+    }
+
+    fn depends_on_quorum(&self) -> bool {
         false
     }
 }
 
 /// Strategy which targets a group. This is very simple and naive; better
 /// strategies are possible with node ageing.
+///
+/// Under the disruption objective, the target is dropped once disrupted, and
+/// nodes are not spent on disrupted groups (unless all are), so that as many
+/// groups as possible are disrupted.
 #[derive(Clone)]
 pub struct SimpleTargettedAttack {
     target: Option<Prefix>,
+    pinned: Option<Prefix>,
+    objective: Objective,
 }
 
 impl SimpleTargettedAttack {
     pub fn new() -> Self {
-        SimpleTargettedAttack { target: None }
+        SimpleTargettedAttack {
+            target: None,
+            pinned: None,
+            objective: Objective::Compromise,
+        }
+    }
+
+    // True if the group with this prefix may be targetted
+    fn allowed(&self, prefix: Prefix) -> bool {
+        self.pinned.map_or(true, |pinned| pinned.is_compatible(prefix))
     }
 }
 
@@ -189,17 +1577,651 @@ impl AttackStrategy for SimpleTargettedAttack {
              new_prefix: Prefix,
              _node_name: NodeName,
              _node_data: &NodeData) {
-        if self.target == Some(old_prefix) {
+        if self.target == Some(old_prefix) && self.allowed(new_prefix) {
             self.target = Some(new_prefix);
         }
     }
 
-    fn reset_node(&mut self, _node: &Node, prefix: Prefix) -> bool {
+    fn reset_node(&mut self, _node: &Node, prefix: Prefix, progress: &AttackProgress) -> bool {
+        if self.objective == Objective::Disruption && progress.disrupted &&
+           !progress.all_disrupted {
+            // Move on to another group
+            if self.target.map_or(false, |target| target.is_compatible(prefix)) {
+                self.target = None;
+            }
+            return true;
+        }
         if let Some(target) = self.target {
+            // The target may have split while none of our nodes were in it (so
+            // `split` was not called); if so, follow it now.
+            if prefix.bit_count() > target.bit_count() && prefix.is_compatible(target) &&
+               self.allowed(prefix) {
+                self.target = Some(prefix);
+                return false;
+            }
             prefix != target
-        } else {
+        } else if self.allowed(prefix) {
             self.target = Some(prefix);
             false
+        } else {
+            true
+        }
+    }
+
+    fn set_target(&mut self, target: Prefix) {
+        self.pinned = Some(target);
+    }
+
+    fn set_objective(&mut self, objective: Objective) {
+        self.objective = objective;
+    }
+
+    // Nodes are only kept in the target, which they may as well seize the seats of
+    fn grinds_name(&self, _prefix: Prefix, _progress: &AttackProgress) -> bool {
+        true
+    }
+
+    // Only disruption is found from the quorum
+    fn depends_on_quorum(&self) -> bool {
+        self.objective == Objective::Disruption
+    }
+}
+
+/// Strategy which lets malicious nodes join wherever they land (and age) until
+/// the last 10% of steps, then behaves like `SimpleTargettedAttack`.
+#[derive(Clone)]
+pub struct DeadlineAttack {
+    targetted: SimpleTargettedAttack,
+}
+
+impl DeadlineAttack {
+    pub fn new() -> Self {
+        DeadlineAttack { targetted: SimpleTargettedAttack::new() }
+    }
+}
+
+impl AttackStrategy for DeadlineAttack {
+    fn split(&mut self,
+             old_prefix: Prefix,
+             new_prefix: Prefix,
+             node_name: NodeName,
+             node_data: &NodeData) {
+        self.targetted.split(old_prefix, new_prefix, node_name, node_data);
+    }
+
+    fn reset_node(&mut self, node: &Node, prefix: Prefix, progress: &AttackProgress) -> bool {
+        if progress.step * 10 < progress.max_steps * 9 {
+            false
+        } else {
+            self.targetted.reset_node(node, prefix, progress)
+        }
+    }
+
+    fn set_target(&mut self, target: Prefix) {
+        self.targetted.set_target(target);
+    }
+
+    fn set_objective(&mut self, objective: Objective) {
+        self.targetted.set_objective(objective);
+    }
+
+    fn grinds_name(&self, prefix: Prefix, progress: &AttackProgress) -> bool {
+        progress.step * 10 >= progress.max_steps * 9 && self.targetted.grinds_name(prefix, progress)
+    }
+
+    fn depends_on_quorum(&self) -> bool {
+        self.targetted.depends_on_quorum()
+    }
+}
+
+/// Strategy which deploys nodes only where they could compromise a group, and
+/// otherwise waits. Each step, if the nodes available would compromise some
+/// group (of the target's lineage, if pinned) were they to join it, the
+/// fewest which would are deployed and target that group like
+/// `SimpleTargettedAttack`, which then makes the decisions; if not, none are.
+/// Deploying no more than needed keeps the rest from being turned away by the
+/// group's limit on new members, to join elsewhere at random. Meanwhile the nodes already in
+/// the network age, and those relocated and reset return to the reserve, so
+/// the attacker may reach the threshold later.
+#[derive(Clone)]
+pub struct ThresholdAttack {
+    targetted: SimpleTargettedAttack,
+}
+
+impl ThresholdAttack {
+    pub fn new() -> Self {
+        ThresholdAttack { targetted: SimpleTargettedAttack::new() }
+    }
+}
+
+impl AttackStrategy for ThresholdAttack {
+    fn split(&mut self,
+             old_prefix: Prefix,
+             new_prefix: Prefix,
+             node_name: NodeName,
+             node_data: &NodeData) {
+        self.targetted.split(old_prefix, new_prefix, node_name, node_data);
+    }
+
+    fn reset_node(&mut self, node: &Node, prefix: Prefix, progress: &AttackProgress) -> bool {
+        self.targetted.reset_node(node, prefix, progress)
+    }
+
+    fn deploy(&mut self, view: &NetworkView, progress: &AttackProgress) -> NN {
+        // The current target first, if it is still a group
+        let prefixes = view.prefixes();
+        let current = match self.targetted.target {
+            Some(target) if prefixes.contains(&target) => Some(target),
+            _ => None,
+        };
+        let reachable = current.into_iter()
+            .chain(prefixes.iter().cloned())
+            .filter(|&prefix| self.targetted.allowed(prefix))
+            .find(|&prefix| view.compromised_with(prefix, progress.available));
+        match reachable {
+            Some(prefix) => {
+                self.targetted.target = Some(prefix);
+                (1..progress.available)
+                    .find(|&extra| view.compromised_with(prefix, extra))
+                    .unwrap_or(progress.available)
+            }
+            None => 0,
         }
     }
+
+    fn set_target(&mut self, target: Prefix) {
+        self.targetted.set_target(target);
+    }
+
+    fn set_objective(&mut self, objective: Objective) {
+        self.targetted.set_objective(objective);
+    }
+
+    fn grinds_name(&self, prefix: Prefix, progress: &AttackProgress) -> bool {
+        self.targetted.grinds_name(prefix, progress)
+    }
+
+    // The threshold is the quorum's
+    fn depends_on_quorum(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn test_threshold_attack() {
+    // Groups 0 and 1 of 10 members, of which 2 and 4 are malicious, compromised by half
+    struct View;
+    impl NetworkView for View {
+        fn prefixes(&self) -> Vec<Prefix> {
+            vec!["0".parse().expect("prefix"), "1".parse().expect("prefix")]
+        }
+        fn compromised_with(&self, prefix: Prefix, extra: NN) -> bool {
+            let malicious = if prefix.bit_count() == 1 && prefix.matches(0) { 2 } else { 4 };
+            (malicious + extra) * 2 >= 10 + extra
+        }
+    }
+    let progress = |available| {
+        AttackProgress {
+            step: 0,
+            max_steps: 10,
+            available: available,
+            disrupted: false,
+            all_disrupted: false,
+        }
+    };
+    let prefix = |bits: &str| bits.parse::<Prefix>().expect("prefix");
+    let node = (0, NodeData::new_malicious());
+    let mut attack = ThresholdAttack::new();
+    // One node reaches no quorum, so waits; two reach one in group 1, which is then targetted
+    assert_eq!(attack.deploy(&View, &progress(1)), 0);
+    assert_eq!(attack.deploy(&View, &progress(2)), 2);
+    // With more, only those needed are deployed
+    assert_eq!(attack.deploy(&View, &progress(4)), 2);
+    assert!(attack.reset_node(&node, prefix("0"), &progress(2)));
+    assert!(!attack.reset_node(&node, prefix("1"), &progress(2)));
+    // Pinned to group 0, six are needed
+    let mut attack = ThresholdAttack::new();
+    attack.set_target(prefix("0"));
+    assert_eq!(attack.deploy(&View, &progress(5)), 0);
+    assert_eq!(attack.deploy(&View, &progress(6)), 6);
+    assert!(!attack.reset_node(&node, prefix("0"), &progress(6)));
+
+    // Other strategies deploy everything
+    assert_eq!(SimpleTargettedAttack::new().deploy(&View, &progress(3)), 3);
+    let mut composite = CompositeAttack::new(Box::new(ThresholdAttack::new()),
+                                             5,
+                                             Box::new(UntargettedAttack));
+    assert_eq!(composite.deploy(&View, &progress(1)), 0);
+    let later = AttackProgress { step: 5, ..progress(1) };
+    assert_eq!(composite.deploy(&View, &later), 1);
+}
+
+/// An `AttackStrategy` which can be cloned when boxed, as needed to combine
+/// strategies chosen at run time. Implemented for all cloneable strategies.
+pub trait BoxedAttack: AttackStrategy {
+    fn box_clone(&self) -> Box<BoxedAttack>;
+}
+
+impl<A: AttackStrategy + Clone + 'static> BoxedAttack for A {
+    fn box_clone(&self) -> Box<BoxedAttack> {
+        Box::new(self.clone())
+    }
+}
+
+/// Strategy which uses one strategy before a given step and another from then
+/// on, e.g. untargetted infiltration followed by targetting. Both are told of
+/// all splits and of any target, so the second is up to date when it takes
+/// over.
+pub struct CompositeAttack {
+    first: Box<BoxedAttack>,
+    switch_step: NN,
+    second: Box<BoxedAttack>,
+}
+
+impl CompositeAttack {
+    /// Use `first` for steps before `switch_step`, then `second`.
+    pub fn new(first: Box<BoxedAttack>, switch_step: NN, second: Box<BoxedAttack>) -> Self {
+        CompositeAttack {
+            first: first,
+            switch_step: switch_step,
+            second: second,
+        }
+    }
+}
+
+impl Clone for CompositeAttack {
+    fn clone(&self) -> Self {
+        CompositeAttack::new(self.first.box_clone(), self.switch_step, self.second.box_clone())
+    }
+}
+
+impl AttackStrategy for CompositeAttack {
+    fn split(&mut self,
+             old_prefix: Prefix,
+             new_prefix: Prefix,
+             node_name: NodeName,
+             node_data: &NodeData) {
+        self.first.split(old_prefix, new_prefix, node_name, node_data);
+        self.second.split(old_prefix, new_prefix, node_name, node_data);
+    }
+
+    fn reset_node(&mut self, node: &Node, prefix: Prefix, progress: &AttackProgress) -> bool {
+        if progress.step < self.switch_step {
+            self.first.reset_node(node, prefix, progress)
+        } else {
+            self.second.reset_node(node, prefix, progress)
+        }
+    }
+
+    fn deploy(&mut self, view: &NetworkView, progress: &AttackProgress) -> NN {
+        if progress.step < self.switch_step {
+            self.first.deploy(view, progress)
+        } else {
+            self.second.deploy(view, progress)
+        }
+    }
+
+    fn set_target(&mut self, target: Prefix) {
+        self.first.set_target(target);
+        self.second.set_target(target);
+    }
+
+    fn set_objective(&mut self, objective: Objective) {
+        self.first.set_objective(objective);
+        self.second.set_objective(objective);
+    }
+
+    fn grinds_name(&self, prefix: Prefix, progress: &AttackProgress) -> bool {
+        if progress.step < self.switch_step {
+            self.first.grinds_name(prefix, progress)
+        } else {
+            self.second.grinds_name(prefix, progress)
+        }
+    }
+
+    fn depends_on_quorum(&self) -> bool {
+        self.first.depends_on_quorum() || self.second.depends_on_quorum()
+    }
+}
+
+#[test]
+fn test_composite_attack() {
+    let mut composite = CompositeAttack::new(Box::new(SimpleTargettedAttack::new()),
+                                             5,
+                                             Box::new(UntargettedAttack));
+    let mut simple = SimpleTargettedAttack::new();
+    let node = (0, NodeData::new_malicious());
+    let prefixes: Vec<Prefix> =
+        ["0", "1", "10"].iter().map(|bits| bits.parse().expect("prefix")).collect();
+    for step in 0..10 {
+        let progress = AttackProgress {
+            step: step,
+            max_steps: 10,
+            available: 0,
+            disrupted: false,
+            all_disrupted: false,
+        };
+        for prefix in &prefixes {
+            let expected = simple.reset_node(&node, *prefix, &progress);
+            if step < 5 {
+                // Same decisions as the first strategy before the switch
+                assert_eq!(composite.reset_node(&node, *prefix, &progress),
+                           expected,
+                           "step {}, prefix {}",
+                           step,
+                           prefix);
+            } else {
+                assert!(!composite.reset_node(&node, *prefix, &progress));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_depends_on_quorum() {
+    let disrupting = |mut attack: Box<BoxedAttack>| {
+        attack.set_objective(Objective::Disruption);
+        attack
+    };
+    assert!(!UntargettedAttack.depends_on_quorum());
+    assert!(!SimpleTargettedAttack::new().depends_on_quorum());
+    assert!(disrupting(Box::new(SimpleTargettedAttack::new())).depends_on_quorum());
+    assert!(!disrupting(Box::new(UntargettedAttack)).depends_on_quorum());
+    assert!(disrupting(Box::new(DeadlineAttack::new())).depends_on_quorum());
+    let composite = || {
+        CompositeAttack::new(Box::new(UntargettedAttack),
+                             5,
+                             Box::new(SimpleTargettedAttack::new()))
+    };
+    assert!(!composite().depends_on_quorum());
+    assert!(disrupting(Box::new(composite())).depends_on_quorum());
+}
+
+#[test]
+fn test_grinds_name() {
+    let prefix = Prefix::default();
+    let progress = |step| {
+        AttackProgress {
+            step: step,
+            max_steps: 100,
+            available: 0,
+            disrupted: false,
+            all_disrupted: false,
+        }
+    };
+    assert!(!UntargettedAttack.grinds_name(prefix, &progress(0)));
+    assert!(SimpleTargettedAttack::new().grinds_name(prefix, &progress(0)));
+    assert!(ThresholdAttack::new().grinds_name(prefix, &progress(0)));
+    // The deadline attack only in its last tenth
+    assert!(!DeadlineAttack::new().grinds_name(prefix, &progress(89)));
+    assert!(DeadlineAttack::new().grinds_name(prefix, &progress(90)));
+    let composite = CompositeAttack::new(Box::new(UntargettedAttack),
+                                         5,
+                                         Box::new(SimpleTargettedAttack::new()));
+    assert!(!composite.grinds_name(prefix, &progress(4)));
+    assert!(composite.grinds_name(prefix, &progress(5)));
+}
+
+#[test]
+fn test_disruption_objective() {
+    let mut attack = SimpleTargettedAttack::new();
+    attack.set_objective(Objective::Disruption);
+    let node = (0, NodeData::new_malicious());
+    let prefix = |bits: &str| bits.parse::<Prefix>().expect("prefix");
+    let progress = |disrupted: bool, all_disrupted: bool| {
+        AttackProgress {
+            step: 0,
+            max_steps: 10,
+            available: 1,
+            disrupted: disrupted,
+            all_disrupted: all_disrupted,
+        }
+    };
+    assert!(!attack.reset_node(&node, prefix("0"), &progress(false, false)));
+    assert!(attack.reset_node(&node, prefix("1"), &progress(false, false)));
+    // Once the target is disrupted, the next undisrupted group is targetted
+    assert!(attack.reset_node(&node, prefix("0"), &progress(true, false)));
+    assert!(!attack.reset_node(&node, prefix("1"), &progress(false, false)));
+    assert!(attack.reset_node(&node, prefix("0"), &progress(false, false)));
+    // With all groups disrupted, nodes join the target as usual
+    assert!(!attack.reset_node(&node, prefix("1"), &progress(true, true)));
+}
+
+
+/// One decision of the attacker: at `step`, whether to reset a malicious node
+/// offered to it in the group of `prefix` (see `AttackStrategy::reset_node`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AttackDecision {
+    pub step: NN,
+    pub prefix: Prefix,
+    pub reset: bool,
+}
+
+/// Displays as "step:prefix:reset", e.g. "12:01:1"; the root prefix is empty.
+impl fmt::Display for AttackDecision {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.step, self.prefix, if self.reset { 1 } else { 0 })
+    }
+}
+
+impl FromStr for AttackDecision {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').collect();
+        if parts.len() != 3 {
+            return Err(format!("expected step:prefix:reset, found '{}'", s));
+        }
+        let step = try!(parts[0].parse().map_err(|_| format!("bad step '{}'", parts[0])));
+        let prefix = try!(parts[1].parse().map_err(|_| format!("bad prefix '{}'", parts[1])));
+        let reset = match parts[2] {
+            "0" => false,
+            "1" => true,
+            x => return Err(format!("bad reset '{}' (must be 0 or 1)", x)),
+        };
+        Ok(AttackDecision {
+            step: step,
+            prefix: prefix,
+            reset: reset,
+        })
+    }
+}
+
+/// Read attacker decisions written as CSV with the columns Combination, Step,
+/// Prefix and Reset (0 or 1), after a header line, as by `--record-attack`.
+/// Comment lines (starting `#`, e.g. the stamp of the model version) are
+/// skipped. Returns the decisions of each combination, in order.
+pub fn read_attack_decisions<R: BufRead>(input: R)
+                                         -> Result<BTreeMap<usize, Vec<AttackDecision>>, String> {
+    let mut decisions = BTreeMap::new();
+    let mut header = true;
+    for (i, line) in input.lines().enumerate() {
+        let line = try!(line.map_err(|e| e.to_string()));
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if header {
+            header = false;
+            continue;
+        }
+        let fields: Vec<&str> = line.trim().split(',').collect();
+        if fields.len() != 4 {
+            return Err(format!("line {}: expected 4 fields, found '{}'", i + 1, line));
+        }
+        let combination: usize = try!(fields[0]
+            .parse()
+            .map_err(|_| format!("line {}: bad combination '{}'", i + 1, fields[0])));
+        let decision = try!(fields[1..]
+            .join(":")
+            .parse::<AttackDecision>()
+            .map_err(|e| format!("line {}: {}", i + 1, e)));
+        decisions.entry(combination).or_insert_with(Vec::new).push(decision);
+    }
+    Ok(decisions)
+}
+
+#[test]
+fn test_read_attack_decisions() {
+    let csv = "Combination,Step,Prefix,Reset\n1,0,,1\n1,0,,0\n2,5,01,1\n";
+    let decisions = read_attack_decisions(csv.as_bytes()).expect("read");
+    assert_eq!(decisions.keys().cloned().collect::<Vec<_>>(), vec![1, 2]);
+    assert_eq!(decisions[&1].iter().map(|d| d.to_string()).collect::<Vec<_>>(),
+               vec!["0::1", "0::0"]);
+    assert_eq!(decisions[&2][0].to_string(), "5:01:1");
+    let stamped = format!("# routing-sims model 1\n{}", csv);
+    assert_eq!(read_attack_decisions(stamped.as_bytes()).expect("read"), decisions);
+    assert!(read_attack_decisions("C,S,P,R\n1,0,2,1\n".as_bytes()).is_err());
+    assert!(read_attack_decisions("C,S,P,R\n1,0,1\n".as_bytes()).is_err());
+}
+
+/// Strategy which records the decisions of another, or replays recorded
+/// decisions in its place, e.g. to compare a change of strategy against the
+/// very same attack.
+///
+/// Replayed decisions are taken in order; once they run out, the inner
+/// strategy decides. The inner strategy is told of splits and any target
+/// either way. Replaying panics if the simulation diverges from the recording,
+/// i.e. if a node is offered at another step or in another group than
+/// recorded, since the recorded decision is then impossible.
+pub struct RecordedAttack<A: AttackStrategy> {
+    inner: A,
+    replay: Vec<AttackDecision>,
+    taken: Vec<AttackDecision>,
+}
+
+impl<A: AttackStrategy> RecordedAttack<A> {
+    /// Record the decisions of `inner`, after replaying `replay`
+    pub fn new(inner: A, replay: Vec<AttackDecision>) -> Self {
+        RecordedAttack {
+            inner: inner,
+            replay: replay,
+            taken: vec![],
+        }
+    }
+
+    /// The decisions taken so far, in order (replayed or not)
+    pub fn decisions(&self) -> &[AttackDecision] {
+        &self.taken
+    }
+
+    /// Take the decisions taken so far
+    pub fn into_decisions(self) -> Vec<AttackDecision> {
+        self.taken
+    }
+}
+
+impl<A: AttackStrategy> AttackStrategy for RecordedAttack<A> {
+    fn split(&mut self,
+             old_prefix: Prefix,
+             new_prefix: Prefix,
+             node_name: NodeName,
+             node_data: &NodeData) {
+        self.inner.split(old_prefix, new_prefix, node_name, node_data);
+    }
+
+    fn reset_node(&mut self, node: &Node, prefix: Prefix, progress: &AttackProgress) -> bool {
+        let reset = match self.replay.get(self.taken.len()) {
+            Some(recorded) => {
+                if recorded.step != progress.step || recorded.prefix != prefix {
+                    panic!("replayed attack diverged at decision {}: recorded {}, but a node \
+                            was offered at step {} in group '{}'",
+                           self.taken.len(),
+                           recorded,
+                           progress.step,
+                           prefix);
+                }
+                recorded.reset
+            }
+            None => self.inner.reset_node(node, prefix, progress),
+        };
+        self.taken.push(AttackDecision {
+            step: progress.step,
+            prefix: prefix,
+            reset: reset,
+        });
+        reset
+    }
+
+    // Deployments follow from the network, so are the same again as long as the replay holds
+    fn deploy(&mut self, view: &NetworkView, progress: &AttackProgress) -> NN {
+        self.inner.deploy(view, progress)
+    }
+
+    fn set_target(&mut self, target: Prefix) {
+        self.inner.set_target(target);
+    }
+
+    fn set_objective(&mut self, objective: Objective) {
+        self.inner.set_objective(objective);
+    }
+
+    // The decisions recorded are whether to reset; grinding follows the strategy
+    fn grinds_name(&self, prefix: Prefix, progress: &AttackProgress) -> bool {
+        self.inner.grinds_name(prefix, progress)
+    }
+
+    fn depends_on_quorum(&self) -> bool {
+        self.inner.depends_on_quorum()
+    }
+}
+
+#[test]
+fn test_recorded_attack() {
+    let node = (0, NodeData::new_malicious());
+    let prefixes: Vec<Prefix> =
+        ["0", "1", "10"].iter().map(|bits| bits.parse().expect("prefix")).collect();
+    let progress = |step| {
+        AttackProgress {
+            step: step,
+            max_steps: 10,
+            available: 0,
+            disrupted: false,
+            all_disrupted: false,
+        }
+    };
+    let mut recording = RecordedAttack::new(SimpleTargettedAttack::new(), vec![]);
+    for step in 0..3 {
+        for prefix in &prefixes {
+            let _ = recording.reset_node(&node, *prefix, &progress(step));
+        }
+    }
+    let recorded = recording.into_decisions();
+    assert_eq!(recorded.len(), 9);
+    for decision in &recorded {
+        assert_eq!(decision.to_string().parse(), Ok(*decision));
+    }
+
+    // Replaying in place of a strategy which never resets
+    let mut replaying = RecordedAttack::new(UntargettedAttack, recorded.clone());
+    for step in 0..3 {
+        for prefix in &prefixes {
+            let _ = replaying.reset_node(&node, *prefix, &progress(step));
+        }
+    }
+    assert_eq!(replaying.decisions(), &recorded[..]);
+    // Then the inner strategy takes over
+    assert!(!replaying.reset_node(&node, prefixes[1], &progress(3)));
+
+    assert!("1:01".parse::<AttackDecision>().is_err());
+    assert!("1:012:1".parse::<AttackDecision>().is_err());
+    assert_eq!("4::1".parse::<AttackDecision>().map(|d| d.prefix.bit_count()), Ok(0));
+}
+
+#[test]
+#[should_panic(expected = "replayed attack diverged")]
+fn test_recorded_attack_diverged() {
+    let node = (0, NodeData::new_malicious());
+    let recorded = vec![AttackDecision {
+                            step: 2,
+                            prefix: "0".parse().expect("prefix"),
+                            reset: true,
+                        }];
+    let mut replaying = RecordedAttack::new(UntargettedAttack, recorded);
+    let progress = AttackProgress {
+        step: 2,
+        max_steps: 10,
+        available: 0,
+        disrupted: false,
+        all_disrupted: false,
+    };
+    let _ = replaying.reset_node(&node, "1".parse().expect("prefix"), &progress);
 }
@@ -0,0 +1,268 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Random number streams of the simulations
+//!
+//! Each source of randomness draws from its own stream, seeded from a master
+//! seed and the stream's name (and, unless the stream is fixed, the number of
+//! the repetition). A stream may thus be fixed across repetitions while the
+//! others vary.
+//!
+//! Every stream counts the values drawn from it (see `CountedRng`), so that
+//! runs which should be identical can be checked to draw alike (see
+//! `--rng-audit`).
+
+use super::NN;
+use super::checkpoint::fnv1a;
+use super::sim::Prefix;
+
+use rand::{thread_rng, Rng, SeedableRng, XorShiftRng};
+
+
+/// A stream of random numbers
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stream {
+    /// Building the network before the attack: names and uptimes of the
+    /// initial nodes, and their relocation
+    Structure,
+    /// Names of malicious nodes (in the structure simulation, which nodes are
+    /// malicious)
+    Attack,
+    /// Churn events and relocation during the attack
+    Churn,
+    /// Which nodes are up when sampling liveness (see `--report liveness`)
+    Liveness,
+}
+
+impl Stream {
+    /// Name, as given to `--fix-stream`
+    pub fn name(self) -> &'static str {
+        match self {
+            Stream::Structure => "structure",
+            Stream::Attack => "attack",
+            Stream::Churn => "churn",
+            Stream::Liveness => "liveness",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Stream> {
+        match name {
+            "structure" => Some(Stream::Structure),
+            "attack" => Some(Stream::Attack),
+            "churn" => Some(Stream::Churn),
+            "liveness" => Some(Stream::Liveness),
+            _ => None,
+        }
+    }
+}
+
+/// All streams, in the order of `Draws`
+pub const STREAMS: [Stream; 4] =
+    [Stream::Structure, Stream::Attack, Stream::Churn, Stream::Liveness];
+
+/// Values drawn from each stream in one repetition, in the order of `STREAMS`
+pub type Draws = [NN; 4];
+
+/// How to seed the streams (common to all parameter sets).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Seeding {
+    /// Master seed; if `None`, each parameter set draws its own
+    pub seed: Option<u64>,
+    /// Streams with the same seed in every repetition
+    pub fixed: Vec<Stream>,
+}
+
+impl Seeding {
+    /// A random master seed and no fixed streams
+    pub fn new() -> Self {
+        Seeding {
+            seed: None,
+            fixed: vec![],
+        }
+    }
+
+    /// The master seed, drawn at random if not given
+    pub fn master(&self) -> u64 {
+        self.seed.unwrap_or_else(|| thread_rng().gen())
+    }
+
+    /// Streams for the given repetition (counting from 0).
+    pub fn streams(&self, master: u64, repetition: NN) -> Streams {
+        let rng = |stream: Stream| self.stream(master, stream, repetition);
+        Streams {
+            structure: rng(Stream::Structure),
+            attack: rng(Stream::Attack),
+            churn: rng(Stream::Churn),
+            liveness: rng(Stream::Liveness),
+        }
+    }
+
+    /// One stream of the given repetition (counting from 0).
+    pub fn stream(&self, master: u64, stream: Stream, repetition: NN) -> CountedRng {
+        let rep = if self.fixed.contains(&stream) {
+            None
+        } else {
+            Some(repetition)
+        };
+        CountedRng {
+            rng: stream_rng(master, stream, rep),
+            draws: 0,
+        }
+    }
+
+    /// Generator of the quorum jitter of the group of `prefix` in the given
+    /// repetition (see `quorum::QuorumJitter`): like the structure stream, the
+    /// same in every repetition if that stream is fixed.
+    pub fn jitter_rng(&self, master: u64, repetition: NN, prefix: Prefix) -> XorShiftRng {
+        let label = if self.fixed.contains(&Stream::Structure) {
+            format!("{} jitter {}", master, prefix)
+        } else {
+            format!("{} jitter {} {}", master, repetition, prefix)
+        };
+        label_rng(&label)
+    }
+
+    /// Generator of which honest members of the group of `prefix` take part in
+    /// its decisions while its membership is `membership` (see
+    /// `quorum::Participation`): like the liveness stream, the same in every
+    /// repetition if that stream is fixed.
+    pub fn participation_rng(&self,
+                             master: u64,
+                             repetition: NN,
+                             prefix: Prefix,
+                             membership: u64)
+                             -> XorShiftRng {
+        let label = if self.fixed.contains(&Stream::Liveness) {
+            format!("{} participation {} {:016x}", master, prefix, membership)
+        } else {
+            format!("{} participation {} {} {:016x}", master, repetition, prefix, membership)
+        };
+        label_rng(&label)
+    }
+
+    /// Description for naming checkpoints, empty unless a seed is given (the
+    /// results of a random seed do not depend on it).
+    pub fn describe(&self) -> String {
+        match self.seed {
+            None => String::new(),
+            Some(seed) => {
+                let fixed: Vec<&str> = self.fixed.iter().map(|s| s.name()).collect();
+                format!(" seed {} fixed {}", seed, fixed.join(","))
+            }
+        }
+    }
+}
+
+/// The random number generators of one repetition
+pub struct Streams {
+    pub structure: CountedRng,
+    pub attack: CountedRng,
+    pub churn: CountedRng,
+    pub liveness: CountedRng,
+}
+
+impl Streams {
+    /// Values drawn from each stream so far
+    pub fn draws(&self) -> Draws {
+        [self.structure.draws, self.attack.draws, self.churn.draws, self.liveness.draws]
+    }
+}
+
+/// A generator counting the values drawn from it, in 32-bit words (so a
+/// `u64` or `f64` counts as two). The values are those of the generator
+/// alone; counting costs an addition per word.
+#[derive(Clone)]
+pub struct CountedRng {
+    rng: XorShiftRng,
+    draws: NN,
+}
+
+impl CountedRng {
+    /// Words drawn so far
+    pub fn draws(&self) -> NN {
+        self.draws
+    }
+}
+
+impl Rng for CountedRng {
+    fn next_u32(&mut self) -> u32 {
+        self.draws += 1;
+        self.rng.next_u32()
+    }
+}
+
+// Generator of `stream` for the given repetition, or for all repetitions if `None`.
+fn stream_rng(master: u64, stream: Stream, repetition: Option<NN>) -> XorShiftRng {
+    let label = match repetition {
+        Some(rep) => format!("{} {} {}", master, stream.name(), rep),
+        None => format!("{} {}", master, stream.name()),
+    };
+    label_rng(&label)
+}
+
+/// Generator of the order in which to run the parameter sets (see
+/// `estimate::Schedule::Shuffle`), seeded like the streams.
+pub fn schedule_rng(seed: u64) -> XorShiftRng {
+    label_rng(&format!("{} schedule", seed))
+}
+
+/// Generator of the points of a scan (see `scan`), seeded like the streams.
+pub fn scan_rng(seed: u64) -> XorShiftRng {
+    label_rng(&format!("{} scan", seed))
+}
+
+// Generator seeded from a hash of `label`
+fn label_rng(label: &str) -> XorShiftRng {
+    let (a, b) = (fnv1a(label), fnv1a(&format!("{} 1", label)));
+    // The seed must not be all zero
+    XorShiftRng::from_seed([a as u32, (a >> 32) as u32, b as u32, (b >> 32) as u32 | 1])
+}
+
+#[test]
+fn test_streams() {
+    let mut seeding = Seeding {
+        seed: Some(42),
+        fixed: vec![Stream::Structure],
+    };
+    let (mut s0, mut s1) = (seeding.streams(42, 0), seeding.streams(42, 1));
+    assert_eq!(s0.structure.gen::<u64>(), s1.structure.gen::<u64>());
+    assert!(s0.attack.gen::<u64>() != s1.attack.gen::<u64>());
+    assert!(s0.churn.gen::<u64>() != s1.churn.gen::<u64>());
+    assert_eq!(seeding.streams(42, 1).liveness.gen::<u64>(),
+               seeding.stream(42, Stream::Liveness, 1).gen::<u64>());
+
+    // Streams differ from each other, and repeat given the same master seed
+    let (mut a, mut b) = (seeding.streams(42, 3), seeding.streams(42, 3));
+    let x = a.structure.gen::<u64>();
+    assert!(x != a.attack.gen::<u64>());
+    assert_eq!(x, b.structure.gen::<u64>());
+    assert!(x != seeding.streams(43, 3).structure.gen::<u64>());
+
+    seeding.fixed.clear();
+    assert!(seeding.streams(42, 0).structure.gen::<u64>() !=
+            seeding.streams(42, 1).structure.gen::<u64>());
+
+    // Counting draws does not change them
+    let mut counted = seeding.stream(42, Stream::Churn, 2);
+    let mut plain = stream_rng(42, Stream::Churn, Some(2));
+    assert_eq!(counted.gen::<u64>(), plain.gen::<u64>());
+    assert_eq!(counted.gen_range(0, 10), plain.gen_range(0, 10));
+    assert_eq!(counted.draws(), 3);
+    let mut streams = seeding.streams(42, 2);
+    let _ = streams.attack.gen::<f64>();
+    assert_eq!(streams.draws(), [0, 2, 0, 0]);
+}
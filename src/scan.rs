@@ -0,0 +1,421 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Quasi-random scans of the parameter space
+//!
+//! A full grid over several parameters grows as the product of their values.
+//! `routing-sims scan` instead takes the ranges of `-n`, `-r`, `-k` and `-q`
+//! as bounds (ignoring their steps) and samples a given number of points
+//! spread within them, by Latin hypercube sampling or a Sobol sequence, each
+//! point giving a parameter set for every combination of the other options.
+//! The sets then run like any others.
+
+use super::{NN, RR};
+use super::args::{SimParams, RelOrAbs};
+use super::batch::{params_row, validate};
+use super::quorum::QuorumSize;
+
+use rand::Rng;
+
+use std::collections::HashSet;
+use std::fmt::{self, Formatter};
+use std::str::FromStr;
+
+
+/// How the points of a scan are spread
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sampling {
+    /// Latin hypercube: the range of each parameter is divided into as many
+    /// strata as there are samples, and each stratum holds one sample
+    LatinHypercube,
+    /// A Sobol sequence, randomly shifted (a digital shift, which keeps its
+    /// stratification)
+    Sobol,
+}
+
+impl fmt::Display for Sampling {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.pad(match *self {
+            Sampling::LatinHypercube => "lhs",
+            Sampling::Sobol => "sobol",
+        })
+    }
+}
+
+impl FromStr for Sampling {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "lhs" => Ok(Sampling::LatinHypercube),
+            "sobol" => Ok(Sampling::Sobol),
+            x => Err(format!("expected lhs or sobol, found '{}'", x)),
+        }
+    }
+}
+
+/// A scan: the number of points sampled, and how
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Scan {
+    pub samples: NN,
+    pub sampling: Sampling,
+}
+
+/// Bounds of the parameters a scan varies: those of `-n`, `-r`, `-k` and `-q`
+/// given as ranges (`None` for those given a single value, which stay as
+/// given). Bounds are inclusive.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ScanBounds {
+    pub nodes: Option<(NN, NN)>,
+    pub malicious: Option<(RelOrAbs, RelOrAbs)>,
+    pub group_size: Option<(NN, NN)>,
+    pub quorum: Option<(QuorumSize, QuorumSize)>,
+}
+
+impl ScanBounds {
+    /// Number of parameters varied
+    pub fn dims(&self) -> usize {
+        [self.nodes.is_some(),
+         self.malicious.is_some(),
+         self.group_size.is_some(),
+         self.quorum.is_some()]
+            .iter()
+            .filter(|&&varied| varied)
+            .count()
+    }
+
+    /// Set the parameters varied of `params` from `point`, a point of the unit
+    /// cube of `dims()` dimensions (in the order of the fields). Counts are
+    /// rounded down, each of the range equally likely; real values are
+    /// rounded to six decimal places.
+    pub fn apply(&self, point: &[RR], params: &mut SimParams) {
+        let mut coords = point.iter().cloned();
+        let mut next = || coords.next().expect("a coordinate per parameter varied");
+        if let Some((low, high)) = self.nodes {
+            params.num_nodes = count(low, high, next());
+        }
+        if let Some(bounds) = self.malicious {
+            params.num_malicious = match bounds {
+                (RelOrAbs::Rel(low), RelOrAbs::Rel(high)) => {
+                    RelOrAbs::Rel(real(low, high, next()))
+                }
+                (RelOrAbs::Abs(low), RelOrAbs::Abs(high)) => {
+                    RelOrAbs::Abs(count(low, high, next()))
+                }
+                _ => panic!("bounds of -r must be of one kind"),
+            };
+        }
+        if let Some((low, high)) = self.group_size {
+            params.min_group_size = count(low, high, next());
+        }
+        if let Some(bounds) = self.quorum {
+            params.quorum = match bounds {
+                (QuorumSize::Proportion(low), QuorumSize::Proportion(high)) => {
+                    QuorumSize::Proportion(real(low, high, next()))
+                }
+                (QuorumSize::Count(low), QuorumSize::Count(high)) => {
+                    QuorumSize::Count(count(low, high, next()))
+                }
+                _ => panic!("bounds of -q must be of one kind"),
+            };
+        }
+    }
+}
+
+// The count from `low` to `high` at `x` of the way (from 0 to 1, exclusive)
+fn count(low: NN, high: NN, x: RR) -> NN {
+    let n = (high - low + 1) as RR;
+    low + ((x * n) as NN).min(high - low)
+}
+
+// The real value from `low` to `high` at `x` of the way, to six decimal places
+fn real(low: RR, high: RR, x: RR) -> RR {
+    ((low + x * (high - low)) * 1e6).round() / 1e6
+}
+
+/// The options of a scan and the arguments of the tool it runs, from those of
+/// `scan`: `scan [structure | full] --samples N [--sampling TYPE] ...` scans
+/// with the options of the structure or full simulation, or otherwise the
+/// direct calculation.
+pub fn parse_argv(argv: &[String]) -> Result<(Scan, Vec<String>), String> {
+    let tool = argv.get(2).and_then(|arg| match arg.as_str() {
+        "full" | "structure" => Some(arg.clone()),
+        _ => None,
+    });
+    let mut tool_argv: Vec<String> = argv.iter()
+        .take(1)
+        .cloned()
+        .chain(Some(tool.clone().unwrap_or("calc".to_owned())))
+        .collect();
+    let mut samples = None;
+    let mut sampling = Sampling::LatinHypercube;
+    let mut rest = argv.iter().skip(if tool.is_some() { 3 } else { 2 });
+    while let Some(arg) = rest.next() {
+        let (option, value) = match arg.find('=') {
+            Some(i) if arg.starts_with("--samp") => (&arg[..i], Some(arg[i + 1..].to_owned())),
+            _ => (arg.as_str(), None),
+        };
+        if option != "--samples" && option != "--sampling" {
+            tool_argv.push(arg.clone());
+            continue;
+        }
+        let value = match value.or_else(|| rest.next().cloned()) {
+            Some(value) => value,
+            None => return Err(format!("{} requires a value", option)),
+        };
+        if option == "--samples" {
+            samples = match value.parse::<NN>() {
+                Ok(n) if n > 0 => Some(n),
+                _ => return Err(format!("bad --samples '{}' (expected at least 1)", value)),
+            };
+        } else {
+            sampling = try!(value.parse().map_err(|e| format!("bad --sampling: {}", e)));
+        }
+    }
+    match samples {
+        Some(samples) => {
+            Ok((Scan {
+                    samples: samples,
+                    sampling: sampling,
+                },
+                tool_argv))
+        }
+        None => Err("--samples N is required".to_owned()),
+    }
+}
+
+impl Scan {
+    /// Points of the unit cube of `dims` dimensions, drawn from `rng`
+    pub fn points<R: Rng>(&self, dims: usize, rng: &mut R) -> Result<Vec<Vec<RR>>, String> {
+        let samples = self.samples as usize;
+        match self.sampling {
+            Sampling::LatinHypercube => Ok(latin_hypercube(samples, dims, rng)),
+            Sampling::Sobol => sobol(samples, dims, rng),
+        }
+    }
+
+    /// The parameter sets of the scan: each point, within `bounds`, applied to
+    /// each of `base` (the sets of the options, those varied taking any value),
+    /// in order of point. Points giving a set already made (counts being
+    /// rounded) or an unusable one (e.g. a quorum count above the group size)
+    /// are left out; returns the sets and the numbers left out for each reason.
+    pub fn param_sets(&self,
+                      base: &[SimParams],
+                      bounds: &ScanBounds,
+                      points: &[Vec<RR>])
+                      -> (Vec<SimParams>, usize, usize) {
+        let mut seen = HashSet::new();
+        let (mut sets, mut repeats, mut unusable) = (vec![], 0, 0);
+        for point in points {
+            for params in base {
+                let mut params = params.clone();
+                bounds.apply(point, &mut params);
+                if validate(&params).is_err() {
+                    unusable += 1;
+                } else if !seen.insert(params_row(&params)) {
+                    repeats += 1;
+                } else {
+                    sets.push(params);
+                }
+            }
+        }
+        (sets, repeats, unusable)
+    }
+}
+
+/// `samples` points of the unit cube of `dims` dimensions by Latin hypercube
+/// sampling: in each dimension, each of `samples` equal strata holds exactly
+/// one point, at random within it, the strata paired at random between
+/// dimensions.
+pub fn latin_hypercube<R: Rng>(samples: usize, dims: usize, rng: &mut R) -> Vec<Vec<RR>> {
+    let mut points = vec![vec![0.0; dims]; samples];
+    for dim in 0..dims {
+        let mut strata: Vec<usize> = (0..samples).collect();
+        rng.shuffle(&mut strata);
+        for (point, stratum) in points.iter_mut().zip(strata) {
+            point[dim] = (stratum as RR + rng.gen::<RR>()) / samples as RR;
+        }
+    }
+    points
+}
+
+/// Dimensions of the Sobol sequence available
+pub const SOBOL_DIMS: usize = 4;
+
+// Degree, coefficients and initial direction numbers of the primitive polynomial of each
+// dimension after the first (from the direction numbers of Joe and Kuo)
+const SOBOL_POLYNOMIALS: [(usize, u32, &'static [u32]); SOBOL_DIMS - 1] =
+    [(1, 0, &[1]), (2, 1, &[1, 3]), (3, 1, &[1, 3, 1])];
+
+// Bits of each coordinate of the Sobol sequence
+const SOBOL_BITS: usize = 32;
+
+// Direction numbers of each dimension of the Sobol sequence, for `SOBOL_BITS` bits
+fn sobol_directions(dims: usize) -> Vec<Vec<u32>> {
+    (0..dims)
+        .map(|dim| {
+            let mut v = vec![0u32; SOBOL_BITS];
+            if dim == 0 {
+                for (k, v) in v.iter_mut().enumerate() {
+                    *v = 1 << (SOBOL_BITS - 1 - k);
+                }
+                return v;
+            }
+            let (s, a, m) = SOBOL_POLYNOMIALS[dim - 1];
+            for k in 0..SOBOL_BITS {
+                v[k] = if k < s {
+                    m[k] << (SOBOL_BITS - 1 - k)
+                } else {
+                    let mut x = v[k - s] ^ (v[k - s] >> s);
+                    for i in 1..s {
+                        if (a >> (s - 1 - i)) & 1 == 1 {
+                            x ^= v[k - i];
+                        }
+                    }
+                    x
+                };
+            }
+            v
+        })
+        .collect()
+}
+
+/// The first `samples` points of the Sobol sequence in `dims` dimensions (at
+/// most `SOBOL_DIMS`), each dimension shifted by random bits from `rng`. For
+/// a power of two samples, each of as many equal strata of each dimension
+/// holds exactly one point.
+pub fn sobol<R: Rng>(samples: usize, dims: usize, rng: &mut R) -> Result<Vec<Vec<RR>>, String> {
+    if dims > SOBOL_DIMS {
+        return Err(format!("Sobol sampling of {} parameters (at most {})", dims, SOBOL_DIMS));
+    }
+    let directions = sobol_directions(dims);
+    let shifts: Vec<u32> = (0..dims).map(|_| rng.gen()).collect();
+    let scale = 2f64.powi(SOBOL_BITS as i32);
+    let mut x = vec![0u32; dims];
+    let mut points = Vec::with_capacity(samples);
+    for i in 0..samples {
+        if i > 0 {
+            // Gray code order: flip the direction of the lowest zero bit of the previous index
+            let bit = (!(i - 1)).trailing_zeros() as usize;
+            for (x, v) in x.iter_mut().zip(&directions) {
+                *x ^= v[bit];
+            }
+        }
+        points.push(x.iter().zip(&shifts).map(|(x, shift)| (x ^ shift) as RR / scale).collect());
+    }
+    Ok(points)
+}
+
+#[cfg(test)]
+fn assert_stratified(points: &[Vec<RR>], dims: usize) {
+    let n = points.len();
+    for dim in 0..dims {
+        let mut strata: Vec<usize> =
+            points.iter().map(|point| (point[dim] * n as RR) as usize).collect();
+        strata.sort();
+        assert_eq!(strata, (0..n).collect::<Vec<_>>(), "dimension {}", dim);
+    }
+}
+
+#[test]
+fn test_latin_hypercube() {
+    use rand::{SeedableRng, XorShiftRng};
+
+    let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+    for &(samples, dims) in &[(1, 1), (10, 3), (37, 4)] {
+        let points = latin_hypercube(samples, dims, &mut rng);
+        assert_eq!(points.len(), samples);
+        assert!(points.iter().all(|point| point.len() == dims));
+        assert_stratified(&points, dims);
+    }
+    // The same seed gives the same points
+    let draw = || latin_hypercube(8, 2, &mut XorShiftRng::from_seed([5, 6, 7, 8]));
+    assert_eq!(draw(), draw());
+}
+
+#[test]
+fn test_sobol() {
+    use rand::{SeedableRng, XorShiftRng};
+
+    // Unshifted, the first points of the first two dimensions
+    let directions = sobol_directions(2);
+    assert_eq!(&directions[0][..3], &[1 << 31, 1 << 30, 1 << 29]);
+    assert_eq!(&directions[1][..3], &[1 << 31, 3 << 30, 5 << 29]);
+
+    let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+    for &samples in &[1, 2, 16, 64] {
+        let points = sobol(samples, SOBOL_DIMS, &mut rng).expect("points");
+        assert_eq!(points.len(), samples);
+        assert_stratified(&points, SOBOL_DIMS);
+    }
+    assert!(sobol(4, SOBOL_DIMS + 1, &mut rng).is_err());
+}
+
+#[test]
+fn test_scan() {
+    use super::args::ArgProc;
+    use rand::{SeedableRng, XorShiftRng};
+
+    let argv: Vec<String> =
+        ["routing-sims", "scan", "full", "-n", "500-900:100", "--samples=9", "-k", "8-10", "-r",
+         "10%-30%", "--sampling", "lhs", "-Q", "all"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+    let (scan, tool_argv) = parse_argv(&argv).expect("parse");
+    assert_eq!(scan,
+               Scan {
+                   samples: 9,
+                   sampling: Sampling::LatinHypercube,
+               });
+    assert_eq!(tool_argv.join(" "),
+               "routing-sims full -n 500-900:100 -k 8-10 -r 10%-30% -Q all");
+    assert!(parse_argv(&argv[..5]).is_err());
+    assert!(parse_argv(&["routing-sims".to_owned(), "scan".to_owned(), "--samples".to_owned()])
+        .is_err());
+
+    let mut arg_proc = ArgProc::from_argv(tool_argv).expect("tool arguments");
+    arg_proc.set_scan();
+    let bounds = arg_proc.scan_bounds().expect("bounds");
+    let percent = |s: &str| s.parse::<RelOrAbs>().expect("percentage");
+    assert_eq!(bounds,
+               ScanBounds {
+                   nodes: Some((500, 900)),
+                   malicious: Some((percent("10%"), percent("30%"))),
+                   group_size: Some((8, 10)),
+                   quorum: None,
+               });
+    // One set per quorum type, the varied options at their lower bounds
+    let base = arg_proc.make_sim_params();
+    assert_eq!(base.len(), 3);
+    assert!(base.iter().all(|params| params.num_nodes == 500 && params.min_group_size == 8));
+
+    let points = scan.points(bounds.dims(), &mut XorShiftRng::from_seed([1, 2, 3, 4]))
+        .expect("points");
+    let (sets, repeats, unusable) = scan.param_sets(&base, &bounds, &points);
+    assert_eq!((sets.len(), repeats, unusable), (27, 0, 0));
+    // Nine samples give each group size three times
+    for k in 8..11 {
+        assert_eq!(sets.iter().filter(|params| params.min_group_size == k).count(), 9);
+    }
+    assert!(sets.iter().all(|params| params.num_nodes >= 500 && params.num_nodes <= 900));
+
+    // Scanning the group size alone, the points round to three sets
+    let only_k = ScanBounds { group_size: Some((8, 10)), ..ScanBounds::default() };
+    let points = latin_hypercube(9, 1, &mut XorShiftRng::from_seed([1, 2, 3, 4]));
+    let (sets, repeats, _) = scan.param_sets(&base[..1], &only_k, &points);
+    assert_eq!((sets.len(), repeats), (3, 6));
+}
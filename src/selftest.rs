@@ -0,0 +1,155 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Self-test: cross-checks of the tools where their results must agree
+//!
+//! The full simulation always ages and relocates nodes, so it only matches
+//! the direct calculation where the outcome is forced: a network too small to
+//! split is one group, holding every malicious node once all have joined. The
+//! group model of the direct calculation (a group is a random draw of nodes) is
+//! checked against sampling such draws.
+
+use super::{ToolArgs, NN, RR};
+use super::prob::prob_compromise;
+use super::quorum::{QuorumSize, SimpleQuorum, UntargettedAttack};
+use super::tools::{Tool, DirectCalcTool, SimStructureTool, FullSimTool};
+
+use rand::{Rng, SeedableRng, XorShiftRng};
+
+
+/// Master seed of the simulations, so that the self-test repeats
+pub const SEED: u64 = 1;
+
+/// Allowed difference between a probability estimated by repetition and its
+/// expected value, in binomial standard errors
+pub const TOLERANCE_SE: RR = 4.0;
+
+// Allowed difference between calculated probabilities (rounding only)
+const EPSILON: RR = 1e-9;
+
+// Network of one group: fewer nodes than needed to split (twice the minimum
+// group size)
+const ONE_GROUP_NODES: NN = 12;
+const ONE_GROUP_MIN_SIZE: NN = 8;
+const FULL_STEPS: NN = 50;
+const FULL_REPETITIONS: NN = 20;
+
+// Groups sampled per check of the group model
+const SAMPLES: NN = 20000;
+
+/// One cross-check: an observed probability against that expected.
+#[derive(Debug)]
+pub struct Check {
+    pub name: String,
+    pub observed: RR,
+    pub expected: RR,
+    /// Largest difference allowed
+    pub tolerance: RR,
+}
+
+impl Check {
+    pub fn passed(&self) -> bool {
+        (self.observed - self.expected).abs() <= self.tolerance
+    }
+}
+
+/// Allowed difference for a probability estimated from `runs` repetitions,
+/// when `p` is expected.
+pub fn binomial_tolerance(p: RR, runs: NN) -> RR {
+    TOLERANCE_SE * (p * (1.0 - p) / runs as RR).sqrt() + EPSILON
+}
+
+/// Run all checks.
+pub fn run() -> Vec<Check> {
+    let mut checks = vec![];
+
+    // Structure and full simulation against the direct calculation, in a
+    // network of one group (which all three tools take to hold all nodes).
+    for &(r, q) in &[(3, 0.5), (6, 0.5), (8, 0.5), (6, 0.6), (8, 0.6)] {
+        let (n, k) = (ONE_GROUP_NODES, ONE_GROUP_MIN_SIZE);
+        let quorum = QuorumSize::Proportion(q);
+        let args = || {
+            let mut args = ToolArgs::new(n, r, k, quorum, FULL_STEPS, FULL_REPETITIONS);
+            args.seeding.seed = Some(SEED);
+            args
+        };
+        let expected = DirectCalcTool::new(args()).calc_p_compromise().p_compromise;
+        let point = format!("n={} r={} k={} q={}", n, r, k, quorum);
+
+        let structure = SimStructureTool::new(args()).calc_p_compromise();
+        checks.push(Check {
+            name: format!("structure vs calc, {}", point),
+            observed: structure.p_compromise,
+            expected: expected,
+            tolerance: EPSILON,
+        });
+
+        let full = FullSimTool::new(args(), SimpleQuorum::new(), UntargettedAttack {})
+            .calc_p_compromise();
+        checks.push(Check {
+            name: format!("full vs calc, {}", point),
+            observed: full.p_compromise,
+            expected: expected,
+            tolerance: binomial_tolerance(expected, full.runs.unwrap_or(0)),
+        });
+    }
+
+    // The group model against sampling groups
+    let mut rng = XorShiftRng::from_seed([SEED as u32, 1, 2, 3]);
+    for &(n, r, k, q) in &[(100, 20, 10, 3), (100, 30, 10, 5), (50, 10, 8, 4)] {
+        let hits = (0..SAMPLES).filter(|_| sample_malicious(n, r, k, &mut rng) >= q).count();
+        let expected = prob_compromise(n, r, k, q);
+        checks.push(Check {
+            name: format!("calc group model vs sampling, n={} r={} k={} q={}a", n, r, k, q),
+            observed: hits as RR / SAMPLES as RR,
+            expected: expected,
+            tolerance: binomial_tolerance(expected, SAMPLES),
+        });
+    }
+
+    checks
+}
+
+// Number of malicious nodes in a group of `k` drawn without replacement from
+// `n` nodes, `r` of them malicious.
+fn sample_malicious<R: Rng>(n: NN, r: NN, k: NN, rng: &mut R) -> NN {
+    let (mut remaining, mut remaining_malicious) = (n, r);
+    let mut malicious = 0;
+    for _ in 0..k {
+        if rng.gen_range(0, remaining) < remaining_malicious {
+            malicious += 1;
+            remaining_malicious -= 1;
+        }
+        remaining -= 1;
+    }
+    malicious
+}
+
+#[test]
+fn test_binomial_tolerance() {
+    assert!(binomial_tolerance(0.0, 100) < 1e-6);
+    assert!(binomial_tolerance(1.0, 100) < 1e-6);
+    // p = 0.5 of 100 runs: standard error 0.05
+    assert!((binomial_tolerance(0.5, 100) - 0.2).abs() < 1e-6);
+    let check = Check {
+        name: "x".to_owned(),
+        observed: 0.3,
+        expected: 0.5,
+        tolerance: 0.1,
+    };
+    assert!(!check.passed());
+}
@@ -26,31 +26,34 @@
 // For now, because lots of stuff isn't implemented yet:
 #![allow(dead_code)]
 
-use super::{NN, Error, Result};
+use super::{NN, RR, Error, Result};
 use super::quorum::AttackStrategy;
+use super::churn_model::ChurnModel;
 
-use std::cmp::{Ordering, min};
+use std::cmp::{Ordering, min, max};
 use std::mem;
 use std::hash::{Hash, Hasher};
 use std::fmt::{self, Formatter, Binary, Debug};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::collections::hash_map::Entry;
 use std::marker::PhantomData;
+use std::result;
+use std::str::FromStr;
 use std::u64;
 
-use rand::{thread_rng, Rng};
+use rand::Rng;
 use rand::distributions::{Range, IndependentSample};
 
 
 // In the future, we may be able to do this:
 // const RANGE_NN: Range<NN> = Range::new(0, NN::max_value());
 #[allow(non_snake_case)]
-fn sample_NN() -> NN {
-    thread_rng().gen()
+fn sample_NN<R: Rng>(rng: &mut R) -> NN {
+    rng.gen()
 }
-fn sample_ub(ub: NN) -> NN {
+fn sample_ub<R: Rng>(ub: NN, rng: &mut R) -> NN {
     let range = Range::new(0, ub);
-    range.ind_sample(&mut thread_rng())
+    range.ind_sample(rng)
 }
 
 
@@ -151,7 +154,7 @@ impl Prefix {
 
     /// Returns a prefix copying the first `bitcount() - 1` bits from `self`,
     /// or `self` if it is already empty.
-    fn popped(mut self) -> Prefix {
+    pub fn popped(mut self) -> Prefix {
         if self.bit_count > 0 {
             self.bit_count -= 1;
             // unused bits should be zero:
@@ -161,12 +164,20 @@ impl Prefix {
     }
 
     /// Returns the number of bits in the prefix.
-    fn bit_count(&self) -> usize {
+    pub fn bit_count(&self) -> usize {
         self.bit_count
     }
 
+    /// Returns the lowest name matching the prefix.
+    pub fn lowest_name(&self) -> NN {
+        self.name
+    }
+
     /// Returns `true` if `self` is a prefix of `other` or vice versa.
-    fn is_compatible(&self, other: Prefix) -> bool {
+    ///
+    /// Since groups only ever split, the groups compatible with some prefix
+    /// are the lineage of the group which was once responsible for it.
+    pub fn is_compatible(&self, other: Prefix) -> bool {
         let i = self.name.common_prefix(other.name);
         i >= self.bit_count || i >= other.bit_count
     }
@@ -176,9 +187,19 @@ impl Prefix {
     }
 
     /// Returns `true` if this is a prefix of the given `name`.
-    fn matches(&self, name: NN) -> bool {
+    pub fn matches(&self, name: NN) -> bool {
         self.name.common_prefix(name) >= self.bit_count
     }
+
+    /// Returns a random name matching this prefix.
+    fn random_name<R: Rng>(&self, rng: &mut R) -> NN {
+        let name = sample_NN(rng);
+        if self.bit_count >= mem::size_of::<NN>() * 8 {
+            return self.name;
+        }
+        let mask = !0 >> self.bit_count;
+        self.name | (name & mask)
+    }
 }
 
 impl PartialEq<Prefix> for Prefix {
@@ -207,6 +228,35 @@ impl Hash for Prefix {
     }
 }
 
+/// Parse from a string of bits, e.g. "0110". The empty string is the root
+/// prefix.
+impl FromStr for Prefix {
+    type Err = ();
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        if s.len() > mem::size_of::<NN>() * 8 {
+            return Err(());
+        }
+        let mut prefix = Prefix::new(0, 0);
+        for c in s.chars() {
+            match c {
+                '0' => prefix = prefix.pushed(false),
+                '1' => prefix = prefix.pushed(true),
+                _ => return Err(()),
+            }
+        }
+        Ok(prefix)
+    }
+}
+
+/// Displays the bits, e.g. "0110"
+impl fmt::Display for Prefix {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        let mut binary = self.name.binary();
+        binary.truncate(self.bit_count);
+        formatter.pad(&binary)
+    }
+}
+
 impl Binary for Prefix {
     fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
         let mut binary = self.name.binary();
@@ -226,34 +276,59 @@ impl Debug for Prefix {
 pub type NodeName = u64;
 
 /// Generate a new node name
-pub fn new_node_name() -> NodeName {
-    sample_NN()
+pub fn new_node_name<R: Rng>(rng: &mut R) -> NodeName {
+    sample_NN(rng)
 }
 
 /// Data stored for a node
 #[derive(Clone, Copy)]
 pub struct NodeData {
     age: u32, // initial age is 0
+    forgone: u32, // age of its identity not recovered on rejoining (see `rejoin_with_age`)
     churns: u32, // initial churns is 0
     is_malicious: bool,
+    uptime: RR, // probability of taking part in a churn event
+    capacity: RR, // weight of votes under capacity weighting (see `quorum::Weighting`)
+    region: u32, // for correlated failures (see `burst::Burst`)
+    failed: bool, // lost in a burst, so down until removed
+    joined: NN, // adds to the network before this node's (see `Network::add_node`)
 }
 
 impl NodeData {
-    /// New data (initial age and churns, not malicious)
+    /// New data (initial age and churns, not malicious, always up)
     pub fn new() -> Self {
+        NodeData::with_uptime(1.0)
+    }
+
+    /// New data (initial age and churns, not malicious), with the given uptime
+    /// (see `uptime::UptimeDist`).
+    pub fn with_uptime(uptime: RR) -> Self {
         NodeData {
             age: 0,
+            forgone: 0,
             churns: 0,
             is_malicious: false,
+            uptime: uptime,
+            capacity: 1.0,
+            region: 0,
+            failed: false,
+            joined: 0,
         }
     }
 
-    /// New data (initial age and churns, is malicious)
+    /// New data (initial age and churns, is malicious). Malicious nodes are
+    /// always up.
     pub fn new_malicious() -> Self {
         NodeData {
             age: 0,
+            forgone: 0,
             churns: 0,
             is_malicious: true,
+            uptime: 1.0,
+            capacity: 1.0,
+            region: 0,
+            failed: false,
+            joined: 0,
         }
     }
 
@@ -262,16 +337,81 @@ impl NodeData {
         self.age
     }
 
+    /// Set the age
+    pub fn set_age(&mut self, age: u32) {
+        self.age = age;
+    }
+
+    /// Set the age of a node rejoining as an identity of age `identity_age`, of which it
+    /// recovers `age`. The identity keeps the rest, so rejoining again does not lose it.
+    pub fn rejoin_with_age(&mut self, identity_age: u32, age: u32) {
+        self.age = age;
+        self.forgone = identity_age.saturating_sub(age);
+    }
+
+    /// Get the age of the node's identity: its age, plus any not recovered on rejoining
+    pub fn identity_age(&self) -> u32 {
+        self.age + self.forgone
+    }
+
     // Increment churns, and return whether this is high enough for relocation
-    fn churn_and_can_age(&mut self) -> bool {
+    fn churn_and_can_age(&mut self, ageing: Ageing) -> bool {
         self.churns += 1;
-        self.churns >= 2u32.pow(self.age)
+        self.churns >= ageing.churns_to_age(self.age)
+    }
+
+    /// Get the uptime (probability of taking part in a churn event)
+    pub fn uptime(&self) -> RR {
+        self.uptime
+    }
+
+    /// Get the capacity (1 unless set)
+    pub fn capacity(&self) -> RR {
+        self.capacity
+    }
+
+    /// Set the capacity
+    pub fn set_capacity(&mut self, capacity: RR) {
+        self.capacity = capacity;
+    }
+
+    /// Get the region (0 unless set)
+    pub fn region(&self) -> u32 {
+        self.region
+    }
+
+    /// Set the region
+    pub fn set_region(&mut self, region: u32) {
+        self.region = region;
+    }
+
+    /// Has this node failed (been lost in a burst)? A failed node is down.
+    pub fn is_failed(&self) -> bool {
+        self.failed
+    }
+
+    /// Set whether failed
+    pub fn set_failed(&mut self, failed: bool) {
+        self.failed = failed;
+    }
+
+    /// When this node joined its group: the number of nodes added to the
+    /// network before it (0 until added)
+    pub fn joined(&self) -> NN {
+        self.joined
     }
 
     /// Is this node malicous?
     pub fn is_malicious(&self) -> bool {
         self.is_malicious
     }
+
+    /// Turn malicious, as when corrupted, keeping age and churns. Malicious
+    /// nodes are always up.
+    pub fn set_malicious(&mut self) {
+        self.is_malicious = true;
+        self.uptime = 1.0;
+    }
 }
 
 /// Type of a node
@@ -289,6 +429,9 @@ pub trait AddRestriction {
 pub struct NoAddRestriction;
 impl AddRestriction for NoAddRestriction {}
 
+/// Most members of age 0 (or of age 1) a group admits under `RestrictOnePerAge`
+pub const MAX_YOUNG_PER_AGE: usize = 2;
+
 pub struct RestrictOnePerAge;
 impl AddRestriction for RestrictOnePerAge {
     fn can_add(node_data: &NodeData, group: &HashMap<NodeName, NodeData>) -> bool {
@@ -296,37 +439,328 @@ impl AddRestriction for RestrictOnePerAge {
         if age > 1 {
             return true;
         }
-        group.values().filter(|data| data.age == age).count() < 2
+        group.values().filter(|data| data.age == age).count() < MAX_YOUNG_PER_AGE
     }
 }
 
 pub type Group = HashMap<NodeName, NodeData>;
 
+/// Churn events a node of age `age` must have taken part in, since joining, to
+/// be relocated (and so age): the node ages on the relocation following its
+/// 1st, 2nd, 4th, 8th, ... churn event.
+pub fn churns_to_age(age: u32) -> u32 {
+    2u32.pow(age)
+}
+
+/// How nodes age with the churn events they take part in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Ageing {
+    /// On the relocation following the 1st, 2nd, 4th, 8th, ... churn event
+    /// (see `churns_to_age`)
+    Doubling,
+    /// Doubling with a mix-up of units, ageing nodes 1000 times too fast: for
+    /// testing that the bound of `max_age` catches such a mistake
+    #[cfg(test)]
+    BrokenDoubling,
+}
+
+impl Ageing {
+    /// Churn events a node of age `age` must have taken part in to age
+    pub fn churns_to_age(self, age: u32) -> u32 {
+        match self {
+            Ageing::Doubling => churns_to_age(age),
+            #[cfg(test)]
+            Ageing::BrokenDoubling => max(churns_to_age(age) / 1000, 1),
+        }
+    }
+
+    /// The oldest age a node can reach having taken part in `churns` churn
+    /// events (including any before it left and rejoined with part of its
+    /// age). Derived from the policy rather than from `churns_to_age`, so
+    /// that a mistake in the rule applied shows as an age above it.
+    pub fn max_age(self, churns: NN) -> u32 {
+        match self {
+            // Age `a` takes 2^(a-1) churns: the age is at most 1 + log2(churns), rounded down
+            Ageing::Doubling => 64 - churns.leading_zeros(),
+            #[cfg(test)]
+            Ageing::BrokenDoubling => Ageing::Doubling.max_age(churns),
+        }
+    }
+}
+
+/// Where relocated nodes are sent
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RelocationTarget {
+    /// A random name, thus a group chosen with probability proportional to the
+    /// size of its part of the name space
+    Random,
+    /// The smallest group (ties broken by prefix)
+    Balanced,
+}
+
+impl RelocationTarget {
+    pub fn name(self) -> &'static str {
+        match self {
+            RelocationTarget::Random => "random",
+            RelocationTarget::Balanced => "balanced",
+        }
+    }
+
+    /// Parse from `name()`
+    pub fn from_name(name: &str) -> Option<RelocationTarget> {
+        [RelocationTarget::Random, RelocationTarget::Balanced]
+            .iter()
+            .cloned()
+            .find(|t| t.name() == name)
+    }
+}
+
+/// Network size at which proof-of-work to join takes one step
+pub const POW_REFERENCE_NODES: NN = 1000;
+
+/// How the proof-of-work to join scales with the size of the network
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PowScaling {
+    /// One step, whatever the size
+    Constant,
+    /// Proportional to the logarithm of the number of nodes
+    LogN,
+    /// Proportional to the number of nodes
+    LinearN,
+}
+
+impl PowScaling {
+    pub fn name(self) -> &'static str {
+        match self {
+            PowScaling::Constant => "none",
+            PowScaling::LogN => "log-n",
+            PowScaling::LinearN => "linear-n",
+        }
+    }
+
+    /// Parse from `name()`
+    pub fn from_name(name: &str) -> Option<PowScaling> {
+        [PowScaling::Constant, PowScaling::LogN, PowScaling::LinearN]
+            .iter()
+            .cloned()
+            .find(|s| s.name() == name)
+    }
+
+    /// Whole steps of proof-of-work to join a network of `nodes` nodes: one
+    /// at `POW_REFERENCE_NODES`, scaled and rounded to the nearest (at least
+    /// one).
+    pub fn join_steps(self, nodes: NN) -> NN {
+        let nodes = max(nodes, 2) as RR;
+        let reference = POW_REFERENCE_NODES as RR;
+        let steps = match self {
+            PowScaling::Constant => 1.0,
+            PowScaling::LogN => nodes.log2() / reference.log2(),
+            PowScaling::LinearN => nodes / reference,
+        };
+        max(steps.round() as NN, 1)
+    }
+}
+
+#[test]
+fn test_pow_scaling() {
+    for &nodes in &[10, 1000, 8000] {
+        assert_eq!(PowScaling::Constant.join_steps(nodes), 1);
+    }
+    assert_eq!(PowScaling::LinearN.join_steps(100), 1);
+    assert_eq!(PowScaling::LinearN.join_steps(1000), 1);
+    assert_eq!(PowScaling::LinearN.join_steps(4400), 4);
+    assert_eq!(PowScaling::LogN.join_steps(1000), 1);
+    assert_eq!(PowScaling::LogN.join_steps(1000000), 2);
+    assert_eq!(PowScaling::from_name("log-n"), Some(PowScaling::LogN));
+    assert_eq!(PowScaling::from_name("log"), None);
+}
+
+#[test]
+fn test_ageing_max_age() {
+    // A node which ages whenever it may reaches the bound exactly; one ageing too fast exceeds it
+    for &ageing in &[Ageing::Doubling, Ageing::BrokenDoubling] {
+        let mut data = NodeData::new();
+        let mut exceeded = false;
+        for churns in 1..5000 {
+            if data.churn_and_can_age(ageing) {
+                data.age += 1;
+            }
+            let bound = ageing.max_age(churns);
+            if ageing == Ageing::Doubling {
+                assert_eq!(data.age, bound, "after {} churns", churns);
+            }
+            exceeded |= data.age > bound;
+        }
+        assert_eq!(exceeded, ageing == Ageing::BrokenDoubling);
+    }
+    assert_eq!(Ageing::Doubling.max_age(0), 0);
+    assert_eq!(Ageing::Doubling.max_age(7), 3);
+    assert_eq!(Ageing::Doubling.max_age(8), 4);
+}
+
+/// A change to the membership of the network, as recorded for
+/// `verify::Verifier` (see `Network::record_events`)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    /// A node joined the group of the prefix
+    Added(Prefix, NodeName),
+    /// A node left the group of the prefix
+    Removed(Prefix, NodeName),
+    /// A node was taken from the group of the prefix to be relocated
+    Relocated(Prefix, NodeName),
+    /// The group of the prefix split
+    Split(Prefix),
+    /// Two nodes traded their data
+    Swapped(NodeName, NodeName),
+}
+
+// Record an event or departure, if recording
+fn record<T>(log: &mut Option<Vec<T>>, item: T) {
+    if let Some(ref mut log) = *log {
+        log.push(item);
+    }
+}
+
 pub struct Network<AddRestriction> {
     min_group_size: usize,
     groups: HashMap<Prefix, Group>,
+    churn_model: ChurnModel,
+    relocation: RelocationTarget,
+    ageing: Ageing,
+    // Groups changed since `take_touched` was last called
+    touched: HashSet<Prefix>,
+    // Events since `take_events` was last called, if recording
+    events: Option<Vec<Event>>,
+    // Members which left since `take_departures` was last called, with their data, if recording
+    departures: Option<Vec<(NodeName, NodeData)>>,
+    // Number of nodes added so far
+    joins: NN,
+    // Sum over the churn events of the most times a node took part in each
+    churns: NN,
     _dummy: PhantomData<AddRestriction>,
 }
 
 impl<AR: AddRestriction> Network<AR> {
     /// Create. Specify minimum group size.
     ///
-    /// An initial, empty, group is created.
+    /// An initial, empty, group is created. Relocation is to a random name, and
+    /// nodes age by doubling.
     pub fn new(min_group_size: usize) -> Self {
         let mut groups = HashMap::new();
         groups.insert(Prefix::new(0, 0), HashMap::new());
         Network {
             min_group_size: min_group_size,
             groups: groups,
+            churn_model: ChurnModel::Fixed,
+            relocation: RelocationTarget::Random,
+            ageing: Ageing::Doubling,
+            touched: Some(Prefix::new(0, 0)).into_iter().collect(),
+            events: None,
+            departures: None,
+            joins: 0,
+            churns: 0,
             _dummy: PhantomData {},
         }
     }
 
+    /// Set where relocated nodes are sent.
+    pub fn set_relocation_target(&mut self, target: RelocationTarget) {
+        self.relocation = target;
+    }
+
+    /// Set how many times honest members take part in each churn event
+    /// (default: `ChurnModel::Fixed`, once)
+    pub fn set_churn_model(&mut self, model: ChurnModel) {
+        self.churn_model = model;
+    }
+
+    /// Set how nodes age (`Ageing::Doubling` unless set).
+    pub fn set_ageing(&mut self, ageing: Ageing) {
+        self.ageing = ageing;
+    }
+
+    /// How nodes age
+    pub fn ageing(&self) -> Ageing {
+        self.ageing
+    }
+
     /// Access groups
     pub fn groups(&self) -> &HashMap<Prefix, HashMap<NodeName, NodeData>> {
         &self.groups
     }
 
+    /// Mutable access to the groups, bypassing the events recorded, for tests
+    /// which corrupt the structure
+    #[cfg(test)]
+    pub fn groups_mut(&mut self) -> &mut HashMap<Prefix, HashMap<NodeName, NodeData>> {
+        &mut self.groups
+    }
+
+    /// The minimum group size
+    pub fn min_group_size(&self) -> usize {
+        self.min_group_size
+    }
+
+    /// Number of nodes added so far (see `NodeData::joined`)
+    pub fn joins(&self) -> NN {
+        self.joins
+    }
+
+    /// The most churn events any node can have taken part in so far: at most
+    /// one for each node added under `ChurnModel::Fixed`, possibly more under
+    /// `ChurnModel::Poisson`
+    pub fn churns(&self) -> NN {
+        self.churns
+    }
+
+    /// Start recording the changes to the membership, to be taken by
+    /// `take_events`. Without, nothing is recorded.
+    pub fn record_events(&mut self) {
+        self.events = Some(vec![]);
+    }
+
+    /// Take the events recorded since last called (none unless recording).
+    pub fn take_events(&mut self) -> Vec<Event> {
+        self.events.as_mut().map_or(vec![], |events| mem::replace(events, vec![]))
+    }
+
+    /// The events recorded since `take_events` was last called, leaving them
+    /// to be taken (none unless recording).
+    pub fn events(&self) -> &[Event] {
+        self.events.as_ref().map_or(&[][..], |events| &events[..])
+    }
+
+    /// Start recording the members which leave (removed, or taken to be
+    /// relocated), to be taken by `take_departures`. Without, nothing is
+    /// recorded.
+    pub fn record_departures(&mut self) {
+        self.departures = Some(vec![]);
+    }
+
+    /// Take the members which left since last called, each with its data as a
+    /// member (none unless recording).
+    pub fn take_departures(&mut self) -> Vec<(NodeName, NodeData)> {
+        self.departures.as_mut().map_or(vec![], |departures| mem::replace(departures, vec![]))
+    }
+
+    /// Bytes held by the tables of groups and their nodes, from their
+    /// capacities, each entry with its hash (to check `estimate::memory_bytes`)
+    pub fn heap_bytes(&self) -> usize {
+        let entry = |size: usize| size + mem::size_of::<u64>();
+        let nodes: usize = self.groups
+            .values()
+            .map(|group| group.capacity() * entry(mem::size_of::<(NodeName, NodeData)>()))
+            .sum();
+        nodes + self.groups.capacity() * entry(mem::size_of::<(Prefix, Group)>())
+    }
+
+    /// Take the prefixes of the groups changed since last called (or created):
+    /// in membership, or in the data of some member. Groups split since are
+    /// included, though no longer in the network.
+    pub fn take_touched(&mut self) -> HashSet<Prefix> {
+        mem::replace(&mut self.touched, HashSet::new())
+    }
+
     /// Get the prefix for the group to which this name belongs.
     pub fn find_prefix(&self, name: NodeName) -> Prefix {
         // There are two strategies here:
@@ -343,22 +777,88 @@ impl<AR: AddRestriction> Network<AR> {
         unreachable!()
     }
 
-    /// Insert a node. Returns the prefix of the group added to.
-    pub fn add_node(&mut self, node_name: NodeName, node_data: NodeData) -> Result<Prefix> {
+    /// Insert a node, noting when it joined (see `NodeData::joined`). Returns
+    /// the prefix of the group added to.
+    pub fn add_node(&mut self, node_name: NodeName, mut node_data: NodeData) -> Result<Prefix> {
         let prefix = self.find_prefix(node_name);
         let mut group = self.groups.get_mut(&prefix).expect("network must include all groups");
         if group.len() > self.min_group_size && !AR::can_add(&node_data, group) {
             return Err(Error::AddRestriction);
         }
+        node_data.joined = self.joins;
         match group.entry(node_name) {
             Entry::Vacant(e) => e.insert(node_data),
             Entry::Occupied(_) => {
                 return Err(Error::AlreadyExists);
             }
         };
+        self.joins += 1;
+        self.touched.insert(prefix);
+        record(&mut self.events, Event::Added(prefix, node_name));
         Ok(prefix)
     }
 
+    /// Set whether a member has failed (see `NodeData::is_failed`). Does nothing
+    /// if not a member.
+    pub fn set_failed(&mut self, node_name: NodeName, failed: bool) {
+        let prefix = self.find_prefix(node_name);
+        let group = self.groups.get_mut(&prefix).expect("network must include all groups");
+        if let Some(data) = group.get_mut(&node_name) {
+            data.set_failed(failed);
+            self.touched.insert(prefix);
+        }
+    }
+
+    /// Turn a member malicious (see `NodeData::set_malicious`). Does nothing
+    /// if not a member.
+    pub fn set_malicious(&mut self, node_name: NodeName) {
+        let prefix = self.find_prefix(node_name);
+        let group = self.groups.get_mut(&prefix).expect("network must include all groups");
+        if let Some(data) = group.get_mut(&node_name) {
+            data.set_malicious();
+            self.touched.insert(prefix);
+        }
+    }
+
+    /// Trade the data of two members, as if each had joined with the other's
+    /// name (see `placement::InitialPlacement`). Does nothing unless both are
+    /// members.
+    pub fn swap_data(&mut self, a: NodeName, b: NodeName) {
+        let (prefix_a, prefix_b) = (self.find_prefix(a), self.find_prefix(b));
+        let data_a = match self.groups[&prefix_a].get(&a) {
+            Some(data) => *data,
+            None => return,
+        };
+        let data_b = match self.groups[&prefix_b].get(&b) {
+            Some(data) => *data,
+            None => return,
+        };
+        let group_a = self.groups.get_mut(&prefix_a).expect("network must include all groups");
+        group_a.insert(a, data_b);
+        let group_b = self.groups.get_mut(&prefix_b).expect("network must include all groups");
+        group_b.insert(b, data_a);
+        self.touched.insert(prefix_a);
+        self.touched.insert(prefix_b);
+        record(&mut self.events, Event::Swapped(a, b));
+    }
+
+    /// Remove a node which has left the network, returning its data (`None` if
+    /// not a member). Groups are never merged, so may fall below the minimum
+    /// size.
+    pub fn remove_node(&mut self, node_name: NodeName) -> Option<NodeData> {
+        let prefix = self.find_prefix(node_name);
+        let removed = self.groups
+            .get_mut(&prefix)
+            .expect("network must include all groups")
+            .remove(&node_name);
+        if let Some(data) = removed {
+            self.touched.insert(prefix);
+            record(&mut self.events, Event::Removed(prefix, node_name));
+            record(&mut self.departures, (node_name, data));
+        }
+        removed
+    }
+
     /// Check need_split and if true call do_split. Return the prefix matching
     /// `name` (the input prefix, if no split occurs).
     pub fn maybe_split(&mut self,
@@ -413,20 +913,24 @@ impl<AR: AddRestriction> Network<AR> {
         let prefix1 = prefix.pushed(true);
         let (group0, group1): (Group, Group) = old_group.into_iter()
             .partition(|node| prefix0.matches(node.0));
-        for (name, data) in &group0 {
-            if data.is_malicious {
-                attack.split(prefix, prefix0, *name, data);
-            }
-        }
-        for (name, data) in &group1 {
-            if data.is_malicious {
-                attack.split(prefix, prefix1, *name, data);
-            }
+        // The attacker hears of its nodes in order of name (those of group 0 first), since a
+        // strategy may follow whichever it hears of first
+        let mut malicious: Vec<(NodeName, NodeData)> = group0.iter()
+            .chain(group1.iter())
+            .filter(|&(_, data)| data.is_malicious)
+            .map(|(name, data)| (*name, *data))
+            .collect();
+        malicious.sort_by_key(|&(name, _)| name);
+        for (name, data) in malicious {
+            let new_prefix = if prefix0.matches(name) { prefix0 } else { prefix1 };
+            attack.split(prefix, new_prefix, name, &data);
         }
         let inserted = self.groups.insert(prefix0, group0).is_none();
         assert!(inserted);
         let inserted = self.groups.insert(prefix1, group1).is_none();
         assert!(inserted);
+        self.touched.extend(&[prefix, prefix0, prefix1]);
+        record(&mut self.events, Event::Split(prefix));
         Ok((prefix0, prefix1))
     }
 
@@ -439,22 +943,70 @@ impl<AR: AddRestriction> Network<AR> {
     ///
     /// The simulation driver chooses when
     /// to trigger this. What we do is (1) age each node by 1, (2) pick the oldest node
-    /// whose age is a power of 2 (there may be none) and relocate it.
-    /// On relocation, the node is returned (the driver should call add_node with it).
-    pub fn churn(&mut self, prefix: Prefix, new_node: NodeName) -> Option<(NodeName, NodeData)> {
+    /// whose age is a power of 2 (there may be none) and relocate it. Nodes which have aged
+    /// and are down (with probability one minus their uptime) miss the churn.
+    /// On relocation, the node is returned with its new name (the driver should call add_node
+    /// with it).
+    ///
+    /// Under `ChurnModel::Poisson` each honest member which has aged counts a Poisson number
+    /// of churns (mean its uptime) instead of at most one; at most one node is still
+    /// relocated.
+    ///
+    /// Random numbers are drawn from `rng`, visiting nodes in order of name, so that the
+    /// result does not depend on the order of the group's hash map.
+    pub fn churn<R: Rng>(&mut self,
+                         prefix: Prefix,
+                         new_node: NodeName,
+                         rng: &mut R)
+                         -> Option<(NodeName, NodeData)> {
+        let node_data = match self.churn_group(prefix, new_node, rng) {
+            Some(data) => data,
+            None => return None,
+        };
+        Some((self.relocation_name(prefix, rng), node_data))
+    }
+
+    // Do the churn event on the group. Returns the data of any node removed for relocation.
+    fn churn_group<R: Rng>(&mut self,
+                           prefix: Prefix,
+                           new_node: NodeName,
+                           rng: &mut R)
+                           -> Option<NodeData> {
+        self.touched.insert(prefix);
+        let churn_model = self.churn_model;
+        let ageing = self.ageing;
         let mut group = self.groups.get_mut(&prefix).expect("churn called with invalid group");
+        let mut names: Vec<NodeName> = group.keys().cloned().filter(|n| *n != new_node).collect();
+        names.sort();
         // Increment churn counters and see if any is ready to be relocated.
         let mut to_relocate: Option<(NodeName, u32)> = None;
-        for (node_name, ref mut node_data) in group.iter_mut() {
-            if *node_name == new_node {
-                continue;   // skip this node
+        let mut most_times = 0;
+        for node_name in names {
+            let node_data = group.get_mut(&node_name).expect("have node");
+            if node_data.failed {
+                continue;   // node is down until removed
+            }
+            // A node which has not yet aged has just done proof-of-work, so is up. (Otherwise
+            // it could block the addition of other young nodes indefinitely.) Malicious nodes
+            // are always up.
+            let times = if node_data.is_malicious || node_data.age == 0 {
+                1
+            } else {
+                churn_model.events(node_data.uptime, rng)
+            };
+            most_times = max(most_times, times);
+            let mut can_age = false;
+            for _ in 0..times {
+                let ready = node_data.churn_and_can_age(ageing);
+                can_age = can_age || ready;
             }
-            if node_data.churn_and_can_age() {
+            if can_age {
                 if to_relocate.map_or(true, |n| node_data.churns > n.1) {
-                    to_relocate = Some((*node_name, node_data.churns));
+                    to_relocate = Some((node_name, node_data.churns));
                 }
             }
         }
+        self.churns += most_times;
         let to_relocate = match to_relocate {
             Some(r) => r.0,
             None => return None,
@@ -469,11 +1021,29 @@ impl<AR: AddRestriction> Network<AR> {
 
         // Remove node, age and return:
         let mut node_data = group.remove(&to_relocate).expect("have node");
+        record(&mut self.departures, (to_relocate, node_data));
         node_data.age += 1;
+        record(&mut self.events, Event::Relocated(prefix, to_relocate));
         trace!("Relocating a node with age {} and churns {}",
                node_data.age,
                node_data.churns);
-        Some((new_node_name(), node_data))
+        Some(node_data)
+    }
+
+    // Get a new name for a node relocated from the group with prefix `from`. Balanced
+    // relocation only picks this group if it is the only one.
+    fn relocation_name<R: Rng>(&self, from: Prefix, rng: &mut R) -> NodeName {
+        match self.relocation {
+            RelocationTarget::Random => new_node_name(rng),
+            RelocationTarget::Balanced => {
+                let prefix = self.groups
+                    .iter()
+                    .filter(|&(prefix, _)| *prefix != from)
+                    .min_by_key(|&(prefix, group)| (group.len(), prefix.bit_count, prefix.name))
+                    .map_or(from, |(prefix, _)| *prefix);
+                prefix.random_name(rng)
+            }
+        }
     }
 
     fn min_new_group_size(&self) -> usize {
@@ -481,3 +1051,110 @@ impl<AR: AddRestriction> Network<AR> {
         self.min_group_size + 1
     }
 }
+
+#[test]
+fn test_poisson_churn_rate() {
+    // Members too old to be relocated, so that every churn event reaches all of them
+    use rand::{SeedableRng, XorShiftRng};
+
+    let (members, events): (u32, u32) = (20, 2000);
+    let mut rng = XorShiftRng::from_seed([5, 6, 7, 8]);
+    let mut churns_per_event = |model: ChurnModel| {
+        let mut net = Network::<NoAddRestriction>::new(10);
+        net.set_churn_model(model);
+        for _ in 0..members {
+            let mut data = NodeData::new();
+            data.age = 30;
+            assert!(net.add_node(new_node_name(&mut rng), data).is_ok());
+        }
+        let prefix = Prefix::new(0, 0);
+        for _ in 0..events {
+            let name = new_node_name(&mut rng);
+            assert!(net.churn(prefix, name, &mut rng).is_none());
+        }
+        let churns = net.groups()[&prefix].values().map(|data| data.churns).sum::<u32>();
+        churns as f64 / (members * events) as f64
+    };
+    assert_eq!(churns_per_event(ChurnModel::Fixed), 1.0);
+    // The same mean as the fixed model, within four standard errors
+    let mean = churns_per_event(ChurnModel::Poisson);
+    assert!((mean - 1.0).abs() <= 4.0 / ((members * events) as f64).sqrt(),
+            "mean {}",
+            mean);
+}
+
+#[cfg(test)]
+fn group_size_variance(target: RelocationTarget) -> f64 {
+    use super::quorum::UntargettedAttack;
+    use rand::thread_rng;
+
+    let mut rng = thread_rng();
+    let mut net = Network::<RestrictOnePerAge>::new(10);
+    net.set_relocation_target(target);
+    let mut to_add: Vec<Node> =
+        (0..1000).map(|_| (new_node_name(&mut rng), NodeData::new())).collect();
+    while let Some((name, data)) = to_add.pop() {
+        match net.add_node(name, data) {
+            Ok(prefix) => {
+                let prefix = net.maybe_split(prefix, name, &mut UntargettedAttack);
+                if let Some(node) = net.churn(prefix, name, &mut rng) {
+                    to_add.push(node);
+                }
+            }
+            Err(_) => to_add.push((new_node_name(&mut rng), NodeData::new())),
+        }
+    }
+    let n = net.groups().len() as f64;
+    let mean = net.groups().values().map(|g| g.len() as f64).sum::<f64>() / n;
+    net.groups().values().map(|g| (g.len() as f64 - mean).powi(2)).sum::<f64>() / n
+}
+
+#[test]
+fn test_balanced_relocation_variance() {
+    // Names are not seeded, so average over a few networks to avoid flakiness.
+    let random = (0..4).map(|_| group_size_variance(RelocationTarget::Random)).sum::<f64>();
+    let balanced = (0..4).map(|_| group_size_variance(RelocationTarget::Balanced)).sum::<f64>();
+    assert!(balanced < random,
+            "variance balanced: {}, random: {}",
+            balanced,
+            random);
+}
+
+#[test]
+fn test_pinned_target_follows_splits() {
+    use super::quorum::{AttackProgress, SimpleTargettedAttack};
+
+    // Names beginning with the given bits
+    let name = |bits: &str| bits.parse::<Prefix>().expect("prefix").name | bits.len() as NN;
+    let progress = AttackProgress {
+        step: 0,
+        max_steps: 1,
+        available: 0,
+        disrupted: false,
+        all_disrupted: false,
+    };
+    let mut attack = SimpleTargettedAttack::new();
+    attack.set_target("10".parse().expect("prefix"));
+
+    let mut net = Network::<NoAddRestriction>::new(1);
+    for bits in &["00", "01", "10", "11"] {
+        assert!(net.add_node(name(bits), NodeData::new_malicious()).is_ok());
+    }
+    let node = (name("00"), NodeData::new_malicious());
+    assert!(!attack.reset_node(&node, Prefix::new(0, 0), &progress));
+
+    // The target must follow the half containing 10, even though nodes in the
+    // other half are seen first.
+    let (p0, p1) = net.do_split(Prefix::new(0, 0), &mut attack)
+        .unwrap_or_else(|e| panic!("split: {}", e));
+    assert!(attack.reset_node(&node, p0, &progress));
+    assert!(!attack.reset_node(&node, p1, &progress));
+
+    for bits in &["100", "110"] {
+        assert!(net.add_node(name(bits), NodeData::new_malicious()).is_ok());
+    }
+    let (p10, p11) = net.do_split(p1, &mut attack).unwrap_or_else(|e| panic!("split: {}", e));
+    assert_eq!(p10.to_string(), "10");
+    assert!(!attack.reset_node(&node, p10, &progress));
+    assert!(attack.reset_node(&node, p11, &progress));
+}
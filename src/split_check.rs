@@ -0,0 +1,233 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Stability of estimates across the repetitions of a simulation
+//!
+//! With `--split-check K` the repetitions of each parameter set are split into
+//! K folds of consecutive repetitions, and P(compromise) is estimated from
+//! each. Repetitions are independent, so the folds should agree to within
+//! sampling error; a dependence on the repetition (a seeding mistake, or state
+//! leaking from one repetition into the next) shows as folds which differ,
+//! typically drifting from the first to the last. Agreement is tested with the
+//! chi-square test of homogeneity of the table of compromised and other runs
+//! of each fold.
+
+use super::{NN, RR};
+
+
+/// P-value below which the folds are flagged as disagreeing. Small, since a
+/// sweep tests many parameter sets and true leakage tends to be blatant.
+pub const FLAG_P_VALUE: RR = 0.001;
+
+/// The estimates of the folds of a parameter set's repetitions, and whether
+/// they agree
+#[derive(Clone, Debug, PartialEq)]
+pub struct SplitCheck {
+    /// Runs and compromises `(runs, compromises)` of each fold, in order of
+    /// repetition. Folds differ in size by at most one run.
+    pub folds: Vec<(NN, NN)>,
+    /// Largest difference of P(compromise) between two folds
+    pub max_difference: RR,
+    /// P-value of the chi-square test of homogeneity of the folds (1 if no
+    /// run or every run was compromised)
+    pub p_value: RR,
+}
+
+impl SplitCheck {
+    /// Check the outcomes (whether each repetition, in order, ended in
+    /// compromise) split into `k` folds. With fewer repetitions than folds,
+    /// each repetition is a fold.
+    pub fn new(outcomes: &[bool], k: NN) -> Self {
+        assert!(k >= 2);
+        let n = outcomes.len();
+        let k = k as usize;
+        let folds: Vec<(NN, NN)> = (0..k)
+            .map(|j| &outcomes[j * n / k..(j + 1) * n / k])
+            .filter(|fold| !fold.is_empty())
+            .map(|fold| (fold.len() as NN, fold.iter().filter(|&&c| c).count() as NN))
+            .collect();
+        let probs: Vec<RR> = folds.iter().map(|&(runs, c)| c as RR / runs as RR).collect();
+        let max_difference = match (probs.iter().cloned().fold(None, min_rr),
+                                    probs.iter().cloned().fold(None, max_rr)) {
+            (Some(low), Some(high)) => high - low,
+            _ => 0.0,
+        };
+        let (statistic, df) = chi_square_homogeneity(&folds);
+        SplitCheck {
+            folds: folds,
+            max_difference: max_difference,
+            p_value: if df == 0 { 1.0 } else { chi_square_sf(statistic, df) },
+        }
+    }
+
+    /// True if the folds disagree (see `FLAG_P_VALUE`)
+    pub fn flagged(&self) -> bool {
+        self.p_value < FLAG_P_VALUE
+    }
+}
+
+fn min_rr(acc: Option<RR>, x: RR) -> Option<RR> {
+    Some(acc.map_or(x, |acc| acc.min(x)))
+}
+
+fn max_rr(acc: Option<RR>, x: RR) -> Option<RR> {
+    Some(acc.map_or(x, |acc| acc.max(x)))
+}
+
+// Pearson's statistic of the table of compromised and other runs of each fold `(runs,
+// compromises)`, and its degrees of freedom. With one fold, or no or every run compromised, there
+// is nothing to test: the degrees of freedom are then 0.
+fn chi_square_homogeneity(folds: &[(NN, NN)]) -> (RR, NN) {
+    let runs: NN = folds.iter().map(|&(runs, _)| runs).sum();
+    let compromises: NN = folds.iter().map(|&(_, c)| c).sum();
+    if folds.len() < 2 || compromises == 0 || compromises == runs {
+        return (0.0, 0);
+    }
+    let p = compromises as RR / runs as RR;
+    let statistic = folds.iter()
+        .map(|&(runs, c)| {
+            let (expected_c, expected_other) = (runs as RR * p, runs as RR * (1.0 - p));
+            (c as RR - expected_c).powi(2) / expected_c +
+            ((runs - c) as RR - expected_other).powi(2) / expected_other
+        })
+        .sum();
+    (statistic, folds.len() as NN - 1)
+}
+
+/// Probability that a chi-square variable with `df` degrees of freedom is at
+/// least `x` (the upper tail)
+pub fn chi_square_sf(x: RR, df: NN) -> RR {
+    if x <= 0.0 {
+        1.0
+    } else {
+        gamma_q(df as RR / 2.0, x / 2.0)
+    }
+}
+
+// Regularised upper incomplete gamma function Q(a, x), by its series for x < a + 1 and by its
+// continued fraction otherwise (each converges quickly there).
+fn gamma_q(a: RR, x: RR) -> RR {
+    const EPSILON: RR = 1e-15;
+    const MAX_TERMS: usize = 1000;
+    let log_prefactor = a * x.ln() - x - ln_gamma(a);
+    if x < a + 1.0 {
+        let (mut term, mut sum, mut denom) = (1.0 / a, 1.0 / a, a);
+        for _ in 0..MAX_TERMS {
+            denom += 1.0;
+            term *= x / denom;
+            sum += term;
+            if term.abs() < sum.abs() * EPSILON {
+                break;
+            }
+        }
+        1.0 - sum * log_prefactor.exp()
+    } else {
+        // Modified Lentz's method
+        let tiny = 1e-300;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for i in 1..MAX_TERMS {
+            let an = -(i as RR) * (i as RR - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < EPSILON {
+                break;
+            }
+        }
+        h * log_prefactor.exp()
+    }
+}
+
+// ln Γ(x) for x > 0, by the Lanczos approximation (g = 7, 9 terms; accurate to about 15
+// significant digits).
+fn ln_gamma(x: RR) -> RR {
+    const COEFFS: [RR; 9] = [0.99999999999980993,
+                             676.5203681218851,
+                             -1259.1392167224028,
+                             771.32342877765313,
+                             -176.61502916214059,
+                             12.507343278686905,
+                             -0.13857109526572012,
+                             9.9843695780195716e-6,
+                             1.5056327351493116e-7];
+    if x < 0.5 {
+        // Reflection: Γ(x) Γ(1 - x) = π / sin(π x)
+        let pi = ::std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let series = COEFFS.iter()
+        .skip(1)
+        .enumerate()
+        .fold(COEFFS[0], |sum, (i, c)| sum + c / (x + i as RR + 1.0));
+    0.5 * (2.0 * ::std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+}
+
+#[test]
+fn test_chi_square_sf() {
+    // Critical values at 5% and 0.1%
+    for &(x, df, p) in &[(3.841459, 1, 0.05),
+                         (5.991465, 2, 0.05),
+                         (9.487729, 4, 0.05),
+                         (10.827566, 1, 0.001),
+                         (29.588298, 10, 0.001)] {
+        assert!((chi_square_sf(x, df) - p).abs() < 1e-6,
+                "x {}, df {}: {}",
+                x,
+                df,
+                chi_square_sf(x, df));
+    }
+    assert_eq!(chi_square_sf(0.0, 3), 1.0);
+    // With two degrees of freedom the tail is exp(-x / 2)
+    assert!((chi_square_sf(1.0, 2) - (-0.5 as RR).exp()).abs() < 1e-12);
+    assert!((ln_gamma(5.0) - (24.0 as RR).ln()).abs() < 1e-12);
+}
+
+#[test]
+fn test_split_check() {
+    // Outcomes spread evenly agree; those drifting from first to last do not
+    let even: Vec<bool> = (0..40).map(|i| i % 4 == 0).collect();
+    let check = SplitCheck::new(&even, 4);
+    assert_eq!(check.folds, vec![(10, 3), (10, 2), (10, 3), (10, 2)]);
+    assert!((check.max_difference - 0.1).abs() < 1e-12);
+    assert!(!check.flagged());
+
+    let drifting: Vec<bool> = (0..40).map(|i| i >= 25).collect();
+    let check = SplitCheck::new(&drifting, 2);
+    assert_eq!(check.folds, vec![(20, 0), (20, 15)]);
+    assert_eq!(check.max_difference, 0.75);
+    assert!(check.flagged(), "p-value {}", check.p_value);
+
+    // Uneven folds, more folds than runs, and nothing to test
+    assert_eq!(SplitCheck::new(&even[..7], 3).folds, vec![(2, 1), (2, 0), (3, 1)]);
+    assert_eq!(SplitCheck::new(&even[..2], 5).folds.len(), 2);
+    let none = SplitCheck::new(&[false; 10], 2);
+    assert_eq!((none.max_difference, none.p_value), (0.0, 1.0));
+}
@@ -0,0 +1,287 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Long-run steady state of the full simulation
+//!
+//! With `--steady-state BURN,B` runs do not stop at compromise. After a
+//! burn-in of BURN steps, each step is counted as compromised (or disrupted)
+//! if some group is held so (see `--hold`), giving the proportion of time the
+//! network spends compromised with a persistent attacker, rather than the
+//! chance of compromise within the steps simulated. The measured steps are
+//! split into B batches of consecutive steps; the batch means (over the steps
+//! of the batch in every run) are close to independent once batches are long
+//! compared to the time the network takes to forget its state, so their
+//! spread gives a confidence interval, even from a single long run. Batch
+//! means which rise or fall throughout suggest the burn-in is too short for
+//! the network to settle. With several runs, which are independent, the
+//! spread of their proportions gives a second interval, which does not rely
+//! on the batches being long enough.
+
+use super::{NN, RR};
+use super::baseline::Z_CRITICAL;
+
+use std::f64::INFINITY;
+use std::str::FromStr;
+
+
+/// Number of batches if not given
+pub const DEFAULT_BATCHES: NN = 10;
+
+/// Fewest batches: two give a variance, and a third tells a trend from noise
+pub const MIN_BATCHES: NN = 3;
+
+/// Measurement of the steady state: the steps of burn-in and the number of
+/// batches the remaining steps are split into. Parsed from `BURN[,B]`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SteadyState {
+    pub burn_in: NN,
+    pub batches: NN,
+}
+
+impl SteadyState {
+    /// Steps in each batch of runs of `max_steps` steps. Batches are of equal
+    /// length; any steps left over lengthen the burn-in.
+    pub fn batch_steps(&self, max_steps: NN) -> NN {
+        max_steps.saturating_sub(self.burn_in) / self.batches
+    }
+
+    /// The batch measuring `step` of runs of `max_steps` steps, if any
+    pub fn batch(&self, step: NN, max_steps: NN) -> Option<usize> {
+        let len = self.batch_steps(max_steps);
+        let start = max_steps - len * self.batches;
+        if len == 0 || step < start || step >= max_steps {
+            None
+        } else {
+            Some(((step - start) / len) as usize)
+        }
+    }
+}
+
+impl FromStr for SteadyState {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.trim().split(',').map(|part| part.trim()).collect();
+        if parts.len() > 2 {
+            return Err(format!("expected BURN[,B], found '{}'", s.trim()));
+        }
+        let mut values = [0, DEFAULT_BATCHES];
+        for (value, part) in values.iter_mut().zip(&parts) {
+            *value = try!(part.parse::<NN>().map_err(|_| format!("bad count '{}'", part)));
+        }
+        if values[1] < MIN_BATCHES {
+            return Err(format!("at least {} batches are needed", MIN_BATCHES));
+        }
+        Ok(SteadyState {
+            burn_in: values[0],
+            batches: values[1],
+        })
+    }
+}
+
+/// The steady state of the full simulation (see the module documentation).
+/// Confidence intervals are two-sided at the 95% level, from Student's t
+/// with one degree of freedom fewer than the batches (or the runs).
+#[derive(Clone, Debug, PartialEq)]
+pub struct SteadyStateReport {
+    /// Proportion of measured steps in which a compromise was held
+    pub compromised: RR,
+    /// Half-width of its confidence interval, from the batch means
+    pub compromised_ci: RR,
+    /// Half-width of its confidence interval from the spread between runs,
+    /// if there are several
+    pub compromised_runs_ci: Option<RR>,
+    /// Proportion of measured steps in which a disruption was held
+    pub disrupted: RR,
+    /// Half-width of its confidence interval, from the batch means
+    pub disrupted_ci: RR,
+    /// Half-width of its confidence interval from the spread between runs,
+    /// if there are several
+    pub disrupted_runs_ci: Option<RR>,
+    /// Whether the batch means of either rise (or fall) from each batch to
+    /// the next, suggesting the burn-in was too short
+    pub trending: bool,
+}
+
+impl SteadyStateReport {
+    /// The report from the compromised and disrupted steps `(compromised,
+    /// disrupted)` of each batch, summed over `runs` runs, the sums over the
+    /// runs of each run's measured compromised steps and their squares, then
+    /// disrupted steps and their squares, and the steps in each batch of a run.
+    pub fn new(sums: &[(NN, NN)],
+               run_sums: (NN, NN, NN, NN),
+               runs: NN,
+               batch_steps: NN)
+               -> Self {
+        let steps = (runs * batch_steps) as RR;
+        let compromised: Vec<RR> = sums.iter().map(|&(c, _)| c as RR / steps).collect();
+        let disrupted: Vec<RR> = sums.iter().map(|&(_, d)| d as RR / steps).collect();
+        let (compromised_mean, compromised_ci) = batch_means(&compromised);
+        let (disrupted_mean, disrupted_ci) = batch_means(&disrupted);
+        let run_steps = batch_steps * sums.len() as NN;
+        let runs_ci = |sum, sum_sq| if runs > 1 {
+            Some(run_means(sum, sum_sq, runs, run_steps).1)
+        } else {
+            None
+        };
+        SteadyStateReport {
+            compromised: compromised_mean,
+            compromised_ci: compromised_ci,
+            compromised_runs_ci: runs_ci(run_sums.0, run_sums.1),
+            disrupted: disrupted_mean,
+            disrupted_ci: disrupted_ci,
+            disrupted_runs_ci: runs_ci(run_sums.2, run_sums.3),
+            trending: monotonic(&compromised) || monotonic(&disrupted),
+        }
+    }
+}
+
+/// The mean of `means` (of batches of equal length) and the half-width of
+/// its confidence interval. Zero and zero without batches; with one, the
+/// interval is unbounded.
+pub fn batch_means(means: &[RR]) -> (RR, RR) {
+    let b = means.len();
+    if b == 0 {
+        return (0.0, 0.0);
+    }
+    let mean = means.iter().sum::<RR>() / b as RR;
+    if b == 1 {
+        return (mean, INFINITY);
+    }
+    let var = means.iter().map(|x| (x - mean) * (x - mean)).sum::<RR>() / (b - 1) as RR;
+    (mean, t_critical(b as NN - 1) * (var / b as RR).sqrt())
+}
+
+/// The mean proportion of `steps` steps counted over `runs` runs, given the
+/// sum of the counts and of their squares, and the half-width of its
+/// confidence interval. Zero and zero without runs or steps; with one run,
+/// the interval is unbounded.
+pub fn run_means(sum: NN, sum_sq: NN, runs: NN, steps: NN) -> (RR, RR) {
+    if runs == 0 || steps == 0 {
+        return (0.0, 0.0);
+    }
+    let (r, n) = (runs as RR, steps as RR);
+    let mean = sum as RR / (r * n);
+    if runs == 1 {
+        return (mean, INFINITY);
+    }
+    // Sample variance of the runs' proportions, clamped against rounding
+    let var = ((sum_sq as RR - sum as RR * sum as RR / r) / (r - 1.0) / (n * n)).max(0.0);
+    (mean, t_critical(runs - 1) * (var / r).sqrt())
+}
+
+/// Whether `means` strictly rise or strictly fall throughout (never for
+/// fewer than `MIN_BATCHES`)
+pub fn monotonic(means: &[RR]) -> bool {
+    means.len() >= MIN_BATCHES as usize &&
+    (means.windows(2).all(|w| w[1] > w[0]) || means.windows(2).all(|w| w[1] < w[0]))
+}
+
+// Two-sided 95% critical value of Student's t with `df` degrees of freedom (tabulated to 30,
+// then that of the normal distribution)
+fn t_critical(df: NN) -> RR {
+    const TABLE: [RR; 30] = [12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262,
+                             2.228, 2.201, 2.179, 2.160, 2.145, 2.131, 2.120, 2.110, 2.101,
+                             2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056, 2.052,
+                             2.048, 2.045, 2.042];
+    match df {
+        0 => INFINITY,
+        df if df <= 30 => TABLE[df as usize - 1],
+        _ => Z_CRITICAL,
+    }
+}
+
+#[test]
+fn test_steady_state() {
+    assert_eq!("100".parse(),
+               Ok(SteadyState {
+                   burn_in: 100,
+                   batches: DEFAULT_BATCHES,
+               }));
+    let steady: SteadyState = "100, 4".parse().expect("parse");
+    assert_eq!(steady,
+               SteadyState {
+                   burn_in: 100,
+                   batches: 4,
+               });
+    assert_eq!("100,2".parse::<SteadyState>(),
+               Err("at least 3 batches are needed".to_owned()));
+    assert!("100,4,1".parse::<SteadyState>().is_err());
+    assert!("x".parse::<SteadyState>().is_err());
+
+    // 402 steps: 100 of burn-in, 2 left over, then 4 batches of 75
+    assert_eq!(steady.batch_steps(402), 75);
+    assert_eq!(steady.batch(101, 402), None);
+    assert_eq!(steady.batch(102, 402), Some(0));
+    assert_eq!(steady.batch(176, 402), Some(0));
+    assert_eq!(steady.batch(177, 402), Some(1));
+    assert_eq!(steady.batch(401, 402), Some(3));
+    assert_eq!(steady.batch(402, 402), None);
+    // Too few steps to measure any
+    assert_eq!(steady.batch_steps(103), 0);
+    assert_eq!(steady.batch(102, 103), None);
+}
+
+#[test]
+fn test_batch_means() {
+    assert_eq!(batch_means(&[]), (0.0, 0.0));
+    assert_eq!(batch_means(&[0.5]), (0.5, INFINITY));
+    assert_eq!(batch_means(&[0.25, 0.25, 0.25]), (0.25, 0.0));
+    // Variance 1/3 over 4 batches: a standard error of 1/sqrt(12)
+    let (mean, ci) = batch_means(&[0.0, 1.0, 0.0, 1.0]);
+    assert_eq!(mean, 0.5);
+    assert!((ci - 3.182 / 12.0f64.sqrt()).abs() < 1e-12);
+}
+
+#[test]
+fn test_run_means() {
+    assert_eq!(run_means(0, 0, 0, 10), (0.0, 0.0));
+    assert_eq!(run_means(5, 25, 1, 10), (0.5, INFINITY));
+    // Three runs of 4 steps, each compromised in 1
+    assert_eq!(run_means(3, 3, 3, 4), (0.25, 0.0));
+    // Four runs of 10 steps, compromised in 0, 10, 0 and 10: proportions of variance 1/3, so
+    // a standard error of 1/sqrt(12)
+    let (mean, ci) = run_means(20, 200, 4, 10);
+    assert_eq!(mean, 0.5);
+    assert!((ci - 3.182 / 12.0f64.sqrt()).abs() < 1e-12);
+    assert_eq!(t_critical(100), Z_CRITICAL);
+
+    assert!(monotonic(&[0.1, 0.2, 0.3]));
+    assert!(monotonic(&[0.3, 0.2, 0.1]));
+    assert!(!monotonic(&[0.1, 0.3, 0.2]));
+    assert!(!monotonic(&[0.2, 0.2, 0.2]));
+    assert!(!monotonic(&[0.1, 0.2]));
+
+    // 2 runs of 4 batches of 10 steps, compromised in 5 and 15 steps and each disrupted in 20
+    let report = SteadyStateReport::new(&[(4, 10), (6, 10), (4, 10), (6, 10)],
+                                        (20, 250, 40, 800),
+                                        2,
+                                        10);
+    assert!((report.compromised - 0.25).abs() < 1e-12);
+    // Batch means 0.2, 0.3, 0.2 and 0.3: variance 1/300, so a standard error of 1/sqrt(1200)
+    assert!((report.compromised_ci - 3.182 / 1200.0f64.sqrt()).abs() < 1e-12);
+    // Run proportions 1/8 and 3/8: a standard error of 1/8
+    let runs_ci = report.compromised_runs_ci.expect("two runs");
+    assert!((runs_ci - 12.706 / 8.0).abs() < 1e-12);
+    assert_eq!((report.disrupted, report.disrupted_ci, report.disrupted_runs_ci),
+               (0.5, 0.0, Some(0.0)));
+    assert!(!report.trending);
+    // A single run still has an interval from its batches, though not from the runs
+    let single = SteadyStateReport::new(&[(0, 1), (0, 2), (0, 3)], (0, 0, 6, 36), 1, 5);
+    assert!(single.trending);
+    assert!(single.disrupted_ci.is_finite() && single.disrupted_ci > 0.0);
+    assert_eq!((single.compromised_ci, single.disrupted_runs_ci), (0.0, None));
+}
@@ -0,0 +1,126 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Sharing the runs of the full simulation between parameter sets which differ
+//! only in the quorum size. Unless the attacker looks at which groups are
+//! disrupted, the quorum only decides when a run stops, so a sweep of `-q`
+//! need only simulate each repetition once and judge it under every size.
+
+use super::args::SimParams;
+use super::combo::serialise;
+use super::tools::SimResult;
+
+use std::collections::HashMap;
+
+
+/// Groups of the indices of `param_sets` which may share their runs: sets
+/// not `skipped` and `sweepable`, identical but for the quorum size (and as
+/// many repetitions, seeded alike). Only groups of two or more sets are given,
+/// each in order and ordered by their first set.
+pub fn quorum_sweeps(param_sets: &[SimParams], skipped: &[bool]) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = vec![];
+    let mut by_key: HashMap<String, usize> = HashMap::new();
+    for (i, params) in param_sets.iter().enumerate() {
+        if skipped[i] || !params.sweepable() {
+            continue;
+        }
+        let key = sweep_key(params);
+        if let Some(&group) = by_key.get(&key) {
+            groups[group].push(i);
+        } else {
+            by_key.insert(key, groups.len());
+            groups.push(vec![i]);
+        }
+    }
+    groups.retain(|group| group.len() > 1);
+    groups
+}
+
+/// Results of the sets of `group` (as from `quorum_sweeps`), in order, from
+/// one run of each repetition. With the same master seed, these are the results
+/// of running each set alone.
+pub fn sweep_results(param_sets: &[SimParams], group: &[usize]) -> Vec<SimResult> {
+    let sizes: Vec<_> = group[1..].iter().map(|&i| param_sets[i].quorum).collect();
+    param_sets[group[0]].sweep_tool(&sizes).calc_sweep()
+}
+
+// Everything deciding a set's runs but its quorum size
+fn sweep_key(params: &SimParams) -> String {
+    let fields: String = serialise(params)
+        .lines()
+        .filter(|line| !line.starts_with("quorum_size="))
+        .map(|line| format!("{}\n", line))
+        .collect();
+    format!("{}repetitions={}\nseeding={:?}\nmaster_seed={:?}\ntermination={}\nparanoid={:?}\n\
+             verify={}\n",
+            fields,
+            params.repetitions,
+            params.seeding,
+            params.master_seed,
+            params.termination,
+            params.paranoid,
+            params.verify)
+}
+
+#[test]
+fn test_quorum_sweeps() {
+    use super::args::sim_params;
+
+    let sweeps = |args: &[&str]| {
+        let sets = sim_params(args);
+        quorum_sweeps(&sets, &vec![false; sets.len()])
+    };
+    // Only the quorum size is swept
+    assert_eq!(sweeps(&["full", "-q", "0.4-0.6:0.1"]), vec![vec![0, 1, 2]]);
+    // With another parameter swept too, each of its values is a group
+    assert_eq!(sweeps(&["full", "-n", "100,200", "-q", "0.4,0.5"]),
+               vec![vec![0, 2], vec![1, 3]]);
+    // Nothing to share
+    assert!(sweeps(&["full", "-n", "100,200"]).is_empty());
+    assert!(sweeps(&["structure", "-q", "0.4,0.5"]).is_empty());
+    // The attacker looks at the quorum under the disruption objective
+    assert!(sweeps(&["full", "-T", "simple", "--objective", "disruption", "-q", "0.4,0.5"])
+        .is_empty());
+    assert_eq!(sweeps(&["full", "-T", "simple", "-q", "0.4,0.5"]), vec![vec![0, 1]]);
+    // Reports looking at the network are per quorum
+    assert!(sweeps(&["full", "--report", "spread", "-q", "0.4,0.5"]).is_empty());
+    assert_eq!(sweeps(&["full", "--report", "termination", "-q", "0.4,0.5"]),
+               vec![vec![0, 1]]);
+    // Skipped sets are left out
+    let sets = sim_params(&["full", "-q", "0.4-0.6:0.1"]);
+    assert_eq!(quorum_sweeps(&sets, &[false, true, false]), vec![vec![0, 2]]);
+    assert!(quorum_sweeps(&sets, &[false, true, true]).is_empty());
+}
+
+#[test]
+fn test_sweep_results() {
+    use super::args::sim_params;
+
+    // Under a common master seed, the shared runs give the results of running each set alone
+    let sets = sim_params(&["full", "-n", "200", "-r", "50", "-k", "8", "-s", "100", "-p", "10",
+                            "-q", "0.3-0.6:0.1", "--seed", "7", "--report", "termination"]);
+    let sweeps = quorum_sweeps(&sets, &vec![false; sets.len()]);
+    assert_eq!(sweeps, vec![vec![0, 1, 2, 3]]);
+    let results = sweep_results(&sets, &sweeps[0]);
+    assert_eq!(results.len(), 4);
+    for (params, result) in sets.iter().zip(&results) {
+        let alone = params.result();
+        assert_eq!((result.p_disrupt, result.p_compromise, result.groups, result.runs),
+                   (alone.p_disrupt, alone.p_compromise, alone.groups, alone.runs));
+        assert_eq!(result.termination, alone.termination);
+    }
+}
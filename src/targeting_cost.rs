@@ -0,0 +1,411 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Expected cost for a targetting attacker to get a node of a given age into
+//! one group (see `routing-sims targeting-cost`)
+//!
+//! One malicious node is followed under the assumptions of the full simulation
+//! with simple targetting: a joining node lands in a random group and is reset
+//! (to join again at once) unless in the target; it ages on the relocation
+//! following its 1st, 2nd, 4th, ... churn event (see `sim::churns_to_age`); and
+//! once relocated out of the target it stays where it lands until next
+//! relocated (the strategy deciding by the group left), then is reset,
+//! rejoining with the age it recovers of its identity's full age (see
+//! `tools::recovered_age`), which the identity keeps. The target is one of `G`
+//! groups of equal share of the name space, each seeing a fixed number of churn
+//! events per step. Relocation is never delayed, by other nodes waiting or for
+//! lack of members, and ageing restrictions on joining are ignored.
+
+use super::{NN, RR};
+use super::args::SamplePoints;
+use super::sim::{RelocationTarget, PowScaling, churns_to_age};
+use super::tools::recovered_age;
+
+use docopt::{self, Docopt};
+use std::cmp::max;
+use std::collections::VecDeque;
+
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const USAGE: &'static str = "
+Expected joins and steps for a targetting attacker to get a node of a given age
+into one group. Steps are a lower bound: relocation is never delayed by other
+nodes waiting to be relocated, as it may be in the full simulation.
+
+Usage:
+    routing-sims targeting-cost [--groups RANGE] [--age RANGE] [--relocation-target TARGET] \
+     [--rejoin-age-recovery F] [--churn-rate C]
+    routing-sims targeting-cost (-h | --help)
+
+Options:
+    -h --help   Show this message
+    --groups RANGE
+                Number of groups, e.g. 16, 4-64:4 or 8,16,32 (default 16).
+    --age RANGE
+                Age the node must reach in the target group (default 4; at
+                most 30).
+    --relocation-target TARGET
+                Where relocated nodes go: random, balanced or all (default
+                random). Balanced relocation never returns a node to its group.
+    --rejoin-age-recovery F
+                Proportion of its age a reset node recovers on rejoining
+                (default 0).
+    --churn-rate C
+                Churn events in the target group per step, e.g. joins to it
+                (default 1).
+";
+
+/// Largest age asked about (the churn events to reach it must fit a `u32`)
+pub const MAX_AGE: u32 = 30;
+
+#[derive(RustcDecodable)]
+struct Args {
+    flag_groups: Option<String>,
+    flag_age: Option<String>,
+    flag_relocation_target: Option<String>,
+    flag_rejoin_age_recovery: Option<RR>,
+    flag_churn_rate: Option<RR>,
+}
+
+/// One question: the cost of reaching `age` in one of `groups` groups.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Query {
+    pub groups: NN,
+    pub age: u32,
+    pub relocation: RelocationTarget,
+    /// Proportion of its age a reset node recovers on rejoining
+    pub recovery: RR,
+    /// Churn events in the target group per step
+    pub churn_rate: RR,
+}
+
+/// Expected cost of reaching the age in the target group
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cost {
+    /// Join attempts (each new or rejoining node, whether reset or not)
+    pub joins: RR,
+    /// Steps, for proof-of-work and for churn in the target group. A lower
+    /// bound: in the full simulation a node ready to be relocated may wait
+    /// behind others, which this does not model.
+    pub steps: RR,
+}
+
+/// The queries of the arguments of `routing-sims targeting-cost` (including
+/// the program name and command), one for each combination of values.
+pub fn parse_argv(argv: &[String]) -> Result<Vec<Query>, docopt::Error> {
+    let args: Args = try!(Docopt::new(USAGE).and_then(|dopt| dopt.argv(argv).decode()));
+    let groups: SamplePoints<NN> = args.flag_groups
+        .as_ref()
+        .map_or(SamplePoints::Number(16), |s| {
+            s.parse().unwrap_or_else(|e| panic!("unexpected: --groups {} ({})", s, e))
+        });
+    let ages: SamplePoints<NN> = args.flag_age
+        .as_ref()
+        .map_or(SamplePoints::Number(4), |s| {
+            s.parse().unwrap_or_else(|e| panic!("unexpected: --age {} ({})", s, e))
+        });
+    let relocation = match args.flag_relocation_target.as_ref().map(|s| s.as_str()) {
+        None => vec![RelocationTarget::Random],
+        Some("all") => vec![RelocationTarget::Random, RelocationTarget::Balanced],
+        Some(x) => {
+            vec![RelocationTarget::from_name(x)
+                     .unwrap_or_else(|| panic!("unexpected: --relocation-target {}", x))]
+        }
+    };
+    let recovery = args.flag_rejoin_age_recovery.unwrap_or(0.0);
+    if recovery < 0.0 || recovery > 1.0 {
+        panic!("unexpected: --rejoin-age-recovery {} (must be from 0 to 1)", recovery);
+    }
+    let churn_rate = args.flag_churn_rate.unwrap_or(1.0);
+    if !(churn_rate > 0.0) {
+        panic!("unexpected: --churn-rate {} (must be positive)", churn_rate);
+    }
+
+    let mut queries = vec![];
+    for groups in groups.iter() {
+        if groups == 0 {
+            panic!("unexpected: --groups 0 (must be at least 1)");
+        }
+        for age in ages.iter() {
+            if age > MAX_AGE as NN {
+                panic!("unexpected: --age {} (must be at most {})", age, MAX_AGE);
+            }
+            for &relocation in &relocation {
+                queries.push(Query {
+                    groups: groups,
+                    age: age as u32,
+                    relocation: relocation,
+                    recovery: recovery,
+                    churn_rate: churn_rate,
+                });
+            }
+        }
+    }
+    Ok(queries)
+}
+
+// Where the attacker's node is: joining (until it lands in the target) as an identity of the
+// given age, in the target with the given age, having been relocated there, or in another group
+// with the given age, having been relocated there from the target; and in a group, the age of its
+// identity not recovered on rejoining
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    Joining(u32),
+    InTarget(u32, u32),
+    Elsewhere(u32, u32),
+}
+
+// Expected joins and steps spent in a state, then the probability of each next state (`None`
+// once the age is reached in the target)
+type Move = (RR, RR, Vec<(RR, Option<State>)>);
+
+impl Query {
+    // Probability of a joining node landing in the target
+    fn p_join(&self) -> RR {
+        1.0 / self.groups as RR
+    }
+
+    // Probability of a node relocated from the target landing in it again
+    fn p_stay(&self) -> RR {
+        match self.relocation {
+            RelocationTarget::Random => 1.0 / self.groups as RR,
+            RelocationTarget::Balanced => if self.groups == 1 { 1.0 } else { 0.0 },
+        }
+    }
+
+    // Steps for `churns` churn events in the target
+    fn churn_steps(&self, churns: u32) -> RR {
+        churns as RR / self.churn_rate
+    }
+
+    // Age of an identity which is remembered (all are the same if none is recovered, keeping the
+    // states finite)
+    fn identity(&self, age: u32) -> u32 {
+        if self.recovery > 0.0 { age } else { 0 }
+    }
+
+    // Next states after relocation from the target with age `age`, `forgone` not recovered
+    fn relocated(&self, age: u32, forgone: u32) -> Vec<(RR, Option<State>)> {
+        let stay = if age >= self.age {
+            None
+        } else {
+            Some(State::InTarget(age, forgone))
+        };
+        let elsewhere = Some(State::Elsewhere(age, forgone));
+        vec![(self.p_stay(), stay), (1.0 - self.p_stay(), elsewhere)]
+    }
+
+    fn next(&self, state: State) -> Move {
+        match state {
+            State::Joining(identity) => {
+                // Join attempts until one lands, then proof-of-work (in the step of the last
+                // attempt, since reset nodes join again at once)
+                let joins = 1.0 / self.p_join();
+                let pow = PowScaling::Constant.join_steps(0) as RR;
+                let age = recovered_age(self.recovery, identity);
+                if age >= self.age {
+                    return (joins, pow, vec![(1.0, None)]);
+                }
+                // A rejoining node starts counting churn events afresh
+                let steps = pow + self.churn_steps(churns_to_age(age));
+                (joins, steps, self.relocated(age + 1, identity - age))
+            }
+            State::InTarget(age, forgone) => {
+                let churns = churns_to_age(age) - churns_to_age(age - 1);
+                (0.0, self.churn_steps(churns), self.relocated(age + 1, forgone))
+            }
+            State::Elsewhere(age, forgone) => {
+                // The group's churn is that of the target, all having equal shares
+                let churns = churns_to_age(age) - churns_to_age(age - 1);
+                let reset = State::Joining(self.identity(age + 1 + forgone));
+                (0.0, self.churn_steps(churns), vec![(1.0, Some(reset))])
+            }
+        }
+    }
+
+    /// The expected cost, or `None` if the age can never be reached (e.g. with
+    /// balanced relocation and little age recovered).
+    pub fn cost(&self) -> Option<Cost> {
+        // States reachable from a new node, and their moves
+        let mut states = vec![State::Joining(0)];
+        let mut moves: Vec<Move> = vec![];
+        let mut queue: VecDeque<usize> = vec![0].into_iter().collect();
+        while let Some(i) = queue.pop_front() {
+            let next = self.next(states[i]);
+            for &(p, state) in &next.2 {
+                if let Some(state) = state {
+                    if p > 0.0 && !states.contains(&state) {
+                        states.push(state);
+                        queue.push_back(states.len() - 1);
+                    }
+                }
+            }
+            // States are visited in the order found
+            moves.push(next);
+        }
+        let index = |state: State| states.iter().position(|s| *s == state).expect("state");
+
+        // Every reachable state must be able to finish, else some runs never do
+        let n = states.len();
+        let mut finishes = vec![false; n];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for i in 0..n {
+                if finishes[i] {
+                    continue;
+                }
+                let finish = moves[i].2.iter().any(|&(p, state)| {
+                    p > 0.0 && state.map_or(true, |state| finishes[index(state)])
+                });
+                finishes[i] = finish;
+                changed |= finish;
+            }
+        }
+        if finishes.iter().any(|f| !f) {
+            return None;
+        }
+
+        // Expected cost x of each state: x = cost + P x, for joins and steps at once
+        let mut rows: Vec<Vec<RR>> = (0..n)
+            .map(|i| {
+                let mut row = vec![0.0; n + 2];
+                row[i] = 1.0;
+                for &(p, state) in &moves[i].2 {
+                    match state {
+                        Some(state) if p > 0.0 => row[index(state)] -= p,
+                        _ => {}
+                    }
+                }
+                row[n] = moves[i].0;
+                row[n + 1] = moves[i].1;
+                row
+            })
+            .collect();
+        let solution = solve(&mut rows);
+        Some(Cost {
+            joins: solution[0].0,
+            steps: solution[0].1,
+        })
+    }
+}
+
+// Solve the linear equations of `rows` (coefficients, then two right-hand sides) by Gaussian
+// elimination with partial pivoting, giving both solutions for each variable
+fn solve(rows: &mut [Vec<RR>]) -> Vec<(RR, RR)> {
+    let n = rows.len();
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|&a, &b| {
+                rows[a][col].abs().partial_cmp(&rows[b][col].abs()).expect("not NaN")
+            })
+            .expect("a row");
+        rows.swap(col, pivot);
+        for row in 0..n {
+            if row != col && rows[row][col] != 0.0 {
+                let factor = rows[row][col] / rows[col][col];
+                for k in col..n + 2 {
+                    rows[row][k] -= factor * rows[col][k];
+                }
+            }
+        }
+    }
+    (0..n).map(|i| (rows[i][n] / rows[i][i], rows[i][n + 1] / rows[i][i])).collect()
+}
+
+/// Table of the costs of `queries`, one line each, with a header line.
+/// Unreachable ages cost "never".
+pub fn report(queries: &[Query]) -> String {
+    let titles = ["Groups", "Age", "Relocation", "Recovery", "ChurnRate", "Joins", "Steps"];
+    let widths: Vec<usize> = titles.iter().map(|title| max(title.len(), 8)).collect();
+    let line = |row: &[String]| -> String {
+        let cells: Vec<String> = row.iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{1:<0$}", width, cell))
+            .collect();
+        cells.join(" ").trim_right().to_owned()
+    };
+    let header: Vec<String> = titles.iter().map(|title| (*title).to_owned()).collect();
+    let mut lines = vec![line(&header)];
+    for query in queries {
+        let (joins, steps) = match query.cost() {
+            Some(cost) => (format!("{:.1}", cost.joins), format!("{:.1}", cost.steps)),
+            None => ("never".to_owned(), "never".to_owned()),
+        };
+        lines.push(line(&[query.groups.to_string(),
+                          query.age.to_string(),
+                          query.relocation.name().to_owned(),
+                          query.recovery.to_string(),
+                          query.churn_rate.to_string(),
+                          joins,
+                          steps]));
+    }
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+fn query(groups: NN, age: u32, relocation: RelocationTarget, recovery: RR) -> Query {
+    Query {
+        groups: groups,
+        age: age,
+        relocation: relocation,
+        recovery: recovery,
+        churn_rate: 1.0,
+    }
+}
+
+#[test]
+fn test_cost_closed_forms() {
+    // With a single group every join lands and every relocation stays: one join, then one step
+    // of proof-of-work and 2^(age - 1) churn events
+    let cost = query(1, 4, RelocationTarget::Random, 0.0).cost().expect("reachable");
+    assert_eq!((cost.joins, cost.steps), (1.0, 1.0 + 8.0));
+    // Age 0 needs only landing: G joins
+    let cost = query(16, 0, RelocationTarget::Random, 0.0).cost().expect("reachable");
+    assert_eq!((cost.joins, cost.steps), (16.0, 1.0));
+    // Age 1 with random relocation and no recovery: land, one churn event, then stay with
+    // probability 1/G, else wait for one more churn event elsewhere and start again: G times (G
+    // joins, 2 steps), and G - 1 waits
+    let cost = query(4, 1, RelocationTarget::Random, 0.0).cost().expect("reachable");
+    assert!((cost.joins - 16.0).abs() < 1e-9 && (cost.steps - 11.0).abs() < 1e-9,
+            "{:?}",
+            cost);
+
+    // Balanced relocation never stays, so the age is only reached by rejoining with it, having
+    // recovered enough of the age gained elsewhere
+    assert_eq!(query(4, 2, RelocationTarget::Balanced, 0.0).cost(), None);
+    assert!(query(4, 2, RelocationTarget::Balanced, 0.5).cost().is_some());
+    assert!(query(4, 2, RelocationTarget::Balanced, 1.0).cost().is_some());
+    // Recovering age can only help
+    let none = query(8, 3, RelocationTarget::Random, 0.0).cost().expect("reachable");
+    let all = query(8, 3, RelocationTarget::Random, 1.0).cost().expect("reachable");
+    assert!(all.joins < none.joins, "{:?} {:?}", all, none);
+}
+
+#[test]
+fn test_parse_argv() {
+    let argv = |args: &[&str]| -> Vec<String> {
+        ["routing-sims", "targeting-cost"].iter().chain(args).map(|s| (*s).to_owned()).collect()
+    };
+    let queries = parse_argv(&argv(&[])).expect("defaults");
+    assert_eq!(queries, vec![query(16, 4, RelocationTarget::Random, 0.0)]);
+    let queries = parse_argv(&argv(&["--groups", "4,8", "--relocation-target", "all"]))
+        .expect("lists");
+    assert_eq!(queries.len(), 4);
+    assert!(parse_argv(&argv(&["--bogus"])).is_err());
+    assert_eq!(report(&queries).lines().count(), 5);
+}
@@ -19,386 +19,6307 @@
 
 
 use super::{NN, RR, ToolArgs, Error};
-use super::quorum::{Quorum, SimpleQuorum, AttackStrategy, UntargettedAttack};
-use super::prob::{prob_disruption, prob_compromise};
-use super::sim::{Network, new_node_name, NodeData, NoAddRestriction, RestrictOnePerAge};
+use super::quorum::{Quorum, QuorumSize, SimpleQuorum, AttackStrategy, AttackProgress,
+                    UntargettedAttack, Objective, Member, AgeCap, QuorumJitter, Participation,
+                    ElderTiebreak, elders_ranked, quorum_disrupted_offset,
+                    quorum_compromised_offset, max_unblocked, AttackDecision, RecordedAttack,
+                    NetworkView, AttackerKnowledge};
+use super::prob::{choose, prob_all_groups_unaffected, prob_binomial_at_least,
+                  prob_taking_part, prob_thinned};
+use super::checkpoint::{Progress, Reports};
+use super::rng::{Draws, Stream, Streams};
+use super::burst::{Burst, sample_region};
+use super::churn_model::{ChurnModel, sample_poisson};
+use super::corrupt::Corruption;
+use super::join_cap::{JoinLimiter, JoinCapMode};
+use super::baseline::Z_CRITICAL;
+use super::split_check::SplitCheck;
+use super::steady::SteadyStateReport;
+use super::close_group::CloseGroupReport;
+use super::sim::{Network, new_node_name, NodeName, NodeData, NoAddRestriction, RestrictOnePerAge,
+                 Group, Prefix, PowScaling, MAX_YOUNG_PER_AGE, AddRestriction, Ageing};
+use super::verify::Verifier;
 
 use std::iter;
-use std::collections::VecDeque;
+use std::cmp::{max, min};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::hash_map::Entry;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+use log::LogLevel;
 
 
 pub struct SimResult {
     pub p_disrupt: RR,
     pub p_compromise: RR,
+    /// Number of groups assumed or simulated (mean over repetitions)
+    pub groups: RR,
+    /// Fewest and most groups simulated in a repetition (`None` if calculated)
+    pub groups_range: Option<(NN, NN)>,
+    /// Mean malicious nodes deployed over the steps of the full simulation
+    /// (mean over repetitions), not counting those held in reserve (see
+    /// `AttackStrategy::deploy`); `None` from other tools, which deploy all
+    pub deployed: Option<RR>,
+    /// Histogram of malicious proportion per group, if requested
+    pub malice_hist: Option<MaliceHist>,
+    /// Elder composition, if requested
+    pub elders: Option<ElderReport>,
+    /// Results under both "any group" models, if requested
+    pub models: Option<ModelComparison>,
+    /// Correction factor of the structure simulation, if requested
+    pub correction: Option<CorrectionFactor>,
+    /// Age share curve `(step, share)`, if requested (see `age_share`)
+    pub age_share: Option<Vec<(NN, RR)>>,
+    /// Liveness of groups, if requested
+    pub liveness: Option<LivenessReport>,
+    /// Whether each repetition, in order, ended in compromise, if requested
+    pub outcomes: Option<Vec<bool>>,
+    /// Why the runs ended, if requested
+    pub termination: Option<TerminationReport>,
+    /// Whether structural changes could be blocked, if requested
+    pub governance: Option<GovernanceReport>,
+    /// Spread of the malicious nodes over the groups, if requested
+    pub spread: Option<SpreadReport>,
+    /// Share of the address space held by compromised and disrupted groups, if
+    /// requested
+    pub coverage: Option<CoverageReport>,
+    /// Compromise of close groups against that of sections, if requested
+    pub close_group: Option<CloseGroupReport>,
+    /// Turnover of the ages of honest nodes, if requested
+    pub turnover: Option<TurnoverReport>,
+    /// Long-run steady state, if measured
+    pub steady_state: Option<SteadyStateReport>,
+    /// The attacker's decisions in the first repetition, if recorded
+    pub attack_record: Option<Vec<AttackDecision>>,
+    /// Values drawn from each random number stream in each repetition, in
+    /// order, if audited
+    pub rng_draws: Option<Vec<Draws>>,
+    /// Number of simulation runs the probabilities were estimated from, if
+    /// estimated by repeated simulation (`None` if calculated)
+    pub runs: Option<NN>,
+    /// Whether the time limit (or an interruption) stopped the simulations
+    /// before all repetitions requested were done
+    pub truncated: bool,
+    /// Agreement of the estimates of folds of the repetitions, if requested
+    pub split_check: Option<SplitCheck>,
+}
+
+
+/// How `DirectCalcTool` combines the per-group probabilities into the
+/// probability of "any group" being disrupted or compromised.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AnyGroupModel {
+    /// Treat groups as independent: `1 - (1 - p)^G`, with `G` = nodes / group
+    /// size (possibly fractional). Since malicious nodes are drawn without
+    /// replacement, a group with few malicious nodes leaves more for the
+    /// others; ignoring this generally underestimates the risk.
+    Independent,
+    /// Calculation over `floor(nodes / group size)` groups of minimum size
+    /// (see `prob::prob_all_groups_at_most`); left-over nodes are in no group.
+    /// Exact, unless the groups and malicious nodes are too many (see
+    /// `prob::MAX_EXACT_TERMS`), when it is approximated to second order.
+    Corrected,
+}
+
+impl AnyGroupModel {
+    pub fn name(self) -> &'static str {
+        match self {
+            AnyGroupModel::Independent => "independent",
+            AnyGroupModel::Corrected => "corrected",
+        }
+    }
+}
+
+/// The calculation of `DirectCalcTool` step by step (see `explain`).
+pub struct CalcBreakdown {
+    pub num_nodes: NN,
+    pub num_malicious: NN,
+    pub min_group_size: NN,
+    /// Size assumed of every group: the minimum, unless the network is too
+    /// small to split
+    pub group_size: NN,
+    /// Malicious nodes needed for compromise of a group of the assumed size
+    pub quorum: NN,
+    /// Malicious nodes needed for compromise of a group of the minimum size
+    pub min_quorum: NN,
+    /// Honest nodes needed to avoid disruption of a group of the assumed size
+    pub honest_needed: NN,
+    /// Probability of one group being disrupted
+    pub p_disrupt_group: RR,
+    /// Probability of one group being compromised
+    pub p_compromise_group: RR,
+    /// The result: probabilities of any group being disrupted or compromised
+    pub result: SimResult,
+}
+
+/// Probabilities of `DirectCalcTool` under each `AnyGroupModel`.
+pub struct ModelComparison {
+    pub p_disrupt_independent: RR,
+    pub p_compromise_independent: RR,
+    pub p_disrupt_corrected: RR,
+    pub p_compromise_corrected: RR,
+}
+
+/// Ratio of the probability of compromise found by `SimStructureTool` to that
+/// of `DirectCalcTool` (with independent groups) at the same parameters: a
+/// factor with which to correct fast analytic sweeps.
+pub struct CorrectionFactor {
+    pub p_calc: RR,
+    /// Mean over the simulated structures
+    pub p_structure: RR,
+    /// The ratio and its confidence interval `(ratio, low, high)`, from the
+    /// variation between simulated structures; `None` (undefined) if `p_calc`
+    /// is zero.
+    pub ratio: Option<(RR, RR, RR)>,
+}
+
+impl CorrectionFactor {
+    /// From the calculated probability and those of each simulated structure.
+    /// The confidence interval is at the level of `baseline::Z_CRITICAL`.
+    pub fn new(p_calc: RR, p_structures: &[RR]) -> Self {
+        let n = p_structures.len() as RR;
+        let mean = p_structures.iter().sum::<RR>() / n;
+        let ratio = if p_calc > 0.0 {
+            let std_err = if p_structures.len() > 1 {
+                let var = p_structures.iter().map(|p| (p - mean).powi(2)).sum::<RR>() / (n - 1.0);
+                (var / n).sqrt()
+            } else {
+                0.0     // no estimate of the variation
+            };
+            let margin = Z_CRITICAL * std_err;
+            Some((mean / p_calc, (mean - margin).max(0.0) / p_calc, (mean + margin) / p_calc))
+        } else {
+            None
+        };
+        CorrectionFactor {
+            p_calc: p_calc,
+            p_structure: mean,
+            ratio: ratio,
+        }
+    }
+}
+
+#[test]
+fn test_correction_factor() {
+    // Mean 0.2, standard error 0.1
+    let factor = CorrectionFactor::new(0.1, &[0.1, 0.3]);
+    assert_eq!(factor.p_structure, 0.2);
+    let (ratio, low, high) = factor.ratio.expect("ratio");
+    assert!((ratio - 2.0).abs() < 1e-12);
+    assert!((low - 0.04).abs() < 1e-9 && (high - 3.96).abs() < 1e-9,
+            "{} - {}",
+            low,
+            high);
+
+    // Zero calculated probability: undefined, not infinite
+    let factor = CorrectionFactor::new(0.0, &[0.0, 1e-6]);
+    assert!(factor.ratio.is_none());
+    assert_eq!(factor.p_structure, 5e-7);
+}
+
+
+// Probability beyond the upper end of a confidence interval at the level of `Z_CRITICAL`
+const ONE_SIDED_ALPHA: RR = 0.025;
+
+/// Ratio of the probability of compromise under the simple quorum to that
+/// under the age quorum, each estimated from the same repetitions (see
+/// `--paired-ageing`): how much ageing reduces the risk.
+pub struct AgeingBenefit {
+    pub p_simple: RR,
+    pub p_age: RR,
+    /// Number of paired repetitions
+    pub runs: NN,
+    /// The ratio and its confidence interval `(ratio, low, high)`, by the delta
+    /// method over the paired outcomes; `None` if the age quorum was never
+    /// compromised.
+    pub ratio: Option<(RR, RR, RR)>,
+    /// If the age quorum was never compromised but the simple quorum was, a
+    /// lower bound of the ratio: `p_simple` over the upper confidence bound of
+    /// `p_age` (one-sided, at the level of the upper end of the interval).
+    pub lower_bound: Option<RR>,
+}
+
+impl AgeingBenefit {
+    /// From whether each repetition ended in compromise under the simple and
+    /// age quorums, paired by repetition. The confidence interval is at the
+    /// level of `baseline::Z_CRITICAL`.
+    pub fn new(simple: &[bool], age: &[bool]) -> Self {
+        assert_eq!(simple.len(), age.len());
+        let n = simple.len() as RR;
+        let mean = |outcomes: &[bool]| outcomes.iter().filter(|&&c| c).count() as RR / n;
+        let (p_simple, p_age) = (mean(simple), mean(age));
+        let (mut ratio, mut lower_bound) = (None, None);
+        if p_age > 0.0 {
+            let r = p_simple / p_age;
+            let std_err = if simple.len() > 1 {
+                // Var(a - r b) / (n mean(b)^2), with outcomes a and b 0 or 1
+                let var = simple.iter()
+                    .zip(age)
+                    .map(|(&a, &b)| {
+                        let (a, b) = (a as u8 as RR, b as u8 as RR);
+                        ((a - p_simple) - r * (b - p_age)).powi(2)
+                    })
+                    .sum::<RR>() / (n - 1.0);
+                (var / n).sqrt() / p_age
+            } else {
+                0.0     // no estimate of the variation
+            };
+            let margin = Z_CRITICAL * std_err;
+            ratio = Some((r, (r - margin).max(0.0), r + margin));
+        } else if p_simple > 0.0 {
+            // No compromise in n runs: p_age is below 1 - alpha^(1/n) (exactly, at level
+            // 1 - alpha)
+            lower_bound = Some(p_simple / (1.0 - ONE_SIDED_ALPHA.powf(1.0 / n)));
+        }
+        AgeingBenefit {
+            p_simple: p_simple,
+            p_age: p_age,
+            runs: simple.len() as NN,
+            ratio: ratio,
+            lower_bound: lower_bound,
+        }
+    }
+}
+
+#[test]
+fn test_ageing_benefit() {
+    // Simple compromised in 8 of 16 runs, age in 4 of the same runs
+    let simple: Vec<bool> = (0..16).map(|i| i % 8 < 4).collect();
+    let age: Vec<bool> = (0..16).map(|i| i % 8 < 2).collect();
+    let benefit = AgeingBenefit::new(&simple, &age);
+    assert_eq!((benefit.p_simple, benefit.p_age, benefit.runs), (0.5, 0.25, 16));
+    let (ratio, low, high) = benefit.ratio.expect("ratio");
+    assert!((ratio - 2.0).abs() < 1e-12);
+    // Residuals (a - 0.5) - 2 (b - 0.25): -1 (4 runs), 1 (4 runs) and 0 (8 runs)
+    let std_err = (8.0f64 / 15.0 / 16.0).sqrt() / 0.25;
+    assert!((high - ratio - Z_CRITICAL * std_err).abs() < 1e-9, "{} - {}", low, high);
+    assert!((ratio - low - Z_CRITICAL * std_err).abs() < 1e-9);
+    assert_eq!(benefit.lower_bound, None);
+
+    // Age never compromised: a bound, not infinity. With 100 runs, p_age is below about
+    // 3.6% (at the 97.5% level).
+    let simple: Vec<bool> = (0..100).map(|i| i < 50).collect();
+    let benefit = AgeingBenefit::new(&simple, &[false; 100]);
+    assert!(benefit.ratio.is_none());
+    let bound = benefit.lower_bound.expect("bound");
+    assert!(bound > 13.0 && bound < 14.5, "bound {}", bound);
+
+    // Neither compromised: undefined
+    let benefit = AgeingBenefit::new(&[false; 10], &[false; 10]);
+    assert!(benefit.ratio.is_none() && benefit.lower_bound.is_none());
+}
+
+/// How often groups lacked a quorum of live members during each simulation
+/// (see `FullSimTool::count_unlive_groups`).
+pub struct LivenessReport {
+    /// Proportion of simulations in which some group lacked a quorum of live
+    /// members at some step
+    pub p_failure: RR,
+    /// Mean number of group-steps without a quorum of live members per
+    /// simulation (a group lacking one for three steps counts three)
+    pub mean_group_steps: RR,
+}
+
+/// Composition of elders of each group at the end of each simulation.
+pub struct ElderReport {
+    /// Mean number of malicious elders per group
+    pub mean_malicious: RR,
+    /// Maximum number of malicious elders in any group
+    pub max_malicious: NN,
+    /// Proportion of simulations in which some group ended with a quorum of
+    /// malicious elders. This should equal the probability of compromise.
+    pub p_quorum: RR,
+}
+
+
+/// Structural changes of groups (splits, and promotions of elders when these
+/// lag) during each simulation, and whether the attacker could have blocked
+/// them: whether the voting members just before each change were disrupted or
+/// compromised.
+pub struct GovernanceReport {
+    /// Proportion of simulations in which the attacker could have blocked some
+    /// structural change
+    pub p_blockable: RR,
+    /// Mean number of structural changes per simulation
+    pub mean_changes: RR,
+    /// Mean number of those the attacker could have blocked per simulation
+    pub mean_blockable: RR,
+}
+
+/// Spread of the malicious nodes over the groups at the end of each
+/// simulation. Untargetted, the malicious nodes should be in about
+/// `min(malicious, groups)` groups; effective targetting concentrates them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpreadReport {
+    /// Mean number of groups holding at least one malicious node
+    pub mean_groups: RR,
+    /// Mean of the most malicious nodes in any one group
+    pub mean_max_cluster: RR,
+}
+
+/// The number of `groups` holding at least one malicious node, and the most
+/// malicious nodes in any one of them.
+pub fn malicious_spread<'a, I>(groups: I) -> (NN, NN)
+    where I: IntoIterator<Item = &'a Group>
+{
+    let mut spread = (0, 0);
+    for group in groups {
+        let bad = group.values().filter(|data| data.is_malicious()).count() as NN;
+        if bad > 0 {
+            spread.0 += 1;
+        }
+        spread.1 = max(spread.1, bad);
+    }
+    spread
+}
+
+#[test]
+fn test_malicious_spread() {
+    // Groups of 10 nodes, the first 0 malicious, the second 1, the third 4 and the fourth 2
+    let groups: Vec<Group> = [0, 1, 4, 2]
+        .iter()
+        .enumerate()
+        .map(|(g, &bad)| {
+            (0..10)
+                .map(|i| {
+                    let data = if i < bad {
+                        NodeData::new_malicious()
+                    } else {
+                        NodeData::new()
+                    };
+                    ((g * 10 + i) as NodeName, data)
+                })
+                .collect()
+        })
+        .collect();
+    assert_eq!(malicious_spread(&groups), (3, 4));
+    assert_eq!(malicious_spread(&groups[..1]), (0, 0));
+    assert_eq!(malicious_spread(&groups[1..2]), (1, 1));
+    assert_eq!(malicious_spread(&groups[..0]), (0, 0));
+}
+
+/// Share of the address space held by groups at the end of each simulation:
+/// that of a group of prefix length `b` is `2^-b`, so a few compromised groups
+/// of short prefixes put more data at risk than many of long ones. The
+/// structure simulation gives the expected shares of each structure.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CoverageReport {
+    /// Mean share held by compromised groups
+    pub mean_compromised: RR,
+    /// Largest share held by compromised groups in any simulation
+    pub max_compromised: RR,
+    /// Mean share held by disrupted groups (including compromised ones)
+    pub mean_disrupted: RR,
+    /// Largest share held by disrupted groups in any simulation
+    pub max_disrupted: RR,
+}
+
+// Whether `net` has a group of `target` or of a prefix extending it
+fn reaches<AR: AddRestriction>(net: &Network<AR>, target: Prefix) -> bool {
+    net.groups().keys().any(|prefix| {
+        prefix.bit_count() >= target.bit_count() && prefix.is_compatible(target)
+    })
+}
+
+/// The share of the address space covered by `prefixes` (which should not
+/// overlap).
+pub fn address_coverage<'a, I>(prefixes: I) -> RR
+    where I: IntoIterator<Item = &'a Prefix>
+{
+    prefixes.into_iter().map(|prefix| 0.5f64.powi(prefix.bit_count() as i32)).sum()
+}
+
+#[test]
+fn test_address_coverage() {
+    // The tree 0, 10, 110, 111
+    let prefixes: Vec<Prefix> = ["0", "10", "110", "111"]
+        .iter()
+        .map(|bits| bits.parse().expect("prefix"))
+        .collect();
+    assert_eq!(address_coverage(&prefixes), 1.0);
+    assert_eq!(address_coverage(&prefixes[..1]), 0.5);
+    assert_eq!(address_coverage(&prefixes[1..3]), 0.375);
+    assert_eq!(address_coverage(&prefixes[3..]), 0.125);
+    assert_eq!(address_coverage(&prefixes[..0]), 0.0);
+    assert_eq!(address_coverage(&[Prefix::default()]), 1.0);
+}
+
+/// How quickly honest nodes age, from the median age of the honest nodes
+/// sampled at each step of the full simulation. Nodes age only through churn,
+/// so with none (no nodes joining once the network is built) the median never
+/// changes. A node's age grows with the logarithm of the churn events it has
+/// seen, so the median rarely doubles within a run; the time for it to double
+/// is extrapolated from the rate at which it grew.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TurnoverReport {
+    /// Steps for the median honest age to double at the mean rate it grew,
+    /// from its mean initial value (`None` if it did not grow)
+    pub mean_doubling_steps: Option<RR>,
+    /// Mean steady-state median honest age: the mean of the samples over the
+    /// second half of each simulation
+    pub mean_median_age: RR,
+}
+
+/// The median age of the honest nodes in `groups`, or `None` if there are none.
+pub fn median_honest_age<'a, I>(groups: I) -> Option<RR>
+    where I: IntoIterator<Item = &'a Group>
+{
+    let mut ages: Vec<u32> = groups.into_iter()
+        .flat_map(|group| group.values())
+        .filter(|data| !data.is_malicious())
+        .map(|data| data.age())
+        .collect();
+    if ages.is_empty() {
+        return None;
+    }
+    ages.sort();
+    let mid = ages.len() / 2;
+    Some(if ages.len() % 2 == 0 {
+        (ages[mid - 1] + ages[mid]) as RR / 2.0
+    } else {
+        ages[mid] as RR
+    })
+}
+
+/// Ages of the nodes of a network, as at the end of a run of the full simulation
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AgeSummary {
+    /// Mean age of the honest nodes, if any
+    pub mean_honest: Option<RR>,
+    /// Mean age of the malicious nodes, if any
+    pub mean_malicious: Option<RR>,
+    pub oldest: u32,
+}
+
+impl AgeSummary {
+    pub fn of(net: &Network<RestrictOnePerAge>) -> Self {
+        let mut sums = [(0, 0); 2];
+        let mut oldest = 0;
+        for data in net.groups().values().flat_map(|group| group.values()) {
+            let sum = &mut sums[data.is_malicious() as usize];
+            sum.0 += data.age() as NN;
+            sum.1 += 1;
+            oldest = max(oldest, data.age());
+        }
+        let mean = |(ages, nodes): (NN, NN)| if nodes == 0 {
+            None
+        } else {
+            Some(ages as RR / nodes as RR)
+        };
+        AgeSummary {
+            mean_honest: mean(sums[0]),
+            mean_malicious: mean(sums[1]),
+            oldest: oldest,
+        }
+    }
+}
+
+/// Check that no node of `net` is older than its ageing allows. Nodes only age
+/// with the churn events they take part in, and a node rejoining recovers at
+/// most the age it left with, so the oldest node can have taken part in no
+/// more churn events than `Network::churns` counts. An older node
+/// shows that ages are out of step with churn (e.g. from a mistake of units),
+/// which would flatter the age quorum and elders, so the run stops with an
+/// internal-consistency error.
+pub fn check_ages(net: &Network<RestrictOnePerAge>) {
+    let oldest = AgeSummary::of(net).oldest;
+    let bound = net.ageing().max_age(net.churns());
+    if oldest > bound {
+        panic!("internal inconsistency: a node of age {} after {} churn events, when ageing \
+                allows at most age {}",
+               oldest,
+               net.churns(),
+               bound);
+    }
+}
+
+/// Summary of the median honest ages `medians` sampled at each step of one
+/// simulation: the steps between the first and last samples, the first
+/// median, its growth from the first to the last sample, and the mean of the
+/// second half of the samples (the steady state). All 0 with no samples.
+pub fn turnover(medians: &[RR]) -> (NN, RR, RR, RR) {
+    let (first, last) = match (medians.first(), medians.last()) {
+        (Some(&first), Some(&last)) => (first, last),
+        _ => return (0, 0.0, 0.0, 0.0),
+    };
+    let tail = &medians[medians.len() / 2..];
+    let steady = tail.iter().sum::<RR>() / tail.len() as RR;
+    ((medians.len() - 1) as NN, first, last - first, steady)
+}
+
+/// Steps for a median starting at `initial` to double, growing by `growth` in
+/// `steps` steps, or `None` if it does not grow (or starts at 0).
+pub fn doubling_steps(steps: NN, initial: RR, growth: RR) -> Option<RR> {
+    if growth > 0.0 && initial > 0.0 {
+        Some(initial * steps as RR / growth)
+    } else {
+        None
+    }
+}
+
+#[test]
+fn test_turnover() {
+    let ages = |ages: &[u32]| -> Group {
+        ages.iter()
+            .enumerate()
+            .map(|(i, &age)| {
+                let mut data = NodeData::new();
+                data.set_age(age);
+                (i as NodeName, data)
+            })
+            .collect()
+    };
+    let mut groups = vec![ages(&[1, 5, 2]), ages(&[3])];
+    assert_eq!(median_honest_age(&groups), Some(2.5));
+    groups.push(ages(&[9]));
+    assert_eq!(median_honest_age(&groups), Some(3.0));
+    // Malicious nodes are not counted
+    groups.push((100..104).map(|name| (name, NodeData::new_malicious())).collect());
+    assert_eq!(median_honest_age(&groups), Some(3.0));
+    assert_eq!(median_honest_age(&groups[3..]), None);
+
+    // A median growing from 4 by 1 every 25 steps doubles in 100 steps
+    let medians: Vec<RR> = (0..200).map(|step| 4.0 + step as RR / 25.0).collect();
+    let (steps, initial, growth, steady) = turnover(&medians);
+    assert_eq!((steps, initial), (199, 4.0));
+    assert!((growth - 7.96).abs() < 1e-9 && (steady - 9.98).abs() < 1e-9);
+    assert_eq!(doubling_steps(steps, initial, growth).map(|s| s.round()), Some(100.0));
+    // A constant median never doubles
+    assert_eq!(turnover(&[3.0; 10]), (9, 3.0, 0.0, 3.0));
+    assert_eq!(doubling_steps(9, 3.0, 0.0), None);
+    assert_eq!(doubling_steps(2, 0.0, 2.0), None);
+    assert_eq!(turnover(&[0.0, 1.0, 2.0]), (2, 0.0, 2.0, 1.5));
+    assert_eq!(turnover(&[5.0]), (0, 5.0, 0.0, 5.0));
+    assert_eq!(turnover(&[]), (0, 0.0, 0.0, 0.0));
+}
+
+/// Why a run of the full simulation ended. Steps are counted from 0.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Termination {
+    /// A group was compromised (the attack succeeded) at this step
+    Compromised(NN),
+    /// From this step nothing could change: the attacker had no nodes to add,
+    /// no node was waiting to join, there were no bursts or evictions and no
+    /// promotion or held condition was pending. The remaining steps are not
+    /// simulated.
+    Stalled(NN),
+    /// The attacker had no nodes left (in the network, joining or to add) from
+    /// this step, so could not succeed, but the network went on changing
+    BudgetExhausted(NN),
+    /// The last step was reached otherwise
+    StepsExhausted,
+    /// The time limit had passed by this step, so the run is incomplete
+    TimedOut(NN),
+}
+
+impl Termination {
+    /// Whether the run ended in compromise
+    pub fn compromised(self) -> bool {
+        match self {
+            Termination::Compromised(_) => true,
+            _ => false,
+        }
+    }
+}
+
+// Steps of the full simulation between checks of the time limit
+const TIME_CHECK_STEPS: NN = 64;
+
+// Resets in a row, per group, after which the full simulation stops deploying malicious nodes for
+// the step (a node landing in a group with probability 1 / groups misses this often with
+// probability about e^-100)
+const RESETS_PER_GROUP: NN = 100;
+
+// Seconds in a duration
+fn seconds(duration: Duration) -> RR {
+    duration.as_secs() as RR + (duration.subsec_nanos() as RR) * 1e-9
+}
+
+// The time limit of repeated simulation: `limit` seconds from creation.
+struct Deadline {
+    start: Instant,
+    limit: RR,
+}
+
+impl Deadline {
+    fn new(limit: RR) -> Self {
+        Deadline {
+            start: Instant::now(),
+            limit: limit,
+        }
+    }
+
+    fn passed(&self) -> bool {
+        seconds(self.start.elapsed()) >= self.limit
+    }
+}
+
+/// How the runs of the full simulation ended (see `Termination`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct TerminationReport {
+    /// Number of runs ending in compromise
+    pub compromised: NN,
+    /// Number of runs which stalled
+    pub stalled: NN,
+    /// Number of runs in which the attacker ran out of nodes
+    pub budget_exhausted: NN,
+    /// Number of runs reaching the last step otherwise
+    pub steps_exhausted: NN,
+    /// Mean number of steps simulated by the runs which stalled (`None` if
+    /// none did)
+    pub mean_stall_steps: Option<RR>,
+}
+
+// Consecutive steps for which each group has met a condition (compromise or disruption), so
+// that it only counts once held for some number of steps (see `--hold`). A group which splits
+// is gone; its children count from zero.
+struct HoldCounter {
+    hold: NN,
+    steps: HashMap<Prefix, NN>,
+}
+
+impl HoldCounter {
+    fn new(hold: NN) -> Self {
+        HoldCounter {
+            hold: hold,
+            steps: HashMap::new(),
+        }
+    }
+
+    // Record the groups meeting the condition at this step (the counts of all others restart).
+    // Returns true if any has now met it for `hold` consecutive steps.
+    fn update(&mut self, met: &[Prefix]) -> bool {
+        let mut steps = HashMap::with_capacity(met.len());
+        for prefix in met {
+            steps.insert(*prefix, self.steps.get(prefix).map_or(1, |n| n + 1));
+        }
+        self.steps = steps;
+        self.steps.values().any(|&n| n >= self.hold)
+    }
+}
+
+// A run of the full simulation as judged under one quorum (see `FullSimTool::run_quorums`)
+struct QuorumRun<'a, Q: 'a> {
+    quorum: &'a Q,
+    // Whether each disrupted group (of the target's lineage, if any) was also compromised, as
+    // last checked (see `FullSimTool::check_groups`)
+    status: HashMap<Prefix, (bool, bool)>,
+    disruption_hold: HoldCounter,
+    compromise_hold: HoldCounter,
+    disruption: bool,
+    // Whether a compromise and a disruption were held at each step, if the steady state is
+    // measured (the run then goes on past compromise)
+    occupancy: Option<Vec<(bool, bool)>>,
+    // The first compromise of a run going on past it, and the number of groups and the address
+    // coverage then
+    compromise: Option<(Termination, NN, (RR, RR))>,
+    // How the run ended, and the number of groups and the address coverage then, once it has
+    ended: Option<(Termination, NN, (RR, RR))>,
+    // The target prefix, if any, and whether the network had reached it when the run ended
+    target: Option<Prefix>,
+    reached: bool,
+    // Ages of the nodes when the run ended, once it has
+    ages: Option<AgeSummary>,
+    // Sum over the steps of the run of the malicious nodes deployed (not held in reserve), and
+    // the number of steps
+    deployed: (NN, NN),
+}
+
+impl<'a, Q: 'a> QuorumRun<'a, Q> {
+    fn new(quorum: &'a Q, hold: NN, steady_state: bool, target: Option<Prefix>) -> Self {
+        QuorumRun {
+            quorum: quorum,
+            status: HashMap::new(),
+            disruption_hold: HoldCounter::new(hold),
+            compromise_hold: HoldCounter::new(hold),
+            disruption: false,
+            occupancy: if steady_state { Some(vec![]) } else { None },
+            compromise: None,
+            ended: None,
+            target: target,
+            reached: false,
+            ages: None,
+            deployed: (0, 0),
+        }
+    }
+
+    // Count `deployed` malicious nodes deployed at a step of the run
+    fn count_deployed(&mut self, deployed: NN) {
+        self.deployed.0 += deployed;
+        self.deployed.1 += 1;
+    }
+
+    // Mean malicious nodes deployed over the steps of the run, or `now` if it had none
+    fn mean_deployed(&self, now: NN) -> RR {
+        match self.deployed {
+            (_, 0) => now as RR,
+            (sum, steps) => sum as RR / steps as RR,
+        }
+    }
+
+    // Count the groups of `status` towards the holds, as at the next step. Gives whether a
+    // compromise is held, and whether nothing more can happen while the status is unchanged.
+    fn hold(&mut self) -> (bool, bool) {
+        let compromised: Vec<Prefix> =
+            self.status.iter().filter(|&(_, s)| s.0).map(|(prefix, _)| *prefix).collect();
+        let disrupted: Vec<Prefix> =
+            self.status.iter().filter(|&(_, s)| s.1).map(|(prefix, _)| *prefix).collect();
+        let disruption = self.disruption_hold.update(&disrupted);
+        self.disruption |= disruption;
+        let compromise = self.compromise_hold.update(&compromised);
+        if let Some(ref mut occupancy) = self.occupancy {
+            occupancy.push((compromise, disruption));
+        }
+        (compromise, compromised.is_empty() && (self.disruption || disrupted.is_empty()))
+    }
+
+    // Shares of the address space of the compromised and of the disrupted groups of `status`.
+    // The shares are powers of two, so their sums do not depend on the order of the map.
+    fn coverage(&self) -> (RR, RR) {
+        let compromised = self.status.iter().filter(|&(_, s)| s.0).map(|(prefix, _)| prefix);
+        (address_coverage(compromised), address_coverage(self.status.keys()))
+    }
+
+    // Note a compromise held at `step`, for a run going on past compromise
+    fn compromised(&mut self, step: NN, net: &Network<RestrictOnePerAge>) {
+        if self.compromise.is_none() {
+            self.compromise =
+                Some((Termination::Compromised(step), net.groups().len() as NN, self.coverage()));
+        }
+    }
+
+    // End the run with `net` as it is now, unless already ended. A run which went on past
+    // compromise ends as at the first, unless timed out.
+    fn end(&mut self, termination: Termination, net: &Network<RestrictOnePerAge>) {
+        if self.ended.is_none() {
+            self.reached = self.target.map_or(false, |target| reaches(net, target));
+            self.ages = Some(AgeSummary::of(net));
+            self.ended = match (termination, self.compromise) {
+                (Termination::TimedOut(_), _) |
+                (_, None) => Some((termination, net.groups().len() as NN, self.coverage())),
+                (_, compromise) => compromise,
+            };
+        }
+    }
+
+    // The verdict on the ended run, `now` malicious nodes being deployed at its end
+    fn into_verdict(self, now: NN) -> QuorumVerdict {
+        let (termination, groups, coverage) = self.ended.expect("ended");
+        let deployed = self.mean_deployed(now);
+        QuorumVerdict {
+            disruption: self.disruption,
+            termination: termination,
+            groups: groups,
+            occupancy: self.occupancy.unwrap_or(vec![]),
+            deployed: deployed,
+            coverage: coverage,
+            reached: self.reached,
+            ages: self.ages.expect("ended"),
+        }
+    }
+}
+
+// How a run of the full simulation went under one quorum (see `FullSimTool::run_quorums`)
+struct QuorumVerdict {
+    // Whether it was disrupted
+    disruption: bool,
+    // How it ended, and the number of groups and the address coverage then
+    termination: Termination,
+    groups: NN,
+    coverage: (RR, RR),
+    // Whether a compromise and a disruption were held at each step, if the steady state is
+    // measured (empty otherwise)
+    occupancy: Vec<(bool, bool)>,
+    // Mean malicious nodes deployed over the steps
+    deployed: RR,
+    // Whether the target prefix (if any) was reached, and the ages of the nodes at the end
+    reached: bool,
+    ages: AgeSummary,
+}
+
+// The network as the attacker may see it (see `AttackStrategy::deploy`): the groups counted
+// under `target` (if any), with their members as in `history` as far as `knowledge` reveals
+// them, judged by `quorum` with `jitter`
+struct GroupsView<'a, Q: 'a> {
+    quorum: &'a Q,
+    jitter: &'a QuorumJitter,
+    net: &'a Network<RestrictOnePerAge>,
+    history: &'a MembershipHistory,
+    target: Option<Prefix>,
+    knowledge: AttackerKnowledge,
+    // Number of honest members of a group and their age, as assumed where they are hidden
+    typical: (NN, u32),
+}
+
+impl<'a, Q: Quorum> GroupsView<'a, Q> {
+    fn new(quorum: &'a Q,
+           jitter: &'a QuorumJitter,
+           net: &'a Network<RestrictOnePerAge>,
+           history: &'a MembershipHistory,
+           target: Option<Prefix>,
+           knowledge: AttackerKnowledge)
+           -> Self {
+        // Only found when needed, being a pass over the whole network
+        let typical = if knowledge == AttackerKnowledge::Full {
+            (0, 0)
+        } else {
+            typical_honest(net)
+        };
+        GroupsView {
+            quorum: quorum,
+            jitter: jitter,
+            net: net,
+            history: history,
+            target: target,
+            knowledge: knowledge,
+            typical: typical,
+        }
+    }
+}
+
+impl<'a, Q: Quorum> NetworkView for GroupsView<'a, Q> {
+    fn prefixes(&self) -> Vec<Prefix> {
+        let mut prefixes: Vec<Prefix> = self.net
+            .groups()
+            .keys()
+            .cloned()
+            .filter(|prefix| self.target.map_or(true, |target| target.is_compatible(*prefix)))
+            .collect();
+        prefixes.sort();
+        prefixes
+    }
+
+    fn compromised_with(&self, prefix: Prefix, extra: NN) -> bool {
+        let group = match self.net.groups().get(&prefix) {
+            Some(group) => group,
+            None => return false,
+        };
+        let mut projected =
+            observed_members(self.knowledge, &self.history.members(&prefix, group), self.typical);
+        // The new nodes only need names of their own, not drawn from any stream. Those the group
+        // does not admit would be renamed, and so join elsewhere.
+        let mut name = 0;
+        for _ in 0..extra {
+            let data = NodeData::new_malicious();
+            if !RestrictOnePerAge::can_add(&data, &projected) {
+                break;
+            }
+            while projected.contains_key(&name) {
+                name += 1;
+            }
+            let _ = projected.insert(name, data);
+        }
+        quorum_compromised_offset(self.quorum, &projected, self.jitter.offset(prefix))
+    }
+}
+
+// The mean number of honest members per group of `net` and their mean age, rounded
+fn typical_honest(net: &Network<RestrictOnePerAge>) -> (NN, u32) {
+    let (mut honest, mut ages) = (0, 0);
+    for data in net.groups().values().flat_map(|group| group.values()) {
+        if !data.is_malicious() {
+            honest += 1;
+            ages += data.age() as NN;
+        }
+    }
+    if honest == 0 {
+        return (0, 0);
+    }
+    let groups = net.groups().len() as RR;
+    ((honest as RR / groups).round() as NN, (ages as RR / honest as RR).round() as u32)
+}
+
+// The members of a group as an attacker with `knowledge` sees them: as they are, with the
+// honest members of the age of `typical`, or only its own members with the number of honest
+// ones of `typical` added
+fn observed_members(knowledge: AttackerKnowledge, members: &Group, typical: (NN, u32)) -> Group {
+    let (typical_members, typical_age) = typical;
+    match knowledge {
+        AttackerKnowledge::Full => members.clone(),
+        AttackerKnowledge::MembershipOnly => {
+            members.iter()
+                .map(|(name, data)| {
+                    let mut data = *data;
+                    if !data.is_malicious() {
+                        data.set_age(typical_age);
+                    }
+                    (*name, data)
+                })
+                .collect()
+        }
+        AttackerKnowledge::OwnNodesOnly => {
+            let mut observed: Group = members.iter()
+                .filter(|&(_, data)| data.is_malicious())
+                .map(|(name, data)| (*name, *data))
+                .collect();
+            let mut name = 0;
+            for _ in 0..typical_members {
+                while observed.contains_key(&name) {
+                    name += 1;
+                }
+                let mut data = NodeData::new();
+                data.set_age(typical_age);
+                let _ = observed.insert(name, data);
+            }
+            observed
+        }
+    }
+}
+
+#[test]
+fn test_attacker_knowledge() {
+    use super::quorum::{AgeQuorum, ThresholdAttack};
+
+    // Under a quorum of half by number and by age, the attacker reaches a quorum soonest in
+    // group 00, whose honest members are young. Taking honest members to be of the mean age
+    // (3, rounded), only its nodes in group 01 outweigh them; taking groups to be of the mean
+    // number of honest members too (5), group 01 needs more nodes still.
+    let prefix = |bits: &str| bits.parse::<Prefix>().expect("prefix");
+    let mut net = Network::<RestrictOnePerAge>::new(4);
+    let groups: [(&str, &[(u32, bool)]); 3] =
+        [("00", &[(1, false), (1, false), (1, false), (3, true), (3, true)]),
+         ("01",
+          &[(4, false), (4, false), (4, false), (4, false), (20, true), (20, true), (20, true)]),
+         ("1",
+          &[(4, false), (4, false), (4, false), (4, false), (4, false), (4, false), (4, false),
+            (4, false), (1, true)])];
+    net.groups_mut().clear();
+    let mut name = 0;
+    for &(bits, members) in &groups {
+        let group = members.iter()
+            .map(|&(age, malicious)| {
+                let mut data = if malicious {
+                    NodeData::new_malicious()
+                } else {
+                    NodeData::new()
+                };
+                data.set_age(age);
+                name += 1;
+                (name, data)
+            })
+            .collect();
+        let _ = net.groups_mut().insert(prefix(bits), group);
+    }
+    assert_eq!(typical_honest(&net), (5, 3));
+    let history = MembershipHistory::new(0, &mut net);
+    let jitter = QuorumJitter::none();
+    let mut quorum = AgeQuorum::new();
+    quorum.set_quorum_size(QuorumSize::Proportion(0.5));
+
+    // The fewest nodes for which the threshold attack (targetting the weakest group it can
+    // find) deploys, and the group it targets then
+    let needed = |knowledge| -> Option<(NN, Prefix)> {
+        let view = GroupsView::new(&quorum, &jitter, &net, &history, None, knowledge);
+        (1..10).find(|&available| {
+                let progress = AttackProgress {
+                    step: 0,
+                    max_steps: 10,
+                    available: available,
+                    disrupted: false,
+                    all_disrupted: false,
+                };
+                ThresholdAttack::new().deploy(&view, &progress) > 0
+            })
+            .map(|available| {
+                let prefix = view.prefixes()
+                    .into_iter()
+                    .find(|&prefix| view.compromised_with(prefix, available))
+                    .expect("reachable");
+                (available, prefix)
+            })
+    };
+    assert_eq!(needed(AttackerKnowledge::Full), Some((1, prefix("00"))));
+    assert_eq!(needed(AttackerKnowledge::MembershipOnly), Some((1, prefix("01"))));
+    assert_eq!(needed(AttackerKnowledge::OwnNodesOnly), Some((2, prefix("01"))));
+}
+
+#[test]
+fn test_hold_counter() {
+    use super::quorum::{QuorumSize, quorum_compromised};
+
+    // A group of 8 whose last three members are malicious, with a fourth malicious member at
+    // the steps marked 1 (compromise under a quorum of half)
+    let script = [1, 0, 1, 1, 0, 1, 1, 1, 0];
+    let prefix: Prefix = "0".parse().expect("parse");
+    let mut quorum = SimpleQuorum::new();
+    quorum.set_quorum_size(QuorumSize::Proportion(0.5));
+    let mut group = HashMap::new();
+    for name in 0..8 {
+        group.insert(name, if name >= 5 { NodeData::new_malicious() } else { NodeData::new() });
+    }
+    // The step at which compromise first counts, holding for 1, 2 and 3 steps
+    for &(hold, first) in &[(1, Some(0)), (2, Some(3)), (3, Some(7)), (4, None)] {
+        let mut counter = HoldCounter::new(hold);
+        let mut counted = None;
+        for (step, &bad) in script.iter().enumerate() {
+            group.insert(4, if bad == 1 { NodeData::new_malicious() } else { NodeData::new() });
+            let met = if quorum_compromised(&quorum, &group) { vec![prefix] } else { vec![] };
+            if counter.update(&met) && counted.is_none() {
+                counted = Some(step);
+            }
+        }
+        assert_eq!(counted, first, "hold {}", hold);
+    }
+
+    // Counts are per group: alternating groups never hold for two steps
+    let other: Prefix = "1".parse().expect("parse");
+    let mut counter = HoldCounter::new(2);
+    assert!(!counter.update(&[prefix]));
+    assert!(!counter.update(&[other]));
+    assert!(!counter.update(&[prefix]));
+    assert!(counter.update(&[prefix, other]));
+}
+
+
+// The elders seated in each group, when promotions lag (see `--promotion-lag`). A member due to
+// become an elder (one of the oldest, see `elders`, but not seated) is promoted `lag` steps after
+// it became due, if still due then; until then the group has fewer elders. An elder leaving the
+// group leaves its seat at once, and an elder no longer among the oldest gives up its seat when a
+// member is promoted in its place. A group not seen before (one of the initial network, or the
+// result of a split) seats its elders at once. With no lag, the elders are always the oldest.
+// Ages are capped for ranking if the age cap applies to ordering, and ties of age are broken by
+// the elder tiebreak rule (see `--elder-tiebreak`).
+struct ElderRoster {
+    lag: NN,
+    elders: usize,
+    ordering_cap: Option<u32>,
+    tiebreak: ElderTiebreak,
+    groups: HashMap<Prefix, Seats>,
+}
+
+struct Seats {
+    seated: Vec<NodeName>,
+    // Members due for promotion, with the step at which each became due
+    due: HashMap<NodeName, NN>,
+}
+
+impl ElderRoster {
+    // Roster of `elders` per group, promotions lagging by `lag` steps (none if `lag` is 0), ages
+    // ranked capped at `ordering_cap`, if any, and ties of age broken by `tiebreak`.
+    fn new(lag: NN, elders: usize, ordering_cap: Option<u32>, tiebreak: ElderTiebreak) -> Self {
+        ElderRoster {
+            lag: lag,
+            elders: elders,
+            ordering_cap: ordering_cap,
+            tiebreak: tiebreak,
+            groups: HashMap::new(),
+        }
+    }
+
+    // Update the seats of every group of the network at this step. Groups no longer in the
+    // network are forgotten. Returns the groups in which some promotion took effect, with their
+    // seats just before (see `update_group`).
+    fn update(&mut self,
+              net: &Network<RestrictOnePerAge>,
+              step: NN)
+              -> Vec<(Prefix, Vec<NodeName>)> {
+        if self.lag == 0 {
+            return vec![];
+        }
+        let mut promoted = vec![];
+        // In order of prefix, so that the promotions are listed alike in every process
+        let mut prefixes: Vec<&Prefix> = net.groups().keys().collect();
+        prefixes.sort();
+        for prefix in prefixes {
+            let members: Vec<Member> = net.groups()[prefix]
+                .iter()
+                .map(|(name, data)| Member::new(*name, data))
+                .collect();
+            if let Some(seated) = self.update_group(*prefix, &members, step) {
+                promoted.push((*prefix, seated));
+            }
+        }
+        self.groups.retain(|prefix, _| net.groups().contains_key(prefix));
+        promoted
+    }
+
+    // Update the seats of one group, with members `members`, at this step. If some promotion
+    // takes effect, returns the seats just before it (those of departed members already empty).
+    fn update_group(&mut self,
+                    prefix: Prefix,
+                    members: &[Member],
+                    step: NN)
+                    -> Option<Vec<NodeName>> {
+        let oldest: Vec<NodeName> =
+            elders_ranked(members, self.elders, self.ordering_cap, self.tiebreak)
+                .iter()
+                .map(|member| member.name)
+                .collect();
+        let seats = match self.groups.entry(prefix) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let _ = entry.insert(Seats {
+                    seated: oldest,
+                    due: HashMap::new(),
+                });
+                return None;
+            }
+        };
+        seats.seated.retain(|name| members.iter().any(|member| member.name == *name));
+        let mut before = None;
+        seats.due.retain(|name, _| oldest.contains(name));
+        // Members most senior first, so that the youngest elder gives up its seat first
+        let ranked = elders_ranked(members, members.len(), self.ordering_cap, self.tiebreak);
+        for name in &oldest {
+            if seats.seated.contains(name) {
+                continue;
+            }
+            let since = *seats.due.entry(*name).or_insert(step);
+            if since + self.lag > step {
+                continue;
+            }
+            let _ = seats.due.remove(name);
+            if before.is_none() {
+                before = Some(seats.seated.clone());
+            }
+            if seats.seated.len() >= self.elders {
+                let displaced = ranked.iter()
+                    .rev()
+                    .map(|member| member.name)
+                    .find(|name| seats.seated.contains(name) && !oldest.contains(name))
+                    .expect("an elder no longer among the oldest");
+                seats.seated.retain(|name| *name != displaced);
+            }
+            seats.seated.push(*name);
+        }
+        before
+    }
+
+    // True if no promotion is pending, so that the seats do not change while the network does not.
+    fn settled(&self) -> bool {
+        self.groups.values().all(|seats| seats.due.is_empty())
+    }
+
+    // The members of a group who may vote: with lagging promotions, its seated elders (the
+    // quorum then finding them all to be elders), otherwise all members.
+    fn voting<'a>(&self, prefix: &Prefix, group: &'a Group) -> Cow<'a, Group> {
+        match self.groups.get(prefix) {
+            Some(seats) if self.lag > 0 => {
+                Cow::Owned(group.iter()
+                    .filter(|&(name, _)| seats.seated.contains(name))
+                    .map(|(name, data)| (*name, *data))
+                    .collect())
+            }
+            _ => Cow::Borrowed(group),
+        }
+    }
+}
+
+#[test]
+fn test_elder_roster() {
+    // Seated elders of a group at each step, with 3 elders and promotions lagging 2 steps.
+    // Members are named by seniority: 1 is oldest.
+    let member = |name: NodeName| {
+        Member {
+            name: name,
+            age: 100 - name as u32,
+            capacity: 1.0,
+            joined: 0,
+        }
+    };
+    let prefix = Prefix::default();
+    let mut roster = ElderRoster::new(2, 3, None, ElderTiebreak::Name);
+    let mut members: Vec<Member> = (1..7).map(&member).collect();
+    let mut seated = vec![];
+    let mut promotions = vec![];
+    for step in 0..11 {
+        match step {
+            // Elder 1 leaves: 4 is due, and promoted at step 3
+            1 => members.retain(|m| m.name != 1),
+            // Elder 2 leaves, then 5 (due at step 4) before its promotion: 6 is due instead,
+            // and promoted at step 7
+            4 => members.retain(|m| m.name != 2),
+            5 => members.retain(|m| m.name != 5),
+            // An older member joins: it is promoted at step 10, displacing the youngest elder
+            8 => members.push(member(0)),
+            _ => {}
+        }
+        if let Some(mut before) = roster.update_group(prefix, &members, step as NN) {
+            before.sort();
+            promotions.push((step, before));
+        }
+        let mut names = roster.groups[&prefix].seated.clone();
+        names.sort();
+        seated.push(names);
+    }
+    assert_eq!(seated,
+               vec![vec![1, 2, 3],
+                    vec![2, 3],
+                    vec![2, 3],
+                    vec![2, 3, 4],
+                    vec![3, 4],
+                    vec![3, 4],
+                    vec![3, 4],
+                    vec![3, 4, 6],
+                    vec![3, 4, 6],
+                    vec![3, 4, 6],
+                    vec![0, 3, 4]]);
+    // Promotions are reported with the seats before them
+    assert_eq!(promotions,
+               vec![(3, vec![2, 3]), (7, vec![3, 4]), (10, vec![3, 4, 6])]);
+
+    // Only the seated elders vote
+    let mut group = HashMap::new();
+    for m in &members {
+        group.insert(m.name, NodeData::new());
+    }
+    let mut voting: Vec<NodeName> = roster.voting(&prefix, &group).keys().cloned().collect();
+    voting.sort();
+    assert_eq!(voting, vec![0, 3, 4]);
+
+    // Without lag, the seats are not tracked and all members vote
+    let mut roster = ElderRoster::new(0, 3, None, ElderTiebreak::Name);
+    let _ = roster.update_group(prefix, &members, 0);
+    assert_eq!(roster.voting(&prefix, &group).len(), members.len());
+}
+
+// The name a malicious node joining the group with prefix `prefix` grinds for (see
+// `--name-grinding`): the lowest name of the prefix not `taken` (by a member or a node waiting to
+// join), so that it wins every tie of age when elders are ranked by name.
+fn ground_name<'a, I>(prefix: Prefix, taken: I) -> NodeName
+    where I: IntoIterator<Item = &'a NodeName>
+{
+    let taken: HashSet<&NodeName> = taken.into_iter().collect();
+    let mut name = prefix.lowest_name();
+    while taken.contains(&name) {
+        name += 1;
+    }
+    name
+}
+
+#[test]
+fn test_ground_name() {
+    // Four honest members of equal age, joined in order of name, and 3 elders
+    let member = |name: NodeName, joined: NN| {
+        Member {
+            name: name,
+            age: 5,
+            capacity: 1.0,
+            joined: joined,
+        }
+    };
+    let prefix = Prefix::default();
+    let mut members: Vec<Member> = vec![member(5, 0), member(9, 1), member(12, 2), member(20, 3)];
+    let names: Vec<NodeName> = members.iter().map(|m| m.name).collect();
+    let ground = ground_name(prefix, &names);
+    assert_eq!(ground, 0);
+    assert_eq!(ground_name(prefix, &[0, 1, 3]), 2);
+
+    // Joining last, the ground name wins a seat when ties go by name, not by join order
+    members.push(member(ground, 4));
+    let seated = |members: &[Member], tiebreak| -> Vec<NodeName> {
+        elders_ranked(members, 3, None, tiebreak).iter().map(|m| m.name).collect()
+    };
+    assert_eq!(seated(&members, ElderTiebreak::Name), vec![0, 5, 9]);
+    assert_eq!(seated(&members, ElderTiebreak::JoinOrder), vec![5, 9, 12]);
+    // An older member still outranks it
+    members[3].age = 6;
+    assert_eq!(seated(&members, ElderTiebreak::Name), vec![20, 0, 5]);
+}
+
+// The membership of each group as messages are validated against it, `lag` steps stale (see
+// `--membership-lag`): a node counts as a member from `lag` steps after joining, and a departed
+// member, with its data as it left, until `lag` steps after leaving. Nodes are tracked by name,
+// so a relocated node leaves under its old name and joins under its new one. Members of the
+// initial network count at once. The lagged membership of a group is derived from its current
+// members, which count if they joined long enough ago (see `NodeData::joined`), and a rolling log
+// of the nodes which joined or left within the last `lag` steps, so the memory needed is
+// proportional to churn × lag. With no lag, nothing is tracked and the membership is that of the
+// network.
+struct MembershipHistory {
+    lag: NN,
+    // The step last updated at
+    step: NN,
+    // Nodes count once they joined before this many were added to the network
+    counted: NN,
+    // The number of nodes added to the network as at the last update
+    joins: NN,
+    // Departed members, with the step until which each counts and its data, earliest departure
+    // first
+    departed: VecDeque<(NN, NodeName, NodeData)>,
+    // Members not yet counting, with the step from which each does and when it joined, earliest
+    // first
+    arriving: VecDeque<(NN, NodeName, NN)>,
+}
+
+impl MembershipHistory {
+    // History of the initial network `net`, lagging by `lag` steps. With a lag, `net` records
+    // its departures from now on.
+    fn new(lag: NN, net: &mut Network<RestrictOnePerAge>) -> Self {
+        if lag > 0 {
+            net.record_departures();
+        }
+        MembershipHistory {
+            lag: lag,
+            step: 0,
+            counted: net.joins(),
+            joins: net.joins(),
+            departed: VecDeque::new(),
+            arriving: VecDeque::new(),
+        }
+    }
+
+    // Update at `step` from the joins and departures of `net` since the last update, given the
+    // groups `changed` (as by `Network::take_touched`), and add to them the groups whose
+    // membership changes at this step though the network's did not (as a join or departure `lag`
+    // steps ago takes effect).
+    fn update(&mut self,
+              net: &mut Network<RestrictOnePerAge>,
+              changed: &mut HashSet<Prefix>,
+              step: NN) {
+        self.step = step;
+        if self.lag == 0 {
+            return;
+        }
+        // Nodes which joined since are members of changed groups (unless they left again, so
+        // never count)
+        let joins = self.joins;
+        let mut joined: Vec<(NN, NodeName)> = changed.iter()
+            .filter_map(|prefix| net.groups().get(prefix))
+            .flat_map(|group| group.iter())
+            .filter(|&(_, data)| data.joined() >= joins)
+            .map(|(name, data)| (data.joined(), *name))
+            .collect();
+        joined.sort();
+        for (joined, name) in joined {
+            self.arriving.push_back((step + self.lag, name, joined));
+        }
+        self.joins = net.joins();
+        for (name, data) in net.take_departures() {
+            self.departed.push_back((step + self.lag, name, data));
+        }
+        while self.departed.front().map_or(false, |&(until, _, _)| until <= step) {
+            let (_, name, _) = self.departed.pop_front().expect("departed");
+            changed.insert(net.find_prefix(name));
+        }
+        while self.arriving.front().map_or(false, |&(from, _, _)| from <= step) {
+            let (_, name, joined) = self.arriving.pop_front().expect("arriving");
+            self.counted = max(self.counted, joined + 1);
+            changed.insert(net.find_prefix(name));
+        }
+    }
+
+    // True if no join or departure is yet to take effect.
+    fn settled(&self) -> bool {
+        self.departed.is_empty() && self.arriving.is_empty()
+    }
+
+    // The members of the group `group` with prefix `prefix`, as at the last update.
+    fn members<'a>(&self, prefix: &Prefix, group: &'a Group) -> Cow<'a, Group> {
+        if self.lag == 0 {
+            return Cow::Borrowed(group);
+        }
+        let counted = self.counted;
+        let mut lagged: Group = group.iter()
+            .filter(|&(_, data)| data.joined() < counted)
+            .map(|(name, data)| (*name, *data))
+            .collect();
+        lagged.extend(self.departed
+            .iter()
+            .filter(|&&(_, name, data)| data.joined() < counted && prefix.matches(name))
+            .map(|&(_, name, data)| (name, data)));
+        Cow::Owned(lagged)
+    }
+}
+
+#[test]
+fn test_membership_history() {
+    // Lagging 3 steps: nodes 1 and 2 are in the initial network, 3 joins at step 1, 1 leaves at
+    // step 2, and 4 joins at step 3 and leaves at step 4, so counts only from its lagged join to
+    // its lagged departure
+    let mut net = Network::<RestrictOnePerAge>::new(8);
+    let prefix = Prefix::default();
+    for name in 1..3 {
+        assert!(net.add_node(name, NodeData::new()).is_ok());
+    }
+    let _ = net.take_touched();
+    let mut history = MembershipHistory::new(3, &mut net);
+    let mut members = vec![];
+    for step in 0..8 {
+        match step {
+            1 => assert!(net.add_node(3, NodeData::new()).is_ok()),
+            2 => {
+                let _ = net.remove_node(1).expect("remove");
+            }
+            3 => assert!(net.add_node(4, NodeData::new()).is_ok()),
+            4 => {
+                let _ = net.remove_node(4).expect("remove");
+            }
+            _ => {}
+        }
+        let mut changed = net.take_touched();
+        history.update(&mut net, &mut changed, step);
+        let mut names: Vec<NodeName> =
+            history.members(&prefix, &net.groups()[&prefix]).keys().cloned().collect();
+        names.sort();
+        members.push((step, changed.contains(&prefix), names));
+    }
+    assert_eq!(members,
+               vec![(0, false, vec![1, 2]),
+                    (1, true, vec![1, 2]),
+                    (2, true, vec![1, 2]),
+                    (3, true, vec![1, 2]),
+                    (4, true, vec![1, 2, 3]),
+                    (5, true, vec![2, 3]),
+                    (6, true, vec![2, 3, 4]),
+                    (7, true, vec![2, 3])]);
+
+    // Without lag, the members are those of the network
+    let history = MembershipHistory::new(0, &mut net);
+    assert_eq!(history.members(&prefix, &net.groups()[&prefix]).len(), 2);
+}
+
+#[test]
+fn test_membership_lag() {
+    use super::quorum::SimpleTargettedAttack;
+    use super::rng::Seeding;
+
+    // Under the join-leave attack, malicious nodes relocated out of the target reset and rejoin.
+    // A lagging membership still counts the departed for a while, but only counts those joining
+    // after as long, so it is the membership of `lag` steps before: over the same steps, long
+    // enough for the runs to end, compromise is no less likely as the lag grows (in fact, just
+    // as likely, only later)
+    let p_compromise = |lag: NN| {
+        let mut total = 0.0;
+        for seed in 0..5 {
+            let mut args = test_args(100, 20, 8);
+            args.max_steps = 300;
+            args.repetitions = 10;
+            args.membership_lag = lag;
+            args.seeding = Seeding {
+                seed: Some(seed),
+                fixed: vec![],
+            };
+            total += FullSimTool::new(args, SimpleQuorum::new(), SimpleTargettedAttack::new())
+                .calc_p_compromise()
+                .p_compromise;
+        }
+        total / 5.0
+    };
+    let probs: Vec<RR> = [0, 2, 5, 10].iter().map(|&lag| p_compromise(lag)).collect();
+    assert!(probs.windows(2).all(|w| w[0] <= w[1]), "P(compromise) by lag {:?}", probs);
+    assert_eq!(probs[0], probs[probs.len() - 1], "P(compromise) by lag {:?}", probs);
+}
+
+/// Steps for which the identities of departed nodes are remembered, by default
+pub const DEFAULT_REJOIN_WINDOW: NN = 100;
+
+// The identities of malicious nodes which have left the network (reset after relocation, in a
+// join-leave attack), with their ages. A new malicious node may take one to rejoin with part of
+// its former age (see `--rejoin-age-recovery`), the identity keeping its full age for the next
+// time it leaves (see `NodeData::identity_age`). Identities are forgotten once `window` steps
+// old, which bounds the memory needed. Honest nodes which leave never rejoin, so are not kept.
+struct DepartedRegistry {
+    recovery: RR,
+    window: NN,
+    // Step of departure and age of each identity
+    departed: Vec<(NN, u32)>,
+}
+
+impl DepartedRegistry {
+    fn new(recovery: RR, window: NN) -> Self {
+        DepartedRegistry {
+            recovery: recovery,
+            window: window,
+            departed: vec![],
+        }
+    }
+
+    // Remember an identity of age `age` departing at `step` (unless no age is recovered).
+    fn depart(&mut self, step: NN, age: u32) {
+        if self.recovery > 0.0 {
+            self.departed.push((step, age));
+        }
+    }
+
+    // Take the remembered identity of greatest age (the latest to depart, of equals) to rejoin
+    // at `step`, forgetting those too old. Returns its step of departure and age, so that it may
+    // be given back if not used.
+    fn rejoin(&mut self, step: NN) -> Option<(NN, u32)> {
+        let window = self.window;
+        self.departed.retain(|&(departed, _)| departed + window > step);
+        let index = self.departed
+            .iter()
+            .enumerate()
+            .max_by_key(|&(i, &(_, age))| (age, i))
+            .map(|(i, _)| i);
+        index.map(|i| self.departed.remove(i))
+    }
+
+    // Age of an identity of age `age` on rejoining (see `recovered_age`)
+    fn recovered_age(&self, age: u32) -> u32 {
+        recovered_age(self.recovery, age)
+    }
+}
+
+/// Age of a malicious identity of age `age` on rejoining after a reset, when
+/// it recovers the proportion `recovery` of its age (rounded)
+pub fn recovered_age(recovery: RR, age: u32) -> u32 {
+    (recovery * age as RR).round() as u32
+}
+
+#[test]
+fn test_departed_registry() {
+    let mut registry = DepartedRegistry::new(0.5, 10);
+    registry.depart(0, 4);
+    registry.depart(3, 2);
+    registry.depart(5, 4);
+    // The oldest, latest first; then expired
+    assert_eq!(registry.rejoin(6), Some((5, 4)));
+    registry.depart(5, 4);
+    assert_eq!(registry.rejoin(9), Some((5, 4)));
+    assert_eq!(registry.rejoin(10), Some((3, 2)));
+    assert_eq!(registry.rejoin(10), None);
+    assert_eq!(registry.recovered_age(3), 2);
+    assert_eq!(registry.recovered_age(1), 1);
+
+    // Nothing is kept if nothing would be recovered
+    let mut registry = DepartedRegistry::new(0.0, 10);
+    registry.depart(0, 4);
+    assert_eq!(registry.rejoin(1), None);
+}
+
+/// Proportion of the total age of a group held by malicious nodes. A group
+/// with total age zero has share zero.
+pub fn age_share(group: &Group) -> RR {
+    let total: u32 = group.values().map(|data| data.age()).sum();
+    if total == 0 {
+        return 0.0;
+    }
+    let malicious: u32 =
+        group.values().filter(|data| data.is_malicious()).map(|data| data.age()).sum();
+    (malicious as RR) / (total as RR)
+}
+
+#[test]
+fn test_age_share() {
+    let mut group = Group::new();
+    assert_eq!(age_share(&group), 0.0);
+    group.insert(0, NodeData::new_malicious());
+    group.insert(1, NodeData::new());
+    assert_eq!(age_share(&group), 0.0);
+    // Churn until some node has aged
+    use rand::thread_rng;
+    let mut rng = thread_rng();
+    let mut net = Network::<NoAddRestriction>::new(100);
+    for name in 0..20 {
+        let data = if name < 5 {
+            NodeData::new_malicious()
+        } else {
+            NodeData::new()
+        };
+        let _ = net.add_node(name, data);
+        let prefix = net.find_prefix(name);
+        let _ = net.churn(prefix, name, &mut rng);
+    }
+    for group in net.groups().values() {
+        let share = age_share(group);
+        assert!(share >= 0.0 && share <= 1.0, "share {}", share);
+    }
+}
+
+
+/// Number of buckets in a `MaliceHist`; each covers 5%.
+pub const MALICE_HIST_BUCKETS: usize = 20;
+
+/// Histogram of the proportion of malicious nodes in each group.
+///
+/// Only a count per bucket is stored, so memory use does not depend on the
+/// number of groups or repetitions. Bucket `i` covers proportions in the range
+/// `[i × 5%, (i + 1) × 5%)`, except that the last bucket also includes 100%.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaliceHist {
+    pub counts: [NN; MALICE_HIST_BUCKETS],
+}
+
+impl MaliceHist {
+    /// New, empty, histogram
+    pub fn new() -> Self {
+        MaliceHist { counts: [0; MALICE_HIST_BUCKETS] }
+    }
+
+    /// Count one group. Empty groups are counted in the first bucket.
+    pub fn add_group(&mut self, group: &Group) {
+        let all = group.len();
+        let bad = group.values().filter(|data| data.is_malicious()).count();
+        let i = if all == 0 {
+            0
+        } else {
+            min(bad * MALICE_HIST_BUCKETS / all, MALICE_HIST_BUCKETS - 1)
+        };
+        self.counts[i] += 1;
+    }
+}
+
+#[test]
+fn test_malice_hist_buckets() {
+    let mut group = Group::new();
+    let mut hist = MaliceHist::new();
+    hist.add_group(&group);
+    for i in 0..20 {
+        let data = if i < 7 {
+            NodeData::new_malicious()
+        } else {
+            NodeData::new()
+        };
+        group.insert(i, data);
+    }
+    hist.add_group(&group);     // 35%
+    for i in 0..20 {
+        group.insert(i, NodeData::new_malicious());
+    }
+    hist.add_group(&group);     // 100%
+    assert_eq!(hist.counts[0], 1);
+    assert_eq!(hist.counts[7], 1);
+    assert_eq!(hist.counts[MALICE_HIST_BUCKETS - 1], 1);
+    assert_eq!(hist.counts.iter().sum::<NN>(), 3);
+}
+
+
+pub trait Tool {
+    /// Print a message about the computation (does not include parameters).
+    fn print_message(&self);
+
+    /// Calculate the probability of compromise (range: 0 to 1).
+    fn calc_p_compromise(&self) -> SimResult;
+
+    /// As `calc_p_compromise`, also returning the wall time taken in seconds.
+    fn timed_calc(&self) -> (SimResult, RR) {
+        let start = Instant::now();
+        let result = self.calc_p_compromise();
+        let elapsed = start.elapsed();
+        (result, elapsed.as_secs() as RR + (elapsed.subsec_nanos() as RR) * 1e-9)
+    }
+
+    /// Progress with no repetitions completed (or as saved in a checkpoint),
+    /// from which to continue with `calc_until`.
+    fn start(&self) -> Progress {
+        Progress::new(Reports::default())
+    }
+
+    /// Continue until `progress` counts `repetitions` completed (at most the
+    /// number requested), and return the results so far. Running all
+    /// repetitions in several parts gives the same results as at once. Tools
+    /// which do not repeat simulations ignore the progress and calculate the
+    /// final results.
+    fn calc_until(&self, _progress: &mut Progress, _repetitions: NN) -> SimResult {
+        self.calc_p_compromise()
+    }
+
+    /// The step at which each repetition, in order, was compromised (`None` if
+    /// it was not), running them as `calc_p_compromise` does. `None` for tools
+    /// which do not simulate steps.
+    fn compromise_steps(&self) -> Option<Vec<Option<NN>>> {
+        None
+    }
+
+    /// The results under the tool's own quorum and then each quorum swept
+    /// (see `FullSimTool::sweep_quorums`), from the same runs. Tools sweeping
+    /// no quorums give those of `calc_p_compromise` alone.
+    fn calc_sweep(&self) -> Vec<SimResult> {
+        vec![self.calc_p_compromise()]
+    }
+}
+
+
+// Probability of any group being affected, given the probability `p` for one
+// group, with `n` nodes in groups of size `k` treated as independent.
+fn any_group_independent(p: RR, n: NN, k: NN) -> RR {
+    1.0 - (1.0 - p).powf((n as RR) / (k as RR))
+}
+
+// Upper bounds `(p_disrupt, p_compromise)` under simple targetting with random relocation, over
+// `steps` steps, for `n` nodes of which `r` are malicious and groups of at least `k` nodes, where
+// a group of `k` has quorum size `q` and needs `q_honest` honest nodes to avoid disruption.
+//
+// The attacker can send new nodes to its target group at will, but node ageing admits at most
+// `MAX_YOUNG_PER_AGE` members of age 0 to a group, and nodes only age by relocation. Every other
+// malicious member was relocated there, to a random name: a group splits before it holds
+// `2 (k + 1)` nodes, so each relocation lands in it with probability about `p = 2 (k + 1) / n` at
+// most, independently of the others.
+//
+// The number of relocations is not bounded by the number of steps: a relocated node rejoins in
+// the same step, and its join may relocate another, so chains of relocations have no fixed
+// length. Misses are bounded instead. A malicious node relocated anywhere but the target is reset,
+// and redeploys the next step at the earliest, so at most `r * steps` relocations of malicious
+// nodes miss the target in all. At least `m = needed - MAX_YOUNG_PER_AGE` of them must land for a
+// group to have `needed` malicious members. That happens only if `m` relocations land before the
+// `r * steps + 1`-th miss, which means at least `m` land among the first `r * steps + m`:
+//
+//     P(compromise) <= P(Binomial(r * steps + m, p) >= m)
+//
+// with `needed = q`, and P(disruption) likewise with `needed = k - q_honest + 1`; each is 0 if
+// there are fewer than that many malicious nodes in all. A larger group needs at least as many
+// malicious nodes for either (neither the quorum size nor the group size less the honest nodes
+// needed decreases with group size). This holds for the simple quorum only; an elder quorum may
+// need fewer malicious nodes.
+fn targetted_bound(n: NN, r: NN, k: NN, q: NN, q_honest: NN, steps: NN) -> (RR, RR) {
+    let bound = |needed: NN| {
+        if r < needed {
+            return 0.0;
+        }
+        prob_landed_at_least(n, r, k, steps, needed.saturating_sub(MAX_YOUNG_PER_AGE as NN))
+    };
+    (bound((k + 1).saturating_sub(q_honest)), bound(q))
+}
+
+// Upper bound on the probability that at least `landed` relocations of malicious nodes land in
+// the target group (see `targetted_bound`)
+fn prob_landed_at_least(n: NN, r: NN, k: NN, steps: NN, landed: NN) -> RR {
+    let p_landing = (2.0 * (k + 1) as RR / n as RR).min(1.0);
+    prob_binomial_at_least(r * steps + landed, p_landing, landed)
+}
+
+// Group size assumed by the direct calculation with `n` nodes and minimum group size `k`: `k`,
+// except while the network bootstraps. With fewer than `2 k` nodes it cannot split, so is one
+// group of all nodes (below the minimum size if `n < k`).
+fn calc_group_size(n: NN, k: NN) -> NN {
+    if n < 2 * k { n } else { k }
+}
+
+// Number of honest nodes needed to avoid disruption of a group of size `k` with quorum size `q`:
+// `q`, unless a blocking proportion is given.
+fn honest_needed(args: &ToolArgs, k: NN, q: NN) -> NN {
+    args.block_prop.map_or(q, |block| k - max_unblocked(block, k))
+}
+
+// Whether a group with `x` malicious members and `h` honest members taking part in a decision is
+// `(disrupted, compromised)`, the quorum size of `s` nodes taking part being `quorum(s)`. A group
+// with nobody taking part decides nothing, so is disrupted.
+fn thinned_check<F: Fn(NN) -> NN>(args: &ToolArgs, quorum: &F, x: NN, h: NN) -> (bool, bool) {
+    let s = x + h;
+    if s == 0 {
+        return (true, false);
+    }
+    let q = quorum(s);
+    (h < honest_needed(args, s, q), x >= q)
+}
+
+// Probabilities `(disruption, compromise)` of a group of size `k`, the quorum size of `s` nodes
+// being `quorum(s)`, with each honest member taking part in a decision with probability
+// `args.participation` (see `prob::prob_thinned`). With every one taking part, those of the cache.
+fn group_probs<F: Fn(NN) -> NN>(args: &ToolArgs, k: NN, quorum: F) -> (RR, RR) {
+    group_probs_among(args, args.num_nodes, args.num_malicious, k, quorum)
+}
+
+// `group_probs` of a group drawn from `n` nodes of which `r` are malicious
+fn group_probs_among<F>(args: &ToolArgs, n: NN, r: NN, k: NN, quorum: F) -> (RR, RR)
+    where F: Fn(NN) -> NN
+{
+    if args.participation == 1.0 {
+        let q = quorum(k);
+        let q_honest = honest_needed(args, k, q);
+        return (args.prob_cache.prob_disruption(n, r, k, q_honest),
+                args.prob_cache.prob_compromise(n, r, k, q));
+    }
+    let p = args.participation;
+    (prob_thinned(n, r, k, p, |x, h| thinned_check(args, &quorum, x, h).0),
+     prob_thinned(n, r, k, p, |x, h| thinned_check(args, &quorum, x, h).1))
+}
+
+// Probability that a group of size `k` is disrupted or compromised, which is how the full
+// simulation counts disrupted groups, given its `probs` of `group_probs`. With every one taking
+// part, both are of the malicious members reaching a threshold, so one event includes the other.
+fn group_prob_either<F>(args: &ToolArgs, k: NN, quorum: F, probs: (RR, RR)) -> RR
+    where F: Fn(NN) -> NN
+{
+    group_prob_either_among(args, args.num_nodes, args.num_malicious, k, quorum, probs)
+}
+
+// `group_prob_either` of a group drawn from `n` nodes of which `r` are malicious
+fn group_prob_either_among<F>(args: &ToolArgs, n: NN, r: NN, k: NN, quorum: F, probs: (RR, RR))
+                              -> RR
+    where F: Fn(NN) -> NN
+{
+    if args.participation == 1.0 {
+        return probs.0.max(probs.1);
+    }
+    prob_thinned(n, r, k, args.participation, |x, h| {
+        let (disrupted, compromised) = thinned_check(args, &quorum, x, h);
+        disrupted || compromised
+    })
+}
+
+// Probabilities `(disruption, compromise, either)` of a group of size `k` known to hold `x`
+// malicious members, as of `group_probs` and `group_prob_either`
+fn placed_group_probs<F: Fn(NN) -> NN>(args: &ToolArgs, k: NN, x: NN, quorum: F) -> (RR, RR, RR) {
+    let p = args.participation;
+    (prob_taking_part(k - x, p, |h| thinned_check(args, &quorum, x, h).0),
+     prob_taking_part(k - x, p, |h| thinned_check(args, &quorum, x, h).1),
+     prob_taking_part(k - x, p, |h| {
+        let (disrupted, compromised) = thinned_check(args, &quorum, x, h);
+        disrupted || compromised
+    }))
+}
+
+
+/// Simplest tool: assumes all groups have minimum size; cannot simulate
+/// targeting or ageing.
+pub struct DirectCalcTool {
+    args: ToolArgs,
+    quorum: SimpleQuorum,
+}
+
+impl DirectCalcTool {
+    pub fn new(args: ToolArgs) -> Self {
+        let mut quorum = SimpleQuorum::from(args.quorum);
+        quorum.set_basis_size(args.quorum_basis.basis_size(args.min_group_size));
+        quorum.set_quorum_floor(args.quorum_floor);
+        DirectCalcTool {
+            args: args,
+            quorum: quorum,
+        }
+    }
+
+    // Quorum size of a group of `k` nodes (taking part)
+    fn quorum_size(&self, k: NN) -> NN {
+        self.quorum.quorum_size(k).expect("simple quorum size")
+    }
+
+    // Like `targetted_bound`, upper bounds on the probabilities that the target group is disrupted
+    // or compromised over `steps` steps, as honest members take part in a decision with
+    // probability `args.participation`: no longer certain once enough malicious nodes land. Both
+    // only grow with the malicious members of the group, so its members are bounded as there:
+    // `MAX_YOUNG_PER_AGE` joining directly plus those relocations landing (at most `r` and `k`
+    // in all).
+    fn thinned_bound(&self, n: NN, r: NN, k: NN, steps: NN) -> (RR, RR) {
+        let landed_at_least = |landed| prob_landed_at_least(n, r, k, steps, landed);
+        let quorum = |s| self.quorum_size(s);
+        let p = self.args.participation;
+        let (mut pd, mut pc) = (0.0, 0.0);
+        let young = min(MAX_YOUNG_PER_AGE as NN, min(r, k));
+        for placed in young...min(r, k) {
+            // Probability of exactly `placed` members (at least, for the last)
+            let landed = placed - young;
+            let p_placed = if placed == min(r, k) {
+                landed_at_least(landed)
+            } else if placed == young {
+                1.0 - landed_at_least(1)
+            } else {
+                landed_at_least(landed) - landed_at_least(landed + 1)
+            };
+            let check = |h| thinned_check(&self.args, &quorum, placed, h);
+            pd += p_placed * prob_taking_part(k - placed, p, |h| check(h).0);
+            pc += p_placed * prob_taking_part(k - placed, p, |h| check(h).1);
+        }
+        (pd, pc)
+    }
+
+    // The corrected model (see `prob::prob_all_groups_at_most`) of `n_groups` groups of `k` nodes,
+    // as honest members take part in a decision with probability `args.participation`: a group
+    // with `x` malicious members is unaffected with the probability that neither too few nor
+    // enough of its honest members take part.
+    fn thinned_corrected(&self, r: NN, k: NN, n_groups: NN) -> (RR, RR, RR) {
+        let n = self.args.num_nodes;
+        let quorum = |s| self.quorum_size(s);
+        let p = self.args.participation;
+        let mut no_disruption = vec![];
+        let mut no_compromise = vec![];
+        for x in 0...k {
+            let check = |h| thinned_check(&self.args, &quorum, x, h);
+            no_disruption.push(1.0 - prob_taking_part(k - x, p, |h| check(h).0));
+            no_compromise.push(1.0 - prob_taking_part(k - x, p, |h| check(h).1));
+        }
+        (1.0 - prob_all_groups_unaffected(n, r, k, n_groups, &no_disruption),
+         1.0 - prob_all_groups_unaffected(n, r, k, n_groups, &no_compromise),
+         n_groups as RR)
+    }
+
+    /// The calculation step by step
+    pub fn breakdown(&self) -> CalcBreakdown {
+        let n = self.args.num_nodes;
+        let r = self.args.num_malicious;
+        let k = calc_group_size(n, self.args.min_group_size);
+        let q = self.quorum.quorum_size(k).expect("simple quorum size");
+        let q_honest = honest_needed(&self.args, k, q);
+        let (pd, pc) = group_probs(&self.args, k, |s| self.quorum_size(s));
+        CalcBreakdown {
+            num_nodes: n,
+            num_malicious: r,
+            min_group_size: self.args.min_group_size,
+            group_size: k,
+            quorum: q,
+            min_quorum: self.quorum
+                .quorum_size(self.args.min_group_size)
+                .expect("simple quorum size"),
+            honest_needed: q_honest,
+            p_disrupt_group: pd,
+            p_compromise_group: pc,
+            result: self.calc_p_compromise(),
+        }
+    }
+}
+
+impl Tool for DirectCalcTool {
+    fn print_message(&self) {
+        println!("Tool: calculate probability of compromise, assuming all groups have minimum \
+                  size");
+        let any_group = true;   // only support this now
+        if any_group {
+            println!("Output: the probability that at least one group is compromised");
+        } else {
+            println!("Output: chance of a randomly selected group being compromised");
+        }
+    }
+
+    fn calc_p_compromise(&self) -> SimResult {
+        let n = self.args.num_nodes;
+        let r = self.args.num_malicious;
+        let k = calc_group_size(n, self.args.min_group_size);
+        let q = self.quorum.quorum_size(k).expect("simple quorum size");
+        let q_honest = honest_needed(&self.args, k, q);
+        if self.args.targetted_bound {
+            let (p_disrupt, p_compromise) = if self.args.participation == 1.0 {
+                targetted_bound(n, r, k, q, q_honest, self.args.max_steps)
+            } else {
+                self.thinned_bound(n, r, k, self.args.max_steps)
+            };
+            return SimResult {
+                p_disrupt: p_disrupt,
+                p_compromise: p_compromise,
+                groups: (n as RR) / (k as RR),
+                groups_range: None,
+                deployed: None,
+                malice_hist: None,
+                elders: None,
+                models: None,
+                correction: None,
+                age_share: None,
+                liveness: None,
+                outcomes: None,
+                termination: None,
+                governance: None,
+                spread: None,
+                coverage: None,
+                close_group: None,
+                turnover: None,
+                steady_state: None,
+                attack_record: None,
+                rng_draws: None,
+                runs: None,
+                truncated: false,
+                split_check: None,
+            };
+        }
+        let probs = &self.args.prob_cache;
+        let (pd, pc) = group_probs(&self.args, k, |s| self.quorum_size(s));
+
+        trace!("n: {}, r: {}, k: {}, q: {}, pd: {:.e}, pc: {:.e}", n, r, k, q, pd, pc);
+
+        let any_group = true;   // only support this now
+        if !any_group {
+            return SimResult {
+                p_disrupt: pd,
+                p_compromise: pc,
+                groups: (n as RR) / (k as RR),
+                groups_range: None,
+                deployed: None,
+                malice_hist: None,
+                elders: None,
+                models: None,
+                correction: None,
+                age_share: None,
+                liveness: None,
+                outcomes: None,
+                termination: None,
+                governance: None,
+                spread: None,
+                coverage: None,
+                close_group: None,
+                turnover: None,
+                steady_state: None,
+                attack_record: None,
+                rng_draws: None,
+                runs: None,
+                truncated: false,
+                split_check: None,
+            };
+        }
+
+        let independent = || {
+            (any_group_independent(pd, n, k),
+             any_group_independent(pc, n, k),
+             (n as RR) / (k as RR))
+        };
+        let corrected = || {
+            // Disruption: more than k - q_honest malicious (fewer than q_honest good) in a
+            // group. Compromise: at least q malicious in a group.
+            let n_groups = n / k;
+            if self.args.participation != 1.0 {
+                return self.thinned_corrected(r, k, n_groups);
+            }
+            let p_disrupt = if q_honest > k {
+                1.0     // no group can have enough honest nodes
+            } else {
+                1.0 - probs.prob_all_groups_at_most(n, r, k, n_groups, k - q_honest)
+            };
+            let p_compromise = if q == 0 {
+                1.0     // zero malicious nodes are enough
+            } else {
+                1.0 - probs.prob_all_groups_at_most(n, r, k, n_groups, q - 1)
+            };
+            (p_disrupt, p_compromise, n_groups as RR)
+        };
+
+        let (p_disrupt, p_compromise, groups) = match self.args.any_group_model {
+            AnyGroupModel::Independent => independent(),
+            AnyGroupModel::Corrected => corrected(),
+        };
+        let models = if self.args.compare_models {
+            let (pd_ind, pc_ind, _) = independent();
+            let (pd_corr, pc_corr, _) = corrected();
+            Some(ModelComparison {
+                p_disrupt_independent: pd_ind,
+                p_compromise_independent: pc_ind,
+                p_disrupt_corrected: pd_corr,
+                p_compromise_corrected: pc_corr,
+            })
+        } else {
+            None
+        };
+        SimResult {
+            p_disrupt: p_disrupt,
+            p_compromise: p_compromise,
+            groups: groups,
+            groups_range: None,
+            deployed: None,
+            malice_hist: None,
+            elders: None,
+            models: models,
+            correction: None,
+            age_share: None,
+            liveness: None,
+            outcomes: None,
+            termination: None,
+            governance: None,
+            spread: None,
+            coverage: None,
+            close_group: None,
+            turnover: None,
+            steady_state: None,
+            attack_record: None,
+            rng_draws: None,
+            runs: None,
+            truncated: false,
+            split_check: None,
+        }
+    }
+}
+
+
+// Arguments for tests: quorum of 0.5, no steps and no repetitions.
+#[cfg(test)]
+fn test_args(num_nodes: NN, num_malicious: NN, min_group_size: NN) -> ToolArgs {
+    ToolArgs {
+        num_nodes: num_nodes,
+        num_malicious: num_malicious,
+        min_group_size: min_group_size,
+        quorum: super::quorum::QuorumSize::Proportion(0.5),
+        any_group: true,
+        max_steps: 0,
+        repetitions: 0,
+        relocation: super::sim::RelocationTarget::Random,
+        placement: super::placement::InitialPlacement::Uniform,
+        malice_hist: false,
+        churn_model: super::churn_model::ChurnModel::Fixed,
+        elders_report: false,
+        any_group_model: AnyGroupModel::Independent,
+        compare_models: false,
+        correction: false,
+        target_prefix: None,
+        age_share_interval: None,
+        liveness: false,
+        outcomes: false,
+        termination: false,
+        governance: false,
+        spread: false,
+        address_coverage: false,
+        close_group: None,
+        turnover: false,
+        steady_state: None,
+        paranoid: Some(1),
+        verify: false,
+        record_attack: false,
+        replay_attack: None,
+        rng_audit: false,
+        time_limit: None,
+        interrupt: super::interrupt::Interrupt::new(),
+        split_check: None,
+        membership_lag: 0,
+        honest_uptime: super::uptime::UptimeDist::new(),
+        weighting: super::quorum::Weighting::Age,
+        age_cap: None,
+        hold: 1,
+        promotion_lag: 0,
+        elder_tiebreak: ElderTiebreak::Name,
+        name_grinding: 0.0,
+        rejoin_recovery: 0.0,
+        rejoin_window: DEFAULT_REJOIN_WINDOW,
+        burst: None,
+        corruption: None,
+        eviction_rate: 0.0,
+        attack_resource: 1.0,
+        pow_scaling: PowScaling::Constant,
+        attack_start: 0,
+        join_cap: None,
+        checkpoint: None,
+        block_prop: None,
+        quorum_basis: super::quorum::QuorumBasis::Actual,
+        quorum_floor: None,
+        quorum_jitter: 0.0,
+        participation: 1.0,
+        seeding: super::rng::Seeding::new(),
+        targetted_bound: false,
+        objective: Objective::Compromise,
+        attacker_knowledge: AttackerKnowledge::Full,
+        prob_cache: ::std::sync::Arc::new(super::prob::ProbCache::new()),
+    }
+}
+
+#[test]
+fn test_direct_calc_groups() {
+    let args = test_args(1000, 100, 10);
+    let result = DirectCalcTool::new(args).calc_p_compromise();
+    assert_eq!(result.groups, 100.0);
+    // Assumed, not simulated, so no range
+    assert_eq!(result.groups_range, None);
+}
+
+#[test]
+fn test_shared_prob_cache() {
+    use std::sync::Arc;
+    use super::prob::ProbCache;
+    use super::quorum::QuorumBasis;
+
+    // Parameter sets differing only in quorum basis (which the calculation does not use)
+    // share the calculated probabilities, with identical results
+    let cache = Arc::new(ProbCache::new());
+    let result = |basis: QuorumBasis, prob_cache: Arc<ProbCache>| {
+        let mut args = test_args(1000, 100, 10);
+        args.quorum_basis = basis;
+        args.prob_cache = prob_cache;
+        DirectCalcTool::new(args).calc_p_compromise()
+    };
+    let first = result(QuorumBasis::Actual, cache.clone());
+    assert_eq!(cache.hits(), 0);
+    let second = result(QuorumBasis::Minimum, cache.clone());
+    assert_eq!((cache.lookups(), cache.hits()), (4, 2));
+    let uncached = result(QuorumBasis::Minimum, Arc::new(ProbCache::disabled()));
+    for other in &[second, uncached] {
+        assert_eq!((first.p_disrupt, first.p_compromise),
+                   (other.p_disrupt, other.p_compromise));
+    }
+}
+
+#[test]
+fn test_quorum_basis_consistent() {
+    use super::quorum::{QuorumBasis, QuorumSize};
+
+    // Quorum 0.55 with minimum group size 8 (5 nodes). DirectCalcTool assumes
+    // groups of the minimum size; the other tools see larger groups too.
+    for &basis in &[QuorumBasis::Actual, QuorumBasis::Minimum] {
+        let args = || {
+            let mut args = test_args(100, 10, 8);
+            args.quorum = QuorumSize::Proportion(0.55);
+            args.quorum_basis = basis;
+            args
+        };
+        let calc = DirectCalcTool::new(args());
+        let structure = SimStructureTool::new(args());
+        let full = FullSimTool::new(args(), SimpleQuorum::new(), UntargettedAttack {});
+        let calc_q = calc.quorum.quorum_size(8).expect("quorum size");
+        assert_eq!(calc_q, 5);
+        for &k in &[8, 11, 15] {
+            let expected = match basis {
+                QuorumBasis::Actual => (0.55 * k as RR).ceil() as NN,
+                QuorumBasis::Minimum => calc_q,
+            };
+            assert_eq!(structure.quorum.quorum_size(k), Some(expected), "{:?} {}", basis, k);
+            assert_eq!(full.quorum.quorum_size(k), Some(expected), "{:?} {}", basis, k);
+            let group: Vec<Member> =
+                (0..k).map(|name| Member::new(name, &NodeData::new())).collect();
+            assert!(full.quorum.is_quorum(&group, &group[..expected as usize]));
+            assert!(!full.quorum.is_quorum(&group, &group[..expected as usize - 1]));
+        }
+    }
+}
+
+#[test]
+fn test_structure_correction() {
+    let mut args = test_args(200, 20, 10);
+    args.correction = true;
+    args.repetitions = 5;
+    let result = SimStructureTool::new(args).calc_p_compromise();
+    let correction = result.correction.expect("correction");
+    let p_calc = DirectCalcTool::new(test_args(200, 20, 10)).calc_p_compromise().p_compromise;
+    assert_eq!(correction.p_calc, p_calc);
+    assert_eq!(result.p_compromise, correction.p_structure);
+    let (ratio, low, high) = correction.ratio.expect("ratio");
+    assert!(low <= ratio && ratio <= high);
+}
+
+#[test]
+fn test_targetted_bound() {
+    use super::quorum::{QuorumSize, SimpleTargettedAttack};
+    use super::rng::Seeding;
+
+    // Quorum 6 of 8: compromise needs 6 malicious members of a group, four of them relocated
+    // there; disruption 3, one of them relocated
+    let bound = |r: NN, steps: NN| {
+        let mut args = test_args(300, r, 8);
+        args.quorum = QuorumSize::Proportion(0.75);
+        args.max_steps = steps;
+        args.targetted_bound = true;
+        DirectCalcTool::new(args).calc_p_compromise()
+    };
+    // One relocation landing, with probability 0.06, before the 31st miss of 3 nodes in 10
+    // steps; too few nodes in all to compromise
+    let three = bound(3, 10);
+    assert!((three.p_disrupt - (1.0 - 0.94f64.powi(31))).abs() < 1e-12);
+    assert_eq!(three.p_compromise, 0.0);
+    // More steps allow more relocations, so a looser bound
+    assert!(bound(8, 1000).p_compromise > bound(8, 20).p_compromise);
+
+    for r in &[2, 8, 12] {
+        let bound = bound(*r, 20);
+        assert!(bound.p_compromise < 1.0);
+        for seed in 0..3 {
+            let mut args = test_args(300, *r, 8);
+            args.quorum = QuorumSize::Proportion(0.75);
+            args.max_steps = 20;
+            args.repetitions = 10;
+            args.seeding = Seeding {
+                seed: Some(seed),
+                fixed: vec![],
+            };
+            let result = FullSimTool::new(args, SimpleQuorum::new(), SimpleTargettedAttack::new())
+                .calc_p_compromise();
+            assert!(result.p_disrupt <= bound.p_disrupt &&
+                    result.p_compromise <= bound.p_compromise,
+                    "r = {}, seed {}: simulated {} / {} above bound",
+                    r,
+                    seed,
+                    result.p_disrupt,
+                    result.p_compromise);
+        }
+    }
+
+    // Many malicious nodes in a small network compromise the target often, relocations
+    // chaining within steps; the bound still holds
+    let args = |targetted_bound| {
+        let mut args = test_args(100, 30, 8);
+        args.max_steps = 50;
+        args.repetitions = 20;
+        args.seeding.seed = Some(1);
+        args.targetted_bound = targetted_bound;
+        args
+    };
+    let result = FullSimTool::new(args(false), SimpleQuorum::new(), SimpleTargettedAttack::new())
+        .calc_p_compromise();
+    let bound = DirectCalcTool::new(args(true)).calc_p_compromise();
+    assert!(result.p_compromise >= 0.1, "P(compromise) {}", result.p_compromise);
+    assert!(bound.p_compromise >= result.p_compromise && bound.p_disrupt >= result.p_disrupt,
+            "simulated {} / {} above bound {} / {}",
+            result.p_disrupt,
+            result.p_compromise,
+            bound.p_disrupt,
+            bound.p_compromise);
+}
+
+#[test]
+fn test_objective() {
+    use super::quorum::{QuorumSize, SimpleTargettedAttack};
+    use super::rng::Seeding;
+
+    // Quorum 0.7 of groups of about 8: disruption needs 3 malicious nodes in a group,
+    // compromise 6. Both objectives target a single group until it is disrupted, so
+    // disruption is at least as likely under the disruption objective, while compromise
+    // is rarer since nodes then go to other groups.
+    let result = |objective: Objective, seed: u64| {
+        let mut args = test_args(100, 20, 8);
+        args.quorum = QuorumSize::Proportion(0.7);
+        args.max_steps = 100;
+        args.repetitions = 10;
+        args.objective = objective;
+        args.seeding = Seeding {
+            seed: Some(seed),
+            fixed: vec![],
+        };
+        FullSimTool::new(args, SimpleQuorum::new(), SimpleTargettedAttack::new())
+            .calc_p_compromise()
+    };
+    let (mut disrupt, mut compromise) = ((0.0, 0.0), (0.0, 0.0));
+    for seed in 0..5 {
+        let (c, d) = (result(Objective::Compromise, seed), result(Objective::Disruption, seed));
+        disrupt.0 += c.p_disrupt;
+        disrupt.1 += d.p_disrupt;
+        compromise.0 += c.p_compromise;
+        compromise.1 += d.p_compromise;
+    }
+    assert!(disrupt.1 >= disrupt.0, "P(disruption) {} / {}", disrupt.1, disrupt.0);
+    assert!(compromise.1 < compromise.0, "P(compromise) {} / {}", compromise.1, compromise.0);
+}
+
+#[test]
+fn test_direct_calc_block_prop() {
+    // A single group of 9 with quorum 0.67 (7 nodes) and blocking proportion 0.34
+    // (prob_compromise requires n - r >= k - q, restricting r to 3-7 here)
+    for r in 3..8 {
+        let mut args = test_args(9, r, 9);
+        args.quorum = super::quorum::QuorumSize::Proportion(0.67);
+        args.block_prop = Some(0.34);
+        let result = DirectCalcTool::new(args).calc_p_compromise();
+        assert_eq!(result.p_disrupt, if r >= 4 { 1.0 } else { 0.0 }, "r = {}", r);
+        assert_eq!(result.p_compromise, if r >= 7 { 1.0 } else { 0.0 }, "r = {}", r);
+    }
+}
+
+#[test]
+fn test_quorum_floor_consistent() {
+    use super::quorum::{AgeQuorum, ElderQuorum, QuorumSize, elders};
+
+    // Quorum 0.66 with floor 5: the floor binds at minimum group size 6 (0.66 of
+    // which is 4 nodes) but not at 10 (7 nodes). Every tool needs the same number.
+    let args = |k: NN| {
+        let mut args = test_args(100, 10, k);
+        args.quorum = QuorumSize::Proportion(0.66);
+        args.quorum_floor = Some(5);
+        args
+    };
+    for &(k, needed) in &[(6, 5), (10, 7)] {
+        let calc = DirectCalcTool::new(args(k));
+        let structure = SimStructureTool::new(args(k));
+        let simple = FullSimTool::new(args(k), SimpleQuorum::new(), UntargettedAttack {});
+        let age = FullSimTool::new(args(k), AgeQuorum::new(), UntargettedAttack {});
+        assert_eq!(calc.quorum.quorum_size(k), Some(needed));
+        assert_eq!(structure.quorum.quorum_size(k), Some(needed));
+        assert_eq!(simple.quorum.quorum_size(k), Some(needed));
+        let group: Vec<Member> = (0..k).map(|name| Member::new(name, &NodeData::new())).collect();
+        for quorum in &[&simple.quorum as &Quorum, &age.quorum as &Quorum] {
+            assert!(quorum.is_quorum(&group, &group[..needed as usize]), "k = {}", k);
+            assert!(!quorum.is_quorum(&group, &group[..needed as usize - 1]), "k = {}", k);
+        }
+    }
+    // Of 6 elders, 0.66 is 4 nodes: the floor binds
+    let elder = FullSimTool::new(args(10), ElderQuorum::with_elders(6), UntargettedAttack {});
+    let group: Vec<Member> = (0..10).map(|name| Member::new(name, &NodeData::new())).collect();
+    let elders = elders(&group, 6);
+    assert!(elder.quorum.is_quorum(&group, &elders[..5]));
+    assert!(!elder.quorum.is_quorum(&group, &elders[..4]));
+
+    // A single group of 6: compromise needs 5 malicious nodes (not 4), and
+    // disruption 2 (fewer than 5 honest)
+    for r in 1..6 {
+        let mut args = test_args(6, r, 6);
+        args.quorum = QuorumSize::Proportion(0.66);
+        args.quorum_floor = Some(5);
+        let result = DirectCalcTool::new(args).calc_p_compromise();
+        assert_eq!(result.p_compromise, if r >= 5 { 1.0 } else { 0.0 }, "r = {}", r);
+        assert_eq!(result.p_disrupt, if r >= 2 { 1.0 } else { 0.0 }, "r = {}", r);
+    }
+}
+
+#[test]
+fn test_direct_calc_models() {
+    let mut args = test_args(105, 40, 10);
+    args.any_group_model = AnyGroupModel::Corrected;
+    args.compare_models = true;
+    let result = DirectCalcTool::new(args).calc_p_compromise();
+    assert_eq!(result.groups, 10.0);
+    let models = result.models.expect("models compared");
+    assert_eq!(result.p_compromise, models.p_compromise_corrected);
+    assert_eq!(result.p_disrupt, models.p_disrupt_corrected);
+    // Same number of groups, so only the dependence between groups differs.
+    let pc = super::prob::prob_compromise(105, 40, 10, 5);
+    assert!(models.p_compromise_corrected > 1.0 - (1.0 - pc).powi(10));
+}
+
+#[test]
+fn test_participation() {
+    use super::quorum::QuorumSize;
+
+    // With every honest member taking part, the thinned probabilities are those of today, with
+    // or without a blocking proportion
+    for &block in &[None, Some(0.34)] {
+        let mut args = test_args(200, 40, 9);
+        args.quorum = QuorumSize::Proportion(0.67);
+        args.block_prop = block;
+        let calc = DirectCalcTool::new(args);
+        let args = &calc.args;
+        let quorum = |s| calc.quorum_size(s);
+        let (pd, pc) = group_probs(args, 9, &quorum);
+        let check = |x, h| thinned_check(args, &quorum, x, h);
+        let thinned_pd = prob_thinned(200, 40, 9, 1.0, |x, h| check(x, h).0);
+        let thinned_pc = prob_thinned(200, 40, 9, 1.0, |x, h| check(x, h).1);
+        assert!((thinned_pd - pd).abs() < 1e-12, "{:?}: {} != {}", block, thinned_pd, pd);
+        assert!((thinned_pc - pc).abs() < 1e-12, "{:?}: {} != {}", block, thinned_pc, pc);
+    }
+
+    // With few honest members taking part, compromise is far more likely, by every tool and
+    // model
+    let args = |participation: RR| {
+        let mut args = test_args(300, 30, 8);
+        args.participation = participation;
+        args.max_steps = 50;
+        args.repetitions = 20;
+        args.seeding.seed = Some(4);
+        args
+    };
+    let calc = |participation: RR, model: AnyGroupModel| {
+        let mut args = args(participation);
+        args.any_group_model = model;
+        DirectCalcTool::new(args).calc_p_compromise()
+    };
+    for &model in &[AnyGroupModel::Independent, AnyGroupModel::Corrected] {
+        let (full, thinned) = (calc(1.0, model), calc(0.3, model));
+        assert!(thinned.p_compromise > full.p_compromise + 0.5,
+                "{:?}: {} / {}",
+                model,
+                thinned.p_compromise,
+                full.p_compromise);
+        assert!(thinned.p_disrupt >= full.p_disrupt);
+    }
+    let structure = |participation: RR| {
+        SimStructureTool::new(args(participation)).calc_p_compromise().p_compromise
+    };
+    assert!(structure(0.3) > structure(1.0) + 0.5);
+    let full = |participation: RR| {
+        FullSimTool::new(args(participation), SimpleQuorum::new(), UntargettedAttack {})
+            .calc_p_compromise()
+            .p_compromise
+    };
+    assert!(full(0.3) > full(1.0) + 0.5, "{} / {}", full(0.3), full(1.0));
+}
+
+
+/// A tool which simulates the group structure (division of nodes in the
+/// network between groups), then does direct calculations based on these
+/// groups. This should be more accurate than DirectCalcTool in "any group"
+/// mode, but has the same limitations.
+///
+/// Does not relocate nodes (node ageing).
+pub struct SimStructureTool {
+    args: ToolArgs,
+    quorum: SimpleQuorum,
+    master_seed: u64,
+}
+
+impl SimStructureTool {
+    pub fn new(args: ToolArgs) -> Self {
+        let mut quorum = SimpleQuorum::from(args.quorum);
+        quorum.set_basis_size(args.quorum_basis.basis_size(args.min_group_size));
+        quorum.set_quorum_floor(args.quorum_floor);
+        let master_seed = args.seeding.master();
+        SimStructureTool {
+            args: args,
+            quorum: quorum,
+            master_seed: master_seed,
+        }
+    }
+
+    // Simulate one network structure, and calculate probabilities for it. Each group's quorum
+    // size is of its jittered proportion, if jittered.
+    fn simulate(&self, repetition: NN) -> SimResult {
+        let mut streams = self.args.seeding.streams(self.master_seed, repetition);
+        let jitter = QuorumJitter::new(self.args.quorum_jitter,
+                                       &self.args.seeding,
+                                       self.master_seed,
+                                       repetition);
+        let net = self.network(&mut streams);
+        self.calc_for(&net, &jitter, &mut streams)
+    }
+
+    // Create a network. Node names are drawn from the structure stream of the
+    // repetition, and which nodes are malicious from the attack stream, then concentrated if so
+    // placed; `calc_for` then counts the group they are concentrated in as holding at least
+    // those placed, the other groups drawing from the remaining nodes.
+    fn network(&self, streams: &mut Streams) -> Network<NoAddRestriction> {
+        // We need an "attack" strategy, though we only support one here
+        let mut attack = UntargettedAttack {};
+
+        let mut net = Network::<NoAddRestriction>::new(self.args.min_group_size as usize);
+        let mut verifier = Verifier::new(self.args.verify, &mut net);
+        let mut remaining = self.args.num_nodes;
+        let mut remaining_malicious = self.args.num_malicious;
+        while remaining > 0 {
+            let name = new_node_name(&mut streams.structure);
+            let malicious = streams.attack.gen_range(0, remaining) < remaining_malicious;
+            let data = if malicious {
+                NodeData::new_malicious()
+            } else {
+                NodeData::new()
+            };
+            match net.add_node(name, data) {
+                Ok(prefix) => {
+                    remaining -= 1;
+                    if malicious {
+                        remaining_malicious -= 1;
+                    }
+                    let _prefix = net.maybe_split(prefix, name, &mut attack);
+                    if let Some(ref mut verifier) = verifier {
+                        let joined = self.args.num_nodes - remaining;
+                        if verifier.due(joined) {
+                            verifier.verify(&mut net, &format!("after {} nodes joined", joined));
+                        }
+                    }
+                }
+                Err(Error::AlreadyExists) => {
+                    continue;
+                }
+                Err(e) => {
+                    panic!("Error adding node: {}", e);
+                }
+            };
+        }
+        let swaps = self.args.placement.swaps(self.args.num_malicious, net.groups());
+        for (malicious, honest) in swaps {
+            net.swap_data(malicious, honest);
+        }
+        if let Some(ref mut verifier) = verifier {
+            verifier.verify(&mut net, "once placed");
+        }
+
+        net
+    }
+
+    // Calculate probabilities for the groups of `net`, their quorums jittered by `jitter`
+    fn calc_for(&self,
+                net: &Network<NoAddRestriction>,
+                jitter: &QuorumJitter,
+                streams: &mut Streams)
+                -> SimResult {
+        let malice_hist = if self.args.malice_hist {
+            let mut hist = MaliceHist::new();
+            for group in net.groups().values() {
+                hist.add_group(group);
+            }
+            Some(hist)
+        } else {
+            None
+        };
+        let spread = if self.args.spread {
+            let (groups, max_cluster) = malicious_spread(net.groups().values());
+            Some(SpreadReport {
+                mean_groups: groups as RR,
+                mean_max_cluster: max_cluster as RR,
+            })
+        } else {
+            None
+        };
+        // Addresses are drawn from the structure stream once the network is built
+        let close_group = self.args.close_group.map(|close_group| {
+            let quorum_size = |k| self.quorum.quorum_size(k).expect("simple quorum size");
+            let counts = close_group.sample(net.groups(), &quorum_size, &mut streams.structure);
+            CloseGroupReport::new(counts, close_group.addresses)
+        });
+
+        let any_group = true;   // only support this now
+        if any_group {
+            // This isn't quite right, since one group not compromised does
+            // tell you _something_ about the distribution of malicious nodes,
+            // thus probabilities are not indepedent. But unless there are a lot
+            // of malicious nodes it should be close.
+            let (n, r) = (self.args.num_nodes, self.args.num_malicious);
+            // Malicious members of the group in which some start concentrated (if any) and their
+            // probabilities: those the group drew, or the number concentrated if more, as the
+            // others trade places with honest members. The other groups draw from the rest.
+            let target = self.args.placement.target(r, net.groups());
+            let cases: Vec<(RR, Option<NN>)> = match target {
+                None => vec![(1.0, None)],
+                Some(prefix) => {
+                    let k = net.groups()[&prefix].len() as NN;
+                    let least = min(self.args.placement.concentrated(r), k);
+                    let drawn = |x: NN| if x > r || k - x > n - r {
+                        0.0
+                    } else {
+                        choose(r, x) * choose(n - r, k - x) / choose(n, k)
+                    };
+                    let placed = |x: NN| if x == least {
+                        (0..least + 1).map(&drawn).sum()
+                    } else {
+                        drawn(x)
+                    };
+                    (least..k + 1)
+                        .map(|x| (placed(x), Some(x)))
+                        .filter(|&(p, _)| p > 0.0)
+                        .collect()
+                }
+            };
+            let mut p_no_disruption = 0.0;
+            let mut p_no_compromise = 0.0;
+            // Expected shares of the address space of disrupted and compromised groups, a
+            // compromised group counting as disrupted too, as in the full simulation
+            let (mut disrupted, mut compromised) = (0.0, 0.0);
+            // In order of prefix, so that the products do not depend on the order of the map
+            let mut prefixes: Vec<&Prefix> = net.groups().keys().collect();
+            prefixes.sort();
+            for (p_case, placed) in cases {
+                let (mut no_disruption, mut no_compromise) = (1.0, 1.0);
+                let (mut case_disrupted, mut case_compromised) = (0.0, 0.0);
+                // Drawn from the nodes outside the target group
+                let (n, r) = match (target, placed) {
+                    (Some(prefix), Some(x)) => (n - net.groups()[&prefix].len() as NN, r - x),
+                    _ => (n, r),
+                };
+                for &prefix in &prefixes {
+                    let k = net.groups()[prefix].len() as NN;
+                    let offset = jitter.offset(*prefix);
+                    let quorum_size = |s| {
+                        self.quorum.quorum_size_offset(s, offset).expect("simple quorum size")
+                    };
+                    let (pd, pc, either) = match placed {
+                        Some(x) if Some(*prefix) == target => {
+                            placed_group_probs(&self.args, k, x, &quorum_size)
+                        }
+                        _ => {
+                            let (pd, pc) = group_probs_among(&self.args, n, r, k, &quorum_size);
+                            let either = if self.args.address_coverage {
+                                group_prob_either_among(&self.args,
+                                                        n,
+                                                        r,
+                                                        k,
+                                                        &quorum_size,
+                                                        (pd, pc))
+                            } else {
+                                0.0
+                            };
+                            (pd, pc, either)
+                        }
+                    };
+                    no_disruption *= 1.0 - pd;
+                    no_compromise *= 1.0 - pc;
+                    if self.args.address_coverage {
+                        let share = address_coverage(iter::once(prefix));
+                        case_disrupted += share * either;
+                        case_compromised += share * pc;
+                    }
+                }
+                p_no_disruption += p_case * no_disruption;
+                p_no_compromise += p_case * no_compromise;
+                disrupted += p_case * case_disrupted;
+                compromised += p_case * case_compromised;
+            }
+            let coverage = if self.args.address_coverage {
+                Some(CoverageReport {
+                    mean_compromised: compromised,
+                    max_compromised: compromised,
+                    mean_disrupted: disrupted,
+                    max_disrupted: disrupted,
+                })
+            } else {
+                None
+            };
+            // The probabilities of the cases sum to one only up to rounding
+            SimResult {
+                p_disrupt: (1.0 - p_no_disruption).max(0.0).min(1.0),
+                p_compromise: (1.0 - p_no_compromise).max(0.0).min(1.0),
+                groups: net.groups().len() as RR,
+                groups_range: Some((net.groups().len() as NN, net.groups().len() as NN)),
+                deployed: None,
+                malice_hist: malice_hist,
+                elders: None,
+                models: None,
+                correction: None,
+                age_share: None,
+                liveness: None,
+                outcomes: None,
+                termination: None,
+                governance: None,
+                spread: spread,
+                coverage: coverage,
+                close_group: close_group,
+                turnover: None,
+                steady_state: None,
+                attack_record: None,
+                rng_draws: None,
+                runs: None,
+                truncated: false,
+                split_check: None,
+            }
+        } else {
+            // Calculate probability of compromise of one selected group.
+
+            // Take the group of the lowest prefix. Names are random, so
+            // there should be nothing special about this group (and unlike
+            // the first in the hash map, it is the same in every process).
+            let (prefix, group) = net.groups()
+                .iter()
+                .min_by_key(|&(prefix, _)| *prefix)
+                .expect("there should be at least one group");
+            let k = group.len() as NN;
+            let offset = jitter.offset(*prefix);
+            let quorum = |s| self.quorum.quorum_size_offset(s, offset).expect("simple quorum size");
+            let q = quorum(k);
+
+            // We already have code to do the rest:
+            let (pd, pc) = group_probs(&self.args, k, quorum);
+
+            trace!("n: {}, r: {}, k: {}, q: {}, pd: {:.e}, pc: {:.e}",
+                   self.args.num_nodes,
+                   self.args.num_malicious,
+                   k,
+                   q,
+                   pd,
+                   pc);
+
+            SimResult {
+                p_disrupt: pd,
+                p_compromise: pc,
+                groups: net.groups().len() as RR,
+                groups_range: Some((net.groups().len() as NN, net.groups().len() as NN)),
+                deployed: None,
+                malice_hist: malice_hist,
+                elders: None,
+                models: None,
+                correction: None,
+                age_share: None,
+                liveness: None,
+                outcomes: None,
+                termination: None,
+                governance: None,
+                spread: spread,
+                coverage: None,
+                close_group: close_group,
+                turnover: None,
+                steady_state: None,
+                attack_record: None,
+                rng_draws: None,
+                runs: None,
+                truncated: false,
+                split_check: None,
+            }
+        }
+    }
+}
+
+impl Tool for SimStructureTool {
+    fn print_message(&self) {
+        println!("Tool: simulate allocation of nodes to groups; each has size at least the \
+                  specified minimum size");
+        let any_group = true;   // only support this now
+        if any_group {
+            println!("Output: the probability that at least one group is compromised");
+        } else {
+            println!("Output: chance of a randomly selected group being compromised");
+        }
+    }
+
+    fn calc_p_compromise(&self) -> SimResult {
+        let deadline = self.args.time_limit.map(Deadline::new);
+        let mut result = self.simulate(0);
+        if !self.args.correction {
+            return result;
+        }
+
+        // Simulate further structures, and report the mean of all (those
+        // simulated, if the time limit passes or the run is interrupted first)
+        let mut p_structures = vec![result.p_compromise];
+        let (mut sum_disrupt, mut sum_groups) = (result.p_disrupt, result.groups);
+        for rep in 1..self.args.repetitions {
+            if deadline.as_ref().map_or(false, Deadline::passed) || self.args.interrupt.is_set() {
+                result.truncated = true;
+                break;
+            }
+            let other = self.simulate(rep);
+            p_structures.push(other.p_compromise);
+            sum_disrupt += other.p_disrupt;
+            sum_groups += other.groups;
+            if let (Some(hist), Some(other_hist)) = (result.malice_hist.as_mut(),
+                                                     other.malice_hist) {
+                for (count, other_count) in hist.counts.iter_mut().zip(other_hist.counts.iter()) {
+                    *count += *other_count;
+                }
+            }
+            if let (Some(spread), Some(other_spread)) = (result.spread.as_mut(), other.spread) {
+                spread.mean_groups += other_spread.mean_groups;
+                spread.mean_max_cluster += other_spread.mean_max_cluster;
+            }
+            if let (Some(coverage), Some(other_coverage)) = (result.coverage.as_mut(),
+                                                             other.coverage) {
+                coverage.mean_compromised += other_coverage.mean_compromised;
+                coverage.max_compromised = coverage.max_compromised
+                    .max(other_coverage.max_compromised);
+                coverage.mean_disrupted += other_coverage.mean_disrupted;
+                coverage.max_disrupted = coverage.max_disrupted.max(other_coverage.max_disrupted);
+            }
+            if let (Some(close), Some(other_close)) = (result.close_group.as_mut(),
+                                                       other.close_group) {
+                close.p_section += other_close.p_section;
+                close.p_close += other_close.p_close;
+                close.p_loss += other_close.p_loss;
+            }
+        }
+        let n = p_structures.len() as RR;
+        result.p_disrupt = sum_disrupt / n;
+        result.groups = sum_groups / n;
+        if let Some(ref mut spread) = result.spread {
+            spread.mean_groups /= n;
+            spread.mean_max_cluster /= n;
+        }
+        if let Some(ref mut coverage) = result.coverage {
+            coverage.mean_compromised /= n;
+            coverage.mean_disrupted /= n;
+        }
+        if let Some(ref mut close) = result.close_group {
+            close.p_section /= n;
+            close.p_close /= n;
+            close.p_loss /= n;
+        }
+
+        let n = self.args.num_nodes;
+        let k = calc_group_size(n, self.args.min_group_size);
+        let (_, pc) = group_probs(&self.args,
+                                  k,
+                                  |s| self.quorum.quorum_size(s).expect("simple quorum size"));
+        let p_calc = any_group_independent(pc, n, k);
+        let correction = CorrectionFactor::new(p_calc, &p_structures);
+        result.p_compromise = correction.p_structure;
+        result.correction = Some(correction);
+        result
+    }
+}
+
+
+#[test]
+fn test_bootstrap_single_group() {
+    // A network of fewer than twice the minimum group size (8) is one group of all nodes, so
+    // each tool finds compromise certain or impossible, and they agree. Compromise needs more
+    // malicious nodes as the group grows.
+    for &n in &[6, 8, 9, 15] {
+        let mut last = None;
+        for r in 0...n {
+            let args = || {
+                let mut args = test_args(n, r, 8);
+                args.max_steps = 3 * n;
+                args.repetitions = 2;
+                args.seeding.seed = Some(1);
+                args
+            };
+            let calc = DirectCalcTool::new(args()).calc_p_compromise();
+            let structure = SimStructureTool::new(args()).calc_p_compromise();
+            let full = FullSimTool::new(args(), SimpleQuorum::new(), UntargettedAttack {})
+                .calc_p_compromise();
+            assert_eq!((calc.groups, structure.groups, full.groups), (1.0, 1.0, 1.0));
+            let p = calc.p_compromise;
+            assert!(p == 0.0 || p == 1.0, "n {} r {}: {}", n, r, p);
+            assert_eq!((structure.p_compromise, full.p_compromise), (p, p), "n {} r {}", n, r);
+            assert_eq!(p, if 2 * r >= n { 1.0 } else { 0.0 });
+            assert!(last.map_or(true, |last| p >= last));
+            last = Some(p);
+        }
+    }
+
+    // A fixed quorum larger than a group below the minimum size is never reached
+    let mut args = test_args(6, 6, 8);
+    args.quorum = super::quorum::QuorumSize::Count(7);
+    let result = DirectCalcTool::new(args).calc_p_compromise();
+    assert_eq!((result.p_disrupt, result.p_compromise), (1.0, 0.0));
+}
+
+#[test]
+fn test_structure_groups() {
+    let tool = SimStructureTool::new(test_args(1000, 100, 10));
+    let mut streams = tool.args.seeding.streams(tool.master_seed, 0);
+    let net = tool.network(&mut streams);
+    let result = tool.calc_for(&net, &QuorumJitter::none(), &mut streams);
+    // The groups reported are those of the network, which hold all the nodes:
+    // the count times their mean size is the number of nodes
+    let sizes: Vec<RR> = net.groups().values().map(|group| group.len() as RR).collect();
+    let mean_size = sizes.iter().sum::<RR>() / sizes.len() as RR;
+    assert_eq!(result.groups, sizes.len() as RR);
+    assert!((result.groups * mean_size - 1000.0).abs() < 1e-9,
+            "{} groups of mean size {}",
+            result.groups,
+            mean_size);
+    // Groups split only once both halves would have k nodes, so none has fewer
+    // (though some have more than 2k, waiting on an uneven split)
+    assert!(sizes.iter().all(|&size| size >= 10.0), "sizes {:?}", sizes);
+    let groups = result.groups as NN;
+    assert_eq!(result.groups_range, Some((groups, groups)));
+}
+
+#[test]
+fn test_spread() {
+    // Placed at random, m malicious nodes in G groups occupy G (1 - (1 - 1/G)^m) groups on
+    // average: somewhat fewer than min(m, G)
+    let occupied = |m: RR, groups: RR| groups * (1.0 - (1.0 - 1.0 / groups).powf(m));
+    let mut args = test_args(1000, 20, 10);
+    args.spread = true;
+    args.correction = true;
+    args.repetitions = 10;
+    args.seeding.seed = Some(3);
+    let structure = SimStructureTool::new(args).calc_p_compromise();
+    let args = || {
+        let mut args = test_args(200, 20, 8);
+        args.max_steps = 50;
+        args.repetitions = 10;
+        args.spread = true;
+        args.seeding.seed = Some(3);
+        args
+    };
+    let full = FullSimTool::new(args(), SimpleQuorum::new(), UntargettedAttack {})
+        .calc_p_compromise();
+    for result in &[structure, full] {
+        let spread = result.spread.as_ref().expect("spread");
+        let expected = occupied(20.0, result.groups);
+        assert!((spread.mean_groups - expected).abs() < 0.15 * expected,
+                "groups {}, expected {}",
+                spread.mean_groups,
+                expected);
+        assert!(spread.mean_max_cluster >= 1.0 && spread.mean_max_cluster < 5.0,
+                "cluster {}",
+                spread.mean_max_cluster);
+    }
+
+    // Not requested, not reported
+    let result = FullSimTool::new(test_args(200, 20, 8), SimpleQuorum::new(), UntargettedAttack {})
+        .calc_p_compromise();
+    assert!(result.spread.is_none());
+}
+
+#[test]
+fn test_coverage_report() {
+    use super::placement::InitialPlacement;
+
+    // Placed in one group, 30 malicious nodes compromise it at once (see
+    // `test_concentrated_placement`): the share held compromised is that of its prefix, at most
+    // that of the shortest prefix of groups of 200 nodes of at least 8
+    let full = |malicious: NN| {
+        let mut args = test_args(200, malicious, 8);
+        args.placement = InitialPlacement::Concentrated(1.0);
+        args.repetitions = 5;
+        args.address_coverage = true;
+        args.seeding.seed = Some(4);
+        FullSimTool::new(args, SimpleQuorum::new(), UntargettedAttack {}).calc_p_compromise()
+    };
+    let result = full(30);
+    assert_eq!(result.p_compromise, 1.0);
+    let coverage = result.coverage.expect("coverage");
+    assert!(coverage.mean_compromised > 0.0 && coverage.max_compromised <= 0.25,
+            "{:?}",
+            coverage);
+    assert!(coverage.mean_compromised <= coverage.max_compromised);
+    assert!(coverage.mean_disrupted >= coverage.mean_compromised);
+    assert!(coverage.max_disrupted >= coverage.max_compromised);
+    let coverage = full(2).coverage.expect("coverage");
+    assert_eq!((coverage.mean_compromised, coverage.max_compromised), (0.0, 0.0));
+
+    // Of a structure, the expected share is a mean of the groups' probabilities, so no more
+    // than that of any group being compromised
+    let mut args = test_args(1000, 200, 10);
+    args.address_coverage = true;
+    args.correction = true;
+    args.repetitions = 5;
+    args.seeding.seed = Some(3);
+    let result = SimStructureTool::new(args).calc_p_compromise();
+    let coverage = result.coverage.expect("coverage");
+    assert!(coverage.mean_compromised > 0.0 && coverage.mean_compromised < result.p_compromise,
+            "{:?}, P(compromise) {}",
+            coverage,
+            result.p_compromise);
+    assert!(coverage.mean_compromised <= coverage.max_compromised);
+    assert!(coverage.mean_disrupted >= coverage.mean_compromised);
+
+    // Not requested, not reported
+    assert!(SimStructureTool::new(test_args(1000, 200, 10)).calc_p_compromise().coverage.is_none());
+}
+
+#[test]
+fn test_turnover_report() {
+    let run = |num_malicious: NN, max_steps: NN| {
+        let mut args = test_args(200, num_malicious, 8);
+        args.max_steps = max_steps;
+        args.repetitions = 5;
+        args.turnover = true;
+        args.seeding.seed = Some(5);
+        let result = FullSimTool::new(args, SimpleQuorum::new(), UntargettedAttack {})
+            .calc_p_compromise();
+        result.turnover.expect("turnover")
+    };
+
+    // With no churn (no nodes joining once the network is built) nodes never age: the median
+    // stays that of the initial network, however long the run
+    let still = run(0, 300);
+    assert_eq!(still.mean_doubling_steps, None);
+    assert_eq!(still.mean_median_age, run(0, 1).mean_median_age);
+    assert!(still.mean_median_age > 0.0);
+
+    // Each malicious node joining is a churn event, ageing the honest nodes of its group
+    let churning = run(100, 300);
+    let steps = churning.mean_doubling_steps.expect("grew");
+    assert!(steps > 0.0 && steps.is_finite(), "{:?}", churning);
+    assert!(churning.mean_median_age > still.mean_median_age,
+            "{:?} {:?}",
+            churning,
+            still);
+
+    // Not requested, not reported
+    let result = FullSimTool::new(test_args(200, 20, 8), SimpleQuorum::new(), UntargettedAttack {})
+        .calc_p_compromise();
+    assert!(result.turnover.is_none());
+}
+
+#[test]
+fn test_static_snapshot() {
+    // With no steps, the full simulation evaluates the initial placement of malicious nodes:
+    // placed at random, like the structure simulation, whose P(compromise) it roughly matches.
+    // Relocation during placement makes more, smaller groups, so it is somewhat higher.
+    let args = || {
+        let mut args = test_args(300, 80, 10);
+        args.repetitions = 100;
+        args.seeding.seed = Some(2);
+        args
+    };
+    let structure = SimStructureTool::new(args()).calc_p_compromise();
+    let tool = FullSimTool::new(args(), SimpleQuorum::new(), UntargettedAttack {});
+    let full = tool.calc_p_compromise();
+    assert!(structure.p_compromise > 0.1 && structure.p_compromise < 0.9,
+            "structure {}",
+            structure.p_compromise);
+    let ratio = full.p_compromise / structure.p_compromise;
+    assert!(ratio > 0.67 && ratio < 1.5,
+            "full {}, structure {}",
+            full.p_compromise,
+            structure.p_compromise);
+    let (_, _, net, _, _) = tool.run_sim(0, &mut |_, _, _| {});
+    let nodes = net.groups().values().flat_map(|group| group.values());
+    assert_eq!(nodes.filter(|data| data.is_malicious()).count(), 80);
+}
+
+#[test]
+fn test_concentrated_placement() {
+    use super::placement::InitialPlacement;
+
+    // Groups of 200 nodes with a minimum of 8 hold about 9 to 17: 30 malicious nodes fill one,
+    // more than its quorum, while 2 are too few for any
+    let tool = |malicious: NN, placement: InitialPlacement, steps: NN| {
+        let mut args = test_args(200, malicious, 8);
+        args.placement = placement;
+        args.max_steps = steps;
+        args.repetitions = 20;
+        args.seeding.seed = Some(4);
+        FullSimTool::new(args, SimpleQuorum::new(), UntargettedAttack {})
+    };
+    let concentrated = InitialPlacement::Concentrated(1.0);
+    assert_eq!(tool(30, concentrated, 0).calc_p_compromise().p_compromise, 1.0);
+    assert!(tool(30, InitialPlacement::Uniform, 0).calc_p_compromise().p_compromise < 1.0);
+    assert_eq!(tool(2, concentrated, 0).calc_p_compromise().p_compromise, 0.0);
+    // With steps, those placed are in the network from the start, so compromise it at once
+    let (_, termination, net, _, _) = tool(30, concentrated, 10).run_sim(0, &mut |_, _, _| {});
+    assert_eq!(termination, Termination::Compromised(0));
+    let nodes = net.groups().values().flat_map(|group| group.values());
+    assert_eq!(nodes.filter(|data| data.is_malicious()).count(), 30);
+
+    // The structure simulation places them likewise
+    let mut args = test_args(200, 6, 8);
+    args.placement = concentrated;
+    args.spread = true;
+    args.seeding.seed = Some(4);
+    let spread = SimStructureTool::new(args).calc_p_compromise().spread.expect("spread");
+    assert_eq!((spread.mean_groups, spread.mean_max_cluster), (1.0, 6.0));
+    // and counts those concentrated in its probabilities
+    let structure = |malicious: NN, placement: InitialPlacement| {
+        let mut args = test_args(200, malicious, 8);
+        args.placement = placement;
+        args.repetitions = 20;
+        args.seeding.seed = Some(4);
+        SimStructureTool::new(args).calc_p_compromise().p_compromise
+    };
+    assert_eq!(structure(30, concentrated), 1.0);
+    assert!(structure(30, InitialPlacement::Uniform) < 1.0);
+    // Half of 10 fill a quorum of the smallest group (of 8 or 9 members) on their own
+    assert!(structure(10, InitialPlacement::Concentrated(0.5)) >
+            structure(10, InitialPlacement::Uniform));
+}
+
+
+/// A tool which simulates group operations.
+///
+/// Can relocate nodes according to the node ageing RFC (roughly).
+pub struct FullSimTool<Q: Quorum, A: AttackStrategy + Clone> {
+    args: ToolArgs,
+    quorum: Q,
+    /// Further quorums the runs are judged under (see `sweep_quorums`)
+    sweep: Vec<Q>,
+    attack: A,
+    master_seed: u64,
+    ageing: Ageing,
+}
+
+impl<Q: Quorum, A: AttackStrategy + Clone> FullSimTool<Q, A> {
+    pub fn new(args: ToolArgs, mut quorum: Q, mut strategy: A) -> Self {
+        quorum.set_quorum_size(args.quorum);
+        Self::configure(&mut quorum, &args);
+        if let Some(target) = args.target_prefix {
+            strategy.set_target(target);
+        }
+        strategy.set_objective(args.objective);
+        let master_seed = args.seeding.master();
+        FullSimTool {
+            args: args,
+            quorum: quorum,
+            sweep: vec![],
+            attack: strategy,
+            master_seed: master_seed,
+            ageing: Ageing::Doubling,
+        }
+    }
+
+    // Age nodes by `ageing` instead, as if the simulation had a mistake
+    #[cfg(test)]
+    fn with_ageing(mut self, ageing: Ageing) -> Self {
+        self.ageing = ageing;
+        self
+    }
+
+    /// Also judge each run under quorums `quorums`, given their sizes and
+    /// otherwise set up like the tool's own. `calc_sweep` then gives the
+    /// results under each, from the same runs, as running a tool for each
+    /// with the same master seed would. This needs an attack strategy which
+    /// does not depend on the quorum, and none of the reports which look at
+    /// the network (only the termination report may be requested).
+    pub fn sweep_quorums(mut self, quorums: Vec<(QuorumSize, Q)>) -> Self {
+        if quorums.is_empty() {
+            return self;
+        }
+        assert!(!self.attack.depends_on_quorum(),
+                "the attack strategy depends on the quorum");
+        let args = &self.args;
+        assert!(!(args.malice_hist || args.age_share_interval.is_some() || args.liveness ||
+                  args.outcomes || args.split_check.is_some() || args.governance ||
+                  args.spread || args.turnover || args.steady_state.is_some() ||
+                  args.record_attack || args.replay_attack.is_some() || args.checkpoint.is_some() ||
+                  self.elder_count().is_some()),
+                "reports of the network cannot be swept");
+        for (size, mut quorum) in quorums {
+            quorum.set_quorum_size(size);
+            Self::configure(&mut quorum, args);
+            self.sweep.push(quorum);
+        }
+        self
+    }
+
+    // Set `quorum` up as `args` ask, except for its size
+    fn configure(quorum: &mut Q, args: &ToolArgs) {
+        quorum.set_basis_size(args.quorum_basis.basis_size(args.min_group_size));
+        quorum.set_quorum_floor(args.quorum_floor);
+        quorum.set_block_prop(args.block_prop);
+        quorum.set_weighting(&args.weighting);
+        quorum.set_age_cap(args.age_cap);
+        quorum.set_elder_tiebreak(args.elder_tiebreak);
+    }
+
+    // Create the initial network of honest nodes, drawing from `rng` (the structure stream),
+    // and of `malicious` malicious nodes placed among them at random, drawn from `attack_rng`
+    // (the attack stream). The attack strategy is told of splits.
+    fn initial_network<R: Rng>(&self,
+                               attack: &mut AttackStrategy,
+                               malicious: NN,
+                               rng: &mut R,
+                               attack_rng: &mut R)
+                               -> Network<RestrictOnePerAge> {
+        // For simplicity, we ignore all add-attempts which fail due to age restrictions
+        // (these do not affect the network and would simply be re-added later).
+        // Because of this and the assumption that malicious nodes are placed like "good" ones
+        // (they cannot reset), we do not need to simulate proof-of-work here.
+        let mut net = Network::<RestrictOnePerAge>::new(self.args.min_group_size as usize);
+        net.set_relocation_target(self.args.relocation);
+        net.set_churn_model(self.args.churn_model);
+        net.set_ageing(self.ageing);
+        let num_initial = self.args.num_nodes - self.args.num_malicious;
+        // Pre-generate all nodes to be added, in a Vec.
+        // We can pop from this and on relocation push.
+        let mut to_add: Vec<_> = iter::repeat(0)
+            .take(num_initial as usize)
+            .map(|_| {
+                let data = self.new_honest_data(rng);
+                (new_node_name(rng), data)
+            })
+            .collect();
+        for _ in 0..malicious {
+            let node = (new_node_name(attack_rng), self.new_malicious_data(attack_rng));
+            let i = attack_rng.gen_range(0, to_add.len() + 1);
+            to_add.insert(i, node);
+        }
+        let mut n_ops = 0;
+        let mut n_relocates = 0;
+        let mut n_rejects = 0;
+        while let Some((node_name, node_data)) = to_add.pop() {
+            n_ops += 1;
+            if log_enabled!(LogLevel::Trace) {
+                trace!("Adding from a queue of length {} with {} groups",
+                       to_add.len() + 1,
+                       net.groups().len());
+            }
+            let age = node_data.age();
+            let malicious_node = node_data.is_malicious();
+            match net.add_node(node_name, node_data) {
+                Ok(prefix) => {
+                    trace!("Added node {} with age {}", node_name, age);
+                    let prefix = net.maybe_split(prefix, node_name, attack);
+                    // Add successful: do churn event.
+                    // The churn may cause a removal from a group; however, either that was an
+                    // old group which just got a new member, or it is a split result with at least
+                    // one node more than the minimum number. Either way merging is not required.
+                    if let Some(node) = net.churn(prefix, node_name, rng) {
+                        n_relocates += 1;
+                        to_add.push(node);
+                    }
+                }
+                Err(Error::AlreadyExists) |
+                Err(Error::AddRestriction) => {
+                    n_rejects += 1;
+                    // We fixed the number of initial nodes. If this one is incompatible,
+                    // find another.
+                    if malicious_node {
+                        let node_data = self.new_malicious_data(attack_rng);
+                        to_add.push((new_node_name(attack_rng), node_data));
+                    } else {
+                        let node_data = self.new_honest_data(rng);
+                        to_add.push((new_node_name(rng), node_data));
+                    }
+                }
+                Err(e) => {
+                    panic!("Error adding node: {}", e);
+                }
+            }
+        }
+        debug!("Init done: added {} nodes in {} steps involving {} relocates and {} rejections",
+               num_initial + malicious,
+               n_ops,
+               n_relocates,
+               n_rejects);
+        net
+    }
+
+    // Data of a new honest node. Regions are only drawn with bursts, so that other results do
+    // not change.
+    fn new_honest_data<R: Rng>(&self, rng: &mut R) -> NodeData {
+        let mut data = NodeData::with_uptime(self.args.honest_uptime.sample(rng));
+        if self.args.burst.is_some() {
+            data.set_region(sample_region(rng));
+        }
+        if let Some(capacity) = self.args.weighting.sample_capacity(rng) {
+            data.set_capacity(capacity);
+        }
+        data
+    }
+
+    // Data of a new malicious node. Its capacity (if weighted) is drawn like that of honest
+    // nodes; the attack strategy may reset it to draw again.
+    fn new_malicious_data<R: Rng>(&self, rng: &mut R) -> NodeData {
+        let mut data = NodeData::new_malicious();
+        if let Some(capacity) = self.args.weighting.sample_capacity(rng) {
+            data.set_capacity(capacity);
+        }
+        data
+    }
+
+    // Run a simulation. Result starts `(any_disruption, termination, network)` where the last
+    // is the network at the end of the simulation. With a target prefix, only disruption or
+    // compromise of the target's lineage counts.
+    //
+    // Random numbers are drawn from the streams of the given repetition (counting from 0).
+    //
+    // `on_step` is called with the step number, network and elder roster at the end of each
+    // attack step, before checking for disruption or compromise. The simulation stops early on
+    // compromise (unless measuring the steady state), so it may be called fewer than
+    // `max_steps` times. Once stalled it is still
+    // called for the remaining steps, with the unchanged network. The roster is also returned,
+    // as at the end, and, if governance is reported, the number of structural changes (splits
+    // and promotions) and of those the attacker could block (see `count_change`).
+    fn run_sim(&self,
+               repetition: NN,
+               on_step: &mut FnMut(NN, &Network<RestrictOnePerAge>, &ElderRoster))
+               -> (bool, Termination, Network<RestrictOnePerAge>, ElderRoster, (NN, NN)) {
+        self.run_recorded(repetition, vec![], None, on_step).0
+    }
+
+    // `run_sim`, with the attacker first replaying the decisions `replay` (see
+    // `RecordedAttack`), stopping with `Termination::TimedOut` if `deadline` passes (checked
+    // every `TIME_CHECK_STEPS` steps). Also returns the decisions the attacker took, if
+    // measuring the steady state, whether a compromise and a disruption were held at each step,
+    // the mean number of malicious nodes deployed (not held in reserve) over the steps, the
+    // shares of the address space of the compromised and disrupted groups at the end (as at the
+    // first compromise, if going on past it) and the values drawn from each stream (none from the
+    // liveness stream, which `on_step` would draw from).
+    fn run_recorded(&self,
+                    repetition: NN,
+                    replay: Vec<AttackDecision>,
+                    deadline: Option<&Deadline>,
+                    on_step: &mut FnMut(NN, &Network<RestrictOnePerAge>, &ElderRoster))
+                    -> ((bool, Termination, Network<RestrictOnePerAge>, ElderRoster, (NN, NN)),
+                        Vec<AttackDecision>,
+                        Vec<(bool, bool)>,
+                        RR,
+                        (RR, RR),
+                        Draws) {
+        let (mut verdicts, net, roster, changes, decisions, draws) =
+            self.run_quorums(repetition, replay, deadline, &[&self.quorum], on_step);
+        let verdict = verdicts.remove(0);
+        ((verdict.disruption, verdict.termination, net, roster, changes),
+         decisions,
+         verdict.occupancy,
+         verdict.deployed,
+         verdict.coverage,
+         draws)
+    }
+
+    // `run_recorded`, judging the trajectory under each of `quorums` (the tool's own quorum
+    // for anything else quorums decide, e.g. governance), and running until it has ended under
+    // all of them. Gives the verdict under each quorum in order.
+    // Under each, this is as a run with that quorum alone, as long as the attack strategy does
+    // not depend on the quorum.
+    fn run_quorums(&self,
+                   repetition: NN,
+                   replay: Vec<AttackDecision>,
+                   deadline: Option<&Deadline>,
+                   quorums: &[&Q],
+                   on_step: &mut FnMut(NN, &Network<RestrictOnePerAge>, &ElderRoster))
+                   -> (Vec<QuorumVerdict>,
+                       Network<RestrictOnePerAge>,
+                       ElderRoster,
+                       (NN, NN),
+                       Vec<AttackDecision>,
+                       Draws) {
+        debug!("Starting sim");
+        assert!(self.args.any_group);
+        let mut budget_exhausted = None;
+        let mut changes = (0, 0);
+        let mut attack = RecordedAttack::new(self.attack.clone(), replay);
+        let mut streams = self.args.seeding.streams(self.master_seed, repetition);
+        let jitter = self.jitter(repetition);
+        let participation = self.participation(repetition);
+
+        // 1. Create initial network. With no steps, the malicious nodes are placed in it, to
+        // evaluate the initial placement only; otherwise only those starting concentrated are,
+        // the others joining in the attack.
+        let initial_malicious = if self.args.max_steps == 0 {
+            self.args.num_malicious
+        } else {
+            self.args.placement.concentrated(self.args.num_malicious)
+        };
+        let mut net = self.initial_network(&mut attack,
+                                           initial_malicious,
+                                           &mut streams.structure,
+                                           &mut streams.attack);
+        let swaps = self.args.placement.swaps(self.args.num_malicious, net.groups());
+        for (malicious, honest) in swaps {
+            net.swap_data(malicious, honest);
+        }
+        // The initial network is checked for its structure only
+        let mut verifier = Verifier::new(self.args.verify, &mut net);
+        if let Some(ref mut verifier) = verifier {
+            verifier.verify(&mut net, "initially");
+        }
+
+        // 2. Start attack
+        // Assumption: all nodes in the network (malicious or not) have the same performance.
+        // Proof-of-work time-outs can be used to filter out any nodes with slow CPU/network.
+        // We use a step, which is how long proof-of-work takes. We don't set a value here, but
+        // for example 10000 × 1-hour steps is over a year.
+
+        // Assumption: only malicious nodes are added after this time, and a fixed number. This
+        // is a worst case scenario; if there were background-adding of other nodes or if all the
+        // attacking nodes were not added simultaneously, the attack would be harder.
+
+        // Assumption: the network gives joining nodes a group immediately, but does not
+        // accept the node as a member until after proof-of-work. Nodes can choose to reset before
+        // doing the work. We assume nodes will not try to reset at any other time.
+        // We assume that nodes can reset and try to join again (with age 0) instantly. This may
+        // be problematic if some nodes target groups purely to cause churn events.
+
+        // Unlike above, we now use a two-step join process: (1) nodes are "pre-added": they are
+        // given a name and group, then either reset or wait until (2) nodes have done
+        // proof-of-work and can join.
+
+        // Assumption: if a node has done proof-of-work but its original target group splits, it
+        // simply joins whichever group it would now be in. If a node has done proof of work and
+        // is not accepted due to age restrictions, it is given a new name and must redo work,
+        // trying again the next step (see `MODEL_VERSION`).
+        let mut n_new_malicious = self.args.num_malicious - initial_malicious;
+        // Queue of nodes doing proof-of-work, each with the step at which it may join. Push to
+        // back, pop from front.
+        let mut waiting = VecDeque::new();
+        // Whether strategies are told which groups are disrupted
+        let seeks_disruption = self.args.objective == Objective::Disruption;
+        let mut runs: Vec<QuorumRun<Q>> =
+            quorums.iter()
+                .map(|&quorum| {
+                    QuorumRun::new(quorum,
+                                   self.args.hold,
+                                   self.args.steady_state.is_some(),
+                                   self.args.target_prefix)
+                })
+                .collect();
+        let mut roster = ElderRoster::new(self.args.promotion_lag,
+                                          self.quorum.elder_count().unwrap_or(0),
+                                          AgeCap::ordering_cap(self.args.age_cap),
+                                          self.args.elder_tiebreak);
+        let mut registry = DepartedRegistry::new(self.args.rejoin_recovery,
+                                                 self.args.rejoin_window);
+        let mut history = MembershipHistory::new(self.args.membership_lag, &mut net);
+        let mut limiter = self.args.join_cap.map(JoinLimiter::new);
+        if self.args.max_steps == 0 {
+            // A static snapshot: elders are seated at once, and disruption or compromise of the
+            // initial placement counts without holding
+            let _ = roster.update(&net, 0);
+            for run in &mut runs {
+                let status = self.check_groups(run.quorum,
+                                               &net,
+                                               &roster,
+                                               &history,
+                                               &jitter,
+                                               &participation);
+                run.disruption = !status.is_empty();
+                let compromised = status.values().any(|check| check.0);
+                run.status = status;
+                if compromised {
+                    run.end(Termination::Compromised(0), &net);
+                }
+            }
+        }
+        'steps: for step in 0..self.args.max_steps {
+            if step % TIME_CHECK_STEPS == 0 && deadline.map_or(false, Deadline::passed) {
+                for run in &mut runs {
+                    run.end(Termination::TimedOut(step), &net);
+                }
+                break 'steps;
+            }
+            // Nodes are corrupted before any burst, which only takes honest nodes
+            if let Some(ref corruption) = self.args.corruption {
+                for fraction in corruption.fractions_at(step) {
+                    self.corrupt(&mut net, fraction, &mut streams.attack);
+                }
+            }
+            if let Some(burst) = self.args.burst {
+                // Under the Poisson churn model a step may see several bursts
+                for _ in 0..self.args.churn_model.events(burst.prob, &mut streams.churn) {
+                    self.do_burst(&mut net, burst, &mut streams.churn);
+                }
+            }
+
+            // Proof-of-work started this step takes this many steps, given the network size now.
+            let join_steps = match self.args.pow_scaling {
+                PowScaling::Constant => 1,
+                scaling => {
+                    scaling.join_steps(net.groups().values().map(|group| group.len() as NN).sum())
+                }
+            };
+
+            // Until the attack starts, the network grows: a new honest node starts proof-of-work
+            // each step (joining like any other, with a churn event), or a Poisson number of them
+            // with mean one under the Poisson churn model
+            let attacking = step >= self.args.attack_start;
+            if !attacking {
+                let arrivals = match self.args.churn_model {
+                    ChurnModel::Fixed => 1,
+                    ChurnModel::Poisson => sample_poisson(1.0, &mut streams.churn),
+                };
+                for _ in 0..arrivals {
+                    let node = (new_node_name(&mut streams.churn),
+                                self.new_honest_data(&mut streams.churn));
+                    waiting.push_back((step + join_steps, node));
+                }
+            }
+
+            // Each round, we firstly deal with all "waiting" nodes, then add any new/reset nodes.
+            // Nodes which cannot be added wait for the next round (retrying them in this round
+            // may never end, if no group can take them). Nodes still doing work wait too.
+            // Nodes kept out by a join cap are renamed to redo proof-of-work, or held in order
+            // ahead of the others.
+            let mut retry = vec![];
+            let mut working = vec![];
+            while let Some((ready, (node_name, node_data))) = waiting.pop_front() {
+                if ready > step {
+                    working.push((ready, (node_name, node_data)));
+                    continue;
+                }
+                if let Some(ref mut limiter) = limiter {
+                    if !limiter.admits(net.find_prefix(node_name), step) {
+                        match limiter.mode() {
+                            JoinCapMode::Reject => {
+                                let node_name = new_node_name(&mut streams.churn);
+                                retry.push((step + join_steps, (node_name, node_data)));
+                            }
+                            JoinCapMode::Queue => limiter.hold((ready, (node_name, node_data))),
+                        }
+                        continue;
+                    }
+                }
+                match net.add_node(node_name, node_data) {
+                    Ok(prefix) => {
+                        if let Some(ref mut limiter) = limiter {
+                            limiter.record(node_name, step);
+                        }
+                        if self.args.governance && net.need_split(prefix) {
+                            let voting = roster.voting(&prefix, &net.groups()[&prefix]);
+                            self.count_change(prefix,
+                                              &voting,
+                                              &jitter,
+                                              &participation,
+                                              &mut changes);
+                        }
+                        let prefix = net.maybe_split(prefix, node_name, &mut attack);
+                        // Add successful: do churn event.
+                        // The churn may cause a removal from a group; however, either that was an
+                        // old group which just got a new member, or it is a split result with at
+                        // least one node more than the minimum number. Either way merging
+                        // is not required.
+                        if let Some(node) = net.churn(prefix, node_name, &mut streams.churn) {
+                            let prefix = net.find_prefix(node_name);
+                            let (disrupted, all_disrupted) = if node.1.is_malicious() &&
+                                                                seeks_disruption {
+                                self.disrupted_groups(&net, &roster, &jitter)
+                            } else {
+                                (vec![], false)
+                            };
+                            let progress = AttackProgress {
+                                step: step,
+                                max_steps: self.args.max_steps,
+                                available: n_new_malicious,
+                                disrupted: disrupted.contains(&prefix),
+                                all_disrupted: all_disrupted,
+                            };
+                            if node.1.is_malicious() &&
+                               attack.reset_node(&node, prefix, &progress) {
+                                n_new_malicious += 1;
+                                registry.depart(step, node.1.identity_age());
+                            } else {
+                                // Relocated nodes join straight away
+                                waiting.push_back((step, node));
+                            }
+                        }
+                    }
+                    Err(Error::AlreadyExists) |
+                    Err(Error::AddRestriction) => {
+                        // Cannot be added: rename and redo proof-of-work.
+                        let node_name = new_node_name(&mut streams.churn);
+                        retry.push((step + join_steps, (node_name, node_data)));
+                    }
+                    Err(e) => {
+                        panic!("Error adding node: {}", e);
+                    }
+                }
+            }
+            if let Some(ref mut limiter) = limiter {
+                waiting.extend(limiter.release());
+            }
+            waiting.extend(working);
+            waiting.extend(retry);
+
+            // The network does not change while new nodes are placed
+            let (disrupted, all_disrupted) = if attacking && n_new_malicious > 0 &&
+                                                 seeks_disruption {
+                self.disrupted_groups(&net, &roster, &jitter)
+            } else {
+                (vec![], false)
+            };
+            // The attacker deploys as many of the nodes available as it chooses, keeping the
+            // rest in reserve
+            let mut deploying = if attacking && n_new_malicious > 0 {
+                let view = GroupsView::new(&self.quorum,
+                                           &jitter,
+                                           &net,
+                                           &history,
+                                           self.args.target_prefix,
+                                           self.args.attacker_knowledge);
+                let progress = AttackProgress {
+                    step: step,
+                    max_steps: self.args.max_steps,
+                    available: n_new_malicious,
+                    disrupted: false,
+                    all_disrupted: all_disrupted,
+                };
+                min(attack.deploy(&view, &progress), n_new_malicious)
+            } else {
+                0
+            };
+            // A reset node is offered again at once, so a strategy which resets every node would
+            // never finish deploying: after this many resets in a row (far more than it takes to
+            // land in any one group), the rest of the deployment is held in reserve
+            let max_resets = RESETS_PER_GROUP * net.groups().len() as NN;
+            let mut resets = 0;
+            while deploying > 0 && resets < max_resets {
+                let name = new_node_name(&mut streams.attack);
+                let mut data = self.new_malicious_data(&mut streams.attack);
+                // A departed identity rejoins, if any is remembered
+                let identity = registry.rejoin(step);
+                if let Some((_, age)) = identity {
+                    data.rejoin_with_age(age, registry.recovered_age(age));
+                }
+                let mut node = (name, data);
+                let prefix = net.find_prefix(node.0);
+                let progress = AttackProgress {
+                    step: step,
+                    max_steps: self.args.max_steps,
+                    available: n_new_malicious,
+                    disrupted: disrupted.contains(&prefix),
+                    all_disrupted: all_disrupted,
+                };
+                if !attack.reset_node(&node, prefix, &progress) {
+                    n_new_malicious -= 1;
+                    deploying -= 1;
+                    resets = 0;
+                    // Drawn only when grinding, so that results without it are unchanged
+                    if self.args.name_grinding > 0.0 && attack.grinds_name(prefix, &progress) &&
+                       streams.attack.gen::<RR>() < self.args.name_grinding {
+                        let taken = net.groups()[&prefix]
+                            .keys()
+                            .chain(waiting.iter().map(|&(_, (ref name, _))| name));
+                        node.0 = ground_name(prefix, taken);
+                    }
+                    waiting.push_back((step + join_steps, node));
+                } else {
+                    resets += 1;
+                    if let Some((departed, age)) = identity {
+                        registry.depart(departed, age);
+                    }
+                }
+            }
+
+            // Storage proofs are checked once nodes have joined. Evicted nodes leave like churned
+            // nodes: the departure is a churn event in their group, whose relocated node (if
+            // any) joins again next step, or is reset. Evicted malicious nodes return to the
+            // attacker, so rejoining takes another new node's proof-of-work.
+            if self.args.eviction_rate > 0.0 {
+                for (name, data, prefix) in self.evict(&mut net, &mut streams.churn) {
+                    if data.is_malicious() {
+                        n_new_malicious += 1;
+                    }
+                    if let Some(node) = net.churn(prefix, name, &mut streams.churn) {
+                        let (disrupted, all_disrupted) = if node.1.is_malicious() &&
+                                                            seeks_disruption {
+                            self.disrupted_groups(&net, &roster, &jitter)
+                        } else {
+                            (vec![], false)
+                        };
+                        let progress = AttackProgress {
+                            step: step,
+                            max_steps: self.args.max_steps,
+                            available: n_new_malicious,
+                            disrupted: disrupted.contains(&prefix),
+                            all_disrupted: all_disrupted,
+                        };
+                        if node.1.is_malicious() && attack.reset_node(&node, prefix, &progress) {
+                            n_new_malicious += 1;
+                            registry.depart(step, node.1.identity_age());
+                        } else {
+                            waiting.push_back((step + 1, node));
+                        }
+                    }
+                }
+            }
+
+            // Elders are promoted once the network is settled for the step
+            let promoted = roster.update(&net, step);
+            if self.args.governance {
+                for &(prefix, ref seated) in &promoted {
+                    let voting: Group = net.groups()[&prefix]
+                        .iter()
+                        .filter(|&(name, _)| seated.contains(name))
+                        .map(|(name, data)| (*name, *data))
+                        .collect();
+                    self.count_change(prefix, &voting, &jitter, &participation, &mut changes);
+                }
+            }
+            on_step(step, &net, &roster);
+            if let Some(ref mut verifier) = verifier {
+                if verifier.due(step) {
+                    verifier.verify(&mut net, &format!("at step {}", step));
+                }
+            }
+
+            // Finally, we check if disruption or compromise occurred (and has held for long
+            // enough). Only groups changed since the last check, with elders newly seated or
+            // with a lagging change of membership are checked again.
+            let mut changed = net.take_touched();
+            changed.extend(promoted.iter().map(|&(prefix, _)| prefix));
+            history.update(&mut net, &mut changed, step);
+            // Whether, under each quorum, nothing more can happen while the network is unchanged
+            let mut settled = vec![];
+            for run in runs.iter_mut().filter(|run| run.ended.is_none()) {
+                run.count_deployed(self.args.num_malicious - n_new_malicious);
+                for prefix in &changed {
+                    let _ = run.status.remove(prefix);
+                    if let Some(group) = net.groups().get(prefix) {
+                        if self.args
+                            .target_prefix
+                            .map_or(true, |target| target.is_compatible(*prefix)) {
+                            let members = history.members(prefix, group);
+                            let check = self.check_group(run.quorum,
+                                                         *prefix,
+                                                         &roster.voting(prefix, &members),
+                                                         &jitter,
+                                                         &participation);
+                            if check.1 {
+                                run.status.insert(*prefix, check);
+                            }
+                        }
+                    }
+                }
+                if self.args.paranoid.map_or(false, |every| (step + 1) % every == 0) {
+                    assert_eq!(run.status,
+                               self.check_groups(run.quorum,
+                                                 &net,
+                                                 &roster,
+                                                 &history,
+                                                 &jitter,
+                                                 &participation),
+                               "incremental checks differ from checking every group at step {}",
+                               step);
+                }
+                let (compromise, quiet) = run.hold();
+                if compromise && run.occupancy.is_none() {
+                    run.end(Termination::Compromised(step), &net);
+                } else {
+                    if compromise {
+                        run.compromised(step, &net);
+                    }
+                    settled.push(quiet);
+                }
+            }
+            if runs.iter().all(|run| run.ended.is_some()) {
+                break 'steps;
+            }
+            if self.args.burst.is_some() {
+                self.remove_failed(&mut net);
+            }
+
+            // Nothing changes the network once the attacker and joining nodes are done, unless
+            // bursts, evictions or corruption to come do; nor do the seats, once no promotion is
+            // pending, or the lagging membership, once no join or departure is yet to take
+            // effect. Neither result changes then, unless a group has yet to be held compromised
+            // or disrupted.
+            let corrupting = self.args.corruption.as_ref().map_or(false, |c| c.last_step() > step);
+            if attacking && n_new_malicious == 0 && waiting.is_empty() && !corrupting &&
+               self.args.burst.is_none() && self.args.eviction_rate == 0.0 &&
+               roster.settled() && history.settled() {
+                let mut settled = settled.into_iter();
+                for run in runs.iter_mut().filter(|run| run.ended.is_none()) {
+                    if settled.next().expect("settled") {
+                        run.end(Termination::Stalled(step), &net);
+                        // Only the holds change now
+                        if run.occupancy.is_some() {
+                            for _ in step + 1..self.args.max_steps {
+                                let _ = run.hold();
+                            }
+                        }
+                    }
+                }
+                if runs.iter().all(|run| run.ended.is_some()) {
+                    debug!("Stalled at step {}", step);
+                    for step in step + 1..self.args.max_steps {
+                        on_step(step, &net, &roster);
+                    }
+                    break 'steps;
+                }
+            }
+            // The attacker has no nodes left once none is in the network, joining or to be
+            // corrupted
+            if attacking && budget_exhausted.is_none() && n_new_malicious == 0 && !corrupting &&
+               !waiting.iter().any(|&(_, ref node)| node.1.is_malicious()) &&
+               !net.groups().values().flat_map(|group| group.values()).any(|d| d.is_malicious()) {
+                budget_exhausted = Some(step);
+            }
+        }
+
+        check_ages(&net);
+        let termination = match budget_exhausted {
+            Some(step) => Termination::BudgetExhausted(step),
+            None => Termination::StepsExhausted,
+        };
+        for run in &mut runs {
+            run.end(termination, &net);
+        }
+        let deployed = self.args.num_malicious - n_new_malicious;
+        let verdicts = runs.into_iter().map(|run| run.into_verdict(deployed)).collect();
+        extra_draw(repetition, &mut streams.churn);
+        (verdicts, net, roster, changes, attack.into_decisions(), streams.draws())
+    }
+
+    // The quorum jitter of the given repetition
+    fn jitter(&self, repetition: NN) -> QuorumJitter {
+        QuorumJitter::new(self.args.quorum_jitter,
+                          &self.args.seeding,
+                          self.master_seed,
+                          repetition)
+    }
+
+    // The honest participation of the given repetition
+    fn participation(&self, repetition: NN) -> Participation {
+        Participation::new(self.args.participation,
+                           &self.args.seeding,
+                           self.master_seed,
+                           repetition)
+    }
+
+    // Whether the group of `prefix` with voting members `group` is `(compromised, disrupted)`
+    // under `quorum`, its proportion offset by `jitter`, when those of `participation` take part.
+    // Compromise implies disruption!
+    fn check_group(&self,
+                   quorum: &Q,
+                   prefix: Prefix,
+                   group: &Group,
+                   jitter: &QuorumJitter,
+                   participation: &Participation)
+                   -> (bool, bool) {
+        let offset = jitter.offset(prefix);
+        let group = participation.participating(prefix, group);
+        if quorum_compromised_offset(quorum, &group, offset) {
+            (true, true)
+        } else {
+            (false, quorum_disrupted_offset(quorum, &group, offset))
+        }
+    }
+
+    // `check_group` of every disrupted group (of the target's lineage, if any), by prefix, with
+    // its members as in `history`
+    fn check_groups(&self,
+                    quorum: &Q,
+                    net: &Network<RestrictOnePerAge>,
+                    roster: &ElderRoster,
+                    history: &MembershipHistory,
+                    jitter: &QuorumJitter,
+                    participation: &Participation)
+                    -> HashMap<Prefix, (bool, bool)> {
+        net.groups()
+            .iter()
+            .filter(|&(prefix, _)| {
+                self.args.target_prefix.map_or(true, |target| target.is_compatible(*prefix))
+            })
+            .map(|(prefix, group)| {
+                let members = history.members(prefix, group);
+                let check = self.check_group(quorum,
+                                             *prefix,
+                                             &roster.voting(prefix, &members),
+                                             jitter,
+                                             participation);
+                (*prefix, check)
+            })
+            .filter(|&(_, check)| check.1)
+            .collect()
+    }
+
+    // Count a structural change (a split or promotion) of the group of `prefix`, decided by
+    // `voting` (its voting members just before the change), unless not of the target's lineage:
+    // `changes` counts the changes and those the attacker could block (by disruption or
+    // compromise of the voters).
+    fn count_change(&self,
+                    prefix: Prefix,
+                    voting: &Group,
+                    jitter: &QuorumJitter,
+                    participation: &Participation,
+                    changes: &mut (NN, NN)) {
+        if self.args.target_prefix.map_or(false, |target| !target.is_compatible(prefix)) {
+            return;
+        }
+        changes.0 += 1;
+        if self.check_group(&self.quorum, prefix, voting, jitter, participation).1 {
+            changes.1 += 1;
+        }
+    }
+
+    // Mark the honest nodes lost in a burst as failed. They are down for the rest of the step,
+    // then removed by `remove_failed`.
+    fn do_burst<R: Rng>(&self, net: &mut Network<RestrictOnePerAge>, burst: Burst, rng: &mut R) {
+        let victims = burst.victims(net.groups().values(), rng);
+        debug!("Burst: {} honest nodes lost", victims.len());
+        for name in victims {
+            net.set_failed(name, true);
+        }
+    }
+
+    // Turn the honest nodes taken by a corruption event of `fraction` malicious, in place.
+    fn corrupt<R: Rng>(&self, net: &mut Network<RestrictOnePerAge>, fraction: RR, rng: &mut R) {
+        let victims = Corruption::victims(fraction, net.groups().values(), rng);
+        debug!("Corruption: {} honest nodes turned malicious", victims.len());
+        for name in victims {
+            net.set_malicious(name);
+        }
+    }
+
+    // Evict the nodes failing their storage proof: each honest node with probability the
+    // eviction rate, each malicious node with that divided by the attacker's resource (at most
+    // 1). Under the Poisson churn model the number of each kind evicted is instead drawn from a
+    // Poisson distribution with mean the expected number (capped at all of them), and that many
+    // are picked at random. Evicted nodes leave at once, but as for `remove_failed` no group is
+    // emptied. Returns the nodes evicted, in order of name, with their groups.
+    fn evict<R: Rng>(&self,
+                     net: &mut Network<RestrictOnePerAge>,
+                     rng: &mut R)
+                     -> Vec<(NodeName, NodeData, Prefix)> {
+        let p_honest = self.args.eviction_rate;
+        let p_malicious = (p_honest / self.args.attack_resource).min(1.0);
+        let mut nodes: Vec<(NodeName, bool)> = net.groups()
+            .values()
+            .flat_map(|group| group.iter())
+            .map(|(name, data)| (*name, data.is_malicious()))
+            .collect();
+        nodes.sort();
+        let (malicious, honest): (Vec<_>, Vec<_>) =
+            nodes.into_iter().partition(|&(_, malicious)| malicious);
+        let mut failing = vec![];
+        for (mut kind, p) in vec![(honest, p_honest), (malicious, p_malicious)] {
+            match self.args.churn_model {
+                ChurnModel::Fixed => {
+                    failing.extend(kind.into_iter().filter(|_| rng.gen::<RR>() < p))
+                }
+                ChurnModel::Poisson => {
+                    let count = min(sample_poisson(p * kind.len() as RR, rng) as usize, kind.len());
+                    rng.shuffle(&mut kind);
+                    failing.extend(kind.into_iter().take(count));
+                }
+            }
+        }
+        // Remove in name order, so that neither kind is favoured as the last node of a group
+        failing.sort();
+        let mut evicted = vec![];
+        for (name, _) in failing {
+            let prefix = net.find_prefix(name);
+            if net.groups()[&prefix].len() > 1 {
+                let data = net.remove_node(name).expect("member");
+                evicted.push((name, data, prefix));
+            }
+        }
+        trace!("Evicted {} nodes", evicted.len());
+        evicted
+    }
+
+    // Remove failed nodes, except that no group is emptied (there being no merging of groups):
+    // the last node of a group recovers instead. The removals are not churn events.
+    fn remove_failed(&self, net: &mut Network<RestrictOnePerAge>) {
+        let mut failed: Vec<NodeName> = net.groups()
+            .values()
+            .flat_map(|group| group.iter())
+            .filter(|&(_, data)| data.is_failed())
+            .map(|(name, _)| *name)
+            .collect();
+        failed.sort();
+        for name in failed {
+            if net.groups()[&net.find_prefix(name)].len() > 1 {
+                net.remove_node(name);
+            } else {
+                net.set_failed(name, false);
+            }
+        }
+    }
+
+    // Prefixes of the disrupted groups counted (of the target's lineage, if any), and whether
+    // all counted groups are disrupted. Only needed under the disruption objective, since only
+    // then do strategies use them.
+    fn disrupted_groups(&self,
+                        net: &Network<RestrictOnePerAge>,
+                        roster: &ElderRoster,
+                        jitter: &QuorumJitter)
+                        -> (Vec<Prefix>, bool) {
+        let mut disrupted = vec![];
+        let mut all = true;
+        for (prefix, group) in net.groups() {
+            if self.args.target_prefix.map_or(false, |target| !target.is_compatible(*prefix)) {
+                continue;
+            }
+            let offset = jitter.offset(*prefix);
+            if quorum_disrupted_offset(&self.quorum, &roster.voting(prefix, group), offset) {
+                disrupted.push(*prefix);
+            } else {
+                all = false;
+            }
+        }
+        (disrupted, all)
+    }
+
+    // Largest `age_share` of any group (of the target's lineage, if any).
+    fn max_age_share(&self, net: &Network<RestrictOnePerAge>) -> RR {
+        net.groups()
+            .iter()
+            .filter(|&(prefix, _)| {
+                self.args.target_prefix.map_or(true, |target| target.is_compatible(*prefix))
+            })
+            .map(|(_, group)| age_share(group))
+            .fold(0.0, RR::max)
+    }
+
+    // Number of groups (of the target's lineage, if any) whose live members do not form a
+    // quorum. Failed nodes are down and malicious nodes are always up; as in churn, other honest
+    // nodes which have aged are up with probability their uptime, and those which have not are
+    // up. With lagging promotions, only the seated elders (of `roster`) count. Groups and their
+    // members are visited in order, so that the draws from `rng` are repeatable.
+    fn count_unlive_groups<R: Rng>(&self,
+                                   net: &Network<RestrictOnePerAge>,
+                                   roster: &ElderRoster,
+                                   jitter: &QuorumJitter,
+                                   rng: &mut R)
+                                   -> NN {
+        let mut prefixes: Vec<Prefix> = net.groups()
+            .keys()
+            .cloned()
+            .filter(|prefix| {
+                self.args.target_prefix.map_or(true, |target| target.is_compatible(*prefix))
+            })
+            .collect();
+        prefixes.sort();
+        let mut count = 0;
+        for prefix in prefixes {
+            let group = roster.voting(&prefix, &net.groups()[&prefix]);
+            let mut all: Vec<Member> =
+                group.iter().map(|(name, data)| Member::new(*name, data)).collect();
+            all.sort_by_key(|member| member.name);
+            let live: Vec<Member> = all.iter()
+                .cloned()
+                .filter(|member| {
+                    let data = &group[&member.name];
+                    !data.is_failed() &&
+                    (data.is_malicious() || data.age() == 0 || data.uptime() >= 1.0 ||
+                     rng.gen::<RR>() < data.uptime())
+                })
+                .collect();
+            if !self.quorum.is_quorum_offset(&all, &live, jitter.offset(prefix)) {
+                count += 1;
+            }
+        }
+        count
+    }
+
+    // Number of elders per group, if the elder report is requested (and the quorum has elders).
+    fn elder_count(&self) -> Option<usize> {
+        if self.args.elders_report {
+            self.quorum.elder_count()
+        } else {
+            None
+        }
+    }
+
+    // Count malicious elders (those seated, in `roster`) in each group. Returns `(sum, max,
+    // any_quorum)`: the total number of malicious elders, the largest number in any group and
+    // whether any group has a quorum of malicious elders.
+    fn count_malicious_elders(&self,
+                              net: &Network<RestrictOnePerAge>,
+                              roster: &ElderRoster,
+                              k: usize,
+                              jitter: &QuorumJitter)
+                              -> (NN, NN, bool) {
+        let mut sum = 0;
+        let mut max = 0;
+        let mut any_quorum = false;
+        for (prefix, group) in net.groups() {
+            let group = roster.voting(prefix, group);
+            let members: Vec<Member> =
+                group.iter().map(|(name, data)| Member::new(*name, data)).collect();
+            let elders = elders_ranked(&members,
+                                       k,
+                                       AgeCap::ordering_cap(self.args.age_cap),
+                                       self.args.elder_tiebreak);
+            let n_malicious = elders.iter()
+                .filter(|elder| group[&elder.name].is_malicious())
+                .count() as NN;
+            sum += n_malicious;
+            if n_malicious > max {
+                max = n_malicious;
+            }
+            let n_elders = elders.len() as NN;
+            let needed = self.args
+                .quorum
+                .offset(n_elders, jitter.offset(*prefix))
+                .of_floored(n_elders, self.args.quorum_floor);
+            if n_malicious >= needed {
+                any_quorum = true;
+            }
+        }
+        (sum, max, any_quorum)
+    }
+}
+
+impl<Q: Quorum, A: AttackStrategy + Clone> Tool for FullSimTool<Q, A> {
+    fn print_message(&self) {
+        println!("Tool: simulate group operations");
+        let any_group = true;   // only support this now
+        if any_group {
+            println!("Output: the probability that at least one group is compromised");
+        } else {
+            println!("Output: chance of a randomly selected group being compromised");
+        }
+    }
+
+    fn compromise_steps(&self) -> Option<Vec<Option<NN>>> {
+        Some((0..self.args.repetitions)
+            .map(|repetition| {
+                // The simulation stops at the step of compromise
+                match self.run_sim(repetition, &mut |_, _, _| {}).1 {
+                    Termination::Compromised(step) => Some(step),
+                    _ => None,
+                }
+            })
+            .collect())
+    }
+
+    fn calc_p_compromise(&self) -> SimResult {
+        let mut progress = self.start();
+        self.calc_until(&mut progress, self.args.repetitions)
+    }
+
+    fn start(&self) -> Progress {
+        let age_share_samples = self.args
+            .age_share_interval
+            .map(|interval| (self.args.max_steps / interval) as usize);
+        let progress = Progress::new(Reports {
+            malice_hist: self.args.malice_hist,
+            elders: self.elder_count().is_some(),
+            age_share_samples: age_share_samples,
+            liveness: self.args.liveness,
+            outcomes: self.args.outcomes || self.args.split_check.is_some(),
+            termination: self.args.termination,
+            governance: self.args.governance,
+            spread: self.args.spread,
+            turnover: self.args.turnover,
+            coverage: self.args.address_coverage,
+            steady_state_batches: self.args.steady_state.map(|s| s.batches as usize),
+            attack_record: self.args.record_attack,
+            rng_draws: self.args.rng_audit,
+        });
+        if let Some(ref file) = self.args.checkpoint {
+            if let Some(saved) = file.load(&progress) {
+                info!("Resuming from checkpoint after {} repetitions",
+                      saved.repetitions);
+                return saved;
+            }
+        }
+        progress
+    }
+
+    fn calc_until(&self, progress: &mut Progress, repetitions: NN) -> SimResult {
+        let elder_count = self.elder_count();
+        let repetitions = min(repetitions, self.args.repetitions);
+        let mut last_save = Instant::now();
+        // The time limit counts the time spent in earlier calls
+        let (start, elapsed) = (Instant::now(), progress.elapsed);
+        let deadline = self.args.time_limit.map(|limit| Deadline::new(limit - elapsed));
+
+        while progress.repetitions < repetitions {
+            if deadline.as_ref().map_or(false, Deadline::passed) || self.args.interrupt.is_set() {
+                break;
+            }
+            let mut samples = Vec::new();
+            let mut liveness_rng =
+                self.args.seeding.stream(self.master_seed, Stream::Liveness, progress.repetitions);
+            let jitter = self.jitter(progress.repetitions);
+            let mut unlive = 0;
+            let mut medians = Vec::new();
+            // Only the first repetition is recorded or replayed
+            let replay = match self.args.replay_attack {
+                Some(ref replay) if progress.repetitions == 0 => replay.clone(),
+                _ => vec![],
+            };
+            let (run, decisions, occupancy, deployed, coverage, mut draws) = {
+                let mut on_step = |step: NN,
+                                   net: &Network<RestrictOnePerAge>,
+                                   roster: &ElderRoster| {
+                    if let Some(interval) = self.args.age_share_interval {
+                        if (step + 1) % interval == 0 {
+                            samples.push(self.max_age_share(net));
+                        }
+                    }
+                    if self.args.liveness {
+                        unlive +=
+                            self.count_unlive_groups(net, roster, &jitter, &mut liveness_rng);
+                    }
+                    if self.args.turnover {
+                        medians.extend(median_honest_age(net.groups().values()));
+                    }
+                };
+                self.run_recorded(progress.repetitions,
+                                  replay,
+                                  deadline.as_ref(),
+                                  &mut on_step)
+            };
+            let (disruption, termination, net, roster, changes) = run;
+            if let Termination::TimedOut(_) = termination {
+                // The incomplete repetition is discarded
+                break;
+            }
+            if progress.repetitions == 0 {
+                if let Some(ref mut record) = progress.attack_record {
+                    *record = decisions;
+                }
+            }
+            if let Some(ref mut record) = progress.rng_draws {
+                draws[Stream::Liveness as usize] += liveness_rng.draws();
+                record.push(draws);
+            }
+            if let Some(ref mut sum) = progress.age_share {
+                // After compromise the network no longer changes
+                let last = self.max_age_share(&net);
+                samples.resize(sum.len(), last);
+                for (sum, sample) in sum.iter_mut().zip(samples) {
+                    *sum += sample;
+                }
+            }
+            if let Some(target) = self.args.target_prefix {
+                progress.target_reached |= reaches(&net, target);
+            }
+            tally(progress,
+                  disruption,
+                  termination,
+                  net.groups().len() as NN,
+                  deployed,
+                  coverage);
+            tally_ages(progress, AgeSummary::of(&net));
+            if let (Some(sums), Some(runs), Some(steady)) = (progress.steady_state.as_mut(),
+                                                             progress.steady_state_runs.as_mut(),
+                                                             self.args.steady_state) {
+                let (mut compromised, mut disrupted) = (0, 0);
+                for (step, &(compromise, disruption)) in occupancy.iter().enumerate() {
+                    if let Some(batch) = steady.batch(step as NN, self.args.max_steps) {
+                        sums[batch].0 += compromise as NN;
+                        sums[batch].1 += disruption as NN;
+                        compromised += compromise as NN;
+                        disrupted += disruption as NN;
+                    }
+                }
+                runs.0 += compromised;
+                runs.1 += compromised * compromised;
+                runs.2 += disrupted;
+                runs.3 += disrupted * disrupted;
+            }
+            if let Some(ref mut governance) = progress.governance {
+                if changes.1 > 0 {
+                    governance.0 += 1;
+                }
+                governance.1 += changes.0;
+                governance.2 += changes.1;
+            }
+            if let Some(ref mut hist) = progress.malice_hist {
+                for group in net.groups().values() {
+                    hist.add_group(group);
+                }
+            }
+            if let Some(ref mut spread) = progress.spread {
+                let (groups, max_cluster) = malicious_spread(net.groups().values());
+                spread.0 += groups;
+                spread.1 += max_cluster;
+            }
+            if let Some(ref mut sums) = progress.turnover {
+                let (steps, initial, growth, steady) = turnover(&medians);
+                sums.0 += steps;
+                sums.1 += initial;
+                sums.2 += growth;
+                sums.3 += steady;
+            }
+            if let (Some(k), Some(elders)) = (elder_count, progress.elders.as_mut()) {
+                let (sum, max, any_quorum) =
+                    self.count_malicious_elders(&net, &roster, k, &jitter);
+                elders.0 += sum;
+                if max > elders.1 {
+                    elders.1 = max;
+                }
+                if any_quorum {
+                    elders.2 += 1;
+                }
+            }
+            if let Some(ref mut liveness) = progress.liveness {
+                if unlive > 0 {
+                    liveness.0 += 1;
+                }
+                liveness.1 += unlive;
+            }
+            progress.repetitions += 1;
+            progress.elapsed = elapsed + seconds(start.elapsed());
+
+            if let Some(ref file) = self.args.checkpoint {
+                let elapsed = last_save.elapsed();
+                let secs = elapsed.as_secs() as RR + (elapsed.subsec_nanos() as RR) * 1e-9;
+                if secs >= file.every() || progress.repetitions == self.args.repetitions {
+                    file.save(progress).unwrap_or_else(|e| {
+                        panic!("unable to write checkpoint {}: {}", file.path().display(), e)
+                    });
+                    last_save = Instant::now();
+                }
+            }
+        }
+        progress.elapsed = elapsed + seconds(start.elapsed());
+        // Only stopped short by the time limit or an interruption
+        let truncated = progress.repetitions < repetitions;
+        if let (true, Some(file)) = (truncated, self.args.checkpoint.as_ref()) {
+            file.save(progress).unwrap_or_else(|e| {
+                panic!("unable to write checkpoint {}: {}", file.path().display(), e)
+            });
+        }
+        self.summarise(progress, truncated)
+    }
+
+    fn calc_sweep(&self) -> Vec<SimResult> {
+        if self.sweep.is_empty() {
+            return vec![self.calc_p_compromise()];
+        }
+        let quorums: Vec<&Q> = Some(&self.quorum).into_iter().chain(&self.sweep).collect();
+        let mut progresses: Vec<Progress> = quorums.iter().map(|_| self.start()).collect();
+        let mut repetitions = 0;
+        while repetitions < self.args.repetitions && !self.args.interrupt.is_set() {
+            let (verdicts, ..) =
+                self.run_quorums(repetitions, vec![], None, &quorums, &mut |_, _, _| {});
+            for (progress, verdict) in progresses.iter_mut().zip(verdicts) {
+                tally(progress,
+                      verdict.disruption,
+                      verdict.termination,
+                      verdict.groups,
+                      verdict.deployed,
+                      verdict.coverage);
+                tally_ages(progress, verdict.ages);
+                progress.target_reached |= verdict.reached;
+                progress.repetitions += 1;
+            }
+            repetitions += 1;
+        }
+        let truncated = repetitions < self.args.repetitions;
+        progresses.iter().map(|progress| self.summarise(progress, truncated)).collect()
+    }
+}
+
+impl<Q: Quorum, A: AttackStrategy + Clone> FullSimTool<Q, A> {
+    // The results of the repetitions counted by `progress`, of which there are fewer than
+    // requested if `truncated`
+    fn summarise(&self, progress: &Progress, truncated: bool) -> SimResult {
+        // Only known to be unreachable once all repetitions are done
+        if let Some(target) = self.args.target_prefix {
+            if progress.repetitions == self.args.repetitions && self.args.repetitions > 0 &&
+               !progress.target_reached {
+                panic!("target prefix {} is longer than any prefix reached", target);
+            }
+        }
+        let denom = progress.repetitions as RR;
+        if progress.repetitions > 0 {
+            // A sanity check on ageing: ages far beyond the steps simulated are suspect
+            let (honest, malicious, malicious_runs, oldest) = progress.ages;
+            let mean_malicious = if malicious_runs == 0 {
+                "-".to_owned()
+            } else {
+                format!("{:.2}", malicious / malicious_runs as RR)
+            };
+            let per_step = if self.args.max_steps == 0 {
+                "-".to_owned()
+            } else {
+                format!("{:.4}", oldest as RR / self.args.max_steps as RR)
+            };
+            debug!("ages at the end of {} runs: mean honest {:.2}, mean malicious {}, oldest {} \
+                    ({} of the {} steps)",
+                   progress.repetitions,
+                   honest / denom,
+                   mean_malicious,
+                   oldest,
+                   per_step,
+                   self.args.max_steps);
+        }
+        let n_groups = progress.groups;
+        let elders = progress.elders.map(|(sum_mal_elders, max_mal_elders, n_elder_quorums)| {
+            ElderReport {
+                mean_malicious: (sum_mal_elders as RR) / (n_groups as RR),
+                max_malicious: max_mal_elders,
+                p_quorum: (n_elder_quorums as RR) / denom,
+            }
+        });
+        let age_share = match (progress.age_share.as_ref(), self.args.age_share_interval) {
+            (Some(sum), Some(interval)) => {
+                Some(sum.iter()
+                    .enumerate()
+                    .map(|(i, sum)| ((i as NN + 1) * interval, sum / denom))
+                    .collect())
+            }
+            _ => None,
+        };
+        let liveness = progress.liveness.map(|(runs, group_steps)| {
+            LivenessReport {
+                p_failure: (runs as RR) / denom,
+                mean_group_steps: (group_steps as RR) / denom,
+            }
+        });
+        let governance = progress.governance.map(|(runs, changes, blockable)| {
+            GovernanceReport {
+                p_blockable: (runs as RR) / denom,
+                mean_changes: (changes as RR) / denom,
+                mean_blockable: (blockable as RR) / denom,
+            }
+        });
+        let spread = progress.spread.map(|(groups, max_cluster)| {
+            SpreadReport {
+                mean_groups: (groups as RR) / denom,
+                mean_max_cluster: (max_cluster as RR) / denom,
+            }
+        });
+        let coverage = progress.coverage.map(|(compromised, max_compromised, disrupted, max)| {
+            CoverageReport {
+                mean_compromised: compromised / denom,
+                max_compromised: max_compromised,
+                mean_disrupted: disrupted / denom,
+                max_disrupted: max,
+            }
+        });
+        let turnover = progress.turnover.map(|(steps, initial, growth, steady)| {
+            TurnoverReport {
+                mean_doubling_steps: doubling_steps(steps, initial / denom, growth),
+                mean_median_age: steady / denom,
+            }
+        });
+        let steady_state = match (progress.steady_state.as_ref(),
+                                  progress.steady_state_runs,
+                                  self.args.steady_state) {
+            (Some(sums), Some(runs), Some(steady)) => {
+                Some(SteadyStateReport::new(sums,
+                                            runs,
+                                            progress.repetitions,
+                                            steady.batch_steps(self.args.max_steps)))
+            }
+            _ => None,
+        };
+        let termination = progress.termination.map(|(stalled, stall_steps, budget_exhausted)| {
+            TerminationReport {
+                compromised: progress.compromises,
+                stalled: stalled,
+                budget_exhausted: budget_exhausted,
+                steps_exhausted: progress.repetitions - progress.compromises - stalled -
+                                 budget_exhausted,
+                mean_stall_steps: if stalled > 0 {
+                    Some(stall_steps as RR / stalled as RR)
+                } else {
+                    None
+                },
+            }
+        });
+        SimResult {
+            p_disrupt: (progress.disruptions as RR) / denom,
+            p_compromise: (progress.compromises as RR) / denom,
+            groups: (n_groups as RR) / denom,
+            groups_range: progress.groups_range,
+            deployed: Some(progress.deployed / denom),
+            malice_hist: progress.malice_hist.clone(),
+            elders: elders,
+            models: None,
+            correction: None,
+            age_share: age_share,
+            liveness: liveness,
+            outcomes: if self.args.outcomes {
+                progress.outcomes.clone()
+            } else {
+                None
+            },
+            termination: termination,
+            governance: governance,
+            spread: spread,
+            coverage: coverage,
+            close_group: None,
+            turnover: turnover,
+            steady_state: steady_state,
+            attack_record: progress.attack_record.clone(),
+            rng_draws: progress.rng_draws.clone(),
+            runs: Some(progress.repetitions),
+            truncated: truncated,
+            split_check: match (self.args.split_check, progress.outcomes.as_ref()) {
+                (Some(k), Some(outcomes)) => Some(SplitCheck::new(outcomes, k)),
+                _ => None,
+            },
+        }
+    }
+}
+
+// Count a repetition of the full simulation, which ended with `termination` and `groups` groups
+// holding `coverage` of the address space compromised and disrupted, was disrupted if
+// `disruption` and deployed `deployed` malicious nodes on average, in `progress` (except in the
+// reports looking at the network)
+fn tally(progress: &mut Progress,
+         disruption: bool,
+         termination: Termination,
+         groups: NN,
+         deployed: RR,
+         coverage: (RR, RR)) {
+    if disruption {
+        progress.disruptions += 1;
+    }
+    if termination.compromised() {
+        progress.compromises += 1;
+    }
+    if let Some(ref mut outcomes) = progress.outcomes {
+        outcomes.push(termination.compromised());
+    }
+    if let Some(ref mut counts) = progress.termination {
+        match termination {
+            Termination::Stalled(step) => {
+                counts.0 += 1;
+                counts.1 += step + 1;
+            }
+            Termination::BudgetExhausted(_) => counts.2 += 1,
+            _ => {}
+        }
+    }
+    if let Some(ref mut sums) = progress.coverage {
+        let (compromised, disrupted) = coverage;
+        sums.0 += compromised;
+        sums.1 = sums.1.max(compromised);
+        sums.2 += disrupted;
+        sums.3 = sums.3.max(disrupted);
+    }
+    progress.groups += groups;
+    progress.groups_range = Some(progress.groups_range.map_or((groups, groups), |(f, m)| {
+        (min(f, groups), max(m, groups))
+    }));
+    progress.deployed += deployed;
+}
+
+// Count the ages at the end of a repetition in `progress`
+fn tally_ages(progress: &mut Progress, ages: AgeSummary) {
+    progress.ages.0 += ages.mean_honest.unwrap_or(0.0);
+    if let Some(mean) = ages.mean_malicious {
+        progress.ages.1 += mean;
+        progress.ages.2 += 1;
+    }
+    progress.ages.3 = max(progress.ages.3, ages.oldest as NN);
+}
+
+// Repetition drawing once more from the churn stream at its end, as a change adding a draw
+// would, for testing `--rng-audit`
+#[cfg(test)]
+thread_local!(static EXTRA_DRAW: ::std::cell::Cell<Option<NN>> = ::std::cell::Cell::new(None));
+
+#[cfg(test)]
+fn extra_draw<R: Rng>(repetition: NN, rng: &mut R) {
+    if EXTRA_DRAW.with(|extra| extra.get()) == Some(repetition) {
+        let _ = rng.next_u32();
+    }
+}
+
+#[cfg(not(test))]
+fn extra_draw<R: Rng>(_repetition: NN, _rng: &mut R) {}
+
+#[test]
+fn test_malice_hist_total() {
+    // Large enough to split, so the number of groups varies between repetitions; every group
+    // of every repetition is counted in exactly one bucket.
+    let args = || {
+        let mut args = test_args(300, 60, 8);
+        args.max_steps = 5;
+        args.repetitions = 7;
+        args.malice_hist = true;
+        args.correction = true;
+        args
+    };
+
+    let tool = FullSimTool::new(args(), SimpleQuorum::new(), UntargettedAttack {});
+    let result = tool.calc_p_compromise();
+    let groups = (0..7)
+        .map(|rep| {
+            let (_, _, net, _, _) = tool.run_sim(rep, &mut |_, _, _| {});
+            net.groups().len() as NN
+        })
+        .sum::<NN>();
+    assert!(result.groups_range.map_or(false, |(_, max)| max > 1));
+    let hist = result.malice_hist.expect("histogram");
+    assert_eq!(hist.counts.iter().sum::<NN>(), groups);
+
+    let tool = SimStructureTool::new(args());
+    let result = tool.calc_p_compromise();
+    let groups = (0..7)
+        .map(|rep| {
+            let mut streams = tool.args.seeding.streams(tool.master_seed, rep);
+            tool.network(&mut streams).groups().len() as NN
+        })
+        .sum::<NN>();
+    assert!(groups > 7);
+    let hist = result.malice_hist.expect("histogram");
+    assert_eq!(hist.counts.iter().sum::<NN>(), groups);
+}
+
+#[test]
+fn test_age_share_curve() {
+    use super::quorum::AgeQuorum;
+
+    let mut args = test_args(200, 40, 8);
+    args.max_steps = 23;
+    args.repetitions = 3;
+    args.age_share_interval = Some(5);
+    let tool = FullSimTool::new(args, AgeQuorum::new(), UntargettedAttack {});
+    let curve = tool.calc_p_compromise().age_share.expect("age share");
+    let steps: Vec<NN> = curve.iter().map(|&(step, _)| step).collect();
+    assert_eq!(steps, vec![5, 10, 15, 20]);
+    for &(_, share) in &curve {
+        assert!(share >= 0.0 && share <= 1.0, "share {}", share);
+    }
+
+    // Without malicious nodes, the share is always zero
+    let mut args = test_args(200, 0, 8);
+    args.max_steps = 10;
+    args.repetitions = 2;
+    args.age_share_interval = Some(1);
+    let tool = FullSimTool::new(args, AgeQuorum::new(), UntargettedAttack {});
+    let curve = tool.calc_p_compromise().age_share.expect("age share");
+    assert_eq!(curve.len(), 10);
+    assert!(curve.iter().all(|&(_, share)| share == 0.0));
+
+    // With the attack from the start no honest node joins, and with no bursts or evictions none
+    // leaves; the untargetted attacker never resets its nodes. Only the attacker's joins churn
+    // the network, so its share rises as they join and then holds
+    let mut args = test_args(200, 40, 8);
+    args.max_steps = 30;
+    args.repetitions = 5;
+    args.age_share_interval = Some(1);
+    args.seeding.seed = Some(1);
+    let tool = FullSimTool::new(args, AgeQuorum::new(), UntargettedAttack {});
+    let curve = tool.calc_p_compromise().age_share.expect("age share");
+    let shares: Vec<RR> = curve.iter().map(|&(_, share)| share).collect();
+    assert!(shares.windows(2).all(|w| w[0] <= w[1]), "shares {:?}", shares);
+    assert!(shares[shares.len() - 1] > shares[0], "shares {:?}", shares);
+}
+
+#[test]
+fn test_fixed_structure_stream() {
+    use super::rng::{Seeding, Stream};
+    let seeding = Seeding {
+        seed: Some(7),
+        fixed: vec![Stream::Structure],
+    };
+
+    // The structure simulation's groups, thus its calculated probabilities, are the same in
+    // every repetition; which nodes are malicious is not
+    let mut args = test_args(1000, 300, 10);
+    args.malice_hist = true;
+    args.seeding = seeding.clone();
+    let tool = SimStructureTool::new(args);
+    let (a, b) = (tool.simulate(0), tool.simulate(1));
+    assert_eq!(a.groups, b.groups);
+    assert!((a.p_compromise - b.p_compromise).abs() < 1e-12);
+    assert!(a.malice_hist.expect("hist").counts != b.malice_hist.expect("hist").counts);
+
+    // Likewise the full simulation's initial network, while the attack varies
+    let mut args = test_args(300, 100, 8);
+    args.max_steps = 20;
+    args.seeding = seeding.clone();
+    let tool = FullSimTool::new(args, SimpleQuorum::new(), UntargettedAttack {});
+    let sorted = |mut v: Vec<usize>| {
+        v.sort();
+        v
+    };
+    let group_sizes = |rep: NN| {
+        let mut streams = seeding.streams(tool.master_seed, rep);
+        let net = tool.initial_network(&mut UntargettedAttack {},
+                                       0,
+                                       &mut streams.structure,
+                                       &mut streams.attack);
+        sorted(net.groups().values().map(|group| group.len()).collect())
+    };
+    assert_eq!(group_sizes(0), group_sizes(1));
+    let malicious_per_group = |rep: NN| {
+        let (_, _, net, _, _) = tool.run_sim(rep, &mut |_, _, _| {});
+        sorted(net.groups()
+            .values()
+            .map(|group| group.values().filter(|node| node.is_malicious()).count())
+            .collect())
+    };
+    assert!(malicious_per_group(0) != malicious_per_group(1));
+}
+
+#[test]
+fn test_honest_uptime_mix() {
+    use super::uptime::UptimeDist;
+
+    // Two kinds of honest node: always up, and rarely up. The former should age
+    // much faster, so the final ages split into two groups.
+    let mut args = test_args(600, 0, 10);
+    args.max_steps = 0;
+    args.honest_uptime = UptimeDist::Mix(vec![(1.0, 0.5), (0.1, 0.5)]);
+    let tool = FullSimTool::new(args, SimpleQuorum::new(), UntargettedAttack {});
+    let (_, _, net, _, _) = tool.run_sim(0, &mut |_, _, _| {});
+    let mean_age = |uptime: RR| {
+        let ages: Vec<u32> = net.groups()
+            .values()
+            .flat_map(|group| group.values())
+            .filter(|data| data.uptime() == uptime)
+            .map(|data| data.age())
+            .collect();
+        assert!(ages.len() > 200, "{} nodes with uptime {}", ages.len(), uptime);
+        (ages.iter().sum::<u32>() as RR) / (ages.len() as RR)
+    };
+    // Ageing from age a needs 2^a churns, so a node up 10% of the time should be
+    // around log2(10) ≈ 3.3 younger.
+    let (up, down) = (mean_age(1.0), mean_age(0.1));
+    assert!(up > down + 2.0, "mean age {} (up), {} (down)", up, down);
+}
+
+#[test]
+fn test_checkpoint_resume() {
+    use super::checkpoint::{Checkpointing, CheckpointFile};
+    use super::quorum::SimpleTargettedAttack;
+    use super::model::MODEL_VERSION;
+    use super::output::{partial_path, test_dir};
+    use std::fs;
+    use std::io::{Read, Write};
+
+    let dir = test_dir();
+    let checkpointing = Checkpointing {
+        dir: dir.clone(),
+        every: 0.0,
+        allow_model_mismatch: false,
+        keep_partial: false,
+    };
+    let id = "v1-0123456789ab";
+    let tool = |checkpoint: bool| {
+        let mut args = test_args(200, 40, 8);
+        args.max_steps = 20;
+        args.repetitions = 10;
+        args.malice_hist = true;
+        args.seeding.seed = Some(11);
+        if checkpoint {
+            args.checkpoint = Some(CheckpointFile::new(&checkpointing, id, "test"));
+        }
+        FullSimTool::new(args, SimpleQuorum::new(), SimpleTargettedAttack::new())
+    };
+    let uninterrupted = tool(false).calc_p_compromise();
+
+    // A run stopped after a checkpoint at 4 repetitions
+    let file = CheckpointFile::new(&checkpointing, id, "test");
+    let name = file.path().file_name().and_then(|name| name.to_str()).expect("name");
+    assert!(name.starts_with("v1-0123456789ab-") && name.ends_with(".ckpt"));
+    {
+        let stopped = tool(true);
+        let mut progress = stopped.start();
+        assert_eq!(stopped.calc_until(&mut progress, 4).runs, Some(4));
+    }
+    let empty = tool(false).start();
+    assert_eq!(file.load(&empty).expect("saved").repetitions, 4);
+
+    // Resumed, it gives the results of the uninterrupted run
+    let resumed = tool(true);
+    assert_eq!(resumed.start().repetitions, 4);
+    let result = resumed.calc_p_compromise();
+    assert_eq!(result.runs, Some(10));
+    assert_eq!((result.p_disrupt, result.p_compromise),
+               (uninterrupted.p_disrupt, uninterrupted.p_compromise));
+    assert_eq!(result.malice_hist, uninterrupted.malice_hist);
+    let progress = file.load(&empty).expect("saved on completion");
+    assert_eq!(progress.repetitions, 10);
+
+    // Running again uses the complete checkpoint
+    let result = tool(true).calc_p_compromise();
+    assert_eq!((result.p_disrupt, result.p_compromise),
+               (uninterrupted.p_disrupt, uninterrupted.p_compromise));
+
+    // A checkpoint of other parameters or reports is ignored
+    assert!(CheckpointFile::new(&checkpointing, id, "other").load(&empty).is_none());
+    let other_reports = Progress::new(Reports::default());
+    assert!(file.load(&other_reports).is_none());
+
+    // One left under its partial name, as by a program stopped before renaming it, is resumed
+    // from only when partial files are kept, and only if complete
+    let mut saved = String::new();
+    let _ = fs::File::open(file.path())
+        .and_then(|mut f| f.read_to_string(&mut saved))
+        .expect("read");
+    let partial = partial_path(file.path());
+    fs::rename(file.path(), &partial).expect("rename");
+    assert!(file.load(&empty).is_none());
+    let keeping = Checkpointing {
+        keep_partial: true,
+        ..checkpointing.clone()
+    };
+    let kept = CheckpointFile::new(&keeping, id, "test");
+    assert_eq!(kept.load(&empty).expect("partial").repetitions, 10);
+    fs::File::create(&partial)
+        .and_then(|mut f| f.write_all(saved[..saved.len() / 2].as_bytes()))
+        .expect("write");
+    assert!(kept.load(&empty).is_none());
+    fs::remove_file(&partial).expect("remove");
+    fs::File::create(file.path()).and_then(|mut f| f.write_all(saved.as_bytes())).expect("write");
+
+    // As is one saved by another version of the model, unless allowed
+    let mut contents = String::new();
+    let _ = fs::File::open(file.path())
+        .and_then(|mut f| f.read_to_string(&mut contents))
+        .expect("read");
+    let version = format!("model {}\n", MODEL_VERSION);
+    assert!(contents.starts_with(&version));
+    let other_model = format!("model 0\n{}", &contents[version.len()..]);
+    fs::File::create(file.path())
+        .and_then(|mut f| f.write_all(other_model.as_bytes()))
+        .expect("write");
+    assert!(file.load(&empty).is_none());
+    let allowing = Checkpointing {
+        allow_model_mismatch: true,
+        ..checkpointing.clone()
+    };
+    assert!(CheckpointFile::new(&allowing, id, "test").load(&empty).is_some());
+
+    fs::remove_dir_all(&dir).expect("remove directory");
+}
+
+#[test]
+fn test_elder_report_matches_compromise() {
+    use super::quorum::ElderQuorum;
+
+    let mut args = test_args(500, 150, 8);
+    args.max_steps = 20;
+    args.repetitions = 10;
+    args.elders_report = true;
+    let tool = FullSimTool::new(args, ElderQuorum::new(), UntargettedAttack {});
+    let result = tool.calc_p_compromise();
+    let elders = result.elders.expect("elder report");
+    assert_eq!(elders.p_quorum, result.p_compromise);
+    assert!(elders.mean_malicious <= elders.max_malicious as RR);
+}
+
+#[test]
+fn test_promotion_lag() {
+    use super::quorum::{ElderQuorum, DEFAULT_ELDERS};
+
+    let args = |lag: NN| {
+        let mut args = test_args(500, 150, 8);
+        args.max_steps = 20;
+        args.repetitions = 10;
+        args.elders_report = true;
+        args.promotion_lag = lag;
+        args.seeding.seed = Some(2);
+        args
+    };
+    // The elder report and quorum checks see the same (seated) elders
+    let tool = FullSimTool::new(args(5), ElderQuorum::new(), UntargettedAttack {});
+    let result = tool.calc_p_compromise();
+    assert_eq!(result.elders.expect("elder report").p_quorum, result.p_compromise);
+
+    // Groups have at most 7 seated elders, all members, and fewer during a lag
+    let mut vacancies = 0;
+    let _ = tool.run_sim(0, &mut |_, net, roster| {
+        for (prefix, group) in net.groups() {
+            let voting = roster.voting(prefix, group);
+            assert!(voting.len() <= DEFAULT_ELDERS);
+            assert!(voting.keys().all(|name| group.contains_key(name)));
+            if voting.len() < DEFAULT_ELDERS {
+                vacancies += 1;
+            }
+        }
+    });
+    assert!(vacancies > 0);
+    let tool = FullSimTool::new(args(0), ElderQuorum::new(), UntargettedAttack {});
+    let _ = tool.run_sim(0, &mut |_, net, roster| {
+        for (prefix, group) in net.groups() {
+            assert_eq!(roster.voting(prefix, group).len(), group.len());
+        }
+    });
+}
+
+#[test]
+fn test_liveness_report() {
+    use super::uptime::UptimeDist;
+
+    // No malicious nodes, so the network does not change during the attack.
+    let tool = |uptime: RR| {
+        let mut args = test_args(200, 0, 10);
+        args.max_steps = 6;
+        args.repetitions = 4;
+        args.liveness = true;
+        args.honest_uptime = UptimeDist::Constant(uptime);
+        args.seeding.seed = Some(7);
+        FullSimTool::new(args, SimpleQuorum::new(), UntargettedAttack {})
+    };
+    // Nodes which have aged are rarely up (never would be, but then the initial
+    // network could not be built): every group lacks a quorum of live members
+    // at every step
+    let result = tool(0.05).calc_p_compromise();
+    let liveness = result.liveness.expect("liveness report");
+    assert_eq!(liveness.p_failure, 1.0);
+    assert_eq!(liveness.mean_group_steps, 6.0 * result.groups);
+    assert_eq!(result.p_disrupt, 0.0);
+
+    // Always up: never
+    let liveness = tool(1.0).calc_p_compromise().liveness.expect("liveness report");
+    assert_eq!((liveness.p_failure, liveness.mean_group_steps), (0.0, 0.0));
+}
+
+#[test]
+fn test_burst_halves_honest_nodes() {
+    use super::burst::Burst;
+
+    let mut args = test_args(200, 20, 10);
+    args.max_steps = 1;
+    args.burst = Some(Burst {
+        prob: 1.0,
+        fraction: 0.5,
+    });
+    let tool = FullSimTool::new(args, SimpleQuorum::new(), UntargettedAttack {});
+    // Honest and failed members at the end of the step
+    let mut honest = vec![];
+    let (_, _, net, _, _) = tool.run_sim(0, &mut |_, net, _| {
+        let members: Vec<&NodeData> =
+            net.groups().values().flat_map(|group| group.values()).collect();
+        honest.push((members.iter().filter(|data| !data.is_malicious()).count(),
+                     members.iter().filter(|data| data.is_failed()).count()));
+    });
+    assert_eq!(honest, vec![(180, 90)]);
+    // Failed nodes are removed once the step is over. New malicious nodes are
+    // still doing proof-of-work, so only honest nodes remain.
+    assert_eq!(net.groups().values().map(|group| group.len()).sum::<usize>(), 90);
+
+    // Losing every honest node leaves no group live, at every step
+    let mut args = test_args(200, 0, 10);
+    args.max_steps = 3;
+    args.repetitions = 2;
+    args.liveness = true;
+    args.burst = Some(Burst {
+        prob: 1.0,
+        fraction: 1.0,
+    });
+    let result = FullSimTool::new(args, SimpleQuorum::new(), UntargettedAttack {})
+        .calc_p_compromise();
+    let liveness = result.liveness.expect("liveness report");
+    assert_eq!(liveness.p_failure, 1.0);
+    assert_eq!(liveness.mean_group_steps, 3.0 * result.groups);
+}
+
+#[test]
+fn test_corruption_keeps_age() {
+    use super::quorum::AgeQuorum;
+
+    // Corrupting a quarter of the honest nodes at the first step gives the attacker as many
+    // nodes as joining them fresh, in their old places and with their ages
+    let mut args = test_args(200, 0, 8);
+    args.max_steps = 1;
+    args.corruption = Some("0.25@0".parse().expect("corruption"));
+    let tool = FullSimTool::new(args, SimpleQuorum::new(), UntargettedAttack {});
+    let (_, _, net, _, _) = tool.run_sim(0, &mut |_, _, _| {});
+    let members: Vec<&NodeData> = net.groups().values().flat_map(|group| group.values()).collect();
+    assert_eq!(members.len(), 200);
+    let malicious: Vec<&&NodeData> = members.iter().filter(|data| data.is_malicious()).collect();
+    assert_eq!(malicious.len(), 50);
+    assert!(malicious.iter().any(|data| data.age() > 0));
+
+    // Under the age quorum, corrupting 30% of the nodes compromises far more often than
+    // joining as many fresh malicious nodes
+    let p_compromise = |corrupt: bool| {
+        let mut args = test_args(200, if corrupt { 0 } else { 60 }, 8);
+        args.max_steps = 50;
+        args.repetitions = 20;
+        if corrupt {
+            args.corruption = Some("0.3@0".parse().expect("corruption"));
+        }
+        args.seeding.seed = Some(1);
+        FullSimTool::new(args, AgeQuorum::new(), UntargettedAttack {})
+            .calc_p_compromise()
+            .p_compromise
+    };
+    let (corrupted, joined) = (p_compromise(true), p_compromise(false));
+    assert!(corrupted >= joined + 0.5, "corrupted {}, joined {}", corrupted, joined);
+}
+
+#[test]
+fn test_eviction_suppresses_compromise() {
+    // Half the nodes are malicious, so without evictions compromise is certain
+    let tool = |rate: RR, resource: RR| {
+        let mut args = test_args(400, 200, 8);
+        args.max_steps = 10;
+        args.repetitions = 10;
+        args.eviction_rate = rate;
+        args.attack_resource = resource;
+        args.seeding.seed = Some(4);
+        FullSimTool::new(args, SimpleQuorum::new(), UntargettedAttack {})
+    };
+    assert_eq!(tool(0.0, 1.0).calc_p_compromise().p_compromise, 1.0);
+    // Malicious nodes are always evicted once joined, honest ones rarely
+    assert_eq!(tool(0.001, 0.001).calc_p_compromise().p_compromise, 0.0);
+
+    // Malicious members at the end of each step, and the network at the end
+    let members = |tool: FullSimTool<SimpleQuorum, UntargettedAttack>| {
+        let mut malicious = vec![];
+        let (_, _, net, _, _) = tool.run_sim(0, &mut |_, net, _| {
+            malicious.push(net.groups()
+                .values()
+                .flat_map(|group| group.values())
+                .filter(|data| data.is_malicious())
+                .count());
+        });
+        (malicious, net)
+    };
+    // Evicted at every check, no malicious node is a member at the end of a step, though each
+    // rejoins
+    let (malicious, net) = members(tool(0.001, 0.001));
+    assert!(malicious.iter().all(|&count| count == 0), "{:?}", malicious);
+    assert!(net.groups().values().map(|group| group.len()).sum::<usize>() > 150);
+
+    // Evicted two thirds of the time, a quarter of the nodes being malicious: malicious nodes
+    // are weeded out but rejoin, so keep being members, and compromise is possible but less
+    // likely than with the honest nodes' resources
+    let partial = |resource: RR| {
+        let mut args = test_args(400, 100, 8);
+        args.max_steps = 30;
+        args.repetitions = 20;
+        args.eviction_rate = 0.01;
+        args.attack_resource = resource;
+        args.seeding.seed = Some(4);
+        FullSimTool::new(args, SimpleQuorum::new(), UntargettedAttack {})
+    };
+    let (malicious, _) = members(partial(0.015));
+    // (after the first step, in which the first of them do proof-of-work)
+    assert!(malicious[1..].iter().all(|&count| count > 0 && count < 100), "{:?}", malicious);
+    let (equal, short) = (partial(1.0).calc_p_compromise().p_compromise,
+                          partial(0.015).calc_p_compromise().p_compromise);
+    assert!(short > 0.0 && short < equal, "resource 1: {}, resource 0.015: {}", equal, short);
+
+    // Each eviction is a churn event in the group left, so nodes age without anyone joining
+    let mut args = test_args(400, 0, 8);
+    args.max_steps = 10;
+    args.eviction_rate = 0.01;
+    args.seeding.seed = Some(4);
+    let tool_honest = FullSimTool::new(args, SimpleQuorum::new(), UntargettedAttack {});
+    let (_, _, net, _, _) = tool_honest.run_sim(0, &mut |_, _, _| {});
+    assert!(net.churns() > 0);
+    assert!(net.groups().values().flat_map(|group| group.values()).any(|data| data.age() > 0));
+
+    // Under the Poisson churn model the numbers evicted vary, with the same means, so a few
+    // malicious nodes may escape a check: compromise is rare rather than impossible
+    let mut tool = tool(0.001, 0.001);
+    tool.args.churn_model = ChurnModel::Poisson;
+    let p = tool.calc_p_compromise().p_compromise;
+    assert!(p < 0.5, "P(compromise) {}", p);
+}
+
+#[test]
+fn test_rejoin_age_recovery() {
+    use super::quorum::{AgeQuorum, SimpleTargettedAttack};
+
+    // A join-leave attack under the age quorum, recovering the proportion `recovery` of age
+    let p_compromise = |recovery: RR| {
+        let mut args = test_args(300, 50, 8);
+        args.max_steps = 100;
+        args.repetitions = 20;
+        args.rejoin_recovery = recovery;
+        args.seeding.seed = Some(1);
+        FullSimTool::new(args, AgeQuorum::new(), SimpleTargettedAttack::new())
+            .calc_p_compromise()
+            .p_compromise
+    };
+    // Recovering half the age materially strengthens the attack: identities keep their full age
+    // however often they rejoin, so reset nodes rejoin with half of an ever greater age.
+    let (none, half, full) = (p_compromise(0.0), p_compromise(0.5), p_compromise(1.0));
+    assert!(half >= none + 0.2, "recovery 0: {}, recovery 0.5: {}", none, half);
+    assert!(full >= half, "recovery 0.5: {}, recovery 1: {}", half, full);
+}
+
+#[test]
+fn test_verify() {
+    use super::burst::Burst;
+    use super::quorum::SimpleTargettedAttack;
+
+    // Departures, corruption, resets and rejoins all keep the invariants of the structure, and
+    // checking them changes nothing
+    let full = |verify: bool| {
+        let mut args = test_args(300, 60, 8);
+        args.max_steps = 100;
+        args.repetitions = 4;
+        args.verify = verify;
+        args.burst = Some(Burst {
+            prob: 0.05,
+            fraction: 0.2,
+        });
+        args.eviction_rate = 0.01;
+        args.corruption = Some("0.1@10".parse().expect("corruption"));
+        args.rejoin_recovery = 0.5;
+        args.placement = "concentrated(0.5)".parse().expect("placement");
+        args.seeding.seed = Some(1);
+        let result = FullSimTool::new(args, SimpleQuorum::new(), SimpleTargettedAttack::new())
+            .calc_p_compromise();
+        (result.p_compromise, result.groups)
+    };
+    assert_eq!(full(true), full(false));
+
+    let structure = |verify: bool| {
+        let mut args = test_args(1000, 100, 10);
+        args.verify = verify;
+        args.placement = "concentrated(0.5)".parse().expect("placement");
+        args.seeding.seed = Some(1);
+        let result = SimStructureTool::new(args).calc_p_compromise();
+        (result.p_compromise, result.groups)
+    };
+    assert_eq!(structure(true), structure(false));
+}
+
+#[test]
+fn test_termination() {
+    let tool = |num_malicious: NN, configure: &Fn(&mut ToolArgs)| {
+        let mut args = test_args(100, num_malicious, 8);
+        args.max_steps = 1000;
+        args.repetitions = 5;
+        args.termination = true;
+        args.seeding.seed = Some(2);
+        configure(&mut args);
+        FullSimTool::new(args, SimpleQuorum::new(), UntargettedAttack {})
+    };
+
+    // Two malicious nodes, untargetted: once they have joined nothing changes, and the run
+    // stops long before the last step (though each step is still reported)
+    let stalling = tool(2, &|_| {});
+    let mut reported = 0;
+    let (_, termination, _, _, _) = stalling.run_sim(0, &mut |_, _, _| reported += 1);
+    match termination {
+        Termination::Stalled(step) => assert!(step < 20, "stalled at step {}", step),
+        other => panic!("expected a stall, not {:?}", other),
+    }
+    assert_eq!(reported, 1000);
+    let report = stalling.calc_p_compromise().termination.expect("termination report");
+    assert_eq!((report.compromised, report.stalled, report.steps_exhausted), (0, 5, 0));
+    assert!(report.mean_stall_steps.expect("mean steps") < 20.0);
+
+    // Malicious nodes always evicted: the attacker never runs out, since they rejoin
+    let evicting = tool(20, &|args| {
+        args.max_steps = 30;
+        args.eviction_rate = 0.001;
+        args.attack_resource = 0.001;
+    });
+    assert_eq!(evicting.run_sim(0, &mut |_, _, _| {}).1, Termination::StepsExhausted);
+
+    // Before the attack starts, honest nodes keep joining
+    let growing = tool(2, &|args| {
+        args.max_steps = 30;
+        args.attack_start = 100;
+    });
+    assert_eq!(growing.run_sim(0, &mut |_, _, _| {}).1, Termination::StepsExhausted);
+}
+
+#[test]
+fn test_governance() {
+    let report = |num_malicious: NN, attack_start: NN| {
+        let mut args = test_args(40, num_malicious, 8);
+        args.max_steps = 40;
+        args.repetitions = 10;
+        args.attack_start = attack_start;
+        args.governance = true;
+        args.seeding.seed = Some(6);
+        FullSimTool::new(args, SimpleQuorum::new(), UntargettedAttack {})
+            .calc_p_compromise()
+            .governance
+            .expect("governance report")
+    };
+    // Honest nodes join until the groups split; with no attacker, no split is blockable
+    let honest = report(0, 40);
+    assert!(honest.mean_changes >= 1.0);
+    assert_eq!((honest.p_blockable, honest.mean_blockable), (0.0, 0.0));
+    // As many malicious nodes join: the groups they infiltrate cannot split without them
+    let infiltrated = report(40, 0);
+    assert!(infiltrated.mean_changes >= 1.0);
+    assert_eq!(infiltrated.p_blockable, 1.0);
+}
+
+#[test]
+fn test_replay_attack() {
+    use super::quorum::{ElderQuorum, SimpleTargettedAttack};
+    let args = |replay: Option<Vec<AttackDecision>>| {
+        let mut args = test_args(200, 40, 8);
+        args.max_steps = 60;
+        args.repetitions = 1;
+        args.record_attack = true;
+        args.replay_attack = replay;
+        args.seeding.seed = Some(8);
+        args
+    };
+    let recorded = FullSimTool::new(args(None), SimpleQuorum::new(), SimpleTargettedAttack::new())
+        .calc_p_compromise();
+    let decisions = recorded.attack_record.clone().expect("attack record");
+    assert!(decisions.iter().any(|d| d.reset));
+
+    // Replayed in place of a strategy which never resets, the (first) repetition is the same
+    let replayed = FullSimTool::new(args(Some(decisions.clone())),
+                                    SimpleQuorum::new(),
+                                    UntargettedAttack {})
+        .calc_p_compromise();
+    assert_eq!(replayed.attack_record.as_ref(), Some(&decisions));
+    assert_eq!((replayed.p_disrupt, replayed.p_compromise, replayed.groups),
+               (recorded.p_disrupt, recorded.p_compromise, recorded.groups));
+
+    // A different quorum type does not change where nodes are offered, so the replay runs
+    let elder = FullSimTool::new(args(Some(decisions.clone())),
+                                 ElderQuorum::new(),
+                                 UntargettedAttack {})
+        .calc_p_compromise();
+    let elder_record = elder.attack_record.expect("attack record");
+    let common = min(elder_record.len(), decisions.len());
+    assert_eq!(&elder_record[..common], &decisions[..common]);
+}
+
+#[test]
+fn test_rng_audit() {
+    use super::audit::{AUDIT_TITLES, audit_rows, compare};
+    use super::uptime::UptimeDist;
+
+    // The audit of three repetitions, the second drawing once more from the churn stream if
+    // `extra`. Honest nodes are not always up, so that liveness draws too.
+    let audit = |extra: bool| {
+        let mut args = test_args(100, 20, 8);
+        args.max_steps = 30;
+        args.repetitions = 3;
+        args.liveness = true;
+        args.honest_uptime = UptimeDist::Constant(0.9);
+        args.rng_audit = true;
+        args.seeding.seed = Some(9);
+        EXTRA_DRAW.with(|draw| draw.set(if extra { Some(1) } else { None }));
+        let result = FullSimTool::new(args, SimpleQuorum::new(), UntargettedAttack {})
+            .calc_p_compromise();
+        EXTRA_DRAW.with(|draw| draw.set(None));
+        let record = result.rng_draws.expect("draws");
+        assert_eq!(record.len(), 3);
+        assert!(record.iter().all(|draws| draws.iter().all(|&n| n > 0)));
+        format!("{}\n{}\n", AUDIT_TITLES, audit_rows(0, "v1-0123456789ab", &record))
+    };
+    let reference = audit(false);
+    assert_eq!(compare(&reference, &audit(false)), Ok(None));
+
+    // The audit finds the extra draw where it was made
+    let divergence = compare(&reference, &audit(true)).expect("audits").expect("divergence");
+    let (first, second) = (divergence.first.expect("first"), divergence.second.expect("second"));
+    assert_eq!((first.repetition, first.stream), (1, Stream::Churn));
+    assert_eq!(second.draws, first.draws + 1);
+}
+
+// Strategy leaking state from one repetition into the next, for testing the split check. Each
+// repetition attacks with a clone of the tool's strategy, and clones share a count of the clones
+// made: the first `targetted` clones reset every malicious node offered outside the lineage of
+// prefix 00000000, so that all join there, and later ones none (as `UntargettedAttack`).
+#[cfg(test)]
+struct LeakyAttack {
+    clones: ::std::sync::Arc<::std::sync::atomic::AtomicUsize>,
+    targetted: usize,
+    target: Option<Prefix>,
+}
+
+#[cfg(test)]
+impl Clone for LeakyAttack {
+    fn clone(&self) -> Self {
+        let clones = self.clones.fetch_add(1, ::std::sync::atomic::Ordering::SeqCst) + 1;
+        LeakyAttack {
+            clones: self.clones.clone(),
+            targetted: self.targetted,
+            target: if clones > self.targetted {
+                None
+            } else {
+                Some("00000000".parse().expect("prefix"))
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+impl AttackStrategy for LeakyAttack {
+    fn split(&mut self,
+             _old_prefix: Prefix,
+             _new_prefix: Prefix,
+             _node_name: NodeName,
+             _node_data: &NodeData) {
+    }
+
+    fn reset_node(&mut self,
+                  _node: &super::sim::Node,
+                  prefix: Prefix,
+                  _progress: &AttackProgress)
+                  -> bool {
+        self.target.map_or(false, |target| !prefix.is_compatible(target))
+    }
+}
+
+#[test]
+fn test_split_check_leakage() {
+    // Many repetitions, so without the checks of every step
+    let args = || {
+        let mut args = test_args(200, 40, 8);
+        args.max_steps = 100;
+        args.repetitions = 200;
+        args.paranoid = None;
+        args.split_check = Some(2);
+        args.seeding.seed = Some(1);
+        args
+    };
+    // Independent repetitions agree
+    let result = FullSimTool::new(args(), SimpleQuorum::new(), UntargettedAttack {})
+        .calc_p_compromise();
+    assert!(result.outcomes.is_none());
+    let check = result.split_check.expect("split check");
+    assert!(!check.flagged(), "p-value {}", check.p_value);
+
+    // Leaking the number of repetitions run targets the attack in the first half only
+    let leaky = LeakyAttack {
+        clones: ::std::sync::Arc::new(::std::sync::atomic::AtomicUsize::new(0)),
+        targetted: 100,
+        target: None,
+    };
+    let check = FullSimTool::new(args(), SimpleQuorum::new(), leaky)
+        .calc_p_compromise()
+        .split_check
+        .expect("split check");
+    assert!(check.flagged(), "p-value {}", check.p_value);
+    assert!(check.folds[0].1 > check.folds[1].1);
+}
+
+#[test]
+fn test_sim_logging() {
+    use super::logging;
+    use log::LogLevelFilter;
+
+    // Each run logs its progress as debug messages, and each node added as trace messages
+    let run = || {
+        let mut args = test_args(100, 10, 8);
+        args.max_steps = 10;
+        args.repetitions = 1;
+        let _ = FullSimTool::new(args, SimpleQuorum::new(), UntargettedAttack {})
+            .calc_p_compromise();
+    };
+    let lines = logging::capture(LogLevelFilter::Debug, &run);
+    assert!(lines.contains("DEBUG routing_sims::tools: Init done: added 90 nodes"),
+            "{}",
+            lines);
+    assert!(lines.contains("DEBUG routing_sims::tools: Starting sim\n"));
+    assert!(!lines.contains("TRACE"));
+    assert!(logging::capture(LogLevelFilter::Trace, &run)
+        .contains("TRACE routing_sims::tools: Added node"));
+    assert_eq!(logging::capture(LogLevelFilter::Info, &run), "");
+}
+
+#[test]
+fn test_time_limit() {
+    let args = |time_limit: Option<RR>| {
+        let mut args = test_args(200, 40, 8);
+        args.max_steps = 100;
+        args.repetitions = 5;
+        args.time_limit = time_limit;
+        args.seeding.seed = Some(4);
+        args
+    };
+    let full = |time_limit| {
+        FullSimTool::new(args(time_limit), SimpleQuorum::new(), UntargettedAttack {})
+    };
+
+    // A generous limit changes nothing
+    let unlimited = full(None).calc_p_compromise();
+    let limited = full(Some(3600.0)).calc_p_compromise();
+    assert!(!unlimited.truncated && !limited.truncated);
+    assert_eq!((limited.runs, limited.p_compromise), (unlimited.runs, unlimited.p_compromise));
+
+    // No time at all: nothing is completed, and a run is stopped at its first check
+    let tool = full(Some(0.0));
+    let result = tool.calc_p_compromise();
+    assert!(result.truncated);
+    assert_eq!(result.runs, Some(0));
+    let deadline = Deadline::new(0.0);
+    let ((_, termination, _, _, _), ..) =
+        tool.run_recorded(0, vec![], Some(&deadline), &mut |_, _, _| {});
+    assert_eq!(termination, Termination::TimedOut(0));
+
+    // Time already spent (e.g. in earlier rounds) counts against the limit
+    let tool = full(Some(60.0));
+    let mut progress = tool.start();
+    assert!(!tool.calc_until(&mut progress, 2).truncated);
+    progress.elapsed = 60.0;
+    let result = tool.calc_until(&mut progress, 5);
+    assert!(result.truncated);
+    assert_eq!(result.runs, Some(2));
+
+    // The structure simulation stops after the first structure
+    let first = SimStructureTool::new(args(None)).calc_p_compromise();
+    let mut structure_args = args(Some(0.0));
+    structure_args.correction = true;
+    let result = SimStructureTool::new(structure_args).calc_p_compromise();
+    assert!(result.truncated);
+    assert_eq!(result.groups, first.groups);
+    assert!((result.p_compromise - first.p_compromise).abs() < 1e-12);
+}
+
+#[test]
+fn test_interrupt() {
+    use super::interrupt::Interrupt;
+
+    let interrupt = Interrupt::new();
+    let args = || {
+        let mut args = test_args(200, 40, 8);
+        args.max_steps = 100;
+        args.repetitions = 5;
+        args.interrupt = interrupt.clone();
+        args.seeding.seed = Some(4);
+        args
+    };
+    let uninterrupted = FullSimTool::new(args(), SimpleQuorum::new(), UntargettedAttack {})
+        .calc_p_compromise();
+    assert!(!uninterrupted.truncated);
+
+    // Interrupted mid-sweep, a set stops at its next repetition, keeping those completed
+    let tool = FullSimTool::new(args(), SimpleQuorum::new(), UntargettedAttack {});
+    let mut progress = tool.start();
+    let first = tool.calc_until(&mut progress, 2);
+    assert!(!first.truncated);
+    interrupt.interrupt();
+    let result = tool.calc_until(&mut progress, 5);
+    assert!(result.truncated);
+    assert_eq!((result.runs, result.p_compromise), (first.runs, first.p_compromise));
+
+    // Sets started afterwards do nothing
+    let result = FullSimTool::new(args(), SimpleQuorum::new(), UntargettedAttack {})
+        .calc_p_compromise();
+    assert!(result.truncated);
+    assert_eq!(result.runs, Some(0));
+    let mut structure_args = args();
+    structure_args.correction = true;
+    assert!(SimStructureTool::new(structure_args).calc_p_compromise().truncated);
+}
+
+#[test]
+fn test_pow_scaling_delays_joins() {
+    // Malicious nodes in the network at the end of each step
+    let placed = |num_nodes: NN, scaling: PowScaling| {
+        let mut args = test_args(num_nodes, 200, 10);
+        args.max_steps = 4;
+        args.pow_scaling = scaling;
+        args.seeding.seed = Some(3);
+        let tool = FullSimTool::new(args, SimpleQuorum::new(), UntargettedAttack {});
+        let mut placed = vec![];
+        tool.run_sim(0, &mut |_, net, _| {
+            placed.push(net.groups()
+                .values()
+                .flat_map(|group| group.values())
+                .filter(|data| data.is_malicious())
+                .count());
+        });
+        placed
+    };
+    // With 1000 honest nodes, proof-of-work takes one step whatever the scaling
+    assert_eq!(placed(1200, PowScaling::LinearN), placed(1200, PowScaling::Constant));
+    // With 2000, it takes two under linear scaling, so the attacker has placed
+    // fewer nodes after the same number of steps
+    let constant = placed(2200, PowScaling::Constant);
+    let linear = placed(2200, PowScaling::LinearN);
+    assert_eq!((constant[0], linear[0]), (0, 0));
+    assert!(constant[1] > 0);
+    assert_eq!(linear[1], 0);
+    assert!(linear[2] > 0);
+}
+
+#[test]
+fn test_later_attack_start_is_harder() {
+    use super::quorum::AgeQuorum;
+
+    let p_compromise = |attack_start: NN| {
+        let mut args = test_args(200, 60, 8);
+        args.max_steps = 60;
+        args.repetitions = 100;
+        args.attack_start = attack_start;
+        args.seeding.seed = Some(1);
+        FullSimTool::new(args, AgeQuorum::new(), UntargettedAttack {})
+            .calc_p_compromise()
+            .p_compromise
+    };
+    let (early, middle, late) = (p_compromise(0), p_compromise(20), p_compromise(40));
+    assert!(early > middle && middle > late,
+            "{} {} {}",
+            early,
+            middle,
+            late);
+}
+
+#[test]
+fn test_join_cap() {
+    use super::join_cap::JoinCap;
+    use super::sim::Event;
+
+    // Whether nodes kept out are rejected or queued, no group takes more than 2 new members
+    // (joining or relocated) in any 3 steps, though the attack would add more. Each admission is
+    // counted, including those of nodes churned or relocated out again within the window: with
+    // --verify the events of each step are taken only after it, so are all seen at its end.
+    for cap in &["2:3", "2:3+queue"] {
+        let cap: JoinCap = cap.parse().expect("parse");
+        let mut args = test_args(200, 60, 8);
+        args.max_steps = 60;
+        args.join_cap = Some(cap);
+        args.verify = true;
+        args.seeding.seed = Some(3);
+        let tool = FullSimTool::new(args, SimpleQuorum::new(), UntargettedAttack {});
+        // Step and name of each node admitted so far
+        let mut admitted: Vec<(NN, NodeName)> = vec![];
+        let mut most = 0;
+        let _ = tool.run_sim(0, &mut |step, net, _| {
+            admitted.extend(net.events().iter().filter_map(|event| match *event {
+                Event::Added(_, name) => Some((step, name)),
+                _ => None,
+            }));
+            // Joins count by name against the group each node is in now
+            for prefix in net.groups().keys() {
+                let in_window = admitted.iter()
+                    .filter(|&&(at, name)| at + cap.window > step && prefix.matches(name))
+                    .count();
+                most = max(most, in_window as NN);
+            }
+        });
+        assert_eq!(most, cap.joins, "{}", cap);
+    }
+}
+
+#[test]
+fn test_join_cap_queue_order() {
+    use super::join_cap::JoinCap;
+    use super::sim::{Event, Node};
+    use std::sync::{Arc, Mutex};
+
+    // Malicious nodes in the order offered to the strategy, i.e. put in the queue to join, with
+    // the step of each
+    #[derive(Clone)]
+    struct Offered(Arc<Mutex<Vec<(NN, NodeName)>>>);
+    impl AttackStrategy for Offered {
+        fn split(&mut self, _: Prefix, _: Prefix, _: NodeName, _: &NodeData) {}
+        fn reset_node(&mut self, node: &Node, _: Prefix, progress: &AttackProgress) -> bool {
+            self.0.lock().expect("lock").push((progress.step, node.0));
+            false
+        }
+    }
+
+    // In queue mode, nodes kept out keep their names and join their group in the order they
+    // were queued, ahead of those queued later
+    let cap: JoinCap = "1:2+queue".parse().expect("parse");
+    let mut args = test_args(200, 60, 8);
+    args.max_steps = 80;
+    args.join_cap = Some(cap);
+    args.verify = true;
+    args.seeding.seed = Some(3);
+    let offered = Arc::new(Mutex::new(vec![]));
+    let tool = FullSimTool::new(args, SimpleQuorum::new(), Offered(offered.clone()));
+    // The order and step in which each node was admitted
+    let mut admitted: HashMap<NodeName, (usize, NN)> = HashMap::new();
+    let (_, _, net, _, _) = tool.run_sim(0, &mut |step, net, _| {
+        for event in net.events() {
+            if let Event::Added(_, name) = *event {
+                let order = admitted.len();
+                let _ = admitted.insert(name, (order, step));
+            }
+        }
+    });
+    let offered = offered.lock().expect("lock");
+    let mut held = 0;
+    for prefix in net.groups().keys() {
+        let mut order = vec![];
+        for &(at, name) in offered.iter().filter(|&&(_, name)| prefix.matches(name)) {
+            if let Some(&(index, step)) = admitted.get(&name) {
+                order.push(index);
+                // New nodes may join the step after being offered, relocated ones at once
+                if step > at + 1 {
+                    held += 1;
+                }
+            }
+        }
+        assert!(order.windows(2).all(|w| w[0] < w[1]), "{:?}: {:?}", prefix, order);
+    }
+    assert!(held > 0);
 }
 
+#[cfg(test)]
+fn age_check_args() -> ToolArgs {
+    let mut args = test_args(200, 20, 8);
+    args.max_steps = 100;
+    args.repetitions = 3;
+    args.seeding.seed = Some(4);
+    args
+}
 
-pub trait Tool {
-    /// Print a message about the computation (does not include parameters).
-    fn print_message(&self);
+#[test]
+fn test_age_check() {
+    // Nodes age within the bound of all the churn events, though well beyond the youngest
+    let tool = FullSimTool::new(age_check_args(), SimpleQuorum::new(), UntargettedAttack {});
+    let (_, _, net, _, _) = tool.run_sim(0, &mut |_, _, _| {});
+    let ages = AgeSummary::of(&net);
+    assert!(ages.oldest >= 3 && ages.oldest <= Ageing::Doubling.max_age(net.churns()));
+    assert!(net.churns() <= net.joins());
+    let mean_honest = ages.mean_honest.expect("honest");
+    assert!(mean_honest > 0.0 && mean_honest < ages.oldest as RR);
+    assert!(ages.mean_malicious.is_some());
+    assert_eq!(tool.calc_p_compromise().runs, Some(3));
 
-    /// Calculate the probability of compromise (range: 0 to 1).
-    fn calc_p_compromise(&self) -> SimResult;
+    // Under the Poisson churn model a node may take part in one event several times, which the
+    // bound allows for
+    let mut args = age_check_args();
+    args.churn_model = ChurnModel::Poisson;
+    let tool = FullSimTool::new(args, SimpleQuorum::new(), UntargettedAttack {});
+    let (_, _, net, _, _) = tool.run_sim(0, &mut |_, _, _| {});
+    assert!(AgeSummary::of(&net).oldest <= Ageing::Doubling.max_age(net.churns()));
+}
+
+#[test]
+#[should_panic(expected = "internal inconsistency: a node of age")]
+fn test_age_check_broken_ageing() {
+    // Ageing 1000 times too fast is caught
+    let tool = FullSimTool::new(age_check_args(), SimpleQuorum::new(), UntargettedAttack {})
+        .with_ageing(Ageing::BrokenDoubling);
+    let _ = tool.run_sim(0, &mut |_, _, _| {});
 }
 
+#[test]
+fn test_threshold_attack_waits() {
+    use super::placement::InitialPlacement;
+    use super::quorum::{SimpleTargettedAttack, ThresholdAttack};
 
-/// Simplest tool: assumes all groups have minimum size; cannot simulate
-/// targeting or ageing.
-pub struct DirectCalcTool {
-    args: ToolArgs,
-    quorum: SimpleQuorum,
+    let args = |num_malicious: NN, placement: InitialPlacement| {
+        let mut args = test_args(400, num_malicious, 8);
+        args.max_steps = 50;
+        args.repetitions = 40;
+        args.placement = placement;
+        args.seeding.seed = Some(2);
+        args
+    };
+    // Four nodes reach no quorum of a group of at least 8, so are all held in reserve, while
+    // simple targetting deploys them to no avail
+    let uniform = InitialPlacement::Uniform;
+    let threshold =
+        FullSimTool::new(args(4, uniform), SimpleQuorum::new(), ThresholdAttack::new())
+            .calc_p_compromise();
+    assert_eq!((threshold.p_compromise, threshold.deployed), (0.0, Some(0.0)));
+    let simple = FullSimTool::new(args(4, uniform),
+                                  SimpleQuorum::new(),
+                                  SimpleTargettedAttack::new())
+        .calc_p_compromise();
+    assert_eq!(simple.p_compromise, 0.0);
+    assert!(simple.deployed.expect("deployed") > 3.0);
+
+    // Four of 8 start in the smallest group, where a node or two more reach a quorum (a group
+    // admits no more than two new members at once): the threshold attacker deploys only those
+    // there, keeping the rest in reserve, while simple targetting commits all four to
+    // whichever group the first lands in
+    let concentrated = InitialPlacement::Concentrated(0.5);
+    let threshold =
+        FullSimTool::new(args(8, concentrated), SimpleQuorum::new(), ThresholdAttack::new())
+            .calc_p_compromise();
+    let simple = FullSimTool::new(args(8, concentrated),
+                                  SimpleQuorum::new(),
+                                  SimpleTargettedAttack::new())
+        .calc_p_compromise();
+    assert!(threshold.p_compromise > simple.p_compromise,
+            "threshold: {}, simple: {}",
+            threshold.p_compromise,
+            simple.p_compromise);
+    assert_eq!(simple.deployed, Some(8.0));
+    assert!(threshold.deployed.expect("deployed") < 7.0);
 }
 
-impl DirectCalcTool {
-    pub fn new(args: ToolArgs) -> Self {
-        let quorum = SimpleQuorum::from(args.quorum_prop);
-        DirectCalcTool {
-            args: args,
-            quorum: quorum,
+#[test]
+fn test_custom_strategies() {
+    use super::sim::Node;
+
+    // The example of `AttackStrategy`: every node held in reserve for the first half of the steps
+    #[derive(Clone)]
+    struct LateAttack;
+    impl AttackStrategy for LateAttack {
+        fn split(&mut self, _: Prefix, _: Prefix, _: NodeName, _: &NodeData) {}
+        fn reset_node(&mut self, _: &Node, _: Prefix, _: &AttackProgress) -> bool {
+            false
+        }
+        fn deploy(&mut self, _view: &NetworkView, progress: &AttackProgress) -> NN {
+            if progress.step < progress.max_steps / 2 {
+                0
+            } else {
+                progress.available
+            }
         }
     }
-}
-
-impl Tool for DirectCalcTool {
-    fn print_message(&self) {
-        println!("Tool: calculate probability of compromise, assuming all groups have minimum \
-                  size");
-        let any_group = true;   // only support this now
-        if any_group {
-            println!("Output: the probability that at least one group is compromised");
-        } else {
-            println!("Output: chance of a randomly selected group being compromised");
+    // A strategy resetting every node offered to it, which must not keep a step deploying forever
+    #[derive(Clone)]
+    struct ResetAll;
+    impl AttackStrategy for ResetAll {
+        fn split(&mut self, _: Prefix, _: Prefix, _: NodeName, _: &NodeData) {}
+        fn reset_node(&mut self, _: &Node, _: Prefix, _: &AttackProgress) -> bool {
+            true
         }
     }
 
-    fn calc_p_compromise(&self) -> SimResult {
-        let k = self.args.min_group_size;
-        let q = self.quorum.quorum_size(k).expect("simple quorum size");
-        let pd = prob_disruption(self.args.num_nodes, self.args.num_malicious, k, q);
-        let pc = prob_compromise(self.args.num_nodes, self.args.num_malicious, k, q);
+    let args = || {
+        let mut args = test_args(200, 20, 8);
+        args.max_steps = 40;
+        args.repetitions = 10;
+        args.seeding.seed = Some(5);
+        args
+    };
+    let late = FullSimTool::new(args(), SimpleQuorum::new(), LateAttack);
+    let mut malicious = vec![];
+    let _ = late.run_sim(0, &mut |_, net, _| {
+        let nodes = net.groups().values().flat_map(|group| group.values());
+        malicious.push(nodes.filter(|data| data.is_malicious()).count());
+    });
+    assert!(malicious[..20].iter().all(|&count| count == 0), "{:?}", malicious);
+    assert!(malicious[20..].iter().any(|&count| count > 0), "{:?}", malicious);
 
-        trace!("n: {}, r: {}, k: {}, q: {}, pd: {:.e}, pc: {:.e}",
-               self.args.num_nodes,
-               self.args.num_malicious,
-               k,
-               q,
-               pd,
-               pc);
+    let reset = FullSimTool::new(args(), SimpleQuorum::new(), ResetAll).calc_p_compromise();
+    assert_eq!((reset.p_compromise, reset.deployed), (0.0, Some(0.0)));
+}
 
-        let any_group = true;   // only support this now
-        if any_group {
-            let n_groups = (self.args.num_nodes as RR) / (self.args.min_group_size as RR);
-            SimResult {
-                p_disrupt: 1.0 - (1.0 - pd).powf(n_groups),
-                p_compromise: 1.0 - (1.0 - pc).powf(n_groups),
-            }
-        } else {
-            SimResult {
-                p_disrupt: pd,
-                p_compromise: pc,
-            }
+#[test]
+fn test_paired_ageing_trajectories() {
+    use super::quorum::AgeQuorum;
+
+    // Every node's name, age and malice at the end of each step of one repetition, and whether
+    // it ended in compromise
+    fn trajectory<Q: Quorum>(quorum: Q, rep: NN) -> (Vec<Vec<(NodeName, u32, bool)>>, bool) {
+        let mut args = test_args(40, 15, 5);
+        args.max_steps = 30;
+        args.seeding.seed = Some(5);
+        let tool = FullSimTool::new(args, quorum, UntargettedAttack {});
+        let mut steps = vec![];
+        let (_, termination, _, _, _) = tool.run_sim(rep, &mut |_, net, _| {
+            let mut nodes: Vec<_> = net.groups()
+                .values()
+                .flat_map(|group| group.iter())
+                .map(|(name, data)| (*name, data.age(), data.is_malicious()))
+                .collect();
+            nodes.sort();
+            steps.push(nodes);
+        });
+        (steps, termination.compromised())
+    }
+
+    // The quorum only decides when a repetition stops (on compromise), so the simple and age
+    // quorums see the same attack until then
+    let mut differ = 0;
+    for rep in 0..10 {
+        let (simple, simple_compromise) = trajectory(SimpleQuorum::new(), rep);
+        let (age, age_compromise) = trajectory(AgeQuorum::new(), rep);
+        let common = min(simple.len(), age.len());
+        assert!(common > 0);
+        assert_eq!(simple[..common], age[..common]);
+        if simple_compromise != age_compromise {
+            differ += 1;
         }
     }
+    // ... while the outcomes may still differ
+    assert!(differ > 0);
 }
 
+#[test]
+fn test_sweep_quorums() {
+    use super::quorum::{QuorumSize, SimpleTargettedAttack, WeightedQuorum};
 
-/// A tool which simulates the group structure (division of nodes in the
-/// network between groups), then does direct calculations based on these
-/// groups. This should be more accurate than DirectCalcTool in "any group"
-/// mode, but has the same limitations.
-///
-/// Does not relocate nodes (node ageing).
-pub struct SimStructureTool {
-    args: ToolArgs,
-    quorum: SimpleQuorum,
-}
-
-impl SimStructureTool {
-    pub fn new(args: ToolArgs) -> Self {
-        let quorum = SimpleQuorum::from(args.quorum_prop);
-        SimStructureTool {
-            args: args,
-            quorum: quorum,
+    // Judged under several quorums in one go, each gives the results of a run with it alone
+    // (with the same master seed)
+    fn check<Q: Quorum, A: AttackStrategy + Clone>(make: &Fn() -> Q, attack: &Fn() -> A) {
+        let sizes = [0.3, 0.4, 0.5, 0.6];
+        let args = |size: RR| {
+            let mut args = test_args(200, 50, 8);
+            args.quorum = QuorumSize::Proportion(size);
+            args.max_steps = 150;
+            args.repetitions = 12;
+            args.hold = 2;
+            args.termination = true;
+            args.seeding.seed = Some(11);
+            args
+        };
+        let swept: Vec<(QuorumSize, Q)> =
+            sizes[1..].iter().map(|&size| (QuorumSize::Proportion(size), make())).collect();
+        let results = FullSimTool::new(args(sizes[0]), make(), attack())
+            .sweep_quorums(swept)
+            .calc_sweep();
+        assert_eq!(results.len(), sizes.len());
+        for (&size, result) in sizes.iter().zip(&results) {
+            let alone = FullSimTool::new(args(size), make(), attack()).calc_p_compromise();
+            assert_eq!((result.p_disrupt, result.p_compromise, result.groups, result.runs),
+                       (alone.p_disrupt, alone.p_compromise, alone.groups, alone.runs),
+                       "quorum {}",
+                       size);
+            assert_eq!(result.termination, alone.termination, "quorum {}", size);
         }
+        // The quorums are told apart
+        let outcomes: Vec<(RR, RR)> =
+            results.iter().map(|r| (r.p_disrupt, r.p_compromise)).collect();
+        assert!(outcomes[0] != outcomes[3], "{:?}", outcomes);
     }
+    check(&SimpleQuorum::new, &|| UntargettedAttack {});
+    check(&SimpleQuorum::new, &SimpleTargettedAttack::new);
+    check(&WeightedQuorum::new, &SimpleTargettedAttack::new);
+
+    // Sweeping nothing, the tool gives its own results
+    let tool = || {
+        let mut args = test_args(100, 30, 8);
+        args.repetitions = 5;
+        args.seeding.seed = Some(11);
+        FullSimTool::new(args, SimpleQuorum::new(), UntargettedAttack {})
+    };
+    let results = tool().calc_sweep();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].p_compromise, tool().calc_p_compromise().p_compromise);
 }
 
-impl Tool for SimStructureTool {
-    fn print_message(&self) {
-        println!("Tool: simulate allocation of nodes to groups; each has size at least the \
-                  specified minimum size");
-        let any_group = true;   // only support this now
-        if any_group {
-            println!("Output: the probability that at least one group is compromised");
-        } else {
-            println!("Output: chance of a randomly selected group being compromised");
-        }
+#[test]
+fn test_independent_of_hash_order() {
+    use std::thread;
+    use super::quorum::{SimpleTargettedAttack, WeightedQuorum};
+
+    // Each network of a seeded run, as sorted (prefix, name, age, malicious) tuples, and the
+    // outcomes of the run's repetitions. Every hash map made gets keys of its own, and a new
+    // thread draws new keys altogether, so runs iterating maps in some decision would differ.
+    fn trajectory() -> (Vec<Vec<(Prefix, NodeName, u32, bool)>>, Vec<bool>) {
+        let args = || {
+            let mut args = test_args(300, 60, 8);
+            args.max_steps = 60;
+            args.repetitions = 6;
+            args.outcomes = true;
+            args.weighting = "capacity(1@0.5,20@0.5)".parse().expect("parse");
+            args.seeding.seed = Some(5);
+            args
+        };
+        let make = || {
+            let mut quorum = WeightedQuorum::with_weighting(args().weighting.clone());
+            quorum.set_quorum_size(QuorumSize::Proportion(0.5));
+            quorum
+        };
+        let tool = FullSimTool::new(args(), make(), SimpleTargettedAttack::new());
+        let mut networks = vec![];
+        tool.run_sim(0, &mut |_, net, _| {
+            let mut nodes: Vec<(Prefix, NodeName, u32, bool)> = net.groups()
+                .iter()
+                .flat_map(|(prefix, group)| {
+                    group.iter().map(move |(name, data)| {
+                        (*prefix, *name, data.age(), data.is_malicious())
+                    })
+                })
+                .collect();
+            nodes.sort();
+            networks.push(nodes);
+        });
+        (networks, tool.calc_p_compromise().outcomes.expect("outcomes"))
     }
 
-    fn calc_p_compromise(&self) -> SimResult {
-        // We need an "attack" strategy, though we only support one here
-        let mut attack = UntargettedAttack {};
+    let first = trajectory();
+    // The run may stop early on compromise, though not before many decisions
+    assert!(first.0.len() > 10, "{} steps", first.0.len());
+    assert_eq!(trajectory(), first);
+    assert_eq!(thread::spawn(trajectory).join().expect("thread"), first);
+}
 
-        // Create a network
-        let mut net = Network::<NoAddRestriction>::new(self.args.min_group_size as usize);
-        let mut remaining = self.args.num_nodes;
-        while remaining > 0 {
-            let name = new_node_name();
-            match net.add_node(name, NodeData::new()) {
-                Ok(prefix) => {
-                    remaining -= 1;
-                    let _prefix = net.maybe_split(prefix, name, &mut attack);
-                }
-                Err(Error::AlreadyExists) => {
-                    continue;
-                }
-                Err(e) => {
-                    panic!("Error adding node: {}", e);
-                }
-            };
-        }
+#[test]
+fn test_quorum_jitter() {
+    use super::quorum::SimpleTargettedAttack;
+    use super::rng::Seeding;
 
-        let any_group = true;   // only support this now
-        if any_group {
-            // This isn't quite right, since one group not compromised does
-            // tell you _something_ about the distribution of malicious nodes,
-            // thus probabilities are not indepedent. But unless there are a lot
-            // of malicious nodes it should be close.
-            let mut p_no_disruption = 1.0;
-            let mut p_no_compromise = 1.0;
-            for (_, group) in net.groups() {
-                let k = group.len() as NN;
-                let q = self.quorum.quorum_size(k).expect("simple quorum size");
-                let pd = prob_disruption(self.args.num_nodes, self.args.num_malicious, k, q);
-                let pc = prob_compromise(self.args.num_nodes, self.args.num_malicious, k, q);
-                p_no_disruption *= 1.0 - pd;
-                p_no_compromise *= 1.0 - pc;
-            }
-            SimResult {
-                p_disrupt: 1.0 - p_no_disruption,
-                p_compromise: 1.0 - p_no_compromise,
-            }
-        } else {
-            // Calculate probability of compromise of one selected group.
+    // A quorum ignoring offsets, judging as without jitter
+    struct Unjittered(SimpleQuorum);
 
-            // Take the group appearing first in self.groups. Since hash-maps
-            // are randomly ordered in Rust, there should be nothing special
-            // about this group.
-            let (_, group) =
-                net.groups().iter().next().expect("there should be at least one group");
-            let k = group.len() as NN;
-            let q = self.quorum.quorum_size(k).expect("simple quorum size");
+    impl Quorum for Unjittered {
+        fn quorum_size(&self, k: NN) -> Option<NN> {
+            self.0.quorum_size(k)
+        }
 
-            // We already have code to do the rest:
-            let pd = prob_disruption(self.args.num_nodes, self.args.num_malicious, k, q);
-            let pc = prob_compromise(self.args.num_nodes, self.args.num_malicious, k, q);
+        fn set_quorum_size(&mut self, size: QuorumSize) {
+            self.0.set_quorum_size(size);
+        }
 
-            trace!("n: {}, r: {}, k: {}, q: {}, pd: {:.e}, pc: {:.e}",
-                   self.args.num_nodes,
-                   self.args.num_malicious,
-                   k,
-                   q,
-                   pd,
-                   pc);
+        fn is_quorum(&self, group: &[Member], voters: &[Member]) -> bool {
+            self.0.is_quorum(group, voters)
+        }
+    }
 
-            SimResult {
-                p_disrupt: pd,
-                p_compromise: pc,
+    let run = |jitter: RR, unjittered: bool| {
+        let mut args = test_args(300, 75, 8);
+        args.max_steps = 100;
+        args.repetitions = 20;
+        args.outcomes = true;
+        args.quorum_jitter = jitter;
+        args.seeding.seed = Some(6);
+        let result = if unjittered {
+            FullSimTool::new(args, Unjittered(SimpleQuorum::new()), SimpleTargettedAttack::new())
+                .calc_p_compromise()
+        } else {
+            FullSimTool::new(args, SimpleQuorum::new(), SimpleTargettedAttack::new())
+                .calc_p_compromise()
+        };
+        (result.p_disrupt, result.p_compromise, result.outcomes.expect("outcomes"))
+    };
+    // Jitter 0 judges exactly as before; other jitter changes the results
+    assert_eq!(run(0.0, false), run(0.0, true));
+    assert!(run(0.3, false) != run(0.3, true));
+
+    // Groups of 20 with each number of malicious members (each a bucket of the malice
+    // histogram of its own), each judged as 64 groups under quorum 0.5. Without jitter, whether
+    // a group is compromised depends on its bucket alone; with jitter, the outcomes in a bucket
+    // spread, and buckets hold both compromised and intact groups.
+    let mut quorum = SimpleQuorum::new();
+    quorum.set_quorum_size(QuorumSize::Proportion(0.5));
+    let seeding = Seeding {
+        seed: Some(7),
+        fixed: vec![],
+    };
+    let prefixes: Vec<Prefix> =
+        (0..64).map(|i| format!("{:06b}", i).parse().expect("parse")).collect();
+    let mixed_buckets = |jitter: RR| {
+        let jitter = QuorumJitter::new(jitter, &seeding, 7, 0);
+        let (mut compromised, mut intact) = (MaliceHist::new(), MaliceHist::new());
+        for bad in 0..21 {
+            let group: Group = (0..20)
+                .map(|name| {
+                    (name, if name < bad { NodeData::new_malicious() } else { NodeData::new() })
+                })
+                .collect();
+            for prefix in &prefixes {
+                if quorum_compromised_offset(&quorum, &group, jitter.offset(*prefix)) {
+                    compromised.add_group(&group);
+                } else {
+                    intact.add_group(&group);
+                }
             }
         }
-    }
+        compromised.counts
+            .iter()
+            .zip(intact.counts.iter())
+            .filter(|&(compromised, intact)| *compromised > 0 && *intact > 0)
+            .count()
+    };
+    assert_eq!(mixed_buckets(0.0), 0);
+    assert!(mixed_buckets(0.2) >= 3);
 }
 
+#[test]
+fn test_steady_state() {
+    use super::placement::InitialPlacement;
+    use super::steady::SteadyState;
 
-/// A tool which simulates group operations.
-///
-/// Can relocate nodes according to the node ageing RFC (roughly).
-pub struct FullSimTool<Q: Quorum, A: AttackStrategy + Clone> {
-    args: ToolArgs,
-    quorum: Q,
-    attack: A,
+    // Fast mixing: the malicious nodes start in one group and are never evicted, while honest
+    // nodes keep joining (the attack never starting) and are evicted after 50 steps on average,
+    // so the groups' membership turns over many times within a batch
+    let config = |steps: NN, repetitions: NN, seed: u64| {
+        let mut args = test_args(100, 10, 5);
+        args.max_steps = steps;
+        args.repetitions = repetitions;
+        args.placement = InitialPlacement::Concentrated(1.0);
+        args.attack_start = args.max_steps;
+        args.eviction_rate = 0.02;
+        args.attack_resource = 1e9;
+        args.seeding.seed = Some(seed);
+        args
+    };
+    let tool = |steady: bool, seed: u64| {
+        let mut args = config(400, 30, seed);
+        args.steady_state = if steady {
+            Some(SteadyState {
+                burn_in: 100,
+                batches: 5,
+            })
+        } else {
+            None
+        };
+        FullSimTool::new(args, SimpleQuorum::new(), UntargettedAttack {})
+    };
+    let reports: Vec<_> = (1..4)
+        .map(|seed| {
+            let result = tool(true, seed).calc_p_compromise();
+            // Going on past compromise does not change whether it happened
+            let within = tool(false, seed).calc_p_compromise();
+            assert_eq!((result.p_disrupt, result.p_compromise),
+                       (within.p_disrupt, within.p_compromise));
+            result.steady_state.expect("steady state")
+        })
+        .collect();
+    for report in &reports {
+        // Neither always nor never compromised, nor still settling
+        assert!(report.compromised > 0.5 && report.compromised < 0.9,
+                "{:?}",
+                report);
+        assert!(!report.trending);
+    }
+    // Estimates from different seeds agree within their intervals, both from the batch means
+    // and from the spread between runs
+    let agree = |a: RR, a_ci: RR, b: RR, b_ci: RR| {
+        assert!((a - b).abs() <= a_ci + b_ci + 1e-9,
+                "{} ± {} and {} ± {} differ",
+                a,
+                a_ci,
+                b,
+                b_ci);
+    };
+    let runs_ci = |ci: Option<RR>| ci.expect("several runs");
+    for pair in reports.windows(2) {
+        agree(pair[0].compromised,
+              pair[0].compromised_ci,
+              pair[1].compromised,
+              pair[1].compromised_ci);
+        agree(pair[0].disrupted, pair[0].disrupted_ci, pair[1].disrupted, pair[1].disrupted_ci);
+        agree(pair[0].compromised,
+              runs_ci(pair[0].compromised_runs_ci),
+              pair[1].compromised,
+              runs_ci(pair[1].compromised_runs_ci));
+    }
+
+    // One long run, the usual way to measure a steady state, has an interval from its batches
+    // alone, and these agree between seeds too
+    let long: Vec<_> = (1..4)
+        .map(|seed| {
+            let mut args = config(3000, 1, seed);
+            args.steady_state = Some(SteadyState {
+                burn_in: 200,
+                batches: 10,
+            });
+            let tool = FullSimTool::new(args, SimpleQuorum::new(), UntargettedAttack {});
+            tool.calc_p_compromise().steady_state.expect("steady state")
+        })
+        .collect();
+    for report in &long {
+        assert!(report.compromised_ci.is_finite() && report.compromised_runs_ci.is_none(),
+                "{:?}",
+                report);
+    }
+    for pair in long.windows(2) {
+        agree(pair[0].compromised,
+              pair[0].compromised_ci,
+              pair[1].compromised,
+              pair[1].compromised_ci);
+    }
 }
 
-impl<Q: Quorum, A: AttackStrategy + Clone> FullSimTool<Q, A> {
-    pub fn new(args: ToolArgs, mut quorum: Q, strategy: A) -> Self {
-        quorum.set_quorum_proportion(args.quorum_prop);
-        FullSimTool {
-            args: args,
-            quorum: quorum,
-            attack: strategy,
-        }
+#[test]
+fn test_weighting() {
+    use super::quorum::{QuorumBasis, Weighting, WeightedQuorum};
+
+    // Outcome of each repetition and P(disruption), with and without a basis size and blocking
+    // proportion
+    fn outcomes<Q: Quorum>(quorum: &Fn() -> Q, weighting: Weighting) -> Vec<(String, RR)> {
+        [(QuorumBasis::Actual, None), (QuorumBasis::Minimum, Some(0.3))]
+            .iter()
+            .map(|&(basis, block)| {
+                let mut args = test_args(60, 25, 5);
+                args.max_steps = 40;
+                args.repetitions = 24;
+                args.outcomes = true;
+                args.quorum_basis = basis;
+                args.block_prop = block;
+                args.weighting = weighting.clone();
+                args.seeding.seed = Some(7);
+                let result = FullSimTool::new(args, quorum(), UntargettedAttack {})
+                    .calc_p_compromise();
+                let outcomes = result.outcomes.expect("outcomes");
+                (outcomes.iter().map(|&c| if c { '1' } else { '0' }).collect(), result.p_disrupt)
+            })
+            .collect()
     }
 
-    // Run a simulation. Result is a pair of booleans, `(any_disruption, any_compromise)`.
-    fn run_sim(&self) -> (bool, bool) {
-        info!("Starting sim");
-        assert!(self.args.any_group);
-        let mut disruption = false;
-        let mut attack = self.attack.clone();
+    // Age weighting gives the results of the age quorum before it was generalised
+    let age = outcomes(&WeightedQuorum::new, Weighting::Age);
+    assert_eq!(age,
+               vec![("111100011001111100011010".to_owned(), 1.0),
+                    ("111100011001111100011010".to_owned(), 1.0)]);
 
-        // 1. Create initial network.
-        // For simplicity, we ignore all add-attempts which fail due to age restrictions
-        // (these do not affect the network and would simply be re-added later).
-        // Because of this and the assumption that all these nodes are "good",
-        // we do not need to simulate proof-of-work here.
-        let mut net = Network::<RestrictOnePerAge>::new(self.args.min_group_size as usize);
-        let num_initial = self.args.num_nodes - self.args.num_malicious;
-        // Pre-generate all nodes to be added, in a Vec.
-        // We can pop from this and on relocation push.
-        let mut to_add: Vec<_> = iter::repeat(0)
-            .take(num_initial as usize)
-            .map(|_| (new_node_name(), NodeData::new()))
-            .collect();
-        let mut n_ops = 0;
-        let mut n_relocates = 0;
-        let mut n_rejects = 0;
-        while let Some((node_name, node_data)) = to_add.pop() {
-            n_ops += 1;
-            trace!("Adding from a queue of length {} with {} groups",
-                   to_add.len() + 1,
-                   net.groups().len());
-            let age = node_data.age();
-            match net.add_node(node_name, node_data) {
-                Ok(prefix) => {
-                    trace!("Added node {} with age {}", node_name, age);
-                    let prefix = net.maybe_split(prefix, node_name, &mut attack);
-                    // Add successful: do churn event.
-                    // The churn may cause a removal from a group; however, either that was an
-                    // old group which just got a new member, or it is a split result with at least
-                    // one node more than the minimum number. Either way merging is not required.
-                    if let Some(node) = net.churn(prefix, node_name) {
-                        n_relocates += 1;
-                        to_add.push(node);
-                    }
-                }
-                Err(Error::AlreadyExists) |
-                Err(Error::AddRestriction) => {
-                    n_rejects += 1;
-                    // We fixed the number of initial nodes. If this one is incompatible,
-                    // find another.
-                    to_add.push((new_node_name(), NodeData::new()));
-                }
-                Err(e) => {
-                    panic!("Error adding node: {}", e);
-                }
-            }
-        }
-        info!("Init done: added {} nodes in {} steps involving {} relocates and {} rejections",
-              num_initial,
-              n_ops,
-              n_relocates,
-              n_rejects);
+    // Uniform weighting gives the results of the simple quorum
+    let uniform = outcomes(&WeightedQuorum::new, Weighting::Uniform);
+    assert_eq!(uniform, outcomes(&SimpleQuorum::new, Weighting::Age));
+    assert!(uniform != age);
 
-        // 2. Start attack
-        // Assumption: all nodes in the network (malicious or not) have the same performance.
-        // Proof-of-work time-outs can be used to filter out any nodes with slow CPU/network.
-        // We use a step, which is how long proof-of-work takes. We don't set a value here, but
-        // for example 10000 × 1-hour steps is over a year.
+    // Weighting by capacity draws from the structure and attack streams, so changes results
+    let capacity: Weighting = "capacity(1@0.5,20@0.5)".parse().expect("parse");
+    assert!(outcomes(&WeightedQuorum::new, capacity) != age);
+}
 
-        // Assumption: only malicious nodes are added after this time, and a fixed number. This
-        // is a worst case scenario; if there were background-adding of other nodes or if all the
-        // attacking nodes were not added simultaneously, the attack would be harder.
+#[test]
+fn test_calc_in_parts() {
+    use super::quorum::SimpleTargettedAttack;
 
-        // Assumption: the network gives joining nodes a group immediately, but does not
-        // accept the node as a member until after proof-of-work. Nodes can choose to reset before
-        // doing the work. We assume nodes will not try to reset at any other time.
-        // We assume that nodes can reset and try to join again (with age 0) instantly. This may
-        // be problematic if some nodes target groups purely to cause churn events.
+    // With a seed, running the repetitions in parts gives the same results as
+    // running them at once
+    let tool = || {
+        let mut args = test_args(200, 40, 8);
+        args.max_steps = 20;
+        args.repetitions = 10;
+        args.malice_hist = true;
+        args.spread = true;
+        args.seeding.seed = Some(11);
+        FullSimTool::new(args, SimpleQuorum::new(), SimpleTargettedAttack::new())
+    };
+    let once = tool().calc_p_compromise();
 
-        // Unlike above, we now use a two-step join process: (1) nodes are "pre-added": they are
-        // given a name and group, then either reset or wait until (2) nodes have done
-        // proof-of-work and can join.
+    let tool = tool();
+    let mut progress = tool.start();
+    let first = tool.calc_until(&mut progress, 4);
+    assert_eq!(first.runs, Some(4));
+    tool.calc_until(&mut progress, 8);
+    let parts = tool.calc_until(&mut progress, 50);   // no more than requested
+    assert_eq!(parts.runs, Some(10));
+    assert_eq!((parts.p_disrupt, parts.p_compromise, parts.groups),
+               (once.p_disrupt, once.p_compromise, once.groups));
+    assert_eq!(parts.malice_hist, once.malice_hist);
+    assert!(once.spread.is_some());
+    assert_eq!(parts.spread, once.spread);
+}
 
-        // Assumption: if a node has done proof-of-work but its original target group splits, it
-        // simply joins whichever group it would now be in. If a node has done proof of work and
-        // is not accepted due to age restrictions, it is given a new name and must redo work.
-        let mut n_new_malicious = self.args.num_malicious;
-        // Queue of nodes doing proof-of-work. Push to back, pop from front.
-        let mut waiting = VecDeque::new();
-        for _ in 0..self.args.max_steps {
-            // Each round, we firstly deal with all "waiting" nodes, then add any new/reset nodes.
-            while let Some((node_name, node_data)) = waiting.pop_front() {
-                match net.add_node(node_name, node_data) {
-                    Ok(prefix) => {
-                        let prefix = net.maybe_split(prefix, node_name, &mut attack);
-                        // Add successful: do churn event.
-                        // The churn may cause a removal from a group; however, either that was an
-                        // old group which just got a new member, or it is a split result with at
-                        // least one node more than the minimum number. Either way merging
-                        // is not required.
-                        if let Some(node) = net.churn(prefix, node_name) {
-                            if node.1.is_malicious() &&
-                               attack.reset_node(&node, net.find_prefix(node_name)) {
-                                n_new_malicious += 1;
-                            } else {
-                                waiting.push_back(node);
-                            }
-                        }
-                    }
-                    Err(Error::AlreadyExists) |
-                    Err(Error::AddRestriction) => {
-                        // Cannot be added: rename and try again next round.
-                        let node = (new_node_name(), node_data);
-                        waiting.push_back(node);
-                    }
-                    Err(e) => {
-                        panic!("Error adding node: {}", e);
-                    }
-                }
-            }
+#[test]
+fn test_targeting_cost_simulated() {
+    // The analytic cost of `targeting-cost` against the full simulation with simple targetting,
+    // a single malicious node and a pinned target. Honest churn comes from eviction (the malicious
+    // node being all but immune): until the attack starts, an honest node joins each step, about
+    // as many as are evicted. Runs whose groups are not all at the target's depth are skipped.
+    use super::quorum::{AttackProgress, NetworkView, SimpleTargettedAttack};
+    use super::sim::{Node, RelocationTarget};
+    use super::targeting_cost::Query;
+    use std::sync::{Arc, Mutex};
 
-            while n_new_malicious > 0 {
-                let node = (new_node_name(), NodeData::new_malicious());
-                let prefix = net.find_prefix(node.0);
-                if !attack.reset_node(&node, prefix) {
-                    n_new_malicious -= 1;
-                    waiting.push_back(node);
+    // Join attempts (each name a deployed node lands with, reset or not, whether rejoining with
+    // an identity's age or not) until a node of the age is in the target and kept, and the step
+    // of that; and the nodes of the current deployment still to land
+    #[derive(Default)]
+    struct Record {
+        attempts: NN,
+        reached: Option<NN>,
+        landing: NN,
+    }
+    #[derive(Clone)]
+    struct Recorded {
+        targetted: SimpleTargettedAttack,
+        target: Prefix,
+        age: u32,
+        record: Arc<Mutex<Record>>,
+    }
+    impl AttackStrategy for Recorded {
+        fn split(&mut self, old: Prefix, new: Prefix, name: NodeName, data: &NodeData) {
+            self.targetted.split(old, new, name, data);
+        }
+        fn reset_node(&mut self, node: &Node, prefix: Prefix, progress: &AttackProgress) -> bool {
+            let reset = self.targetted.reset_node(node, prefix, progress);
+            let mut record = self.record.lock().expect("lock");
+            // A deployed node which is reset draws another name at once, each a join attempt
+            if record.landing > 0 {
+                if record.reached.is_none() {
+                    record.attempts += 1;
                 }
+                if !reset {
+                    record.landing -= 1;
+                }
+            }
+            // Landing in the target with the age, joining or relocated there
+            if record.reached.is_none() && !reset && self.target.matches(node.0) &&
+               node.1.age() >= self.age {
+                record.reached = Some(progress.step);
             }
+            reset
+        }
+        fn deploy(&mut self, view: &NetworkView, progress: &AttackProgress) -> NN {
+            let deploying = self.targetted.deploy(view, progress);
+            self.record.lock().expect("lock").landing = min(deploying, progress.available);
+            deploying
+        }
+        fn set_target(&mut self, target: Prefix) {
+            self.targetted.set_target(target);
+        }
+        fn grinds_name(&self, prefix: Prefix, progress: &AttackProgress) -> bool {
+            self.targetted.grinds_name(prefix, progress)
+        }
+    }
 
-            // Finally, we check if disruption or compromise occurred:
-            for (_, ref group) in net.groups() {
-                if self.quorum.quorum_compromised(group) {
-                    // Compromise implies disruption!
-                    return (true, true);
-                } else if self.quorum.quorum_disrupted(group) {
-                    disruption = true;
+    let (start, steps) = (300, 60);
+    for &(num_nodes, target, age, relocation, recovery) in
+        &[(150, "0", 1, RelocationTarget::Random, 0.0),
+          (150, "0", 2, RelocationTarget::Random, 0.0),
+          (150, "0", 3, RelocationTarget::Random, 0.0),
+          (300, "00", 1, RelocationTarget::Random, 0.0),
+          (150, "0", 3, RelocationTarget::Random, 0.5),
+          (150, "0", 2, RelocationTarget::Balanced, 1.0)] {
+        let target: Prefix = target.parse().expect("prefix");
+        let mut args = test_args(num_nodes, 1, 45);
+        args.max_steps = start + steps;
+        args.attack_start = start;
+        args.eviction_rate = 1.0 / num_nodes as RR;
+        args.attack_resource = 1e9;
+        args.target_prefix = Some(target);
+        args.relocation = relocation;
+        args.rejoin_recovery = recovery;
+        args.seeding.seed = Some(3);
+        let record = Arc::new(Mutex::new(Record::default()));
+        let tool = FullSimTool::new(args,
+                                    SimpleQuorum::new(),
+                                    Recorded {
+                                        targetted: SimpleTargettedAttack::new(),
+                                        target: target,
+                                        age: age,
+                                        record: record.clone(),
+                                    });
+        // Runs not reaching the age count their attempts and steps too: the cost of each success
+        // is the total spent over the successes
+        let (mut attempts, mut spent, mut churns, mut successes) = (0, 0, 0, 0);
+        // Attempts and success of each run counted, for the error of the joins
+        let mut runs: Vec<(RR, RR)> = vec![];
+        for repetition in 0..300 {
+            *record.lock().expect("lock") = Record::default();
+            let mut churns_at = vec![];
+            let mut even = true;
+            let _ = tool.run_sim(repetition, &mut |step, net, _| {
+                if step == start {
+                    even = net.groups().keys().all(|p| p.bit_count() == target.bit_count());
                 }
+                churns_at.push(net.churns());
+            });
+            let record = record.lock().expect("lock");
+            if !even {
+                continue;
             }
+            let end = record.reached.unwrap_or(start + steps);
+            attempts += record.attempts;
+            runs.push((record.attempts as RR, record.reached.map_or(0.0, |_| 1.0)));
+            spent += end - start;
+            churns += churns_at[end as usize - 1] - churns_at[start as usize - 1];
+            successes += record.reached.map_or(0, |_| 1);
         }
-
-        // If we didn't return already, no compromise occurred, but disruption may have
-        (disruption, false)
+        let groups = 1 << target.bit_count();
+        let query = Query {
+            groups: groups,
+            age: age,
+            relocation: relocation,
+            recovery: recovery,
+            churn_rate: churns as RR / spent as RR / groups as RR,
+        };
+        let cost = query.cost().expect("reachable");
+        let (joins, steps) = (attempts as RR / successes as RR, spent as RR / successes as RR);
+        // Joins agree within four standard errors of the ratio of attempts to successes (by the
+        // delta method). The analytic steps are a lower bound: in the simulation, the node often
+        // waits behind honest nodes ready to be relocated before it.
+        assert!(successes >= 100, "{:?}: {} successes", query, successes);
+        let n = runs.len() as RR;
+        let mean_successes = successes as RR / n;
+        let var = runs.iter().map(|&(a, s)| (a - joins * s) * (a - joins * s)).sum::<RR>() /
+                  (n - 1.0);
+        let se = (var / n).sqrt() / mean_successes;
+        assert!((joins - cost.joins).abs() <= 4.0 * se,
+                "{:?}: joins {} ± {} against {:?}",
+                query,
+                joins,
+                se,
+                cost);
+        assert!(steps >= cost.steps, "{:?}: steps {} against {:?}", query, steps, cost);
     }
 }
 
-impl<Q: Quorum, A: AttackStrategy + Clone> Tool for FullSimTool<Q, A> {
-    fn print_message(&self) {
-        println!("Tool: simulate group operations");
-        let any_group = true;   // only support this now
-        if any_group {
-            println!("Output: the probability that at least one group is compromised");
-        } else {
-            println!("Output: chance of a randomly selected group being compromised");
-        }
-    }
+#[test]
+fn test_sweep_target_reached() {
+    use super::quorum::{QuorumSize, SimpleTargettedAttack};
 
-    fn calc_p_compromise(&self) -> SimResult {
-        let mut n_disruptions = 0;
-        let mut n_compromises = 0;
-        for _ in 0..self.args.repetitions {
-            let r = self.run_sim();
-            if r.0 {
-                n_disruptions += 1;
-            }
-            if r.1 {
-                n_compromises += 1;
-            }
+    // Runs of a sweep end at different steps under each quorum, the network splitting meanwhile:
+    // whether the target was reached is that of the run under the quorum alone
+    let target: Prefix = "11".parse().expect("prefix");
+    let sizes = [0.2, 0.3, 0.4, 0.5, 0.6, 0.7];
+    let tool = |size: RR| {
+        let mut args = test_args(100, 60, 8);
+        args.quorum = QuorumSize::Proportion(size);
+        args.max_steps = 150;
+        args.hold = 2;
+        args.target_prefix = Some(target);
+        args.seeding.seed = Some(11);
+        FullSimTool::new(args, SimpleQuorum::new(), SimpleTargettedAttack::new())
+    };
+    let alone: Vec<_> = sizes.iter().map(|&size| tool(size)).collect();
+    let swept: Vec<(QuorumSize, SimpleQuorum)> = sizes[1..]
+        .iter()
+        .map(|&size| (QuorumSize::Proportion(size), SimpleQuorum::new()))
+        .collect();
+    let sweep = tool(sizes[0]).sweep_quorums(swept);
+    let quorums: Vec<&SimpleQuorum> = Some(&sweep.quorum).into_iter().chain(&sweep.sweep).collect();
+    let mut differ = 0;
+    for repetition in 0..30 {
+        let (verdicts, ..) =
+            sweep.run_quorums(repetition, vec![], None, &quorums, &mut |_, _, _| {});
+        let reached: Vec<bool> = verdicts.iter().map(|verdict| verdict.reached).collect();
+        for (tool, verdict) in alone.iter().zip(&verdicts) {
+            let (_, _, net, ..) = tool.run_sim(repetition, &mut |_, _, _| {});
+            assert_eq!(verdict.reached, reaches(&net, target), "repetition {}", repetition);
+            // Likewise the ages, as at the end of each quorum's run
+            assert_eq!(verdict.ages, AgeSummary::of(&net), "repetition {}", repetition);
         }
-        let denom = self.args.repetitions as RR;
-        SimResult {
-            p_disrupt: (n_disruptions as RR) / denom,
-            p_compromise: (n_compromises as RR) / denom,
+        if reached.iter().any(|&r| r != reached[0]) {
+            differ += 1;
         }
     }
+    // The longest run alone would not do
+    assert!(differ > 0);
 }
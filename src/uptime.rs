@@ -0,0 +1,191 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Uptime of honest nodes
+
+use super::RR;
+
+use std::fmt::{self, Formatter};
+use std::str::FromStr;
+
+use rand::Rng;
+
+
+/// Distribution of the uptime (participation factor, range 0-1) of honest
+/// nodes. A node takes part in each churn event of its group with probability
+/// equal to its uptime, so this scales both how fast it ages and how often it
+/// is relocated.
+///
+/// Written (and parsed) as a constant, e.g. `1`, as `uniform(A,B)`, or as a mix
+/// of uptimes with weights, e.g. `0.95@0.2,0.3@0.8` (20% of nodes have uptime
+/// 0.95, 80% have 0.3). Since output is comma separated, `Display` uses `;`
+/// instead of `,`; either is accepted when parsing.
+///
+/// The same form is used for the capacity of nodes (see `parse_capacity`).
+#[derive(Clone, Debug, PartialEq)]
+pub enum UptimeDist {
+    Constant(RR),
+    Uniform(RR, RR),
+    /// `(uptime, weight)` pairs; weights need not sum to 1.
+    Mix(Vec<(RR, RR)>),
+}
+
+impl UptimeDist {
+    /// Constant 1: all honest nodes always take part.
+    pub fn new() -> Self {
+        UptimeDist::Constant(1.0)
+    }
+
+    /// Parse a distribution of node capacity (see `quorum::Weighting`). This
+    /// has the same form as a distribution of uptime, but values may be any
+    /// non-negative number.
+    pub fn parse_capacity(s: &str) -> Result<Self, String> {
+        parse_dist(s, "CAPACITY", parse_capacity)
+    }
+
+    /// Draw the uptime (or capacity) of one node from `rng`.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> RR {
+        match *self {
+            UptimeDist::Constant(x) => x,
+            UptimeDist::Uniform(a, b) => {
+                if a == b {
+                    a
+                } else {
+                    rng.gen_range(a, b)
+                }
+            }
+            UptimeDist::Mix(ref mix) => {
+                let total: RR = mix.iter().map(|&(_, w)| w).sum();
+                let mut x = rng.gen_range(0.0, total);
+                for &(uptime, weight) in mix {
+                    if x < weight {
+                        return uptime;
+                    }
+                    x -= weight;
+                }
+                mix.last().expect("mix is not empty").0
+            }
+        }
+    }
+}
+
+impl fmt::Display for UptimeDist {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let s = match *self {
+            UptimeDist::Constant(x) => format!("{}", x),
+            UptimeDist::Uniform(a, b) => format!("uniform({};{})", a, b),
+            UptimeDist::Mix(ref mix) => {
+                mix.iter().map(|&(u, w)| format!("{}@{}", u, w)).collect::<Vec<_>>().join(";")
+            }
+        };
+        f.pad(&s)
+    }
+}
+
+// Parse an uptime, which must be in the range 0-1.
+fn parse_uptime(s: &str) -> Result<RR, String> {
+    match s.trim().parse::<RR>() {
+        Ok(x) if x >= 0.0 && x <= 1.0 => Ok(x),
+        Ok(_) => Err(format!("uptime {} outside range 0-1", s.trim())),
+        Err(_) => Err(format!("bad number '{}'", s.trim())),
+    }
+}
+
+// Parse a capacity, which must not be negative.
+fn parse_capacity(s: &str) -> Result<RR, String> {
+    match s.trim().parse::<RR>() {
+        Ok(x) if x >= 0.0 => Ok(x),
+        Ok(_) => Err(format!("capacity {} is negative", s.trim())),
+        Err(_) => Err(format!("bad number '{}'", s.trim())),
+    }
+}
+
+impl FromStr for UptimeDist {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_dist(s, "UPTIME", parse_uptime)
+    }
+}
+
+// Parse a distribution, whose values are named `what` in errors and parsed by `parse_value`.
+fn parse_dist(s: &str,
+              what: &str,
+              parse_value: fn(&str) -> Result<RR, String>)
+              -> Result<UptimeDist, String> {
+    let s = s.trim();
+    let separators: &[char] = &[',', ';'];
+    if s.starts_with("uniform(") {
+        if !s.ends_with(')') {
+            return Err(format!("missing ')' in '{}'", s));
+        }
+        let bounds: Vec<&str> = s["uniform(".len()..s.len() - 1].split(separators).collect();
+        if bounds.len() != 2 {
+            return Err(format!("expected uniform(A,B), found '{}'", s));
+        }
+        let (a, b) = (try!(parse_value(bounds[0])), try!(parse_value(bounds[1])));
+        if a > b {
+            return Err(format!("empty range in '{}'", s));
+        }
+        Ok(UptimeDist::Uniform(a, b))
+    } else if s.contains('@') {
+        let mut mix = Vec::new();
+        for part in s.split(separators) {
+            let mut iter = part.split('@');
+            let (value, weight) = match (iter.next(), iter.next(), iter.next()) {
+                (Some(value), Some(weight), None) => (value, weight),
+                _ => return Err(format!("expected {}@WEIGHT, found '{}'", what, part)),
+            };
+            let weight = match weight.trim().parse::<RR>() {
+                Ok(w) if w > 0.0 => w,
+                _ => return Err(format!("bad weight '{}'", weight.trim())),
+            };
+            mix.push((try!(parse_value(value)), weight));
+        }
+        Ok(UptimeDist::Mix(mix))
+    } else {
+        parse_value(s).map(UptimeDist::Constant)
+    }
+}
+
+#[test]
+fn test_parse_uptime_dist() {
+    assert_eq!("1".parse(), Ok(UptimeDist::Constant(1.0)));
+    assert_eq!("uniform(0.2, 0.9)".parse(), Ok(UptimeDist::Uniform(0.2, 0.9)));
+    assert_eq!("0.95@0.2,0.3@0.8".parse(),
+               Ok(UptimeDist::Mix(vec![(0.95, 0.2), (0.3, 0.8)])));
+    for dist in &["0.5", "uniform(0.2,0.9)", "0.95@0.2,0.3@0.8"] {
+        let dist: UptimeDist = dist.parse().expect("parse");
+        assert_eq!(dist.to_string().parse(), Ok(dist));
+    }
+
+    let err = |s: &str| s.parse::<UptimeDist>().err().expect("error");
+    assert_eq!(err("1.5"), "uptime 1.5 outside range 0-1");
+    assert_eq!(err("x"), "bad number 'x'");
+    assert_eq!(err("uniform(0.9,0.2)"), "empty range in 'uniform(0.9,0.2)'");
+    assert_eq!(err("uniform(0.2)"), "expected uniform(A,B), found 'uniform(0.2)'");
+    assert_eq!(err("uniform(0.2,0.9"), "missing ')' in 'uniform(0.2,0.9'");
+    assert_eq!(err("0.9@0.5,0.3"), "expected UPTIME@WEIGHT, found '0.3'");
+    assert_eq!(err("0.9@0"), "bad weight '0'");
+
+    // Capacities may exceed 1
+    assert_eq!(UptimeDist::parse_capacity("1@0.9,10@0.1"),
+               Ok(UptimeDist::Mix(vec![(1.0, 0.9), (10.0, 0.1)])));
+    assert_eq!(UptimeDist::parse_capacity("uniform(1,5)"), Ok(UptimeDist::Uniform(1.0, 5.0)));
+    assert_eq!(UptimeDist::parse_capacity("-1"), Err("capacity -1 is negative".to_owned()));
+    assert_eq!(UptimeDist::parse_capacity("2@1,3"),
+               Err("expected CAPACITY@WEIGHT, found '3'".to_owned()));
+}
@@ -0,0 +1,435 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Invariants of the network structure, checked as the simulations run (see
+//! `--verify`)
+//!
+//! Splits, churn, relocation and departures all change the groups, and a
+//! mistake in how they interact would corrupt the results without any visible
+//! error. The verifier checks, once the network has settled for a step:
+//!
+//! *   no node is lost: the groups hold the nodes added, less those removed or
+//!     taken for relocation since;
+//! *   groups are disjoint: no group's prefix extends another's, and each
+//!     member matches its group's prefix (so no node is in two groups);
+//! *   no group is below the minimum size, unless it is the only group (the
+//!     network is still growing) or a member has left it (groups never merge);
+//! *   no node's age decreases, unless it left and rejoined with the same name
+//!     or traded data with another (see `Network::swap_data`).
+//!
+//! Debug builds check every `DEBUG_STEPS` steps, and `--verify` every step, in
+//! any build.
+
+use super::NN;
+use super::sim::{AddRestriction, Event, Network, NodeName, Prefix};
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+
+
+/// Steps between checks in debug builds, without `--verify`
+pub const DEBUG_STEPS: NN = 64;
+
+/// Number of the last events dumped on a violation
+pub const RECENT_EVENTS: usize = 16;
+
+/// A broken invariant
+#[derive(Clone, Debug, PartialEq)]
+pub struct Violation {
+    /// The offending group, if one is
+    pub prefix: Option<Prefix>,
+    /// What is wrong
+    pub message: String,
+}
+
+impl Violation {
+    fn new(prefix: Option<Prefix>, message: String) -> Self {
+        Violation {
+            prefix: prefix,
+            message: message,
+        }
+    }
+}
+
+/// Checks the invariants of a network as it changes, from the events it
+/// records
+pub struct Verifier {
+    every: NN,
+    // Nodes in the network as of the last check, from the events
+    count: usize,
+    // Age of each node as of the last check
+    ages: HashMap<NodeName, u32>,
+    // Groups a member has left since they were created
+    shrunk: HashSet<Prefix>,
+    // The last events, oldest first
+    recent: VecDeque<Event>,
+}
+
+impl Verifier {
+    /// The verifier of a simulation, if any: checking every step with
+    /// `verify`, otherwise every `DEBUG_STEPS` steps in debug builds only.
+    /// Starts `net` recording its events; the network as it is now is taken
+    /// as correct.
+    pub fn new<AR: AddRestriction>(verify: bool, net: &mut Network<AR>) -> Option<Self> {
+        if verify {
+            Some(Verifier::with_interval(1, net))
+        } else if cfg!(debug_assertions) {
+            Some(Verifier::with_interval(DEBUG_STEPS, net))
+        } else {
+            None
+        }
+    }
+
+    /// A verifier checking every `every` steps.
+    pub fn with_interval<AR: AddRestriction>(every: NN, net: &mut Network<AR>) -> Self {
+        assert!(every >= 1);
+        net.record_events();
+        let ages = net.groups()
+            .values()
+            .flat_map(|group| group.iter())
+            .map(|(name, data)| (*name, data.age()))
+            .collect::<HashMap<_, _>>();
+        Verifier {
+            every: every,
+            count: ages.len(),
+            ages: ages,
+            shrunk: HashSet::new(),
+            recent: VecDeque::new(),
+        }
+    }
+
+    /// Whether to check at this step.
+    pub fn due(&self, step: NN) -> bool {
+        step % self.every == 0
+    }
+
+    /// Check the invariants, panicking on a violation with a dump of the
+    /// offending group and the last events. `when` says where the simulation
+    /// is, e.g. "at step 10".
+    pub fn verify<AR: AddRestriction>(&mut self, net: &mut Network<AR>, when: &str) {
+        if let Err(violation) = self.check(net) {
+            panic!("{}", self.report(net, &violation, when));
+        }
+    }
+
+    /// Check the invariants, given the events of `net` since the last check.
+    pub fn check<AR: AddRestriction>(&mut self, net: &mut Network<AR>) -> Result<(), Violation> {
+        let events = net.take_events();
+        for event in &events {
+            match *event {
+                Event::Added(_, _) => self.count += 1,
+                Event::Removed(prefix, name) => {
+                    self.count -= 1;
+                    let _ = self.ages.remove(&name);
+                    self.shrunk.insert(prefix);
+                }
+                Event::Relocated(_, name) => {
+                    self.count -= 1;
+                    let _ = self.ages.remove(&name);
+                }
+                Event::Split(prefix) => {
+                    // Both halves start above the minimum size
+                    let _ = self.shrunk.remove(&prefix);
+                }
+                Event::Swapped(a, b) => {
+                    let _ = self.ages.remove(&a);
+                    let _ = self.ages.remove(&b);
+                }
+            }
+        }
+        self.recent.extend(events);
+        while self.recent.len() > RECENT_EVENTS {
+            let _ = self.recent.pop_front();
+        }
+
+        let groups = net.groups();
+        let mut prefixes: Vec<Prefix> = groups.keys().cloned().collect();
+        prefixes.sort();
+        for &prefix in &prefixes {
+            let mut ancestor = prefix;
+            while ancestor.bit_count() > 0 {
+                ancestor = ancestor.popped();
+                if groups.contains_key(&ancestor) {
+                    return Err(Violation::new(Some(prefix),
+                                              format!("group overlaps group {:?}", ancestor)));
+                }
+            }
+            let mut names: Vec<NodeName> = groups[&prefix].keys().cloned().collect();
+            names.sort();
+            if let Some(name) = names.into_iter().find(|name| !prefix.matches(*name)) {
+                return Err(Violation::new(Some(prefix),
+                                          format!("member {:016x} does not match the prefix",
+                                                  name)));
+            }
+        }
+        let min_size = net.min_group_size();
+        for &prefix in &prefixes {
+            let size = groups[&prefix].len();
+            if size < min_size && prefixes.len() > 1 && !self.shrunk.contains(&prefix) {
+                return Err(Violation::new(Some(prefix),
+                                          format!("{} members, below the minimum group size \
+                                                   {}, though none has left",
+                                                  size,
+                                                  min_size)));
+            }
+        }
+        for &prefix in &prefixes {
+            let mut members: Vec<(NodeName, u32)> = groups[&prefix]
+                .iter()
+                .map(|(name, data)| (*name, data.age()))
+                .collect();
+            members.sort();
+            for (name, age) in members {
+                match self.ages.get(&name) {
+                    Some(&old) if old > age => {
+                        return Err(Violation::new(Some(prefix),
+                                                  format!("member {:016x} aged from {} to {}",
+                                                          name,
+                                                          old,
+                                                          age)));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        let total: usize = groups.values().map(|group| group.len()).sum();
+        if total != self.count {
+            return Err(Violation::new(None,
+                                      format!("{} nodes in the groups, but {} added and not \
+                                               removed",
+                                              total,
+                                              self.count)));
+        }
+
+        self.ages = groups.values()
+            .flat_map(|group| group.iter())
+            .map(|(name, data)| (*name, data.age()))
+            .collect();
+        Ok(())
+    }
+
+    // Describe a violation: the offending group's members (in order of name) and the last
+    // events.
+    fn report<AR: AddRestriction>(&self,
+                                  net: &Network<AR>,
+                                  violation: &Violation,
+                                  when: &str)
+                                  -> String {
+        let mut report = format!("network invariant violated {}: {}\n", when, violation.message);
+        if let Some(prefix) = violation.prefix {
+            if let Some(group) = net.groups().get(&prefix) {
+                let mut members: Vec<_> = group.iter().collect();
+                members.sort_by_key(|&(name, _)| *name);
+                let _ = writeln!(report, "group {:?}, {} members:", prefix, members.len());
+                for (name, data) in members {
+                    let _ = writeln!(report,
+                                     "    {:016x} age {}{}{}",
+                                     name,
+                                     data.age(),
+                                     if data.is_malicious() { " malicious" } else { "" },
+                                     if data.is_failed() { " failed" } else { "" });
+                }
+            }
+        }
+        let _ = writeln!(report, "last events, oldest first:");
+        for event in &self.recent {
+            let _ = writeln!(report, "    {:?}", event);
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+fn grown_network() -> (Network<super::sim::NoAddRestriction>, Verifier) {
+    use super::quorum::UntargettedAttack;
+    use super::sim::{Node, NodeData, new_node_name};
+    use rand::{SeedableRng, XorShiftRng};
+
+    let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+    let mut net = Network::new(8);
+    let mut verifier = Verifier::with_interval(1, &mut net);
+    let mut to_add: Vec<Node> =
+        (0..300).map(|_| (new_node_name(&mut rng), NodeData::new())).collect();
+    while let Some((name, data)) = to_add.pop() {
+        if let Ok(prefix) = net.add_node(name, data) {
+            let prefix = net.maybe_split(prefix, name, &mut UntargettedAttack);
+            if let Some(node) = net.churn(prefix, name, &mut rng) {
+                to_add.push(node);
+            }
+        }
+        verifier.verify(&mut net, "while growing");
+    }
+    assert!(net.groups().len() > 4);
+    (net, verifier)
+}
+
+// The largest group and its members, in order of name
+#[cfg(test)]
+fn largest_group<AR: AddRestriction>(net: &Network<AR>) -> (Prefix, Vec<NodeName>) {
+    let (prefix, group) = net.groups()
+        .iter()
+        .max_by_key(|&(prefix, group)| (group.len(), *prefix))
+        .expect("a group");
+    let mut names: Vec<NodeName> = group.keys().cloned().collect();
+    names.sort();
+    (*prefix, names)
+}
+
+#[test]
+fn test_verifier_accepts_changes() {
+    let (mut net, mut verifier) = grown_network();
+
+    // Departures may take a group below the minimum size
+    let (prefix, names) = largest_group(&net);
+    for name in &names[..names.len() - 2] {
+        assert!(net.remove_node(*name).is_some());
+    }
+    assert_eq!(verifier.check(&mut net), Ok(()));
+    assert!(net.groups()[&prefix].len() < net.min_group_size());
+
+    // Trading data may lower a node's age
+    let mut nodes: Vec<(NodeName, u32)> = net.groups()
+        .values()
+        .flat_map(|group| group.iter())
+        .map(|(name, data)| (*name, data.age()))
+        .collect();
+    nodes.sort();
+    let oldest = *nodes.iter().max_by_key(|&&(_, age)| age).expect("a node");
+    let youngest = *nodes.iter().min_by_key(|&&(_, age)| age).expect("a node");
+    assert!(oldest.1 > youngest.1);
+    net.swap_data(oldest.0, youngest.0);
+    assert_eq!(verifier.check(&mut net), Ok(()));
+}
+
+// A node of `net` which has aged, the first in order of name
+#[cfg(test)]
+fn aged_node<AR: AddRestriction>(net: &Network<AR>) -> (Prefix, NodeName) {
+    let mut aged: Vec<(NodeName, Prefix)> = net.groups()
+        .iter()
+        .flat_map(|(prefix, group)| {
+            group.iter().filter(|&(_, data)| data.age() > 0).map(move |(name, _)| (*name, *prefix))
+        })
+        .collect();
+    aged.sort();
+    let (name, prefix) = aged[0];
+    (prefix, name)
+}
+
+#[test]
+fn test_verifier_detects_corruption() {
+    let detect = |corrupt: &Fn(&mut Network<super::sim::NoAddRestriction>), expected: &str| {
+        let (mut net, mut verifier) = grown_network();
+        corrupt(&mut net);
+        match verifier.check(&mut net) {
+            Ok(()) => panic!("corruption not detected: {}", expected),
+            Err(violation) => {
+                assert!(violation.message.contains(expected),
+                        "{} (expected {})",
+                        violation.message,
+                        expected)
+            }
+        }
+    };
+
+    // A node lost from a group
+    detect(&|net| {
+               let (prefix, names) = largest_group(&*net);
+               assert!(names.len() > net.min_group_size());
+               let _ = net.groups_mut().get_mut(&prefix).expect("group").remove(&names[0]);
+           },
+           "nodes in the groups");
+    // A node in two groups
+    detect(&|net| {
+               let (prefix, names) = largest_group(&*net);
+               let data = net.groups()[&prefix][&names[0]];
+               let other = *net.groups().keys().find(|p| **p != prefix).expect("other group");
+               let _ = net.groups_mut().get_mut(&other).expect("group").insert(names[0], data);
+           },
+           "does not match");
+    // A group overlapping another
+    detect(&|net| {
+               let (prefix, _) = largest_group(&*net);
+               let _ = net.groups_mut().insert(prefix.popped(), HashMap::new());
+           },
+           "overlaps");
+    // A group below the minimum size though none left
+    detect(&|net| {
+               let (prefix, names) = largest_group(&*net);
+               let group = net.groups_mut().get_mut(&prefix).expect("group");
+               for name in &names[1..] {
+                   let _ = group.remove(name);
+               }
+           },
+           "below the minimum group size");
+    // A node getting younger
+    detect(&|net| {
+               let (prefix, name) = aged_node(&*net);
+               let group = net.groups_mut().get_mut(&prefix).expect("group");
+               let data = group.get_mut(&name).expect("member");
+               let age = data.age();
+               data.set_age(age - 1);
+           },
+           "aged from");
+}
+
+#[test]
+fn test_violation_report() {
+    let (mut net, mut verifier) = grown_network();
+    let (prefix, name) = aged_node(&net);
+    let data = net.groups()[&prefix][&name];
+    assert!(net.remove_node(name).is_some());
+    assert!(net.add_node(name, data).is_ok());
+    let set_age = |net: &mut Network<_>, age| {
+        let group = net.groups_mut().get_mut(&prefix).expect("group");
+        group.get_mut(&name).expect("member").set_age(age)
+    };
+    // Rejoining with the same name, a node may be younger
+    set_age(&mut net, 0);
+    assert_eq!(verifier.check(&mut net), Ok(()));
+
+    set_age(&mut net, data.age());
+    assert_eq!(verifier.check(&mut net), Ok(()));
+    set_age(&mut net, 0);
+    let violation = match verifier.check(&mut net) {
+        Ok(()) => panic!("corruption not detected"),
+        Err(violation) => violation,
+    };
+    assert_eq!(violation.prefix, Some(prefix));
+    let report = verifier.report(&net, &violation, "after rejoining");
+    let lines: Vec<&str> = report.lines().collect();
+    assert_eq!(lines[0],
+               format!("network invariant violated after rejoining: member {:016x} aged from \
+                        {} to 0",
+                       name,
+                       data.age()));
+    assert_eq!(lines[1],
+               format!("group {:?}, {} members:", prefix, net.groups()[&prefix].len()));
+    assert!(lines.contains(&format!("    {:016x} age 0", name).as_str()));
+    // The events of the rejoin are the last, though not since the last check
+    let n = lines.len();
+    assert_eq!(lines[n - 2], format!("    {:?}", Event::Removed(prefix, name)));
+    assert_eq!(lines[n - 1], format!("    {:?}", Event::Added(prefix, name)));
+}
+
+#[test]
+#[should_panic(expected = "network invariant violated at step 3: 299 nodes in the groups")]
+fn test_verify_panics() {
+    let (mut net, mut verifier) = grown_network();
+    let (prefix, names) = largest_group(&net);
+    let _ = net.groups_mut().get_mut(&prefix).expect("group").remove(&names[0]);
+    verifier.verify(&mut net, "at step 3");
+}